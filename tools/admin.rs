@@ -0,0 +1,160 @@
+//! Operational state behind the `/admin/*` API: who's allowed to call it,
+//! whether the server is draining, and the current log level. Kept
+//! separate from `auth::AuthRegistry` — an admin key can load/unload
+//! models and flip settings server-wide, so it's a distinct, smaller trust
+//! boundary from a regular chat-completion key, with its own key list.
+//!
+//! Nothing in this tree has structured logging yet (`println!` calls
+//! sprinkled through `main()` are all there is), so [`AdminState::log_level`]
+//! has no reader today — it exists so `/admin/log-level` has somewhere
+//! real to write to, and a future logger reads from the same place instead
+//! of `/admin/log-level` needing to change again once one exists.
+
+use crate::http::Request;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+#[derive(Debug, PartialEq)]
+pub enum AdminAuthError {
+    MissingApiKey,
+    InvalidApiKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<LogLevel> {
+        match s {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+
+    fn from_u8(n: u8) -> LogLevel {
+        match n {
+            0 => LogLevel::Debug,
+            1 => LogLevel::Info,
+            2 => LogLevel::Warn,
+            _ => LogLevel::Error,
+        }
+    }
+}
+
+/// Admin-only state guarding `/admin/*`. `draining` and `log_level` are
+/// plain atomics rather than a `Mutex` since they're single independent
+/// values read far more often than written, same shape as
+/// `resources::MemoryBudget`'s `admitted_bytes`.
+pub struct AdminState {
+    admin_keys: HashSet<String>,
+    draining: AtomicBool,
+    log_level: AtomicU8,
+}
+
+impl AdminState {
+    pub fn new(admin_keys: Vec<String>) -> Self {
+        AdminState { admin_keys: admin_keys.into_iter().collect(), draining: AtomicBool::new(false), log_level: AtomicU8::new(LogLevel::Info as u8) }
+    }
+
+    /// Checks `req`'s `Authorization` header against the configured admin
+    /// keys. Unlike `auth::AuthRegistry`, an *empty* key list does not
+    /// disable this check — an admin API left unconfigured should refuse
+    /// everything, not allow everything, since it's the more dangerous of
+    /// the two APIs to leave open by accident.
+    pub fn authenticate(&self, req: &Request) -> Result<(), AdminAuthError> {
+        let key = req.header("authorization").and_then(|h| h.strip_prefix("Bearer ")).ok_or(AdminAuthError::MissingApiKey)?;
+        if self.admin_keys.contains(key) {
+            Ok(())
+        } else {
+            Err(AdminAuthError::InvalidApiKey)
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::SeqCst);
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        LogLevel::from_u8(self.log_level.load(Ordering::SeqCst))
+    }
+
+    pub fn set_log_level(&self, level: LogLevel) {
+        self.log_level.store(level as u8, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_bearer(token: Option<&str>) -> Request {
+        let mut headers = std::collections::BTreeMap::new();
+        if let Some(token) = token {
+            headers.insert("authorization".to_string(), format!("Bearer {token}"));
+        }
+        Request { method: crate::http::Method::Post, path: "/admin/drain".to_string(), query: Default::default(), headers, body: Vec::new() }
+    }
+
+    #[test]
+    fn authenticate_rejects_everything_when_no_admin_keys_are_configured() {
+        let admin = AdminState::new(Vec::new());
+        assert_eq!(admin.authenticate(&request_with_bearer(Some("anything"))), Err(AdminAuthError::InvalidApiKey));
+    }
+
+    #[test]
+    fn authenticate_rejects_a_missing_header() {
+        let admin = AdminState::new(vec!["admin-secret".to_string()]);
+        assert_eq!(admin.authenticate(&request_with_bearer(None)), Err(AdminAuthError::MissingApiKey));
+    }
+
+    #[test]
+    fn authenticate_accepts_a_configured_admin_key() {
+        let admin = AdminState::new(vec!["admin-secret".to_string()]);
+        assert_eq!(admin.authenticate(&request_with_bearer(Some("admin-secret"))), Ok(()));
+    }
+
+    #[test]
+    fn draining_starts_false_and_reflects_set_draining() {
+        let admin = AdminState::new(vec!["admin-secret".to_string()]);
+        assert!(!admin.is_draining());
+        admin.set_draining(true);
+        assert!(admin.is_draining());
+    }
+
+    #[test]
+    fn log_level_starts_at_info_and_reflects_set_log_level() {
+        let admin = AdminState::new(vec!["admin-secret".to_string()]);
+        assert_eq!(admin.log_level(), LogLevel::Info);
+        admin.set_log_level(LogLevel::Debug);
+        assert_eq!(admin.log_level(), LogLevel::Debug);
+    }
+
+    #[test]
+    fn log_level_parse_round_trips_through_as_str() {
+        for level in [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+            assert_eq!(LogLevel::parse(level.as_str()), Some(level));
+        }
+        assert_eq!(LogLevel::parse("verbose"), None);
+    }
+}
@@ -0,0 +1,424 @@
+//! Agent orchestration: given a goal, [`run`] loops a backend through a
+//! scratchpad transcript, letting it call [`AgentTools`]' shell/HTTP/file
+//! tools between generations until it produces a plain-text final answer
+//! or `max_steps` runs out. `server.rs` exposes this as `POST
+//! /v1/agents/runs`, streaming one JSON record per step over the same
+//! [`http::SseWriter`] `handle_chat_completions` uses for token streaming.
+//!
+//! Tool calls are parsed with `tool_calls::parse_tool_call` against
+//! [`AgentTools::definitions`] — the exact machinery a chat completion's
+//! own `tools` array goes through — so a completion that doesn't match
+//! any tool's schema falls back to being the run's final answer, the same
+//! `ToolChoice::Auto` fallback a chat completion gets. Unlike a chat
+//! completion, though, this server *does* execute the call: an agent run
+//! has no human in the loop to relay a `tool_calls` message back to, so
+//! [`AgentTools::call`] runs it directly and feeds the observation back
+//! into the transcript as the next step's context.
+//!
+//! A goal string is untrusted input, so every tool is allowlisted rather
+//! than run unconditionally: `shell` checks the command's first word
+//! against `shell_allowlist`, `http` checks the target host against
+//! `http_allowlist`, and `read_file`/`write_file` are confined under
+//! `file_root` the same way `resolve_path` refuses to let a `..` segment
+//! escape it. There's no "disabled means every check passes through
+//! untouched" registry here the way `guardrails`/`plugins`/`mcp` have one
+//! for their off state — an agent tool call with nothing on its
+//! allowlist should fail closed, not silently no-op — so `server.rs`
+//! only mounts `/v1/agents/runs` at all when `[agent]` is enabled.
+
+use crate::json::{Json, ObjectBuilder};
+use crate::tool_calls::{self, ToolCall, ToolChoice, ToolDefinition};
+use crate::InferenceBackend;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub type RunId = String;
+
+static RUN_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a fresh run id, built the same way `batches::new_batch_id`
+/// and `sessions::new_session_id` build theirs: a timestamp plus a
+/// process-local counter so ids stay unique across restarts too.
+pub fn new_run_id() -> RunId {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let n = RUN_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("run-{nanos:x}-{n}")
+}
+
+#[derive(Debug)]
+pub enum ToolError {
+    NotAllowed(String),
+    Io(String),
+}
+
+impl ToolError {
+    fn message(&self) -> String {
+        match self {
+            ToolError::NotAllowed(m) => m.clone(),
+            ToolError::Io(m) => m.clone(),
+        }
+    }
+}
+
+fn object_schema(properties: Json, required: &[&str]) -> Json {
+    ObjectBuilder::new()
+        .set("type", Json::String("object".to_string()))
+        .set("properties", properties)
+        .set("required", Json::Array(required.iter().map(|s| Json::String(s.to_string())).collect()))
+        .build()
+}
+
+fn string_property() -> Json {
+    ObjectBuilder::new().set("type", Json::String("string".to_string())).build()
+}
+
+fn shell_tool_definition() -> ToolDefinition {
+    let properties = ObjectBuilder::new().set("command", string_property()).build();
+    ToolDefinition {
+        name: "shell".to_string(),
+        description: Some("Run a shell command on the server host".to_string()),
+        parameters: object_schema(properties, &["command"]),
+    }
+}
+
+fn http_tool_definition() -> ToolDefinition {
+    let properties = ObjectBuilder::new()
+        .set("host", string_property())
+        .set("path", string_property())
+        .set("method", string_property())
+        .set("body", string_property())
+        .build();
+    ToolDefinition {
+        name: "http".to_string(),
+        description: Some("Make an HTTP request to an allowlisted host".to_string()),
+        parameters: object_schema(properties, &["host"]),
+    }
+}
+
+fn read_file_tool_definition() -> ToolDefinition {
+    let properties = ObjectBuilder::new().set("path", string_property()).build();
+    ToolDefinition {
+        name: "read_file".to_string(),
+        description: Some("Read a file under the agent's file root".to_string()),
+        parameters: object_schema(properties, &["path"]),
+    }
+}
+
+fn write_file_tool_definition() -> ToolDefinition {
+    let properties = ObjectBuilder::new().set("path", string_property()).set("contents", string_property()).build();
+    ToolDefinition {
+        name: "write_file".to_string(),
+        description: Some("Write a file under the agent's file root".to_string()),
+        parameters: object_schema(properties, &["path", "contents"]),
+    }
+}
+
+/// POSTs (or GETs) `path` on `host`, the same plain `TcpStream` HTTP/1.1
+/// framing `router.rs`'s `probe` and `mcp::call` speak toward a peer, and
+/// returns the status line and body joined as plain text for the model to
+/// read back.
+fn http_request(host: &str, method: &str, path: &str, body: &str, timeout: Duration) -> Result<String, String> {
+    let stream = TcpStream::connect(host).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(timeout)).ok();
+    stream.set_write_timeout(Some(timeout)).ok();
+    let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+    write!(
+        writer,
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(|e| e.to_string())?;
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut response_body = vec![0u8; content_length];
+    reader.read_exact(&mut response_body).map_err(|e| e.to_string())?;
+    Ok(format!("{}\n{}", status_line.trim_end(), String::from_utf8_lossy(&response_body)))
+}
+
+/// Shell, HTTP, and file tools an agent run may invoke, each gated by an
+/// allowlist so a goal string from an untrusted caller can't turn into
+/// arbitrary command execution, network egress, or filesystem access —
+/// the same reasoning `tenancy::TenantRegistry::allows_model` gates model
+/// access with an allowlist rather than trusting the request.
+pub struct AgentTools {
+    shell_allowlist: Vec<String>,
+    http_allowlist: Vec<String>,
+    file_root: PathBuf,
+    http_timeout: Duration,
+}
+
+impl AgentTools {
+    /// Creates `file_root` if it doesn't exist yet.
+    pub fn open(file_root: impl Into<PathBuf>, shell_allowlist: Vec<String>, http_allowlist: Vec<String>, http_timeout: Duration) -> std::io::Result<AgentTools> {
+        let file_root = file_root.into();
+        fs::create_dir_all(&file_root)?;
+        Ok(AgentTools { shell_allowlist, http_allowlist, file_root, http_timeout })
+    }
+
+    /// A tool set with empty allowlists and no file root — every call
+    /// fails closed. Exists for tests; `server.rs` only ever builds a
+    /// real [`AgentTools::open`] because it only mounts `/v1/agents/runs`
+    /// when `[agent]` is enabled in config in the first place.
+    pub fn disabled() -> AgentTools {
+        AgentTools { shell_allowlist: Vec::new(), http_allowlist: Vec::new(), file_root: PathBuf::new(), http_timeout: Duration::from_secs(10) }
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        vec![shell_tool_definition(), http_tool_definition(), read_file_tool_definition(), write_file_tool_definition()]
+    }
+
+    pub fn call(&self, call: &ToolCall) -> Result<String, ToolError> {
+        match call.name.as_str() {
+            "shell" => self.run_shell(&call.arguments),
+            "http" => self.run_http(&call.arguments),
+            "read_file" => self.read_file(&call.arguments),
+            "write_file" => self.write_file(&call.arguments),
+            other => Err(ToolError::NotAllowed(format!("unknown tool \"{other}\""))),
+        }
+    }
+
+    /// Runs the allowlisted program directly with its remaining
+    /// whitespace-split tokens as `argv`, never through `sh -c` — a goal
+    /// string that only got past the allowlist because its first word was
+    /// `echo` must not be able to smuggle a `;`, `&&`, `|`, backtick, or
+    /// `$()` into a shell that then runs something else entirely. Argument
+    /// splitting has no quoting support (`"a b"` stays two tokens, not
+    /// one), which is a correctness limitation, not a security one.
+    fn run_shell(&self, arguments: &Json) -> Result<String, ToolError> {
+        let command = arguments.get("command").and_then(Json::as_str).ok_or_else(|| ToolError::Io("\"command\" must be a string".to_string()))?;
+        let mut tokens = command.split_whitespace();
+        let program = tokens.next().unwrap_or("");
+        if !self.shell_allowlist.iter().any(|allowed| allowed == program) {
+            return Err(ToolError::NotAllowed(format!("shell command \"{program}\" is not on the allowlist")));
+        }
+        let output = std::process::Command::new(program).args(tokens).output().map_err(|e| ToolError::Io(e.to_string()))?;
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok(text)
+    }
+
+    fn run_http(&self, arguments: &Json) -> Result<String, ToolError> {
+        let host = arguments.get("host").and_then(Json::as_str).ok_or_else(|| ToolError::Io("\"host\" must be a string".to_string()))?;
+        if !self.http_allowlist.iter().any(|allowed| allowed == host) {
+            return Err(ToolError::NotAllowed(format!("http host \"{host}\" is not on the allowlist")));
+        }
+        let path = arguments.get("path").and_then(Json::as_str).unwrap_or("/");
+        let method = arguments.get("method").and_then(Json::as_str).unwrap_or("GET").to_uppercase();
+        let body = arguments.get("body").and_then(Json::as_str).unwrap_or("");
+        http_request(host, &method, path, body, self.http_timeout).map_err(ToolError::Io)
+    }
+
+    /// Resolves a tool-supplied relative path under `file_root`, refusing
+    /// anything a `..` segment (or a symlink) would let escape it — the
+    /// same containment check `resolve_path`'s canonicalize-then-`starts_with`
+    /// shape gives a request-supplied path everywhere else in this tree
+    /// that reads one off disk on a caller's say-so.
+    fn resolve_path(&self, arguments: &Json) -> Result<PathBuf, ToolError> {
+        let relative = arguments.get("path").and_then(Json::as_str).ok_or_else(|| ToolError::Io("\"path\" must be a string".to_string()))?;
+        let root = self.file_root.canonicalize().map_err(|e| ToolError::Io(e.to_string()))?;
+        let candidate = self.file_root.join(relative);
+        if let Ok(canonical) = candidate.canonicalize() {
+            if !canonical.starts_with(&root) {
+                return Err(ToolError::NotAllowed(format!("path \"{relative}\" escapes the agent file root")));
+            }
+            return Ok(canonical);
+        }
+        // A file that doesn't exist yet (the write_file case) can't be
+        // canonicalized itself, so contain it by its parent directory
+        // instead.
+        let parent = candidate.parent().unwrap_or(&self.file_root).canonicalize().map_err(|e| ToolError::Io(e.to_string()))?;
+        if !parent.starts_with(&root) {
+            return Err(ToolError::NotAllowed(format!("path \"{relative}\" escapes the agent file root")));
+        }
+        Ok(candidate)
+    }
+
+    fn read_file(&self, arguments: &Json) -> Result<String, ToolError> {
+        let path = self.resolve_path(arguments)?;
+        fs::read_to_string(path).map_err(|e| ToolError::Io(e.to_string()))
+    }
+
+    fn write_file(&self, arguments: &Json) -> Result<String, ToolError> {
+        let path = self.resolve_path(arguments)?;
+        let contents = arguments.get("contents").and_then(Json::as_str).unwrap_or("");
+        fs::write(&path, contents).map_err(|e| ToolError::Io(e.to_string()))?;
+        Ok(format!("wrote {} bytes to {}", contents.len(), path.display()))
+    }
+}
+
+/// Runs an agent loop toward `goal`: each step asks `backend` to continue
+/// a scratchpad transcript, parses the completion as a call to one of
+/// `tools`'s definitions (see `tool_calls::parse_tool_call`), executes it
+/// through `tools`, and feeds the observation back into the transcript as
+/// the next step's context. A completion that doesn't match any tool's
+/// schema is treated as the run's final answer — the same fallback an
+/// `ToolChoice::Auto` chat completion gets when its output turns out to
+/// be plain content.
+///
+/// `on_step` is called once per step with that step's JSON record — the
+/// same "let the caller decide what to do with each unit of output"
+/// shape `InferenceBackend::stream`'s `on_token` callback takes.
+pub fn run(backend: &dyn InferenceBackend, tools: &AgentTools, goal: &str, max_steps: usize, on_step: &mut dyn FnMut(&Json)) {
+    let definitions = tools.definitions();
+    let mut transcript = tool_calls::append_tool_definitions(&format!("Goal: {goal}\n"), &definitions);
+
+    for index in 0..max_steps {
+        let completion = backend.generate(&transcript);
+        let tool_call = tool_calls::parse_tool_call(&completion, &definitions, &ToolChoice::Auto).unwrap_or(None);
+        let Some(call) = tool_call else {
+            on_step(&ObjectBuilder::new().set("index", Json::Number(index as f64)).set("answer", Json::String(completion)).set("done", Json::Bool(true)).build());
+            return;
+        };
+        let observation = match tools.call(&call) {
+            Ok(text) => text,
+            Err(err) => format!("error: {}", err.message()),
+        };
+        transcript.push_str(&format!("\nTool call: {}({})\nObservation: {observation}\n", call.name, call.arguments.to_string()));
+        on_step(
+            &ObjectBuilder::new()
+                .set("index", Json::Number(index as f64))
+                .set("tool_call", ObjectBuilder::new().set("name", Json::String(call.name)).set("arguments", call.arguments).build())
+                .set("observation", Json::String(observation))
+                .set("done", Json::Bool(false))
+                .build(),
+        );
+    }
+    on_step(
+        &ObjectBuilder::new()
+            .set("index", Json::Number(max_steps as f64))
+            .set("error", Json::String("max_steps reached without a final answer".to_string()))
+            .set("done", Json::Bool(true))
+            .build(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ScriptedBackend {
+        responses: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl ScriptedBackend {
+        fn new(responses: Vec<&str>) -> ScriptedBackend {
+            ScriptedBackend { responses: std::sync::Mutex::new(responses.into_iter().rev().map(str::to_string).collect()) }
+        }
+    }
+
+    impl InferenceBackend for ScriptedBackend {
+        fn model_id(&self) -> &str {
+            "scripted"
+        }
+        fn generate(&self, _prompt: &str) -> String {
+            self.responses.lock().unwrap().pop().unwrap_or_default()
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-agent-test-{name}-{:x}", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn run_returns_a_final_answer_when_the_first_completion_matches_no_tool() {
+        let backend = ScriptedBackend::new(vec!["the answer is 4"]);
+        let tools = AgentTools::disabled();
+        let mut steps = Vec::new();
+        run(&backend, &tools, "what is 2+2", 5, &mut |step| steps.push(step.clone()));
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].get("answer").and_then(Json::as_str), Some("the answer is 4"));
+        assert_eq!(steps[0].get("done").and_then(Json::as_bool), Some(true));
+    }
+
+    #[test]
+    fn run_executes_an_allowlisted_shell_tool_call_and_feeds_back_the_observation() {
+        let dir = temp_dir("shell");
+        let tools = AgentTools::open(&dir, vec!["echo".to_string()], Vec::new(), Duration::from_secs(1)).unwrap();
+        let call = r#"{"arguments":{"command":"echo hi"},"name":"shell"}"#;
+        let backend = ScriptedBackend::new(vec![call, "done: hi"]);
+        let mut steps = Vec::new();
+        run(&backend, &tools, "say hi", 5, &mut |step| steps.push(step.clone()));
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].get("tool_call").and_then(|t| t.get("name")).and_then(Json::as_str), Some("shell"));
+        assert!(steps[0].get("observation").and_then(Json::as_str).unwrap().contains("hi"));
+        assert_eq!(steps[1].get("answer").and_then(Json::as_str), Some("done: hi"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn run_records_a_not_allowed_observation_for_a_disallowed_shell_command() {
+        let dir = temp_dir("disallowed");
+        let tools = AgentTools::open(&dir, Vec::new(), Vec::new(), Duration::from_secs(1)).unwrap();
+        let call = r#"{"arguments":{"command":"rm -rf /"},"name":"shell"}"#;
+        let backend = ScriptedBackend::new(vec![call, "gave up"]);
+        let mut steps = Vec::new();
+        run(&backend, &tools, "delete everything", 5, &mut |step| steps.push(step.clone()));
+        assert!(steps[0].get("observation").and_then(Json::as_str).unwrap().contains("not on the allowlist"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn run_stops_at_max_steps_without_a_final_answer() {
+        let call = r#"{"arguments":{"command":"echo loop"},"name":"shell"}"#;
+        let dir = temp_dir("loop");
+        let tools = AgentTools::open(&dir, vec!["echo".to_string()], Vec::new(), Duration::from_secs(1)).unwrap();
+        let backend = ScriptedBackend::new(vec![call, call, call]);
+        let mut steps = Vec::new();
+        run(&backend, &tools, "loop forever", 3, &mut |step| steps.push(step.clone()));
+        assert_eq!(steps.len(), 4);
+        assert!(steps.last().unwrap().get("error").is_some());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn write_file_then_read_file_round_trips_through_the_file_root() {
+        let dir = temp_dir("files");
+        let tools = AgentTools::open(&dir, Vec::new(), Vec::new(), Duration::from_secs(1)).unwrap();
+        let write_call = ToolCall { name: "write_file".to_string(), arguments: Json::parse(r#"{"path":"notes.txt","contents":"hello"}"#).unwrap() };
+        tools.call(&write_call).unwrap();
+        let read_call = ToolCall { name: "read_file".to_string(), arguments: Json::parse(r#"{"path":"notes.txt"}"#).unwrap() };
+        assert_eq!(tools.call(&read_call).unwrap(), "hello");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn read_file_refuses_a_path_that_escapes_the_file_root() {
+        let dir = temp_dir("escape");
+        let tools = AgentTools::open(&dir, Vec::new(), Vec::new(), Duration::from_secs(1)).unwrap();
+        let call = ToolCall { name: "read_file".to_string(), arguments: Json::parse(r#"{"path":"../../etc/passwd"}"#).unwrap() };
+        assert!(matches!(tools.call(&call), Err(ToolError::NotAllowed(_))));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn http_tool_refuses_a_host_not_on_the_allowlist() {
+        let tools = AgentTools::disabled();
+        let call = ToolCall { name: "http".to_string(), arguments: Json::parse(r#"{"host":"example.com:80"}"#).unwrap() };
+        assert!(matches!(tools.call(&call), Err(ToolError::NotAllowed(_))));
+    }
+}
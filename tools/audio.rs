@@ -0,0 +1,172 @@
+//! Audio decoding for the transcription/speech endpoints. Only WAV (PCM)
+//! is decoded in pure Rust here — mp3 and ogg/opus are lossy-compressed
+//! formats with real codecs behind them (Huffman-coded MDCT frames, not a
+//! simple binary layout like WAV or GGUF), and hand-rolling one is out of
+//! scope for this tree's no-dependency policy the same way TLS was for
+//! `downloader.rs`. Callers that need mp3/ogg input should transcode to
+//! WAV first (e.g. via a sidecar `ffmpeg` call) until a pure-Rust decoder
+//! lands.
+
+#[derive(Debug, PartialEq)]
+pub enum AudioError {
+    NotRiffWave,
+    MissingFormatChunk,
+    MissingDataChunk,
+    UnsupportedFormat(u16),
+}
+
+/// Decoded PCM audio: interleaved samples normalized to `[-1.0, 1.0]`.
+#[derive(Debug, PartialEq)]
+pub struct PcmAudio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+/// Parses a canonical RIFF/WAVE file with 16-bit PCM samples (format tag
+/// `1`) — the format whisper.cpp and most STT pipelines expect as input.
+pub fn decode_wav(bytes: &[u8]) -> Result<PcmAudio, AudioError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(AudioError::NotRiffWave);
+    }
+
+    let mut pos = 12;
+    let mut format_tag = None;
+    let mut channels = None;
+    let mut sample_rate = None;
+    let mut bits_per_sample = None;
+    let mut data: Option<&[u8]> = None;
+
+    while pos + 8 <= bytes.len() {
+        let chunk_id = &bytes[pos..pos + 4];
+        let chunk_len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body_start = pos + 8;
+        let body_end = (body_start + chunk_len).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match chunk_id {
+            b"fmt " if body.len() >= 16 => {
+                format_tag = Some(u16::from_le_bytes(body[0..2].try_into().unwrap()));
+                channels = Some(u16::from_le_bytes(body[2..4].try_into().unwrap()));
+                sample_rate = Some(u32::from_le_bytes(body[4..8].try_into().unwrap()));
+                bits_per_sample = Some(u16::from_le_bytes(body[14..16].try_into().unwrap()));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        pos = body_end + (chunk_len % 2);
+    }
+
+    let format_tag = format_tag.ok_or(AudioError::MissingFormatChunk)?;
+    if format_tag != 1 {
+        return Err(AudioError::UnsupportedFormat(format_tag));
+    }
+    let channels = channels.ok_or(AudioError::MissingFormatChunk)?;
+    let sample_rate = sample_rate.ok_or(AudioError::MissingFormatChunk)?;
+    let bits_per_sample = bits_per_sample.ok_or(AudioError::MissingFormatChunk)?;
+    let data = data.ok_or(AudioError::MissingDataChunk)?;
+
+    let samples = match bits_per_sample {
+        16 => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+            .collect(),
+        8 => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        other => return Err(AudioError::UnsupportedFormat(other)),
+    };
+
+    Ok(PcmAudio { sample_rate, channels, samples })
+}
+
+/// Writes `audio` out as a canonical 16-bit PCM RIFF/WAVE file — the
+/// inverse of `decode_wav`, used by `/v1/audio/speech` to return
+/// synthesized audio in a format every client can play without an opus
+/// decoder.
+pub fn encode_wav(audio: &PcmAudio) -> Vec<u8> {
+    let data: Vec<u8> = audio
+        .samples
+        .iter()
+        .map(|&s| ((s.clamp(-1.0, 1.0)) * i16::MAX as f32) as i16)
+        .flat_map(i16::to_le_bytes)
+        .collect();
+    let byte_rate = audio.sample_rate * audio.channels as u32 * 2;
+    let block_align = audio.channels * 2;
+
+    let mut out = Vec::with_capacity(44 + data.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&audio.channels.to_le_bytes());
+    out.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    out.extend_from_slice(&byte_rate.to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_wav(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data_bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let byte_rate = sample_rate * channels as u32 * 2;
+        let block_align = channels * 2;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&byte_rate.to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data_bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(&data_bytes);
+        out
+    }
+
+    #[test]
+    fn decodes_a_minimal_mono_pcm16_wav() {
+        let wav = make_wav(16000, 1, &[0, i16::MAX, i16::MIN]);
+        let audio = decode_wav(&wav).unwrap();
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.samples.len(), 3);
+        assert!((audio.samples[1] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rejects_non_riff_input() {
+        assert_eq!(decode_wav(b"not a wav file"), Err(AudioError::NotRiffWave));
+    }
+
+    #[test]
+    fn rejects_unsupported_format_tags() {
+        let mut wav = make_wav(16000, 1, &[0]);
+        wav[20] = 3; // format tag byte -> IEEE float, unsupported here
+        assert_eq!(decode_wav(&wav), Err(AudioError::UnsupportedFormat(3)));
+    }
+
+    #[test]
+    fn encode_wav_round_trips_through_decode_wav() {
+        let original = PcmAudio { sample_rate: 22050, channels: 1, samples: vec![0.0, 0.5, -0.5, 1.0, -1.0] };
+        let encoded = encode_wav(&original);
+        let decoded = decode_wav(&encoded).unwrap();
+        assert_eq!(decoded.sample_rate, original.sample_rate);
+        assert_eq!(decoded.channels, original.channels);
+        for (a, b) in original.samples.iter().zip(&decoded.samples) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Append-only, tamper-evident audit log for compliance: every request
+//! through `server.rs`'s dispatcher gets one [`AuditRecord`], hash-chained
+//! to the entry before it so an edited or deleted line breaks the chain
+//! (see [`verify_chain`]) without needing a separate signing key.
+//!
+//! Prompt/response bodies are recorded only when `include_bodies` is set
+//! at construction, and even then are run through [`redact`] first —
+//! request bodies routinely carry customer PII that a compliance program
+//! has no business storing in the clear.
+//!
+//! Two sinks, chosen by `[audit]` config (see `config.rs`): appending
+//! JSON lines to a file, the same shape `logging::JsonLogger` already
+//! writes, or forwarding each line as an RFC 5424 syslog message over
+//! UDP for shops that already centralize logs that way. The syslog sink
+//! is a bare `UdpSocket` plus the wire format rather than a syslog crate
+//! or `libc` socket API, matching this tree's no-external-dependency
+//! policy (see `sha256.rs`'s own hand-rolled reasoning).
+
+use crate::json::{Json, ObjectBuilder};
+use crate::sha256;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::UdpSocket;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replaces every occurrence of each `patterns` entry in `text` with
+/// `[REDACTED]`. Patterns are plain substrings rather than regexes — this
+/// tree has no regex engine — so a deployment redacting something
+/// pattern-shaped (an email domain, a card prefix) lists the literal
+/// values it knows will appear rather than a general pattern.
+pub fn redact(text: &str, patterns: &[String]) -> String {
+    let mut out = text.to_string();
+    for pattern in patterns {
+        if !pattern.is_empty() {
+            out = out.replace(pattern.as_str(), "[REDACTED]");
+        }
+    }
+    out
+}
+
+/// One request's audit entry, hash-chained to the entry before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub request_id: String,
+    pub timestamp: u64,
+    pub method: String,
+    pub path: String,
+    pub client: Option<String>,
+    pub status: u16,
+    pub prompt: Option<String>,
+    pub response: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn to_json(&self) -> Json {
+        let mut builder = ObjectBuilder::new()
+            .set("request_id", Json::String(self.request_id.clone()))
+            .set("timestamp", Json::Number(self.timestamp as f64))
+            .set("method", Json::String(self.method.clone()))
+            .set("path", Json::String(self.path.clone()))
+            .set("status", Json::Number(self.status as f64))
+            .set("prev_hash", Json::String(self.prev_hash.clone()))
+            .set("hash", Json::String(self.hash.clone()));
+        if let Some(client) = &self.client {
+            builder = builder.set("client", Json::String(client.clone()));
+        }
+        if let Some(prompt) = &self.prompt {
+            builder = builder.set("prompt", Json::String(prompt.clone()));
+        }
+        if let Some(response) = &self.response {
+            builder = builder.set("response", Json::String(response.clone()));
+        }
+        builder.build()
+    }
+
+    fn from_json(parsed: &Json) -> Option<AuditRecord> {
+        Some(AuditRecord {
+            request_id: parsed.get("request_id").and_then(Json::as_str)?.to_string(),
+            timestamp: parsed.get("timestamp").and_then(Json::as_f64)? as u64,
+            method: parsed.get("method").and_then(Json::as_str)?.to_string(),
+            path: parsed.get("path").and_then(Json::as_str)?.to_string(),
+            client: parsed.get("client").and_then(Json::as_str).map(str::to_string),
+            status: parsed.get("status").and_then(Json::as_f64)? as u16,
+            prompt: parsed.get("prompt").and_then(Json::as_str).map(str::to_string),
+            response: parsed.get("response").and_then(Json::as_str).map(str::to_string),
+            prev_hash: parsed.get("prev_hash").and_then(Json::as_str)?.to_string(),
+            hash: parsed.get("hash").and_then(Json::as_str)?.to_string(),
+        })
+    }
+
+    /// `sha256(canonical json with `hash` cleared)` — the value [`AuditLogger::log`]
+    /// stamps into `hash` and [`verify_chain`] recomputes to check it.
+    fn content_hash(&self) -> String {
+        let mut unhashed = self.clone();
+        unhashed.hash = String::new();
+        sha256::hex(&sha256::sha256(unhashed.to_json().to_string().as_bytes()))
+    }
+}
+
+/// Where an [`AuditLogger`] writes rendered entries.
+enum AuditSink {
+    File(Mutex<File>),
+    Syslog(UdpSocket),
+}
+
+/// Appends one [`AuditRecord`] per request. The same leaked-`'static`-
+/// plus-interior-mutability shape as `logging::JsonLogger`, but tracking
+/// `last_hash` so consecutive entries chain together.
+pub struct AuditLogger {
+    sink: AuditSink,
+    include_bodies: bool,
+    redact_patterns: Vec<String>,
+    last_hash: Mutex<String>,
+}
+
+impl AuditLogger {
+    /// Opens (creating if needed) an append-only audit log at `path`.
+    pub fn open_file(path: impl AsRef<Path>, include_bodies: bool, redact_patterns: Vec<String>) -> std::io::Result<AuditLogger> {
+        let file = OpenOptions::new().create(true).append(true).open(path.as_ref())?;
+        Ok(AuditLogger { sink: AuditSink::File(Mutex::new(file)), include_bodies, redact_patterns, last_hash: Mutex::new(String::new()) })
+    }
+
+    /// Binds an ephemeral local UDP socket and connects it to `addr`, the
+    /// syslog receiver each entry is forwarded to.
+    pub fn open_syslog(addr: &str, include_bodies: bool, redact_patterns: Vec<String>) -> std::io::Result<AuditLogger> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(addr)?;
+        Ok(AuditLogger { sink: AuditSink::Syslog(socket), include_bodies, redact_patterns, last_hash: Mutex::new(String::new()) })
+    }
+
+    /// Appends one entry for a completed request. `prompt`/`response` are
+    /// dropped entirely unless `include_bodies` was set at construction,
+    /// and [`redact`]ed through `redact_patterns` when they're kept.
+    pub fn log(&self, request_id: &str, method: &str, path: &str, client: Option<&str>, status: u16, prompt: Option<&str>, response: Option<&str>) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut last_hash = self.last_hash.lock().unwrap();
+        let mut record = AuditRecord {
+            request_id: request_id.to_string(),
+            timestamp,
+            method: method.to_string(),
+            path: path.to_string(),
+            client: client.map(str::to_string),
+            status,
+            prompt: if self.include_bodies { prompt.map(|p| redact(p, &self.redact_patterns)) } else { None },
+            response: if self.include_bodies { response.map(|r| redact(r, &self.redact_patterns)) } else { None },
+            prev_hash: last_hash.clone(),
+            hash: String::new(),
+        };
+        record.hash = record.content_hash();
+        *last_hash = record.hash.clone();
+        drop(last_hash);
+        self.write(&record.to_json().to_string());
+    }
+
+    fn write(&self, line: &str) {
+        match &self.sink {
+            AuditSink::File(file) => {
+                let mut file = file.lock().unwrap();
+                let _ = writeln!(file, "{line}");
+            }
+            AuditSink::Syslog(socket) => {
+                // RFC 5424 header (`<PRI>VERSION TIMESTAMP HOST APP-NAME PROCID MSGID`)
+                // ahead of the structured-data-less message; PRI 14 = facility 1
+                // (user-level), severity 6 (informational).
+                let _ = socket.send(format!("<14>1 - - ai-server - audit - {line}").as_bytes());
+            }
+        }
+    }
+}
+
+/// Reads back every [`AuditRecord`] from a file-sink audit log, in
+/// append order — the counterpart to `usage::UsageStore::query`'s
+/// full-file scan, for `ai-server audit verify` and the tests below.
+pub fn read_file(path: impl AsRef<Path>) -> std::io::Result<Vec<AuditRecord>> {
+    let file = File::open(path)?;
+    Ok(BufReader::new(file).lines().map_while(Result::ok).filter_map(|line| AuditRecord::from_json(&Json::parse(&line).ok()?)).collect())
+}
+
+/// Recomputes each record's hash from its own fields and checks it
+/// against both the stored `hash` and the previous record's stored
+/// `hash` — a mismatch anywhere means a line was edited, reordered, or
+/// removed after the fact.
+pub fn verify_chain(records: &[AuditRecord]) -> bool {
+    let mut expected_prev = String::new();
+    for record in records {
+        if record.prev_hash != expected_prev || record.content_hash() != record.hash {
+            return false;
+        }
+        expected_prev = record.hash.clone();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("ai-server-audit-test-{name}-{:x}.jsonl", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]))
+    }
+
+    #[test]
+    fn redact_replaces_every_occurrence_of_each_pattern() {
+        let redacted = redact("email me at a@b.com, cc a@b.com", &["a@b.com".to_string()]);
+        assert_eq!(redacted, "email me at [REDACTED], cc [REDACTED]");
+    }
+
+    #[test]
+    fn redact_is_a_no_op_with_no_patterns() {
+        assert_eq!(redact("hello", &[]), "hello");
+    }
+
+    #[test]
+    fn log_omits_bodies_when_include_bodies_is_false() {
+        let path = temp_path("no-bodies");
+        let logger = AuditLogger::open_file(&path, false, Vec::new()).unwrap();
+        logger.log("r1", "POST", "/v1/completions", Some("key-a"), 200, Some("secret prompt"), Some("secret response"));
+
+        let records = read_file(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].prompt, None);
+        assert_eq!(records[0].response, None);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn log_redacts_bodies_when_include_bodies_is_true() {
+        let path = temp_path("redacted-bodies");
+        let logger = AuditLogger::open_file(&path, true, vec!["secret".to_string()]).unwrap();
+        logger.log("r1", "POST", "/v1/completions", Some("key-a"), 200, Some("this is secret"), Some("no secret here"));
+
+        let records = read_file(&path).unwrap();
+        assert_eq!(records[0].prompt.as_deref(), Some("this is [REDACTED]"));
+        assert_eq!(records[0].response.as_deref(), Some("no [REDACTED] here"));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn consecutive_entries_chain_hashes_and_the_chain_verifies() {
+        let path = temp_path("chain");
+        let logger = AuditLogger::open_file(&path, false, Vec::new()).unwrap();
+        logger.log("r1", "POST", "/v1/completions", None, 200, None, None);
+        logger.log("r2", "POST", "/v1/completions", None, 200, None, None);
+
+        let records = read_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].prev_hash, "");
+        assert_eq!(records[1].prev_hash, records[0].hash);
+        assert!(verify_chain(&records));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn verify_chain_detects_an_edited_entry() {
+        let path = temp_path("tamper");
+        let logger = AuditLogger::open_file(&path, false, Vec::new()).unwrap();
+        logger.log("r1", "POST", "/v1/completions", None, 200, None, None);
+        logger.log("r2", "POST", "/v1/completions", None, 200, None, None);
+
+        let mut records = read_file(&path).unwrap();
+        records[0].status = 500;
+        assert!(!verify_chain(&records));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn verify_chain_detects_a_removed_entry() {
+        let path = temp_path("removed");
+        let logger = AuditLogger::open_file(&path, false, Vec::new()).unwrap();
+        logger.log("r1", "POST", "/v1/completions", None, 200, None, None);
+        logger.log("r2", "POST", "/v1/completions", None, 200, None, None);
+        logger.log("r3", "POST", "/v1/completions", None, 200, None, None);
+
+        let mut records = read_file(&path).unwrap();
+        records.remove(1);
+        assert!(!verify_chain(&records));
+
+        std::fs::remove_file(&path).ok();
+    }
+}
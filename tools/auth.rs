@@ -0,0 +1,335 @@
+//! API key authentication and per-key rate limiting for the HTTP API.
+//! Disabled by default (an empty key list means anyone can connect, the
+//! same posture this server has always had); once `[auth].api_keys` is
+//! set, every request needs a valid `Authorization: Bearer <key>` header,
+//! and each key gets its own token-bucket request rate and daily token
+//! budget so one noisy client on the LAN can't starve the others.
+//!
+//! There's no per-request token count available uniformly across every
+//! handler (only the buffered completion/chat paths know what they
+//! generated, and even then only after the fact), so [`AuthRegistry`]
+//! meters the response body's byte length as a token proxy — the same
+//! trade `handle_embeddings` makes treating input bytes as token ids.
+//! Streaming responses take over the connection directly and never reach
+//! the point where [`AuthRegistry::record_usage`] would be called, so
+//! quota tracking is buffered-response-only for now.
+
+use crate::http::Request;
+use crate::scheduler::PriorityClass;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    MissingApiKey,
+    InvalidApiKey,
+    RateLimited,
+    QuotaExceeded,
+}
+
+/// Per-key generation bounds `server.rs` enforces around each
+/// `InferenceBackend::generate`/`generate_with_images` call: a wall-clock
+/// deadline (see `health::check_backend_responsive`'s `mpsc`/`recv_timeout`
+/// pattern, reused there for the same "can't preempt a synchronous call"
+/// reason) and a cap on how much of the completion is returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationLimits {
+    pub max_output_tokens: usize,
+    pub timeout: Duration,
+}
+
+impl Default for GenerationLimits {
+    fn default() -> Self {
+        GenerationLimits { max_output_tokens: 256, timeout: Duration::from_secs(60) }
+    }
+}
+
+/// Refills at `requests_per_minute / 60` tokens per second, up to a
+/// capacity of `requests_per_minute` — a short burst is allowed, but
+/// sustained traffic is capped at the configured rate.
+struct TokenBucket {
+    tokens: f64,
+    capacity: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: usize) -> Self {
+        let capacity = requests_per_minute as f64;
+        TokenBucket { tokens: capacity, capacity, refill_per_second: capacity / 60.0, last_refill: Instant::now() }
+    }
+
+    fn try_take(&mut self) -> bool {
+        let elapsed = self.last_refill.elapsed();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+        self.last_refill = Instant::now();
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// One key's rate-limit bucket and running daily token usage.
+struct KeyState {
+    bucket: TokenBucket,
+    quota_day: u64,
+    quota_used: u64,
+}
+
+impl KeyState {
+    fn new(requests_per_minute: usize) -> Self {
+        KeyState { bucket: TokenBucket::new(requests_per_minute), quota_day: current_day(), quota_used: 0 }
+    }
+
+    /// Zeroes `quota_used` when the wall-clock day has rolled over since
+    /// the last request, so quotas reset at UTC midnight instead of on a
+    /// rolling 24h window from first use.
+    fn roll_over_if_new_day(&mut self) {
+        let today = current_day();
+        if today != self.quota_day {
+            self.quota_day = today;
+            self.quota_used = 0;
+        }
+    }
+}
+
+fn current_day() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs() / 86_400
+}
+
+/// Validates API keys and enforces per-key rate limits and daily token
+/// quotas. Empty `keys` disables auth entirely: [`authenticate`](Self::authenticate)
+/// always succeeds and returns `None` in place of a key.
+pub struct AuthRegistry {
+    keys: HashSet<String>,
+    requests_per_minute: usize,
+    daily_token_quota: usize,
+    state: Mutex<HashMap<String, KeyState>>,
+    /// Which `scheduler::PriorityClass` each key's requests should be
+    /// submitted under. A key with no entry here gets `PriorityClass`'s
+    /// default (`Interactive`) — see [`priority_class_for`](Self::priority_class_for).
+    priority_by_key: HashMap<String, PriorityClass>,
+    /// [`GenerationLimits`] applied to a key with no entry in
+    /// `generation_limits_by_key` — see [`generation_limits_for`](Self::generation_limits_for).
+    default_generation_limits: GenerationLimits,
+    /// Per-key [`GenerationLimits`] overrides, keyed the same way as
+    /// `priority_by_key`.
+    generation_limits_by_key: HashMap<String, GenerationLimits>,
+}
+
+impl AuthRegistry {
+    pub fn new(keys: Vec<String>, requests_per_minute: usize, daily_token_quota: usize) -> Self {
+        AuthRegistry::with_priority_classes(keys, requests_per_minute, daily_token_quota, HashMap::new())
+    }
+
+    pub fn with_priority_classes(
+        keys: Vec<String>,
+        requests_per_minute: usize,
+        daily_token_quota: usize,
+        priority_by_key: HashMap<String, PriorityClass>,
+    ) -> Self {
+        AuthRegistry::with_generation_limits(
+            keys,
+            requests_per_minute,
+            daily_token_quota,
+            priority_by_key,
+            GenerationLimits::default(),
+            HashMap::new(),
+        )
+    }
+
+    pub fn with_generation_limits(
+        keys: Vec<String>,
+        requests_per_minute: usize,
+        daily_token_quota: usize,
+        priority_by_key: HashMap<String, PriorityClass>,
+        default_generation_limits: GenerationLimits,
+        generation_limits_by_key: HashMap<String, GenerationLimits>,
+    ) -> Self {
+        AuthRegistry {
+            keys: keys.into_iter().collect(),
+            requests_per_minute,
+            daily_token_quota,
+            state: Mutex::new(HashMap::new()),
+            priority_by_key,
+            default_generation_limits,
+            generation_limits_by_key,
+        }
+    }
+
+    /// Looks up the `scheduler::PriorityClass` configured for `key` (the
+    /// value [`authenticate`](Self::authenticate) returned), defaulting to
+    /// `Interactive` for an unconfigured key or when auth is disabled and
+    /// no key is available at all.
+    pub fn priority_class_for(&self, key: Option<&str>) -> PriorityClass {
+        key.and_then(|k| self.priority_by_key.get(k).copied()).unwrap_or_default()
+    }
+
+    /// Looks up the [`GenerationLimits`] configured for `key`, falling back
+    /// to `default_generation_limits` for an unconfigured key or when auth
+    /// is disabled and no key is available at all — mirrors
+    /// [`priority_class_for`](Self::priority_class_for).
+    pub fn generation_limits_for(&self, key: Option<&str>) -> GenerationLimits {
+        key.and_then(|k| self.generation_limits_by_key.get(k).copied()).unwrap_or(self.default_generation_limits)
+    }
+
+    /// Checks `req`'s `Authorization` header against the configured keys
+    /// and, for a valid key, consumes one rate-limit token and confirms
+    /// the daily quota isn't already spent. Returns the key on success so
+    /// the caller can pass it to [`record_usage`](Self::record_usage)
+    /// once the response is known.
+    pub fn authenticate(&self, req: &Request) -> Result<Option<String>, AuthError> {
+        if self.keys.is_empty() {
+            return Ok(None);
+        }
+        let key = req.header("authorization").and_then(|h| h.strip_prefix("Bearer ")).ok_or(AuthError::MissingApiKey)?;
+        if !self.keys.contains(key) {
+            return Err(AuthError::InvalidApiKey);
+        }
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entry(key.to_string()).or_insert_with(|| KeyState::new(self.requests_per_minute));
+        if !entry.bucket.try_take() {
+            return Err(AuthError::RateLimited);
+        }
+        entry.roll_over_if_new_day();
+        if self.daily_token_quota > 0 && entry.quota_used >= self.daily_token_quota as u64 {
+            return Err(AuthError::QuotaExceeded);
+        }
+        Ok(Some(key.to_string()))
+    }
+
+    /// Adds `tokens` to `key`'s running daily total. A no-op for a key
+    /// that was never authenticated (shouldn't happen) or when auth is
+    /// disabled, since callers only have a key to pass here at all when
+    /// [`authenticate`](Self::authenticate) returned one.
+    pub fn record_usage(&self, key: &str, tokens: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.get_mut(key) {
+            entry.roll_over_if_new_day();
+            entry.quota_used += tokens as u64;
+        }
+    }
+
+    /// `key`'s running daily token total, `0` for a key that's never
+    /// authenticated. Used by `tenancy::TenantRegistry`'s callers in
+    /// `server.rs` to roll up usage across a tenant's keys.
+    pub fn quota_used(&self, key: &str) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        match state.get_mut(key) {
+            Some(entry) => {
+                entry.roll_over_if_new_day();
+                entry.quota_used
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_bearer(token: Option<&str>) -> Request {
+        let mut headers = std::collections::BTreeMap::new();
+        if let Some(token) = token {
+            headers.insert("authorization".to_string(), format!("Bearer {token}"));
+        }
+        Request { method: crate::http::Method::Post, path: "/v1/completions".to_string(), query: Default::default(), headers, body: Vec::new() }
+    }
+
+    #[test]
+    fn authenticate_is_a_no_op_when_no_keys_are_configured() {
+        let auth = AuthRegistry::new(Vec::new(), 60, 0);
+        assert_eq!(auth.authenticate(&request_with_bearer(None)), Ok(None));
+    }
+
+    #[test]
+    fn authenticate_rejects_a_missing_header_when_keys_are_configured() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.authenticate(&request_with_bearer(None)), Err(AuthError::MissingApiKey));
+    }
+
+    #[test]
+    fn authenticate_rejects_an_unknown_key() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.authenticate(&request_with_bearer(Some("wrong"))), Err(AuthError::InvalidApiKey));
+    }
+
+    #[test]
+    fn authenticate_accepts_a_configured_key() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.authenticate(&request_with_bearer(Some("secret"))), Ok(Some("secret".to_string())));
+    }
+
+    #[test]
+    fn authenticate_rate_limits_after_the_bucket_is_drained() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 1, 0);
+        assert!(auth.authenticate(&request_with_bearer(Some("secret"))).is_ok());
+        assert_eq!(auth.authenticate(&request_with_bearer(Some("secret"))), Err(AuthError::RateLimited));
+    }
+
+    #[test]
+    fn record_usage_trips_the_daily_quota() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 1000, 10);
+        assert!(auth.authenticate(&request_with_bearer(Some("secret"))).is_ok());
+        auth.record_usage("secret", 10);
+        assert_eq!(auth.authenticate(&request_with_bearer(Some("secret"))), Err(AuthError::QuotaExceeded));
+    }
+
+    #[test]
+    fn quota_used_reports_zero_for_a_key_that_never_authenticated() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.quota_used("secret"), 0);
+    }
+
+    #[test]
+    fn quota_used_reflects_recorded_usage() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert!(auth.authenticate(&request_with_bearer(Some("secret"))).is_ok());
+        auth.record_usage("secret", 7);
+        assert_eq!(auth.quota_used("secret"), 7);
+    }
+
+    #[test]
+    fn priority_class_for_defaults_to_interactive_for_an_unconfigured_key() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.priority_class_for(Some("secret")), PriorityClass::Interactive);
+        assert_eq!(auth.priority_class_for(None), PriorityClass::Interactive);
+    }
+
+    #[test]
+    fn priority_class_for_returns_the_configured_class() {
+        let mut priority_by_key = HashMap::new();
+        priority_by_key.insert("bulk-embedder".to_string(), PriorityClass::Batch);
+        let auth = AuthRegistry::with_priority_classes(vec!["bulk-embedder".to_string()], 60, 0, priority_by_key);
+        assert_eq!(auth.priority_class_for(Some("bulk-embedder")), PriorityClass::Batch);
+    }
+
+    #[test]
+    fn generation_limits_for_defaults_when_unconfigured() {
+        let auth = AuthRegistry::new(vec!["secret".to_string()], 60, 0);
+        assert_eq!(auth.generation_limits_for(Some("secret")), GenerationLimits::default());
+        assert_eq!(auth.generation_limits_for(None), GenerationLimits::default());
+    }
+
+    #[test]
+    fn generation_limits_for_returns_the_configured_override() {
+        let mut generation_limits_by_key = HashMap::new();
+        let limits = GenerationLimits { max_output_tokens: 32, timeout: Duration::from_secs(5) };
+        generation_limits_by_key.insert("bulk-embedder".to_string(), limits);
+        let auth = AuthRegistry::with_generation_limits(
+            vec!["bulk-embedder".to_string()],
+            60,
+            0,
+            HashMap::new(),
+            GenerationLimits::default(),
+            generation_limits_by_key,
+        );
+        assert_eq!(auth.generation_limits_for(Some("bulk-embedder")), limits);
+        assert_eq!(auth.generation_limits_for(Some("other")), GenerationLimits::default());
+    }
+}
@@ -0,0 +1,283 @@
+//! Picks which compute backend an inference request should run on,
+//! instead of making the operator pass a `--backend` flag and hope it
+//! matches the box it's running on. [`candidates`] enumerates what's
+//! actually available on this host by reusing the detection each
+//! backend-specific module already does (`gpu.rs` for Metal, `cuda.rs`,
+//! `vulkan.rs`, and `hardware.rs`'s NEON flag for the two CPU paths), and
+//! [`select`] scores them against a model's memory requirement
+//! (`resources::ModelMemoryProfile::estimated_request_bytes`, at zero
+//! context tokens — just the resident weights) to pick one, honoring a
+//! config override when the operator wants to force a specific backend.
+//!
+//! [`Backend::CpuScalar`] is always available and always fits (it has no
+//! separate device memory of its own — see `hardware.rs`'s memory
+//! reporting), so [`select`] can never come back empty. No
+//! [`InferenceBackend`](crate::InferenceBackend) besides `EchoBackend`
+//! exists to actually run on the four accelerated backends yet (see
+//! `cuda.rs`/`vulkan.rs`'s module doc comments), so today `select`'s
+//! result only drives the `chosen_backend` metric/log line in `server.rs`
+//! — it's the layer a real GPU-backed backend would ask before loading a
+//! model, once one exists.
+
+use crate::cuda;
+use crate::gpu;
+use crate::hardware::HardwareProfile;
+use crate::vulkan;
+
+/// A compute backend this tree knows how to detect. Ordered by
+/// [`select`]'s default preference: an accelerator, if one's present and
+/// fits, beats either CPU path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Metal,
+    Cuda,
+    Vulkan,
+    CpuNeon,
+    CpuScalar,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "metal" => Some(Backend::Metal),
+            "cuda" => Some(Backend::Cuda),
+            "vulkan" => Some(Backend::Vulkan),
+            "cpu-neon" => Some(Backend::CpuNeon),
+            "cpu-scalar" => Some(Backend::CpuScalar),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Metal => "metal",
+            Backend::Cuda => "cuda",
+            Backend::Vulkan => "vulkan",
+            Backend::CpuNeon => "cpu-neon",
+            Backend::CpuScalar => "cpu-scalar",
+        }
+    }
+}
+
+/// One backend's availability on this host, and the memory the model
+/// would have to fit into if chosen — `None` means "no separate device
+/// memory to check", which is true of both CPU paths (they share the
+/// process's own heap, not a fixed device budget) and any accelerator
+/// this host's detection couldn't size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendCandidate {
+    pub backend: Backend,
+    pub available: bool,
+    pub device_memory_bytes: Option<u64>,
+    /// Human-readable reason for `--explain`-style output: why this
+    /// backend is or isn't available, and (if available) what it offers.
+    pub reason: String,
+}
+
+impl BackendCandidate {
+    /// Whether a model needing `weights_bytes` of resident memory can run
+    /// on this candidate: unavailable backends never fit, and a backend
+    /// with no known device budget is assumed to fit (its own loader
+    /// would be the one to fail, the same way `EchoBackend` never
+    /// triggers `resources::MemoryBudget` today).
+    pub fn fits(&self, weights_bytes: u64) -> bool {
+        if !self.available {
+            return false;
+        }
+        match self.device_memory_bytes {
+            Some(budget) => weights_bytes <= budget,
+            None => true,
+        }
+    }
+}
+
+/// Enumerates every backend's availability on this host, in [`Backend`]'s
+/// preference order.
+pub fn candidates(hardware: &HardwareProfile) -> Vec<BackendCandidate> {
+    let metal = gpu::GpuInfo::detect();
+    let cuda = cuda::CudaInfo::detect();
+    let vulkan = vulkan::VulkanInfo::detect();
+
+    vec![
+        match &metal {
+            Some(gpu) => BackendCandidate {
+                backend: Backend::Metal,
+                available: true,
+                device_memory_bytes: gpu.unified_memory_bytes,
+                reason: format!("Metal GPU detected ({})", gpu.chipset),
+            },
+            None => BackendCandidate {
+                backend: Backend::Metal,
+                available: false,
+                device_memory_bytes: None,
+                reason: "no Metal GPU detected".to_string(),
+            },
+        },
+        match &cuda {
+            Some(info) => BackendCandidate {
+                backend: Backend::Cuda,
+                available: true,
+                device_memory_bytes: Some(info.total_memory_bytes()),
+                reason: format!("{} NVIDIA GPU(s) detected", info.devices.len()),
+            },
+            None => BackendCandidate {
+                backend: Backend::Cuda,
+                available: false,
+                device_memory_bytes: None,
+                reason: "no NVIDIA GPU detected (or nvidia-smi unavailable)".to_string(),
+            },
+        },
+        match &vulkan {
+            Some(info) => BackendCandidate {
+                backend: Backend::Vulkan,
+                available: true,
+                // `vulkaninfo --summary` doesn't report device memory, so
+                // there's no budget to check here — see `fits`'s doc
+                // comment for why that means "assume it fits".
+                device_memory_bytes: None,
+                reason: format!("{} Vulkan device(s) detected", info.devices.len()),
+            },
+            None => BackendCandidate {
+                backend: Backend::Vulkan,
+                available: false,
+                device_memory_bytes: None,
+                reason: "no Vulkan device detected (or vulkaninfo unavailable)".to_string(),
+            },
+        },
+        BackendCandidate {
+            backend: Backend::CpuNeon,
+            available: hardware.cpu.neon,
+            device_memory_bytes: hardware.memory.total_bytes,
+            reason: if hardware.cpu.neon { "host CPU supports NEON".to_string() } else { "host CPU has no NEON support".to_string() },
+        },
+        BackendCandidate {
+            backend: Backend::CpuScalar,
+            available: true,
+            device_memory_bytes: hardware.memory.total_bytes,
+            reason: "always available as the scalar fallback".to_string(),
+        },
+    ]
+}
+
+/// The outcome of [`select`]: which backend was picked, and the full
+/// candidate list with reasons — the "--explain" output `cli.rs`'s
+/// `probe` subcommand prints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub chosen: Backend,
+    pub candidates: Vec<BackendCandidate>,
+}
+
+/// Picks a backend for a model needing `weights_bytes` of resident
+/// memory (pass `0` when that isn't known yet — every candidate trivially
+/// "fits" a zero-byte model). `override_backend`, when given, is honored
+/// as long as it's available; an override that's present but doesn't fit
+/// the model, or isn't available on this host at all, falls through to
+/// the normal preference order rather than failing outright — the same
+/// "don't make the operator's config choice fail the whole request"
+/// stance `context_policy.rs`'s per-request override takes.
+pub fn select(hardware: &HardwareProfile, weights_bytes: u64, override_backend: Option<Backend>) -> Selection {
+    let candidates = candidates(hardware);
+
+    if let Some(backend) = override_backend {
+        if let Some(candidate) = candidates.iter().find(|c| c.backend == backend) {
+            if candidate.fits(weights_bytes) {
+                return Selection { chosen: backend, candidates };
+            }
+        }
+    }
+
+    let chosen = candidates
+        .iter()
+        .find(|c| c.fits(weights_bytes))
+        .map(|c| c.backend)
+        .unwrap_or(Backend::CpuScalar);
+    Selection { chosen, candidates }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::{CoreTopology, CpuCapabilities, MemoryInfo};
+
+    fn hardware_with_neon(neon: bool) -> HardwareProfile {
+        HardwareProfile {
+            cpu: CpuCapabilities {
+                arch: "aarch64",
+                logical_cores: 8,
+                physical_cores: 8,
+                cache_line_size: 64,
+                neon,
+                sve: false,
+                fp16: false,
+                avx2: false,
+                avx512f: false,
+                avx512vnni: false,
+                fma: false,
+            },
+            memory: MemoryInfo { total_bytes: Some(16 * 1024 * 1024 * 1024), available_bytes: None },
+            topology: CoreTopology::Uniform { cores: 8 },
+        }
+    }
+
+    #[test]
+    fn parse_and_as_str_round_trip() {
+        for backend in [Backend::Metal, Backend::Cuda, Backend::Vulkan, Backend::CpuNeon, Backend::CpuScalar] {
+            assert_eq!(Backend::parse(backend.as_str()), Some(backend));
+        }
+        assert_eq!(Backend::parse("bogus"), None);
+    }
+
+    #[test]
+    fn candidate_without_a_device_budget_always_fits() {
+        let candidate = BackendCandidate { backend: Backend::Vulkan, available: true, device_memory_bytes: None, reason: String::new() };
+        assert!(candidate.fits(u64::MAX));
+    }
+
+    #[test]
+    fn unavailable_candidate_never_fits() {
+        let candidate = BackendCandidate { backend: Backend::Cuda, available: false, device_memory_bytes: Some(u64::MAX), reason: String::new() };
+        assert!(!candidate.fits(0));
+    }
+
+    #[test]
+    fn available_candidate_with_a_budget_rejects_a_model_too_large_for_it() {
+        let candidate = BackendCandidate { backend: Backend::Cuda, available: true, device_memory_bytes: Some(1024), reason: String::new() };
+        assert!(candidate.fits(1024));
+        assert!(!candidate.fits(1025));
+    }
+
+    #[test]
+    fn select_without_gpus_or_neon_falls_back_to_cpu_scalar() {
+        let selection = select(&hardware_with_neon(false), 0, None);
+        assert_eq!(selection.chosen, Backend::CpuScalar);
+        assert_eq!(selection.candidates.len(), 5);
+    }
+
+    #[test]
+    fn select_prefers_neon_over_scalar_when_available() {
+        let selection = select(&hardware_with_neon(true), 0, None);
+        // Neither Metal, CUDA, nor Vulkan can be asserted present in a test
+        // environment, but NEON always outranks scalar when it's the best
+        // available candidate, since it precedes CpuScalar in `candidates`.
+        assert_ne!(selection.chosen, Backend::CpuScalar);
+    }
+
+    #[test]
+    fn select_falls_through_an_override_that_is_not_available() {
+        let selection = select(&hardware_with_neon(false), 0, Some(Backend::Cuda));
+        assert_eq!(selection.chosen, Backend::CpuScalar);
+    }
+
+    #[test]
+    fn select_falls_through_an_override_that_does_not_fit_the_model() {
+        let selection = select(&hardware_with_neon(true), u64::MAX, Some(Backend::CpuNeon));
+        assert_eq!(selection.chosen, Backend::CpuScalar);
+    }
+
+    #[test]
+    fn select_honors_an_override_that_fits() {
+        let selection = select(&hardware_with_neon(true), 0, Some(Backend::CpuNeon));
+        assert_eq!(selection.chosen, Backend::CpuNeon);
+    }
+}
@@ -0,0 +1,65 @@
+//! Minimal base64 (standard alphabet, with padding) encoder/decoder.
+//! Encoding is used by the WebSocket handshake's `Sec-WebSocket-Accept`
+//! header; decoding is used to carry binary payloads (WAV audio) over
+//! WebSocket text frames, since `websocket.rs` only reads text frames.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, PartialEq)]
+pub struct Base64Error;
+
+pub fn decode(input: &str) -> Result<Vec<u8>, Base64Error> {
+    let input = input.trim_end_matches('=');
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for c in input.bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c).ok_or(Base64Error)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+pub fn encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity((input.len() + 2) / 3 * 4);
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(ALPHABET[(n >> 18 & 0x3f) as usize] as char);
+        out.push(ALPHABET[(n >> 12 & 0x3f) as usize] as char);
+        out.push(if chunk.len() > 1 { ALPHABET[(n >> 6 & 0x3f) as usize] as char } else { '=' });
+        out.push(if chunk.len() > 2 { ALPHABET[(n & 0x3f) as usize] as char } else { '=' });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_known_vectors() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn decode_reverses_encode() {
+        for input in [&b""[..], b"f", b"fo", b"foo", b"foobar"] {
+            assert_eq!(decode(&encode(input)).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_invalid_characters() {
+        assert_eq!(decode("not valid base64!"), Err(Base64Error));
+    }
+}
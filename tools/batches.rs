@@ -0,0 +1,470 @@
+//! Offline batch inference: `POST /v1/batches` accepts many completion
+//! requests at once — a JSON array under `"requests"`, or a JSONL string
+//! there instead — and works through them in a background thread rather
+//! than holding the HTTP connection open for however long a
+//! dataset-labeling run takes. Progress is persisted the same way
+//! `sessions.rs` persists conversation state — one status file per batch —
+//! plus a JSONL output file that grows as requests complete, so a client
+//! polls status instead of streaming the whole job over one connection.
+//!
+//! A batch resolves one model (and optionally one lora adapter) for all of
+//! its requests, from the same top-level `model`/`lora` fields a single
+//! `/v1/completions` call would use — `server.rs` resolves both once,
+//! synchronously, before handing the background thread a `process` closure
+//! that already has everything it needs. That keeps this module itself
+//! backend-agnostic: it has no opinion on how one request turns into a
+//! completion, only on queuing, running, and persisting the results.
+//!
+//! Multi-hour batches (bulk transcription, bulk embedding) on a laptop get
+//! interrupted constantly, so progress is also checkpointed: the original
+//! `requests` are persisted to `<id>.requests.jsonl` at submit time, and
+//! the output file's line count is itself the resume cursor — each line is
+//! one fully-processed request, written in submission order, so "how many
+//! lines are in the output file" and "how many requests to skip on resume"
+//! are the same number. [`BatchStore::resumable`] finds every batch a
+//! crash caught mid-run (anything not `Completed`/`Failed`) for `server.rs`
+//! to hand back to [`resume`] alongside a freshly re-resolved backend.
+//! Progress itself is only flushed to disk every [`CHECKPOINT_INTERVAL`]
+//! items (and always once a batch finishes) rather than on every single
+//! one, so a long batch isn't paying for an `fsync` per request.
+
+use crate::json::{Json, ObjectBuilder};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How often [`run_batch`] persists progress and `fsync`s the output file —
+/// see this module's doc comment for why checkpointing less often than
+/// every item is worth the small amount of duplicated work a crash between
+/// checkpoints could cause on resume.
+const CHECKPOINT_INTERVAL: usize = 10;
+
+pub type BatchId = String;
+
+static BATCH_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a fresh batch id, distinct from `sessions::new_session_id`'s
+/// ids (a batch isn't a conversation) but built the same way: a timestamp
+/// plus a process-local counter so ids stay unique across restarts too.
+pub fn new_batch_id() -> BatchId {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let n = BATCH_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("batch-{nanos:x}-{n}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl BatchStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BatchStatus::Queued => "queued",
+            BatchStatus::Running => "running",
+            BatchStatus::Completed => "completed",
+            BatchStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(BatchStatus::Queued),
+            "running" => Some(BatchStatus::Running),
+            "completed" => Some(BatchStatus::Completed),
+            "failed" => Some(BatchStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    pub status: BatchStatus,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    InvalidRequest(String),
+    Io(String),
+}
+
+/// Reads the `"requests"` field of a batch submission: either a JSON array
+/// of request objects, or a string holding one JSON object per line
+/// (JSONL) — the same two shapes OpenAI's batch file format and this
+/// tree's own preference for not requiring a real file upload both need
+/// to support.
+pub fn parse_requests(requests: &Json) -> Result<Vec<Json>, BatchError> {
+    match requests {
+        Json::Array(items) => Ok(items.clone()),
+        Json::String(text) => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Json::parse(line).map_err(|e| BatchError::InvalidRequest(e.to_string())))
+            .collect(),
+        _ => Err(BatchError::InvalidRequest("\"requests\" must be a JSON array or a JSONL string".to_string())),
+    }
+}
+
+/// Disk-backed batch jobs under a single root directory: `<root>/<id>.json`
+/// holds progress (plus the `model` id the batch was submitted against, so
+/// a restart can re-resolve the same backend), `<root>/<id>.requests.jsonl`
+/// holds the original submitted requests for [`resumable`](Self::resumable)
+/// to replay, and `<root>/<id>.output.jsonl` holds one result line per
+/// completed (or failed) request, appended in submission order.
+pub struct BatchStore {
+    root: PathBuf,
+}
+
+impl BatchStore {
+    pub fn open(root: impl Into<PathBuf>) -> std::io::Result<BatchStore> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(BatchStore { root })
+    }
+
+    fn meta_path(&self, id: &BatchId) -> PathBuf {
+        self.root.join(format!("{id}.json"))
+    }
+
+    pub fn output_path(&self, id: &BatchId) -> PathBuf {
+        self.root.join(format!("{id}.output.jsonl"))
+    }
+
+    fn requests_path(&self, id: &BatchId) -> PathBuf {
+        self.root.join(format!("{id}.requests.jsonl"))
+    }
+
+    fn save_progress(&self, id: &BatchId, model_id: &str, progress: &BatchProgress) -> std::io::Result<()> {
+        let body = ObjectBuilder::new()
+            .set("status", Json::String(progress.status.as_str().to_string()))
+            .set("model", Json::String(model_id.to_string()))
+            .set("total", Json::Number(progress.total as f64))
+            .set("completed", Json::Number(progress.completed as f64))
+            .set("failed", Json::Number(progress.failed as f64))
+            .build();
+        fs::write(self.meta_path(id), body.to_string())
+    }
+
+    /// Reads back a batch's current progress, or `None` for an id this
+    /// store never created (or one whose status file is missing/corrupt).
+    pub fn progress(&self, id: &BatchId) -> Option<BatchProgress> {
+        let (_model_id, progress) = self.progress_with_model(id)?;
+        Some(progress)
+    }
+
+    fn progress_with_model(&self, id: &BatchId) -> Option<(String, BatchProgress)> {
+        let text = fs::read_to_string(self.meta_path(id)).ok()?;
+        let parsed = Json::parse(&text).ok()?;
+        let status = BatchStatus::parse(parsed.get("status").and_then(Json::as_str)?)?;
+        let model_id = parsed.get("model").and_then(Json::as_str).unwrap_or_default().to_string();
+        let progress = BatchProgress {
+            status,
+            total: parsed.get("total").and_then(Json::as_f64)? as usize,
+            completed: parsed.get("completed").and_then(Json::as_f64)? as usize,
+            failed: parsed.get("failed").and_then(Json::as_f64)? as usize,
+        };
+        Some((model_id, progress))
+    }
+
+    /// Reads back a completed (or still-running) batch's output file. An
+    /// id with no output file yet (nothing has completed) returns an empty
+    /// string rather than an error.
+    pub fn output(&self, id: &BatchId) -> std::io::Result<String> {
+        match fs::read_to_string(self.output_path(id)) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Every batch this store knows about that a crash (rather than a
+    /// normal finish) interrupted: anything whose progress file still says
+    /// `Queued` or `Running` and still has its `<id>.requests.jsonl`
+    /// sidecar around. Returns each one's id, the `model` id it was
+    /// submitted against, the original requests to replay, and how many of
+    /// them are already accounted for in the output file — `server.rs`
+    /// re-resolves a backend for `model_id` and hands all of this to
+    /// [`resume`] at startup, the same "scan the directory, pick up
+    /// whatever's unfinished" shape `JobRegistry::reload` uses for its own
+    /// `*.state.json` files.
+    pub fn resumable(&self) -> Vec<(BatchId, String, Vec<Json>, usize)> {
+        let Ok(entries) = fs::read_dir(&self.root) else { return Vec::new() };
+        let mut found = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else { continue };
+            let Some((model_id, progress)) = self.progress_with_model(&id) else { continue };
+            if matches!(progress.status, BatchStatus::Completed | BatchStatus::Failed) {
+                continue;
+            }
+            let Ok(requests_text) = fs::read_to_string(self.requests_path(&id)) else { continue };
+            let Ok(requests) = parse_requests(&Json::String(requests_text)) else { continue };
+            let resume_from = self.output(&id).map(|text| text.lines().count()).unwrap_or(0);
+            found.push((id, model_id, requests, resume_from));
+        }
+        found
+    }
+}
+
+/// Records a fresh `Queued` progress file for `requests` (plus a
+/// `<id>.requests.jsonl` sidecar so a crash mid-run can be replayed, see
+/// [`BatchStore::resumable`]) and spawns a background thread that runs
+/// `process` against each one in submission order — one thread per batch,
+/// the same "just spawn it" approach `config::watch`'s poller and
+/// `model_pool::spawn_idle_reaper` take for their own background work.
+/// Returns the assigned id immediately without waiting on any request to
+/// finish.
+pub fn submit(
+    store: &'static BatchStore,
+    model_id: &str,
+    requests: Vec<Json>,
+    process: impl Fn(&Json) -> Result<Json, String> + Send + 'static,
+) -> Result<BatchId, BatchError> {
+    if requests.is_empty() {
+        return Err(BatchError::InvalidRequest("a batch must contain at least one request".to_string()));
+    }
+
+    let id = new_batch_id();
+    let progress = BatchProgress { status: BatchStatus::Queued, total: requests.len(), completed: 0, failed: 0 };
+    store.save_progress(&id, model_id, &progress).map_err(|e| BatchError::Io(e.to_string()))?;
+    let requests_text: String = requests.iter().map(|r| format!("{}\n", r.to_string())).collect();
+    fs::write(store.requests_path(&id), requests_text).map_err(|e| BatchError::Io(e.to_string()))?;
+
+    let model_id = model_id.to_string();
+    let thread_id = id.clone();
+    std::thread::spawn(move || run_batch(store, thread_id, model_id, requests, 0, process));
+    Ok(id)
+}
+
+/// Resumes a batch a crash caught mid-run: `resume_from` (from
+/// [`BatchStore::resumable`]) skips however many requests the output file
+/// already accounts for, appending rather than truncating it, and
+/// `progress.completed`/`failed` pick up from the persisted counts instead
+/// of restarting at zero.
+pub fn resume(
+    store: &'static BatchStore,
+    id: BatchId,
+    model_id: String,
+    requests: Vec<Json>,
+    resume_from: usize,
+    process: impl Fn(&Json) -> Result<Json, String> + Send + 'static,
+) {
+    std::thread::spawn(move || run_batch(store, id, model_id, requests, resume_from, process));
+}
+
+fn run_batch(store: &BatchStore, id: BatchId, model_id: String, requests: Vec<Json>, resume_from: usize, process: impl Fn(&Json) -> Result<Json, String>) {
+    // Derived from the output file itself rather than the last-checkpointed
+    // progress counters, which may be stale by up to `CHECKPOINT_INTERVAL`
+    // items if a crash landed between checkpoints.
+    let (completed_so_far, failed_so_far) = store
+        .output(&id)
+        .map(|text| {
+            text.lines().filter_map(|line| Json::parse(line).ok()).fold((0usize, 0usize), |(completed, failed), line| {
+                if line.get("error").is_some() {
+                    (completed, failed + 1)
+                } else {
+                    (completed + 1, failed)
+                }
+            })
+        })
+        .unwrap_or((0, 0));
+    let mut progress = BatchProgress { status: BatchStatus::Running, total: requests.len(), completed: completed_so_far, failed: failed_so_far };
+    let _ = store.save_progress(&id, &model_id, &progress);
+
+    let output = OpenOptions::new().create(true).append(resume_from > 0).truncate(resume_from == 0).write(true).open(store.output_path(&id));
+    let mut output = match output {
+        Ok(file) => file,
+        Err(_) => {
+            progress.status = BatchStatus::Failed;
+            let _ = store.save_progress(&id, &model_id, &progress);
+            return;
+        }
+    };
+
+    let mut since_checkpoint = 0usize;
+    for request in requests.iter().skip(resume_from) {
+        let custom_id = request.get("custom_id").and_then(Json::as_str).map(|s| Json::String(s.to_string())).unwrap_or(Json::Null);
+        let line = match process(request) {
+            Ok(response) => {
+                progress.completed += 1;
+                ObjectBuilder::new().set("custom_id", custom_id).set("response", response).build()
+            }
+            Err(message) => {
+                progress.failed += 1;
+                ObjectBuilder::new().set("custom_id", custom_id).set("error", Json::String(message)).build()
+            }
+        };
+        let _ = writeln!(output, "{}", line.to_string());
+        since_checkpoint += 1;
+        if since_checkpoint >= CHECKPOINT_INTERVAL {
+            let _ = output.sync_data();
+            let _ = store.save_progress(&id, &model_id, &progress);
+            since_checkpoint = 0;
+        }
+    }
+
+    let _ = output.sync_data();
+    progress.status = BatchStatus::Completed;
+    let _ = store.save_progress(&id, &model_id, &progress);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> &'static BatchStore {
+        let dir = std::env::temp_dir()
+            .join(format!("ai-server-batches-test-{:x}", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        Box::leak(Box::new(BatchStore::open(dir).unwrap()))
+    }
+
+    fn wait_for_completion(store: &BatchStore, id: &BatchId) -> BatchProgress {
+        for _ in 0..200 {
+            if let Some(progress) = store.progress(id) {
+                if progress.status == BatchStatus::Completed || progress.status == BatchStatus::Failed {
+                    return progress;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("batch did not finish in time");
+    }
+
+    #[test]
+    fn parse_requests_reads_jsonl_lines() {
+        let requests = parse_requests(&Json::String("{\"prompt\":\"a\"}\n{\"prompt\":\"b\"}\n".to_string())).unwrap();
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn parse_requests_reads_an_inline_array() {
+        let requests = parse_requests(&Json::parse(r#"[{"prompt":"a"},{"prompt":"b"}]"#).unwrap()).unwrap();
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn parse_requests_rejects_malformed_json() {
+        assert!(matches!(parse_requests(&Json::String("not json".to_string())), Err(BatchError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn parse_requests_rejects_a_non_array_non_string_value() {
+        assert!(matches!(parse_requests(&Json::Null), Err(BatchError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn submit_rejects_an_empty_batch() {
+        let store = temp_store();
+        let err = submit(store, "echo", Vec::new(), |_| Ok(Json::Null)).unwrap_err();
+        assert!(matches!(err, BatchError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn submit_processes_every_request_and_writes_the_output_file() {
+        let store = temp_store();
+        let requests = parse_requests(&Json::String("{\"prompt\":\"a\"}\n{\"prompt\":\"b\"}\n".to_string())).unwrap();
+        let id = submit(store, "echo", requests, |req| {
+            let prompt = req.get("prompt").and_then(Json::as_str).unwrap_or_default();
+            Ok(Json::String(format!("echo:{prompt}")))
+        })
+        .unwrap();
+
+        let progress = wait_for_completion(store, &id);
+        assert_eq!(progress, BatchProgress { status: BatchStatus::Completed, total: 2, completed: 2, failed: 0 });
+
+        let output = store.output(&id).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.contains("echo:a"));
+        assert!(output.contains("echo:b"));
+    }
+
+    #[test]
+    fn submit_records_failures_without_aborting_the_rest_of_the_batch() {
+        let store = temp_store();
+        let requests = parse_requests(&Json::String("{\"prompt\":\"ok\"}\n{\"prompt\":\"boom\"}\n".to_string())).unwrap();
+        let id = submit(store, "echo", requests, |req| {
+            let prompt = req.get("prompt").and_then(Json::as_str).unwrap_or_default();
+            if prompt == "boom" {
+                Err("failed to generate".to_string())
+            } else {
+                Ok(Json::String("done".to_string()))
+            }
+        })
+        .unwrap();
+
+        let progress = wait_for_completion(store, &id);
+        assert_eq!(progress, BatchProgress { status: BatchStatus::Completed, total: 2, completed: 1, failed: 1 });
+        assert!(store.output(&id).unwrap().contains("failed to generate"));
+    }
+
+    #[test]
+    fn progress_returns_none_for_an_unknown_id() {
+        let store = temp_store();
+        assert!(store.progress(&"batch-does-not-exist".to_string()).is_none());
+    }
+
+    #[test]
+    fn output_is_empty_before_anything_has_completed() {
+        let store = temp_store();
+        assert_eq!(store.output(&"batch-does-not-exist".to_string()).unwrap(), "");
+    }
+
+    #[test]
+    fn resumable_is_empty_once_a_batch_completes() {
+        let store = temp_store();
+        let requests = parse_requests(&Json::String("{\"prompt\":\"a\"}\n".to_string())).unwrap();
+        let id = submit(store, "echo", requests, |_| Ok(Json::String("done".to_string()))).unwrap();
+        wait_for_completion(store, &id);
+        assert!(store.resumable().is_empty());
+    }
+
+    #[test]
+    fn resumable_finds_a_batch_a_crash_left_running_and_resume_picks_up_the_rest() {
+        let store = temp_store();
+        let requests = parse_requests(&Json::String("{\"prompt\":\"a\"}\n{\"prompt\":\"b\"}\n{\"prompt\":\"c\"}\n".to_string())).unwrap();
+
+        // Simulate a crash: write the requests sidecar and a progress file
+        // covering just the first request, with no background thread
+        // actually running (what `submit` leaves on disk before its
+        // thread's first checkpoint, if the process died right there).
+        let id = new_batch_id();
+        fs::write(store.requests_path(&id), requests.iter().map(|r| format!("{}\n", r.to_string())).collect::<String>()).unwrap();
+        let mut output = OpenOptions::new().create(true).write(true).open(store.output_path(&id)).unwrap();
+        writeln!(output, "{}", ObjectBuilder::new().set("custom_id", Json::Null).set("response", Json::String("echo:a".to_string())).build().to_string()).unwrap();
+        drop(output);
+        store.save_progress(&id, "echo", &BatchProgress { status: BatchStatus::Running, total: 3, completed: 1, failed: 0 }).unwrap();
+
+        let resumable = store.resumable();
+        assert_eq!(resumable.len(), 1);
+        let (found_id, model_id, found_requests, resume_from) = &resumable[0];
+        assert_eq!(found_id, &id);
+        assert_eq!(model_id, "echo");
+        assert_eq!(found_requests.len(), 3);
+        assert_eq!(*resume_from, 1);
+
+        resume(store, id.clone(), model_id.clone(), found_requests.clone(), *resume_from, |req| {
+            let prompt = req.get("prompt").and_then(Json::as_str).unwrap_or_default();
+            Ok(Json::String(format!("echo:{prompt}")))
+        });
+
+        let progress = wait_for_completion(store, &id);
+        assert_eq!(progress, BatchProgress { status: BatchStatus::Completed, total: 3, completed: 3, failed: 0 });
+        let output = store.output(&id).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.contains("echo:a"));
+        assert!(output.contains("echo:b"));
+        assert!(output.contains("echo:c"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Startup compute self-benchmark: estimates sustained FLOPS and how well
+//! they scale across the cores reported by `available_parallelism()`, so
+//! operators can sanity-check a box before loading a model onto it.
+
+use crate::runtime::Runtime;
+use std::time::Instant;
+
+/// One size/thread-count measurement from the self-benchmark.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    pub size: usize,
+    pub threads: usize,
+    pub gflops: f64,
+}
+
+/// `A(i,j) = 1 / ((i+j)(i+j+1)/2 + i + 1)`, the implicit matrix used by the
+/// classic "spectral norm" power-iteration benchmark.
+fn a(i: usize, j: usize) -> f64 {
+    let ij = i + j;
+    1.0 / ((ij * (ij + 1) / 2 + i + 1) as f64)
+}
+
+/// Runs `row(i)` for each output row in parallel across `threads` of `rt`'s
+/// workers, via [`Runtime::parallel_for_chunks_mut`] so that, when pinning
+/// is enabled, the actual matrix-kernel worker threads are the ones pinned
+/// to cores — not just the outer thread driving the benchmark. Returns the
+/// number of worker threads actually used, which can be less than `threads`
+/// when `result` is shorter than the requested thread count.
+fn parallel_rows<F>(rt: &Runtime, result: &mut [f64], threads: usize, row: F) -> usize
+where
+    F: Fn(usize) -> f64 + Sync,
+{
+    rt.parallel_for_chunks_mut(result, threads, |chunk_start, chunk| {
+        for (offset, out) in chunk.iter_mut().enumerate() {
+            *out = row(chunk_start + offset);
+        }
+    })
+}
+
+/// `result = A · v`, parallelized over output rows, two columns at a time so
+/// the compiler can autovectorize the inner dot product. Returns the actual
+/// worker count, as per [`parallel_rows`].
+fn times_a(rt: &Runtime, v: &[f64], result: &mut [f64], threads: usize) -> usize {
+    parallel_rows(rt, result, threads, |i| {
+        let mut sum = 0.0;
+        let mut pairs = v.chunks_exact(2);
+        let mut j = 0;
+        for chunk in &mut pairs {
+            let pair: [f64; 2] = [chunk[0], chunk[1]];
+            sum += pair[0] * a(i, j) + pair[1] * a(i, j + 1);
+            j += 2;
+        }
+        for (k, &vj) in pairs.remainder().iter().enumerate() {
+            sum += vj * a(i, j + k);
+        }
+        sum
+    })
+}
+
+/// `result = Aᵀ · v`, parallelized the same way as [`times_a`].
+fn times_a_transp(rt: &Runtime, v: &[f64], result: &mut [f64], threads: usize) -> usize {
+    parallel_rows(rt, result, threads, |i| {
+        let mut sum = 0.0;
+        let mut pairs = v.chunks_exact(2);
+        let mut j = 0;
+        for chunk in &mut pairs {
+            let pair: [f64; 2] = [chunk[0], chunk[1]];
+            sum += pair[0] * a(j, i) + pair[1] * a(j + 1, i);
+            j += 2;
+        }
+        for (k, &vj) in pairs.remainder().iter().enumerate() {
+            sum += vj * a(j + k, i);
+        }
+        sum
+    })
+}
+
+/// Runs `out = Aᵀ·(A·v)` and returns the actual worker count used (`times_a`
+/// and `times_a_transp` operate on equal-length slices here, so their
+/// actual counts always agree).
+fn times_a_transp_times_a(rt: &Runtime, v: &[f64], out: &mut [f64], tmp: &mut [f64], threads: usize) -> usize {
+    times_a(rt, v, tmp, threads);
+    times_a_transp(rt, tmp, out, threads)
+}
+
+fn dot(x: &[f64], y: &[f64]) -> f64 {
+    x.iter().zip(y).map(|(a, b)| a * b).sum()
+}
+
+/// Runs ~10 rounds of power iteration on the spectral-norm matrix of the
+/// given `size` and returns the estimated spectral norm along with the
+/// actual worker count used, which can fall short of `threads` when
+/// `threads > size`.
+fn spectral_norm(rt: &Runtime, size: usize, threads: usize) -> (f64, usize) {
+    let mut u = vec![1.0; size];
+    let mut v = vec![0.0; size];
+    let mut tmp = vec![0.0; size];
+
+    let mut actual_threads = 0;
+    for _ in 0..10 {
+        let _ = times_a_transp_times_a(rt, &u, &mut v, &mut tmp, threads);
+        actual_threads = times_a_transp_times_a(rt, &v, &mut u, &mut tmp, threads);
+    }
+
+    ((dot(&u, &v) / dot(&v, &v)).sqrt(), actual_threads)
+}
+
+/// Flop count for one `times_a_transp_times_a` call: two matrix-vector
+/// products, each `2*size^2` flops (one multiply + one add per entry).
+fn flops_per_round(size: usize) -> f64 {
+    4.0 * (size * size) as f64
+}
+
+/// Runs the self-benchmark at increasing sizes for each thread count in
+/// `thread_counts`, returning one [`BenchResult`] per (size, threads) pair.
+/// `rt` drives the parallel matrix-vector products, so when it's built with
+/// pinning enabled the kernel's real worker threads get pinned to cores.
+/// `BenchResult.threads` reports the worker count actually used, not the
+/// requested count, since those can differ for `size < threads`.
+pub fn run_self_test(rt: &Runtime, thread_counts: &[usize]) -> Vec<BenchResult> {
+    const SIZES: [usize; 3] = [1000, 2000, 4000];
+    const ROUNDS: usize = 20; // 10 rounds each of u- and v-updates
+
+    let mut results = Vec::with_capacity(SIZES.len() * thread_counts.len());
+    for &threads in thread_counts {
+        for &size in &SIZES {
+            let start = Instant::now();
+            let (norm, actual_threads) = spectral_norm(rt, size, threads);
+            std::hint::black_box(norm);
+            let elapsed = start.elapsed();
+
+            let gflops = (ROUNDS as f64 * flops_per_round(size)) / elapsed.as_secs_f64() / 1e9;
+            results.push(BenchResult {
+                size,
+                threads: actual_threads,
+                gflops,
+            });
+        }
+    }
+    results
+}
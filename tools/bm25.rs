@@ -0,0 +1,150 @@
+//! Hand-rolled BM25 full-text index. `vectorstore.rs`'s HNSW graph finds
+//! semantically similar vectors but has no notion of exact terms, so an
+//! error code or an identifier that an embedding model tokenizes oddly can
+//! fall out of the top-k entirely; a keyword index alongside it, combined
+//! via reciprocal rank fusion (see `Collection::hybrid_query`), catches
+//! what pure vector similarity misses. There's no `tantivy` or other
+//! full-text search crate in this tree (the same "no external
+//! dependencies" constraint behind `json.rs` and `config.rs`'s hand-rolled
+//! parsers), so this is Robertson & Walker's BM25 scoring formula
+//! implemented directly over an in-memory inverted index.
+
+use crate::vectorstore::VectorId;
+use std::collections::HashMap;
+
+/// Term-frequency saturation: higher values let repeated terms keep adding
+/// to the score for longer before diminishing returns kick in. `1.2` is
+/// the value most BM25 references (and Lucene's default) use.
+const K1: f32 = 1.2;
+/// How strongly document length is normalized against the average — `0`
+/// disables length normalization entirely, `1` fully normalizes; `0.75` is
+/// the standard default.
+const B: f32 = 0.75;
+
+/// Lowercases and splits on runs of non-alphanumeric characters — good
+/// enough to match whole words and identifiers without pulling in a real
+/// tokenizer, the same scope `rag.rs`'s `word_count` keeps for its own
+/// whitespace-based token approximation.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()).map(|t| t.to_lowercase()).collect()
+}
+
+/// An inverted index over one collection's indexed text, scored with BM25.
+/// `insert`/`remove` are meant to be called alongside `Collection::upsert`/
+/// `delete` so the keyword index never drifts out of sync with the vector
+/// index it sits beside.
+#[derive(Default)]
+pub struct Bm25Index {
+    /// `postings[term][id]` is how many times `term` appears in `id`'s
+    /// indexed text.
+    postings: HashMap<String, HashMap<VectorId, u32>>,
+    doc_lengths: HashMap<VectorId, usize>,
+    total_length: usize,
+}
+
+impl Bm25Index {
+    /// Indexes (or re-indexes) `id`'s text. Calling this again for an `id`
+    /// already present first removes its old postings, so updating a
+    /// document's text doesn't leave stale term counts behind.
+    pub fn insert(&mut self, id: VectorId, text: &str) {
+        self.remove(id);
+        let terms = tokenize(text);
+        self.total_length += terms.len();
+        self.doc_lengths.insert(id, terms.len());
+        for term in terms {
+            *self.postings.entry(term).or_default().entry(id).or_insert(0) += 1;
+        }
+    }
+
+    /// Removes `id` from the index. A no-op if `id` was never indexed.
+    pub fn remove(&mut self, id: VectorId) {
+        let Some(length) = self.doc_lengths.remove(&id) else { return };
+        self.total_length -= length;
+        self.postings.retain(|_, docs| {
+            docs.remove(&id);
+            !docs.is_empty()
+        });
+    }
+
+    fn average_doc_length(&self) -> f32 {
+        if self.doc_lengths.is_empty() {
+            0.0
+        } else {
+            self.total_length as f32 / self.doc_lengths.len() as f32
+        }
+    }
+
+    /// Scores every document containing at least one of `query`'s terms,
+    /// returning `(id, score)` pairs sorted by descending BM25 score.
+    /// Documents matching none of the query's terms aren't included at all
+    /// (a BM25 score of zero carries no ranking information).
+    pub fn search(&self, query: &str) -> Vec<(VectorId, f32)> {
+        let n = self.doc_lengths.len() as f32;
+        let avg_len = self.average_doc_length();
+        let mut scores: HashMap<VectorId, f32> = HashMap::new();
+
+        for term in tokenize(query) {
+            let Some(docs) = self.postings.get(&term) else { continue };
+            let doc_freq = docs.len() as f32;
+            // The "+1" keeps idf non-negative even when a term appears in
+            // more than half the collection, per the BM25+ convention.
+            let idf = ((n - doc_freq + 0.5) / (doc_freq + 0.5) + 1.0).ln();
+            for (&id, &freq) in docs {
+                let freq = freq as f32;
+                let length = self.doc_lengths.get(&id).copied().unwrap_or(0) as f32;
+                let denom = freq + K1 * (1.0 - B + B * length / avg_len.max(1.0));
+                *scores.entry(id).or_insert(0.0) += idf * (freq * (K1 + 1.0)) / denom;
+            }
+        }
+
+        let mut scores: Vec<(VectorId, f32)> = scores.into_iter().collect();
+        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_ranks_the_document_mentioning_the_term_more_often_first() {
+        let mut index = Bm25Index::default();
+        index.insert(1, "the quick brown fox");
+        index.insert(2, "fox fox fox jumps over the fox");
+        let results = index.search("fox");
+        assert_eq!(results[0].0, 2);
+        assert_eq!(results[1].0, 1);
+    }
+
+    #[test]
+    fn search_finds_nothing_for_an_unindexed_term() {
+        let mut index = Bm25Index::default();
+        index.insert(1, "the quick brown fox");
+        assert!(index.search("giraffe").is_empty());
+    }
+
+    #[test]
+    fn reinserting_a_document_replaces_its_old_postings() {
+        let mut index = Bm25Index::default();
+        index.insert(1, "apples apples apples");
+        index.insert(1, "oranges");
+        assert!(index.search("apples").is_empty());
+        assert_eq!(index.search("oranges")[0].0, 1);
+    }
+
+    #[test]
+    fn remove_drops_a_document_from_future_searches() {
+        let mut index = Bm25Index::default();
+        index.insert(1, "error code E1234");
+        index.remove(1);
+        assert!(index.search("E1234").is_empty());
+    }
+
+    #[test]
+    fn tokenize_is_case_insensitive_and_splits_on_punctuation() {
+        let mut index = Bm25Index::default();
+        index.insert(1, "Error-Code: E1234!");
+        assert_eq!(index.search("e1234")[0].0, 1);
+    }
+}
@@ -0,0 +1,118 @@
+//! Cooperative cancellation for in-flight chat completions. An HTTP client
+//! dropping its connection, a WebSocket close frame, or an explicit
+//! `/v1/cancel/{request_id}` call all need to stop an
+//! [`InferenceBackend::stream`](crate::InferenceBackend::stream) loop
+//! before it burns through the rest of `max_new_tokens` for a client that
+//! isn't listening anymore, and free the scheduler slot / KV blocks it was
+//! holding. `stream`'s `on_token` callback returns `bool` for exactly this
+//! reason: once it returns `false`, the backend stops emitting tokens, so
+//! all this module has to do is give the caller something to flip from
+//! another thread (or the same call stack, for a failed SSE write).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A cheap, clonable cancel flag: one side calls [`cancel`](Self::cancel),
+/// the other polls [`is_cancelled`](Self::is_cancelled) from inside its
+/// generation loop.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Tracks the cancellation token for every request currently streaming a
+/// completion, keyed by the same id returned in the response body (and
+/// exposed to `/v1/cancel/{request_id}`). Entries are removed once a
+/// request finishes so this doesn't grow unbounded across the server's
+/// lifetime.
+#[derive(Default)]
+pub struct CancellationRegistry {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl CancellationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a fresh token for `request_id`, returning it so the
+    /// caller can thread it into its own `on_token` check for the
+    /// duration of generation.
+    pub fn register(&self, request_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.tokens.lock().unwrap().insert(request_id.to_string(), token.clone());
+        token
+    }
+
+    /// Removes `request_id`'s token once its request is no longer
+    /// in-flight, whether it finished normally or was cancelled.
+    pub fn deregister(&self, request_id: &str) {
+        self.tokens.lock().unwrap().remove(request_id);
+    }
+
+    /// Cancels `request_id`'s in-flight generation. Returns `false` if no
+    /// request with that id is currently registered (already finished, or
+    /// never existed) so the caller can turn that into a 404.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(request_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_starts_uncancelled() {
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+
+    #[test]
+    fn cancel_is_visible_through_a_clone() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn registry_cancel_returns_false_for_an_unknown_request() {
+        let registry = CancellationRegistry::new();
+        assert!(!registry.cancel("missing"));
+    }
+
+    #[test]
+    fn registry_cancel_flips_the_registered_token() {
+        let registry = CancellationRegistry::new();
+        let token = registry.register("req-1");
+        assert!(registry.cancel("req-1"));
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn deregister_makes_a_later_cancel_return_false() {
+        let registry = CancellationRegistry::new();
+        registry.register("req-1");
+        registry.deregister("req-1");
+        assert!(!registry.cancel("req-1"));
+    }
+}
@@ -0,0 +1,336 @@
+//! `ai-server chat`: an interactive terminal client for a running
+//! server. A real TUI would reach for `ratatui`, but this tree has no
+//! dependency manager to pull it in (see `cli.rs`'s own doc comment for
+//! the same trade at the subcommand-parsing level), so this is a plain
+//! line-oriented terminal session instead: streamed tokens print as they
+//! arrive over the same chunked-SSE wire format `loadtest.rs` already
+//! parses to drive `/v1/chat/completions`, and a small set of
+//! `/`-prefixed commands cover what a TUI's widgets and menus would
+//! otherwise be for. It doubles as a living integration test of the
+//! streaming API — every token a user reads here went through the exact
+//! parser below, not a mocked one.
+//!
+//! Multi-line input: everything typed accumulates into the pending
+//! message until a lone `/send` line submits it, so pasting or composing
+//! a multi-paragraph prompt doesn't require escaping newlines. A `/`
+//! command is only recognized at the start of a fresh message (an empty
+//! pending buffer) — once you've started typing a message, a line
+//! beginning with `/` is just part of it.
+
+use crate::json::{Json, ObjectBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+#[derive(Debug)]
+pub enum ChatClientError {
+    Io(String),
+    Http(String),
+}
+
+impl std::fmt::Display for ChatClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatClientError::Io(e) => write!(f, "io error: {e}"),
+            ChatClientError::Http(e) => write!(f, "http error: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+/// One chat session's client-side state: which server and model it's
+/// talking to, the sampling params in effect, and the transcript so far.
+/// Everything here round-trips through `/save` and `/load` as plain JSON,
+/// the same `{role, content}` shape `sessions.rs` persists server-side.
+pub struct ChatSession {
+    host_port: String,
+    model: String,
+    temperature: f64,
+    messages: Vec<Message>,
+}
+
+impl ChatSession {
+    pub fn new(host_port: String) -> ChatSession {
+        ChatSession { host_port, model: "default".to_string(), temperature: 1.0, messages: Vec::new() }
+    }
+
+    /// Drives the REPL against stdin/stdout until `/quit` or EOF.
+    pub fn run(&mut self) -> Result<(), ChatClientError> {
+        println!("ai-server chat — connected to {}. Type /help for commands.", self.host_port);
+        let stdin = io::stdin();
+        let mut lines = BufReader::new(stdin.lock()).lines();
+        let mut pending = String::new();
+
+        loop {
+            print!("{}", if pending.is_empty() { "> " } else { ". " });
+            io::stdout().flush().map_err(|e| ChatClientError::Io(e.to_string()))?;
+
+            let Some(line) = lines.next() else { return Ok(()) };
+            let line = line.map_err(|e| ChatClientError::Io(e.to_string()))?;
+
+            if pending.is_empty() && line.starts_with('/') {
+                if line == "/send" {
+                    println!("nothing to send");
+                    continue;
+                }
+                if !self.handle_command(&line)? {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            if line == "/send" {
+                let content = pending.trim_end_matches('\n').to_string();
+                pending.clear();
+                if content.is_empty() {
+                    println!("nothing to send");
+                    continue;
+                }
+                self.send(content)?;
+                continue;
+            }
+
+            pending.push_str(&line);
+            pending.push('\n');
+        }
+    }
+
+    /// Returns `Ok(false)` for `/quit`, `Ok(true)` otherwise.
+    fn handle_command(&mut self, line: &str) -> Result<bool, ChatClientError> {
+        let mut parts = line.split_whitespace();
+        match parts.next().unwrap_or("") {
+            "/quit" | "/exit" => return Ok(false),
+            "/help" => println!(
+                "commands:\n  \
+                 /model <id>        switch model for subsequent turns\n  \
+                 /temperature <n>   switch sampling temperature\n  \
+                 /save <path>       save the conversation to a JSON file\n  \
+                 /load <path>       replace the conversation with one loaded from a JSON file\n  \
+                 /send              submit the message typed so far (supports multi-line input)\n  \
+                 /quit              exit"
+            ),
+            "/model" => match parts.next() {
+                Some(id) => {
+                    self.model = id.to_string();
+                    println!("model set to {id}");
+                }
+                None => println!("usage: /model <id>"),
+            },
+            "/temperature" => match parts.next().and_then(|v| v.parse::<f64>().ok()) {
+                Some(temperature) => {
+                    self.temperature = temperature;
+                    println!("temperature set to {temperature}");
+                }
+                None => println!("usage: /temperature <number>"),
+            },
+            "/save" => match parts.next() {
+                Some(path) => match self.save(path) {
+                    Ok(()) => println!("saved to {path}"),
+                    Err(e) => println!("error saving: {e}"),
+                },
+                None => println!("usage: /save <path>"),
+            },
+            "/load" => match parts.next() {
+                Some(path) => match self.load(path) {
+                    Ok(()) => println!("loaded {path}"),
+                    Err(e) => println!("error loading: {e}"),
+                },
+                None => println!("usage: /load <path>"),
+            },
+            other => println!("unknown command {other:?}, type /help"),
+        }
+        Ok(true)
+    }
+
+    fn save(&self, path: &str) -> Result<(), ChatClientError> {
+        let messages: Vec<Json> = self
+            .messages
+            .iter()
+            .map(|m| ObjectBuilder::new().set("role", Json::String(m.role.clone())).set("content", Json::String(m.content.clone())).build())
+            .collect();
+        let body = ObjectBuilder::new()
+            .set("model", Json::String(self.model.clone()))
+            .set("temperature", Json::Number(self.temperature))
+            .set("messages", Json::Array(messages))
+            .build();
+        std::fs::write(path, body.to_string()).map_err(|e| ChatClientError::Io(e.to_string()))
+    }
+
+    fn load(&mut self, path: &str) -> Result<(), ChatClientError> {
+        let text = std::fs::read_to_string(path).map_err(|e| ChatClientError::Io(e.to_string()))?;
+        let parsed = Json::parse(&text).map_err(|e| ChatClientError::Http(e.to_string()))?;
+        if let Some(model) = parsed.get("model").and_then(Json::as_str) {
+            self.model = model.to_string();
+        }
+        if let Some(temperature) = parsed.get("temperature").and_then(Json::as_f64) {
+            self.temperature = temperature;
+        }
+        self.messages = parsed
+            .get("messages")
+            .and_then(Json::as_array)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|m| {
+                        let role = m.get("role").and_then(Json::as_str)?;
+                        let content = m.get("content").and_then(Json::as_str)?;
+                        Some(Message { role: role.to_string(), content: content.to_string() })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    /// Appends `content` as a user turn, streams the assistant's reply
+    /// from `/v1/chat/completions`, printing each token as it arrives,
+    /// then appends the assembled reply as an assistant turn.
+    fn send(&mut self, content: String) -> Result<(), ChatClientError> {
+        self.messages.push(Message { role: "user".to_string(), content });
+
+        let messages: Vec<Json> = self
+            .messages
+            .iter()
+            .map(|m| ObjectBuilder::new().set("role", Json::String(m.role.clone())).set("content", Json::String(m.content.clone())).build())
+            .collect();
+        let body = ObjectBuilder::new()
+            .set("model", Json::String(self.model.clone()))
+            .set("stream", Json::Bool(true))
+            .set("temperature", Json::Number(self.temperature))
+            .set("messages", Json::Array(messages))
+            .build()
+            .to_string();
+
+        let mut stream = TcpStream::connect(&self.host_port).map_err(|e| ChatClientError::Io(e.to_string()))?;
+        write!(
+            stream,
+            "POST /v1/chat/completions HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.host_port,
+            body.len()
+        )
+        .map_err(|e| ChatClientError::Io(e.to_string()))?;
+
+        let mut reader = BufReader::new(stream);
+        skip_headers(&mut reader)?;
+
+        print!("assistant: ");
+        io::stdout().flush().map_err(|e| ChatClientError::Io(e.to_string()))?;
+        let mut reply = String::new();
+        let mut buffer = Vec::new();
+        read_chunked_body(&mut reader, |chunk| {
+            buffer.extend_from_slice(chunk);
+            while let Some(pos) = find(&buffer, b"\n\n") {
+                let event = buffer[..pos].to_vec();
+                buffer.drain(..pos + 2);
+                let Some(data) = event.strip_prefix(b"data: ") else { continue };
+                if data == b"[DONE]" {
+                    continue;
+                }
+                if let Ok(token) = std::str::from_utf8(data) {
+                    print!("{token}");
+                    let _ = io::stdout().flush();
+                    reply.push_str(token);
+                }
+            }
+        })?;
+        println!();
+
+        self.messages.push(Message { role: "assistant".to_string(), content: reply });
+        Ok(())
+    }
+}
+
+fn skip_headers(reader: &mut BufReader<TcpStream>) -> Result<(), ChatClientError> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(|e| ChatClientError::Io(e.to_string()))?;
+    if !status_line.contains("200") {
+        return Err(ChatClientError::Http(format!("unexpected status line: {}", status_line.trim())));
+    }
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| ChatClientError::Io(e.to_string()))?;
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads an HTTP/1.1 chunked-transfer-encoded body, calling `on_chunk`
+/// with each chunk's raw bytes until the zero-length terminating chunk —
+/// the same shape `loadtest.rs`'s `read_chunked_body` uses for the same
+/// wire format.
+fn read_chunked_body(reader: &mut BufReader<TcpStream>, mut on_chunk: impl FnMut(&[u8])) -> Result<(), ChatClientError> {
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line).map_err(|e| ChatClientError::Io(e.to_string()))?;
+        let size = usize::from_str_radix(size_line.trim(), 16)
+            .map_err(|_| ChatClientError::Http(format!("bad chunk size: {:?}", size_line.trim())))?;
+        if size == 0 {
+            return Ok(());
+        }
+        let mut chunk = vec![0u8; size];
+        reader.read_exact(&mut chunk).map_err(|e| ChatClientError::Io(e.to_string()))?;
+        on_chunk(&chunk);
+
+        let mut trailer = [0u8; 2];
+        reader.read_exact(&mut trailer).map_err(|e| ChatClientError::Io(e.to_string()))?;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_locates_a_subslice() {
+        assert_eq!(find(b"hello\n\nworld", b"\n\n"), Some(5));
+        assert_eq!(find(b"no separator here", b"\n\n"), None);
+    }
+
+    #[test]
+    fn save_then_load_round_trips_model_temperature_and_messages() {
+        let path = std::env::temp_dir().join(format!("ai-server-chat-client-test-{}.json", std::process::id()));
+        let mut session = ChatSession::new("127.0.0.1:0".to_string());
+        session.model = "my-model".to_string();
+        session.temperature = 0.4;
+        session.messages = vec![Message { role: "user".to_string(), content: "hi".to_string() }];
+        session.save(path.to_str().unwrap()).unwrap();
+
+        let mut reloaded = ChatSession::new("127.0.0.1:0".to_string());
+        reloaded.load(path.to_str().unwrap()).unwrap();
+        assert_eq!(reloaded.model, "my-model");
+        assert_eq!(reloaded.temperature, 0.4);
+        assert_eq!(reloaded.messages, session.messages);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_reports_an_error_for_a_missing_file() {
+        let mut session = ChatSession::new("127.0.0.1:0".to_string());
+        assert!(session.load("/nonexistent/path/does-not-exist.json").is_err());
+    }
+
+    #[test]
+    fn handle_command_model_and_temperature_update_session_state() {
+        let mut session = ChatSession::new("127.0.0.1:0".to_string());
+        assert!(session.handle_command("/model llama-3").unwrap());
+        assert_eq!(session.model, "llama-3");
+        assert!(session.handle_command("/temperature 0.7").unwrap());
+        assert_eq!(session.temperature, 0.7);
+    }
+
+    #[test]
+    fn handle_command_quit_returns_false() {
+        let mut session = ChatSession::new("127.0.0.1:0".to_string());
+        assert!(!session.handle_command("/quit").unwrap());
+    }
+}
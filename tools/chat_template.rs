@@ -0,0 +1,704 @@
+//! Per-model chat prompt templates. Every model family lays out its chat
+//! turns differently — a Llama-3 prompt looks nothing like a Mistral one —
+//! and GGUF files embed the answer as a Jinja2 template in the
+//! `tokenizer.chat_template` metadata key (see `gguf.rs`'s `GgufModel`).
+//! `constraints.rs` already established this tree's approach to "compile a
+//! small language from scratch instead of pulling in a crate": this module
+//! does the same for templates, covering the subset of Jinja these chat
+//! templates actually use — `{{ }}` output, dotted/bracket variable
+//! access, `~` string concatenation, `{% for %}` over the message list
+//! (with `loop.first`/`loop.last`), `{% if/elif/else %}` with
+//! `==`/`!=`/`in`/`and`/`or`/`not`, and `{%-`/`-%}` whitespace control. It
+//! does not implement macros, filters, or arbitrary Python expressions —
+//! real upstream templates that lean on those need trimming down to this
+//! subset, same as a GBNF grammar that uses an unsupported repetition
+//! operator needs rewriting.
+//!
+//! A registry override lets an operator supply a replacement template for
+//! a model whose embedded one (or lack of one) doesn't render correctly
+//! here, without needing a new GGUF file.
+
+use crate::json::Json;
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+    ParseError(String),
+    UnsupportedConstruct(String),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::ParseError(msg) => write!(f, "template parse error: {msg}"),
+            TemplateError::UnsupportedConstruct(what) => write!(f, "unsupported template construct: {what}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Name(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Str(String),
+    Var(Vec<Segment>),
+    Concat(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
+    In(Box<Expr>, Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Output(Expr),
+    For { var: String, iterable: Expr, body: Vec<Node> },
+    If { arms: Vec<(Expr, Vec<Node>)>, otherwise: Vec<Node> },
+}
+
+/// A compiled chat template, ready to render against a message list.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let tokens = lex(source);
+        let mut parser = Parser { tokens, pos: 0 };
+        let (nodes, terminator) = parser.parse_nodes(&[])?;
+        if let Some(tag) = terminator {
+            return Err(TemplateError::ParseError(format!("unexpected {tag:?}")));
+        }
+        Ok(Template { nodes })
+    }
+
+    /// Renders this template against `context`, an object typically holding
+    /// `messages` (an array of `{"role", "content"}` objects),
+    /// `add_generation_prompt`, and any model-specific tokens the template
+    /// references (`bos_token`, `eos_token`, ...).
+    pub fn render(&self, context: &Json) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &[], context, &mut out)?;
+        Ok(out)
+    }
+}
+
+// ---- Lexer: splits source into text runs and `{{ }}` / `{% %}` tags ----
+
+enum RawToken {
+    Text(String),
+    Expr(String),
+    Tag(String),
+}
+
+fn lex(source: &str) -> Vec<RawToken> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    loop {
+        let expr_pos = rest.find("{{");
+        let tag_pos = rest.find("{%");
+        let start = match (expr_pos, tag_pos) {
+            (Some(e), Some(t)) => e.min(t),
+            (Some(e), None) => e,
+            (None, Some(t)) => t,
+            (None, None) => {
+                if !rest.is_empty() {
+                    tokens.push(RawToken::Text(rest.to_string()));
+                }
+                break;
+            }
+        };
+        let is_expr = expr_pos == Some(start);
+        let close = if is_expr { "}}" } else { "%}" };
+        let Some(close_rel) = rest[start + 2..].find(close) else {
+            tokens.push(RawToken::Text(rest.to_string()));
+            break;
+        };
+        let inner_start = start + 2;
+        let inner_end = start + 2 + close_rel;
+        let tag_end = inner_end + close.len();
+
+        let mut inner = &rest[inner_start..inner_end];
+        let trim_left = inner.starts_with('-');
+        if trim_left {
+            inner = &inner[1..];
+        }
+        let trim_right = inner.ends_with('-');
+        if trim_right {
+            inner = &inner[..inner.len() - 1];
+        }
+
+        let mut text = rest[..start].to_string();
+        if trim_left {
+            text = text.trim_end().to_string();
+        }
+        if !text.is_empty() {
+            tokens.push(RawToken::Text(text));
+        }
+        if is_expr {
+            tokens.push(RawToken::Expr(inner.trim().to_string()));
+        } else {
+            tokens.push(RawToken::Tag(inner.trim().to_string()));
+        }
+
+        rest = &rest[tag_end..];
+        if trim_right {
+            rest = rest.trim_start();
+        }
+    }
+    tokens
+}
+
+// ---- Parser: builds the Node tree, tracking {% for/if %} nesting ----
+
+struct Parser {
+    tokens: Vec<RawToken>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Parses nodes until end-of-input or a tag whose leading keyword is in
+    /// `stop_words`, returning that tag's full source (still unconsumed at
+    /// `self.pos`) so the caller can branch on which one it was.
+    fn parse_nodes(&mut self, stop_words: &[&str]) -> Result<(Vec<Node>, Option<String>), TemplateError> {
+        let mut nodes = Vec::new();
+        while self.pos < self.tokens.len() {
+            match &self.tokens[self.pos] {
+                RawToken::Text(t) => {
+                    nodes.push(Node::Text(t.clone()));
+                    self.pos += 1;
+                }
+                RawToken::Expr(e) => {
+                    let expr = parse_expr(e)?;
+                    nodes.push(Node::Output(expr));
+                    self.pos += 1;
+                }
+                RawToken::Tag(tag) => {
+                    let tag = tag.clone();
+                    let word = tag.split_whitespace().next().unwrap_or("").to_string();
+                    if stop_words.contains(&word.as_str()) {
+                        return Ok((nodes, Some(tag)));
+                    }
+                    match word.as_str() {
+                        "for" => nodes.push(self.parse_for(&tag)?),
+                        "if" => nodes.push(self.parse_if(&tag)?),
+                        _ => return Err(TemplateError::UnsupportedConstruct(word)),
+                    }
+                }
+            }
+        }
+        Ok((nodes, None))
+    }
+
+    fn parse_for(&mut self, tag: &str) -> Result<Node, TemplateError> {
+        let rest = tag.strip_prefix("for").unwrap().trim();
+        let (var, iter_src) = rest
+            .split_once(" in ")
+            .ok_or_else(|| TemplateError::ParseError(format!("malformed for tag: {tag:?}")))?;
+        let iterable = parse_expr(iter_src.trim())?;
+        self.pos += 1;
+        let (body, terminator) = self.parse_nodes(&["endfor"])?;
+        if terminator.is_none() {
+            return Err(TemplateError::ParseError("unterminated {% for %}".to_string()));
+        }
+        self.pos += 1;
+        Ok(Node::For { var: var.trim().to_string(), iterable, body })
+    }
+
+    fn parse_if(&mut self, tag: &str) -> Result<Node, TemplateError> {
+        let cond_src = tag.strip_prefix("if").unwrap().trim();
+        let mut arms = vec![(parse_expr(cond_src)?, Vec::new())];
+        self.pos += 1;
+        loop {
+            let (body, terminator) = self.parse_nodes(&["elif", "else", "endif"])?;
+            arms.last_mut().unwrap().1 = body;
+            let Some(tag) = terminator else {
+                return Err(TemplateError::ParseError("unterminated {% if %}".to_string()));
+            };
+            let word = tag.split_whitespace().next().unwrap_or("");
+            match word {
+                "elif" => {
+                    let cond_src = tag.strip_prefix("elif").unwrap().trim();
+                    arms.push((parse_expr(cond_src)?, Vec::new()));
+                    self.pos += 1;
+                }
+                "else" => {
+                    self.pos += 1;
+                    let (otherwise, terminator) = self.parse_nodes(&["endif"])?;
+                    if terminator.is_none() {
+                        return Err(TemplateError::ParseError("unterminated {% if %}".to_string()));
+                    }
+                    self.pos += 1;
+                    return Ok(Node::If { arms, otherwise });
+                }
+                "endif" => {
+                    self.pos += 1;
+                    return Ok(Node::If { arms, otherwise: Vec::new() });
+                }
+                _ => unreachable!("parse_nodes only stops on elif/else/endif"),
+            }
+        }
+    }
+}
+
+// ---- Expression parser: hand-rolled recursive descent over a token list ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Str(String),
+    Ident(String),
+    Dot,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Comma,
+    Tilde,
+    Eq,
+    Ne,
+}
+
+fn lex_expr(source: &str) -> Result<Vec<ExprToken>, TemplateError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let quote = c;
+            let mut s = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != quote {
+                s.push(chars[i]);
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(TemplateError::ParseError(format!("unterminated string in {source:?}")));
+            }
+            i += 1;
+            tokens.push(ExprToken::Str(s));
+        } else if c == '.' {
+            tokens.push(ExprToken::Dot);
+            i += 1;
+        } else if c == '[' {
+            tokens.push(ExprToken::LBracket);
+            i += 1;
+        } else if c == ']' {
+            tokens.push(ExprToken::RBracket);
+            i += 1;
+        } else if c == '(' {
+            tokens.push(ExprToken::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(ExprToken::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(ExprToken::Comma);
+            i += 1;
+        } else if c == '~' {
+            tokens.push(ExprToken::Tilde);
+            i += 1;
+        } else if c == '=' && chars.get(i + 1) == Some(&'=') {
+            tokens.push(ExprToken::Eq);
+            i += 2;
+        } else if c == '!' && chars.get(i + 1) == Some(&'=') {
+            tokens.push(ExprToken::Ne);
+            i += 2;
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut ident = String::new();
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                ident.push(chars[i]);
+                i += 1;
+            }
+            tokens.push(ExprToken::Ident(ident));
+        } else {
+            return Err(TemplateError::ParseError(format!("unexpected character {c:?} in {source:?}")));
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [ExprToken],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_ident(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(ExprToken::Ident(w)) if w == word)
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        if self.peek_ident(word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.parse_and()?;
+        while self.eat_ident("or") {
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.parse_not()?;
+        while self.eat_ident("and") {
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, TemplateError> {
+        if self.eat_ident("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, TemplateError> {
+        let left = self.parse_concat()?;
+        if matches!(self.peek(), Some(ExprToken::Eq)) {
+            self.pos += 1;
+            let right = self.parse_concat()?;
+            return Ok(Expr::Eq(Box::new(left), Box::new(right)));
+        }
+        if matches!(self.peek(), Some(ExprToken::Ne)) {
+            self.pos += 1;
+            let right = self.parse_concat()?;
+            return Ok(Expr::Ne(Box::new(left), Box::new(right)));
+        }
+        if self.eat_ident("in") {
+            let list = self.parse_list_literal()?;
+            return Ok(Expr::In(Box::new(left), list));
+        }
+        Ok(left)
+    }
+
+    fn parse_concat(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.parse_primary()?;
+        while matches!(self.peek(), Some(ExprToken::Tilde)) {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            left = Expr::Concat(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_list_literal(&mut self) -> Result<Vec<Expr>, TemplateError> {
+        if !matches!(self.peek(), Some(ExprToken::LBracket)) {
+            return Err(TemplateError::ParseError("expected a list literal after \"in\"".to_string()));
+        }
+        self.pos += 1;
+        let mut items = Vec::new();
+        if !matches!(self.peek(), Some(ExprToken::RBracket)) {
+            loop {
+                items.push(self.parse_primary()?);
+                if matches!(self.peek(), Some(ExprToken::Comma)) {
+                    self.pos += 1;
+                    continue;
+                }
+                break;
+            }
+        }
+        if !matches!(self.peek(), Some(ExprToken::RBracket)) {
+            return Err(TemplateError::ParseError("unterminated list literal".to_string()));
+        }
+        self.pos += 1;
+        Ok(items)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, TemplateError> {
+        match self.peek().cloned() {
+            Some(ExprToken::Str(s)) => {
+                self.pos += 1;
+                Ok(Expr::Str(s))
+            }
+            Some(ExprToken::LParen) => {
+                self.pos += 1;
+                let inner = self.parse_or()?;
+                if !matches!(self.peek(), Some(ExprToken::RParen)) {
+                    return Err(TemplateError::ParseError("unterminated ( ... )".to_string()));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(ExprToken::Ident(name)) => {
+                self.pos += 1;
+                let mut segments = vec![Segment::Name(name)];
+                loop {
+                    match self.peek() {
+                        Some(ExprToken::Dot) => {
+                            self.pos += 1;
+                            let Some(ExprToken::Ident(field)) = self.peek().cloned() else {
+                                return Err(TemplateError::ParseError("expected a field name after \".\"".to_string()));
+                            };
+                            self.pos += 1;
+                            segments.push(Segment::Name(field));
+                        }
+                        Some(ExprToken::LBracket) => {
+                            self.pos += 1;
+                            match self.peek().cloned() {
+                                Some(ExprToken::Str(key)) => {
+                                    self.pos += 1;
+                                    segments.push(Segment::Name(key));
+                                }
+                                Some(ExprToken::Ident(digits)) if digits.chars().all(|c| c.is_ascii_digit()) => {
+                                    self.pos += 1;
+                                    segments.push(Segment::Index(digits.parse().unwrap()));
+                                }
+                                _ => return Err(TemplateError::ParseError("expected a string or number in [ ... ]".to_string())),
+                            }
+                            if !matches!(self.peek(), Some(ExprToken::RBracket)) {
+                                return Err(TemplateError::ParseError("unterminated [ ... ]".to_string()));
+                            }
+                            self.pos += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                Ok(Expr::Var(segments))
+            }
+            other => Err(TemplateError::ParseError(format!("unexpected token {other:?}"))),
+        }
+    }
+}
+
+fn parse_expr(source: &str) -> Result<Expr, TemplateError> {
+    let tokens = lex_expr(source)?;
+    let mut parser = ExprParser { tokens: &tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(TemplateError::ParseError(format!("trailing tokens in expression {source:?}")));
+    }
+    Ok(expr)
+}
+
+// ---- Evaluation and rendering ----
+
+fn lookup(scopes: &[(String, Json)], context: &Json, name: &str) -> Json {
+    scopes
+        .iter()
+        .rev()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.clone())
+        .or_else(|| context.get(name).cloned())
+        .unwrap_or(Json::Null)
+}
+
+fn index_into(value: Json, segment: &Segment) -> Json {
+    match (value, segment) {
+        (Json::Object(map), Segment::Name(key)) => map.get(key).cloned().unwrap_or(Json::Null),
+        (Json::Array(items), Segment::Index(i)) => items.get(*i).cloned().unwrap_or(Json::Null),
+        _ => Json::Null,
+    }
+}
+
+fn eval(expr: &Expr, scopes: &[(String, Json)], context: &Json) -> Json {
+    match expr {
+        Expr::Str(s) => Json::String(s.clone()),
+        Expr::Var(segments) => {
+            let Some((first, rest)) = segments.split_first() else { return Json::Null };
+            let Segment::Name(root_name) = first else { return Json::Null };
+            rest.iter().fold(lookup(scopes, context, root_name), index_into)
+        }
+        Expr::Concat(a, b) => Json::String(format!("{}{}", display(&eval(a, scopes, context)), display(&eval(b, scopes, context)))),
+        Expr::Eq(a, b) => Json::Bool(eval(a, scopes, context) == eval(b, scopes, context)),
+        Expr::Ne(a, b) => Json::Bool(eval(a, scopes, context) != eval(b, scopes, context)),
+        Expr::In(target, list) => {
+            let target = eval(target, scopes, context);
+            Json::Bool(list.iter().any(|item| eval(item, scopes, context) == target))
+        }
+        Expr::Not(inner) => Json::Bool(!truthy(&eval(inner, scopes, context))),
+        Expr::And(a, b) => Json::Bool(truthy(&eval(a, scopes, context)) && truthy(&eval(b, scopes, context))),
+        Expr::Or(a, b) => Json::Bool(truthy(&eval(a, scopes, context)) || truthy(&eval(b, scopes, context))),
+    }
+}
+
+fn truthy(value: &Json) -> bool {
+    match value {
+        Json::Null => false,
+        Json::Bool(b) => *b,
+        Json::Number(n) => *n != 0.0,
+        Json::String(s) => !s.is_empty(),
+        Json::Array(a) => !a.is_empty(),
+        Json::Object(m) => !m.is_empty(),
+    }
+}
+
+fn display(value: &Json) -> String {
+    match value {
+        Json::String(s) => s.clone(),
+        Json::Null => String::new(),
+        Json::Bool(b) => b.to_string(),
+        Json::Number(n) => n.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[(String, Json)], context: &Json, out: &mut String) -> Result<(), TemplateError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Output(expr) => out.push_str(&display(&eval(expr, scopes, context))),
+            Node::For { var, iterable, body } => {
+                let Json::Array(items) = eval(iterable, scopes, context) else {
+                    continue;
+                };
+                let count = items.len();
+                for (index, item) in items.into_iter().enumerate() {
+                    let mut inner_scopes = scopes.to_vec();
+                    inner_scopes.push((var.clone(), item));
+                    let loop_info = Json::Object(
+                        [
+                            ("first".to_string(), Json::Bool(index == 0)),
+                            ("last".to_string(), Json::Bool(index + 1 == count)),
+                            ("index0".to_string(), Json::Number(index as f64)),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    );
+                    inner_scopes.push(("loop".to_string(), loop_info));
+                    render_nodes(body, &inner_scopes, context, out)?;
+                }
+            }
+            Node::If { arms, otherwise } => {
+                let mut matched = false;
+                for (cond, body) in arms {
+                    if truthy(&eval(cond, scopes, context)) {
+                        render_nodes(body, scopes, context, out)?;
+                        matched = true;
+                        break;
+                    }
+                }
+                if !matched {
+                    render_nodes(otherwise, scopes, context, out)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Chooses the template source to compile for a model: the registry's
+/// per-model override if one is set, else the GGUF-embedded
+/// `tokenizer.chat_template` metadata string, else `None` (the caller
+/// falls back to a generic prompt, same as `EchoBackend` does today with
+/// no template at all).
+pub fn resolve_template_source<'a>(gguf_chat_template: Option<&'a str>, registry_override: Option<&'a str>) -> Option<&'a str> {
+    registry_override.or(gguf_chat_template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn messages(pairs: &[(&str, &str)]) -> Json {
+        Json::Array(
+            pairs
+                .iter()
+                .map(|(role, content)| {
+                    let mut m = BTreeMap::new();
+                    m.insert("role".to_string(), Json::String(role.to_string()));
+                    m.insert("content".to_string(), Json::String(content.to_string()));
+                    Json::Object(m)
+                })
+                .collect(),
+        )
+    }
+
+    fn context(pairs: &[(&str, &str)], add_generation_prompt: bool) -> Json {
+        let mut m = BTreeMap::new();
+        m.insert("messages".to_string(), messages(pairs));
+        m.insert("add_generation_prompt".to_string(), Json::Bool(add_generation_prompt));
+        m.insert("bos_token".to_string(), Json::String("<s>".to_string()));
+        m.insert("eos_token".to_string(), Json::String("</s>".to_string()));
+        Json::Object(m)
+    }
+
+    // A representative (not byte-for-byte upstream) Llama-3-style template:
+    // per-message headers, then an assistant header when generation should
+    // continue.
+    const LLAMA3: &str = "{{ bos_token }}{% for message in messages %}<|start_header_id|>{{ message['role'] }}<|end_header_id|>\n\n{{ message['content'] }}<|eot_id|>{% endfor %}{% if add_generation_prompt %}<|start_header_id|>assistant<|end_header_id|>\n\n{% endif %}";
+
+    #[test]
+    fn renders_a_llama3_style_template() {
+        let template = Template::parse(LLAMA3).unwrap();
+        let rendered = template.render(&context(&[("user", "hi")], true)).unwrap();
+        assert_eq!(
+            rendered,
+            "<s><|start_header_id|>user<|end_header_id|>\n\nhi<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+    }
+
+    // A representative Mistral-style template: `[INST]`/`[/INST]` wrapping
+    // user turns, assistant turns followed by the EOS token, system
+    // messages folded into the first `[INST]` block.
+    const MISTRAL: &str = "{{ bos_token }}{% for message in messages %}{% if message['role'] == 'user' %}[INST] {{ message['content'] }} [/INST]{% elif message['role'] == 'assistant' %}{{ message['content'] }}{{ eos_token }}{% endif %}{% endfor %}";
+
+    #[test]
+    fn renders_a_mistral_style_template() {
+        let template = Template::parse(MISTRAL).unwrap();
+        let rendered = template.render(&context(&[("user", "hi"), ("assistant", "hello")], false)).unwrap();
+        assert_eq!(rendered, "<s>[INST] hi [/INST]hello</s>");
+    }
+
+    // A representative Qwen-style ChatML template: `<|im_start|>role\n...
+    // <|im_end|>` per turn.
+    const QWEN: &str = "{% for message in messages %}<|im_start|>{{ message['role'] }}\n{{ message['content'] }}<|im_end|>\n{% endfor %}{% if add_generation_prompt %}<|im_start|>assistant\n{% endif %}";
+
+    #[test]
+    fn renders_a_qwen_style_chatml_template() {
+        let template = Template::parse(QWEN).unwrap();
+        let rendered = template.render(&context(&[("system", "be terse"), ("user", "hi")], true)).unwrap();
+        assert_eq!(
+            rendered,
+            "<|im_start|>system\nbe terse<|im_end|>\n<|im_start|>user\nhi<|im_end|>\n<|im_start|>assistant\n"
+        );
+    }
+
+    // A representative Gemma-style template: `<start_of_turn>` blocks that
+    // relabel "assistant" as "model", using `loop.last` to add the
+    // generation prompt only after the final turn.
+    const GEMMA: &str =
+        "{% for message in messages %}<start_of_turn>{% if message['role'] == 'assistant' %}model{% else %}{{ message['role'] }}{% endif %}\n{{ message['content'] }}<end_of_turn>\n{% if loop.last and add_generation_prompt %}<start_of_turn>model\n{% endif %}{% endfor %}";
+
+    #[test]
+    fn renders_a_gemma_style_template_with_loop_last() {
+        let template = Template::parse(GEMMA).unwrap();
+        let rendered = template.render(&context(&[("user", "hi")], true)).unwrap();
+        assert_eq!(rendered, "<start_of_turn>user\nhi<end_of_turn>\n<start_of_turn>model\n");
+    }
+
+    #[test]
+    fn parse_rejects_an_unterminated_for_loop() {
+        assert!(Template::parse("{% for m in messages %}{{ m }}").is_err());
+    }
+
+    #[test]
+    fn resolve_template_source_prefers_the_registry_override() {
+        assert_eq!(resolve_template_source(Some("gguf"), Some("override")), Some("override"));
+        assert_eq!(resolve_template_source(Some("gguf"), None), Some("gguf"));
+        assert_eq!(resolve_template_source(None, None), None);
+    }
+}
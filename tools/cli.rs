@@ -0,0 +1,642 @@
+//! Single `ai-server` CLI entry point wrapping the tree's separate tools
+//! behind subcommands (`serve`, `models`, `bench`, `probe`, `tokenize`)
+//! instead of one ad hoc binary per tool. A real CLI would reach for
+//! `clap`, but this tree has no dependency manager to declare it against
+//! (see `json.rs`/`http.rs` for the same trade made for their protocols),
+//! so argument parsing here is a small hand-rolled subcommand dispatcher:
+//! the first positional argument selects the subcommand, and `--flag
+//! value`/`--flag` pairs are pulled out of the rest, the same shape
+//! `test_rust_arm64.rs`'s `parse_usize_flag`/`parse_str_flag` already use.
+//!
+//! Most subcommands are thin wrappers around an existing module: `models`
+//! drives `registry::ModelRegistry` and `downloader::download`, `bench`
+//! and `probe` reuse `bench.rs`/`diagnostics.rs`, and `tokenize` loads a
+//! GGUF file's vocab through `tokenizer.rs`. `serve` is the exception —
+//! `server.rs` is written (like every other file here) as its own crate
+//! root, so its `mod audio;`-style declarations resolve `crate::audio` to
+//! *its* root, not this one. Folding it in as a submodule would mean
+//! rewriting every `crate::`-rooted import in `server.rs` (and everything
+//! it depends on) to `super::`, which would break `server.rs`'s ability to
+//! keep compiling as its own standalone binary. Short of a Cargo
+//! workspace splitting the shared pieces into a library crate, `serve`
+//! instead execs the separately-built `server` binary as a subprocess,
+//! same as a shell wrapper script would.
+
+mod backend;
+mod bench;
+mod chat_client;
+mod cuda;
+mod diagnostics;
+mod discovery;
+mod downloader;
+mod durability;
+mod eval;
+mod gguf;
+mod gpu;
+mod hardware;
+mod json;
+mod loadtest;
+mod mmap_loader;
+mod model_loader;
+mod quantize;
+mod registry;
+mod runtime;
+mod safetensors;
+mod sampling;
+mod service;
+mod sha256;
+mod storage;
+mod thermal;
+mod threading;
+mod tokenizer;
+mod updater;
+mod vulkan;
+
+use json::{Json, ObjectBuilder};
+use model_loader::ModelLoader;
+use registry::ModelRegistry;
+use runtime::Runtime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tokenizer::BpeTokenizer;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(subcommand) = args.get(1) else {
+        eprintln!("{}", usage());
+        std::process::exit(2);
+    };
+
+    let result = match subcommand.as_str() {
+        "serve" => run_serve(),
+        "chat" => run_chat(&args[2..]),
+        "discover" => run_discover(&args[2..]),
+        "update" => run_update(&args[2..]),
+        "models" => run_models(&args[2..]),
+        "bench" => run_bench(&args[2..]),
+        "probe" => run_probe(&args[2..]),
+        "tokenize" => run_tokenize(&args[2..]),
+        "quantize" => run_quantize(&args[2..]),
+        "inspect" => run_inspect(&args[2..]),
+        "eval" => run_eval(&args[2..]),
+        "service" => run_service(&args[2..]),
+        "help" | "--help" | "-h" => {
+            println!("{}", usage());
+            return;
+        }
+        other => Err(format!("unknown subcommand {other:?}\n\n{}", usage())),
+    };
+
+    if let Err(message) = result {
+        eprintln!("error: {message}");
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> &'static str {
+    "usage: ai-server <subcommand> [args]\n\n\
+     subcommands:\n  \
+     serve                          start the HTTP/RPC server\n  \
+     chat [--target host:port]      interactive terminal chat client against a running server (default 127.0.0.1:8080)\n  \
+     discover [--timeout-ms N] [--report json]  list `--discovery.enabled` server instances broadcasting on the local LAN\n  \
+     update [--manifest-url URL] [--rollback]  check for and install a new release, or restore the previous binary\n  \
+     models list                    list models under the local registry\n  \
+     models pull <repo>/<file>      download a model into the registry\n  \
+     models verify <id> --sha256 HASH [--sig PATH --pubkey PATH]  check a model's integrity\n  \
+     models rm <id>                 remove a registered model\n  \
+     models gc --max-bytes N [--report json]  evict least-recently-used models until the cache is under N bytes\n  \
+     models preset set <id> --name NAME [--temperature F] [--top-p F] [--repetition-penalty F] [--stop S]...  attach named sampling defaults\n  \
+     models preset clear <id>       remove a model's sampling preset\n  \
+     models clamps set <id> [--temperature MIN,MAX] [--top-p MIN,MAX] [--repetition-penalty MIN,MAX]  set hard sampling-value bounds\n  \
+     bench [--threads N] [--pin-workers] [--ignore-topology] [--deterministic]  run the local compute self-benchmark\n  \
+     bench --target host:port       run a streaming throughput/TTFT workload against a running server\n  \
+     probe [--report json]          print a hardware capability report\n  \
+     probe --explain                print each compute backend's availability and which one would be selected\n  \
+     tokenize <model.gguf> <text>   encode text with a model's BPE vocab\n  \
+     quantize <model.gguf> [--default TYPE] [--override name=TYPE]  report per-tensor quantized sizes (TYPE: Q8_0, Q4_K, Q5_K)\n  \
+     inspect <model>                list tensor names/shapes/dtypes (.gguf, .safetensors, or a *.safetensors.index.json)\n  \
+     eval perplexity <model.gguf> <corpus.txt>       report a corpus's perplexity as JSON\n  \
+     eval mcq <model.gguf> <spec.jsonl>              report a multiple-choice suite's accuracy as JSON\n  \
+     service install [--config PATH] [--user NAME] [--log-file PATH] [--label NAME] [--platform launchd|systemd]  register ai-server to start on boot\n  \
+     service uninstall [--label NAME] [--platform launchd|systemd]  stop and remove the boot service\n  \
+     service status [--label NAME] [--platform launchd|systemd]  report whether the boot service is registered"
+}
+
+/// Execs the `server` binary built alongside this one (see the module doc
+/// comment for why this can't just be an in-process call), forwarding its
+/// exit status. Looks next to the current executable first so a locally
+/// built CLI picks up its sibling binary without needing `PATH` set up,
+/// falling back to `PATH` otherwise.
+fn run_serve() -> Result<(), String> {
+    let sibling = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("server")));
+    let binary = sibling.filter(|p| p.exists()).unwrap_or_else(|| PathBuf::from("server"));
+
+    let status = Command::new(&binary)
+        .status()
+        .map_err(|e| format!("launching {}: {e}", binary.display()))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("server exited with {status}"))
+    }
+}
+
+/// Registers, unregisters, or reports on the launchd/systemd unit that
+/// keeps `ai-server serve` running across reboots (see `service.rs`).
+/// `--config` (default `./ai-server.toml`, matching `run_serve`'s
+/// `AI_SERVER_CONFIG` default) is resolved to an absolute path since a
+/// boot-time service has no meaningful working directory of its own to
+/// resolve a relative one against. Reading `config`'s `log_file` itself
+/// would mean pulling `config.rs` (and, transitively through
+/// `guardrails.rs`, `crate::InferenceBackend`) into this crate root, which
+/// only `server.rs` defines — see this file's module doc comment on why
+/// that split exists — so `--log-file` is its own flag, defaulted to the
+/// same `./ai-server.log` `config::ServerConfig` itself defaults to.
+fn run_service(args: &[String]) -> Result<(), String> {
+    let action = args.first().map(String::as_str);
+    let label = parse_str_flag(args, "--label").unwrap_or_else(|| "ai-server".to_string());
+    let platform = match parse_str_flag(args, "--platform") {
+        Some(raw) => service::ServicePlatform::parse(&raw).ok_or_else(|| format!("unknown --platform {raw:?}, expected launchd/systemd"))?,
+        None => service::ServicePlatform::host().ok_or("this platform has no supported service manager (expected macOS or Linux); pass --platform to render a unit anyway")?,
+    };
+
+    match action {
+        Some("install") => {
+            let config_path = parse_str_flag(args, "--config").unwrap_or_else(|| "./ai-server.toml".to_string());
+            let config_path = std::fs::canonicalize(&config_path).map_err(|e| format!("resolving {config_path}: {e}"))?;
+            let working_dir = config_path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("/"));
+            let user = parse_str_flag(args, "--user")
+                .or_else(|| std::env::var("SUDO_USER").ok())
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| "root".to_string());
+            let log_file = parse_str_flag(args, "--log-file").unwrap_or_else(|| "./ai-server.log".to_string());
+            let sibling = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("server")));
+            let binary_path = sibling.filter(|p| p.exists()).unwrap_or_else(|| PathBuf::from("server"));
+
+            let spec = service::ServiceSpec { label, binary_path, config_path, working_dir, user, log_file: PathBuf::from(log_file) };
+            let unit_path = service::install(platform, &spec)?;
+            println!("installed and started {}", unit_path.display());
+            Ok(())
+        }
+        Some("uninstall") => {
+            service::uninstall(platform, &label)?;
+            println!("{label}: service stopped and removed");
+            Ok(())
+        }
+        Some("status") => {
+            match service::status(platform, &label) {
+                service::ServiceStatus::Installed { unit_path } => println!("{label}: installed at {}", unit_path.display()),
+                service::ServiceStatus::NotInstalled => println!("{label}: not installed"),
+            }
+            Ok(())
+        }
+        other => Err(format!("unknown `service` action {other:?}, expected install/uninstall/status")),
+    }
+}
+
+/// Runs an interactive terminal session against a running `ai-server
+/// serve` instance. See `chat_client.rs`'s module doc comment for why
+/// this is a plain line-oriented client rather than a `ratatui` TUI.
+fn run_chat(args: &[String]) -> Result<(), String> {
+    let host_port = parse_str_flag(args, "--target").unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    chat_client::ChatSession::new(host_port).run().map_err(|e| e.to_string())
+}
+
+/// Listens for `--timeout-ms` (default 2000) and prints every
+/// `discovery::Announcement` heard, one instance per line — the "browse"
+/// half of `discovery.rs`'s LAN discovery; `serve`'s `[discovery]` config
+/// controls the "advertise" half.
+fn run_discover(args: &[String]) -> Result<(), String> {
+    let timeout = std::time::Duration::from_millis(parse_u64_flag(args, "--timeout-ms").unwrap_or(2000));
+    let announcements = discovery::discover(discovery::DISCOVERY_PORT, timeout).map_err(|e| format!("listening for announcements: {e}"))?;
+    if parse_str_flag(args, "--report").as_deref() == Some("json") {
+        let json = Json::Array(
+            announcements
+                .iter()
+                .map(|a| {
+                    ObjectBuilder::new()
+                        .set("name", Json::String(a.name.clone()))
+                        .set("host_port", Json::String(a.host_port.clone()))
+                        .set("models", Json::Array(a.models.iter().cloned().map(Json::String).collect()))
+                        .set("capabilities", Json::Array(a.capabilities.iter().cloned().map(Json::String).collect()))
+                        .build()
+                })
+                .collect(),
+        );
+        println!("{}", json.to_string());
+    } else if announcements.is_empty() {
+        println!("no ai-server instances found (is `[discovery] enabled = true` set on the servers you expect to see?)");
+    } else {
+        for a in &announcements {
+            println!("{}\t{}\tmodels={}\tcapabilities={}", a.name, a.host_port, a.models.join(","), a.capabilities.join(","));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the release manifest at `--manifest-url` (default points at this
+/// project's own release host) and installs whatever it finds if it
+/// differs from `updater::CURRENT_VERSION`, or restores the previous
+/// binary with `--rollback`. Installing requires `--pubkey PATH`, the
+/// release signing key checked against the downloaded binary's detached
+/// signature — see `updater.rs`'s module doc comment for why that key has
+/// to come from here rather than from the manifest itself, and for the
+/// backup-then-swap mechanics.
+fn run_update(args: &[String]) -> Result<(), String> {
+    let current_exe = std::env::current_exe().map_err(|e| format!("locating the running executable: {e}"))?;
+
+    if args.iter().any(|a| a == "--rollback") {
+        updater::rollback(&current_exe).map_err(|e| e.to_string())?;
+        println!("rolled back to the previous version");
+        return Ok(());
+    }
+
+    let manifest_url =
+        parse_str_flag(args, "--manifest-url").unwrap_or_else(|| "http://localhost/ai-server-releases/stable.json".to_string());
+    match updater::check_for_update(&manifest_url, updater::CURRENT_VERSION).map_err(|e| e.to_string())? {
+        None => {
+            println!("already on the latest version ({})", updater::CURRENT_VERSION);
+            Ok(())
+        }
+        Some(manifest) => {
+            let public_key_path = parse_str_flag(args, "--pubkey").ok_or("update requires --pubkey PATH (the release signing key)")?;
+            updater::apply_update(&manifest, &current_exe, Path::new(&public_key_path)).map_err(|e| e.to_string())?;
+            println!("updated to {}", manifest.version);
+            Ok(())
+        }
+    }
+}
+
+fn models_dir() -> PathBuf {
+    std::env::var("AI_SERVER_MODELS_DIR").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("./models"))
+}
+
+fn run_models(args: &[String]) -> Result<(), String> {
+    let mut registry = ModelRegistry::open(&models_dir()).map_err(|e| format!("opening model registry: {e}"))?;
+    match args.first().map(String::as_str) {
+        Some("list") => {
+            for entry in registry.list() {
+                println!(
+                    "{}\t{}\t{} bytes\t{}",
+                    entry.id,
+                    entry.path.display(),
+                    entry.size_bytes,
+                    entry.verification.as_str()
+                );
+            }
+            Ok(())
+        }
+        Some("pull") => {
+            let spec = args.get(1).ok_or("models pull requires a <repo>/<file> argument")?;
+            let (repo_id, filename) = spec.rsplit_once('/').ok_or("expected <repo>/<file>, e.g. org/model/model.gguf")?;
+            let base_url = parse_str_flag(args, "--base-url").unwrap_or_else(|| "http://localhost".to_string());
+            let expected_sha256 = parse_str_flag(args, "--sha256").unwrap_or_default();
+            let dest = models_dir().join(filename);
+
+            downloader::download(&base_url, repo_id, filename, &dest, &expected_sha256)
+                .map_err(|e| format!("downloading {spec}: {e:?}"))?;
+            let size_bytes = std::fs::metadata(&dest).map_err(|e| e.to_string())?.len();
+            let id = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+            registry.register(id, dest, size_bytes).map_err(|e| format!("updating registry: {e}"))?;
+            println!("pulled {id}");
+            Ok(())
+        }
+        Some("verify") => {
+            let id = args.get(1).ok_or("models verify requires a model id")?;
+            let expected_sha256 = parse_str_flag(args, "--sha256").ok_or("models verify requires --sha256 HASH")?;
+            let sig = parse_str_flag(args, "--sig");
+            let pubkey = parse_str_flag(args, "--pubkey");
+            let signature = match (&sig, &pubkey) {
+                (Some(sig), Some(pubkey)) => {
+                    Some(registry::SignatureCheck { signature_path: Path::new(sig), public_key_path: Path::new(pubkey) })
+                }
+                (None, None) => None,
+                _ => return Err("models verify requires both --sig and --pubkey, or neither".to_string()),
+            };
+
+            let status = registry.verify(id, &expected_sha256, signature).map_err(|e| format!("verifying {id}: {e}"))?;
+            println!("{id}: {}", status.as_str());
+            if status == registry::VerificationStatus::Verified { Ok(()) } else { Err(format!("{id} failed verification: {}", status.as_str())) }
+        }
+        Some("rm") => {
+            let id = args.get(1).ok_or("models rm requires a model id")?;
+            if registry.remove(id).map_err(|e| format!("removing {id}: {e}"))? {
+                println!("removed {id}");
+                Ok(())
+            } else {
+                Err(format!("no such model {id:?}"))
+            }
+        }
+        Some("gc") => {
+            let max_cache_bytes = parse_u64_flag(args, "--max-bytes").ok_or("models gc requires --max-bytes N")?;
+            // No `model_pool::ModelPool` exists in this one-shot process, so
+            // nothing is "currently loaded" to protect the way
+            // `server.rs`'s `/admin/cache/gc` does.
+            let report = storage::gc(&mut registry, max_cache_bytes, &std::collections::HashSet::new())
+                .map_err(|e| format!("collecting garbage: {e}"))?;
+            if parse_str_flag(args, "--report").as_deref() == Some("json") {
+                println!("{}", report.to_json());
+            } else {
+                for id in &report.evicted {
+                    println!("evicted {id}");
+                }
+                println!("freed {} bytes, {} bytes remaining", report.freed_bytes, report.remaining_bytes);
+            }
+            Ok(())
+        }
+        Some("preset") => match args.get(1).map(String::as_str) {
+            Some("set") => {
+                let id = args.get(2).ok_or("models preset set requires a model id")?;
+                let name = parse_str_flag(args, "--name").ok_or("models preset set requires --name NAME")?;
+                let preset = sampling::GenerationPreset {
+                    name,
+                    temperature: parse_f32_flag(args, "--temperature"),
+                    top_p: parse_f32_flag(args, "--top-p"),
+                    repetition_penalty: parse_f32_flag(args, "--repetition-penalty"),
+                    stop: parse_repeated_flag(args, "--stop"),
+                };
+                registry.set_preset(id, Some(preset)).map_err(|e| format!("setting preset for {id}: {e}"))?;
+                println!("{id}: preset set");
+                Ok(())
+            }
+            Some("clear") => {
+                let id = args.get(2).ok_or("models preset clear requires a model id")?;
+                registry.set_preset(id, None).map_err(|e| format!("clearing preset for {id}: {e}"))?;
+                println!("{id}: preset cleared");
+                Ok(())
+            }
+            other => Err(format!("unknown `models preset` action {other:?}, expected set/clear")),
+        },
+        Some("clamps") => match args.get(1).map(String::as_str) {
+            Some("set") => {
+                let id = args.get(2).ok_or("models clamps set requires a model id")?;
+                let clamps = sampling::GenerationClamps {
+                    temperature: parse_f32_range_flag(args, "--temperature")?,
+                    top_p: parse_f32_range_flag(args, "--top-p")?,
+                    repetition_penalty: parse_f32_range_flag(args, "--repetition-penalty")?,
+                };
+                registry.set_clamps(id, clamps).map_err(|e| format!("setting clamps for {id}: {e}"))?;
+                println!("{id}: clamps set");
+                Ok(())
+            }
+            other => Err(format!("unknown `models clamps` action {other:?}, expected set")),
+        },
+        other => Err(format!("unknown `models` action {other:?}, expected list/pull/verify/rm/gc/preset/clamps")),
+    }
+}
+
+fn run_bench(args: &[String]) -> Result<(), String> {
+    let report_json = parse_str_flag(args, "--report").as_deref() == Some("json");
+
+    if let Some(target) = parse_str_flag(args, "--target") {
+        let config = loadtest::WorkloadConfig {
+            prompt_tokens: parse_usize_flag(args, "--prompt-tokens").unwrap_or(128),
+            concurrency: parse_usize_flag(args, "--concurrency").unwrap_or(1),
+            requests: parse_usize_flag(args, "--requests").unwrap_or(8),
+        };
+        let report = loadtest::run(&target, &config).map_err(|e| format!("benchmarking {target}: {e:?}"))?;
+        if report_json {
+            println!("{}", report.to_json());
+        } else {
+            println!("{report}");
+        }
+        return Ok(());
+    }
+
+    let threads = parse_usize_flag(args, "--threads");
+    let pin_workers = args.iter().any(|a| a == "--pin-workers");
+    let mut rt = Runtime::new(threads, pin_workers);
+    // `--ignore-topology` opts back into the plain round-robin core index:
+    // an escape hatch for hosts where the operator wants to hand-manage
+    // affinity (e.g. via `taskset`) without this fighting them over it.
+    if pin_workers && !args.iter().any(|a| a == "--ignore-topology") {
+        rt = rt.with_topology(hardware::HardwareProfile::probe());
+    }
+    // `--deterministic` trades away the parallel speedup for a pinned,
+    // single-threaded execution order — useful when comparing GFLOPS
+    // numbers across runs where any variance should come from the
+    // hardware, not from how this tool happened to schedule its chunks.
+    if args.iter().any(|a| a == "--deterministic") {
+        rt = rt.with_deterministic(true);
+    }
+    let thread_counts: Vec<usize> = (1..=rt.worker_count()).collect();
+    for result in bench::run_self_test(&rt, &thread_counts) {
+        println!("size={:<5} threads={:<3} {:>8.3} GFLOPS", result.size, result.threads, result.gflops);
+    }
+    Ok(())
+}
+
+fn run_probe(args: &[String]) -> Result<(), String> {
+    if args.iter().any(|a| a == "--explain") {
+        print_backend_explanation();
+        return Ok(());
+    }
+    if parse_str_flag(args, "--report").as_deref() == Some("json") {
+        println!("{}", diagnostics::Diagnostics::collect(None).to_json());
+    } else {
+        println!("{}", hardware::CpuCapabilities::detect());
+    }
+    Ok(())
+}
+
+/// Prints every backend `backend::candidates` considered and which one
+/// `backend::select` picked, so an operator can see why without having to
+/// read `backend.rs`'s scoring logic themselves. Run with `0` bytes of
+/// model weight requirement, same as `server.rs`'s startup call, since
+/// this subcommand has no particular model in mind.
+fn print_backend_explanation() {
+    let profile = hardware::HardwareProfile::probe();
+    let selection = backend::select(&profile, 0, None);
+    for candidate in &selection.candidates {
+        let marker = if candidate.backend == selection.chosen { "*" } else { " " };
+        let status = if candidate.available { "available" } else { "unavailable" };
+        println!("{marker} {:<10} {status:<12} {}", candidate.backend.as_str(), candidate.reason);
+    }
+    println!("selected: {}", selection.chosen.as_str());
+}
+
+fn run_tokenize(args: &[String]) -> Result<(), String> {
+    let model_path = args.first().ok_or("tokenize requires a <model.gguf> path")?;
+    let text = args.get(1).ok_or("tokenize requires a text argument")?;
+
+    let model = gguf::GgufModel::open(Path::new(model_path)).map_err(|e| format!("reading {model_path}: {e}"))?;
+    let tokenizer = BpeTokenizer::from_gguf(&model).map_err(|e| format!("loading tokenizer: {e:?}"))?;
+    let ids = tokenizer.encode(text);
+    println!("{}", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" "));
+    Ok(())
+}
+
+/// Reports how large each tensor in `model_path` would be after
+/// quantization, without touching any tensor data: `gguf.rs` deliberately
+/// doesn't decode tensor bytes yet (see its doc comment), so this is a
+/// planning report against the tensor table's declared dimensions, not an
+/// in-place rewrite of the file. `--default TYPE` sets the fallback quant
+/// type (Q8_0 if unset); repeat `--override name=TYPE` to pin specific
+/// tensors, the same per-tensor addressing `quantize::QuantPlan` uses.
+/// Assumes an `F16` source per element, matching this tool's stated scope.
+fn run_quantize(args: &[String]) -> Result<(), String> {
+    let model_path = args.first().ok_or("quantize requires a <model.gguf> path")?;
+    let model = gguf::GgufModel::open(Path::new(model_path)).map_err(|e| format!("reading {model_path}: {e}"))?;
+
+    let default = match parse_str_flag(args, "--default") {
+        Some(name) => Some(quantize::QuantType::from_name(&name).ok_or(format!("unknown quant type {name:?}"))?),
+        None => Some(quantize::QuantType::Q8_0),
+    };
+    let mut overrides = std::collections::BTreeMap::new();
+    for value in parse_repeated_flag(args, "--override") {
+        let (name, type_name) = value.split_once('=').ok_or_else(|| format!("--override expects name=TYPE, got {value:?}"))?;
+        let quant_type = quantize::QuantType::from_name(type_name).ok_or(format!("unknown quant type {type_name:?}"))?;
+        overrides.insert(name.to_string(), quant_type);
+    }
+    let plan = quantize::QuantPlan { default, overrides };
+
+    let mut original_total = 0u64;
+    let mut quantized_total = 0u64;
+    for tensor in &model.tensors {
+        let element_count: u64 = tensor.dims.iter().product();
+        let original_bytes = element_count * 2; // F16 source, per this tool's scope
+        original_total += original_bytes;
+
+        match plan.target_for(&tensor.name) {
+            Some(target) => {
+                let quantized_bytes = target.bytes_for(element_count as usize);
+                quantized_total += quantized_bytes;
+                println!("{}\t{}\t{original_bytes} -> {quantized_bytes} bytes", tensor.name, target.name());
+            }
+            None => {
+                quantized_total += original_bytes;
+                println!("{}\tskip (no plan target)\t{original_bytes} bytes unchanged", tensor.name);
+            }
+        }
+    }
+
+    println!(
+        "total: {original_total} -> {quantized_total} bytes ({:.1}% of original)",
+        quantized_total as f64 / original_total.max(1) as f64 * 100.0
+    );
+    Ok(())
+}
+
+fn run_eval(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("perplexity") => run_eval_perplexity(&args[1..]),
+        Some("mcq") => run_eval_mcq(&args[1..]),
+        other => Err(format!("unknown `eval` action {other:?}, expected perplexity/mcq")),
+    }
+}
+
+/// Tokenizes `corpus.txt` with `model.gguf`'s vocab and reports its
+/// perplexity under `eval::UniformScorer` — a placeholder log-probability
+/// distribution (see `eval.rs`'s doc comment), since this tree has no
+/// backend that produces real per-token logits yet. The JSON shape here is
+/// the one a future real backend's numbers would also be reported in, so
+/// results stay comparable once one exists.
+fn run_eval_perplexity(args: &[String]) -> Result<(), String> {
+    let model_path = args.first().ok_or("eval perplexity requires a <model.gguf> path")?;
+    let corpus_path = args.get(1).ok_or("eval perplexity requires a <corpus.txt> path")?;
+
+    let model = gguf::GgufModel::open(Path::new(model_path)).map_err(|e| format!("reading {model_path}: {e}"))?;
+    let tokenizer = BpeTokenizer::from_gguf(&model).map_err(|e| format!("loading tokenizer: {e:?}"))?;
+    let corpus = std::fs::read_to_string(corpus_path).map_err(|e| format!("reading {corpus_path}: {e}"))?;
+
+    let tokens = tokenizer.encode(&corpus);
+    let scorer = eval::UniformScorer { vocab_size: tokenizer.vocab_size() };
+    let score = eval::perplexity(&tokens, &scorer);
+
+    let report = ObjectBuilder::new()
+        .set("corpus_tokens", Json::Number(tokens.len() as f64))
+        .set("perplexity", Json::Number(score))
+        .build();
+    println!("{}", report.to_string());
+    Ok(())
+}
+
+/// Runs a multiple-choice task suite from a JSONL spec against
+/// `eval::UniformScorer` (see [`run_eval_perplexity`]'s doc comment for why
+/// there's no real scorer yet) and reports accuracy as JSON.
+fn run_eval_mcq(args: &[String]) -> Result<(), String> {
+    let model_path = args.first().ok_or("eval mcq requires a <model.gguf> path")?;
+    let spec_path = args.get(1).ok_or("eval mcq requires a <spec.jsonl> path")?;
+
+    let model = gguf::GgufModel::open(Path::new(model_path)).map_err(|e| format!("reading {model_path}: {e}"))?;
+    let tokenizer = BpeTokenizer::from_gguf(&model).map_err(|e| format!("loading tokenizer: {e:?}"))?;
+    let spec_text = std::fs::read_to_string(spec_path).map_err(|e| format!("reading {spec_path}: {e}"))?;
+    let items = eval::parse_mcq_spec(&spec_text)?;
+
+    let scorer = eval::UniformScorer { vocab_size: tokenizer.vocab_size() };
+    let result = eval::run_mcq_suite(&items, &tokenizer, &scorer);
+
+    let report = ObjectBuilder::new()
+        .set("total", Json::Number(result.total as f64))
+        .set("correct", Json::Number(result.correct as f64))
+        .set("accuracy", Json::Number(result.accuracy()))
+        .build();
+    println!("{}", report.to_string());
+    Ok(())
+}
+
+/// Lists tensor names/shapes/dtypes for either weight-file format this tree
+/// understands the structure of, dispatching on `path`'s extension:
+/// `.gguf` loads through `gguf::GgufModel`, anything else (a `.safetensors`
+/// file or a `*.safetensors.index.json` shard index) through
+/// `safetensors::SafetensorsModel`. Both go through the same
+/// [`ModelLoader`] trait, so this doesn't need its own per-format branch
+/// past picking which one to open.
+fn run_inspect(args: &[String]) -> Result<(), String> {
+    let path_str = args.first().ok_or("inspect requires a <model> path")?;
+    let path = Path::new(path_str);
+
+    let loader: Box<dyn ModelLoader> = if path.extension().and_then(|e| e.to_str()) == Some("gguf") {
+        Box::new(gguf::GgufModel::open(path).map_err(|e| format!("reading {path_str}: {e}"))?)
+    } else {
+        Box::new(safetensors::SafetensorsModel::open(path).map_err(|e| format!("reading {path_str}: {e}"))?)
+    };
+
+    let names = loader.tensor_names();
+    println!("tensors: {}", names.len());
+    for name in &names {
+        let dtype = loader.tensor_dtype(name).unwrap_or("?");
+        let shape = loader.tensor_shape(name).map(|s| format!("{s:?}")).unwrap_or_else(|| "?".to_string());
+        println!("{name}\t{dtype}\t{shape}");
+    }
+    Ok(())
+}
+
+/// Collects every value passed after a repeated flag like `--override
+/// a=Q8_0 --override b=Q4_K`, in the order given.
+fn parse_repeated_flag(args: &[String], flag: &str) -> Vec<String> {
+    args.iter()
+        .zip(args.iter().skip(1))
+        .filter_map(|(a, value)| (a == flag).then(|| value.clone()))
+        .collect()
+}
+
+/// Parses `--flag VALUE` out of the raw argument list, used for simple
+/// numeric overrides like `--threads N`.
+fn parse_usize_flag(args: &[String], flag: &str) -> Option<usize> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).and_then(|v| v.parse().ok())
+}
+
+fn parse_u64_flag(args: &[String], flag: &str) -> Option<u64> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).and_then(|v| v.parse().ok())
+}
+
+fn parse_f32_flag(args: &[String], flag: &str) -> Option<f32> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).and_then(|v| v.parse().ok())
+}
+
+/// Parses a `--flag MIN,MAX` pair used by `models clamps set`, e.g.
+/// `--temperature 0.0,1.5`.
+fn parse_f32_range_flag(args: &[String], flag: &str) -> Result<Option<(f32, f32)>, String> {
+    let Some(raw) = parse_str_flag(args, flag) else { return Ok(None) };
+    let (min, max) = raw.split_once(',').ok_or_else(|| format!("{flag} expects MIN,MAX, got {raw:?}"))?;
+    let min: f32 = min.trim().parse().map_err(|_| format!("{flag} expects MIN,MAX, got {raw:?}"))?;
+    let max: f32 = max.trim().parse().map_err(|_| format!("{flag} expects MIN,MAX, got {raw:?}"))?;
+    Ok(Some((min, max)))
+}
+
+/// Parses `--flag VALUE` out of the raw argument list, used for string
+/// options like `--report json`.
+fn parse_str_flag(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
@@ -0,0 +1,718 @@
+//! Typed, blocking Rust client for this tree's HTTP API: chat completions
+//! (buffered and streamed) and embeddings, with retries and a configurable
+//! per-request timeout.
+//!
+//! The request that prompted this file asked for an `ai-server-client`
+//! crate built on `reqwest` + `serde` + `futures`, published so other Rust
+//! projects could depend on it. This tree has no `Cargo.toml` anywhere and
+//! no dependency manager to declare any of those three against (see
+//! `cli.rs`'s and `json.rs`'s own doc comments on the same constraint), so
+//! nothing here can be published as a crate and nothing here is async.
+//! What this file gives instead is the closest std-only equivalent, built
+//! the same way `chat_client.rs` already talks to a running server: a
+//! blocking [`TcpStream`], this tree's own `json.rs` for request/response
+//! bodies, and the same chunked-SSE parsing `chat_client.rs`/`loadtest.rs`
+//! already use for streaming. [`Client::stream_chat_completion`] returns a
+//! blocking, pull-based [`Iterator`] — the closest std-only analogue to a
+//! `futures::Stream`, since there's no async runtime in this tree for a
+//! real `Stream` to poll against. [`ClientConfig::with_unix_socket_path`]
+//! connects over a Unix domain socket instead of TCP, for talking to a
+//! server embedded in a local desktop app via `server.unix_socket_path`
+//! (see `transport.rs`).
+
+mod json;
+
+use crate::json::{Json, ObjectBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// Either half of [`Client`]'s connection: a TCP socket, or (on Unix) a
+/// Unix domain socket for talking to a server embedded in a local desktop
+/// app with `server.unix_socket_path` set — see `transport.rs`'s
+/// server-side counterpart. Kept as its own small enum here rather than
+/// shared with `transport::Transport` since `client.rs` is a separate
+/// crate root with no `mod` path back to `server.rs`'s files.
+enum ClientStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ClientStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            ClientStream::Tcp(s) => {
+                s.set_read_timeout(Some(timeout))?;
+                s.set_write_timeout(Some(timeout))
+            }
+            #[cfg(unix)]
+            ClientStream::Unix(s) => {
+                s.set_read_timeout(Some(timeout))?;
+                s.set_write_timeout(Some(timeout))
+            }
+        }
+    }
+}
+
+impl Read for ClientStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ClientStream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            ClientStream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for ClientStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ClientStream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            ClientStream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ClientStream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            ClientStream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(String),
+    Timeout(String),
+    Http { status: u16, message: String },
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "io error: {e}"),
+            ClientError::Timeout(e) => write!(f, "timed out: {e}"),
+            ClientError::Http { status, message } => write!(f, "http {status}: {message}"),
+        }
+    }
+}
+
+fn map_io_error(e: std::io::Error) -> ClientError {
+    match e.kind() {
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => ClientError::Timeout(e.to_string()),
+        _ => ClientError::Io(e.to_string()),
+    }
+}
+
+/// A `role`/`content` turn — the same shape `chat_client.rs`'s own
+/// `Message` uses, exported here since a caller building a request needs
+/// to construct one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage { role: "system".to_string(), content: content.into() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage { role: "user".to_string(), content: content.into() }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage { role: "assistant".to_string(), content: content.into() }
+    }
+
+    fn to_json(&self) -> Json {
+        ObjectBuilder::new().set("role", Json::String(self.role.clone())).set("content", Json::String(self.content.clone())).build()
+    }
+}
+
+/// A `/v1/chat/completions` request body, built up with `with_*` calls the
+/// same way `model_pool::ModelPool` builds itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub temperature: Option<f64>,
+}
+
+impl ChatCompletionRequest {
+    pub fn new(model: impl Into<String>, messages: Vec<ChatMessage>) -> Self {
+        ChatCompletionRequest { model: model.into(), messages, temperature: None }
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    fn to_json(&self, stream: bool) -> Json {
+        let mut builder = ObjectBuilder::new()
+            .set("model", Json::String(self.model.clone()))
+            .set("messages", Json::Array(self.messages.iter().map(ChatMessage::to_json).collect()))
+            .set("stream", Json::Bool(stream));
+        if let Some(temperature) = self.temperature {
+            builder = builder.set("temperature", Json::Number(temperature));
+        }
+        builder.build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatChoice {
+    pub index: usize,
+    pub role: String,
+    pub content: String,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatCompletionResponse {
+    pub id: String,
+    pub model: String,
+    pub choices: Vec<ChatChoice>,
+}
+
+impl ChatCompletionResponse {
+    fn from_json(parsed: &Json) -> Option<ChatCompletionResponse> {
+        let choices = parsed
+            .get("choices")
+            .and_then(Json::as_array)?
+            .iter()
+            .map(|choice| {
+                let message = choice.get("message");
+                Some(ChatChoice {
+                    index: choice.get("index").and_then(Json::as_f64)? as usize,
+                    role: message.and_then(|m| m.get("role")).and_then(Json::as_str).unwrap_or("assistant").to_string(),
+                    content: message.and_then(|m| m.get("content")).and_then(Json::as_str).unwrap_or("").to_string(),
+                    finish_reason: choice.get("finish_reason").and_then(Json::as_str).unwrap_or("").to_string(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(ChatCompletionResponse {
+            id: parsed.get("id").and_then(Json::as_str)?.to_string(),
+            model: parsed.get("model").and_then(Json::as_str)?.to_string(),
+            choices,
+        })
+    }
+}
+
+/// A `/v1/embeddings` request body: one model, one or more inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+impl EmbeddingsRequest {
+    pub fn new(model: impl Into<String>, input: Vec<String>) -> Self {
+        EmbeddingsRequest { model: model.into(), input }
+    }
+
+    fn to_json(&self) -> Json {
+        ObjectBuilder::new()
+            .set("model", Json::String(self.model.clone()))
+            .set("input", Json::Array(self.input.iter().cloned().map(Json::String).collect()))
+            .build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingResponse {
+    /// One vector per input, in request order.
+    pub embeddings: Vec<Vec<f64>>,
+}
+
+impl EmbeddingResponse {
+    fn from_json(parsed: &Json) -> Option<EmbeddingResponse> {
+        let embeddings = parsed
+            .get("data")
+            .and_then(Json::as_array)?
+            .iter()
+            .map(|entry| {
+                entry.get("embedding").and_then(Json::as_array).map(|values| values.iter().filter_map(Json::as_f64).collect::<Vec<f64>>())
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(EmbeddingResponse { embeddings })
+    }
+}
+
+/// `Client`'s connection behavior: which server to talk to, how long to
+/// wait before giving up on a single attempt, and how many times to retry
+/// an attempt that failed to even get a response (a connection refused, a
+/// timeout) before surfacing the error to the caller. A response the
+/// server did answer — even a `4xx`/`5xx` — is never retried, since
+/// retrying a request the server has already rejected wouldn't change its
+/// answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    pub host_port: String,
+    pub timeout: Duration,
+    pub max_retries: usize,
+    pub retry_backoff: Duration,
+    /// When set, [`Client`] connects over this Unix domain socket path
+    /// instead of `host_port` — the client-side counterpart to
+    /// `server.unix_socket_path`, for a local desktop app that embeds this
+    /// server and never opens a network port. `host_port` is still
+    /// required (it's still sent as the request's `Host` header) but is
+    /// otherwise unused once this is set. Unix-only; connecting fails with
+    /// [`ClientError::Io`] on other platforms.
+    pub unix_socket_path: Option<String>,
+}
+
+impl ClientConfig {
+    pub fn new(host_port: impl Into<String>) -> Self {
+        ClientConfig {
+            host_port: host_port.into(),
+            timeout: Duration::from_secs(30),
+            max_retries: 0,
+            retry_backoff: Duration::from_millis(200),
+            unix_socket_path: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    pub fn with_unix_socket_path(mut self, path: impl Into<String>) -> Self {
+        self.unix_socket_path = Some(path.into());
+        self
+    }
+}
+
+pub struct Client {
+    config: ClientConfig,
+}
+
+impl Client {
+    pub fn new(config: ClientConfig) -> Client {
+        Client { config }
+    }
+
+    pub fn chat_completion(&self, request: &ChatCompletionRequest) -> Result<ChatCompletionResponse, ClientError> {
+        let body = request.to_json(false).to_string();
+        let (status, text) = self.send_with_retries("/v1/chat/completions", &body)?;
+        let parsed = Json::parse(&text).map_err(|e| ClientError::Http { status, message: e.to_string() })?;
+        if status != 200 {
+            return Err(ClientError::Http { status, message: text });
+        }
+        ChatCompletionResponse::from_json(&parsed).ok_or_else(|| ClientError::Http { status, message: "malformed chat completion response".to_string() })
+    }
+
+    pub fn embeddings(&self, request: &EmbeddingsRequest) -> Result<EmbeddingResponse, ClientError> {
+        let body = request.to_json().to_string();
+        let (status, text) = self.send_with_retries("/v1/embeddings", &body)?;
+        let parsed = Json::parse(&text).map_err(|e| ClientError::Http { status, message: e.to_string() })?;
+        if status != 200 {
+            return Err(ClientError::Http { status, message: text });
+        }
+        EmbeddingResponse::from_json(&parsed).ok_or_else(|| ClientError::Http { status, message: "malformed embeddings response".to_string() })
+    }
+
+    /// Opens one connection and returns a [`TokenStream`] pulling
+    /// `data: <token>` SSE events off it one at a time, the same wire
+    /// format `chat_client.rs::ChatSession::send` prints as it arrives.
+    /// Not retried — a stream that's already started sending tokens can't
+    /// be safely replayed without risking duplicated output.
+    pub fn stream_chat_completion(&self, request: &ChatCompletionRequest) -> Result<TokenStream, ClientError> {
+        let body = request.to_json(true).to_string();
+        let mut stream = self.connect()?;
+        write!(
+            stream,
+            "POST /v1/chat/completions HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.config.host_port,
+            body.len()
+        )
+        .map_err(map_io_error)?;
+        let mut reader = BufReader::new(stream);
+        let (status, _) = read_status_and_content_length(&mut reader)?;
+        if status != 200 {
+            let mut body = String::new();
+            reader.read_to_string(&mut body).ok();
+            return Err(ClientError::Http { status, message: body });
+        }
+        Ok(TokenStream { reader, buffer: Vec::new(), done: false })
+    }
+
+    fn connect(&self) -> Result<ClientStream, ClientError> {
+        let stream = match &self.config.unix_socket_path {
+            Some(path) => {
+                #[cfg(unix)]
+                {
+                    ClientStream::Unix(UnixStream::connect(path).map_err(map_io_error)?)
+                }
+                #[cfg(not(unix))]
+                {
+                    let _ = path;
+                    return Err(ClientError::Io("unix domain sockets are not supported on this platform".to_string()));
+                }
+            }
+            None => ClientStream::Tcp(TcpStream::connect(&self.config.host_port).map_err(map_io_error)?),
+        };
+        stream.set_timeouts(self.config.timeout).map_err(map_io_error)?;
+        Ok(stream)
+    }
+
+    fn send_with_retries(&self, path: &str, body: &str) -> Result<(u16, String), ClientError> {
+        let mut attempts_left = self.config.max_retries;
+        loop {
+            match self.send_once(path, body) {
+                Ok(result) => return Ok(result),
+                Err(ClientError::Http { .. }) => unreachable!("send_once never returns an Http error; the caller inspects status itself"),
+                Err(_) if attempts_left > 0 => {
+                    attempts_left -= 1;
+                    std::thread::sleep(self.config.retry_backoff);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Sends `body` to `path` and reads back a fully-buffered response —
+    /// only `Io`/`Timeout` failures reach `send_with_retries`'s retry loop,
+    /// since this never inspects the status code itself.
+    fn send_once(&self, path: &str, body: &str) -> Result<(u16, String), ClientError> {
+        let mut stream = self.connect()?;
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.config.host_port,
+            body.len()
+        )
+        .map_err(map_io_error)?;
+        let mut reader = BufReader::new(stream);
+        let (status, content_length) = read_status_and_content_length(&mut reader)?;
+        let mut buf = vec![0u8; content_length];
+        reader.read_exact(&mut buf).map_err(map_io_error)?;
+        let text = String::from_utf8(buf).map_err(|e| ClientError::Io(e.to_string()))?;
+        Ok((status, text))
+    }
+}
+
+/// Reads the status line and headers off `reader`, returning the status
+/// code and the `Content-Length` header's value (`0` if absent, matching
+/// an empty body).
+fn read_status_and_content_length(reader: &mut BufReader<ClientStream>) -> Result<(u16, usize), ClientError> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(map_io_error)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| ClientError::Io(format!("malformed status line: {:?}", status_line.trim())))?;
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(map_io_error)?;
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:").map(str::to_string) {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    Ok((status, content_length))
+}
+
+/// A pull-based iterator over one streaming chat completion's tokens,
+/// parsing the same chunked-SSE wire format
+/// `chat_client.rs::read_chunked_body` does. Each `next()` call blocks
+/// until either a full token has arrived or the stream ends; the final
+/// `[DONE]` sentinel ends iteration rather than being yielded as a token.
+pub struct TokenStream {
+    reader: BufReader<ClientStream>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl Iterator for TokenStream {
+    type Item = Result<String, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(pos) = find(&self.buffer, b"\n\n") {
+                let event = self.buffer[..pos].to_vec();
+                self.buffer.drain(..pos + 2);
+                let Some(data) = event.strip_prefix(b"data: ") else { continue };
+                if data == b"[DONE]" {
+                    self.done = true;
+                    return None;
+                }
+                return match std::str::from_utf8(data) {
+                    Ok(token) => Some(Ok(token.to_string())),
+                    Err(e) => Some(Err(ClientError::Io(e.to_string()))),
+                };
+            }
+            match read_one_chunk(&mut self.reader) {
+                Ok(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Reads one HTTP chunk, returning `None` at the zero-length terminating
+/// chunk — the same shape `chat_client.rs::read_chunked_body` uses inline,
+/// pulled out here so [`TokenStream::next`] can read one chunk at a time
+/// instead of looping over the whole body up front.
+fn read_one_chunk(reader: &mut BufReader<ClientStream>) -> Result<Option<Vec<u8>>, ClientError> {
+    let mut size_line = String::new();
+    reader.read_line(&mut size_line).map_err(map_io_error)?;
+    let size = usize::from_str_radix(size_line.trim(), 16)
+        .map_err(|_| ClientError::Io(format!("bad chunk size: {:?}", size_line.trim())))?;
+    if size == 0 {
+        return Ok(None);
+    }
+    let mut chunk = vec![0u8; size];
+    reader.read_exact(&mut chunk).map_err(map_io_error)?;
+    let mut trailer = [0u8; 2];
+    reader.read_exact(&mut trailer).map_err(map_io_error)?;
+    Ok(Some(chunk))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn chat_message_constructors_set_the_expected_role() {
+        assert_eq!(ChatMessage::user("hi").role, "user");
+        assert_eq!(ChatMessage::system("hi").role, "system");
+        assert_eq!(ChatMessage::assistant("hi").role, "assistant");
+    }
+
+    #[test]
+    fn chat_completion_request_to_json_includes_stream_and_temperature() {
+        let request = ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")]).with_temperature(0.5);
+        let json = request.to_json(true).to_string();
+        assert!(json.contains("\"stream\":true"));
+        assert!(json.contains("\"temperature\":0.5"));
+    }
+
+    #[test]
+    fn chat_completion_response_from_json_parses_choices() {
+        let body = ObjectBuilder::new()
+            .set("id", Json::String("cmpl-1".to_string()))
+            .set("model", Json::String("m".to_string()))
+            .set(
+                "choices",
+                Json::Array(vec![ObjectBuilder::new()
+                    .set("index", Json::Number(0.0))
+                    .set(
+                        "message",
+                        ObjectBuilder::new().set("role", Json::String("assistant".to_string())).set("content", Json::String("hello".to_string())).build(),
+                    )
+                    .set("finish_reason", Json::String("stop".to_string()))
+                    .build()]),
+            )
+            .build();
+        let response = ChatCompletionResponse::from_json(&body).unwrap();
+        assert_eq!(response.id, "cmpl-1");
+        assert_eq!(response.choices.len(), 1);
+        assert_eq!(response.choices[0].content, "hello");
+        assert_eq!(response.choices[0].finish_reason, "stop");
+    }
+
+    #[test]
+    fn embedding_response_from_json_parses_one_vector_per_input() {
+        let body = ObjectBuilder::new()
+            .set("object", Json::String("list".to_string()))
+            .set(
+                "data",
+                Json::Array(vec![
+                    ObjectBuilder::new().set("index", Json::Number(0.0)).set("embedding", Json::Array(vec![Json::Number(1.0), Json::Number(2.0)])).build(),
+                    ObjectBuilder::new().set("index", Json::Number(1.0)).set("embedding", Json::Array(vec![Json::Number(3.0)])).build(),
+                ]),
+            )
+            .build();
+        let response = EmbeddingResponse::from_json(&body).unwrap();
+        assert_eq!(response.embeddings, vec![vec![1.0, 2.0], vec![3.0]]);
+    }
+
+    fn spawn_json_server(response_body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            if let Ok((mut socket, _)) = listener.accept() {
+                let mut reader = BufReader::new(socket.try_clone().unwrap());
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                write!(socket, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", response_body.len(), response_body).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn chat_completion_round_trips_through_a_real_tcp_connection() {
+        let body = r#"{"id":"cmpl-1","model":"m","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}]}"#;
+        let addr = spawn_json_server(body);
+        let client = Client::new(ClientConfig::new(addr).with_timeout(Duration::from_secs(2)));
+        let response = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap();
+        assert_eq!(response.choices[0].content, "hi");
+    }
+
+    #[test]
+    fn chat_completion_surfaces_a_non_200_status_as_an_http_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            if let Ok((mut socket, _)) = listener.accept() {
+                let mut reader = BufReader::new(socket.try_clone().unwrap());
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                let body = r#"{"error":{"message":"bad model"}}"#;
+                write!(socket, "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        let client = Client::new(ClientConfig::new(addr).with_timeout(Duration::from_secs(2)));
+        let err = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap_err();
+        assert!(matches!(err, ClientError::Http { status: 404, .. }));
+    }
+
+    #[test]
+    fn connect_reports_a_timeout_as_a_timeout_error_not_an_io_error() {
+        // Nothing is listening on this port, so the connection itself
+        // fails fast (connection refused) rather than timing out — this
+        // exercises the `Io` branch of `map_io_error` for a connect
+        // failure, the counterpart to the timeout path exercised by the
+        // slow-response test below.
+        let client = Client::new(ClientConfig::new("127.0.0.1:1").with_timeout(Duration::from_millis(50)));
+        let err = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap_err();
+        assert!(matches!(err, ClientError::Io(_)));
+    }
+
+    #[test]
+    fn a_slow_server_triggers_a_timeout_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            if let Ok((socket, _)) = listener.accept() {
+                thread::sleep(Duration::from_secs(2));
+                drop(socket);
+            }
+        });
+        let client = Client::new(ClientConfig::new(addr).with_timeout(Duration::from_millis(50)));
+        let err = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap_err();
+        assert!(matches!(err, ClientError::Timeout(_)));
+    }
+
+    #[test]
+    fn stream_chat_completion_yields_tokens_in_order_and_stops_at_done() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            if let Ok((mut socket, _)) = listener.accept() {
+                let mut reader = BufReader::new(socket.try_clone().unwrap());
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                write!(socket, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+                for event in ["data: one \n\n", "data: two \n\n", "data: [DONE]\n\n"] {
+                    write!(socket, "{:x}\r\n{}\r\n", event.len(), event).unwrap();
+                }
+                write!(socket, "0\r\n\r\n").unwrap();
+            }
+        });
+        let client = Client::new(ClientConfig::new(addr).with_timeout(Duration::from_secs(2)));
+        let tokens: Vec<String> =
+            client.stream_chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap().map(|t| t.unwrap()).collect();
+        assert_eq!(tokens, vec!["one ", "two "]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chat_completion_round_trips_through_a_real_unix_socket() {
+        use std::os::unix::net::UnixListener;
+
+        let body = r#"{"id":"cmpl-1","model":"m","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}]}"#;
+        let path = std::env::temp_dir().join(format!("ai-server-client-test-{:?}.sock", thread::current().id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            if let Ok((mut socket, _)) = listener.accept() {
+                let mut reader = BufReader::new(socket.try_clone().unwrap());
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                write!(socket, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        let client = Client::new(
+            ClientConfig::new("localhost").with_timeout(Duration::from_secs(2)).with_unix_socket_path(path.to_str().unwrap()),
+        );
+        let response = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap();
+        assert_eq!(response.choices[0].content, "hi");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn retries_exhaust_before_surfacing_a_connection_error() {
+        let started = std::time::Instant::now();
+        let client = Client::new(ClientConfig::new("127.0.0.1:1").with_timeout(Duration::from_millis(50)).with_max_retries(2).with_retry_backoff(Duration::from_millis(10)));
+        let err = client.chat_completion(&ChatCompletionRequest::new("m", vec![ChatMessage::user("hi")])).unwrap_err();
+        assert!(matches!(err, ClientError::Io(_)));
+        // Two retries means three attempts total; each retry sleeps for
+        // `retry_backoff` first, so at least two backoff sleeps elapsed.
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,2517 @@
+//! Layered server configuration: a TOML file provides the base, individual
+//! keys can be overridden by `AI_SERVER_*` environment variables, and a
+//! background poller picks up in-place edits to the file for the settings
+//! that are safe to change without a restart.
+//!
+//! The TOML parsing here is a hand-rolled subset (`[section]` headers plus
+//! `key = value` pairs, string/integer/bool values, `#` comments) rather
+//! than pulling in the `toml` crate, matching this tree's no-dependency
+//! policy — the same trade `json.rs` made for JSON. Nested tables, arrays,
+//! and multi-line strings aren't supported; this server's config is flat
+//! enough not to need them yet.
+//!
+//! True SIGHUP/inotify hot-reload would need signal-handling or `inotify(7)`
+//! FFI (the same category of platform-specific `extern "C"` calls as
+//! `hardware.rs`'s `sysctlbyname`), which is a lot of unsafe surface for a
+//! feature whose whole point is convenience. [`watch`] gets the same
+//! outcome — edits picked up without restarting the process — with a
+//! polling thread instead.
+
+use crate::backend::Backend;
+use crate::context_policy::ContextPolicy;
+use crate::gguf::RopeScaling;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Io(String),
+    Malformed { line: usize, message: String },
+    InvalidValue { key: String, message: String },
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TomlValue {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+/// Parses the supported TOML subset into a flat map keyed by
+/// `"section.key"` (or just `"key"` for keys before any `[section]`).
+pub(crate) fn parse_toml(text: &str) -> Result<BTreeMap<String, TomlValue>, ConfigError> {
+    let mut values = BTreeMap::new();
+    let mut section = String::new();
+
+    for (line_no, raw_line) in text.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ConfigError::Malformed { line: line_no + 1, message: format!("expected \"key = value\", got {line:?}") });
+        };
+        let key = key.trim();
+        let full_key = if section.is_empty() { key.to_string() } else { format!("{section}.{key}") };
+        let value = parse_toml_value(value.trim())
+            .ok_or_else(|| ConfigError::Malformed { line: line_no + 1, message: format!("unrecognized value {:?}", value.trim()) })?;
+        values.insert(full_key, value);
+    }
+    Ok(values)
+}
+
+fn parse_toml_value(raw: &str) -> Option<TomlValue> {
+    if let Some(inner) = raw.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return Some(TomlValue::String(inner.to_string()));
+    }
+    match raw {
+        "true" => return Some(TomlValue::Bool(true)),
+        "false" => return Some(TomlValue::Bool(false)),
+        _ => {}
+    }
+    raw.parse::<i64>().ok().map(TomlValue::Integer)
+}
+
+/// Typed, validated server configuration. Every field has a sensible
+/// default so a deployment can start from an empty (or missing) config
+/// file and override only what it needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub rpc_bind_address: String,
+    pub max_batch_size: usize,
+    pub max_context_tokens: usize,
+    /// Default behavior when a request's prompt exceeds `max_context_tokens`
+    /// and the request itself doesn't specify a `context_policy` — one of
+    /// `context_policy::ContextPolicy`'s `as_str()` values (`"error"`,
+    /// `"truncate"`, `"slide"`). Stored as a `String` rather than the enum
+    /// directly since the hand-rolled TOML parser here only produces
+    /// string/integer/bool values (see the module doc comment); validated
+    /// against `ContextPolicy::parse` in `validate`.
+    pub context_overflow_policy: String,
+    pub otlp_endpoint: Option<(String, u16)>,
+    pub models_dir: String,
+    /// How long a model can sit unused in the [`crate::model_pool::ModelPool`]
+    /// before it's unloaded.
+    pub model_idle_timeout_seconds: usize,
+    /// Dummy prefill/decode passes `model_pool::ModelPool` runs against a
+    /// model right after loading it, so JIT compilation and cache
+    /// population happen there instead of on a user's first real request.
+    /// `0` disables warmup entirely.
+    pub warmup_runs: usize,
+    /// Prompt used for each warmup pass; its content doesn't matter, only
+    /// that it exercises the same prefill/decode path a real request would.
+    pub warmup_prompt: String,
+    /// Refuse to load any model whose `registry::ModelEntry::verification`
+    /// isn't `Verified` — see `registry.rs`'s `verify` and
+    /// [`crate::model_pool::ModelPool`]'s factory in `server.rs`. Off by
+    /// default so a fresh deployment that hasn't run `models verify` yet
+    /// still serves.
+    pub strict_model_verification: bool,
+    /// Caps total disk usage of `models_dir` as seen by `storage::gc`;
+    /// once exceeded, the least-recently-used unloaded models are deleted
+    /// first. `0` means unlimited, matching this file's other
+    /// `0`-means-unlimited fields (`log_max_bytes`, `daily_token_quota`).
+    pub max_cache_bytes: u64,
+    /// Per-model RoPE scaling override, each written as `id=mode:factor`
+    /// (comma-separated, like `max_output_tokens_by_key`) — e.g.
+    /// `llama-13b=yarn:4.0`. `mode` is one of `gguf::RopeScaling::as_str`'s
+    /// values; an operator's override here beats whatever a model's own
+    /// GGUF header declares (see `registry::ModelEntry::rope_scaling`),
+    /// the same way it would for any other file the operator knows more
+    /// about than its own metadata.
+    pub rope_scaling_by_model: Vec<String>,
+    /// Static API keys accepted by `auth::AuthRegistry`. Empty disables
+    /// auth entirely, matching this server's long-standing open-by-default
+    /// posture.
+    pub api_keys: Vec<String>,
+    pub requests_per_minute: usize,
+    /// Per-key daily token budget; `0` means unlimited.
+    pub daily_token_quota: usize,
+    /// Default cap on how many (whitespace-split) tokens a completion is
+    /// truncated to, for a key with no entry in `max_output_tokens_by_key`
+    /// — see `auth::GenerationLimits`.
+    pub default_max_output_tokens: usize,
+    /// Default wall-clock deadline `server.rs`'s generation timeout enforces
+    /// around a `generate`/`generate_with_images` call, for a key with no
+    /// entry in `request_timeout_by_key_seconds`.
+    pub default_request_timeout_seconds: u64,
+    /// Per-key `max_output_tokens` overrides, each written as `key=tokens`
+    /// (comma-separated, like `tenant_keys`) — see `auth::GenerationLimits`.
+    pub max_output_tokens_by_key: Vec<String>,
+    /// Per-key request timeout overrides in seconds, each written as
+    /// `key=seconds` (comma-separated, like `max_output_tokens_by_key`).
+    pub request_timeout_by_key_seconds: Vec<String>,
+    /// API keys accepted by `admin::AdminState`. Unlike `api_keys`, empty
+    /// means the `/admin` API refuses every request rather than allowing
+    /// them — see `AdminState::authenticate`.
+    pub admin_keys: Vec<String>,
+    /// Where `logging::JsonLogger` appends structured request logs.
+    pub log_file: String,
+    /// Rotate `log_file` once it reaches this size; `0` disables rotation.
+    pub log_max_bytes: usize,
+    /// Use `logging::LogFormat::Pretty` instead of one-JSON-object-per-line.
+    pub log_pretty: bool,
+    /// Bounds how long `/readyz`'s slower checks (`health::check_backend_responsive`,
+    /// `health::check_disk_space`'s `df` subprocess) wait before that check
+    /// is reported unhealthy.
+    pub readiness_check_timeout_ms: usize,
+    /// How long a SIGTERM/SIGINT-triggered shutdown waits for in-flight
+    /// requests to finish before exiting anyway.
+    pub shutdown_drain_timeout_seconds: usize,
+    /// How long a `response_cache::ResponseCache` entry stays valid before
+    /// a re-request recomputes it instead of serving a stale hit.
+    pub response_cache_ttl_seconds: usize,
+    /// Max entries `response_cache::ResponseCache` holds before evicting
+    /// its oldest one to make room.
+    pub response_cache_max_entries: usize,
+    /// Max entries `embedding_cache::EmbeddingCache` holds on disk before
+    /// evicting its oldest one to make room.
+    pub embedding_cache_max_entries: usize,
+    /// How long an `Idempotency-Key` stays claimed in
+    /// `response_cache::ResponseCache` — a retry using the same key within
+    /// this window is served the original request's result instead of
+    /// starting a second generation.
+    pub idempotency_key_ttl_seconds: usize,
+    /// API keys whose requests `scheduler::Scheduler` should submit under
+    /// `PriorityClass::Batch`. A key not listed here or in
+    /// `background_priority_keys` defaults to `Interactive`.
+    pub batch_priority_keys: Vec<String>,
+    /// API keys whose requests should be submitted under
+    /// `PriorityClass::Background` — see `batch_priority_keys`.
+    pub background_priority_keys: Vec<String>,
+    /// Max requests `scheduler::Scheduler` lets queue up in the `Batch`
+    /// priority class before shedding load; `0` means unlimited.
+    pub scheduler_batch_queue_limit: usize,
+    /// Max requests `scheduler::Scheduler` lets queue up in the
+    /// `Background` priority class before shedding load; `0` means
+    /// unlimited.
+    pub scheduler_background_queue_limit: usize,
+    /// Downstream nodes to forward `/v1/completions`/`/v1/chat/completions`
+    /// to instead of serving them locally, each written as `id=host:port`
+    /// (comma-separated, like `api_keys`) — see `router.rs`'s doc comment.
+    /// Empty (the default) means router mode is off and this server
+    /// serves requests itself.
+    pub router_nodes: Vec<String>,
+    /// Forces `backend::select` to pick this backend (one of
+    /// `backend::Backend`'s `as_str()` values) instead of the best
+    /// available one, as long as it's actually available on this host —
+    /// see `backend.rs`'s doc comment for what happens when it isn't.
+    /// `None` (the default) leaves the choice to `backend::select`.
+    pub backend_override: Option<String>,
+    /// Passed through to `llama_ffi::LlamaModel::load`'s `flash_attn`
+    /// argument once a `Metal`/`Cuda`-backed `InferenceBackend` actually
+    /// loads models through it — llama.cpp's own GPU backends already ship
+    /// a fused, tiled flash-attention kernel (MSL on Metal, a CUDA kernel
+    /// on Nvidia) behind this toggle, so there's no kernel for this Rust
+    /// tree to author or numerically verify itself. Defaults to `false`
+    /// since llama.cpp's flash-attention path only recently stabilized
+    /// across quantization formats.
+    pub flash_attention_enabled: bool,
+    /// An operator-supplied `tensor_split` (comma-separated, e.g.
+    /// `"0.5,0.3,0.2"`), overriding `cuda::CudaInfo::tensor_split`'s
+    /// proportional-to-VRAM default the same way `backend_override` beats
+    /// `backend::select`'s own preference order — see
+    /// `cuda::CudaInfo::effective_tensor_split`. Empty (the default) leaves
+    /// the split to that default.
+    pub tensor_split_override: Vec<String>,
+    /// Manual override for how many of a model's transformer layers run on
+    /// GPU rather than CPU — llama.cpp's own `n_gpu_layers` convention.
+    /// `None` (the default) leaves the choice to
+    /// `resources::ModelMemoryProfile::gpu_layers_for_budget`'s
+    /// memory-budget-driven calculation; see
+    /// `resources::ModelMemoryProfile::effective_gpu_layers`.
+    pub n_gpu_layers_override: Option<usize>,
+    /// `"record"` or `"replay"`, selecting `replay_backend::RecordingBackend`
+    /// or `replay_backend::ReplayBackend` in front of (or in place of) the
+    /// normal backend — see that module's doc comment. `None` (the
+    /// default) runs the normal backend unwrapped. Requires `replay_file`
+    /// to also be set.
+    pub replay_mode: Option<String>,
+    /// Path `replay_mode`'s `RecordingBackend` appends interactions to, or
+    /// `ReplayBackend` reads them back from.
+    pub replay_file: Option<String>,
+    /// Serves `mock_backend::MockBackend` in place of the normal backend
+    /// for every model id — see that module's doc comment. Meant for CI
+    /// and local development on a machine with no model files at all, so
+    /// unlike `replay_mode` it needs no prior recording pass or file.
+    pub mock_backend_enabled: bool,
+    /// `MockBackend`'s response for any prompt not otherwise scripted.
+    /// `None` (the default) falls back to `MockBackend`'s own default text.
+    pub mock_default_response: Option<String>,
+    /// Artificial delay `MockBackend` sleeps before answering, in
+    /// milliseconds. `None` (the default) answers immediately.
+    pub mock_latency_ms: Option<usize>,
+    /// Which tenant each API key belongs to, each written as `key=tenant`
+    /// (comma-separated, like `router_nodes`). A key with no entry here
+    /// belongs to no tenant — see `tenancy::TenantRegistry::tenant_for`.
+    pub tenant_keys: Vec<String>,
+    /// Per-tenant model allow-lists, each written as
+    /// `tenant:model1|model2` (comma-separated entries, `|`-separated
+    /// models within one). A tenant with no entry here is unrestricted —
+    /// see `tenancy::TenantRegistry::allows_model`.
+    pub tenant_models: Vec<String>,
+    /// Named model aliases a request's `"model"` field can target instead
+    /// of a real model id, each written as
+    /// `alias:model1=70|model2=30|shadow=model3` (comma-separated entries,
+    /// like `tenant_models`) — a percentage-weighted split across one or
+    /// more real models, plus an optional `shadow=` target whose response
+    /// is generated and timed but never returned. See
+    /// `model_alias.rs`'s module doc comment.
+    pub model_aliases: Vec<String>,
+    /// Whether `main` stands up an `audit::AuditLogger` at all. Off by
+    /// default since recording (and possibly retaining) request/response
+    /// bodies is a compliance decision an operator should opt into, not
+    /// one this server makes for them.
+    pub audit_enabled: bool,
+    /// `"file"` or `"syslog"` — which `audit::AuditLogger` constructor
+    /// `main` calls. See `audit.rs`'s module doc comment for what each
+    /// sink does.
+    pub audit_sink: String,
+    /// Destination path when `audit_sink` is `"file"`.
+    pub audit_file: String,
+    /// `host:port` of the syslog receiver when `audit_sink` is `"syslog"`.
+    pub audit_syslog_addr: String,
+    /// Whether audit entries carry the request prompt and response text
+    /// (redacted per `audit_redact_patterns`) or just metadata
+    /// (timestamps, path, status, client). Off by default for the same
+    /// reason `audit_enabled` is.
+    pub audit_include_bodies: bool,
+    /// Literal substrings replaced with `[REDACTED]` in any prompt/response
+    /// text an audit entry carries — comma-separated, like `api_keys`. See
+    /// `audit::redact`'s doc comment for why these are substrings rather
+    /// than patterns.
+    pub audit_redact_patterns: Vec<String>,
+    /// Whether `main` stands up a `guardrails::GuardrailsEngine` with the
+    /// rules below, or `GuardrailsEngine::disabled()`.
+    pub guardrails_enabled: bool,
+    /// Substrings that block a request outright when found in its prompt
+    /// or completion — comma-separated.
+    pub guardrails_block_patterns: Vec<String>,
+    /// Substrings replaced with `[REDACTED]` when found — comma-separated.
+    pub guardrails_redact_patterns: Vec<String>,
+    /// Substrings that only get reported in the response's `"moderation"`
+    /// field without changing anything — comma-separated.
+    pub guardrails_annotate_patterns: Vec<String>,
+    /// Prepended to the checked text and run through the request's own
+    /// backend as a classifier prompt (see `guardrails.rs`'s doc comment);
+    /// empty disables the classifier.
+    pub guardrails_classifier_prompt: String,
+    /// One of `guardrails::Action::as_str`'s values — what the classifier
+    /// triggers when it flags text.
+    pub guardrails_classifier_action: String,
+    /// Whether `main` stands up a `plugins::PluginRegistry` at all. Off by
+    /// default — running arbitrary operator-supplied WASM against every
+    /// request is an opt-in capability, not a default one.
+    pub plugins_enabled: bool,
+    /// Directory scanned for `*.wasm` plugin modules; each file's stem
+    /// becomes its plugin id. See `plugins.rs`'s module doc comment for the
+    /// stdin/stdout transform ABI a plugin implements.
+    pub plugins_dir: String,
+    /// Path (or bare name resolved via `PATH`) of the `wasmtime` CLI binary
+    /// `plugins::PluginRegistry` shells out to for each invocation — no
+    /// linked WASM runtime crate exists in this dependency-free tree.
+    pub plugins_wasmtime_path: String,
+    /// How often `plugins::watch` rescans `plugins_dir` for added or
+    /// removed files, in seconds.
+    pub plugins_reload_interval_seconds: usize,
+    /// Whether `main` serves `POST /mcp`, exposing this server's
+    /// generation capability as an MCP tool (see `mcp::dispatch`). Off by
+    /// default, like the other opt-in `[audit]`/`[guardrails]`/`[plugins]`
+    /// surfaces.
+    pub mcp_enabled: bool,
+    /// External MCP servers to connect to as a client, each written as
+    /// `name=host:port` (comma-separated, same shape as `router_nodes`).
+    /// Empty means no client-mode connections at all — `main` stands up
+    /// `mcp::McpClientRegistry::disabled()` in that case.
+    pub mcp_client_servers: Vec<String>,
+    /// Read/write timeout for a client-mode call to an external MCP
+    /// server, in milliseconds.
+    pub mcp_client_timeout_ms: usize,
+    /// How often `mcp::watch` re-runs `tools/list` against every
+    /// configured `mcp_client_servers` entry, in seconds.
+    pub mcp_client_refresh_interval_seconds: usize,
+    /// Whether `main` serves `POST /v1/agents/runs` at all, backed by an
+    /// `agent::AgentTools`. Off by default: an agent run executes shell
+    /// commands, HTTP requests, and file I/O on the model's own say-so,
+    /// so — like `[plugins]`'s arbitrary WASM execution — it's opt-in
+    /// rather than a default surface.
+    pub agent_enabled: bool,
+    /// Shell command names an agent run's `shell` tool may invoke,
+    /// comma-separated. Empty means the `shell` tool always fails closed.
+    pub agent_shell_allowlist: Vec<String>,
+    /// `host:port` targets an agent run's `http` tool may reach,
+    /// comma-separated. Empty means the `http` tool always fails closed.
+    pub agent_http_allowlist: Vec<String>,
+    /// Directory an agent run's `read_file`/`write_file` tools are
+    /// confined to; see `agent::AgentTools::resolve_path`.
+    pub agent_file_root: String,
+    /// Upper bound on steps in one `agent::run` loop, overridable
+    /// downward (never upward) by a request's own `max_steps`.
+    pub agent_max_steps: usize,
+    /// Whether `main` stands up a `pipelines::PipelineRegistry` and serves
+    /// `POST /v1/pipelines/{name}/run` at all. Off by default, the same
+    /// opt-in posture `[plugins]`/`[agent]` take toward their own surfaces.
+    pub pipelines_enabled: bool,
+    /// Directory scanned for `*.yaml`/`*.yml` pipeline definitions; each
+    /// file's stem becomes its pipeline name. See `pipelines.rs`'s module
+    /// doc comment for the YAML subset a pipeline file is written in.
+    pub pipelines_dir: String,
+    /// How often `pipelines::watch` rescans `pipelines_dir` for added,
+    /// removed, or edited files, in seconds.
+    pub pipelines_reload_interval_seconds: usize,
+    /// Whether `main` stands up a `jobs::JobRegistry` and its scheduler
+    /// thread, plus the `/v1/jobs` list/trigger/cancel routes. Off by
+    /// default, the same opt-in posture `[pipelines]`/`[agent]` take
+    /// toward their own surfaces.
+    pub jobs_enabled: bool,
+    /// Directory scanned for `<id>.toml` job definitions (and where each
+    /// job's `<id>.state.json` run state is persisted). See `jobs.rs`'s
+    /// module doc comment for a job file's cron-like schedule syntax.
+    pub jobs_dir: String,
+    /// Whether `main` stands up a `watcher::DocumentWatcher` background
+    /// poller keeping `watcher_collection` in sync with `watcher_dir`.
+    /// Off by default, the same opt-in posture `[jobs]`/`[pipelines]`
+    /// take toward their own surfaces.
+    pub watcher_enabled: bool,
+    /// The single folder watched for new, changed, or removed documents.
+    /// See `watcher.rs`'s module doc comment for which file types it can
+    /// actually extract text from today.
+    pub watcher_dir: String,
+    /// The vector store collection kept in sync with `watcher_dir`.
+    pub watcher_collection: String,
+    /// How often the watcher rescans `watcher_dir`, in seconds — also
+    /// the effective debounce window, since a file is only picked up
+    /// once a full scan sees it settled.
+    pub watcher_poll_interval_seconds: usize,
+    /// Whether `route` also serves Ollama-style `/api/generate`,
+    /// `/api/chat`, `/api/tags`, and `/api/pull` alongside this server's
+    /// own `/v1/*` routes. Off by default, the same opt-in posture
+    /// `[pipelines]`/`[jobs]`/`[agent]` take toward their own surfaces.
+    pub ollama_compat_enabled: bool,
+    /// Whether `main` broadcasts a `discovery::Announcement` over UDP
+    /// every `discovery_interval_seconds` so a `discover` CLI run on the
+    /// same LAN segment finds this instance without being told its
+    /// address up front. Off by default, the same opt-in posture
+    /// `[pipelines]`/`[jobs]`/`[agent]` take toward their own surfaces.
+    pub discovery_enabled: bool,
+    /// How often the discovery broadcast repeats, in seconds.
+    pub discovery_interval_seconds: usize,
+    /// The `name` field advertised in each broadcast. Defaults to
+    /// `"ai-server"` when absent, the same "sensible default, operator
+    /// override" shape `mock_default_response` uses.
+    pub discovery_name: Option<String>,
+    /// Whether connections are terminated over TLS via `tls::TlsAcceptor`
+    /// instead of plain TCP. Off by default — see `tls.rs`'s module doc
+    /// comment for why turning this on doesn't yet change what `main`'s
+    /// accept loop actually does.
+    pub tls_enabled: bool,
+    /// PEM certificate chain path, required when `tls_enabled` is set.
+    pub tls_cert_path: Option<String>,
+    /// PEM private key path, required when `tls_enabled` is set.
+    pub tls_key_path: Option<String>,
+    /// Whether client connections must present a certificate signed by
+    /// `tls_client_ca_path` — `tls::TlsConfig`'s mTLS mode.
+    pub tls_mtls_enabled: bool,
+    /// PEM CA bundle used to verify client certificates, required when
+    /// `tls_mtls_enabled` is set.
+    pub tls_client_ca_path: Option<String>,
+    /// When set, `main` also accepts connections on this Unix domain
+    /// socket path (in addition to `server.host`/`server.port`), wrapped
+    /// in the same `transport::Transport` the TCP listener uses — a local
+    /// desktop app talking to this instance over the socket never opens a
+    /// network port at all. Unix-only; see `transport.rs`'s module doc
+    /// comment for why there's no Windows named-pipe equivalent.
+    pub unix_socket_path: Option<String>,
+    /// Filesystem permissions applied to `unix_socket_path` after
+    /// binding, as an octal string (e.g. `"600"`). Defaults to owner-only
+    /// access, the same restrictive default `agent_file_root` and
+    /// `plugins_wasmtime_path` take toward paths an operator can loosen
+    /// themselves.
+    pub unix_socket_permissions: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:8080".to_string(),
+            rpc_bind_address: "127.0.0.1:8081".to_string(),
+            max_batch_size: 8,
+            max_context_tokens: 4096,
+            context_overflow_policy: "error".to_string(),
+            otlp_endpoint: None,
+            models_dir: "./models".to_string(),
+            model_idle_timeout_seconds: 900,
+            warmup_runs: 0,
+            warmup_prompt: "warmup".to_string(),
+            strict_model_verification: false,
+            max_cache_bytes: 0,
+            rope_scaling_by_model: Vec::new(),
+            api_keys: Vec::new(),
+            requests_per_minute: 60,
+            daily_token_quota: 0,
+            default_max_output_tokens: 256,
+            default_request_timeout_seconds: 60,
+            max_output_tokens_by_key: Vec::new(),
+            request_timeout_by_key_seconds: Vec::new(),
+            admin_keys: Vec::new(),
+            log_file: "./ai-server.log".to_string(),
+            log_max_bytes: 100 * 1024 * 1024,
+            log_pretty: false,
+            readiness_check_timeout_ms: 2000,
+            shutdown_drain_timeout_seconds: 30,
+            response_cache_ttl_seconds: 300,
+            response_cache_max_entries: 1000,
+            embedding_cache_max_entries: 10000,
+            idempotency_key_ttl_seconds: 300,
+            batch_priority_keys: Vec::new(),
+            background_priority_keys: Vec::new(),
+            scheduler_batch_queue_limit: 64,
+            scheduler_background_queue_limit: 256,
+            router_nodes: Vec::new(),
+            backend_override: None,
+            flash_attention_enabled: false,
+            tensor_split_override: Vec::new(),
+            n_gpu_layers_override: None,
+            replay_mode: None,
+            replay_file: None,
+            mock_backend_enabled: false,
+            mock_default_response: None,
+            mock_latency_ms: None,
+            tenant_keys: Vec::new(),
+            tenant_models: Vec::new(),
+            model_aliases: Vec::new(),
+            audit_enabled: false,
+            audit_sink: "file".to_string(),
+            audit_file: "./audit.log".to_string(),
+            audit_syslog_addr: String::new(),
+            audit_include_bodies: false,
+            audit_redact_patterns: Vec::new(),
+            guardrails_enabled: false,
+            guardrails_block_patterns: Vec::new(),
+            guardrails_redact_patterns: Vec::new(),
+            guardrails_annotate_patterns: Vec::new(),
+            guardrails_classifier_prompt: String::new(),
+            guardrails_classifier_action: "annotate".to_string(),
+            plugins_enabled: false,
+            plugins_dir: "./plugins".to_string(),
+            plugins_wasmtime_path: "wasmtime".to_string(),
+            plugins_reload_interval_seconds: 5,
+            mcp_enabled: false,
+            mcp_client_servers: Vec::new(),
+            mcp_client_timeout_ms: 2000,
+            mcp_client_refresh_interval_seconds: 30,
+            agent_enabled: false,
+            agent_shell_allowlist: Vec::new(),
+            agent_http_allowlist: Vec::new(),
+            agent_file_root: "./agent-workspace".to_string(),
+            agent_max_steps: 10,
+            pipelines_enabled: false,
+            pipelines_dir: "./pipelines".to_string(),
+            pipelines_reload_interval_seconds: 5,
+            jobs_enabled: false,
+            jobs_dir: "./jobs".to_string(),
+            watcher_enabled: false,
+            watcher_dir: "./watched-docs".to_string(),
+            watcher_collection: "watched-docs".to_string(),
+            watcher_poll_interval_seconds: 30,
+            ollama_compat_enabled: false,
+            discovery_enabled: false,
+            discovery_interval_seconds: 5,
+            discovery_name: None,
+            tls_enabled: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+            tls_mtls_enabled: false,
+            tls_client_ca_path: None,
+            unix_socket_path: None,
+            unix_socket_permissions: "600".to_string(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Builds a config from TOML text layered over the defaults, using
+    /// keys under `[server]` (`bind_address`, `rpc_bind_address`,
+    /// `max_batch_size`, `max_context_tokens`, `context_overflow_policy`), `[tracing]`
+    /// (`otlp_host`, `otlp_port`), `[models]` (`models_dir`,
+    /// `idle_timeout_seconds`, `warmup_runs`, `warmup_prompt`,
+    /// `strict_verification`, `max_cache_bytes`, `rope_scaling_by_model`,
+    /// comma-separated `id=mode:factor` entries), `[auth]` (`api_keys`, a comma-separated
+    /// list, `requests_per_minute`, `daily_token_quota`, `default_max_output_tokens`,
+    /// `default_request_timeout_seconds`, `max_output_tokens_by_key` and
+    /// `request_timeout_by_key_seconds` (both comma-separated `key=value`
+    /// entries — see `auth::GenerationLimits`), `admin_keys`, also
+    /// comma-separated), `[logging]` (`log_file`, `log_max_bytes`,
+    /// `log_pretty`), `[health]` (`readiness_check_timeout_ms`),
+    /// `[shutdown]` (`drain_timeout_seconds`), `[response_cache]`
+    /// (`ttl_seconds`, `max_entries`, `idempotency_key_ttl_seconds`),
+    /// `[embedding_cache]` (`max_entries`), and `[scheduler]`
+    /// (`batch_priority_keys`, `background_priority_keys`, both
+    /// comma-separated, `batch_queue_limit`, `background_queue_limit`), and
+    /// `[router]` (`nodes`, comma-separated `id=host:port` entries),
+    /// `[backend]` (`override`, one of `backend::Backend`'s `as_str()` values,
+    /// `flash_attention_enabled`, `tensor_split_override`, a
+    /// comma-separated list of non-negative shares, and
+    /// `n_gpu_layers_override`, an integer),
+    /// `[replay]` (`mode`, one of `"record"`/`"replay"`, and `file`, a path —
+    /// see `replay_backend.rs`),
+    /// `[mock]` (`enabled`, `default_response`, and `latency_ms` — see
+    /// `mock_backend.rs`),
+    /// and `[tenancy]` (`tenant_keys`, comma-separated `key=tenant` entries,
+    /// `tenant_models`, comma-separated `tenant:model1|model2` entries),
+    /// `[routing]` (`model_aliases`, comma-separated
+    /// `alias:model1=70|model2=30` entries, optionally with a
+    /// `|shadow=model3` target — see `model_alias.rs`), and
+    /// `[audit]` (`enabled`, `sink` — `"file"` or `"syslog"` — `file`,
+    /// `syslog_addr`, `include_bodies`, `redact_patterns`, comma-separated),
+    /// and `[guardrails]` (`enabled`, `block_patterns`, `redact_patterns`,
+    /// `annotate_patterns` — each comma-separated — `classifier_prompt`,
+    /// `classifier_action`), and `[plugins]` (`enabled`, `dir`,
+    /// `wasmtime_path`, `reload_interval_seconds`), and `[mcp]` (`enabled`,
+    /// `client_servers` — comma-separated `name=host:port` entries —
+    /// `client_timeout_ms`, `client_refresh_interval_seconds`), and
+    /// `[agent]` (`enabled`, `shell_allowlist`, `http_allowlist` — both
+    /// comma-separated — `file_root`, `max_steps`), and `[pipelines]`
+    /// (`enabled`, `dir`, `reload_interval_seconds`), and `[jobs]`
+    /// (`enabled`, `dir`), and `[watcher]` (`enabled`, `dir`, `collection`,
+    /// `poll_interval_seconds`), and `[ollama]` (`enabled` — see
+    /// `server::route`'s Ollama-compatibility routes), and `[discovery]`
+    /// (`enabled`, `interval_seconds`, `name` — see `discovery.rs`), and
+    /// `[tls]` (`enabled`, `cert_path`, `key_path`, `mtls_enabled`,
+    /// `client_ca_path` — see `tls.rs`), and `[server]`'s
+    /// `unix_socket_path`/`unix_socket_permissions` (see `transport.rs`).
+    pub fn from_toml_str(text: &str) -> Result<ServerConfig, ConfigError> {
+        let values = parse_toml(text)?;
+        let mut config = ServerConfig::default();
+
+        if let Some(v) = values.get("server.bind_address") {
+            config.bind_address = expect_string("server.bind_address", v)?;
+        }
+        if let Some(v) = values.get("server.rpc_bind_address") {
+            config.rpc_bind_address = expect_string("server.rpc_bind_address", v)?;
+        }
+        if let Some(v) = values.get("server.max_batch_size") {
+            config.max_batch_size = expect_integer("server.max_batch_size", v)?;
+        }
+        if let Some(v) = values.get("server.max_context_tokens") {
+            config.max_context_tokens = expect_integer("server.max_context_tokens", v)?;
+        }
+        if let Some(v) = values.get("server.context_overflow_policy") {
+            config.context_overflow_policy = expect_string("server.context_overflow_policy", v)?;
+        }
+        let otlp_host = values.get("tracing.otlp_host").map(|v| expect_string("tracing.otlp_host", v)).transpose()?;
+        let otlp_port = values.get("tracing.otlp_port").map(|v| expect_integer("tracing.otlp_port", v)).transpose()?;
+        if let (Some(host), Some(port)) = (otlp_host, otlp_port) {
+            config.otlp_endpoint = Some((host, port as u16));
+        }
+        if let Some(v) = values.get("models.models_dir") {
+            config.models_dir = expect_string("models.models_dir", v)?;
+        }
+        if let Some(v) = values.get("models.idle_timeout_seconds") {
+            config.model_idle_timeout_seconds = expect_integer("models.idle_timeout_seconds", v)?;
+        }
+        if let Some(v) = values.get("models.warmup_runs") {
+            config.warmup_runs = expect_integer("models.warmup_runs", v)?;
+        }
+        if let Some(v) = values.get("models.warmup_prompt") {
+            config.warmup_prompt = expect_string("models.warmup_prompt", v)?;
+        }
+        if let Some(v) = values.get("models.strict_verification") {
+            config.strict_model_verification = expect_bool("models.strict_verification", v)?;
+        }
+        if let Some(v) = values.get("models.max_cache_bytes") {
+            config.max_cache_bytes = expect_integer("models.max_cache_bytes", v)? as u64;
+        }
+        if let Some(v) = values.get("models.rope_scaling_by_model") {
+            config.rope_scaling_by_model = split_api_keys(&expect_string("models.rope_scaling_by_model", v)?);
+        }
+        if let Some(v) = values.get("auth.api_keys") {
+            config.api_keys = split_api_keys(&expect_string("auth.api_keys", v)?);
+        }
+        if let Some(v) = values.get("auth.requests_per_minute") {
+            config.requests_per_minute = expect_integer("auth.requests_per_minute", v)?;
+        }
+        if let Some(v) = values.get("auth.daily_token_quota") {
+            config.daily_token_quota = expect_integer("auth.daily_token_quota", v)?;
+        }
+        if let Some(v) = values.get("auth.default_max_output_tokens") {
+            config.default_max_output_tokens = expect_integer("auth.default_max_output_tokens", v)?;
+        }
+        if let Some(v) = values.get("auth.default_request_timeout_seconds") {
+            config.default_request_timeout_seconds = expect_integer("auth.default_request_timeout_seconds", v)? as u64;
+        }
+        if let Some(v) = values.get("auth.max_output_tokens_by_key") {
+            config.max_output_tokens_by_key = split_api_keys(&expect_string("auth.max_output_tokens_by_key", v)?);
+        }
+        if let Some(v) = values.get("auth.request_timeout_by_key_seconds") {
+            config.request_timeout_by_key_seconds = split_api_keys(&expect_string("auth.request_timeout_by_key_seconds", v)?);
+        }
+        if let Some(v) = values.get("auth.admin_keys") {
+            config.admin_keys = split_api_keys(&expect_string("auth.admin_keys", v)?);
+        }
+        if let Some(v) = values.get("logging.log_file") {
+            config.log_file = expect_string("logging.log_file", v)?;
+        }
+        if let Some(v) = values.get("logging.log_max_bytes") {
+            config.log_max_bytes = expect_integer("logging.log_max_bytes", v)?;
+        }
+        if let Some(v) = values.get("logging.log_pretty") {
+            config.log_pretty = expect_bool("logging.log_pretty", v)?;
+        }
+        if let Some(v) = values.get("health.readiness_check_timeout_ms") {
+            config.readiness_check_timeout_ms = expect_integer("health.readiness_check_timeout_ms", v)?;
+        }
+        if let Some(v) = values.get("shutdown.drain_timeout_seconds") {
+            config.shutdown_drain_timeout_seconds = expect_integer("shutdown.drain_timeout_seconds", v)?;
+        }
+        if let Some(v) = values.get("response_cache.ttl_seconds") {
+            config.response_cache_ttl_seconds = expect_integer("response_cache.ttl_seconds", v)?;
+        }
+        if let Some(v) = values.get("response_cache.max_entries") {
+            config.response_cache_max_entries = expect_integer("response_cache.max_entries", v)?;
+        }
+        if let Some(v) = values.get("embedding_cache.max_entries") {
+            config.embedding_cache_max_entries = expect_integer("embedding_cache.max_entries", v)?;
+        }
+        if let Some(v) = values.get("response_cache.idempotency_key_ttl_seconds") {
+            config.idempotency_key_ttl_seconds = expect_integer("response_cache.idempotency_key_ttl_seconds", v)?;
+        }
+        if let Some(v) = values.get("scheduler.batch_priority_keys") {
+            config.batch_priority_keys = split_api_keys(&expect_string("scheduler.batch_priority_keys", v)?);
+        }
+        if let Some(v) = values.get("scheduler.background_priority_keys") {
+            config.background_priority_keys = split_api_keys(&expect_string("scheduler.background_priority_keys", v)?);
+        }
+        if let Some(v) = values.get("scheduler.batch_queue_limit") {
+            config.scheduler_batch_queue_limit = expect_integer("scheduler.batch_queue_limit", v)?;
+        }
+        if let Some(v) = values.get("scheduler.background_queue_limit") {
+            config.scheduler_background_queue_limit = expect_integer("scheduler.background_queue_limit", v)?;
+        }
+        if let Some(v) = values.get("router.nodes") {
+            config.router_nodes = split_api_keys(&expect_string("router.nodes", v)?);
+        }
+        if let Some(v) = values.get("backend.override") {
+            config.backend_override = Some(expect_string("backend.override", v)?);
+        }
+        if let Some(v) = values.get("backend.flash_attention_enabled") {
+            config.flash_attention_enabled = expect_bool("backend.flash_attention_enabled", v)?;
+        }
+        if let Some(v) = values.get("backend.tensor_split_override") {
+            config.tensor_split_override = split_api_keys(&expect_string("backend.tensor_split_override", v)?);
+        }
+        if let Some(v) = values.get("backend.n_gpu_layers_override") {
+            config.n_gpu_layers_override = Some(expect_integer("backend.n_gpu_layers_override", v)?);
+        }
+        if let Some(v) = values.get("replay.mode") {
+            config.replay_mode = Some(expect_string("replay.mode", v)?);
+        }
+        if let Some(v) = values.get("replay.file") {
+            config.replay_file = Some(expect_string("replay.file", v)?);
+        }
+        if let Some(v) = values.get("mock.enabled") {
+            config.mock_backend_enabled = expect_bool("mock.enabled", v)?;
+        }
+        if let Some(v) = values.get("mock.default_response") {
+            config.mock_default_response = Some(expect_string("mock.default_response", v)?);
+        }
+        if let Some(v) = values.get("mock.latency_ms") {
+            config.mock_latency_ms = Some(expect_integer("mock.latency_ms", v)?);
+        }
+        if let Some(v) = values.get("tenancy.tenant_keys") {
+            config.tenant_keys = split_api_keys(&expect_string("tenancy.tenant_keys", v)?);
+        }
+        if let Some(v) = values.get("tenancy.tenant_models") {
+            config.tenant_models = split_api_keys(&expect_string("tenancy.tenant_models", v)?);
+        }
+        if let Some(v) = values.get("routing.model_aliases") {
+            config.model_aliases = split_api_keys(&expect_string("routing.model_aliases", v)?);
+        }
+        if let Some(v) = values.get("audit.enabled") {
+            config.audit_enabled = expect_bool("audit.enabled", v)?;
+        }
+        if let Some(v) = values.get("audit.sink") {
+            config.audit_sink = expect_string("audit.sink", v)?;
+        }
+        if let Some(v) = values.get("audit.file") {
+            config.audit_file = expect_string("audit.file", v)?;
+        }
+        if let Some(v) = values.get("audit.syslog_addr") {
+            config.audit_syslog_addr = expect_string("audit.syslog_addr", v)?;
+        }
+        if let Some(v) = values.get("audit.include_bodies") {
+            config.audit_include_bodies = expect_bool("audit.include_bodies", v)?;
+        }
+        if let Some(v) = values.get("audit.redact_patterns") {
+            config.audit_redact_patterns = split_api_keys(&expect_string("audit.redact_patterns", v)?);
+        }
+        if let Some(v) = values.get("guardrails.enabled") {
+            config.guardrails_enabled = expect_bool("guardrails.enabled", v)?;
+        }
+        if let Some(v) = values.get("guardrails.block_patterns") {
+            config.guardrails_block_patterns = split_api_keys(&expect_string("guardrails.block_patterns", v)?);
+        }
+        if let Some(v) = values.get("guardrails.redact_patterns") {
+            config.guardrails_redact_patterns = split_api_keys(&expect_string("guardrails.redact_patterns", v)?);
+        }
+        if let Some(v) = values.get("guardrails.annotate_patterns") {
+            config.guardrails_annotate_patterns = split_api_keys(&expect_string("guardrails.annotate_patterns", v)?);
+        }
+        if let Some(v) = values.get("guardrails.classifier_prompt") {
+            config.guardrails_classifier_prompt = expect_string("guardrails.classifier_prompt", v)?;
+        }
+        if let Some(v) = values.get("guardrails.classifier_action") {
+            config.guardrails_classifier_action = expect_string("guardrails.classifier_action", v)?;
+        }
+        if let Some(v) = values.get("plugins.enabled") {
+            config.plugins_enabled = expect_bool("plugins.enabled", v)?;
+        }
+        if let Some(v) = values.get("plugins.dir") {
+            config.plugins_dir = expect_string("plugins.dir", v)?;
+        }
+        if let Some(v) = values.get("plugins.wasmtime_path") {
+            config.plugins_wasmtime_path = expect_string("plugins.wasmtime_path", v)?;
+        }
+        if let Some(v) = values.get("plugins.reload_interval_seconds") {
+            config.plugins_reload_interval_seconds = expect_integer("plugins.reload_interval_seconds", v)?;
+        }
+        if let Some(v) = values.get("mcp.enabled") {
+            config.mcp_enabled = expect_bool("mcp.enabled", v)?;
+        }
+        if let Some(v) = values.get("mcp.client_servers") {
+            config.mcp_client_servers = split_api_keys(&expect_string("mcp.client_servers", v)?);
+        }
+        if let Some(v) = values.get("mcp.client_timeout_ms") {
+            config.mcp_client_timeout_ms = expect_integer("mcp.client_timeout_ms", v)?;
+        }
+        if let Some(v) = values.get("mcp.client_refresh_interval_seconds") {
+            config.mcp_client_refresh_interval_seconds = expect_integer("mcp.client_refresh_interval_seconds", v)?;
+        }
+        if let Some(v) = values.get("agent.enabled") {
+            config.agent_enabled = expect_bool("agent.enabled", v)?;
+        }
+        if let Some(v) = values.get("agent.shell_allowlist") {
+            config.agent_shell_allowlist = split_api_keys(&expect_string("agent.shell_allowlist", v)?);
+        }
+        if let Some(v) = values.get("agent.http_allowlist") {
+            config.agent_http_allowlist = split_api_keys(&expect_string("agent.http_allowlist", v)?);
+        }
+        if let Some(v) = values.get("agent.file_root") {
+            config.agent_file_root = expect_string("agent.file_root", v)?;
+        }
+        if let Some(v) = values.get("agent.max_steps") {
+            config.agent_max_steps = expect_integer("agent.max_steps", v)?;
+        }
+        if let Some(v) = values.get("pipelines.enabled") {
+            config.pipelines_enabled = expect_bool("pipelines.enabled", v)?;
+        }
+        if let Some(v) = values.get("pipelines.dir") {
+            config.pipelines_dir = expect_string("pipelines.dir", v)?;
+        }
+        if let Some(v) = values.get("pipelines.reload_interval_seconds") {
+            config.pipelines_reload_interval_seconds = expect_integer("pipelines.reload_interval_seconds", v)?;
+        }
+        if let Some(v) = values.get("jobs.enabled") {
+            config.jobs_enabled = expect_bool("jobs.enabled", v)?;
+        }
+        if let Some(v) = values.get("jobs.dir") {
+            config.jobs_dir = expect_string("jobs.dir", v)?;
+        }
+        if let Some(v) = values.get("watcher.enabled") {
+            config.watcher_enabled = expect_bool("watcher.enabled", v)?;
+        }
+        if let Some(v) = values.get("watcher.dir") {
+            config.watcher_dir = expect_string("watcher.dir", v)?;
+        }
+        if let Some(v) = values.get("watcher.collection") {
+            config.watcher_collection = expect_string("watcher.collection", v)?;
+        }
+        if let Some(v) = values.get("watcher.poll_interval_seconds") {
+            config.watcher_poll_interval_seconds = expect_integer("watcher.poll_interval_seconds", v)?;
+        }
+        if let Some(v) = values.get("ollama.enabled") {
+            config.ollama_compat_enabled = expect_bool("ollama.enabled", v)?;
+        }
+        if let Some(v) = values.get("discovery.enabled") {
+            config.discovery_enabled = expect_bool("discovery.enabled", v)?;
+        }
+        if let Some(v) = values.get("discovery.interval_seconds") {
+            config.discovery_interval_seconds = expect_integer("discovery.interval_seconds", v)?;
+        }
+        if let Some(v) = values.get("discovery.name") {
+            config.discovery_name = Some(expect_string("discovery.name", v)?);
+        }
+        if let Some(v) = values.get("tls.enabled") {
+            config.tls_enabled = expect_bool("tls.enabled", v)?;
+        }
+        if let Some(v) = values.get("tls.cert_path") {
+            config.tls_cert_path = Some(expect_string("tls.cert_path", v)?);
+        }
+        if let Some(v) = values.get("tls.key_path") {
+            config.tls_key_path = Some(expect_string("tls.key_path", v)?);
+        }
+        if let Some(v) = values.get("tls.mtls_enabled") {
+            config.tls_mtls_enabled = expect_bool("tls.mtls_enabled", v)?;
+        }
+        if let Some(v) = values.get("tls.client_ca_path") {
+            config.tls_client_ca_path = Some(expect_string("tls.client_ca_path", v)?);
+        }
+        if let Some(v) = values.get("server.unix_socket_path") {
+            config.unix_socket_path = Some(expect_string("server.unix_socket_path", v)?);
+        }
+        if let Some(v) = values.get("server.unix_socket_permissions") {
+            config.unix_socket_permissions = expect_string("server.unix_socket_permissions", v)?;
+        }
+
+        config.apply_env_overrides();
+        config.validate()
+    }
+
+    /// Reads and parses the config file at `path`.
+    pub fn load(path: &Path) -> Result<ServerConfig, ConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        ServerConfig::from_toml_str(&text)
+    }
+
+    /// Like [`load`](Self::load), but treats a missing file as an empty
+    /// config (defaults plus env overrides) rather than an error — the
+    /// common case for a fresh deployment that hasn't written one yet.
+    pub fn load_or_default(path: &Path) -> Result<ServerConfig, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => ServerConfig::from_toml_str(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ServerConfig::from_toml_str(""),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Overrides fields from `AI_SERVER_*` environment variables, taking
+    /// precedence over whatever the TOML file set — the usual
+    /// file-then-env layering so a container can tweak one setting
+    /// without templating the whole file.
+    fn apply_env_overrides(&mut self) {
+        if let Ok(v) = std::env::var("AI_SERVER_BIND_ADDRESS") {
+            self.bind_address = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_RPC_BIND_ADDRESS") {
+            self.rpc_bind_address = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MAX_BATCH_SIZE") {
+            if let Ok(n) = v.parse() {
+                self.max_batch_size = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MAX_CONTEXT_TOKENS") {
+            if let Ok(n) = v.parse() {
+                self.max_context_tokens = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_CONTEXT_OVERFLOW_POLICY") {
+            self.context_overflow_policy = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MODELS_DIR") {
+            self.models_dir = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MODEL_IDLE_TIMEOUT_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.model_idle_timeout_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MODEL_WARMUP_RUNS") {
+            if let Ok(n) = v.parse() {
+                self.warmup_runs = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MODEL_WARMUP_PROMPT") {
+            self.warmup_prompt = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_STRICT_MODEL_VERIFICATION") {
+            if let Ok(b) = v.parse() {
+                self.strict_model_verification = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MAX_CACHE_BYTES") {
+            if let Ok(n) = v.parse() {
+                self.max_cache_bytes = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_ROPE_SCALING_BY_MODEL") {
+            self.rope_scaling_by_model = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_API_KEYS") {
+            self.api_keys = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_REQUESTS_PER_MINUTE") {
+            if let Ok(n) = v.parse() {
+                self.requests_per_minute = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DAILY_TOKEN_QUOTA") {
+            if let Ok(n) = v.parse() {
+                self.daily_token_quota = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DEFAULT_MAX_OUTPUT_TOKENS") {
+            if let Ok(n) = v.parse() {
+                self.default_max_output_tokens = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DEFAULT_REQUEST_TIMEOUT_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.default_request_timeout_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MAX_OUTPUT_TOKENS_BY_KEY") {
+            self.max_output_tokens_by_key = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_REQUEST_TIMEOUT_BY_KEY_SECONDS") {
+            self.request_timeout_by_key_seconds = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_ADMIN_KEYS") {
+            self.admin_keys = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_LOG_FILE") {
+            self.log_file = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_LOG_MAX_BYTES") {
+            if let Ok(n) = v.parse() {
+                self.log_max_bytes = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_LOG_PRETTY") {
+            if let Ok(b) = v.parse() {
+                self.log_pretty = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_READINESS_CHECK_TIMEOUT_MS") {
+            if let Ok(n) = v.parse() {
+                self.readiness_check_timeout_ms = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_SHUTDOWN_DRAIN_TIMEOUT_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.shutdown_drain_timeout_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_RESPONSE_CACHE_TTL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.response_cache_ttl_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_RESPONSE_CACHE_MAX_ENTRIES") {
+            if let Ok(n) = v.parse() {
+                self.response_cache_max_entries = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_EMBEDDING_CACHE_MAX_ENTRIES") {
+            if let Ok(n) = v.parse() {
+                self.embedding_cache_max_entries = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_IDEMPOTENCY_KEY_TTL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.idempotency_key_ttl_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_BATCH_PRIORITY_KEYS") {
+            self.batch_priority_keys = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_BACKGROUND_PRIORITY_KEYS") {
+            self.background_priority_keys = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_SCHEDULER_BATCH_QUEUE_LIMIT") {
+            if let Ok(n) = v.parse() {
+                self.scheduler_batch_queue_limit = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_SCHEDULER_BACKGROUND_QUEUE_LIMIT") {
+            if let Ok(n) = v.parse() {
+                self.scheduler_background_queue_limit = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_ROUTER_NODES") {
+            self.router_nodes = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_BACKEND_OVERRIDE") {
+            self.backend_override = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_FLASH_ATTENTION_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.flash_attention_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TENSOR_SPLIT_OVERRIDE") {
+            self.tensor_split_override = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_N_GPU_LAYERS_OVERRIDE") {
+            if let Ok(n) = v.parse() {
+                self.n_gpu_layers_override = Some(n);
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_REPLAY_MODE") {
+            self.replay_mode = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_REPLAY_FILE") {
+            self.replay_file = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MOCK_BACKEND_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.mock_backend_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MOCK_DEFAULT_RESPONSE") {
+            self.mock_default_response = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MOCK_LATENCY_MS") {
+            if let Ok(n) = v.parse() {
+                self.mock_latency_ms = Some(n);
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TENANT_KEYS") {
+            self.tenant_keys = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TENANT_MODELS") {
+            self.tenant_models = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MODEL_ALIASES") {
+            self.model_aliases = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.audit_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_SINK") {
+            self.audit_sink = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_FILE") {
+            self.audit_file = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_SYSLOG_ADDR") {
+            self.audit_syslog_addr = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_INCLUDE_BODIES") {
+            if let Ok(b) = v.parse() {
+                self.audit_include_bodies = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AUDIT_REDACT_PATTERNS") {
+            self.audit_redact_patterns = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.guardrails_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_BLOCK_PATTERNS") {
+            self.guardrails_block_patterns = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_REDACT_PATTERNS") {
+            self.guardrails_redact_patterns = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_ANNOTATE_PATTERNS") {
+            self.guardrails_annotate_patterns = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_CLASSIFIER_PROMPT") {
+            self.guardrails_classifier_prompt = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_GUARDRAILS_CLASSIFIER_ACTION") {
+            self.guardrails_classifier_action = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PLUGINS_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.plugins_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PLUGINS_DIR") {
+            self.plugins_dir = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PLUGINS_WASMTIME_PATH") {
+            self.plugins_wasmtime_path = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PLUGINS_RELOAD_INTERVAL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.plugins_reload_interval_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MCP_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.mcp_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MCP_CLIENT_SERVERS") {
+            self.mcp_client_servers = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MCP_CLIENT_TIMEOUT_MS") {
+            if let Ok(n) = v.parse() {
+                self.mcp_client_timeout_ms = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_MCP_CLIENT_REFRESH_INTERVAL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.mcp_client_refresh_interval_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AGENT_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.agent_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AGENT_SHELL_ALLOWLIST") {
+            self.agent_shell_allowlist = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AGENT_HTTP_ALLOWLIST") {
+            self.agent_http_allowlist = split_api_keys(&v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AGENT_FILE_ROOT") {
+            self.agent_file_root = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_AGENT_MAX_STEPS") {
+            if let Ok(n) = v.parse() {
+                self.agent_max_steps = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PIPELINES_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.pipelines_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PIPELINES_DIR") {
+            self.pipelines_dir = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_PIPELINES_RELOAD_INTERVAL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.pipelines_reload_interval_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_JOBS_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.jobs_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_JOBS_DIR") {
+            self.jobs_dir = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_WATCHER_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.watcher_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_WATCHER_DIR") {
+            self.watcher_dir = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_WATCHER_COLLECTION") {
+            self.watcher_collection = v;
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_WATCHER_POLL_INTERVAL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.watcher_poll_interval_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_OLLAMA_COMPAT_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.ollama_compat_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DISCOVERY_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.discovery_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DISCOVERY_INTERVAL_SECONDS") {
+            if let Ok(n) = v.parse() {
+                self.discovery_interval_seconds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_DISCOVERY_NAME") {
+            self.discovery_name = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TLS_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.tls_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TLS_CERT_PATH") {
+            self.tls_cert_path = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TLS_KEY_PATH") {
+            self.tls_key_path = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TLS_MTLS_ENABLED") {
+            if let Ok(b) = v.parse() {
+                self.tls_mtls_enabled = b;
+            }
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_TLS_CLIENT_CA_PATH") {
+            self.tls_client_ca_path = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_UNIX_SOCKET_PATH") {
+            self.unix_socket_path = Some(v);
+        }
+        if let Ok(v) = std::env::var("AI_SERVER_UNIX_SOCKET_PERMISSIONS") {
+            self.unix_socket_permissions = v;
+        }
+    }
+
+    fn validate(self) -> Result<ServerConfig, ConfigError> {
+        if self.max_batch_size == 0 {
+            return Err(ConfigError::InvalidValue { key: "server.max_batch_size".to_string(), message: "must be at least 1".to_string() });
+        }
+        if self.max_context_tokens == 0 {
+            return Err(ConfigError::InvalidValue { key: "server.max_context_tokens".to_string(), message: "must be at least 1".to_string() });
+        }
+        if ContextPolicy::parse(&self.context_overflow_policy).is_none() {
+            return Err(ConfigError::InvalidValue {
+                key: "server.context_overflow_policy".to_string(),
+                message: "must be one of \"error\", \"truncate\", \"slide\"".to_string(),
+            });
+        }
+        for entry in &self.router_nodes {
+            let Some((id, address)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "router.nodes".to_string(),
+                    message: format!("entry {entry:?} must be written as \"id=host:port\""),
+                });
+            };
+            if id.is_empty() || address.is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    key: "router.nodes".to_string(),
+                    message: format!("entry {entry:?} must be written as \"id=host:port\""),
+                });
+            }
+        }
+        for entry in &self.max_output_tokens_by_key {
+            let Some((key, tokens)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "auth.max_output_tokens_by_key".to_string(),
+                    message: format!("entry {entry:?} must be written as \"key=tokens\""),
+                });
+            };
+            if key.is_empty() || tokens.parse::<usize>().map_or(true, |n| n == 0) {
+                return Err(ConfigError::InvalidValue {
+                    key: "auth.max_output_tokens_by_key".to_string(),
+                    message: format!("entry {entry:?}: tokens for {key:?} must be a positive integer"),
+                });
+            }
+        }
+        for entry in &self.rope_scaling_by_model {
+            let Some((id, scaling)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "models.rope_scaling_by_model".to_string(),
+                    message: format!("entry {entry:?} must be written as \"id=mode:factor\""),
+                });
+            };
+            let Some((mode, factor)) = scaling.split_once(':') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "models.rope_scaling_by_model".to_string(),
+                    message: format!("entry {entry:?} must be written as \"id=mode:factor\""),
+                });
+            };
+            if id.is_empty() || RopeScaling::parse(mode).is_none() || factor.parse::<f64>().map_or(true, |f| f <= 0.0) {
+                return Err(ConfigError::InvalidValue {
+                    key: "models.rope_scaling_by_model".to_string(),
+                    message: format!(
+                        "entry {entry:?}: mode for {id:?} must be one of \"none\", \"linear\", \"ntk\", \"yarn\" with a positive factor"
+                    ),
+                });
+            }
+        }
+        for entry in &self.request_timeout_by_key_seconds {
+            let Some((key, seconds)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "auth.request_timeout_by_key_seconds".to_string(),
+                    message: format!("entry {entry:?} must be written as \"key=seconds\""),
+                });
+            };
+            if key.is_empty() || seconds.parse::<u64>().map_or(true, |n| n == 0) {
+                return Err(ConfigError::InvalidValue {
+                    key: "auth.request_timeout_by_key_seconds".to_string(),
+                    message: format!("entry {entry:?}: seconds for {key:?} must be a positive integer"),
+                });
+            }
+        }
+        for entry in &self.tenant_keys {
+            let Some((key, tenant)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "tenancy.tenant_keys".to_string(),
+                    message: format!("entry {entry:?} must be written as \"key=tenant\""),
+                });
+            };
+            if key.is_empty() || tenant.is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    key: "tenancy.tenant_keys".to_string(),
+                    message: format!("entry {entry:?} must be written as \"key=tenant\""),
+                });
+            }
+        }
+        for entry in &self.tenant_models {
+            let Some((tenant, models)) = entry.split_once(':') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "tenancy.tenant_models".to_string(),
+                    message: format!("entry {entry:?} must be written as \"tenant:model1|model2\""),
+                });
+            };
+            if tenant.is_empty() || models.is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    key: "tenancy.tenant_models".to_string(),
+                    message: format!("entry {entry:?} must be written as \"tenant:model1|model2\""),
+                });
+            }
+        }
+        for entry in &self.model_aliases {
+            let malformed = || ConfigError::InvalidValue {
+                key: "routing.model_aliases".to_string(),
+                message: format!("entry {entry:?} must be written as \"alias:model1=70|model2=30\", optionally with a \"|shadow=model3\" target"),
+            };
+            let (alias, spec) = entry.split_once(':').ok_or_else(malformed)?;
+            if alias.is_empty() || spec.is_empty() {
+                return Err(malformed());
+            }
+            for token in spec.split('|') {
+                let (key, value) = token.split_once('=').ok_or_else(malformed)?;
+                if key.is_empty() || value.is_empty() {
+                    return Err(malformed());
+                }
+                if key != "shadow" && value.parse::<u32>().map_or(true, |weight| weight == 0) {
+                    return Err(ConfigError::InvalidValue {
+                        key: "routing.model_aliases".to_string(),
+                        message: format!("entry {entry:?}: weight for {key:?} must be a positive integer"),
+                    });
+                }
+            }
+        }
+        if let Some(backend) = &self.backend_override {
+            if Backend::parse(backend).is_none() {
+                return Err(ConfigError::InvalidValue {
+                    key: "backend.override".to_string(),
+                    message: "must be one of \"metal\", \"cuda\", \"vulkan\", \"cpu-neon\", \"cpu-scalar\"".to_string(),
+                });
+            }
+        }
+        for entry in &self.tensor_split_override {
+            if entry.parse::<f32>().map_or(true, |share| share < 0.0) {
+                return Err(ConfigError::InvalidValue {
+                    key: "backend.tensor_split_override".to_string(),
+                    message: format!("entry {entry:?} must be a non-negative number"),
+                });
+            }
+        }
+        if let Some(mode) = &self.replay_mode {
+            match mode.as_str() {
+                "record" | "replay" => {}
+                _ => {
+                    return Err(ConfigError::InvalidValue {
+                        key: "replay.mode".to_string(),
+                        message: "must be \"record\" or \"replay\"".to_string(),
+                    });
+                }
+            }
+            if self.replay_file.is_none() {
+                return Err(ConfigError::InvalidValue { key: "replay.file".to_string(), message: "must be set when replay.mode is set".to_string() });
+            }
+        }
+        if self.mock_backend_enabled && self.replay_mode.is_some() {
+            return Err(ConfigError::InvalidValue {
+                key: "mock.enabled".to_string(),
+                message: "cannot be set together with replay.mode; they both replace the model pool's backend".to_string(),
+            });
+        }
+        if self.audit_enabled {
+            match self.audit_sink.as_str() {
+                "file" => {}
+                "syslog" if !self.audit_syslog_addr.is_empty() => {}
+                "syslog" => {
+                    return Err(ConfigError::InvalidValue {
+                        key: "audit.syslog_addr".to_string(),
+                        message: "must be set when audit.sink is \"syslog\"".to_string(),
+                    });
+                }
+                _ => {
+                    return Err(ConfigError::InvalidValue {
+                        key: "audit.sink".to_string(),
+                        message: "must be \"file\" or \"syslog\"".to_string(),
+                    });
+                }
+            }
+        }
+        if crate::guardrails::Action::parse(&self.guardrails_classifier_action).is_none() {
+            return Err(ConfigError::InvalidValue {
+                key: "guardrails.classifier_action".to_string(),
+                message: "must be one of \"block\", \"redact\", \"annotate\"".to_string(),
+            });
+        }
+        if self.plugins_enabled && self.plugins_wasmtime_path.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "plugins.wasmtime_path".to_string(),
+                message: "must not be empty when plugins.enabled is true".to_string(),
+            });
+        }
+        for entry in &self.mcp_client_servers {
+            let Some((name, address)) = entry.split_once('=') else {
+                return Err(ConfigError::InvalidValue {
+                    key: "mcp.client_servers".to_string(),
+                    message: format!("entry {entry:?} must be written as \"name=host:port\""),
+                });
+            };
+            if name.is_empty() || address.is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    key: "mcp.client_servers".to_string(),
+                    message: format!("entry {entry:?} must be written as \"name=host:port\""),
+                });
+            }
+        }
+        if self.agent_enabled && self.agent_file_root.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "agent.file_root".to_string(),
+                message: "must not be empty when agent.enabled is true".to_string(),
+            });
+        }
+        if self.agent_max_steps == 0 {
+            return Err(ConfigError::InvalidValue { key: "agent.max_steps".to_string(), message: "must be at least 1".to_string() });
+        }
+        if self.pipelines_enabled && self.pipelines_dir.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "pipelines.dir".to_string(),
+                message: "must not be empty when pipelines.enabled is true".to_string(),
+            });
+        }
+        if self.jobs_enabled && self.jobs_dir.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "jobs.dir".to_string(),
+                message: "must not be empty when jobs.enabled is true".to_string(),
+            });
+        }
+        if self.watcher_enabled && self.watcher_dir.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "watcher.dir".to_string(),
+                message: "must not be empty when watcher.enabled is true".to_string(),
+            });
+        }
+        if self.watcher_enabled && self.watcher_collection.is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "watcher.collection".to_string(),
+                message: "must not be empty when watcher.enabled is true".to_string(),
+            });
+        }
+        if self.tls_enabled && self.tls_cert_path.as_deref().unwrap_or("").is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "tls.cert_path".to_string(),
+                message: "must be set when tls.enabled is true".to_string(),
+            });
+        }
+        if self.tls_enabled && self.tls_key_path.as_deref().unwrap_or("").is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "tls.key_path".to_string(),
+                message: "must be set when tls.enabled is true".to_string(),
+            });
+        }
+        if self.tls_mtls_enabled && !self.tls_enabled {
+            return Err(ConfigError::InvalidValue {
+                key: "tls.mtls_enabled".to_string(),
+                message: "requires tls.enabled to be true".to_string(),
+            });
+        }
+        if self.tls_mtls_enabled && self.tls_client_ca_path.as_deref().unwrap_or("").is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: "tls.client_ca_path".to_string(),
+                message: "must be set when tls.mtls_enabled is true".to_string(),
+            });
+        }
+        if u32::from_str_radix(&self.unix_socket_permissions, 8).is_err() {
+            return Err(ConfigError::InvalidValue {
+                key: "server.unix_socket_permissions".to_string(),
+                message: "must be an octal permissions string, e.g. \"600\"".to_string(),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Settings safe to change without rebinding a listener or restarting
+    /// a backend — the ones [`watch`]'s poller applies on reload.
+    /// `bind_address`/`rpc_bind_address` are deliberately excluded since a
+    /// live `TcpListener` can't be re-pointed at a new address in place.
+    fn apply_non_structural(&mut self, reloaded: &ServerConfig) {
+        self.max_batch_size = reloaded.max_batch_size;
+        self.max_context_tokens = reloaded.max_context_tokens;
+        self.context_overflow_policy = reloaded.context_overflow_policy.clone();
+        self.otlp_endpoint = reloaded.otlp_endpoint.clone();
+        self.model_idle_timeout_seconds = reloaded.model_idle_timeout_seconds;
+        self.max_cache_bytes = reloaded.max_cache_bytes;
+        self.readiness_check_timeout_ms = reloaded.readiness_check_timeout_ms;
+        self.shutdown_drain_timeout_seconds = reloaded.shutdown_drain_timeout_seconds;
+        self.response_cache_ttl_seconds = reloaded.response_cache_ttl_seconds;
+        self.response_cache_max_entries = reloaded.response_cache_max_entries;
+        self.embedding_cache_max_entries = reloaded.embedding_cache_max_entries;
+        self.idempotency_key_ttl_seconds = reloaded.idempotency_key_ttl_seconds;
+    }
+}
+
+pub(crate) fn expect_string(key: &str, value: &TomlValue) -> Result<String, ConfigError> {
+    match value {
+        TomlValue::String(s) => Ok(s.clone()),
+        other => Err(ConfigError::InvalidValue { key: key.to_string(), message: format!("expected a string, got {other:?}") }),
+    }
+}
+
+pub(crate) fn expect_integer(key: &str, value: &TomlValue) -> Result<usize, ConfigError> {
+    match value {
+        TomlValue::Integer(n) if *n >= 0 => Ok(*n as usize),
+        other => Err(ConfigError::InvalidValue { key: key.to_string(), message: format!("expected a non-negative integer, got {other:?}") }),
+    }
+}
+
+pub(crate) fn expect_bool(key: &str, value: &TomlValue) -> Result<bool, ConfigError> {
+    match value {
+        TomlValue::Bool(b) => Ok(*b),
+        other => Err(ConfigError::InvalidValue { key: key.to_string(), message: format!("expected a bool, got {other:?}") }),
+    }
+}
+
+/// Splits a comma-separated `api_keys` value into individual keys,
+/// trimming surrounding whitespace and dropping empty entries — the
+/// hand-rolled TOML parser here has no array syntax (see this module's
+/// doc comment), so a delimited string is the flat-config equivalent.
+pub(crate) fn split_api_keys(raw: &str) -> Vec<String> {
+    raw.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+/// Polls `path`'s mtime every `interval` and, on change, re-parses it and
+/// applies whatever changed to `config`'s non-structural fields in place
+/// (see [`ServerConfig::apply_non_structural`]). Parse/validation errors
+/// are swallowed with the previous config left untouched — a typo in a
+/// hot-reloaded file shouldn't take a running server down.
+pub fn watch(path: PathBuf, config: &'static Mutex<ServerConfig>, interval: Duration) {
+    std::thread::spawn(move || {
+        let mut last_modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        loop {
+            std::thread::sleep(interval);
+            let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else { continue };
+            if Some(modified) == last_modified {
+                continue;
+            }
+            last_modified = Some(modified);
+            if let Ok(reloaded) = ServerConfig::load(&path) {
+                config.lock().unwrap().apply_non_structural(&reloaded);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sections_and_typed_values() {
+        let config = ServerConfig::from_toml_str(
+            "[server]\nbind_address = \"0.0.0.0:9000\"\nmax_batch_size = 16\n",
+        )
+        .unwrap();
+        assert_eq!(config.bind_address, "0.0.0.0:9000");
+        assert_eq!(config.max_batch_size, 16);
+        assert_eq!(config.max_context_tokens, 4096); // untouched default
+    }
+
+    #[test]
+    fn rejects_zero_max_batch_size_with_the_offending_key() {
+        let err = ServerConfig::from_toml_str("[server]\nmax_batch_size = 0\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "server.max_batch_size".to_string(), message: "must be at least 1".to_string() });
+    }
+
+    #[test]
+    fn context_overflow_policy_defaults_to_error() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.context_overflow_policy, "error");
+    }
+
+    #[test]
+    fn parses_a_valid_context_overflow_policy() {
+        let config = ServerConfig::from_toml_str("[server]\ncontext_overflow_policy = \"truncate\"\n").unwrap();
+        assert_eq!(config.context_overflow_policy, "truncate");
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_context_overflow_policy() {
+        let err = ServerConfig::from_toml_str("[server]\ncontext_overflow_policy = \"bogus\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "server.context_overflow_policy".to_string(),
+                message: "must be one of \"error\", \"truncate\", \"slide\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn router_nodes_default_to_empty() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.router_nodes.is_empty());
+    }
+
+    #[test]
+    fn parses_a_comma_separated_router_nodes_list() {
+        let config = ServerConfig::from_toml_str("[router]\nnodes = \"a=10.0.0.1:8080, b=10.0.0.2:8080\"\n").unwrap();
+        assert_eq!(config.router_nodes, vec!["a=10.0.0.1:8080".to_string(), "b=10.0.0.2:8080".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_router_node_missing_the_id_or_address() {
+        let err = ServerConfig::from_toml_str("[router]\nnodes = \"no-equals-sign\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "router.nodes".to_string(),
+                message: "entry \"no-equals-sign\" must be written as \"id=host:port\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn tenant_keys_and_models_default_to_empty() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.tenant_keys.is_empty());
+        assert!(config.tenant_models.is_empty());
+    }
+
+    #[test]
+    fn parses_tenant_keys_and_models_under_the_tenancy_section() {
+        let config = ServerConfig::from_toml_str(
+            "[tenancy]\ntenant_keys = \"key-a=teamA, key-b=teamB\"\ntenant_models = \"teamA:small|medium\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.tenant_keys, vec!["key-a=teamA".to_string(), "key-b=teamB".to_string()]);
+        assert_eq!(config.tenant_models, vec!["teamA:small|medium".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_tenant_key_missing_the_key_or_tenant() {
+        let err = ServerConfig::from_toml_str("[tenancy]\ntenant_keys = \"no-equals-sign\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "tenancy.tenant_keys".to_string(),
+                message: "entry \"no-equals-sign\" must be written as \"key=tenant\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn model_aliases_default_to_empty() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.model_aliases.is_empty());
+    }
+
+    #[test]
+    fn parses_model_aliases_under_the_routing_section() {
+        let config = ServerConfig::from_toml_str("[routing]\nmodel_aliases = \"prod:modelA=70|modelB=30|shadow=modelC\"\n").unwrap();
+        assert_eq!(config.model_aliases, vec!["prod:modelA=70|modelB=30|shadow=modelC".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_model_alias_missing_the_alias_or_spec() {
+        let err = ServerConfig::from_toml_str("[routing]\nmodel_aliases = \"no-colon-here\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "routing.model_aliases".to_string(),
+                message: "entry \"no-colon-here\" must be written as \"alias:model1=70|model2=30\", optionally with a \"|shadow=model3\" target"
+                    .to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_model_alias_target_with_a_non_positive_weight() {
+        let err = ServerConfig::from_toml_str("[routing]\nmodel_aliases = \"prod:modelA=0\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "routing.model_aliases".to_string(),
+                message: "entry \"prod:modelA=0\": weight for \"modelA\" must be a positive integer".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn backend_override_defaults_to_none() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.backend_override, None);
+    }
+
+    #[test]
+    fn parses_a_valid_backend_override() {
+        let config = ServerConfig::from_toml_str("[backend]\noverride = \"cuda\"\n").unwrap();
+        assert_eq!(config.backend_override, Some("cuda".to_string()));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_backend_override() {
+        let err = ServerConfig::from_toml_str("[backend]\noverride = \"bogus\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "backend.override".to_string(),
+                message: "must be one of \"metal\", \"cuda\", \"vulkan\", \"cpu-neon\", \"cpu-scalar\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn flash_attention_enabled_defaults_to_false() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.flash_attention_enabled);
+    }
+
+    #[test]
+    fn parses_flash_attention_enabled_under_the_backend_section() {
+        let config = ServerConfig::from_toml_str("[backend]\nflash_attention_enabled = true\n").unwrap();
+        assert!(config.flash_attention_enabled);
+    }
+
+    #[test]
+    fn tensor_split_override_defaults_to_empty() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.tensor_split_override.is_empty());
+    }
+
+    #[test]
+    fn parses_tensor_split_override_under_the_backend_section() {
+        let config = ServerConfig::from_toml_str("[backend]\ntensor_split_override = \"0.5,0.3,0.2\"\n").unwrap();
+        assert_eq!(config.tensor_split_override, vec!["0.5".to_string(), "0.3".to_string(), "0.2".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_non_numeric_tensor_split_override_entry() {
+        let err = ServerConfig::from_toml_str("[backend]\ntensor_split_override = \"bogus\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "backend.tensor_split_override".to_string(),
+                message: "entry \"bogus\" must be a non-negative number".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_negative_tensor_split_override_entry() {
+        let err = ServerConfig::from_toml_str("[backend]\ntensor_split_override = \"0.5,-0.1\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "backend.tensor_split_override"));
+    }
+
+    #[test]
+    fn n_gpu_layers_override_defaults_to_none() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.n_gpu_layers_override, None);
+    }
+
+    #[test]
+    fn parses_n_gpu_layers_override_under_the_backend_section() {
+        let config = ServerConfig::from_toml_str("[backend]\nn_gpu_layers_override = 20\n").unwrap();
+        assert_eq!(config.n_gpu_layers_override, Some(20));
+    }
+
+    #[test]
+    fn replay_mode_and_file_default_to_none() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.replay_mode, None);
+        assert_eq!(config.replay_file, None);
+    }
+
+    #[test]
+    fn parses_replay_mode_and_file_under_the_replay_section() {
+        let config = ServerConfig::from_toml_str("[replay]\nmode = \"record\"\nfile = \"./replay.jsonl\"\n").unwrap();
+        assert_eq!(config.replay_mode, Some("record".to_string()));
+        assert_eq!(config.replay_file, Some("./replay.jsonl".to_string()));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_replay_mode() {
+        let err = ServerConfig::from_toml_str("[replay]\nmode = \"bogus\"\nfile = \"./replay.jsonl\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "replay.mode"));
+    }
+
+    #[test]
+    fn rejects_a_replay_mode_with_no_replay_file() {
+        let err = ServerConfig::from_toml_str("[replay]\nmode = \"record\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "replay.file"));
+    }
+
+    #[test]
+    fn mock_backend_settings_default_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.mock_backend_enabled);
+        assert_eq!(config.mock_default_response, None);
+        assert_eq!(config.mock_latency_ms, None);
+    }
+
+    #[test]
+    fn parses_mock_settings_under_the_mock_section() {
+        let config = ServerConfig::from_toml_str("[mock]\nenabled = true\ndefault_response = \"canned\"\nlatency_ms = 50\n").unwrap();
+        assert!(config.mock_backend_enabled);
+        assert_eq!(config.mock_default_response, Some("canned".to_string()));
+        assert_eq!(config.mock_latency_ms, Some(50));
+    }
+
+    #[test]
+    fn rejects_mock_enabled_together_with_replay_mode() {
+        let err = ServerConfig::from_toml_str("[mock]\nenabled = true\n\n[replay]\nmode = \"record\"\nfile = \"./replay.jsonl\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "mock.enabled"));
+    }
+
+    #[test]
+    fn rejects_malformed_lines_with_the_line_number() {
+        let err = ServerConfig::from_toml_str("[server]\nnot a key value pair\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Malformed { line: 2, .. }));
+    }
+
+    #[test]
+    fn parses_models_section_with_dir_and_idle_timeout() {
+        let config = ServerConfig::from_toml_str("[models]\nmodels_dir = \"/data/models\"\nidle_timeout_seconds = 60\n").unwrap();
+        assert_eq!(config.models_dir, "/data/models");
+        assert_eq!(config.model_idle_timeout_seconds, 60);
+    }
+
+    #[test]
+    fn parses_warmup_settings_under_the_models_section() {
+        let config = ServerConfig::from_toml_str("[models]\nwarmup_runs = 3\nwarmup_prompt = \"hello\"\n").unwrap();
+        assert_eq!(config.warmup_runs, 3);
+        assert_eq!(config.warmup_prompt, "hello");
+    }
+
+    #[test]
+    fn defaults_to_warmup_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.warmup_runs, 0);
+        assert_eq!(config.warmup_prompt, "warmup");
+    }
+
+    #[test]
+    fn defaults_to_strict_model_verification_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.strict_model_verification);
+    }
+
+    #[test]
+    fn parses_strict_verification_under_the_models_section() {
+        let config = ServerConfig::from_toml_str("[models]\nstrict_verification = true\n").unwrap();
+        assert!(config.strict_model_verification);
+    }
+
+    #[test]
+    fn defaults_to_unlimited_max_cache_bytes() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.max_cache_bytes, 0);
+    }
+
+    #[test]
+    fn parses_max_cache_bytes_under_the_models_section() {
+        let config = ServerConfig::from_toml_str("[models]\nmax_cache_bytes = 1073741824\n").unwrap();
+        assert_eq!(config.max_cache_bytes, 1_073_741_824);
+    }
+
+    #[test]
+    fn defaults_to_no_rope_scaling_overrides() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.rope_scaling_by_model.is_empty());
+    }
+
+    #[test]
+    fn parses_rope_scaling_by_model_under_the_models_section() {
+        let config = ServerConfig::from_toml_str("[models]\nrope_scaling_by_model = \"llama-13b=yarn:4.0\"\n").unwrap();
+        assert_eq!(config.rope_scaling_by_model, vec!["llama-13b=yarn:4.0".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_rope_scaling_entry_missing_the_colon() {
+        let err = ServerConfig::from_toml_str("[models]\nrope_scaling_by_model = \"llama-13b=yarn\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "models.rope_scaling_by_model".to_string(),
+                message: "entry \"llama-13b=yarn\" must be written as \"id=mode:factor\"".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_rope_scaling_mode() {
+        let err = ServerConfig::from_toml_str("[models]\nrope_scaling_by_model = \"llama-13b=exotic:4.0\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "models.rope_scaling_by_model"));
+    }
+
+    #[test]
+    fn rejects_a_non_positive_rope_scaling_factor() {
+        let err = ServerConfig::from_toml_str("[models]\nrope_scaling_by_model = \"llama-13b=yarn:0\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "models.rope_scaling_by_model"));
+    }
+
+    #[test]
+    fn parses_tracing_section_into_an_otlp_endpoint() {
+        let config = ServerConfig::from_toml_str("[tracing]\notlp_host = \"localhost\"\notlp_port = 4318\n").unwrap();
+        assert_eq!(config.otlp_endpoint, Some(("localhost".to_string(), 4318)));
+    }
+
+    #[test]
+    fn parses_auth_section_splitting_the_comma_separated_key_list() {
+        let config = ServerConfig::from_toml_str(
+            "[auth]\napi_keys = \"key-one, key-two\"\nrequests_per_minute = 30\ndaily_token_quota = 100000\n",
+        )
+        .unwrap();
+        assert_eq!(config.api_keys, vec!["key-one".to_string(), "key-two".to_string()]);
+        assert_eq!(config.requests_per_minute, 30);
+        assert_eq!(config.daily_token_quota, 100000);
+    }
+
+    #[test]
+    fn defaults_to_no_api_keys() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.api_keys.is_empty());
+    }
+
+    #[test]
+    fn parses_admin_keys_separately_from_api_keys() {
+        let config = ServerConfig::from_toml_str("[auth]\napi_keys = \"user-key\"\nadmin_keys = \"admin-key\"\n").unwrap();
+        assert_eq!(config.api_keys, vec!["user-key".to_string()]);
+        assert_eq!(config.admin_keys, vec!["admin-key".to_string()]);
+    }
+
+    #[test]
+    fn generation_limits_default_to_two_fifty_six_tokens_and_sixty_seconds() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.default_max_output_tokens, 256);
+        assert_eq!(config.default_request_timeout_seconds, 60);
+        assert!(config.max_output_tokens_by_key.is_empty());
+        assert!(config.request_timeout_by_key_seconds.is_empty());
+    }
+
+    #[test]
+    fn parses_generation_limits_under_the_auth_section() {
+        let config = ServerConfig::from_toml_str(
+            "[auth]\ndefault_max_output_tokens = 512\ndefault_request_timeout_seconds = 30\nmax_output_tokens_by_key = \"bulk-key=4096\"\nrequest_timeout_by_key_seconds = \"bulk-key=120\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.default_max_output_tokens, 512);
+        assert_eq!(config.default_request_timeout_seconds, 30);
+        assert_eq!(config.max_output_tokens_by_key, vec!["bulk-key=4096".to_string()]);
+        assert_eq!(config.request_timeout_by_key_seconds, vec!["bulk-key=120".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_max_output_tokens_entry_missing_an_equals_sign() {
+        let err = ServerConfig::from_toml_str("[auth]\nmax_output_tokens_by_key = \"no-equals-sign\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "auth.max_output_tokens_by_key".to_string(),
+                message: "entry \"no-equals-sign\" must be written as \"key=tokens\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_non_positive_max_output_tokens_value() {
+        let err = ServerConfig::from_toml_str("[auth]\nmax_output_tokens_by_key = \"bulk-key=0\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "auth.max_output_tokens_by_key".to_string(),
+                message: "entry \"bulk-key=0\": tokens for \"bulk-key\" must be a positive integer".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_request_timeout_entry_missing_an_equals_sign() {
+        let err = ServerConfig::from_toml_str("[auth]\nrequest_timeout_by_key_seconds = \"no-equals-sign\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "auth.request_timeout_by_key_seconds".to_string(),
+                message: "entry \"no-equals-sign\" must be written as \"key=seconds\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_non_positive_request_timeout_value() {
+        let err = ServerConfig::from_toml_str("[auth]\nrequest_timeout_by_key_seconds = \"bulk-key=0\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "auth.request_timeout_by_key_seconds".to_string(),
+                message: "entry \"bulk-key=0\": seconds for \"bulk-key\" must be a positive integer".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_logging_section() {
+        let config = ServerConfig::from_toml_str(
+            "[logging]\nlog_file = \"/var/log/ai-server.log\"\nlog_max_bytes = 1024\nlog_pretty = true\n",
+        )
+        .unwrap();
+        assert_eq!(config.log_file, "/var/log/ai-server.log");
+        assert_eq!(config.log_max_bytes, 1024);
+        assert!(config.log_pretty);
+    }
+
+    #[test]
+    fn defaults_to_a_local_log_file_with_rotation_and_non_pretty_output() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.log_file, "./ai-server.log");
+        assert!(config.log_max_bytes > 0);
+        assert!(!config.log_pretty);
+    }
+
+    #[test]
+    fn parses_health_section() {
+        let config = ServerConfig::from_toml_str("[health]\nreadiness_check_timeout_ms = 500\n").unwrap();
+        assert_eq!(config.readiness_check_timeout_ms, 500);
+    }
+
+    #[test]
+    fn defaults_to_a_two_second_readiness_check_timeout() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.readiness_check_timeout_ms, 2000);
+    }
+
+    #[test]
+    fn parses_shutdown_section() {
+        let config = ServerConfig::from_toml_str("[shutdown]\ndrain_timeout_seconds = 5\n").unwrap();
+        assert_eq!(config.shutdown_drain_timeout_seconds, 5);
+    }
+
+    #[test]
+    fn defaults_to_a_thirty_second_drain_timeout() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.shutdown_drain_timeout_seconds, 30);
+    }
+
+    #[test]
+    fn parses_response_cache_section() {
+        let config = ServerConfig::from_toml_str("[response_cache]\nttl_seconds = 60\nmax_entries = 10\n").unwrap();
+        assert_eq!(config.response_cache_ttl_seconds, 60);
+        assert_eq!(config.response_cache_max_entries, 10);
+    }
+
+    #[test]
+    fn defaults_to_a_five_minute_response_cache_with_a_thousand_entries() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.response_cache_ttl_seconds, 300);
+        assert_eq!(config.response_cache_max_entries, 1000);
+    }
+
+    #[test]
+    fn parses_embedding_cache_section() {
+        let config = ServerConfig::from_toml_str("[embedding_cache]\nmax_entries = 500\n").unwrap();
+        assert_eq!(config.embedding_cache_max_entries, 500);
+    }
+
+    #[test]
+    fn defaults_to_a_ten_thousand_entry_embedding_cache() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.embedding_cache_max_entries, 10000);
+    }
+
+    #[test]
+    fn parses_idempotency_key_ttl_under_the_response_cache_section() {
+        let config = ServerConfig::from_toml_str("[response_cache]\nidempotency_key_ttl_seconds = 60\n").unwrap();
+        assert_eq!(config.idempotency_key_ttl_seconds, 60);
+    }
+
+    #[test]
+    fn defaults_to_a_five_minute_idempotency_key_ttl() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.idempotency_key_ttl_seconds, 300);
+    }
+
+    #[test]
+    fn parses_scheduler_section() {
+        let config = ServerConfig::from_toml_str(
+            "[scheduler]\nbatch_priority_keys = \"embed-key\"\nbackground_priority_keys = \"nightly-key, sweep-key\"\nbatch_queue_limit = 8\nbackground_queue_limit = 32\n",
+        )
+        .unwrap();
+        assert_eq!(config.batch_priority_keys, vec!["embed-key".to_string()]);
+        assert_eq!(config.background_priority_keys, vec!["nightly-key".to_string(), "sweep-key".to_string()]);
+        assert_eq!(config.scheduler_batch_queue_limit, 8);
+        assert_eq!(config.scheduler_background_queue_limit, 32);
+    }
+
+    #[test]
+    fn defaults_to_no_priority_keys_with_bounded_batch_and_background_queues() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(config.batch_priority_keys.is_empty());
+        assert!(config.background_priority_keys.is_empty());
+        assert_eq!(config.scheduler_batch_queue_limit, 64);
+        assert_eq!(config.scheduler_background_queue_limit, 256);
+    }
+
+    #[test]
+    fn watch_picks_up_an_edited_file_without_touching_bind_address() {
+        let path = std::env::temp_dir().join(format!("ai-server-config-test-{}.toml", std::process::id()));
+        std::fs::write(&path, "[server]\nbind_address = \"127.0.0.1:1\"\nmax_batch_size = 1\n").unwrap();
+
+        let config: &'static Mutex<ServerConfig> = Box::leak(Box::new(Mutex::new(ServerConfig::load(&path).unwrap())));
+        watch(path.clone(), config, Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::write(&path, "[server]\nbind_address = \"127.0.0.1:1\"\nmax_batch_size = 32\n").unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let reloaded = config.lock().unwrap();
+        assert_eq!(reloaded.max_batch_size, 32);
+        assert_eq!(reloaded.bind_address, "127.0.0.1:1"); // structural, never reloaded live
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn audit_defaults_to_disabled_with_a_local_file_sink() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.audit_enabled);
+        assert_eq!(config.audit_sink, "file");
+        assert_eq!(config.audit_file, "./audit.log");
+        assert!(!config.audit_include_bodies);
+        assert!(config.audit_redact_patterns.is_empty());
+    }
+
+    #[test]
+    fn parses_audit_settings_under_the_audit_section() {
+        let config = ServerConfig::from_toml_str(
+            "[audit]\nenabled = true\nsink = \"syslog\"\nsyslog_addr = \"127.0.0.1:514\"\ninclude_bodies = true\nredact_patterns = \"secret, token\"\n",
+        )
+        .unwrap();
+        assert!(config.audit_enabled);
+        assert_eq!(config.audit_sink, "syslog");
+        assert_eq!(config.audit_syslog_addr, "127.0.0.1:514");
+        assert!(config.audit_include_bodies);
+        assert_eq!(config.audit_redact_patterns, vec!["secret".to_string(), "token".to_string()]);
+    }
+
+    #[test]
+    fn rejects_an_enabled_syslog_sink_with_no_address() {
+        let err = ServerConfig::from_toml_str("[audit]\nenabled = true\nsink = \"syslog\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue { key: "audit.syslog_addr".to_string(), message: "must be set when audit.sink is \"syslog\"".to_string() }
+        );
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_audit_sink() {
+        let err = ServerConfig::from_toml_str("[audit]\nenabled = true\nsink = \"bogus\"\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "audit.sink".to_string(), message: "must be \"file\" or \"syslog\"".to_string() });
+    }
+
+    #[test]
+    fn a_disabled_audit_sink_is_not_validated() {
+        assert!(ServerConfig::from_toml_str("[audit]\nsink = \"bogus\"\n").is_ok());
+    }
+
+    #[test]
+    fn guardrails_default_to_disabled_with_no_rules() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.guardrails_enabled);
+        assert!(config.guardrails_block_patterns.is_empty());
+        assert_eq!(config.guardrails_classifier_action, "annotate");
+    }
+
+    #[test]
+    fn parses_guardrails_settings_under_the_guardrails_section() {
+        let config = ServerConfig::from_toml_str(
+            "[guardrails]\nenabled = true\nblock_patterns = \"bomb, weapon\"\nredact_patterns = \"ssn\"\nannotate_patterns = \"darn\"\nclassifier_prompt = \"classify: \"\nclassifier_action = \"block\"\n",
+        )
+        .unwrap();
+        assert!(config.guardrails_enabled);
+        assert_eq!(config.guardrails_block_patterns, vec!["bomb".to_string(), "weapon".to_string()]);
+        assert_eq!(config.guardrails_redact_patterns, vec!["ssn".to_string()]);
+        assert_eq!(config.guardrails_annotate_patterns, vec!["darn".to_string()]);
+        assert_eq!(config.guardrails_classifier_prompt, "classify: ");
+        assert_eq!(config.guardrails_classifier_action, "block");
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_guardrails_classifier_action() {
+        let err = ServerConfig::from_toml_str("[guardrails]\nclassifier_action = \"bogus\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "guardrails.classifier_action".to_string(),
+                message: "must be one of \"block\", \"redact\", \"annotate\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn plugins_default_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.plugins_enabled);
+        assert_eq!(config.plugins_dir, "./plugins");
+        assert_eq!(config.plugins_wasmtime_path, "wasmtime");
+        assert_eq!(config.plugins_reload_interval_seconds, 5);
+    }
+
+    #[test]
+    fn parses_plugins_settings_under_the_plugins_section() {
+        let config = ServerConfig::from_toml_str(
+            "[plugins]\nenabled = true\ndir = \"./my-plugins\"\nwasmtime_path = \"/usr/local/bin/wasmtime\"\nreload_interval_seconds = 30\n",
+        )
+        .unwrap();
+        assert!(config.plugins_enabled);
+        assert_eq!(config.plugins_dir, "./my-plugins");
+        assert_eq!(config.plugins_wasmtime_path, "/usr/local/bin/wasmtime");
+        assert_eq!(config.plugins_reload_interval_seconds, 30);
+    }
+
+    #[test]
+    fn rejects_an_enabled_plugins_section_with_an_empty_wasmtime_path() {
+        let err = ServerConfig::from_toml_str("[plugins]\nenabled = true\nwasmtime_path = \"\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "plugins.wasmtime_path".to_string(),
+                message: "must not be empty when plugins.enabled is true".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn mcp_defaults_to_disabled_with_no_client_servers() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.mcp_enabled);
+        assert!(config.mcp_client_servers.is_empty());
+        assert_eq!(config.mcp_client_timeout_ms, 2000);
+        assert_eq!(config.mcp_client_refresh_interval_seconds, 30);
+    }
+
+    #[test]
+    fn parses_mcp_settings_under_the_mcp_section() {
+        let config = ServerConfig::from_toml_str(
+            "[mcp]\nenabled = true\nclient_servers = \"tools=10.0.0.5:9000, search=10.0.0.6:9000\"\nclient_timeout_ms = 500\nclient_refresh_interval_seconds = 10\n",
+        )
+        .unwrap();
+        assert!(config.mcp_enabled);
+        assert_eq!(config.mcp_client_servers, vec!["tools=10.0.0.5:9000".to_string(), "search=10.0.0.6:9000".to_string()]);
+        assert_eq!(config.mcp_client_timeout_ms, 500);
+        assert_eq!(config.mcp_client_refresh_interval_seconds, 10);
+    }
+
+    #[test]
+    fn rejects_an_mcp_client_server_missing_the_name_or_address() {
+        let err = ServerConfig::from_toml_str("[mcp]\nclient_servers = \"no-equals-sign\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue {
+                key: "mcp.client_servers".to_string(),
+                message: "entry \"no-equals-sign\" must be written as \"name=host:port\"".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn agent_defaults_to_disabled_with_a_ten_step_cap() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.agent_enabled);
+        assert!(config.agent_shell_allowlist.is_empty());
+        assert!(config.agent_http_allowlist.is_empty());
+        assert_eq!(config.agent_file_root, "./agent-workspace");
+        assert_eq!(config.agent_max_steps, 10);
+    }
+
+    #[test]
+    fn parses_agent_settings_under_the_agent_section() {
+        let config = ServerConfig::from_toml_str(
+            "[agent]\nenabled = true\nshell_allowlist = \"echo, ls\"\nhttp_allowlist = \"10.0.0.5:9000\"\nfile_root = \"./runs\"\nmax_steps = 25\n",
+        )
+        .unwrap();
+        assert!(config.agent_enabled);
+        assert_eq!(config.agent_shell_allowlist, vec!["echo".to_string(), "ls".to_string()]);
+        assert_eq!(config.agent_http_allowlist, vec!["10.0.0.5:9000".to_string()]);
+        assert_eq!(config.agent_file_root, "./runs");
+        assert_eq!(config.agent_max_steps, 25);
+    }
+
+    #[test]
+    fn rejects_an_empty_file_root_when_agent_is_enabled() {
+        let err = ServerConfig::from_toml_str("[agent]\nenabled = true\nfile_root = \"\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue { key: "agent.file_root".to_string(), message: "must not be empty when agent.enabled is true".to_string() }
+        );
+    }
+
+    #[test]
+    fn rejects_a_zero_max_steps() {
+        let err = ServerConfig::from_toml_str("[agent]\nmax_steps = 0\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "agent.max_steps".to_string(), message: "must be at least 1".to_string() });
+    }
+
+    #[test]
+    fn pipelines_defaults_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.pipelines_enabled);
+        assert_eq!(config.pipelines_dir, "./pipelines");
+        assert_eq!(config.pipelines_reload_interval_seconds, 5);
+    }
+
+    #[test]
+    fn parses_pipelines_settings_under_the_pipelines_section() {
+        let config = ServerConfig::from_toml_str("[pipelines]\nenabled = true\ndir = \"./flows\"\nreload_interval_seconds = 30\n").unwrap();
+        assert!(config.pipelines_enabled);
+        assert_eq!(config.pipelines_dir, "./flows");
+        assert_eq!(config.pipelines_reload_interval_seconds, 30);
+    }
+
+    #[test]
+    fn rejects_an_empty_pipelines_dir_when_pipelines_is_enabled() {
+        let err = ServerConfig::from_toml_str("[pipelines]\nenabled = true\ndir = \"\"\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::InvalidValue { key: "pipelines.dir".to_string(), message: "must not be empty when pipelines.enabled is true".to_string() }
+        );
+    }
+
+    #[test]
+    fn jobs_defaults_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.jobs_enabled);
+        assert_eq!(config.jobs_dir, "./jobs");
+    }
+
+    #[test]
+    fn parses_jobs_settings_under_the_jobs_section() {
+        let config = ServerConfig::from_toml_str("[jobs]\nenabled = true\ndir = \"./scheduled\"\n").unwrap();
+        assert!(config.jobs_enabled);
+        assert_eq!(config.jobs_dir, "./scheduled");
+    }
+
+    #[test]
+    fn rejects_an_empty_jobs_dir_when_jobs_is_enabled() {
+        let err = ServerConfig::from_toml_str("[jobs]\nenabled = true\ndir = \"\"\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "jobs.dir".to_string(), message: "must not be empty when jobs.enabled is true".to_string() });
+    }
+
+    #[test]
+    fn watcher_defaults_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.watcher_enabled);
+        assert_eq!(config.watcher_dir, "./watched-docs");
+        assert_eq!(config.watcher_collection, "watched-docs");
+        assert_eq!(config.watcher_poll_interval_seconds, 30);
+    }
+
+    #[test]
+    fn parses_watcher_settings_under_the_watcher_section() {
+        let config = ServerConfig::from_toml_str("[watcher]\nenabled = true\ndir = \"./notes\"\ncollection = \"notes\"\npoll_interval_seconds = 10\n").unwrap();
+        assert!(config.watcher_enabled);
+        assert_eq!(config.watcher_dir, "./notes");
+        assert_eq!(config.watcher_collection, "notes");
+        assert_eq!(config.watcher_poll_interval_seconds, 10);
+    }
+
+    #[test]
+    fn rejects_an_empty_watcher_dir_when_watcher_is_enabled() {
+        let err = ServerConfig::from_toml_str("[watcher]\nenabled = true\ndir = \"\"\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "watcher.dir".to_string(), message: "must not be empty when watcher.enabled is true".to_string() });
+    }
+
+    #[test]
+    fn rejects_an_empty_watcher_collection_when_watcher_is_enabled() {
+        let err = ServerConfig::from_toml_str("[watcher]\nenabled = true\ndir = \"./notes\"\ncollection = \"\"\n").unwrap_err();
+        assert_eq!(err, ConfigError::InvalidValue { key: "watcher.collection".to_string(), message: "must not be empty when watcher.enabled is true".to_string() });
+    }
+
+    #[test]
+    fn ollama_compat_is_disabled_by_default() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.ollama_compat_enabled);
+    }
+
+    #[test]
+    fn parses_ollama_enabled_under_the_ollama_section() {
+        let config = ServerConfig::from_toml_str("[ollama]\nenabled = true\n").unwrap();
+        assert!(config.ollama_compat_enabled);
+    }
+
+    #[test]
+    fn discovery_settings_default_to_disabled() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.discovery_enabled);
+        assert_eq!(config.discovery_interval_seconds, 5);
+        assert_eq!(config.discovery_name, None);
+    }
+
+    #[test]
+    fn parses_discovery_settings_under_the_discovery_section() {
+        let config = ServerConfig::from_toml_str("[discovery]\nenabled = true\ninterval_seconds = 10\nname = \"studio-mac\"\n").unwrap();
+        assert!(config.discovery_enabled);
+        assert_eq!(config.discovery_interval_seconds, 10);
+        assert_eq!(config.discovery_name, Some("studio-mac".to_string()));
+    }
+
+    #[test]
+    fn tls_is_disabled_by_default() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert!(!config.tls_enabled);
+        assert!(!config.tls_mtls_enabled);
+    }
+
+    #[test]
+    fn parses_tls_settings_under_the_tls_section() {
+        let config = ServerConfig::from_toml_str(
+            "[tls]\nenabled = true\ncert_path = \"cert.pem\"\nkey_path = \"key.pem\"\nmtls_enabled = true\nclient_ca_path = \"ca.pem\"\n",
+        )
+        .unwrap();
+        assert!(config.tls_enabled);
+        assert_eq!(config.tls_cert_path, Some("cert.pem".to_string()));
+        assert_eq!(config.tls_key_path, Some("key.pem".to_string()));
+        assert!(config.tls_mtls_enabled);
+        assert_eq!(config.tls_client_ca_path, Some("ca.pem".to_string()));
+    }
+
+    #[test]
+    fn rejects_tls_enabled_without_a_cert_path() {
+        let err = ServerConfig::from_toml_str("[tls]\nenabled = true\nkey_path = \"key.pem\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "tls.cert_path"));
+    }
+
+    #[test]
+    fn rejects_mtls_enabled_without_a_client_ca_path() {
+        let err =
+            ServerConfig::from_toml_str("[tls]\nenabled = true\ncert_path = \"cert.pem\"\nkey_path = \"key.pem\"\nmtls_enabled = true\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "tls.client_ca_path"));
+    }
+
+    #[test]
+    fn unix_socket_path_is_unset_by_default_with_owner_only_permissions() {
+        let config = ServerConfig::from_toml_str("").unwrap();
+        assert_eq!(config.unix_socket_path, None);
+        assert_eq!(config.unix_socket_permissions, "600");
+    }
+
+    #[test]
+    fn parses_unix_socket_settings_under_the_server_section() {
+        let config =
+            ServerConfig::from_toml_str("[server]\nunix_socket_path = \"/tmp/ai-server.sock\"\nunix_socket_permissions = \"660\"\n").unwrap();
+        assert_eq!(config.unix_socket_path, Some("/tmp/ai-server.sock".to_string()));
+        assert_eq!(config.unix_socket_permissions, "660");
+    }
+
+    #[test]
+    fn rejects_a_non_octal_unix_socket_permissions_string() {
+        let err = ServerConfig::from_toml_str("[server]\nunix_socket_permissions = \"rwx\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "server.unix_socket_permissions"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Grammar-constrained generation. Compiles either a GBNF grammar or a
+//! (small, common-subset) JSON Schema into a [`Grammar`] that can check
+//! whether appending a candidate string keeps a partial completion
+//! grammar-valid — the same shape `sampling.rs` needs to zero out logits
+//! for tokens that would leave the valid language.
+//!
+//! Full GBNF and full JSON Schema are both large specs; this module covers
+//! the subset chat-completion callers actually hit in practice (literals,
+//! character classes, sequencing, alternation, repetition for GBNF; object
+//! property/type/enum for JSON Schema) and is meant to grow incrementally
+//! rather than block structured output on a complete implementation.
+
+use crate::json::Json;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum ConstraintError {
+    UnknownRule(String),
+    ParseError(String),
+    UnsupportedSchema(String),
+}
+
+/// One GBNF grammar element.
+#[derive(Debug, Clone)]
+enum Element {
+    Literal(String),
+    CharClass { chars: Vec<(char, char)>, negated: bool },
+    Rule(String),
+    Sequence(Vec<Element>),
+    Alternation(Vec<Element>),
+    Repeat(Box<Element>, usize, Option<usize>),
+}
+
+/// A compiled grammar: a root rule name plus every named rule it (and its
+/// dependents) can reference.
+pub struct Grammar {
+    rules: HashMap<String, Element>,
+    root: String,
+}
+
+impl Grammar {
+    /// Parses a GBNF source string. Each line is `name ::= expr`; the
+    /// first rule defined becomes the root unless a rule named `root`
+    /// exists.
+    pub fn from_gbnf(source: &str) -> Result<Grammar, ConstraintError> {
+        let mut rules = HashMap::new();
+        let mut first_name = None;
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, expr) = line
+                .split_once("::=")
+                .ok_or_else(|| ConstraintError::ParseError(format!("missing ::= in {line:?}")))?;
+            let name = name.trim().to_string();
+            if first_name.is_none() {
+                first_name = Some(name.clone());
+            }
+            let element = parse_expr(expr.trim())?;
+            rules.insert(name, element);
+        }
+        let root = if rules.contains_key("root") { "root".to_string() } else { first_name.ok_or_else(|| ConstraintError::ParseError("empty grammar".to_string()))? };
+        Ok(Grammar { rules, root })
+    }
+
+    /// Builds a grammar that accepts exactly the JSON produced by a
+    /// (subset) JSON Schema: `{"type": "object", "properties": {...},
+    /// "required": [...]}` with `string`/`number`/`boolean`/`enum` leaf
+    /// types. Anything else is rejected rather than silently ignored.
+    pub fn from_json_schema(schema: &Json) -> Result<Grammar, ConstraintError> {
+        let mut rules = HashMap::new();
+        let root = schema_to_element(schema, &mut rules, 0)?;
+        rules.insert("root".to_string(), root);
+        Ok(Grammar { rules, root: "root".to_string() })
+    }
+
+    /// Returns whether `text` is a complete, valid string in this grammar.
+    pub fn matches(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        match_element(&self.rules[&self.root], &self.rules, &chars, 0) == Some(chars.len())
+    }
+
+    /// Returns whether `text` could still be extended into a valid string
+    /// (i.e. it's a prefix of *some* accepted string) — this is what
+    /// sampling calls per-candidate-token to decide whether to mask it out.
+    pub fn is_valid_prefix(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        prefix_reachable(&self.rules[&self.root], &self.rules, &chars, 0)
+    }
+}
+
+fn parse_expr(expr: &str) -> Result<Element, ConstraintError> {
+    let alternatives: Vec<&str> = split_top_level(expr, '|');
+    if alternatives.len() > 1 {
+        let elements = alternatives.into_iter().map(|a| parse_sequence(a.trim())).collect::<Result<_, _>>()?;
+        return Ok(Element::Alternation(elements));
+    }
+    parse_sequence(expr)
+}
+
+fn parse_sequence(expr: &str) -> Result<Element, ConstraintError> {
+    let mut elements = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let element = if c == '"' {
+            chars.next();
+            let mut literal = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                literal.push(c);
+            }
+            Element::Literal(literal)
+        } else if c == '[' {
+            chars.next();
+            let mut spec = String::new();
+            for c in chars.by_ref() {
+                if c == ']' {
+                    break;
+                }
+                spec.push(c);
+            }
+            parse_char_class(&spec)
+        } else {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' || c == '-' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if name.is_empty() {
+                return Err(ConstraintError::ParseError(format!("unexpected character {c:?}")));
+            }
+            Element::Rule(name)
+        };
+
+        let element = match chars.peek() {
+            Some('*') => { chars.next(); Element::Repeat(Box::new(element), 0, None) }
+            Some('+') => { chars.next(); Element::Repeat(Box::new(element), 1, None) }
+            Some('?') => { chars.next(); Element::Repeat(Box::new(element), 0, Some(1)) }
+            _ => element,
+        };
+        elements.push(element);
+    }
+    Ok(if elements.len() == 1 { elements.remove(0) } else { Element::Sequence(elements) })
+}
+
+fn parse_char_class(spec: &str) -> Element {
+    let (negated, spec) = if let Some(rest) = spec.strip_prefix('^') { (true, rest) } else { (false, spec) };
+    let chars: Vec<char> = spec.chars().collect();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            ranges.push((chars[i], chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((chars[i], chars[i]));
+            i += 1;
+        }
+    }
+    Element::CharClass { chars: ranges, negated }
+}
+
+fn split_top_level(expr: &str, sep: char) -> Vec<&str> {
+    let mut in_literal = false;
+    let mut bracket_depth = 0;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        match c {
+            '"' => in_literal = !in_literal,
+            '[' if !in_literal => bracket_depth += 1,
+            ']' if !in_literal => bracket_depth -= 1,
+            _ if c == sep && !in_literal && bracket_depth == 0 => {
+                parts.push(&expr[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&expr[start..]);
+    parts
+}
+
+/// Tries to match `element` against `chars` starting at `pos`, returning
+/// the furthest position reached on success. Alternation and repetition
+/// take the first successful branch in the recursive-descent style GBNF
+/// itself is normally interpreted with.
+fn match_element(element: &Element, rules: &HashMap<String, Element>, chars: &[char], pos: usize) -> Option<usize> {
+    match element {
+        Element::Literal(lit) => {
+            let lit_chars: Vec<char> = lit.chars().collect();
+            if chars[pos..].starts_with(lit_chars.as_slice()) { Some(pos + lit_chars.len()) } else { None }
+        }
+        Element::CharClass { chars: ranges, negated } => {
+            let c = *chars.get(pos)?;
+            let in_class = ranges.iter().any(|&(lo, hi)| c >= lo && c <= hi);
+            if in_class != *negated { Some(pos + 1) } else { None }
+        }
+        Element::Rule(name) => rules.get(name).and_then(|e| match_element(e, rules, chars, pos)),
+        Element::Sequence(elements) => {
+            let mut cur = pos;
+            for e in elements {
+                cur = match_element(e, rules, chars, cur)?;
+            }
+            Some(cur)
+        }
+        Element::Alternation(alts) => alts.iter().find_map(|e| match_element(e, rules, chars, pos)),
+        Element::Repeat(inner, min, max) => {
+            let mut cur = pos;
+            let mut count = 0;
+            while max.map(|m| count < m).unwrap_or(true) {
+                match match_element(inner, rules, chars, cur) {
+                    Some(next) if next > cur => { cur = next; count += 1; }
+                    _ => break,
+                }
+            }
+            if count >= *min { Some(cur) } else { None }
+        }
+    }
+}
+
+/// Like [`match_element`] but succeeds as soon as `chars` is exhausted
+/// partway through a valid derivation, since a partial completion is a
+/// *prefix* of some eventual full string, not necessarily a complete one.
+fn prefix_reachable(element: &Element, rules: &HashMap<String, Element>, chars: &[char], pos: usize) -> bool {
+    if pos >= chars.len() {
+        return true;
+    }
+    match element {
+        Element::Literal(lit) => {
+            let lit_chars: Vec<char> = lit.chars().collect();
+            let remaining = &chars[pos..];
+            remaining.len() <= lit_chars.len() && remaining == &lit_chars[..remaining.len()]
+        }
+        Element::CharClass { .. } => match_element(element, rules, chars, pos).is_some(),
+        Element::Rule(name) => rules.get(name).map(|e| prefix_reachable(e, rules, chars, pos)).unwrap_or(false),
+        Element::Sequence(elements) => sequence_prefix_reachable(elements, rules, chars, pos),
+        Element::Alternation(alts) => alts.iter().any(|e| prefix_reachable(e, rules, chars, pos)),
+        Element::Repeat(inner, _, _) => sequence_prefix_reachable(std::slice::from_ref(inner.as_ref()), rules, chars, pos)
+            || prefix_reachable_loop(inner, rules, chars, pos),
+    }
+}
+
+fn prefix_reachable_loop(inner: &Element, rules: &HashMap<String, Element>, chars: &[char], pos: usize) -> bool {
+    match match_element(inner, rules, chars, pos) {
+        Some(next) if next > pos => next >= chars.len() || prefix_reachable_loop(inner, rules, chars, next),
+        _ => false,
+    }
+}
+
+fn sequence_prefix_reachable(elements: &[Element], rules: &HashMap<String, Element>, chars: &[char], pos: usize) -> bool {
+    let Some((first, rest)) = elements.split_first() else { return pos >= chars.len() };
+    if pos >= chars.len() {
+        return true;
+    }
+    match match_element(first, rules, chars, pos) {
+        Some(next) => sequence_prefix_reachable(rest, rules, chars, next),
+        None => prefix_reachable(first, rules, chars, pos),
+    }
+}
+
+fn schema_to_element(schema: &Json, rules: &mut HashMap<String, Element>, depth: usize) -> Result<Element, ConstraintError> {
+    if let Some(values) = schema.get("enum").and_then(Json::as_array) {
+        let alts = values.iter().filter_map(Json::as_str).map(|s| Element::Literal(format!("\"{s}\""))).collect();
+        return Ok(Element::Alternation(alts));
+    }
+    match schema.get("type").and_then(Json::as_str) {
+        Some("string") => Ok(Element::Sequence(vec![
+            Element::Literal("\"".to_string()),
+            Element::Repeat(Box::new(Element::CharClass { chars: vec![('"', '"')], negated: true }), 0, None),
+            Element::Literal("\"".to_string()),
+        ])),
+        Some("number") | Some("integer") => Ok(Element::Repeat(Box::new(Element::CharClass { chars: vec![('0', '9'), ('.', '.'), ('-', '-')], negated: false }), 1, None)),
+        Some("boolean") => Ok(Element::Alternation(vec![Element::Literal("true".to_string()), Element::Literal("false".to_string())])),
+        Some("object") => {
+            let properties = schema.get("properties").ok_or_else(|| ConstraintError::UnsupportedSchema("object without properties".to_string()))?;
+            let mut fields = Vec::new();
+            if let Json::Object(map) = properties {
+                for (name, sub_schema) in map {
+                    let rule_name = format!("prop_{depth}_{name}");
+                    let value_element = schema_to_element(sub_schema, rules, depth + 1)?;
+                    rules.insert(rule_name.clone(), value_element);
+                    fields.push(Element::Sequence(vec![
+                        Element::Literal(format!("\"{name}\":")),
+                        Element::Rule(rule_name),
+                    ]));
+                }
+            }
+            let mut sequence = vec![Element::Literal("{".to_string())];
+            for (i, field) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    sequence.push(Element::Literal(",".to_string()));
+                }
+                sequence.push(field);
+            }
+            sequence.push(Element::Literal("}".to_string()));
+            Ok(Element::Sequence(sequence))
+        }
+        Some(other) => Err(ConstraintError::UnsupportedSchema(other.to_string())),
+        None => Err(ConstraintError::UnsupportedSchema("missing type".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gbnf_literal_alternation_matches_either_branch() {
+        let g = Grammar::from_gbnf(r#"root ::= "yes" | "no""#).unwrap();
+        assert!(g.matches("yes"));
+        assert!(g.matches("no"));
+        assert!(!g.matches("maybe"));
+    }
+
+    #[test]
+    fn gbnf_repeat_matches_zero_or_more_digits() {
+        let g = Grammar::from_gbnf(r#"root ::= [0-9]*"#).unwrap();
+        assert!(g.matches(""));
+        assert!(g.matches("42"));
+        assert!(!g.matches("4a"));
+    }
+
+    #[test]
+    fn prefix_is_valid_partway_through_a_literal() {
+        let g = Grammar::from_gbnf(r#"root ::= "hello""#).unwrap();
+        assert!(g.is_valid_prefix("hel"));
+        assert!(!g.is_valid_prefix("world"));
+    }
+
+    #[test]
+    fn json_schema_object_grammar_matches_a_conforming_document() {
+        let schema = Json::parse(r#"{"type":"object","properties":{"name":{"type":"string"}}}"#).unwrap();
+        let g = Grammar::from_json_schema(&schema).unwrap();
+        assert!(g.matches(r#"{"name":"a"}"#));
+        assert!(!g.matches(r#"{"name":1}"#));
+    }
+}
@@ -0,0 +1,160 @@
+//! Packs candidate context blocks (RAG chunks, system instructions, history
+//! turns, ...) into a fixed token budget for a target model, backing
+//! `/v1/context/assemble`. Every caller that assembles a prompt from
+//! several pieces today either concatenates them all and hopes the result
+//! fits, or estimates size with the character-count heuristics `rag.rs`
+//! documents as a stopgap — this does the packing itself, counted with a
+//! real tokenizer when one is available (see `token_counter_for`) rather
+//! than a character or whitespace proxy.
+
+use crate::gguf::GgufModel;
+use crate::registry::ModelRegistry;
+use crate::tokenizer::BpeTokenizer;
+use std::path::Path;
+
+/// One piece of context competing for a place in the assembled prompt.
+/// Higher `priority` blocks are kept first when the budget is tight; ties
+/// keep the caller's original ordering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBlock {
+    pub id: String,
+    pub text: String,
+    pub priority: i32,
+}
+
+/// One block that made it into the packed prompt, and how many tokens it
+/// cost against the budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedBlock {
+    pub id: String,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedContext {
+    pub prompt: String,
+    pub included: Vec<PackedBlock>,
+    pub dropped: Vec<String>,
+    pub total_tokens: usize,
+}
+
+/// Greedily packs `blocks` into `budget_tokens`, highest-priority first:
+/// each block is counted whole with `count_tokens` and kept only if it
+/// still fits the remaining budget, never truncated mid-block, since a
+/// context chunk cut off partway through is often worse than dropping it
+/// entirely. Kept blocks are re-joined in the caller's original order (not
+/// priority order) so the assembled prompt reads the way it was submitted,
+/// with a blank line between blocks.
+pub fn pack_context(blocks: &[ContextBlock], budget_tokens: usize, mut count_tokens: impl FnMut(&str) -> usize) -> PackedContext {
+    let mut order: Vec<usize> = (0..blocks.len()).collect();
+    order.sort_by(|&a, &b| blocks[b].priority.cmp(&blocks[a].priority));
+
+    let mut remaining = budget_tokens;
+    let mut kept = vec![false; blocks.len()];
+    let mut included = Vec::new();
+    let mut dropped = Vec::new();
+    for i in order {
+        let tokens = count_tokens(&blocks[i].text);
+        if tokens <= remaining {
+            kept[i] = true;
+            remaining -= tokens;
+            included.push(PackedBlock { id: blocks[i].id.clone(), tokens });
+        } else {
+            dropped.push(blocks[i].id.clone());
+        }
+    }
+
+    let prompt = (0..blocks.len()).filter(|&i| kept[i]).map(|i| blocks[i].text.as_str()).collect::<Vec<_>>().join("\n\n");
+    let total_tokens = included.iter().map(|b| b.tokens).sum();
+    PackedContext { prompt, included, dropped, total_tokens }
+}
+
+/// Loads `model_path` as a GGUF file and builds a token counter from its
+/// bundled vocab/merges (see `tokenizer::BpeTokenizer`). Returns `None` for
+/// anything that isn't a GGUF file with a vocab section, e.g. a test double
+/// registered by path alone — callers fall back to a proxy count in that
+/// case, the same way `context_policy.rs` already does for prompt fitting.
+pub fn real_token_counter(model_path: &Path) -> Option<impl Fn(&str) -> usize> {
+    let model = GgufModel::open(model_path).ok()?;
+    let tokenizer = BpeTokenizer::from_gguf(&model).ok()?;
+    Some(move |text: &str| tokenizer.encode(text).len())
+}
+
+/// Counts `text`'s tokens using `model_id`'s real tokenizer when the
+/// registry resolves it to a loadable GGUF file, falling back to the
+/// whitespace-word proxy used elsewhere in `server.rs` (`admit_request`,
+/// `context_policy::apply`) so an unregistered or non-GGUF model still gets
+/// a usable, if approximate, count instead of an error.
+pub fn count_tokens_for_model(registry: &ModelRegistry, model_id: &str, text: &str) -> usize {
+    if let Some(entry) = registry.resolve(model_id) {
+        if let Some(counter) = real_token_counter(&entry.path) {
+            return counter(text);
+        }
+    }
+    text.split_whitespace().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: &str, text: &str, priority: i32) -> ContextBlock {
+        ContextBlock { id: id.to_string(), text: text.to_string(), priority }
+    }
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn keeps_every_block_that_fits_the_budget() {
+        let blocks = vec![block("a", "one two", 0), block("b", "three four", 0)];
+        let packed = pack_context(&blocks, 10, words);
+        assert_eq!(packed.included.len(), 2);
+        assert!(packed.dropped.is_empty());
+        assert_eq!(packed.prompt, "one two\n\nthree four");
+    }
+
+    #[test]
+    fn drops_lower_priority_blocks_first_when_the_budget_is_tight() {
+        let blocks = vec![block("low", "one two three", 0), block("high", "four five", 5)];
+        let packed = pack_context(&blocks, 2, words);
+        assert_eq!(packed.included, vec![PackedBlock { id: "high".to_string(), tokens: 2 }]);
+        assert_eq!(packed.dropped, vec!["low".to_string()]);
+    }
+
+    #[test]
+    fn never_truncates_a_block_that_partially_fits() {
+        let blocks = vec![block("only", "one two three four five", 0)];
+        let packed = pack_context(&blocks, 3, words);
+        assert!(packed.included.is_empty());
+        assert_eq!(packed.dropped, vec!["only".to_string()]);
+        assert_eq!(packed.prompt, "");
+    }
+
+    #[test]
+    fn preserves_original_order_in_the_assembled_prompt_regardless_of_priority() {
+        let blocks = vec![block("first", "alpha", 0), block("second", "beta", 10)];
+        let packed = pack_context(&blocks, 10, words);
+        assert_eq!(packed.prompt, "alpha\n\nbeta");
+    }
+
+    #[test]
+    fn real_token_counter_returns_none_for_a_non_gguf_file() {
+        let dir = std::env::temp_dir().join(format!("context-assembly-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("not-a-model.gguf");
+        std::fs::write(&path, b"not a gguf file").unwrap();
+        assert!(real_token_counter(&path).is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn count_tokens_for_model_falls_back_to_the_word_proxy_for_an_unregistered_model() {
+        let dir = std::env::temp_dir().join(format!("context-assembly-registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let registry = ModelRegistry::open(&dir).unwrap();
+        assert_eq!(count_tokens_for_model(&registry, "missing", "one two three"), 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,147 @@
+//! What to do when a request's prompt is longer than the model's context
+//! window — `server.rs`'s `max_context_tokens` sets the limit, but nothing
+//! previously decided what happened once a prompt crossed it, so long
+//! prompts either silently degraded quality (if the backend truncated
+//! internally) or failed in whatever way the backend happened to fail.
+//! [`ContextPolicy`] makes that an explicit, per-request choice instead:
+//! reject outright, truncate the oldest turns, or slide the window forward.
+//!
+//! Token counting here is the same `split_whitespace()` proxy
+//! `admit_request`/tokens-per-second already use elsewhere in `server.rs` —
+//! a real backend's tokenizer would replace it, but which words get kept or
+//! dropped doesn't depend on how they're counted.
+
+/// A request or model's declared way of handling a too-long prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextPolicy {
+    /// Reject the request outright — the safest default, since a truncated
+    /// or slid prompt is silently different from the one the caller sent.
+    Error,
+    /// Keep the system prompt (if any) intact and drop the oldest words of
+    /// the remaining prompt until it fits.
+    Truncate,
+    /// Same externally observable truncate-oldest behavior as `Truncate`.
+    /// A KV-cache-aware backend would slide its existing cache forward
+    /// instead of recomputing from scratch, but nothing in this tree keeps
+    /// a real KV cache yet (see `model_pool.rs`'s prefix-cache doc
+    /// comment), so there's no cheaper path to take here today — the
+    /// distinct variant exists so a future backend has somewhere to hang
+    /// that optimization without changing the request's API shape.
+    Slide,
+}
+
+impl ContextPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(ContextPolicy::Error),
+            "truncate" => Some(ContextPolicy::Truncate),
+            "slide" => Some(ContextPolicy::Slide),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContextPolicy::Error => "error",
+            ContextPolicy::Truncate => "truncate",
+            ContextPolicy::Slide => "slide",
+        }
+    }
+}
+
+/// A prompt that didn't fit and, under [`ContextPolicy::Error`], wasn't
+/// allowed to be shrunk to fit either.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextOverflow {
+    pub prompt_tokens: usize,
+    pub limit: usize,
+}
+
+/// The result of running a prompt through [`apply`]: the prompt to
+/// actually use, and which policy (if any) had to act on it — `None` means
+/// the prompt already fit and nothing was changed.
+#[derive(Debug)]
+pub struct ContextFit {
+    pub prompt: String,
+    pub policy_applied: Option<ContextPolicy>,
+}
+
+/// Applies `policy` to `prompt` against `max_context_tokens`. `system_prompt`,
+/// when given, is always preserved in full ahead of whatever's kept of
+/// `prompt` — the oldest *user* content is what gets dropped first, not the
+/// instructions the caller specifically asked to keep in effect.
+pub fn apply(prompt: &str, system_prompt: Option<&str>, max_context_tokens: usize, policy: ContextPolicy) -> Result<ContextFit, ContextOverflow> {
+    let words: Vec<&str> = prompt.split_whitespace().collect();
+    if words.len() <= max_context_tokens {
+        return Ok(ContextFit { prompt: prompt.to_string(), policy_applied: None });
+    }
+
+    match policy {
+        ContextPolicy::Error => Err(ContextOverflow { prompt_tokens: words.len(), limit: max_context_tokens }),
+        ContextPolicy::Truncate | ContextPolicy::Slide => {
+            let system_words = system_prompt.map(|s| s.split_whitespace().count()).unwrap_or(0);
+            let budget = max_context_tokens.saturating_sub(system_words).max(1);
+            let kept = &words[words.len().saturating_sub(budget)..];
+            let mut fitted = String::new();
+            if let Some(sys) = system_prompt {
+                fitted.push_str(sys);
+                fitted.push(' ');
+            }
+            fitted.push_str(&kept.join(" "));
+            Ok(ContextFit { prompt: fitted, policy_applied: Some(policy) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_prompt_within_the_limit_is_returned_unchanged() {
+        let fit = apply("one two three", None, 10, ContextPolicy::Error).unwrap();
+        assert_eq!(fit.prompt, "one two three");
+        assert!(fit.policy_applied.is_none());
+    }
+
+    #[test]
+    fn error_policy_rejects_an_overflowing_prompt() {
+        let err = apply("one two three", None, 2, ContextPolicy::Error).unwrap_err();
+        assert_eq!(err, ContextOverflow { prompt_tokens: 3, limit: 2 });
+    }
+
+    #[test]
+    fn truncate_policy_keeps_the_most_recent_words() {
+        let fit = apply("one two three four", None, 2, ContextPolicy::Truncate).unwrap();
+        assert_eq!(fit.prompt, "three four");
+        assert_eq!(fit.policy_applied, Some(ContextPolicy::Truncate));
+    }
+
+    #[test]
+    fn truncate_policy_preserves_the_system_prompt_in_full() {
+        let fit = apply("one two three four five", Some("system rules"), 3, ContextPolicy::Truncate).unwrap();
+        assert_eq!(fit.prompt, "system rules five");
+    }
+
+    #[test]
+    fn truncate_policy_never_drops_below_one_word_of_the_prompt() {
+        let fit = apply("one two three", Some("a very long system prompt indeed"), 2, ContextPolicy::Truncate).unwrap();
+        assert!(fit.prompt.ends_with("three"));
+    }
+
+    #[test]
+    fn slide_policy_matches_truncates_externally_observable_result() {
+        let truncated = apply("one two three four", None, 2, ContextPolicy::Truncate).unwrap();
+        let slid = apply("one two three four", None, 2, ContextPolicy::Slide).unwrap();
+        assert_eq!(truncated.prompt, slid.prompt);
+        assert_eq!(slid.policy_applied, Some(ContextPolicy::Slide));
+    }
+
+    #[test]
+    fn parse_and_as_str_round_trip() {
+        for policy in [ContextPolicy::Error, ContextPolicy::Truncate, ContextPolicy::Slide] {
+            assert_eq!(ContextPolicy::parse(policy.as_str()), Some(policy));
+        }
+        assert_eq!(ContextPolicy::parse("bogus"), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! NVIDIA/CUDA GPU detection on Linux — the same "shell out instead of
+//! binding a vendor SDK" approach `gpu.rs` takes for Metal, since nothing
+//! in this tree has a dependency manager to declare a `cudarc`/`cuda-sys`
+//! binding against. `nvidia-smi` ships with every NVIDIA driver install,
+//! including Jetson's and Grace's, so parsing its CSV output covers
+//! desktop/server boxes and embedded boards with the same code path.
+//!
+//! This only detects hardware and derives the tensor-split ratios
+//! `llama_ffi.rs::LlamaModel::load` accepts; no [`InferenceBackend`] in
+//! this tree actually drives llama.cpp's CUDA path yet (see
+//! `llama_ffi.rs`'s module doc comment — `EchoBackend` is still the only
+//! backend that exists), so today the values computed here only feed the
+//! `gpu_memory_bytes` metric. They're the values a CUDA-backed backend
+//! would pass through unchanged once one exists.
+//!
+//! [`CudaInfo::effective_tensor_split`] lets `config::ServerConfig`'s
+//! `backend.tensor_split_override` replace the proportional-to-VRAM
+//! default with an operator-chosen split. That's as far as tensor
+//! parallelism goes in this tree today: actually splitting attention heads
+//! and MLP columns across devices and reducing their partial results back
+//! together is llama.cpp's own CUDA kernel work, done entirely on the
+//! other side of the `llama_ffi.rs` boundary this tree has no linked
+//! `libllama` to exercise (see that module's doc comment) — there's no
+//! all-reduce to implement here without a real multi-GPU kernel on either
+//! end of it.
+
+use std::process::Command;
+
+/// One GPU as reported by `nvidia-smi`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CudaDevice {
+    pub index: u32,
+    pub name: String,
+    pub memory_total_bytes: u64,
+}
+
+/// All NVIDIA GPUs visible to the driver on this host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CudaInfo {
+    pub devices: Vec<CudaDevice>,
+}
+
+impl CudaInfo {
+    #[cfg(target_os = "linux")]
+    pub fn detect() -> Option<CudaInfo> {
+        let csv = run_nvidia_smi()?;
+        let devices = parse_nvidia_smi_csv(&csv);
+        if devices.is_empty() {
+            return None;
+        }
+        Some(CudaInfo { devices })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn detect() -> Option<CudaInfo> {
+        None
+    }
+
+    /// Total VRAM across every detected device, for the same
+    /// `gpu_memory_bytes` metric `gpu.rs`'s `unified_memory_bytes` feeds.
+    pub fn total_memory_bytes(&self) -> u64 {
+        self.devices.iter().map(|d| d.memory_total_bytes).sum()
+    }
+
+    /// The `tensor_split` ratios `llama_ffi.rs::LlamaModel::load` expects:
+    /// each device's share of total VRAM, in device-index order. This is
+    /// the same proportional-to-memory default llama.cpp itself falls
+    /// back to when a caller doesn't set `tensor_split` explicitly, so a
+    /// single-GPU host (or one where every GPU has equal memory) gets
+    /// the same behavior it always had. Empty when there's no VRAM to
+    /// divide, since a zero-length split means "let llama.cpp use its own
+    /// default" rather than dividing by zero.
+    pub fn tensor_split(&self) -> Vec<f32> {
+        let total = self.total_memory_bytes();
+        if total == 0 {
+            return Vec::new();
+        }
+        self.devices.iter().map(|d| d.memory_total_bytes as f32 / total as f32).collect()
+    }
+
+    /// [`tensor_split`](Self::tensor_split)'s proportional-to-VRAM shares,
+    /// unless `config::ServerConfig::tensor_split_override` gives its own —
+    /// an operator may know something about their placement (NVLink
+    /// topology, a GPU reserved for another process) this host's VRAM
+    /// totals alone can't capture, the same way `backend_override` lets
+    /// them beat `backend::select`'s own preference order. `override_split`
+    /// is trusted as-is: `config::ServerConfig::validate` already rejected
+    /// anything that doesn't parse as a non-negative share.
+    pub fn effective_tensor_split(&self, override_split: Option<&[f32]>) -> Vec<f32> {
+        match override_split {
+            Some(split) if !split.is_empty() => split.to_vec(),
+            _ => self.tensor_split(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn run_nvidia_smi() -> Option<String> {
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=index,name,memory.total", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Parses `nvidia-smi --query-gpu=index,name,memory.total
+/// --format=csv,noheader,nounits` output, e.g. `0, NVIDIA A100, 40960`
+/// (memory reported in MiB). Lines that don't parse are skipped rather
+/// than aborting the whole scan, so one malformed row doesn't hide every
+/// other GPU on the host.
+fn parse_nvidia_smi_csv(csv: &str) -> Vec<CudaDevice> {
+    csv.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let index = fields.next()?.parse().ok()?;
+            let name = fields.next()?.to_string();
+            let memory_total_mib: u64 = fields.next()?.parse().ok()?;
+            Some(CudaDevice { index, name, memory_total_bytes: memory_total_mib * 1024 * 1024 })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_single_gpu_row() {
+        let devices = parse_nvidia_smi_csv("0, NVIDIA A100, 40960\n");
+        assert_eq!(devices, vec![CudaDevice { index: 0, name: "NVIDIA A100".to_string(), memory_total_bytes: 40960 * 1024 * 1024 }]);
+    }
+
+    #[test]
+    fn parses_multiple_gpu_rows() {
+        let devices = parse_nvidia_smi_csv("0, NVIDIA A100, 40960\n1, NVIDIA A100, 40960\n");
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[1].index, 1);
+    }
+
+    #[test]
+    fn skips_malformed_rows_instead_of_failing_the_whole_scan() {
+        let devices = parse_nvidia_smi_csv("not a gpu row\n0, NVIDIA A100, 40960\n");
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].index, 0);
+    }
+
+    #[test]
+    fn empty_output_yields_no_devices() {
+        assert!(parse_nvidia_smi_csv("").is_empty());
+    }
+
+    #[test]
+    fn total_memory_bytes_sums_every_device() {
+        let info = CudaInfo {
+            devices: vec![
+                CudaDevice { index: 0, name: "A".to_string(), memory_total_bytes: 10 },
+                CudaDevice { index: 1, name: "B".to_string(), memory_total_bytes: 30 },
+            ],
+        };
+        assert_eq!(info.total_memory_bytes(), 40);
+    }
+
+    #[test]
+    fn tensor_split_divides_proportionally_to_memory() {
+        let info = CudaInfo {
+            devices: vec![
+                CudaDevice { index: 0, name: "A".to_string(), memory_total_bytes: 10 },
+                CudaDevice { index: 1, name: "B".to_string(), memory_total_bytes: 30 },
+            ],
+        };
+        assert_eq!(info.tensor_split(), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn tensor_split_is_empty_when_there_is_no_vram_to_divide() {
+        let info = CudaInfo { devices: vec![CudaDevice { index: 0, name: "A".to_string(), memory_total_bytes: 0 }] };
+        assert!(info.tensor_split().is_empty());
+    }
+
+    #[test]
+    fn effective_tensor_split_falls_back_to_the_proportional_default_with_no_override() {
+        let info = CudaInfo {
+            devices: vec![
+                CudaDevice { index: 0, name: "A".to_string(), memory_total_bytes: 10 },
+                CudaDevice { index: 1, name: "B".to_string(), memory_total_bytes: 30 },
+            ],
+        };
+        assert_eq!(info.effective_tensor_split(None), vec![0.25, 0.75]);
+        assert_eq!(info.effective_tensor_split(Some(&[])), vec![0.25, 0.75]);
+    }
+
+    #[test]
+    fn effective_tensor_split_honors_an_operator_override() {
+        let info = CudaInfo {
+            devices: vec![
+                CudaDevice { index: 0, name: "A".to_string(), memory_total_bytes: 10 },
+                CudaDevice { index: 1, name: "B".to_string(), memory_total_bytes: 30 },
+            ],
+        };
+        assert_eq!(info.effective_tensor_split(Some(&[0.6, 0.4])), vec![0.6, 0.4]);
+    }
+}
@@ -0,0 +1,102 @@
+//! A single self-contained admin dashboard page: inline CSS and vanilla
+//! JS, no build step or external assets, served straight out of the
+//! binary as one HTML string. `admin.rs` already gives operators a JSON
+//! API for load/unload/drain/scheduler state, and `/metrics` reports
+//! request counts and queue depth (see `metrics::Registry::render`);
+//! this is that same data wrapped in a browser view for an operator on a
+//! Mac mini who wants to glance at what's loaded rather than reach for
+//! curl.
+//!
+//! The page itself carries no secret and isn't served under `/admin/` —
+//! it's reachable the same unauthenticated way `/healthz` is (see
+//! `server.rs`'s `route`), since an HTML page can't attach an
+//! `Authorization` header to its own initial load. The admin key is
+//! entered by the operator into the page and kept only in the browser
+//! tab's memory; every fetch the buttons trigger sends it as a `Bearer`
+//! header against the real `/admin/*` and `/metrics` endpoints, which
+//! enforce it exactly as they would for a curl invocation.
+pub const DASHBOARD_HTML: &str = r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>AI-server admin</title>
+<style>
+  body { font-family: -apple-system, sans-serif; margin: 2rem; color: #222; }
+  h1 { font-size: 1.2rem; }
+  section { margin-bottom: 1.5rem; }
+  input[type=text] { width: 20rem; padding: 0.3rem; }
+  button { padding: 0.3rem 0.8rem; margin-right: 0.4rem; }
+  pre { background: #f4f4f4; padding: 0.8rem; overflow-x: auto; white-space: pre-wrap; }
+  ul { padding-left: 1.2rem; }
+  .row { margin: 0.4rem 0; }
+</style>
+</head>
+<body>
+<h1>AI-server admin</h1>
+
+<section>
+  <div class="row">
+    Admin key: <input type="text" id="admin-key" placeholder="Bearer token">
+  </div>
+  <div class="row">
+    API key (for /metrics, leave blank to reuse the admin key): <input type="text" id="api-key" placeholder="Bearer token">
+    <button onclick="refresh()">Refresh</button>
+  </div>
+</section>
+
+<section>
+  <h2>Loaded models</h2>
+  <ul id="loaded-models"></ul>
+  <div class="row">
+    <input type="text" id="model-id" placeholder="model id">
+    <button onclick="modelAction('load')">Load</button>
+    <button onclick="modelAction('unload')">Unload</button>
+  </div>
+</section>
+
+<section>
+  <h2>Drain</h2>
+  <div class="row">
+    <button onclick="setDraining(true)">Start draining</button>
+    <button onclick="setDraining(false)">Stop draining</button>
+  </div>
+</section>
+
+<section>
+  <h2>Metrics (requests, queue depth, latency — raw Prometheus text)</h2>
+  <pre id="metrics">(not loaded)</pre>
+</section>
+
+<script>
+function adminKey() { return document.getElementById('admin-key').value; }
+function apiKey() { return document.getElementById('api-key').value || adminKey(); }
+function authHeaders() { return { 'Authorization': 'Bearer ' + adminKey() }; }
+
+async function refresh() {
+  const scheduler = await fetch('/admin/scheduler', { headers: authHeaders() }).then(r => r.json()).catch(() => null);
+  const list = document.getElementById('loaded-models');
+  list.innerHTML = '';
+  (scheduler && scheduler.loaded_models || []).forEach(id => {
+    const li = document.createElement('li');
+    li.textContent = id;
+    list.appendChild(li);
+  });
+
+  const metrics = await fetch('/metrics', { headers: { 'Authorization': 'Bearer ' + apiKey() } }).then(r => r.text()).catch(e => String(e));
+  document.getElementById('metrics').textContent = metrics;
+}
+
+async function modelAction(action) {
+  const id = document.getElementById('model-id').value;
+  if (!id) return;
+  await fetch(`/admin/models/${id}/${action}`, { method: 'POST', headers: authHeaders() });
+  refresh();
+}
+
+async function setDraining(draining) {
+  await fetch('/admin/drain', { method: 'POST', headers: authHeaders(), body: JSON.stringify({ draining }) });
+}
+</script>
+</body>
+</html>
+"#;
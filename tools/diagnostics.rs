@@ -0,0 +1,235 @@
+//! Structured hardware/capability report so a deployment orchestrator can
+//! scrape each node's capabilities programmatically (e.g. to decide which
+//! model size to schedule on which box) instead of parsing `println!`
+//! output.
+
+use crate::gpu::GpuInfo;
+use crate::hardware::CpuCapabilities;
+use crate::thermal::ThermalReading;
+
+/// Machine-readable snapshot of a node's hardware capabilities.
+#[derive(Debug)]
+pub struct Diagnostics {
+    pub arch: &'static str,
+    pub os: &'static str,
+    pub family: &'static str,
+    pub logical_cores: usize,
+    pub physical_cores: usize,
+    pub simd_features: SimdFeatures,
+    pub gflops: Option<f64>,
+    pub memory_available_bytes: Option<u64>,
+    pub gpu: Option<GpuInfo>,
+    pub thermal: Option<ThermalReading>,
+}
+
+#[derive(Debug)]
+pub struct SimdFeatures {
+    pub neon: bool,
+    pub sve: bool,
+    pub fp16: bool,
+    pub avx2: bool,
+    pub avx512f: bool,
+    pub fma: bool,
+}
+
+impl Diagnostics {
+    /// Collects a full diagnostics snapshot. `gflops` is the result of the
+    /// startup self-benchmark, if it was run; `None` when `--self-test` was
+    /// not passed, since running it is too expensive to do unconditionally.
+    pub fn collect(gflops: Option<f64>) -> Self {
+        let caps = CpuCapabilities::detect();
+        Diagnostics {
+            arch: caps.arch,
+            os: std::env::consts::OS,
+            family: std::env::consts::FAMILY,
+            logical_cores: caps.logical_cores,
+            physical_cores: caps.physical_cores,
+            simd_features: SimdFeatures {
+                neon: caps.neon,
+                sve: caps.sve,
+                fp16: caps.fp16,
+                avx2: caps.avx2,
+                avx512f: caps.avx512f,
+                fma: caps.fma,
+            },
+            gflops,
+            memory_available_bytes: Self::detect_memory_available(),
+            gpu: GpuInfo::detect(),
+            thermal: ThermalReading::probe(),
+        }
+    }
+
+    /// Serializes this report as JSON for `--report json`. Hand-rolled
+    /// rather than pulling in `serde`/`serde_json`, matching the rest of
+    /// this file's no-external-dependency approach; every field here is
+    /// either a fixed Rust identifier (`arch`/`os`/`family`) or a number, so
+    /// no string escaping is needed.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"arch\": \"{}\",\n  \"os\": \"{}\",\n  \"family\": \"{}\",\n  \"logical_cores\": {},\n  \"physical_cores\": {},\n  \"simd_features\": {{\n    \"neon\": {},\n    \"sve\": {},\n    \"fp16\": {},\n    \"avx2\": {},\n    \"avx512f\": {},\n    \"fma\": {}\n  }},\n  \"gflops\": {},\n  \"memory_available_bytes\": {},\n  \"gpu\": {},\n  \"thermal\": {}\n}}",
+            self.arch,
+            self.os,
+            self.family,
+            self.logical_cores,
+            self.physical_cores,
+            self.simd_features.neon,
+            self.simd_features.sve,
+            self.simd_features.fp16,
+            self.simd_features.avx2,
+            self.simd_features.avx512f,
+            self.simd_features.fma,
+            json_opt_f64(self.gflops),
+            json_opt_u64(self.memory_available_bytes),
+            json_opt_gpu(self.gpu.as_ref()),
+            json_opt_thermal(self.thermal.as_ref()),
+        )
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_memory_available() -> Option<u64> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        meminfo.lines().find_map(|line| {
+            let rest = line.strip_prefix("MemAvailable:")?;
+            let kib: u64 = rest.split_whitespace().next()?.parse().ok()?;
+            Some(kib * 1024)
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn detect_memory_available() -> Option<u64> {
+        None
+    }
+}
+
+fn json_opt_f64(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{v}"),
+        None => "null".to_string(),
+    }
+}
+
+fn json_opt_u64(value: Option<u64>) -> String {
+    match value {
+        Some(v) => format!("{v}"),
+        None => "null".to_string(),
+    }
+}
+
+fn json_opt_gpu(gpu: Option<&GpuInfo>) -> String {
+    match gpu {
+        None => "null".to_string(),
+        Some(gpu) => format!(
+            "{{\n    \"chipset\": \"{}\",\n    \"core_count\": {},\n    \"unified_memory_bytes\": {}\n  }}",
+            gpu.chipset,
+            json_opt_u64(gpu.core_count),
+            json_opt_u64(gpu.unified_memory_bytes),
+        ),
+    }
+}
+
+fn json_opt_thermal(thermal: Option<&ThermalReading>) -> String {
+    match thermal {
+        None => "null".to_string(),
+        Some(thermal) => format!(
+            "{{\n    \"temperature_celsius\": {},\n    \"power_watts\": {},\n    \"speed_limit_percent\": {}\n  }}",
+            json_opt_f64(thermal.temperature_celsius),
+            json_opt_f64(thermal.power_watts),
+            thermal.speed_limit_percent,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Diagnostics {
+        Diagnostics {
+            arch: "x86_64",
+            os: "linux",
+            family: "unix",
+            logical_cores: 4,
+            physical_cores: 2,
+            simd_features: SimdFeatures {
+                neon: false,
+                sve: false,
+                fp16: false,
+                avx2: true,
+                avx512f: false,
+                fma: true,
+            },
+            gflops: Some(1.5),
+            memory_available_bytes: Some(1024),
+            gpu: None,
+            thermal: None,
+        }
+    }
+
+    #[test]
+    fn json_opt_f64_formats_some_and_null_for_none() {
+        assert_eq!(json_opt_f64(Some(2.5)), "2.5");
+        assert_eq!(json_opt_f64(None), "null");
+    }
+
+    #[test]
+    fn json_opt_u64_formats_some_and_null_for_none() {
+        assert_eq!(json_opt_u64(Some(7)), "7");
+        assert_eq!(json_opt_u64(None), "null");
+    }
+
+    #[test]
+    fn to_json_includes_every_field_with_real_values() {
+        let json = sample().to_json();
+        assert!(json.contains("\"arch\": \"x86_64\""));
+        assert!(json.contains("\"logical_cores\": 4"));
+        assert!(json.contains("\"physical_cores\": 2"));
+        assert!(json.contains("\"avx2\": true"));
+        assert!(json.contains("\"avx512f\": false"));
+        assert!(json.contains("\"gflops\": 1.5"));
+        assert!(json.contains("\"memory_available_bytes\": 1024"));
+    }
+
+    #[test]
+    fn to_json_emits_null_for_absent_optional_fields() {
+        let mut diag = sample();
+        diag.gflops = None;
+        diag.memory_available_bytes = None;
+        let json = diag.to_json();
+        assert!(json.contains("\"gflops\": null"));
+        assert!(json.contains("\"memory_available_bytes\": null"));
+    }
+
+    #[test]
+    fn to_json_emits_null_gpu_when_absent() {
+        assert!(sample().to_json().contains("\"gpu\": null"));
+    }
+
+    #[test]
+    fn to_json_includes_gpu_fields_when_present() {
+        let mut diag = sample();
+        diag.gpu = Some(GpuInfo {
+            chipset: "Apple M2 Pro".to_string(),
+            core_count: Some(19),
+            unified_memory_bytes: Some(34_359_738_368),
+            max_threadgroup_memory_bytes: 32 * 1024,
+        });
+        let json = diag.to_json();
+        assert!(json.contains("\"chipset\": \"Apple M2 Pro\""));
+        assert!(json.contains("\"core_count\": 19"));
+    }
+
+    #[test]
+    fn to_json_emits_null_thermal_when_absent() {
+        assert!(sample().to_json().contains("\"thermal\": null"));
+    }
+
+    #[test]
+    fn to_json_includes_thermal_fields_when_present() {
+        let mut diag = sample();
+        diag.thermal = Some(ThermalReading { temperature_celsius: Some(65.0), power_watts: None, speed_limit_percent: 80 });
+        let json = diag.to_json();
+        assert!(json.contains("\"temperature_celsius\": 65"));
+        assert!(json.contains("\"power_watts\": null"));
+        assert!(json.contains("\"speed_limit_percent\": 80"));
+    }
+}
@@ -0,0 +1,187 @@
+//! Zero-config LAN discovery: a periodic UDP broadcast the server sends
+//! announcing itself, and a listener the `discover` CLI subcommand
+//! (`cli.rs`) drives to find every instance currently broadcasting.
+//!
+//! Real Bonjour/mDNS is DNS-SD over RFC 6762 multicast
+//! (224.0.0.251:5353), carrying DNS resource records (PTR/SRV/TXT). This
+//! tree has no DNS message codec and no dependency manager to pull one in
+//! (see `json.rs`'s own doc comment on the same constraint), so this
+//! builds the same "periodically shout your presence, listen for shouts"
+//! shape on plain UDP broadcast instead of true DNS-SD: an
+//! [`Announcement`] is a JSON object (the same `json.rs`/`ObjectBuilder`
+//! shape every other wire format in this tree already uses) sent as a
+//! single UDP datagram to the LAN broadcast address, rather than a DNS
+//! packet sent to a multicast group. A client on the same broadcast
+//! domain listening on [`DISCOVERY_PORT`] sees the same "who's out
+//! there" answer a real mDNS browse would give it, just not from an
+//! mDNS-speaking client (`dns-sd`, `avahi-browse`) — that would need the
+//! real wire format this tree can't build without a DNS/mDNS crate.
+
+use crate::json::{Json, ObjectBuilder};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// The fixed port every instance broadcasts to and `discover` listens on
+/// — analogous to mDNS's fixed 5353, but plain UDP broadcast rather than
+/// multicast DNS.
+pub const DISCOVERY_PORT: u16 = 53530;
+
+/// One instance's announcement: enough for a client to know it exists,
+/// where to reach it, and roughly what it can do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    pub name: String,
+    pub host_port: String,
+    pub models: Vec<String>,
+    pub capabilities: Vec<String>,
+}
+
+impl Announcement {
+    fn to_json(&self) -> Json {
+        ObjectBuilder::new()
+            .set("name", Json::String(self.name.clone()))
+            .set("host_port", Json::String(self.host_port.clone()))
+            .set("models", Json::Array(self.models.iter().cloned().map(Json::String).collect()))
+            .set("capabilities", Json::Array(self.capabilities.iter().cloned().map(Json::String).collect()))
+            .build()
+    }
+
+    fn from_json(parsed: &Json) -> Option<Announcement> {
+        let models: Vec<String> =
+            parsed.get("models").and_then(Json::as_array)?.iter().map(|m| m.as_str().map(str::to_string)).collect::<Option<_>>()?;
+        let capabilities: Vec<String> = parsed
+            .get("capabilities")
+            .and_then(Json::as_array)?
+            .iter()
+            .map(|c| c.as_str().map(str::to_string))
+            .collect::<Option<_>>()?;
+        Some(Announcement {
+            name: parsed.get("name").and_then(Json::as_str)?.to_string(),
+            host_port: parsed.get("host_port").and_then(Json::as_str)?.to_string(),
+            models,
+            capabilities,
+        })
+    }
+}
+
+/// Sends one `announcement` datagram to `addr`. A fresh ephemeral socket
+/// per call, the same one-socket-per-send shape
+/// `audit::AuditLogger::open_syslog` uses for its UDP sink, since
+/// broadcasting is cheap and infrequent (see `advertise_periodically`'s
+/// interval). Kept private and address-parameterized so tests can target
+/// `127.0.0.1` directly instead of the real LAN broadcast address.
+fn send_to(announcement: &Announcement, addr: (&str, u16)) -> std::io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_broadcast(true)?;
+    socket.send_to(announcement.to_json().to_string().as_bytes(), addr)?;
+    Ok(())
+}
+
+/// Broadcasts `announcement` once to the LAN broadcast address on `port`.
+pub fn advertise_once(announcement: &Announcement, port: u16) -> std::io::Result<()> {
+    send_to(announcement, ("255.255.255.255", port))
+}
+
+/// Spawns a background thread broadcasting `announcement_fn()`'s result
+/// every `interval` until the process exits. `announcement_fn` is called
+/// fresh each tick rather than once, so a caller whose model list changes
+/// at runtime (a newly loaded model, a config reload) doesn't need to
+/// restart this loop to advertise it. A failed send (no broadcast-capable
+/// interface, network unreachable) is dropped rather than logged here —
+/// the next tick tries again, the same "best effort, keep going" posture
+/// `metrics::Registry`'s counters take toward a single bad sample.
+pub fn advertise_periodically(announcement_fn: impl Fn() -> Announcement + Send + 'static, port: u16, interval: Duration) {
+    std::thread::spawn(move || loop {
+        let _ = advertise_once(&announcement_fn(), port);
+        std::thread::sleep(interval);
+    });
+}
+
+/// Listens on `port` for `duration`, collecting one [`Announcement`] per
+/// distinct `host_port` seen — a still-broadcasting instance would
+/// otherwise show up once per tick during the listen window. Used by the
+/// `discover` CLI subcommand.
+pub fn discover(port: u16, duration: Duration) -> std::io::Result<Vec<Announcement>> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))?;
+    socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+    let deadline = Instant::now() + duration;
+    let mut found: Vec<Announcement> = Vec::new();
+    let mut buf = [0u8; 4096];
+    while Instant::now() < deadline {
+        match socket.recv_from(&mut buf) {
+            Ok((n, _addr)) => {
+                let text = String::from_utf8_lossy(&buf[..n]);
+                if let Ok(parsed) = Json::parse(&text) {
+                    if let Some(announcement) = Announcement::from_json(&parsed) {
+                        if !found.iter().any(|a| a.host_port == announcement.host_port) {
+                            found.push(announcement);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Announcement {
+        Announcement {
+            name: "ai-server".to_string(),
+            host_port: "127.0.0.1:8080".to_string(),
+            models: vec!["echo-0".to_string()],
+            capabilities: vec!["chat_completions".to_string()],
+        }
+    }
+
+    #[test]
+    fn announcement_round_trips_through_json() {
+        let announcement = sample();
+        assert_eq!(Announcement::from_json(&announcement.to_json()), Some(announcement));
+    }
+
+    #[test]
+    fn from_json_rejects_an_object_missing_models() {
+        let json = ObjectBuilder::new().set("name", Json::String("x".to_string())).set("host_port", Json::String("h:1".to_string())).build();
+        assert_eq!(Announcement::from_json(&json), None);
+    }
+
+    #[test]
+    fn discover_finds_an_announcement_sent_to_loopback() {
+        let announcement = sample();
+        let port = 58631;
+        let sent = announcement.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            send_to(&sent, ("127.0.0.1", port)).unwrap();
+        });
+        let found = discover(port, Duration::from_millis(500)).unwrap();
+        assert_eq!(found, vec![announcement]);
+    }
+
+    #[test]
+    fn discover_deduplicates_repeated_announcements_from_the_same_instance() {
+        let announcement = sample();
+        let port = 58632;
+        let sent = announcement.clone();
+        std::thread::spawn(move || {
+            for _ in 0..3 {
+                std::thread::sleep(Duration::from_millis(20));
+                send_to(&sent, ("127.0.0.1", port)).unwrap();
+            }
+        });
+        let found = discover(port, Duration::from_millis(500)).unwrap();
+        assert_eq!(found, vec![announcement]);
+    }
+
+    #[test]
+    fn discover_returns_empty_when_nothing_broadcasts() {
+        let found = discover(58633, Duration::from_millis(100)).unwrap();
+        assert_eq!(found, vec![]);
+    }
+}
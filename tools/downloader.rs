@@ -0,0 +1,155 @@
+//! HuggingFace Hub model downloader with resume and checksum verification.
+//!
+//! This tree has no TLS implementation (see `sha1.rs`'s note on hand-rolled
+//! crypto being scoped to one protocol constant, not a general-purpose
+//! primitive) and Hub URLs are HTTPS-only, so `download` expects `base_url`
+//! to point at a plain-HTTP mirror or a local TLS-terminating proxy in
+//! front of `huggingface.co` — the download/resume/verify logic below is
+//! transport-agnostic once that's in place.
+
+use crate::sha256::{hex, sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Io(std::io::Error),
+    Http(String),
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(e: std::io::Error) -> Self {
+        DownloadError::Io(e)
+    }
+}
+
+/// Downloads `repo_id/filename` to `dest`, resuming from `dest.part` if a
+/// previous attempt left one behind, and verifying against
+/// `expected_sha256` (lowercase hex) once the transfer completes.
+pub fn download(
+    base_url: &str,
+    repo_id: &str,
+    filename: &str,
+    dest: &std::path::Path,
+    expected_sha256: &str,
+) -> Result<(), DownloadError> {
+    let partial_path = dest.with_extension("part");
+    let mut already_have = partial_path.metadata().map(|m| m.len()).unwrap_or(0);
+
+    loop {
+        let path = format!("/{repo_id}/resolve/main/{filename}");
+        let response = http_get_range(base_url, &path, already_have)?;
+        if response.status == 200 && already_have > 0 {
+            // Server ignored our Range request (no partial-content support);
+            // restart the file from scratch rather than corrupt it.
+            fs::remove_file(&partial_path).ok();
+            already_have = 0;
+        } else if response.status != 200 && response.status != 206 {
+            return Err(DownloadError::Http(format!("unexpected status {}", response.status)));
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&partial_path)?;
+        file.write_all(&response.body)?;
+
+        if let Some(total) = response.content_range_total {
+            let have_now = already_have + response.body.len() as u64;
+            if have_now < total {
+                already_have = have_now;
+                continue;
+            }
+        }
+        break;
+    }
+
+    let mut contents = Vec::new();
+    File::open(&partial_path)?.read_to_end(&mut contents)?;
+    let actual = hex(&sha256(&contents));
+    if !actual.eq_ignore_ascii_case(expected_sha256) {
+        return Err(DownloadError::ChecksumMismatch { expected: expected_sha256.to_string(), actual });
+    }
+
+    fs::rename(&partial_path, dest)?;
+    Ok(())
+}
+
+struct RawResponse {
+    status: u16,
+    /// Total resource size from a `Content-Range: bytes X-Y/TOTAL` header,
+    /// used to know when a resumed download is actually finished.
+    content_range_total: Option<u64>,
+    body: Vec<u8>,
+}
+
+/// Issues a `GET` with `Range: bytes=<start>-` against a plain-HTTP
+/// `host[:port]` base URL. No redirect following, no HTTPS — see the
+/// module doc comment.
+fn http_get_range(base_url: &str, path: &str, start: u64) -> Result<RawResponse, DownloadError> {
+    let host = base_url.trim_start_matches("http://");
+    let mut stream = TcpStream::connect(host)
+        .map_err(|e| DownloadError::Http(format!("connect to {host} failed: {e}")))?;
+    let host_header = host.split(':').next().unwrap_or(host);
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: {host_header}\r\nRange: bytes={start}-\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    parse_raw_response(&raw)
+}
+
+fn parse_raw_response(raw: &[u8]) -> Result<RawResponse, DownloadError> {
+    let split_at = find_header_body_split(raw)
+        .ok_or_else(|| DownloadError::Http("malformed HTTP response".to_string()))?;
+    let head = std::str::from_utf8(&raw[..split_at])
+        .map_err(|_| DownloadError::Http("non-UTF-8 response headers".to_string()))?;
+    let body = raw[split_at..].to_vec();
+
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or("");
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| DownloadError::Http(format!("bad status line: {status_line}")))?;
+
+    let content_range_total = lines
+        .find_map(|line| line.strip_prefix("Content-Range: bytes "))
+        .and_then(|v| v.split('/').nth(1))
+        .and_then(|v| v.trim().parse().ok());
+
+    Ok(RawResponse { status, content_range_total, body })
+}
+
+fn find_header_body_split(raw: &[u8]) -> Option<usize> {
+    raw.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_raw_response_reads_status_and_content_range() {
+        let raw = b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 10-19/20\r\n\r\nhelloworld";
+        let response = parse_raw_response(raw).unwrap();
+        assert_eq!(response.status, 206);
+        assert_eq!(response.content_range_total, Some(20));
+        assert_eq!(response.body, b"helloworld");
+    }
+
+    #[test]
+    fn parse_raw_response_handles_missing_content_range() {
+        let raw = b"HTTP/1.1 200 OK\r\n\r\nfull-body";
+        let response = parse_raw_response(raw).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_range_total, None);
+    }
+
+    #[test]
+    fn parse_raw_response_rejects_malformed_input() {
+        assert!(parse_raw_response(b"garbage no separator").is_err());
+    }
+}
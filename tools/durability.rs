@@ -0,0 +1,129 @@
+//! Crash-safe writes and startup recovery for this tree's disk-backed
+//! stores (`sessions::SessionStore`, `registry::ModelRegistry`,
+//! `jobs::JobRegistry`, ...). Each keeps its own directory and file
+//! layout — there's no single unified "state directory" — so rather than
+//! impose one, this module gives every store the same two primitives:
+//!
+//! - [`atomic_write`] never overwrites a file in place. It writes to a
+//!   sibling `<path>.tmp`, `fsync`s it, then `rename`s it over `path` —
+//!   the same "write to a `.part` sibling, verify, then `fs::rename` over
+//!   the real path" shape `downloader.rs` already uses to make a
+//!   mid-download crash resumable rather than corrupting. POSIX
+//!   `rename()` is atomic on the same filesystem, so a reader — including
+//!   the process itself on its next startup — only ever sees the old
+//!   complete file or the new complete one, never a partial write.
+//! - [`recover_dir`] is the startup counterpart: a crash landing between
+//!   the write and the rename leaves a `<path>.tmp` behind with no
+//!   corresponding partial `path`, since `path` itself was never touched.
+//!   Each store's `open()` runs this over its own root before trusting
+//!   anything it finds there, deleting any leftover `.tmp` file — the
+//!   in-progress write it belonged to never completed, so there is
+//!   nothing to recover from it, only to discard.
+//!
+//! This isn't a write-ahead log: nothing here lets a store resume a write
+//! that was interrupted, only guarantees it never observes a half-written
+//! one. `registry::ModelRegistry` goes further still by treating its
+//! catalog as a rebuildable cache (see its own doc comment), which is a
+//! stronger guarantee this module doesn't try to replace, only to make
+//! less often necessary.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Writes `contents` to `path` without ever leaving a partially-written
+/// `path` behind. See the module doc comment for the write-tmp-then-rename
+/// approach and why it's sufficient without a write-ahead log.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp_path = tmp_path_for(path);
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Reports what a [`recover_dir`] pass found and discarded.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RecoveryReport {
+    pub removed_partial_writes: Vec<PathBuf>,
+}
+
+/// Deletes every `*.tmp` file directly under `root`, each one the
+/// leftover of an [`atomic_write`] that crashed before its final rename.
+/// Not recursive: every store in this tree lays its own files out flat
+/// under its root (`sessions::SessionStore`'s `<root>/<id>.json`,
+/// `registry::ModelRegistry`'s `<root>/catalog.json`, ...), so a flat scan
+/// is enough. Safe to call on a directory with no `.tmp` files present —
+/// the common case on a clean shutdown.
+pub fn recover_dir(root: &Path) -> std::io::Result<RecoveryReport> {
+    let mut report = RecoveryReport::default();
+    if !root.exists() {
+        return Ok(report);
+    }
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("tmp") {
+            std::fs::remove_file(&path)?;
+            report.removed_partial_writes.push(path);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_write_creates_the_file_with_the_given_contents() {
+        let dir = std::env::temp_dir().join(format!("ai-server-durability-test-create-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        atomic_write(&path, b"{\"a\":1}").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"a\":1}");
+        assert!(!tmp_path_for(&path).exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn atomic_write_replaces_existing_contents_without_a_leftover_tmp_file() {
+        let dir = std::env::temp_dir().join(format!("ai-server-durability-test-replace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        atomic_write(&path, b"old").unwrap();
+        atomic_write(&path, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!tmp_path_for(&path).exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn recover_dir_removes_stray_tmp_files_and_leaves_real_files_alone() {
+        let dir = std::env::temp_dir().join(format!("ai-server-durability-test-recover-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("catalog.json"), "{}").unwrap();
+        std::fs::write(dir.join("catalog.json.tmp"), "partial").unwrap();
+
+        let report = recover_dir(&dir).unwrap();
+        assert_eq!(report.removed_partial_writes, vec![dir.join("catalog.json.tmp")]);
+        assert!(dir.join("catalog.json").exists());
+        assert!(!dir.join("catalog.json.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn recover_dir_is_a_no_op_on_a_missing_directory() {
+        let dir = std::env::temp_dir().join(format!("ai-server-durability-test-missing-{}", std::process::id()));
+        assert_eq!(recover_dir(&dir).unwrap(), RecoveryReport::default());
+    }
+}
@@ -0,0 +1,299 @@
+//! Persistent embedding cache: `/v1/embeddings` and `watcher.rs`'s folder
+//! re-indexing both re-embed a chunk of text every time they see it, even
+//! when the exact same (model, text) pair was already embedded on a
+//! previous request or a previous scan of the same document — editing one
+//! paragraph of a large file re-embeds every chunk in it today, not just
+//! the changed one. Keyed by a hash of the model id and the raw input
+//! text so an unchanged chunk is a cache hit even across restarts; disk
+//! layout mirrors `sessions.rs`'s one-file-per-key store.
+//!
+//! `max_entries` bounds disk usage the same "evict the single oldest
+//! entry" way `response_cache.rs` bounds its in-memory table — a full LRU
+//! isn't worth the bookkeeping here either.
+
+use crate::durability;
+use crate::embeddings::{embed_batch, EmbeddingBackend, EmbeddingRequest};
+use crate::json::{Json, ObjectBuilder};
+use crate::sha256::sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type CacheKey = String;
+
+/// Hashes the (model, content) pair a cached vector is keyed by.
+pub fn cache_key(model_id: &str, content: &str) -> CacheKey {
+    let digest = sha256(format!("{model_id}\u{0}{content}").as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+struct CachedVector {
+    vector: Vec<f32>,
+    inserted_at: u64,
+}
+
+#[derive(Default)]
+struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Disk-backed cache of embedding vectors, laid out as
+/// `<root>/<hash>.json`, one file per (model, content) pair.
+pub struct EmbeddingCache {
+    root: PathBuf,
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, CachedVector>>,
+    stats: Stats,
+    on_evict: Option<Box<dyn Fn(&str) + Send + Sync>>,
+}
+
+impl EmbeddingCache {
+    /// Opens (creating if needed) a cache rooted at `root`, loading every
+    /// entry already on disk into memory up front — the same eager-load
+    /// posture `registry::ModelRegistry::open` takes toward its catalog.
+    pub fn open(root: impl Into<PathBuf>, max_entries: usize) -> std::io::Result<EmbeddingCache> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        durability::recover_dir(&root)?;
+        let mut entries = HashMap::new();
+        for entry in fs::read_dir(&root)? {
+            let path = entry?.path();
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let Ok(text) = fs::read_to_string(&path) else { continue };
+            let Ok(parsed) = Json::parse(&text) else { continue };
+            let Some(vector) = parsed
+                .get("vector")
+                .and_then(Json::as_array)
+                .map(|values| values.iter().filter_map(Json::as_f64).map(|v| v as f32).collect())
+            else {
+                continue;
+            };
+            let inserted_at = parsed.get("inserted_at").and_then(Json::as_f64).unwrap_or(0.0) as u64;
+            entries.insert(key.to_string(), CachedVector { vector, inserted_at });
+        }
+        Ok(EmbeddingCache { root, max_entries, entries: Mutex::new(entries), stats: Stats::default(), on_evict: None })
+    }
+
+    /// Registers a hook called with the evicted key each time
+    /// [`insert`](Self::insert) drops the oldest entry to stay within
+    /// `max_entries` — mirrors `response_cache::ResponseCache::with_eviction_hook`.
+    pub fn with_eviction_hook(mut self, hook: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Box::new(hook));
+        self
+    }
+
+    fn path_for(&self, key: &CacheKey) -> PathBuf {
+        self.root.join(format!("{key}.json"))
+    }
+
+    /// Returns the cached vector for `(model_id, content)`, recording a
+    /// hit or miss for [`hit_rate`](Self::hit_rate).
+    pub fn get(&self, model_id: &str, content: &str) -> Option<Vec<f32>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&cache_key(model_id, content)) {
+            Some(cached) => {
+                self.stats.hits.fetch_add(1, Ordering::Relaxed);
+                Some(cached.vector.clone())
+            }
+            None => {
+                self.stats.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Inserts `vector` under `(model_id, content)`, evicting the single
+    /// oldest entry first if the cache is already at `max_entries`.
+    pub fn insert(&self, model_id: &str, content: &str, vector: Vec<f32>) -> std::io::Result<()> {
+        let key = cache_key(model_id, content);
+        let inserted_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, v)| v.inserted_at).map(|(k, _)| k.clone()) {
+                let _ = fs::remove_file(self.path_for(&oldest));
+                entries.remove(&oldest);
+                if let Some(on_evict) = &self.on_evict {
+                    on_evict(&oldest);
+                }
+            }
+        }
+        let body = ObjectBuilder::new()
+            .set("vector", Json::Array(vector.iter().map(|&v| Json::Number(v as f64)).collect()))
+            .set("inserted_at", Json::Number(inserted_at as f64))
+            .build();
+        durability::atomic_write(&self.path_for(&key), body.to_string().as_bytes())?;
+        entries.insert(key, CachedVector { vector, inserted_at });
+        Ok(())
+    }
+
+    /// Fraction of [`get`](Self::get) calls that found a cached vector,
+    /// `0.0` before the first call — surfaced on `/metrics` via
+    /// `metrics::Registry::set_embedding_cache_hit_ratio`.
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.stats.hits.load(Ordering::Relaxed) as f64;
+        let misses = self.stats.misses.load(Ordering::Relaxed) as f64;
+        if hits + misses == 0.0 {
+            0.0
+        } else {
+            hits / (hits + misses)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+/// Embeds `inputs` under `model_id`, serving every input already present
+/// in `cache` from disk instead of calling `backend`, and inserting the
+/// rest into `cache` once computed. Preserves `inputs`' order in the
+/// returned vector the same way plain [`embed_batch`] does.
+pub fn embed_batch_cached(
+    cache: &EmbeddingCache,
+    backend: &dyn EmbeddingBackend,
+    model_id: &str,
+    inputs: &[String],
+    params: &EmbeddingRequest,
+) -> Vec<Vec<f32>> {
+    let mut results: Vec<Option<Vec<f32>>> = inputs.iter().map(|text| cache.get(model_id, text)).collect();
+
+    let misses: Vec<usize> = results.iter().enumerate().filter(|(_, v)| v.is_none()).map(|(i, _)| i).collect();
+    if !misses.is_empty() {
+        let token_batches: Vec<Vec<u32>> = misses.iter().map(|&i| inputs[i].bytes().map(u32::from).collect()).collect();
+        let vectors = embed_batch(backend, &token_batches, params);
+        for (&i, vector) in misses.iter().zip(vectors) {
+            let _ = cache.insert(model_id, &inputs[i], vector.clone());
+            results[i] = Some(vector);
+        }
+    }
+
+    results.into_iter().map(|v| v.unwrap_or_default()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingBackend {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl EmbeddingBackend for CountingBackend {
+        fn hidden_size(&self) -> usize {
+            4
+        }
+
+        fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            tokens.iter().map(|&t| vec![t as f32; 4]).collect()
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-embedding-cache-test-{name}-{:x}", sha256(name.as_bytes())[0]));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn get_returns_none_for_a_missing_entry() {
+        let dir = temp_dir("missing");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        assert!(cache.get("m", "hello").is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn insert_then_get_round_trips_the_vector() {
+        let dir = temp_dir("roundtrip");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        cache.insert("m", "hello", vec![1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(cache.get("m", "hello"), Some(vec![1.0, 2.0, 3.0]));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn different_models_do_not_share_a_cache_entry() {
+        let dir = temp_dir("per-model");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        cache.insert("m1", "hello", vec![1.0]).unwrap();
+        assert!(cache.get("m2", "hello").is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn entries_survive_reopening_the_cache_from_disk() {
+        let dir = temp_dir("persist");
+        {
+            let cache = EmbeddingCache::open(&dir, 10).unwrap();
+            cache.insert("m", "hello", vec![9.0, 8.0]).unwrap();
+        }
+        let reopened = EmbeddingCache::open(&dir, 10).unwrap();
+        assert_eq!(reopened.get("m", "hello"), Some(vec![9.0, 8.0]));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn insert_evicts_the_oldest_entry_once_max_entries_is_reached() {
+        let dir = temp_dir("evict");
+        let cache = EmbeddingCache::open(&dir, 2).unwrap();
+        cache.insert("m", "a", vec![1.0]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        cache.insert("m", "b", vec![2.0]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        cache.insert("m", "c", vec![3.0]).unwrap();
+        assert!(cache.get("m", "a").is_none());
+        assert!(cache.get("m", "b").is_some());
+        assert!(cache.get("m", "c").is_some());
+        assert_eq!(cache.len(), 2);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn hit_rate_reflects_gets_after_a_cold_start() {
+        let dir = temp_dir("hit-rate");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        assert_eq!(cache.hit_rate(), 0.0);
+        cache.insert("m", "hello", vec![1.0]).unwrap();
+        cache.get("m", "hello");
+        cache.get("m", "missing");
+        assert_eq!(cache.hit_rate(), 0.5);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn embed_batch_cached_only_calls_the_backend_for_uncached_inputs() {
+        let dir = temp_dir("batch");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        let backend = CountingBackend { calls: std::sync::atomic::AtomicUsize::new(0) };
+        let params = EmbeddingRequest::default();
+        let inputs = vec!["one".to_string(), "two".to_string()];
+
+        let first = embed_batch_cached(&cache, &backend, "m", &inputs, &params);
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 2);
+
+        let second = embed_batch_cached(&cache, &backend, "m", &inputs, &params);
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 2);
+        assert_eq!(first, second);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn embed_batch_cached_only_recomputes_the_changed_input() {
+        let dir = temp_dir("partial");
+        let cache = EmbeddingCache::open(&dir, 10).unwrap();
+        let backend = CountingBackend { calls: std::sync::atomic::AtomicUsize::new(0) };
+        let params = EmbeddingRequest::default();
+
+        embed_batch_cached(&cache, &backend, "m", &["one".to_string(), "two".to_string()], &params);
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 2);
+
+        embed_batch_cached(&cache, &backend, "m", &["one".to_string(), "three".to_string()], &params);
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 3);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
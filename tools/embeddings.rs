@@ -0,0 +1,122 @@
+//! Embedding generation: turns per-token hidden states into a single
+//! fixed-size vector per input, via configurable pooling and optional
+//! L2 normalization / dimension truncation. Backed by the same
+//! [`crate::InferenceBackend`]-style trait split as chat completions, so a
+//! real backend supplies hidden states and this module only does pooling
+//! math — no model-specific code belongs here.
+
+/// How per-token hidden states are pooled into one vector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pooling {
+    Mean,
+    Cls,
+    LastToken,
+}
+
+#[derive(Debug)]
+pub struct EmbeddingRequest {
+    pub pooling: Pooling,
+    pub normalize: bool,
+    /// Truncate the pooled vector to this many leading dimensions
+    /// (Matryoshka-style truncated embeddings). `None` keeps the full width.
+    pub dimensions: Option<usize>,
+}
+
+impl Default for EmbeddingRequest {
+    fn default() -> Self {
+        EmbeddingRequest { pooling: Pooling::Mean, normalize: true, dimensions: None }
+    }
+}
+
+/// A model backend that can turn one input's tokens into a
+/// `[num_tokens, hidden_size]` matrix of hidden states.
+pub trait EmbeddingBackend: Send + Sync {
+    fn hidden_size(&self) -> usize;
+    fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>>;
+}
+
+/// Pools `hidden_states` (one row per token) down to a single vector per
+/// [`EmbeddingRequest`]'s [`Pooling`] strategy, then applies normalization
+/// and truncation.
+pub fn embed(hidden_states: &[Vec<f32>], params: &EmbeddingRequest) -> Vec<f32> {
+    let mut pooled = match params.pooling {
+        Pooling::Cls => hidden_states.first().cloned().unwrap_or_default(),
+        Pooling::LastToken => hidden_states.last().cloned().unwrap_or_default(),
+        Pooling::Mean => mean_pool(hidden_states),
+    };
+
+    if let Some(dims) = params.dimensions {
+        pooled.truncate(dims);
+    }
+    if params.normalize {
+        l2_normalize(&mut pooled);
+    }
+    pooled
+}
+
+/// Runs [`embed`] over every input in `batch`, one call per input — the
+/// backend itself decides whether to actually batch the underlying
+/// forward passes; this just keeps the per-input pooling logic in one
+/// place for callers handling `/v1/embeddings`'s `input: [...]` array.
+pub fn embed_batch(backend: &dyn EmbeddingBackend, inputs: &[Vec<u32>], params: &EmbeddingRequest) -> Vec<Vec<f32>> {
+    inputs.iter().map(|tokens| embed(&backend.hidden_states(tokens), params)).collect()
+}
+
+fn mean_pool(hidden_states: &[Vec<f32>]) -> Vec<f32> {
+    let Some(width) = hidden_states.first().map(Vec::len) else { return Vec::new() };
+    let mut sum = vec![0.0f32; width];
+    for row in hidden_states {
+        for (s, &v) in sum.iter_mut().zip(row) {
+            *s += v;
+        }
+    }
+    let n = hidden_states.len() as f32;
+    for s in &mut sum {
+        *s /= n;
+    }
+    sum
+}
+
+fn l2_normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in vector {
+            *v /= norm;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mean_pooling_averages_across_tokens() {
+        let states = vec![vec![1.0, 1.0], vec![3.0, 5.0]];
+        let params = EmbeddingRequest { pooling: Pooling::Mean, normalize: false, dimensions: None };
+        assert_eq!(embed(&states, &params), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn cls_pooling_takes_the_first_token() {
+        let states = vec![vec![9.0, 9.0], vec![0.0, 0.0]];
+        let params = EmbeddingRequest { pooling: Pooling::Cls, normalize: false, dimensions: None };
+        assert_eq!(embed(&states, &params), vec![9.0, 9.0]);
+    }
+
+    #[test]
+    fn normalize_produces_a_unit_vector() {
+        let states = vec![vec![3.0, 4.0]];
+        let params = EmbeddingRequest { pooling: Pooling::LastToken, normalize: true, dimensions: None };
+        let v = embed(&states, &params);
+        let norm = (v[0] * v[0] + v[1] * v[1]).sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dimensions_truncates_before_normalizing() {
+        let states = vec![vec![3.0, 4.0, 0.0]];
+        let params = EmbeddingRequest { pooling: Pooling::LastToken, normalize: true, dimensions: Some(2) };
+        assert_eq!(embed(&states, &params).len(), 2);
+    }
+}
@@ -0,0 +1,198 @@
+//! Crate-wide error taxonomy for turning a failure into an OpenAI-style
+//! `{"error": {...}}` JSON response. `server.rs`'s old `error_response`
+//! helper hardcoded `"type": "invalid_request_error"` on every status
+//! code, so a `500` from a broken session file looked identical, on the
+//! wire, to a genuinely malformed request — a client had no way to tell a
+//! transient failure worth retrying apart from one that never will
+//! succeed. [`ServerError::retryable`] is what actually answers that
+//! question; [`ServerError::error_type`]/[`ServerError::code`] give the
+//! same shape OpenAI's own API does.
+//!
+//! Most of `server.rs`'s ~100 error call sites report a `(status, reason,
+//! message)` triple they've already chosen rather than picking one of
+//! these five named failure modes, so [`classify`] buckets those by
+//! status-code range instead of guessing a specific variant it has no
+//! evidence for. Call sites that already know which of the five they're
+//! looking at (model resolution, admission control) construct the
+//! variant directly and get its `code` for free.
+
+use crate::http::Response;
+use crate::json::{Json, ObjectBuilder};
+
+/// A named failure mode, each with a fixed HTTP status and OpenAI-style
+/// `error.type`/`error.code`. The `String` in every variant is the
+/// human-readable message for `error.message`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerError {
+    ModelNotFound(String),
+    ContextLengthExceeded(String),
+    Overloaded(String),
+    BackendFailure(String),
+    InvalidRequest(String),
+}
+
+impl ServerError {
+    pub fn status(&self) -> u16 {
+        match self {
+            ServerError::ModelNotFound(_) => 404,
+            ServerError::ContextLengthExceeded(_) => 400,
+            ServerError::Overloaded(_) => 429,
+            ServerError::BackendFailure(_) => 502,
+            ServerError::InvalidRequest(_) => 400,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            ServerError::ModelNotFound(_) => "Not Found",
+            ServerError::ContextLengthExceeded(_) => "Bad Request",
+            ServerError::Overloaded(_) => "Too Many Requests",
+            ServerError::BackendFailure(_) => "Bad Gateway",
+            ServerError::InvalidRequest(_) => "Bad Request",
+        }
+    }
+
+    /// OpenAI's own top-level `error.type` bucket.
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            ServerError::ModelNotFound(_) | ServerError::ContextLengthExceeded(_) | ServerError::InvalidRequest(_) => {
+                "invalid_request_error"
+            }
+            ServerError::Overloaded(_) => "overloaded_error",
+            ServerError::BackendFailure(_) => "server_error",
+        }
+    }
+
+    /// OpenAI's own `error.code`, for the two failure modes it documents
+    /// one for. The rest carry no code more specific than their
+    /// `error_type`.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            ServerError::ModelNotFound(_) => Some("model_not_found"),
+            ServerError::ContextLengthExceeded(_) => Some("context_length_exceeded"),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ServerError::ModelNotFound(m)
+            | ServerError::ContextLengthExceeded(m)
+            | ServerError::Overloaded(m)
+            | ServerError::BackendFailure(m)
+            | ServerError::InvalidRequest(m) => m,
+        }
+    }
+
+    /// Whether a client should expect a bare retry (after backing off) to
+    /// possibly succeed, as opposed to the request itself needing to
+    /// change first. Only an overloaded server or a transient backend
+    /// fault fit that description — a model that doesn't exist, a request
+    /// too long for any budget, or a malformed body won't fix themselves
+    /// on a second attempt.
+    pub fn retryable(&self) -> bool {
+        matches!(self, ServerError::Overloaded(_) | ServerError::BackendFailure(_))
+    }
+
+    pub fn into_response(self) -> Response {
+        let status = self.status();
+        let reason = self.reason();
+        let error_type = self.error_type();
+        let code = self.code();
+        let retryable = self.retryable();
+        let mut error_object = ObjectBuilder::new()
+            .set("message", Json::String(self.message().to_string()))
+            .set("type", Json::String(error_type.to_string()))
+            .set("retryable", Json::Bool(retryable));
+        if let Some(code) = code {
+            error_object = error_object.set("code", Json::String(code.to_string()));
+        }
+        let body = ObjectBuilder::new().set("error", error_object.build()).build();
+        Response::json(status, reason, &body.to_string())
+    }
+}
+
+/// Classifies an ad-hoc `(status, message)` error for the JSON body's
+/// `type`/`retryable` fields without changing the status the caller
+/// already chose — `429` is `overloaded_error` (the one status this tree
+/// already reserves for transient rate/budget limits — see
+/// `server::admit_request`), `5xx` is a `server_error`, everything else is
+/// an `invalid_request_error`. No `error.code` is included here since a
+/// bare status code doesn't say which specific failure this was.
+pub fn classify(status: u16) -> (&'static str, bool) {
+    match status {
+        429 => ("overloaded_error", true),
+        500..=599 => ("server_error", true),
+        _ => ("invalid_request_error", false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_not_found_reports_a_404_with_its_documented_code() {
+        let response = ServerError::ModelNotFound("model \"x\" is not available".to_string()).into_response();
+        assert_eq!(response.status, 404);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"type\":\"invalid_request_error\""));
+        assert!(body.contains("\"code\":\"model_not_found\""));
+        assert!(body.contains("\"retryable\":false"));
+    }
+
+    #[test]
+    fn context_length_exceeded_reports_a_400_with_its_documented_code() {
+        let response = ServerError::ContextLengthExceeded("too long".to_string()).into_response();
+        assert_eq!(response.status, 400);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"code\":\"context_length_exceeded\""));
+        assert!(body.contains("\"retryable\":false"));
+    }
+
+    #[test]
+    fn overloaded_is_retryable() {
+        let response = ServerError::Overloaded("try again later".to_string()).into_response();
+        assert_eq!(response.status, 429);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"type\":\"overloaded_error\""));
+        assert!(body.contains("\"retryable\":true"));
+        assert!(!body.contains("\"code\""));
+    }
+
+    #[test]
+    fn backend_failure_is_retryable_and_has_no_code() {
+        let response = ServerError::BackendFailure("inference backend crashed".to_string()).into_response();
+        assert_eq!(response.status, 502);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"type\":\"server_error\""));
+        assert!(body.contains("\"retryable\":true"));
+        assert!(!body.contains("\"code\""));
+    }
+
+    #[test]
+    fn invalid_request_is_not_retryable() {
+        let response = ServerError::InvalidRequest("\"model\" is required".to_string()).into_response();
+        assert_eq!(response.status, 400);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"type\":\"invalid_request_error\""));
+        assert!(body.contains("\"retryable\":false"));
+    }
+
+    #[test]
+    fn classify_marks_429_as_overloaded_and_retryable() {
+        assert_eq!(classify(429), ("overloaded_error", true));
+    }
+
+    #[test]
+    fn classify_marks_5xx_as_server_error_and_retryable() {
+        assert_eq!(classify(500), ("server_error", true));
+        assert_eq!(classify(503), ("server_error", true));
+    }
+
+    #[test]
+    fn classify_defaults_everything_else_to_invalid_request() {
+        assert_eq!(classify(404), ("invalid_request_error", false));
+        assert_eq!(classify(400), ("invalid_request_error", false));
+    }
+}
@@ -0,0 +1,186 @@
+//! Perplexity and multiple-choice evaluation math, decoupled from any
+//! particular model backend via the [`Scorer`] trait: whatever ends up
+//! providing real per-token log-probabilities (llama.cpp FFI, pure-Rust
+//! kernels — see `server.rs`'s `InferenceBackend` doc comment) only needs to
+//! implement `Scorer::token_logprob`, and the perplexity/accuracy math below
+//! doesn't change. Until a real backend exists, [`UniformScorer`] plays the
+//! same role `EchoBackend` plays for the HTTP surface: it proves this
+//! module's contract end to end without claiming to say anything about
+//! actual model quality.
+
+use crate::json::Json;
+use crate::tokenizer::BpeTokenizer;
+
+/// Something that can score how likely `token` is given the tokens that
+/// came before it. A real backend implements this from its own logits;
+/// [`UniformScorer`] is the placeholder used until one exists.
+pub trait Scorer {
+    fn token_logprob(&self, context: &[u32], token: u32) -> f64;
+}
+
+/// Assigns every token the same log-probability, `ln(1 / vocab_size)`,
+/// regardless of context. Not a real language model — a fixed uniform
+/// distribution over the vocabulary — but enough to exercise the
+/// perplexity/accuracy math below without a real backend wired in yet (see
+/// the module doc comment).
+pub struct UniformScorer {
+    pub vocab_size: usize,
+}
+
+impl Scorer for UniformScorer {
+    fn token_logprob(&self, _context: &[u32], _token: u32) -> f64 {
+        -(self.vocab_size.max(1) as f64).ln()
+    }
+}
+
+/// Perplexity of `tokens` under `scorer`: `exp(-mean(log P(token_i |
+/// token_<i)))`, each token's context being everything before it in
+/// `tokens`. This is the standard left-to-right definition used to compare
+/// language models on a held-out corpus.
+pub fn perplexity(tokens: &[u32], scorer: &dyn Scorer) -> f64 {
+    if tokens.is_empty() {
+        return f64::NAN;
+    }
+    let mean_logprob: f64 = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, &token)| scorer.token_logprob(&tokens[..i], token))
+        .sum::<f64>()
+        / tokens.len() as f64;
+    (-mean_logprob).exp()
+}
+
+/// One multiple-choice item: a question, its candidate continuations, and
+/// which one is correct.
+#[derive(Debug, Clone)]
+pub struct McqItem {
+    pub question: String,
+    pub choices: Vec<String>,
+    pub answer_index: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct McqResult {
+    pub correct: usize,
+    pub total: usize,
+}
+
+impl McqResult {
+    pub fn accuracy(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.total as f64
+        }
+    }
+}
+
+/// Scores every choice of every item by summing `scorer`'s per-token
+/// log-probability over the choice's tokens (context: the question's
+/// tokens, followed by the choice's own tokens seen so far), picks the
+/// highest-scoring choice, and tallies how often that matches
+/// `answer_index` — the standard "log-likelihood of the continuation"
+/// scoring multiple-choice benchmarks like this use.
+pub fn run_mcq_suite(items: &[McqItem], tokenizer: &BpeTokenizer, scorer: &dyn Scorer) -> McqResult {
+    let mut correct = 0;
+    for item in items {
+        let question_tokens = tokenizer.encode(&item.question);
+        let mut best_index = 0;
+        let mut best_score = f64::NEG_INFINITY;
+        for (index, choice) in item.choices.iter().enumerate() {
+            let mut context = question_tokens.clone();
+            let mut score = 0.0;
+            for token in tokenizer.encode(choice) {
+                score += scorer.token_logprob(&context, token);
+                context.push(token);
+            }
+            if score > best_score {
+                best_score = score;
+                best_index = index;
+            }
+        }
+        if best_index == item.answer_index {
+            correct += 1;
+        }
+    }
+    McqResult { correct, total: items.len() }
+}
+
+/// Parses one multiple-choice item per line from a JSONL spec:
+/// `{"question": "...", "choices": ["...", "..."], "answer_index": 0}`.
+pub fn parse_mcq_spec(text: &str) -> Result<Vec<McqItem>, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let value = Json::parse(line).map_err(|e| e.to_string())?;
+            let question = value
+                .get("question")
+                .and_then(Json::as_str)
+                .ok_or("item is missing \"question\"")?
+                .to_string();
+            let choices = value
+                .get("choices")
+                .and_then(Json::as_array)
+                .ok_or("item is missing \"choices\"")?
+                .iter()
+                .map(|c| c.as_str().map(str::to_string).ok_or_else(|| "choice is not a string".to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let answer_index = value
+                .get("answer_index")
+                .and_then(Json::as_f64)
+                .ok_or("item is missing \"answer_index\"")? as usize;
+            Ok(McqItem { question, choices, answer_index })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perplexity_of_a_uniform_scorer_equals_the_vocab_size() {
+        let scorer = UniformScorer { vocab_size: 100 };
+        let tokens = vec![1, 2, 3, 4];
+        assert!((perplexity(&tokens, &scorer) - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn perplexity_of_an_empty_sequence_is_nan() {
+        let scorer = UniformScorer { vocab_size: 100 };
+        assert!(perplexity(&[], &scorer).is_nan());
+    }
+
+    struct PreferFirstTokenScorer;
+
+    impl Scorer for PreferFirstTokenScorer {
+        fn token_logprob(&self, _context: &[u32], token: u32) -> f64 {
+            if token == 0 {
+                -0.1
+            } else {
+                -5.0
+            }
+        }
+    }
+
+    #[test]
+    fn parse_mcq_spec_reads_one_item_per_line() {
+        let text = "{\"question\":\"q1\",\"choices\":[\"a\",\"b\"],\"answer_index\":1}\n\
+                     {\"question\":\"q2\",\"choices\":[\"c\",\"d\"],\"answer_index\":0}\n";
+        let items = parse_mcq_spec(text).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].question, "q1");
+        assert_eq!(items[1].answer_index, 0);
+    }
+
+    #[test]
+    fn parse_mcq_spec_rejects_a_missing_field() {
+        assert!(parse_mcq_spec("{\"question\":\"q\"}").is_err());
+    }
+
+    #[test]
+    fn mcq_result_accuracy_handles_an_empty_suite() {
+        let result = McqResult { correct: 0, total: 0 };
+        assert_eq!(result.accuracy(), 0.0);
+    }
+}
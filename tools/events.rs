@@ -0,0 +1,207 @@
+//! In-process pub/sub for structured server events (model loaded/evicted,
+//! request started/finished, cache evictions, errors), fanned out to
+//! `/admin/events` subscribers over SSE/WebSocket instead of making every
+//! dashboard poll `/metrics` on an interval to notice something happened.
+//! Shaped like `cancellation::CancellationRegistry`: a shared registry
+//! threaded into `route()` and into whichever module already knows an
+//! event occurred (`model_pool.rs`, `response_cache.rs`, `embedding_cache.rs`).
+
+use crate::json::{Json, ObjectBuilder};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+
+/// Per-subscriber channel capacity. Once a subscriber is this far behind,
+/// it's disconnected rather than let its queue grow without bound — see
+/// [`EventBus::publish`].
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> Option<Severity> {
+        match s {
+            "debug" => Some(Severity::Debug),
+            "info" => Some(Severity::Info),
+            "warn" => Some(Severity::Warn),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// One thing that happened, worth telling a connected dashboard about.
+/// `subsystem` is a free-form source tag (`"model_pool"`, `"http"`,
+/// `"response_cache"`, ...) rather than an enum, the same trade
+/// `logging::LogEvent`'s free-form `model`/`client` strings make, since new
+/// sources shouldn't require touching this type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerEvent {
+    pub subsystem: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ServerEvent {
+    pub fn new(subsystem: impl Into<String>, severity: Severity, message: impl Into<String>) -> ServerEvent {
+        ServerEvent { subsystem: subsystem.into(), severity, message: message.into() }
+    }
+
+    pub fn to_json(&self) -> Json {
+        ObjectBuilder::new()
+            .set("subsystem", Json::String(self.subsystem.clone()))
+            .set("severity", Json::String(self.severity.as_str().to_string()))
+            .set("message", Json::String(self.message.clone()))
+            .build()
+    }
+}
+
+/// A subscriber's filter: only events whose severity is at or above
+/// `min_severity` and, if `subsystem` is set, whose subsystem matches
+/// exactly. Matches the two `/admin/events` query parameters
+/// (`?severity=warn&subsystem=model_pool`) one-for-one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventFilter {
+    pub min_severity: Option<Severity>,
+    pub subsystem: Option<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &ServerEvent) -> bool {
+        self.min_severity.is_none_or(|min| event.severity >= min) && self.subsystem.as_deref().is_none_or(|s| s == event.subsystem)
+    }
+}
+
+/// Fans published events out to every live subscriber. Each subscriber
+/// gets its own bounded mpsc channel rather than sharing one queue, so a
+/// slow dashboard connection can't hold up another one's delivery — the
+/// same "each request gets its own state" preference `CancellationRegistry`
+/// takes with per-request tokens instead of one shared flag. Bounded means
+/// a subscriber that falls more than [`SUBSCRIBER_QUEUE_CAPACITY`] events
+/// behind is disconnected rather than buffered forever — see
+/// [`EventBus::publish`] for the drop policy.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<(EventFilter, SyncSender<ServerEvent>)>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a fresh subscriber matching `filter`, returning the
+    /// receiving half a handler reads from until its connection closes.
+    /// Once the `Receiver` is dropped, the next [`publish`](Self::publish)
+    /// notices the send failed and drops this subscriber's slot — there's
+    /// no explicit unsubscribe call.
+    pub fn subscribe(&self, filter: EventFilter) -> Receiver<ServerEvent> {
+        let (tx, rx) = sync_channel(SUBSCRIBER_QUEUE_CAPACITY);
+        self.subscribers.lock().unwrap().push((filter, tx));
+        rx
+    }
+
+    /// Delivers `event` to every subscriber whose filter matches it. A
+    /// subscriber is dropped, the same as if its `Receiver` had gone away,
+    /// once its queue is full — publishing must never block on (or grow
+    /// without bound for) a stalled `/admin/events` reader, so a slow
+    /// dashboard loses its connection rather than the rest of the fleet's
+    /// events piling up in memory behind it.
+    pub fn publish(&self, event: ServerEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|(filter, tx)| {
+            if !filter.matches(&event) {
+                return true;
+            }
+            tx.try_send(event.clone()).is_ok()
+        });
+    }
+
+    #[cfg(test)]
+    fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn severity_parse_round_trips_through_as_str() {
+        for severity in [Severity::Debug, Severity::Info, Severity::Warn, Severity::Error] {
+            assert_eq!(Severity::parse(severity.as_str()), Some(severity));
+        }
+        assert_eq!(Severity::parse("critical"), None);
+    }
+
+    #[test]
+    fn severity_ordering_treats_error_as_more_severe_than_debug() {
+        assert!(Severity::Error > Severity::Debug);
+    }
+
+    #[test]
+    fn subscriber_with_no_filter_receives_every_event() {
+        let bus = EventBus::new();
+        let rx = bus.subscribe(EventFilter::default());
+        bus.publish(ServerEvent::new("model_pool", Severity::Info, "loaded m1"));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().message, "loaded m1");
+    }
+
+    #[test]
+    fn subscriber_min_severity_filters_out_lower_severity_events() {
+        let bus = EventBus::new();
+        let rx = bus.subscribe(EventFilter { min_severity: Some(Severity::Warn), subsystem: None });
+        bus.publish(ServerEvent::new("http", Severity::Info, "request finished"));
+        bus.publish(ServerEvent::new("http", Severity::Error, "request failed"));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().message, "request failed");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn subscriber_subsystem_filter_only_matches_that_subsystem() {
+        let bus = EventBus::new();
+        let rx = bus.subscribe(EventFilter { min_severity: None, subsystem: Some("model_pool".to_string()) });
+        bus.publish(ServerEvent::new("http", Severity::Info, "request finished"));
+        bus.publish(ServerEvent::new("model_pool", Severity::Info, "loaded m1"));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().subsystem, "model_pool");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn publish_drops_a_subscriber_whose_receiver_was_dropped() {
+        let bus = EventBus::new();
+        drop(bus.subscribe(EventFilter::default()));
+        assert_eq!(bus.subscriber_count(), 1);
+        bus.publish(ServerEvent::new("http", Severity::Info, "anything"));
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn publish_disconnects_a_subscriber_whose_queue_is_full_instead_of_blocking() {
+        let bus = EventBus::new();
+        let rx = bus.subscribe(EventFilter::default());
+        for _ in 0..SUBSCRIBER_QUEUE_CAPACITY + 1 {
+            bus.publish(ServerEvent::new("http", Severity::Info, "request finished"));
+        }
+        assert_eq!(bus.subscriber_count(), 0);
+        // The channel still holds whatever fit before it was dropped, not
+        // an unbounded backlog.
+        assert!(std::iter::from_fn(|| rx.try_recv().ok()).count() <= SUBSCRIBER_QUEUE_CAPACITY);
+    }
+}
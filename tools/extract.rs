@@ -0,0 +1,292 @@
+//! Text extraction for the RAG ingestion pipeline: turns a file on disk
+//! into normalized text plus heading metadata that
+//! `rag::chunk_document`'s paragraph-aware splitting can key off of.
+//! `watcher::DocumentWatcher` and `jobs::JobAction::ReembedFolder` both
+//! read a file's text through [`extract`] rather than each doing their
+//! own extension check, so a format either of them gains support for is
+//! immediately available to both.
+//!
+//! `.txt` and `.md` are read as plain UTF-8. `.html`/`.htm` gets a
+//! readability-style pass: `<script>`/`<style>` bodies are dropped,
+//! remaining tags are stripped, a handful of named/numeric HTML entities
+//! are decoded, and `<h1>`–`<h6>` elements become section headings rather
+//! than being stripped down to plain text like everything else — this is
+//! a hand-rolled tag stripper, not a real HTML/CSS box-model renderer, so
+//! it can't tell "boilerplate" navigation text from body text the way a
+//! browser-based readability extractor can; it only removes markup.
+//!
+//! `.pdf` and `.docx` are recognized but not supported: a PDF is its own
+//! binary format and a DOCX is a zip archive of XML parts, and this tree
+//! has no PDF parser, ZIP/DEFLATE decoder, or XML parser to build one on
+//! top of (the same "no external crates" constraint that makes `json.rs`,
+//! `config.rs`'s TOML subset, and `http.rs` all hand-rolled) — extracting
+//! either would mean writing a general-purpose binary format parser from
+//! scratch, well past what this module can honestly claim to do today.
+//! [`extract`] reports both as [`ExtractError::Unsupported`] so a caller
+//! can skip the file and count it rather than silently losing it.
+
+use crate::rag::{self, Chunk, ChunkStrategy};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(String),
+    Unsupported(String),
+}
+
+impl ExtractError {
+    pub fn message(&self) -> String {
+        match self {
+            ExtractError::Io(m) => m.clone(),
+            ExtractError::Unsupported(format) => format!("no parser for \"{format}\" files in this tree"),
+        }
+    }
+}
+
+/// One heading-delimited piece of a document. `page` is always `None`
+/// today — none of the supported formats (plain text, Markdown, HTML)
+/// have a notion of pages; it's here so a future PDF extractor has
+/// somewhere to put page numbers without changing this struct's shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub heading: Option<String>,
+    pub page: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedDocument {
+    /// Every section's heading (if any) followed by its text, joined with
+    /// blank lines — this is what gets handed to `rag::chunk_document`,
+    /// so a heading rides along as part of the paragraph it introduces
+    /// instead of needing a separate metadata channel through the
+    /// chunker.
+    pub text: String,
+    pub sections: Vec<Section>,
+}
+
+fn document_from_sections(sections: Vec<Section>) -> ExtractedDocument {
+    let text = sections
+        .iter()
+        .map(|s| match &s.heading {
+            Some(heading) => format!("{heading}\n\n{}", s.text),
+            None => s.text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    ExtractedDocument { text, sections }
+}
+
+fn extract_plain_text(text: String) -> ExtractedDocument {
+    document_from_sections(vec![Section { heading: None, page: None, text }])
+}
+
+/// Splits Markdown on ATX headings (`#` through `######`); everything
+/// before the first heading becomes a heading-less leading section.
+fn extract_markdown(text: &str) -> ExtractedDocument {
+    let mut sections = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut body = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if level >= 1 && level <= 6 && trimmed[level..].starts_with(' ') {
+            if heading.is_some() || !body.trim().is_empty() {
+                sections.push(Section { heading: heading.take(), page: None, text: body.trim().to_string() });
+                body.clear();
+            }
+            heading = Some(trimmed.to_string());
+        } else {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    if heading.is_some() || !body.trim().is_empty() {
+        sections.push(Section { heading, page: None, text: body.trim().to_string() });
+    }
+    document_from_sections(sections)
+}
+
+const HEADING_TAGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+const SKIPPED_TAGS: [&str; 2] = ["script", "style"];
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|&i| i <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" | "#39" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity.strip_prefix('#').and_then(|n| n.parse::<u32>().ok()).and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => out.push(c),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Strips tags from `html`, decoding entities and treating `<h1>`–`<h6>`
+/// content as section headings — a hand-rolled boilerplate remover, not
+/// a spec-compliant HTML parser (malformed markup is tolerated by just
+/// treating any `<...>` run as a tag, not by implementing the HTML5
+/// parsing algorithm's error-recovery rules).
+fn extract_html(html: &str) -> ExtractedDocument {
+    let mut sections = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut body = String::new();
+    let mut in_heading = false;
+    let mut skip_until: Option<String> = None;
+
+    let bytes = html.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'<' {
+            let Some(close) = html[i..].find('>').map(|j| i + j) else { break };
+            let tag = &html[i + 1..close];
+            let tag_name: String = tag.trim_start_matches('/').chars().take_while(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+            let is_closing = tag.starts_with('/');
+
+            if let Some(skipping) = &skip_until {
+                if is_closing && &tag_name == skipping {
+                    skip_until = None;
+                }
+                i = close + 1;
+                continue;
+            }
+            if !is_closing && SKIPPED_TAGS.contains(&tag_name.as_str()) {
+                skip_until = Some(tag_name);
+                i = close + 1;
+                continue;
+            }
+            if HEADING_TAGS.contains(&tag_name.as_str()) {
+                if !is_closing {
+                    if heading.is_some() || !body.trim().is_empty() {
+                        sections.push(Section { heading: heading.take().map(|h| h.trim().to_string()), page: None, text: body.trim().to_string() });
+                        body.clear();
+                    }
+                    in_heading = true;
+                } else {
+                    in_heading = false;
+                }
+            } else if matches!(tag_name.as_str(), "p" | "br" | "div" | "li") {
+                body.push('\n');
+            }
+            i = close + 1;
+            continue;
+        }
+        let Some(next_tag) = html[i..].find('<').map(|j| i + j) else {
+            if skip_until.is_none() {
+                let text = decode_entities(&html[i..]);
+                if in_heading {
+                    heading.get_or_insert_with(String::new).push_str(text.trim());
+                } else {
+                    body.push_str(&text);
+                }
+            }
+            break;
+        };
+        if skip_until.is_none() {
+            let text = decode_entities(&html[i..next_tag]);
+            if in_heading {
+                heading.get_or_insert_with(String::new).push_str(text.trim());
+            } else {
+                body.push_str(&text);
+            }
+        }
+        i = next_tag;
+    }
+    if heading.is_some() || !body.trim().is_empty() {
+        sections.push(Section { heading: heading.map(|h| h.trim().to_string()), page: None, text: body.trim().to_string() });
+    }
+    document_from_sections(sections)
+}
+
+/// Extracts `path`'s text, dispatching on its extension. See this
+/// module's doc comment for which extensions are actually supported.
+pub fn extract(path: &Path) -> Result<ExtractedDocument, ExtractError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("txt") => std::fs::read_to_string(path).map(extract_plain_text).map_err(|e| ExtractError::Io(e.to_string())),
+        Some("md") => std::fs::read_to_string(path).map(|text| extract_markdown(&text)).map_err(|e| ExtractError::Io(e.to_string())),
+        Some("html") | Some("htm") => std::fs::read_to_string(path).map(|text| extract_html(&text)).map_err(|e| ExtractError::Io(e.to_string())),
+        Some(ext @ ("pdf" | "docx")) => Err(ExtractError::Unsupported(ext.to_string())),
+        Some(ext) => Err(ExtractError::Unsupported(ext.to_string())),
+        None => Err(ExtractError::Unsupported("(no extension)".to_string())),
+    }
+}
+
+/// Chunks an already-extracted document, the same way a caller would
+/// chunk raw text via `rag::chunk_document` — a thin convenience so
+/// ingestion call sites don't need to reach into `doc.text` themselves.
+pub fn chunk(doc: &ExtractedDocument, strategy: ChunkStrategy) -> Vec<Chunk> {
+    rag::chunk_document(&doc.text, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_markdown_splits_on_atx_headings() {
+        let doc = extract_markdown("# Title\n\nIntro text.\n\n## Section One\n\nBody one.\n");
+        assert_eq!(doc.sections.len(), 2);
+        assert_eq!(doc.sections[0].heading.as_deref(), Some("# Title"));
+        assert_eq!(doc.sections[0].text, "Intro text.");
+        assert_eq!(doc.sections[1].heading.as_deref(), Some("## Section One"));
+        assert_eq!(doc.sections[1].text, "Body one.");
+    }
+
+    #[test]
+    fn extract_markdown_keeps_a_leading_heading_less_section() {
+        let doc = extract_markdown("Just a paragraph, no heading.\n");
+        assert_eq!(doc.sections.len(), 1);
+        assert_eq!(doc.sections[0].heading, None);
+        assert_eq!(doc.sections[0].text, "Just a paragraph, no heading.");
+    }
+
+    #[test]
+    fn extract_html_strips_tags_and_keeps_headings() {
+        let doc = extract_html("<html><body><h1>Title</h1><p>Hello &amp; welcome.</p></body></html>");
+        assert_eq!(doc.sections.len(), 1);
+        assert_eq!(doc.sections[0].heading.as_deref(), Some("Title"));
+        assert_eq!(doc.sections[0].text, "Hello & welcome.");
+    }
+
+    #[test]
+    fn extract_html_drops_script_and_style_bodies() {
+        let doc = extract_html("<style>.a{color:red}</style><script>alert(1)</script><p>Visible text</p>");
+        assert_eq!(doc.text.trim(), "Visible text");
+    }
+
+    #[test]
+    fn extract_html_decodes_numeric_entities() {
+        let doc = extract_html("<p>caf&#233;</p>");
+        assert_eq!(doc.text.trim(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn extract_reports_unsupported_for_pdf_and_docx() {
+        assert!(matches!(extract(Path::new("report.pdf")), Err(ExtractError::Unsupported(f)) if f == "pdf"));
+        assert!(matches!(extract(Path::new("report.docx")), Err(ExtractError::Unsupported(f)) if f == "docx"));
+    }
+
+    #[test]
+    fn extract_reports_unsupported_for_an_unknown_extension() {
+        assert!(matches!(extract(Path::new("report.xyz")), Err(ExtractError::Unsupported(_))));
+    }
+}
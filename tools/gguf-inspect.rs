@@ -0,0 +1,26 @@
+//! CLI wrapper around [`gguf::GgufModel`]: prints a GGUF file's metadata
+//! summary. Usage: `gguf-inspect <path-to-model.gguf>`.
+
+mod gguf;
+mod model_loader;
+
+use gguf::GgufModel;
+use std::path::Path;
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: gguf-inspect <path-to-model.gguf>");
+            std::process::exit(2);
+        }
+    };
+
+    match GgufModel::open(Path::new(&path)) {
+        Ok(model) => println!("{}", model.inspect()),
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
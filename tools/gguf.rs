@@ -0,0 +1,577 @@
+//! GGUF file parser and metadata inspector. GGUF is llama.cpp's model
+//! container format: a fixed header, a key/value metadata block, then a
+//! tensor-info table, followed by the tensor data itself (aligned per the
+//! `general.alignment` metadata key, 32 bytes by default). This module only
+//! reads the header/metadata/tensor-info sections — actual tensor bytes are
+//! read on demand by whatever loads the model into memory.
+//!
+//! Spec: https://github.com/ggerganov/ggml/blob/master/docs/gguf.md
+
+use crate::model_loader::ModelLoader;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read};
+
+const MAGIC: u32 = 0x4655_4747; // b"GGUF" read as little-endian u32
+
+#[derive(Debug)]
+pub enum GgufError {
+    Io(io::Error),
+    BadMagic(u32),
+    UnsupportedVersion(u32),
+    Truncated,
+}
+
+impl From<io::Error> for GgufError {
+    fn from(e: io::Error) -> Self {
+        GgufError::Io(e)
+    }
+}
+
+impl fmt::Display for GgufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GgufError::Io(e) => write!(f, "I/O error: {e}"),
+            GgufError::BadMagic(m) => write!(f, "not a GGUF file (magic 0x{m:08x})"),
+            GgufError::UnsupportedVersion(v) => write!(f, "unsupported GGUF version {v}"),
+            GgufError::Truncated => write!(f, "file ended before header was fully read"),
+        }
+    }
+}
+
+/// A single metadata value. GGUF's value types map onto this one-to-one,
+/// except that all integer widths collapse to `i64`/`u64` here for
+/// convenience — callers that care about the original width can still see
+/// it via [`GgufValue::type_name`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+}
+
+impl GgufValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::U64(v) => Some(*v),
+            GgufValue::I64(v) if *v >= 0 => Some(*v as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            GgufValue::F64(v) => Some(*v),
+            GgufValue::U64(v) => Some(*v as f64),
+            GgufValue::I64(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            GgufValue::U64(_) => "uint",
+            GgufValue::I64(_) => "int",
+            GgufValue::F64(_) => "float",
+            GgufValue::Bool(_) => "bool",
+            GgufValue::String(_) => "string",
+            GgufValue::Array(_) => "array",
+        }
+    }
+}
+
+/// One entry in the tensor-info table: the tensor's name, shape, storage
+/// type (a `ggml_type` enum value, kept opaque here since interpreting it
+/// is the quantization/loader's job), and byte offset into the data
+/// section.
+#[derive(Debug, Clone)]
+pub struct TensorInfo {
+    pub name: String,
+    pub dims: Vec<u64>,
+    pub ggml_type: u32,
+    pub offset: u64,
+}
+
+/// A parsed GGUF file: header info, key/value metadata, and the tensor
+/// table. Does not hold tensor data.
+#[derive(Debug)]
+pub struct GgufModel {
+    pub version: u32,
+    pub metadata: BTreeMap<String, GgufValue>,
+    pub tensors: Vec<TensorInfo>,
+}
+
+impl GgufModel {
+    pub fn parse<R: Read>(reader: &mut R) -> Result<GgufModel, GgufError> {
+        let magic = read_u32(reader)?;
+        if magic != MAGIC {
+            return Err(GgufError::BadMagic(magic));
+        }
+        let version = read_u32(reader)?;
+        if version < 2 || version > 3 {
+            return Err(GgufError::UnsupportedVersion(version));
+        }
+
+        let tensor_count = read_u64(reader)?;
+        let kv_count = read_u64(reader)?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..kv_count {
+            let key = read_gguf_string(reader)?;
+            let value = read_value(reader)?;
+            metadata.insert(key, value);
+        }
+
+        let mut tensors = Vec::with_capacity(tensor_count as usize);
+        for _ in 0..tensor_count {
+            let name = read_gguf_string(reader)?;
+            let n_dims = read_u32(reader)?;
+            let mut dims = Vec::with_capacity(n_dims as usize);
+            for _ in 0..n_dims {
+                dims.push(read_u64(reader)?);
+            }
+            let ggml_type = read_u32(reader)?;
+            let offset = read_u64(reader)?;
+            tensors.push(TensorInfo { name, dims, ggml_type, offset });
+        }
+
+        Ok(GgufModel { version, metadata, tensors })
+    }
+
+    pub fn open(path: &std::path::Path) -> Result<GgufModel, GgufError> {
+        let mut file = std::fs::File::open(path)?;
+        GgufModel::parse(&mut file)
+    }
+
+    /// Summarizes the fields the server actually needs at load time:
+    /// architecture name, context length, and RoPE scaling, read from
+    /// `general.architecture`, `<architecture>.context_length`,
+    /// `<architecture>.rope.scaling.type`, and
+    /// `<architecture>.rope.scaling.factor`.
+    pub fn inspect(&self) -> ModelSummary {
+        let architecture = self
+            .metadata
+            .get("general.architecture")
+            .and_then(GgufValue::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        let context_length = self
+            .metadata
+            .get(&format!("{architecture}.context_length"))
+            .and_then(GgufValue::as_u64);
+        let rope_scaling = self
+            .metadata
+            .get(&format!("{architecture}.rope.scaling.type"))
+            .and_then(GgufValue::as_str)
+            .and_then(RopeScaling::parse)
+            .unwrap_or(RopeScaling::None);
+        let rope_scaling_factor = self
+            .metadata
+            .get(&format!("{architecture}.rope.scaling.factor"))
+            .and_then(GgufValue::as_f64)
+            .unwrap_or(1.0);
+        let name = self
+            .metadata
+            .get("general.name")
+            .and_then(GgufValue::as_str)
+            .map(str::to_string);
+
+        ModelSummary {
+            name,
+            architecture,
+            context_length,
+            rope_scaling,
+            rope_scaling_factor,
+            tensor_count: self.tensors.len(),
+            metadata_count: self.metadata.len(),
+        }
+    }
+}
+
+/// RoPE scaling strategy for serving a model past the context length it was
+/// trained at. Linear and YaRN are the two types llama.cpp itself writes
+/// into `<architecture>.rope.scaling.type` (see the GGUF spec linked
+/// above); NTK-aware scaling has no GGUF metadata type of its own — it's
+/// computed at load time from the same base frequency and scale factor —
+/// so it's only ever selected via `config::ServerConfig::rope_scaling_by_model`,
+/// never read from a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RopeScaling {
+    None,
+    Linear,
+    Ntk,
+    Yarn,
+}
+
+impl RopeScaling {
+    pub fn parse(s: &str) -> Option<RopeScaling> {
+        match s {
+            "none" => Some(RopeScaling::None),
+            "linear" => Some(RopeScaling::Linear),
+            "ntk" => Some(RopeScaling::Ntk),
+            "yarn" => Some(RopeScaling::Yarn),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RopeScaling::None => "none",
+            RopeScaling::Linear => "linear",
+            RopeScaling::Ntk => "ntk",
+            RopeScaling::Yarn => "yarn",
+        }
+    }
+
+    /// Effective context length once `factor` is applied to
+    /// `trained_context_length`. Linear, NTK, and YaRN scaling reach that
+    /// factor by stretching RoPE's rotation differently (replaying
+    /// position IDs at 1/`factor` the resolution, adjusting the rotation
+    /// base frequency, and a temperature-adjusted mix of the two,
+    /// respectively — see Peng et al., "YaRN: Efficient Context Window
+    /// Extension of Large Language Models", 2023), but all three are tuned
+    /// against the same target: a context window `factor` times the
+    /// trained one.
+    pub fn effective_context_length(self, trained_context_length: u64, factor: f64) -> u64 {
+        match self {
+            RopeScaling::None => trained_context_length,
+            RopeScaling::Linear | RopeScaling::Ntk | RopeScaling::Yarn => {
+                (trained_context_length as f64 * factor).round() as u64
+            }
+        }
+    }
+}
+
+impl ModelLoader for GgufModel {
+    fn tensor_names(&self) -> Vec<&str> {
+        self.tensors.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn tensor_shape(&self, name: &str) -> Option<&[u64]> {
+        self.tensors.iter().find(|t| t.name == name).map(|t| t.dims.as_slice())
+    }
+
+    fn tensor_dtype(&self, name: &str) -> Option<&str> {
+        self.tensors.iter().find(|t| t.name == name).map(|t| ggml_type_name(t.ggml_type))
+    }
+}
+
+/// Renders a `ggml_type` tensor storage code as its conventional name.
+/// Codes this tree hasn't needed to distinguish yet fall back to
+/// `"unknown"` rather than growing the match for types nothing here reads.
+fn ggml_type_name(ggml_type: u32) -> &'static str {
+    match ggml_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        6 => "Q5_0",
+        7 => "Q5_1",
+        8 => "Q8_0",
+        9 => "Q8_1",
+        10 => "Q2_K",
+        11 => "Q3_K",
+        12 => "Q4_K",
+        13 => "Q5_K",
+        14 => "Q6_K",
+        15 => "Q8_K",
+        18 => "I8",
+        19 => "I16",
+        20 => "I32",
+        _ => "unknown",
+    }
+}
+
+/// Human-facing summary of a GGUF file, the shape returned by the
+/// `tools/gguf-inspect` binary and reused by the model registry.
+#[derive(Debug)]
+pub struct ModelSummary {
+    pub name: Option<String>,
+    pub architecture: String,
+    pub context_length: Option<u64>,
+    /// `RopeScaling::None`, and `rope_scaling_factor` `1.0`, when the file
+    /// doesn't declare `<architecture>.rope.scaling.type`/`.factor` — the
+    /// same as `context_length` on its own being the effective one.
+    pub rope_scaling: RopeScaling,
+    pub rope_scaling_factor: f64,
+    pub tensor_count: usize,
+    pub metadata_count: usize,
+}
+
+impl ModelSummary {
+    /// `context_length` stretched by `rope_scaling`/`rope_scaling_factor`
+    /// as read from the file, or by `override_scaling` instead when the
+    /// caller passes one (see `config::ServerConfig::rope_scaling_by_model`
+    /// — an operator's explicit choice for a model beats whatever the file
+    /// itself declares). `None` when the file didn't report a
+    /// `context_length` to scale in the first place.
+    pub fn effective_context_length(&self, override_scaling: Option<(RopeScaling, f64)>) -> Option<u64> {
+        let (scaling, factor) = override_scaling.unwrap_or((self.rope_scaling, self.rope_scaling_factor));
+        self.context_length.map(|trained| scaling.effective_context_length(trained, factor))
+    }
+}
+
+impl fmt::Display for ModelSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name: {}", self.name.as_deref().unwrap_or("(unnamed)"))?;
+        writeln!(f, "architecture: {}", self.architecture)?;
+        writeln!(
+            f,
+            "context_length: {}",
+            self.context_length.map(|v| v.to_string()).unwrap_or_else(|| "?".to_string())
+        )?;
+        writeln!(f, "rope_scaling: {} (factor {})", self.rope_scaling.as_str(), self.rope_scaling_factor)?;
+        writeln!(f, "tensors: {}", self.tensor_count)?;
+        write!(f, "metadata entries: {}", self.metadata_count)
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, GgufError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, GgufError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, GgufError> {
+    read_u64(reader).map(|v| v as i64)
+}
+
+fn read_f64<R: Read>(reader: &mut R, wide: bool) -> Result<f64, GgufError> {
+    if wide {
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+        Ok(f64::from_le_bytes(buf))
+    } else {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+        Ok(f32::from_le_bytes(buf) as f64)
+    }
+}
+
+fn read_gguf_string<R: Read>(reader: &mut R) -> Result<String, GgufError> {
+    let len = read_u64(reader)? as usize;
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Reads one metadata value, dispatching on the leading `u32` type tag per
+/// the GGUF spec's `gguf_metadata_value_type` enum.
+fn read_value<R: Read>(reader: &mut R) -> Result<GgufValue, GgufError> {
+    let type_id = read_u32(reader)?;
+    read_typed_value(reader, type_id)
+}
+
+fn read_typed_value<R: Read>(reader: &mut R, type_id: u32) -> Result<GgufValue, GgufError> {
+    match type_id {
+        0 | 2 | 4 | 10 => read_u64_of_width(reader, type_id).map(GgufValue::U64),
+        1 | 3 | 5 | 11 => read_i64_of_width(reader, type_id).map(GgufValue::I64),
+        6 => read_f64(reader, false).map(GgufValue::F64),
+        12 => read_f64(reader, true).map(GgufValue::F64),
+        7 => {
+            let mut buf = [0u8; 1];
+            reader.read_exact(&mut buf).map_err(|_| GgufError::Truncated)?;
+            Ok(GgufValue::Bool(buf[0] != 0))
+        }
+        8 => read_gguf_string(reader).map(GgufValue::String),
+        9 => {
+            let elem_type = read_u32(reader)?;
+            let count = read_u64(reader)?;
+            let mut items = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                items.push(read_typed_value(reader, elem_type)?);
+            }
+            Ok(GgufValue::Array(items))
+        }
+        _ => Err(GgufError::Truncated),
+    }
+}
+
+fn read_u64_of_width<R: Read>(reader: &mut R, type_id: u32) -> Result<u64, GgufError> {
+    match type_id {
+        0 => {
+            let mut b = [0u8; 1];
+            reader.read_exact(&mut b).map_err(|_| GgufError::Truncated)?;
+            Ok(b[0] as u64)
+        }
+        2 => {
+            let mut b = [0u8; 2];
+            reader.read_exact(&mut b).map_err(|_| GgufError::Truncated)?;
+            Ok(u16::from_le_bytes(b) as u64)
+        }
+        4 => read_u32(reader).map(|v| v as u64),
+        10 => read_u64(reader),
+        _ => unreachable!(),
+    }
+}
+
+fn read_i64_of_width<R: Read>(reader: &mut R, type_id: u32) -> Result<i64, GgufError> {
+    match type_id {
+        1 => {
+            let mut b = [0u8; 1];
+            reader.read_exact(&mut b).map_err(|_| GgufError::Truncated)?;
+            Ok(b[0] as i8 as i64)
+        }
+        3 => {
+            let mut b = [0u8; 2];
+            reader.read_exact(&mut b).map_err(|_| GgufError::Truncated)?;
+            Ok(i16::from_le_bytes(b) as i64)
+        }
+        5 => read_u32(reader).map(|v| v as i32 as i64),
+        11 => read_i64(reader),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn write_gguf_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn sample_bytes() -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&MAGIC.to_le_bytes());
+        buf.extend_from_slice(&3u32.to_le_bytes()); // version
+        buf.extend_from_slice(&1u64.to_le_bytes()); // tensor_count
+        buf.extend_from_slice(&2u64.to_le_bytes()); // kv_count
+
+        write_gguf_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes()); // string
+        write_gguf_string(&mut buf, "llama");
+
+        write_gguf_string(&mut buf, "llama.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes()); // uint32
+        buf.extend_from_slice(&4096u32.to_le_bytes());
+
+        write_gguf_string(&mut buf, "token_embd.weight");
+        buf.extend_from_slice(&2u32.to_le_bytes()); // n_dims
+        buf.extend_from_slice(&32u64.to_le_bytes());
+        buf.extend_from_slice(&128u64.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes()); // ggml_type
+        buf.extend_from_slice(&0u64.to_le_bytes()); // offset
+
+        buf
+    }
+
+    #[test]
+    fn parses_header_metadata_and_tensor_table() {
+        let bytes = sample_bytes();
+        let model = GgufModel::parse(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(model.version, 3);
+        assert_eq!(model.tensors.len(), 1);
+        assert_eq!(model.tensors[0].name, "token_embd.weight");
+        assert_eq!(model.tensors[0].dims, vec![32, 128]);
+        assert_eq!(
+            model.metadata.get("general.architecture").unwrap().as_str(),
+            Some("llama")
+        );
+    }
+
+    #[test]
+    fn inspect_reads_architecture_and_context_length() {
+        let model = GgufModel::parse(&mut Cursor::new(sample_bytes())).unwrap();
+        let summary = model.inspect();
+        assert_eq!(summary.architecture, "llama");
+        assert_eq!(summary.context_length, Some(4096));
+        assert_eq!(summary.tensor_count, 1);
+    }
+
+    #[test]
+    fn inspect_defaults_to_no_rope_scaling_when_the_file_declares_none() {
+        let model = GgufModel::parse(&mut Cursor::new(sample_bytes())).unwrap();
+        let summary = model.inspect();
+        assert_eq!(summary.rope_scaling, RopeScaling::None);
+        assert_eq!(summary.rope_scaling_factor, 1.0);
+        assert_eq!(summary.effective_context_length(None), Some(4096));
+    }
+
+    #[test]
+    fn inspect_reads_rope_scaling_type_and_factor() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&MAGIC.to_le_bytes());
+        buf.extend_from_slice(&3u32.to_le_bytes()); // version
+        buf.extend_from_slice(&0u64.to_le_bytes()); // tensor_count
+        buf.extend_from_slice(&4u64.to_le_bytes()); // kv_count
+
+        write_gguf_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes()); // string
+        write_gguf_string(&mut buf, "llama");
+
+        write_gguf_string(&mut buf, "llama.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes()); // uint32
+        buf.extend_from_slice(&4096u32.to_le_bytes());
+
+        write_gguf_string(&mut buf, "llama.rope.scaling.type");
+        buf.extend_from_slice(&8u32.to_le_bytes()); // string
+        write_gguf_string(&mut buf, "yarn");
+
+        write_gguf_string(&mut buf, "llama.rope.scaling.factor");
+        buf.extend_from_slice(&6u32.to_le_bytes()); // float32
+        buf.extend_from_slice(&4.0f32.to_le_bytes());
+
+        let model = GgufModel::parse(&mut Cursor::new(buf)).unwrap();
+        let summary = model.inspect();
+        assert_eq!(summary.rope_scaling, RopeScaling::Yarn);
+        assert_eq!(summary.rope_scaling_factor, 4.0);
+        assert_eq!(summary.effective_context_length(None), Some(16384));
+    }
+
+    #[test]
+    fn effective_context_length_honors_an_override_over_the_files_own_scaling() {
+        let model = GgufModel::parse(&mut Cursor::new(sample_bytes())).unwrap();
+        let summary = model.inspect();
+        assert_eq!(summary.effective_context_length(Some((RopeScaling::Ntk, 2.0))), Some(8192));
+    }
+
+    #[test]
+    fn rope_scaling_parse_round_trips_through_as_str() {
+        for scaling in [RopeScaling::None, RopeScaling::Linear, RopeScaling::Ntk, RopeScaling::Yarn] {
+            assert_eq!(RopeScaling::parse(scaling.as_str()), Some(scaling));
+        }
+        assert_eq!(RopeScaling::parse("bogus"), None);
+    }
+
+    #[test]
+    fn model_loader_reports_tensor_shape_and_dtype_by_name() {
+        let model = GgufModel::parse(&mut Cursor::new(sample_bytes())).unwrap();
+        assert_eq!(model.tensor_names(), vec!["token_embd.weight"]);
+        assert_eq!(model.tensor_shape("token_embd.weight"), Some(&[32u64, 128][..]));
+        assert_eq!(model.tensor_dtype("token_embd.weight"), Some("F32"));
+        assert_eq!(model.tensor_shape("missing"), None);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let bytes = vec![0u8; 16];
+        let err = GgufModel::parse(&mut Cursor::new(bytes)).unwrap_err();
+        assert!(matches!(err, GgufError::BadMagic(_)));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let bytes = MAGIC.to_le_bytes().to_vec();
+        let err = GgufModel::parse(&mut Cursor::new(bytes)).unwrap_err();
+        assert!(matches!(err, GgufError::Truncated));
+    }
+}
@@ -0,0 +1,116 @@
+//! Metal GPU detection on Apple Silicon. There's no Metal binding in this
+//! tree (no `metal-rs`, since nothing here has a dependency manager to
+//! declare it against — see `hardware.rs`'s `sysctlbyname` note), so this
+//! shells out to `system_profiler`, which reports the same chipset/GPU-core
+//! info without needing an Objective-C bridge, and to `sysctl` for unified
+//! memory size.
+
+use crate::json::Json;
+use std::process::Command;
+
+/// GPU capability info for a Metal-capable host. `max_threadgroup_memory`
+/// is a fixed constant on Apple GPU families rather than something
+/// `system_profiler` reports, so it's looked up from `family`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuInfo {
+    pub chipset: String,
+    pub core_count: Option<u64>,
+    pub unified_memory_bytes: Option<u64>,
+    pub max_threadgroup_memory_bytes: u64,
+}
+
+impl GpuInfo {
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    pub fn detect() -> Option<GpuInfo> {
+        let profiler_json = run_system_profiler()?;
+        let parsed = Json::parse(&profiler_json).ok()?;
+        let (chipset, core_count) = parse_displays_report(&parsed)?;
+        Some(GpuInfo {
+            max_threadgroup_memory_bytes: threadgroup_memory_for(&chipset),
+            chipset,
+            core_count,
+            unified_memory_bytes: sysctl_u64("hw.memsize"),
+        })
+    }
+
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    pub fn detect() -> Option<GpuInfo> {
+        None
+    }
+}
+
+/// All current Apple Silicon GPU families expose 32 KiB of threadgroup
+/// memory per threadgroup; kept as a lookup (rather than a bare constant)
+/// so a future family with a different limit only needs an entry here.
+fn threadgroup_memory_for(_chipset: &str) -> u64 {
+    32 * 1024
+}
+
+/// Pulls the chipset name and GPU core count out of
+/// `system_profiler SPDisplaysDataType -json` output, e.g.
+/// `{"SPDisplaysDataType": [{"sppci_model": "Apple M2 Pro", "sppci_cores": "19"}]}`.
+fn parse_displays_report(report: &Json) -> Option<(String, Option<u64>)> {
+    let entry = report.get("SPDisplaysDataType")?.as_array()?.first()?;
+    let chipset = entry.get("sppci_model")?.as_str()?.to_string();
+    let core_count = entry
+        .get("sppci_cores")
+        .and_then(Json::as_str)
+        .and_then(|s| s.parse::<u64>().ok());
+    Some((chipset, core_count))
+}
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+fn run_system_profiler() -> Option<String> {
+    let output = Command::new("system_profiler")
+        .args(["SPDisplaysDataType", "-json"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+fn sysctl_u64(name: &str) -> Option<u64> {
+    let output = Command::new("sysctl").args(["-n", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_displays_report_extracts_chipset_and_core_count() {
+        let report = Json::parse(
+            r#"{"SPDisplaysDataType": [{"sppci_model": "Apple M2 Pro", "sppci_cores": "19"}]}"#,
+        )
+        .unwrap();
+        let (chipset, cores) = parse_displays_report(&report).unwrap();
+        assert_eq!(chipset, "Apple M2 Pro");
+        assert_eq!(cores, Some(19));
+    }
+
+    #[test]
+    fn parse_displays_report_tolerates_missing_core_count() {
+        let report = Json::parse(r#"{"SPDisplaysDataType": [{"sppci_model": "Apple M1"}]}"#).unwrap();
+        let (chipset, cores) = parse_displays_report(&report).unwrap();
+        assert_eq!(chipset, "Apple M1");
+        assert_eq!(cores, None);
+    }
+
+    #[test]
+    fn parse_displays_report_returns_none_for_empty_report() {
+        let report = Json::parse(r#"{"SPDisplaysDataType": []}"#).unwrap();
+        assert!(parse_displays_report(&report).is_none());
+    }
+
+    #[test]
+    fn threadgroup_memory_is_32kib_for_known_chipsets() {
+        assert_eq!(threadgroup_memory_for("Apple M2 Pro"), 32 * 1024);
+    }
+}
@@ -0,0 +1,233 @@
+//! Content moderation hooks run on a request's prompt (pre-generation) and
+//! completion (post-generation): keyword filters plus an optional
+//! classifier prompt run through the same `InferenceBackend` that serves
+//! the request, each rule mapped to an [`Action`] (block, redact,
+//! annotate). `server.rs` runs [`GuardrailsEngine::check`] before calling
+//! `generate`/`stream` and again on the result, surfacing whatever it
+//! found as a `"moderation"` field on the response (see
+//! `handle_completions`/`handle_chat_completions`) rather than silently
+//! dropping or rewriting content the caller can't see happened.
+//!
+//! Filters are substrings, not regexes, for the same no-dependency reason
+//! `audit::redact` uses substrings — this tree has no regex engine.
+
+use crate::json::{Json, ObjectBuilder};
+use crate::InferenceBackend;
+
+/// What to do when a rule (or the classifier) flags text. Variants are
+/// ordered least to most severe so [`stronger`] can compare them with `>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Annotate,
+    Redact,
+    Block,
+}
+
+impl Action {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "annotate" => Some(Action::Annotate),
+            "redact" => Some(Action::Redact),
+            "block" => Some(Action::Block),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Annotate => "annotate",
+            Action::Redact => "redact",
+            Action::Block => "block",
+        }
+    }
+}
+
+/// A single keyword filter: any occurrence of `pattern` in the checked
+/// text triggers `action`, reported under `label`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub label: String,
+    pub pattern: String,
+    pub action: Action,
+}
+
+/// The outcome of running [`GuardrailsEngine::check`] once, over one piece
+/// of text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub action: Option<Action>,
+    pub matched_rules: Vec<String>,
+    /// `text` with any `Action::Redact` matches replaced by `[REDACTED]` —
+    /// equal to the input when nothing redact-worthy matched.
+    pub text: String,
+}
+
+impl ModerationResult {
+    pub fn to_json(&self) -> Json {
+        let mut builder = ObjectBuilder::new()
+            .set("flagged", Json::Bool(self.flagged))
+            .set("matched_rules", Json::Array(self.matched_rules.iter().cloned().map(Json::String).collect()));
+        if let Some(action) = self.action {
+            builder = builder.set("action", Json::String(action.as_str().to_string()));
+        }
+        builder.build()
+    }
+}
+
+/// Merges a pre-generation and a post-generation [`ModerationResult`] into
+/// one, for the `"moderation"` field a response reports once it has run
+/// both checks — `text` comes from `post` since that's the one a caller
+/// actually receives.
+pub fn combine(pre: &ModerationResult, post: &ModerationResult) -> ModerationResult {
+    let mut matched_rules = pre.matched_rules.clone();
+    matched_rules.extend(post.matched_rules.iter().cloned());
+    ModerationResult { flagged: pre.flagged || post.flagged, action: pre.action.max(post.action), matched_rules, text: post.text.clone() }
+}
+
+/// Keyword rules plus an optional classifier, applied by [`check`](Self::check)
+/// to a request's prompt before generation and its completion after.
+pub struct GuardrailsEngine {
+    rules: Vec<Rule>,
+    /// Prepended to the checked text and run through the request's own
+    /// backend when set; a response containing the word "flag" (case
+    /// insensitive) trips `classifier_action`. There's no dedicated
+    /// classifier model in this tree — `backend::EchoBackend` and its
+    /// siblings are the only `InferenceBackend`s around — so this reuses
+    /// whichever backend is already serving the request, the same
+    /// "no separate model-serving path" tradeoff `rag.rs` makes reusing
+    /// `EmbeddingBackend` instead of standing up a retrieval-specific one.
+    classifier_prompt: Option<String>,
+    classifier_action: Action,
+}
+
+impl GuardrailsEngine {
+    pub fn new(rules: Vec<Rule>, classifier_prompt: Option<String>, classifier_action: Action) -> GuardrailsEngine {
+        GuardrailsEngine { rules, classifier_prompt, classifier_action }
+    }
+
+    /// An engine with no rules and no classifier — every check passes
+    /// through untouched. `server.rs` uses this when `[guardrails]` isn't
+    /// enabled in config, so callers never need to special-case "off".
+    pub fn disabled() -> GuardrailsEngine {
+        GuardrailsEngine { rules: Vec::new(), classifier_prompt: None, classifier_action: Action::Annotate }
+    }
+
+    /// Runs every keyword rule against `text`, then the classifier prompt
+    /// (if configured and `backend` is given), and returns the combined
+    /// verdict with `Action::Redact` matches already applied to `text`.
+    pub fn check(&self, text: &str, backend: Option<&dyn InferenceBackend>) -> ModerationResult {
+        let mut matched_rules = Vec::new();
+        let mut action: Option<Action> = None;
+        for rule in &self.rules {
+            if text.contains(rule.pattern.as_str()) {
+                matched_rules.push(rule.label.clone());
+                action = action.max(Some(rule.action));
+            }
+        }
+        if let (Some(prefix), Some(backend)) = (&self.classifier_prompt, backend) {
+            let verdict = backend.generate(&format!("{prefix}{text}"));
+            if verdict.to_lowercase().contains("flag") {
+                matched_rules.push("classifier".to_string());
+                action = action.max(Some(self.classifier_action));
+            }
+        }
+        let mut redacted = text.to_string();
+        if action == Some(Action::Redact) {
+            for rule in &self.rules {
+                if rule.action == Action::Redact && matched_rules.contains(&rule.label) {
+                    redacted = redacted.replace(rule.pattern.as_str(), "[REDACTED]");
+                }
+            }
+        }
+        ModerationResult { flagged: !matched_rules.is_empty(), action, matched_rules, text: redacted }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubBackend {
+        model_id: String,
+        response: String,
+    }
+
+    impl InferenceBackend for StubBackend {
+        fn model_id(&self) -> &str {
+            &self.model_id
+        }
+        fn generate(&self, _prompt: &str) -> String {
+            self.response.clone()
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    fn rule(label: &str, pattern: &str, action: Action) -> Rule {
+        Rule { label: label.to_string(), pattern: pattern.to_string(), action }
+    }
+
+    #[test]
+    fn disabled_engine_never_flags_anything() {
+        let result = GuardrailsEngine::disabled().check("anything at all", None);
+        assert!(!result.flagged);
+        assert_eq!(result.action, None);
+        assert_eq!(result.text, "anything at all");
+    }
+
+    #[test]
+    fn a_matching_block_rule_flags_without_altering_the_text() {
+        let engine = GuardrailsEngine::new(vec![rule("weapons", "bomb", Action::Block)], None, Action::Annotate);
+        let result = engine.check("how do I build a bomb", None);
+        assert!(result.flagged);
+        assert_eq!(result.action, Some(Action::Block));
+        assert_eq!(result.matched_rules, vec!["weapons".to_string()]);
+        assert_eq!(result.text, "how do I build a bomb");
+    }
+
+    #[test]
+    fn a_matching_redact_rule_replaces_the_pattern_in_the_returned_text() {
+        let engine = GuardrailsEngine::new(vec![rule("ssn", "123-45-6789", Action::Redact)], None, Action::Annotate);
+        let result = engine.check("my ssn is 123-45-6789", None);
+        assert_eq!(result.action, Some(Action::Redact));
+        assert_eq!(result.text, "my ssn is [REDACTED]");
+    }
+
+    #[test]
+    fn the_strongest_action_wins_when_multiple_rules_match() {
+        let engine =
+            GuardrailsEngine::new(vec![rule("mild", "darn", Action::Annotate), rule("severe", "bomb", Action::Block)], None, Action::Annotate);
+        let result = engine.check("darn, a bomb", None);
+        assert_eq!(result.action, Some(Action::Block));
+        assert_eq!(result.matched_rules, vec!["mild".to_string(), "severe".to_string()]);
+    }
+
+    #[test]
+    fn the_classifier_flags_when_its_backend_response_contains_flag() {
+        let backend = StubBackend { model_id: "m".to_string(), response: "verdict: FLAG".to_string() };
+        let engine = GuardrailsEngine::new(Vec::new(), Some("classify: ".to_string()), Action::Block);
+        let result = engine.check("some text", Some(&backend));
+        assert_eq!(result.action, Some(Action::Block));
+        assert_eq!(result.matched_rules, vec!["classifier".to_string()]);
+    }
+
+    #[test]
+    fn the_classifier_is_skipped_without_a_backend() {
+        let engine = GuardrailsEngine::new(Vec::new(), Some("classify: ".to_string()), Action::Block);
+        let result = engine.check("some text", None);
+        assert!(!result.flagged);
+    }
+
+    #[test]
+    fn combine_merges_matched_rules_and_takes_the_stronger_action() {
+        let pre = ModerationResult { flagged: true, action: Some(Action::Annotate), matched_rules: vec!["a".to_string()], text: "in".to_string() };
+        let post =
+            ModerationResult { flagged: true, action: Some(Action::Redact), matched_rules: vec!["b".to_string()], text: "out".to_string() };
+        let combined = combine(&pre, &post);
+        assert_eq!(combined.action, Some(Action::Redact));
+        assert_eq!(combined.matched_rules, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(combined.text, "out");
+    }
+}
@@ -0,0 +1,532 @@
+//! Runtime hardware probe used to pick inference kernel code paths (scalar
+//! vs. NEON vs. AVX), thread counts, and backend selection instead of baking
+//! in a single fixed path. [`HardwareProfile`] is the entry point; it
+//! composes CPU feature detection, memory sizing, and core topology.
+
+use std::fmt;
+
+/// Capabilities of the host CPU, probed at runtime rather than assumed from
+/// the compile-time target triple.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuCapabilities {
+    pub arch: &'static str,
+    pub logical_cores: usize,
+    pub physical_cores: usize,
+    pub cache_line_size: usize,
+    pub neon: bool,
+    pub sve: bool,
+    pub fp16: bool,
+    pub avx2: bool,
+    pub avx512f: bool,
+    /// AVX-512 VNNI (`AVX512_VNNI`): fused `i8`×`i8`→`i32` multiply-accumulate
+    /// in one instruction, which is exactly the inner loop `kernels.rs`'s
+    /// quantized dot product needs — faster than AVX-512F alone even though
+    /// both require the same 512-bit registers.
+    pub avx512vnni: bool,
+    pub fma: bool,
+}
+
+/// SIMD width, in lanes of `f32`, that the best available kernel can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdWidth {
+    Scalar,
+    Neon,
+    Avx2,
+    Avx512,
+    Avx512Vnni,
+}
+
+impl CpuCapabilities {
+    /// Probes the host CPU for the features relevant to inference kernels.
+    pub fn detect() -> Self {
+        let logical_cores = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        CpuCapabilities {
+            arch: std::env::consts::ARCH,
+            logical_cores,
+            physical_cores: Self::detect_physical_cores(logical_cores),
+            cache_line_size: Self::detect_cache_line_size(),
+            neon: Self::detect_neon(),
+            sve: Self::detect_sve(),
+            fp16: Self::detect_fp16(),
+            avx2: Self::detect_avx2(),
+            avx512f: Self::detect_avx512f(),
+            avx512vnni: Self::detect_avx512vnni(),
+            fma: Self::detect_fma(),
+        }
+    }
+
+    /// Picks the widest SIMD code path this host actually supports.
+    pub fn best_simd_width(&self) -> SimdWidth {
+        if self.avx512vnni {
+            SimdWidth::Avx512Vnni
+        } else if self.avx512f {
+            SimdWidth::Avx512
+        } else if self.avx2 && self.fma {
+            SimdWidth::Avx2
+        } else if self.neon {
+            SimdWidth::Neon
+        } else {
+            SimdWidth::Scalar
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect_neon() -> bool {
+        std::arch::is_aarch64_feature_detected!("neon")
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    fn detect_neon() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect_sve() -> bool {
+        std::arch::is_aarch64_feature_detected!("sve")
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    fn detect_sve() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect_fp16() -> bool {
+        std::arch::is_aarch64_feature_detected!("fp16")
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    fn detect_fp16() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn detect_avx2() -> bool {
+        std::arch::is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    fn detect_avx2() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn detect_avx512f() -> bool {
+        std::arch::is_x86_feature_detected!("avx512f")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    fn detect_avx512f() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn detect_avx512vnni() -> bool {
+        std::arch::is_x86_feature_detected!("avx512vnni")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    fn detect_avx512vnni() -> bool {
+        false
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn detect_fma() -> bool {
+        std::arch::is_x86_feature_detected!("fma")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    fn detect_fma() -> bool {
+        false
+    }
+
+    /// Physical core count, derived by counting distinct `(physical id,
+    /// core id)` pairs in `/proc/cpuinfo`. Falls back to the logical count
+    /// when those fields are absent (as on most aarch64 hosts) or the file
+    /// can't be read.
+    #[cfg(target_os = "linux")]
+    fn detect_physical_cores(logical_cores: usize) -> usize {
+        let Ok(cpuinfo) = std::fs::read_to_string("/proc/cpuinfo") else {
+            return logical_cores;
+        };
+        parse_physical_cores(&cpuinfo).unwrap_or(logical_cores)
+    }
+
+    /// Physical-core detection is not implemented on non-Linux platforms;
+    /// returns the logical count as a placeholder.
+    #[cfg(not(target_os = "linux"))]
+    fn detect_physical_cores(logical_cores: usize) -> usize {
+        logical_cores
+    }
+
+    /// Cache-line size in bytes, read from sysfs. Falls back to 64 bytes —
+    /// which covers the overwhelming majority of aarch64 and x86_64 hosts
+    /// we deploy on — when sysfs doesn't expose it (as on non-Linux hosts)
+    /// or the value can't be read.
+    #[cfg(target_os = "linux")]
+    fn detect_cache_line_size() -> usize {
+        std::fs::read_to_string(
+            "/sys/devices/system/cpu/cpu0/cache/index0/coherency_line_size",
+        )
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(64)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn detect_cache_line_size() -> usize {
+        64
+    }
+}
+
+/// RAM visible to the process, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryInfo {
+    pub total_bytes: Option<u64>,
+    pub available_bytes: Option<u64>,
+}
+
+impl MemoryInfo {
+    fn detect() -> Self {
+        MemoryInfo {
+            total_bytes: Self::detect_total(),
+            available_bytes: Self::detect_available(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_total() -> Option<u64> {
+        meminfo_field("MemTotal:")
+    }
+    #[cfg(not(target_os = "linux"))]
+    fn detect_total() -> Option<u64> {
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_available() -> Option<u64> {
+        meminfo_field("MemAvailable:")
+    }
+    #[cfg(not(target_os = "linux"))]
+    fn detect_available() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn meminfo_field(prefix: &str) -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines().find_map(|line| {
+        let rest = line.strip_prefix(prefix)?;
+        let kib: u64 = rest.split_whitespace().next()?.parse().ok()?;
+        Some(kib * 1024)
+    })
+}
+
+/// Core topology of the host, distinguishing the big.LITTLE / P-core+E-core
+/// layouts common on Apple Silicon and recent x86 laptop chips from a plain
+/// symmetric core count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreTopology {
+    /// All cores are the same class.
+    Uniform { cores: usize },
+    /// Performance and efficiency cores are distinguished, as reported by
+    /// `sysctl hw.perflevel{0,1}.physicalcpu` on Apple Silicon.
+    Heterogeneous { performance: usize, efficiency: usize },
+}
+
+impl CoreTopology {
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    fn detect(physical_cores: usize) -> Self {
+        match (
+            sysctl_u32("hw.perflevel0.physicalcpu"),
+            sysctl_u32("hw.perflevel1.physicalcpu"),
+        ) {
+            (Some(p), Some(e)) if p + e > 0 => CoreTopology::Heterogeneous {
+                performance: p as usize,
+                efficiency: e as usize,
+            },
+            _ => CoreTopology::Uniform { cores: physical_cores },
+        }
+    }
+
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    fn detect(physical_cores: usize) -> Self {
+        CoreTopology::Uniform { cores: physical_cores }
+    }
+}
+
+/// Reads a `u32` sysctl by name via the raw `sysctlbyname(3)` syscall,
+/// avoiding a dependency on the `libc`/`sysctl` crates for a single lookup.
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+fn sysctl_u32(name: &str) -> Option<u32> {
+    use std::ffi::CString;
+    use std::os::raw::{c_int, c_void};
+
+    extern "C" {
+        fn sysctlbyname(
+            name: *const i8,
+            oldp: *mut c_void,
+            oldlenp: *mut usize,
+            newp: *mut c_void,
+            newlen: usize,
+        ) -> c_int;
+    }
+
+    let cname = CString::new(name).ok()?;
+    let mut value: u32 = 0;
+    let mut len = std::mem::size_of::<u32>();
+    let rc = unsafe {
+        sysctlbyname(
+            cname.as_ptr(),
+            &mut value as *mut u32 as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if rc == 0 {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Full hardware picture for a node: CPU capabilities, memory, and core
+/// topology. This is the entry point the rest of the server should use to
+/// pick inference backends and thread counts, rather than re-deriving any
+/// of this piecemeal.
+#[derive(Debug, Clone, Copy)]
+pub struct HardwareProfile {
+    pub cpu: CpuCapabilities,
+    pub memory: MemoryInfo,
+    pub topology: CoreTopology,
+}
+
+impl HardwareProfile {
+    /// Probes the full hardware profile of the host.
+    pub fn probe() -> Self {
+        let cpu = CpuCapabilities::detect();
+        HardwareProfile {
+            topology: CoreTopology::detect(cpu.physical_cores),
+            memory: MemoryInfo::detect(),
+            cpu,
+        }
+    }
+
+    /// Recommended worker count for CPU-bound inference kernels: performance
+    /// cores only when the topology is heterogeneous (efficiency cores tend
+    /// to bottleneck tightly-synchronized matmul loops), otherwise every
+    /// physical core.
+    pub fn recommended_workers(&self) -> usize {
+        match self.topology {
+            CoreTopology::Heterogeneous { performance, .. } if performance > 0 => performance,
+            CoreTopology::Uniform { cores } => cores,
+            CoreTopology::Heterogeneous { performance, efficiency } => performance + efficiency,
+        }
+    }
+}
+
+/// Counts distinct `(physical id, core id)` pairs in the text of
+/// `/proc/cpuinfo`, returning `None` when neither field is present (e.g. on
+/// most aarch64 hosts, which don't report CPU topology this way).
+#[cfg(target_os = "linux")]
+fn parse_physical_cores(cpuinfo: &str) -> Option<usize> {
+    use std::collections::HashSet;
+
+    let mut physical_id = 0usize;
+    let mut seen = HashSet::new();
+    for line in cpuinfo.lines() {
+        if let Some(value) = line.strip_prefix("physical id") {
+            if let Some(value) = value.split(':').nth(1) {
+                physical_id = value.trim().parse().unwrap_or(0);
+            }
+        } else if let Some(value) = line.strip_prefix("core id") {
+            if let Some(value) = value.split(':').nth(1) {
+                if let Ok(core_id) = value.trim().parse::<usize>() {
+                    seen.insert((physical_id, core_id));
+                }
+            }
+        }
+    }
+
+    if seen.is_empty() {
+        None
+    } else {
+        Some(seen.len())
+    }
+}
+
+impl fmt::Display for CpuCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "✅ Rust ARM64 compilation test")?;
+        writeln!(f, "   Architecture: {}", self.arch)?;
+        writeln!(f, "   OS: {}", std::env::consts::OS)?;
+        writeln!(f, "   Family: {}", std::env::consts::FAMILY)?;
+        writeln!(
+            f,
+            "   Cores: {} logical / {} physical (cache line {} bytes)",
+            self.logical_cores, self.physical_cores, self.cache_line_size
+        )?;
+        writeln!(
+            f,
+            "   SIMD: neon={} sve={} fp16={} avx2={} avx512f={} avx512vnni={} fma={}",
+            self.neon, self.sve, self.fp16, self.avx2, self.avx512f, self.avx512vnni, self.fma
+        )?;
+        write!(f, "   Best SIMD width: {:?}", self.best_simd_width())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_physical_cores_counts_distinct_physical_core_pairs() {
+        // Two sockets, two cores each, one hyperthread sibling per core —
+        // 8 logical processors, 4 distinct (physical id, core id) pairs.
+        let cpuinfo = "\
+processor\t: 0
+physical id\t: 0
+core id\t: 0
+
+processor\t: 1
+physical id\t: 0
+core id\t: 0
+
+processor\t: 2
+physical id\t: 0
+core id\t: 1
+
+processor\t: 3
+physical id\t: 0
+core id\t: 1
+
+processor\t: 4
+physical id\t: 1
+core id\t: 0
+
+processor\t: 5
+physical id\t: 1
+core id\t: 0
+
+processor\t: 6
+physical id\t: 1
+core id\t: 1
+
+processor\t: 7
+physical id\t: 1
+core id\t: 1
+";
+        assert_eq!(parse_physical_cores(cpuinfo), Some(4));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_physical_cores_returns_none_without_topology_fields() {
+        // Typical aarch64 /proc/cpuinfo: no "physical id"/"core id" lines.
+        let cpuinfo = "processor\t: 0\nBogoMIPS\t: 100.00\n\nprocessor\t: 1\nBogoMIPS\t: 100.00\n";
+        assert_eq!(parse_physical_cores(cpuinfo), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_physical_cores_returns_none_for_empty_input() {
+        assert_eq!(parse_physical_cores(""), None);
+    }
+
+    #[test]
+    fn best_simd_width_prefers_avx512_over_avx2_and_neon() {
+        let caps = CpuCapabilities {
+            arch: "x86_64",
+            logical_cores: 1,
+            physical_cores: 1,
+            cache_line_size: 64,
+            neon: true,
+            sve: false,
+            fp16: false,
+            avx2: true,
+            avx512f: true,
+            avx512vnni: false,
+            fma: true,
+        };
+        assert_eq!(caps.best_simd_width(), SimdWidth::Avx512);
+    }
+
+    #[test]
+    fn best_simd_width_prefers_avx512vnni_over_plain_avx512() {
+        let caps = CpuCapabilities {
+            arch: "x86_64",
+            logical_cores: 1,
+            physical_cores: 1,
+            cache_line_size: 64,
+            neon: false,
+            sve: false,
+            fp16: false,
+            avx2: true,
+            avx512f: true,
+            avx512vnni: true,
+            fma: true,
+        };
+        assert_eq!(caps.best_simd_width(), SimdWidth::Avx512Vnni);
+    }
+
+    #[test]
+    fn recommended_workers_uses_performance_cores_on_heterogeneous_topology() {
+        let profile = HardwareProfile {
+            cpu: CpuCapabilities {
+                arch: "aarch64",
+                logical_cores: 10,
+                physical_cores: 10,
+                cache_line_size: 128,
+                neon: true,
+                sve: false,
+                fp16: true,
+                avx2: false,
+                avx512f: false,
+                avx512vnni: false,
+                fma: false,
+            },
+            memory: MemoryInfo { total_bytes: None, available_bytes: None },
+            topology: CoreTopology::Heterogeneous { performance: 4, efficiency: 6 },
+        };
+        assert_eq!(profile.recommended_workers(), 4);
+    }
+
+    #[test]
+    fn recommended_workers_uses_all_cores_on_uniform_topology() {
+        let profile = HardwareProfile {
+            cpu: CpuCapabilities {
+                arch: "x86_64",
+                logical_cores: 8,
+                physical_cores: 8,
+                cache_line_size: 64,
+                neon: false,
+                sve: false,
+                fp16: false,
+                avx2: true,
+                avx512f: false,
+                avx512vnni: false,
+                fma: true,
+            },
+            memory: MemoryInfo { total_bytes: None, available_bytes: None },
+            topology: CoreTopology::Uniform { cores: 8 },
+        };
+        assert_eq!(profile.recommended_workers(), 8);
+    }
+
+    #[test]
+    fn best_simd_width_falls_back_to_scalar_with_nothing_detected() {
+        let caps = CpuCapabilities {
+            arch: "x86_64",
+            logical_cores: 1,
+            physical_cores: 1,
+            cache_line_size: 64,
+            neon: false,
+            sve: false,
+            fp16: false,
+            avx2: false,
+            avx512f: false,
+            avx512vnni: false,
+            fma: false,
+        };
+        assert_eq!(caps.best_simd_width(), SimdWidth::Scalar);
+    }
+}
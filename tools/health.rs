@@ -0,0 +1,183 @@
+//! `/healthz` (liveness) and `/readyz` (readiness) support: the individual
+//! checks live here as plain functions returning [`CheckResult`], and
+//! `server.rs`'s handlers assemble them into a response. Liveness only
+//! answers "is the process alive to handle a request at all" — readiness
+//! is where the deeper, slower checks (model availability, backend
+//! responsiveness, disk space, GPU reachability) belong, since a load
+//! balancer polls `/readyz` far more cautiously than `/healthz`.
+//!
+//! Every check accepts a `timeout` so a wedged backend or a slow `df`
+//! subprocess degrades one check to `false` instead of hanging the whole
+//! readiness probe — the same "bounded wait, not a hang" shape as
+//! `resources::MemoryBudget`'s admission check.
+
+use crate::model_pool::ModelPool;
+use crate::InferenceBackend;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// One check's outcome: whether it passed, plus a short human-readable
+/// reason for `/readyz`'s response body when it didn't.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        CheckResult { name, healthy: true, detail: detail.into() }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        CheckResult { name, healthy: false, detail: detail.into() }
+    }
+}
+
+/// At least one model is currently loaded, or the registry has at least
+/// one it could load on demand — an empty `models_dir` means nothing here
+/// can ever serve a request, which is exactly what readiness should catch.
+pub fn check_model_loaded(pool: &ModelPool, registry: &crate::registry::ModelRegistry) -> CheckResult {
+    if !pool.loaded_model_ids().is_empty() {
+        return CheckResult::ok("model_loaded", "at least one model resident");
+    }
+    if registry.list().next().is_some() {
+        return CheckResult::ok("model_loaded", "no model resident yet, but the registry has one to load");
+    }
+    CheckResult::fail("model_loaded", "no models loaded or registered")
+}
+
+/// Calls `backend.generate` on a background thread and waits up to
+/// `timeout` for it to return — a real GGUF backend could deadlock or spin
+/// forever on a corrupt model, and this check shouldn't block `/readyz`
+/// waiting to find out.
+pub fn check_backend_responsive(backend: &'static dyn InferenceBackend, timeout: Duration) -> CheckResult {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(backend.generate("ping"));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(_) => CheckResult::ok("backend_responsive", "generate() returned within timeout"),
+        Err(_) => CheckResult::fail("backend_responsive", format!("no response within {timeout:?}")),
+    }
+}
+
+/// Free space on the filesystem holding `path`, via `df` — there's no
+/// portable free-space query in `std`, and this tree avoids a `libc`
+/// dependency just for `statvfs` (the same trade `gpu.rs` makes calling
+/// `system_profiler` as a subprocess instead of linking IOKit).
+fn free_bytes(path: &str) -> Option<u64> {
+    let output = Command::new("df").args(["-Pk", path]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let fields: Vec<&str> = text.lines().nth(1)?.split_whitespace().collect();
+    let available_kb: u64 = fields.get(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+/// Fails once free space on `path`'s filesystem drops below
+/// `min_free_bytes` — a model directory that fills its disk can't write a
+/// downloaded model or a prefix-cache persist file, so refusing new
+/// traffic is safer than accepting it and failing mid-request.
+pub fn check_disk_space(path: &str, min_free_bytes: u64) -> CheckResult {
+    match free_bytes(path) {
+        Some(free) if free >= min_free_bytes => CheckResult::ok("disk_space", format!("{free} bytes free")),
+        Some(free) => CheckResult::fail("disk_space", format!("only {free} bytes free, need {min_free_bytes}")),
+        None => CheckResult::fail("disk_space", "could not determine free space"),
+    }
+}
+
+/// GPU reachability is best-effort: on a host with no GPU (or on a
+/// platform this tree can't probe — see `gpu::GpuInfo::detect`), this
+/// reports healthy rather than failing readiness, since plenty of valid
+/// deployments run CPU-only.
+pub fn check_gpu_reachable() -> CheckResult {
+    match crate::gpu::GpuInfo::detect() {
+        Some(gpu) => CheckResult::ok("gpu_reachable", format!("{} detected", gpu.chipset)),
+        None => CheckResult::ok("gpu_reachable", "no GPU probe available on this platform; treated as CPU-only"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubBackend;
+    impl InferenceBackend for StubBackend {
+        fn model_id(&self) -> &str {
+            "stub"
+        }
+        fn generate(&self, _prompt: &str) -> String {
+            "pong".to_string()
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    struct HangingBackend;
+    impl InferenceBackend for HangingBackend {
+        fn model_id(&self) -> &str {
+            "hanging"
+        }
+        fn generate(&self, _prompt: &str) -> String {
+            std::thread::sleep(Duration::from_secs(60));
+            String::new()
+        }
+        fn stream(&self, _prompt: &str, _on_token: &mut dyn FnMut(&str) -> bool) {}
+    }
+
+    fn empty_registry(name: &str) -> crate::registry::ModelRegistry {
+        let dir = std::env::temp_dir().join(format!("ai-server-health-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        crate::registry::ModelRegistry::open(&dir).unwrap()
+    }
+
+    #[test]
+    fn check_model_loaded_fails_when_nothing_is_loaded_or_registered() {
+        let pool = ModelPool::new(Duration::from_secs(60), |_| None);
+        let registry = empty_registry("none");
+        assert!(!check_model_loaded(&pool, &registry).healthy);
+    }
+
+    #[test]
+    fn check_model_loaded_passes_once_a_model_is_resident() {
+        let pool = ModelPool::new(Duration::from_secs(60), |id| {
+            Some(Box::new(crate::EchoBackend::new(id)) as Box<dyn InferenceBackend>)
+        });
+        pool.get_or_load("m").unwrap();
+        let registry = empty_registry("resident");
+        assert!(check_model_loaded(&pool, &registry).healthy);
+    }
+
+    #[test]
+    fn check_backend_responsive_passes_for_a_fast_backend() {
+        let backend: &'static StubBackend = Box::leak(Box::new(StubBackend));
+        assert!(check_backend_responsive(backend, Duration::from_millis(200)).healthy);
+    }
+
+    #[test]
+    fn check_backend_responsive_fails_when_generate_exceeds_the_timeout() {
+        let backend: &'static HangingBackend = Box::leak(Box::new(HangingBackend));
+        assert!(!check_backend_responsive(backend, Duration::from_millis(20)).healthy);
+    }
+
+    #[test]
+    fn check_disk_space_passes_for_a_trivially_small_requirement() {
+        assert!(check_disk_space("/", 1).healthy);
+    }
+
+    #[test]
+    fn check_disk_space_fails_for_an_impossibly_large_requirement() {
+        assert!(!check_disk_space("/", u64::MAX).healthy);
+    }
+
+    #[test]
+    fn check_gpu_reachable_never_fails() {
+        assert!(check_gpu_reachable().healthy);
+    }
+}
@@ -0,0 +1,242 @@
+//! Minimal HTTP/1.1 request/response handling over `std::net`, hand-rolled
+//! rather than pulling in `hyper`/`axum` (this tree has no dependency
+//! manager to declare them against; see `diagnostics.rs` for the same
+//! reasoning applied to JSON). Covers just enough of the protocol for a
+//! JSON/SSE API server: request-line + header parsing, `Content-Length`
+//! bodies, and chunked SSE responses.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use crate::transport::Transport;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Other(String),
+}
+
+impl Method {
+    fn parse(text: &str) -> Method {
+        match text {
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            other => Method::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: Method,
+    /// Path without the query string, e.g. `/v1/chat/completions`.
+    pub path: String,
+    /// `?key=value&...` parsed off the request target. No percent-decoding
+    /// (every user of this today is a plain-token filter value, not
+    /// arbitrary text) and empty for a target with no `?` at all. Most
+    /// handlers don't need this — bodies carry request parameters
+    /// everywhere else in this API — but a long-lived GET like
+    /// `/admin/events` has no body to put filter parameters in.
+    pub query: BTreeMap<String, String>,
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(|s| s.as_str())
+    }
+
+    pub fn body_str(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.body)
+    }
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    Io(std::io::Error),
+    Malformed(String),
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(e: std::io::Error) -> Self {
+        HttpError::Io(e)
+    }
+}
+
+/// Reads one HTTP/1.1 request off `stream`. Does not support chunked
+/// request bodies or keep-alive pipelining beyond the first request; the
+/// server treats each accepted connection as a single request/response.
+pub fn read_request(stream: &mut Transport) -> Result<Request, HttpError> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.trim_end().splitn(3, ' ');
+    let method = parts
+        .next()
+        .ok_or_else(|| HttpError::Malformed("missing method".to_string()))?;
+    let target = parts
+        .next()
+        .ok_or_else(|| HttpError::Malformed("missing request target".to_string()))?;
+    let mut target_parts = target.splitn(2, '?');
+    let path = target_parts.next().unwrap_or(target).to_string();
+    let query = target_parts.next().map(parse_query).unwrap_or_default();
+
+    let mut headers = BTreeMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(HttpError::Malformed(format!("bad header line: {line}")));
+        };
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request { method: Method::parse(method), path, query, headers, body })
+}
+
+/// Splits a `key=value&key2=value2` query string into a map. A key with no
+/// `=value` (e.g. a bare `?verbose`) maps to an empty string rather than
+/// being dropped, so a handler can still tell the key was present.
+fn parse_query(raw: &str) -> BTreeMap<String, String> {
+    raw.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k.to_string(), v.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+pub struct Response {
+    pub status: u16,
+    pub reason: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, reason: &'static str, body: &str) -> Response {
+        Response {
+            status,
+            reason,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn ok_json(body: &str) -> Response {
+        Response::json(200, "OK", body)
+    }
+
+    /// A `200 OK` response with an arbitrary `Content-Type`, used by
+    /// endpoints that don't speak JSON (e.g. `/metrics`'s Prometheus text
+    /// exposition format).
+    pub fn ok_text(body: &str, content_type: &str) -> Response {
+        Response {
+            status: 200,
+            reason: "OK",
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn not_found() -> Response {
+        Response::json(404, "Not Found", r#"{"error":{"message":"not found","type":"invalid_request_error"}}"#)
+    }
+
+    pub fn write_to(&self, stream: &mut Transport) -> std::io::Result<()> {
+        write!(stream, "HTTP/1.1 {} {}\r\n", self.status, self.reason)?;
+        for (name, value) in &self.headers {
+            write!(stream, "{name}: {value}\r\n")?;
+        }
+        write!(stream, "Content-Length: {}\r\n\r\n", self.body.len())?;
+        stream.write_all(&self.body)
+    }
+}
+
+/// Writer for a `text/event-stream` (SSE) response body. Callers write the
+/// headers once via [`SseWriter::start`] and then push one event per token.
+pub struct SseWriter<'a> {
+    stream: &'a mut Transport,
+}
+
+impl<'a> SseWriter<'a> {
+    pub fn start(stream: &'a mut Transport) -> std::io::Result<Self> {
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n\r\n"
+        )?;
+        Ok(SseWriter { stream })
+    }
+
+    /// Sends `data` as one SSE event, wrapped in an HTTP chunk.
+    pub fn send(&mut self, data: &str) -> std::io::Result<()> {
+        let event = format!("data: {data}\n\n");
+        write!(self.stream, "{:x}\r\n{}\r\n", event.len(), event)
+    }
+
+    /// Sends the OpenAI-style `data: [DONE]` sentinel and closes the chunked
+    /// body with the zero-length terminating chunk.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.send("[DONE]")?;
+        write!(self.stream, "0\r\n\r\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_parse_recognizes_get_and_post() {
+        assert_eq!(Method::parse("GET"), Method::Get);
+        assert_eq!(Method::parse("POST"), Method::Post);
+        assert_eq!(Method::parse("PATCH"), Method::Other("PATCH".to_string()));
+    }
+
+    #[test]
+    fn parse_query_splits_key_value_pairs() {
+        let query = parse_query("severity=warn&subsystem=model_pool");
+        assert_eq!(query.get("severity").map(String::as_str), Some("warn"));
+        assert_eq!(query.get("subsystem").map(String::as_str), Some("model_pool"));
+    }
+
+    #[test]
+    fn parse_query_treats_a_bare_key_as_present_with_an_empty_value() {
+        let query = parse_query("verbose");
+        assert_eq!(query.get("verbose").map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn response_write_to_includes_status_and_content_length() {
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut client = Transport::Tcp(TcpStream::connect(addr).unwrap());
+            Response::ok_json("{}").write_to(&mut client).unwrap();
+        });
+        let (mut server_side, _) = listener.accept().unwrap();
+        let mut buf = String::new();
+        std::io::Read::read_to_string(&mut server_side, &mut buf).unwrap();
+        handle.join().unwrap();
+
+        assert!(buf.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(buf.contains("Content-Length: 2\r\n"));
+        assert!(buf.ends_with("{}"));
+    }
+}
@@ -0,0 +1,633 @@
+//! Cron-style scheduled jobs: a `[job]` file dropped into a jobs directory
+//! names a schedule (a restricted 5-field cron expression) and an action —
+//! `reembed_folder`, `refresh_model`, `run_pipeline`, or `shell` — and
+//! [`JobRegistry::run_due`] runs any job whose schedule matches the
+//! current minute, the same "poll on an interval, act on what's due" shape
+//! `plugins::watch`/`mcp::watch`/`pipelines::watch` already use for their
+//! own background work, except here the interval is fixed at one minute
+//! (cron's own granularity) rather than configurable. Built for the
+//! unattended home-lab case: re-embed a docs folder nightly, refresh the
+//! model registry after a new GGUF lands, or run a pipeline on a timer,
+//! all without a separate `cron(1)` process reaching back into this
+//! server over HTTP.
+//!
+//! A job file reuses `config.rs`'s own restricted TOML subset — one
+//! `[job]` section, `schedule = "..."` plus action-specific keys — rather
+//! than inventing a second config format; [`JobRegistry::reload`] scans
+//! for `*.toml` files under a directory the same `fs::read_dir` +
+//! extension filter `pipelines::PipelineRegistry::reload` uses for its own
+//! `*.yaml` files, each file's stem becoming the job's id.
+//!
+//! The cron subset supports `*`, `*/N` step values, and comma-separated
+//! lists in each of the five fields (minute, hour, day-of-month, month,
+//! day-of-week) — no named months/weekdays, no `L`/`W`/`#` extensions.
+//! Unlike standard cron, a restricted day-of-month *and* day-of-week are
+//! ANDed together rather than ORed; a job wanting "runs on both" schedules
+//! two files instead.
+//!
+//! Run state (last run time, status, and the action's own output) is
+//! persisted to `<id>.state.json` next to `<id>.toml`, the same
+//! one-file-per-id persistence `batches::BatchStore` uses for progress, so
+//! a restart doesn't forget whether a job already ran this minute.
+
+use crate::config::{self, TomlValue};
+use crate::durability;
+use crate::embeddings::{embed_batch, EmbeddingBackend, EmbeddingRequest};
+use crate::json::{Json, ObjectBuilder};
+use crate::pipelines;
+use crate::rag;
+use crate::registry::ModelRegistry;
+use crate::response_cache::ResponseCache;
+use crate::vectorstore::{VectorId, VectorStore};
+use crate::InferenceBackend;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub enum JobError {
+    Parse(String),
+    Io(String),
+    NotFound(String),
+}
+
+impl JobError {
+    pub fn message(&self) -> String {
+        match self {
+            JobError::Parse(m) => m.clone(),
+            JobError::Io(m) => m.clone(),
+            JobError::NotFound(m) => m.clone(),
+        }
+    }
+}
+
+impl From<config::ConfigError> for JobError {
+    fn from(e: config::ConfigError) -> Self {
+        JobError::Parse(format!("{e:?}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Any,
+    Values(Vec<u32>),
+}
+
+impl Field {
+    fn matches(&self, value: u32) -> bool {
+        match self {
+            Field::Any => true,
+            Field::Values(values) => values.contains(&value),
+        }
+    }
+}
+
+fn parse_field(text: &str, min: u32, max: u32, key: &str) -> Result<Field, JobError> {
+    if text == "*" {
+        return Ok(Field::Any);
+    }
+    if let Some(step) = text.strip_prefix("*/") {
+        let step: u32 = step.parse().map_err(|_| JobError::Parse(format!("\"{key}\": invalid step {text:?}")))?;
+        if step == 0 {
+            return Err(JobError::Parse(format!("\"{key}\": step must be at least 1")));
+        }
+        return Ok(Field::Values((min..=max).step_by(step as usize).collect()));
+    }
+    let values: Vec<u32> = text
+        .split(',')
+        .map(|part| part.trim().parse::<u32>().map_err(|_| JobError::Parse(format!("\"{key}\": invalid value {part:?}"))))
+        .collect::<Result<_, _>>()?;
+    for &value in &values {
+        if value < min || value > max {
+            return Err(JobError::Parse(format!("\"{key}\": {value} is outside {min}-{max}")));
+        }
+    }
+    Ok(Field::Values(values))
+}
+
+/// A restricted 5-field cron expression (minute, hour, day-of-month,
+/// month, day-of-week) — see this module's doc comment for what's
+/// supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minute: Field,
+    hour: Field,
+    day_of_month: Field,
+    month: Field,
+    day_of_week: Field,
+}
+
+impl Schedule {
+    pub fn parse(text: &str) -> Result<Schedule, JobError> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(JobError::Parse(format!("schedule {text:?} must have 5 fields (minute hour day-of-month month day-of-week)")));
+        };
+        Ok(Schedule {
+            minute: parse_field(minute, 0, 59, "minute")?,
+            hour: parse_field(hour, 0, 23, "hour")?,
+            day_of_month: parse_field(day_of_month, 1, 31, "day-of-month")?,
+            month: parse_field(month, 1, 12, "month")?,
+            day_of_week: parse_field(day_of_week, 0, 6, "day-of-week")?,
+        })
+    }
+
+    fn matches(&self, civil: &CivilTime) -> bool {
+        self.minute.matches(civil.minute)
+            && self.hour.matches(civil.hour)
+            && self.day_of_month.matches(civil.day)
+            && self.month.matches(civil.month)
+            && self.day_of_week.matches(civil.weekday)
+    }
+}
+
+struct CivilTime {
+    minute: u32,
+    hour: u32,
+    day: u32,
+    month: u32,
+    weekday: u32,
+}
+
+/// Converts days-since-1970-01-01 into a (year, month, day) triple via
+/// Howard Hinnant's `civil_from_days` algorithm — this tree has no date
+/// library, and a cron schedule only needs the calendar fields, not a
+/// general date type. UTC only: there's no timezone or DST handling here,
+/// the same "good enough for this server's own clock" scope `auth.rs`'s
+/// day-bucketed rate limiting keeps.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if m <= 2 { y + 1 } else { y };
+    (year, m, d)
+}
+
+fn civil_time_from_unix(seconds: u64) -> CivilTime {
+    let days = (seconds / 86_400) as i64;
+    let remainder = (seconds % 86_400) as u32;
+    let (_year, month, day) = civil_from_days(days);
+    // 1970-01-01 (day 0) was a Thursday (weekday 4 in a Sunday=0 scheme).
+    let weekday = ((days.rem_euclid(7)) as u32 + 4) % 7;
+    CivilTime { minute: (remainder % 3600) / 60, hour: remainder / 3600, day, month, weekday }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobAction {
+    ReembedFolder { collection: String, directory: String },
+    RefreshModel,
+    RunPipeline { pipeline: String, input: String },
+    Shell { command: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobDefinition {
+    pub id: String,
+    pub schedule: Schedule,
+    pub action: JobAction,
+}
+
+fn require(values: &BTreeMap<String, TomlValue>, key: &str) -> Result<String, JobError> {
+    let value = values.get(key).ok_or_else(|| JobError::Parse(format!("missing required field \"{key}\"")))?;
+    Ok(config::expect_string(key, value)?)
+}
+
+fn action_from_values(kind: &str, values: &BTreeMap<String, TomlValue>) -> Result<JobAction, JobError> {
+    match kind {
+        "reembed_folder" => Ok(JobAction::ReembedFolder { collection: require(values, "job.collection")?, directory: require(values, "job.directory")? }),
+        "refresh_model" => Ok(JobAction::RefreshModel),
+        "run_pipeline" => Ok(JobAction::RunPipeline {
+            pipeline: require(values, "job.pipeline")?,
+            input: values.get("job.input").map(|v| config::expect_string("job.input", v)).transpose()?.unwrap_or_default(),
+        }),
+        "shell" => Ok(JobAction::Shell { command: require(values, "job.command")? }),
+        other => Err(JobError::Parse(format!("unknown job action \"{other}\""))),
+    }
+}
+
+/// Parses one job file's `[job]` section (`schedule`, `action`, plus
+/// whatever keys that action needs) using `config.rs`'s own restricted
+/// TOML subset.
+pub fn parse_job_file(id: &str, text: &str) -> Result<JobDefinition, JobError> {
+    let values = config::parse_toml(text)?;
+    let schedule = Schedule::parse(&require(&values, "job.schedule")?)?;
+    let kind = require(&values, "job.action")?;
+    let action = action_from_values(&kind, &values)?;
+    Ok(JobDefinition { id: id.to_string(), schedule, action })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Ok,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Ok => "ok",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<JobStatus> {
+        match s {
+            "ok" => Some(JobStatus::Ok),
+            "failed" => Some(JobStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobState {
+    pub last_run_minute: Option<u64>,
+    pub last_status: Option<JobStatus>,
+    pub last_output: String,
+    pub run_count: u64,
+}
+
+impl Default for JobState {
+    fn default() -> JobState {
+        JobState { last_run_minute: None, last_status: None, last_output: String::new(), run_count: 0 }
+    }
+}
+
+fn state_to_json(state: &JobState) -> Json {
+    let mut builder = ObjectBuilder::new()
+        .set("last_output", Json::String(state.last_output.clone()))
+        .set("run_count", Json::Number(state.run_count as f64));
+    if let Some(minute) = state.last_run_minute {
+        builder = builder.set("last_run_minute", Json::Number(minute as f64));
+    }
+    if let Some(status) = &state.last_status {
+        builder = builder.set("last_status", Json::String(status.as_str().to_string()));
+    }
+    builder.build()
+}
+
+fn state_from_json(parsed: &Json) -> JobState {
+    JobState {
+        last_run_minute: parsed.get("last_run_minute").and_then(Json::as_f64).map(|f| f as u64),
+        last_status: parsed.get("last_status").and_then(Json::as_str).and_then(JobStatus::parse),
+        last_output: parsed.get("last_output").and_then(Json::as_str).unwrap_or_default().to_string(),
+        run_count: parsed.get("run_count").and_then(Json::as_f64).unwrap_or(0.0) as u64,
+    }
+}
+
+/// Everything a running job's action needs, borrowed from the same
+/// `'static` singletons `server.rs` threads through `route()`.
+pub struct JobContext<'a> {
+    pub backend: &'a dyn InferenceBackend,
+    pub embedding_backend: &'a dyn EmbeddingBackend,
+    pub vector_store: &'a Mutex<VectorStore>,
+    pub model_registry: &'a Mutex<ModelRegistry>,
+    pub pipelines: &'a pipelines::PipelineRegistry,
+    pub response_cache: &'a ResponseCache,
+}
+
+fn vector_id_for(path: &Path, start_token: usize) -> VectorId {
+    let digest = crate::sha1::sha1(format!("{}:{start_token}", path.display()).as_bytes());
+    u64::from_be_bytes(digest[0..8].try_into().unwrap())
+}
+
+fn reembed_folder(collection: &str, directory: &str, ctx: &JobContext) -> Result<String, JobError> {
+    let entries = std::fs::read_dir(directory).map_err(|e| JobError::Io(e.to_string()))?;
+    let mut count = 0usize;
+    let mut store = ctx.vector_store.lock().unwrap();
+    for entry in entries {
+        let path = entry.map_err(|e| JobError::Io(e.to_string()))?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(doc) = crate::extract::extract(&path) else { continue };
+        let chunks = rag::chunk_document(&doc.text, rag::ChunkStrategy::Recursive { max_tokens: 200, overlap: 20 });
+        let token_batches: Vec<Vec<u32>> = chunks.iter().map(|c| c.text.bytes().map(u32::from).collect()).collect();
+        let vectors = embed_batch(ctx.embedding_backend, &token_batches, &EmbeddingRequest::default());
+        for (chunk, vector) in chunks.iter().zip(vectors) {
+            let id = vector_id_for(&path, chunk.start_token);
+            store.collection(collection).upsert(id, vector, Json::String(chunk.text.clone())).map_err(|e| JobError::Io(format!("{e:?}")))?;
+            count += 1;
+        }
+    }
+    store.persist(collection).map_err(|e| JobError::Io(format!("{e:?}")))?;
+    Ok(format!("re-embedded {count} chunk(s) from \"{directory}\" into \"{collection}\""))
+}
+
+fn execute_action(action: &JobAction, ctx: &JobContext) -> Result<String, JobError> {
+    match action {
+        JobAction::ReembedFolder { collection, directory } => reembed_folder(collection, directory, ctx),
+        JobAction::RefreshModel => {
+            ctx.model_registry.lock().unwrap().rescan().map_err(|e| JobError::Io(e.to_string()))?;
+            Ok("model registry rescanned".to_string())
+        }
+        JobAction::RunPipeline { pipeline, input } => {
+            let Some(loaded) = ctx.pipelines.get(pipeline) else {
+                return Err(JobError::NotFound(format!("no such pipeline \"{pipeline}\"")));
+            };
+            let mut last_output = String::new();
+            pipelines::run(&loaded, ctx.backend, ctx.embedding_backend, ctx.vector_store, ctx.response_cache, input, &mut |step| {
+                if let Some(output) = step.get("output").and_then(Json::as_str) {
+                    last_output = output.to_string();
+                }
+            })
+            .map_err(|e| JobError::Io(e.message()))?;
+            Ok(last_output)
+        }
+        JobAction::Shell { command } => {
+            let output = std::process::Command::new("sh").arg("-c").arg(command).output().map_err(|e| JobError::Io(e.to_string()))?;
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            Ok(text)
+        }
+    }
+}
+
+/// Jobs found under a directory, each `<id>.toml` file's stem becoming its
+/// id, with run state persisted alongside as `<id>.state.json`.
+pub struct JobRegistry {
+    dir: PathBuf,
+    jobs: Mutex<BTreeMap<String, (JobDefinition, JobState)>>,
+}
+
+impl JobRegistry {
+    pub fn open(dir: impl Into<PathBuf>) -> std::io::Result<JobRegistry> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        durability::recover_dir(&dir)?;
+        let registry = JobRegistry { dir, jobs: Mutex::new(BTreeMap::new()) };
+        registry.reload()?;
+        Ok(registry)
+    }
+
+    /// A registry with no jobs loaded, without touching the filesystem at
+    /// all — `server.rs` uses this when `[jobs]` isn't enabled in config,
+    /// the same "off means every check passes through untouched" shape
+    /// `plugins::PluginRegistry::disabled` gives callers.
+    pub fn disabled() -> JobRegistry {
+        JobRegistry { dir: PathBuf::new(), jobs: Mutex::new(BTreeMap::new()) }
+    }
+
+    fn state_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.state.json"))
+    }
+
+    /// Rebuilds the job map from whatever `*.toml` files currently exist
+    /// under `dir`, preserving each already-known job's run state and
+    /// loading a persisted `<id>.state.json` for one that's new to this
+    /// process. A file that fails to parse is skipped rather than failing
+    /// the whole reload, the same posture `pipelines::PipelineRegistry::reload`
+    /// takes toward a bad `*.yaml` file.
+    pub fn reload(&self) -> std::io::Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut fresh = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let Ok(text) = std::fs::read_to_string(&path) else { continue };
+            let Ok(definition) = parse_job_file(id, &text) else { continue };
+            let state = jobs
+                .remove(id)
+                .map(|(_, state)| state)
+                .or_else(|| std::fs::read_to_string(self.state_path(id)).ok().and_then(|text| Json::parse(&text).ok()).map(|parsed| state_from_json(&parsed)))
+                .unwrap_or_default();
+            fresh.insert(id.to_string(), (definition, state));
+        }
+        *jobs = fresh;
+        Ok(())
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.jobs.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<(JobDefinition, JobState)> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    fn record_run(&self, id: &str, minute: u64, result: &Result<String, JobError>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some((_, state)) = jobs.get_mut(id) {
+            state.last_run_minute = Some(minute);
+            state.run_count += 1;
+            match result {
+                Ok(output) => {
+                    state.last_status = Some(JobStatus::Ok);
+                    state.last_output = output.clone();
+                }
+                Err(err) => {
+                    state.last_status = Some(JobStatus::Failed);
+                    state.last_output = err.message();
+                }
+            }
+            let _ = durability::atomic_write(&self.state_path(id), state_to_json(state).to_string().as_bytes());
+        }
+    }
+
+    /// Runs one job immediately regardless of its schedule, recording the
+    /// result the same way a scheduled run does — the API-triggered path
+    /// `server.rs`'s `POST /v1/jobs/{id}/trigger` calls into.
+    pub fn trigger(&self, id: &str, ctx: &JobContext) -> Result<String, JobError> {
+        let (definition, _) = self.get(id).ok_or_else(|| JobError::NotFound(format!("no such job \"{id}\"")))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let result = execute_action(&definition.action, ctx);
+        self.record_run(id, now / 60, &result);
+        result.map_err(|e| e)
+    }
+
+    /// Runs every job whose schedule matches the minute containing `now`
+    /// and that hasn't already run during that same minute — the dedupe
+    /// that keeps a job from firing more than once if `run_due` is polled
+    /// faster than once a minute.
+    pub fn run_due(&self, now: u64, ctx: &JobContext) {
+        let minute = now / 60;
+        let civil = civil_time_from_unix(now);
+        let due: Vec<String> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (definition, state))| definition.schedule.matches(&civil) && state.last_run_minute != Some(minute))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in due {
+            let Some((definition, _)) = self.get(&id) else { continue };
+            let result = execute_action(&definition.action, ctx);
+            self.record_run(&id, minute, &result);
+        }
+    }
+
+    /// Removes a job from the in-memory map (and its persisted state) so
+    /// it stops being scheduled — the file under `dir` is left alone, so a
+    /// [`reload`](Self::reload) (or the next `watch` tick) brings it right
+    /// back; deleting a job for good means deleting its `*.toml` file.
+    pub fn cancel(&self, id: &str) -> bool {
+        let removed = self.jobs.lock().unwrap().remove(id).is_some();
+        if removed {
+            let _ = std::fs::remove_file(self.state_path(id));
+        }
+        removed
+    }
+}
+
+/// Calls [`JobRegistry::run_due`] every minute in a background thread —
+/// cron's own granularity — the same polling shape `plugins::watch` and
+/// `pipelines::watch` use for their own directory rescans.
+pub fn watch(registry: &'static JobRegistry, ctx_factory: impl Fn() -> JobContext<'static> + Send + 'static) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(60));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        registry.run_due(now, &ctx_factory());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoBackend;
+    impl InferenceBackend for EchoBackend {
+        fn model_id(&self) -> &str {
+            "echo"
+        }
+        fn generate(&self, prompt: &str) -> String {
+            format!("echo: {prompt}")
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    struct ZeroEmbeddingBackend;
+    impl EmbeddingBackend for ZeroEmbeddingBackend {
+        fn hidden_size(&self) -> usize {
+            4
+        }
+        fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+            tokens.iter().map(|_| vec![0.0; 4]).collect()
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-jobs-test-{name}-{:x}", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn parse_field_expands_a_step_expression() {
+        let field = parse_field("*/15", 0, 59, "minute").unwrap();
+        assert_eq!(field, Field::Values(vec![0, 15, 30, 45]));
+    }
+
+    #[test]
+    fn parse_field_rejects_a_value_outside_range() {
+        assert!(parse_field("60", 0, 59, "minute").is_err());
+    }
+
+    #[test]
+    fn schedule_parse_rejects_the_wrong_number_of_fields() {
+        assert!(Schedule::parse("* * *").is_err());
+    }
+
+    #[test]
+    fn schedule_matches_every_field_independently() {
+        let schedule = Schedule::parse("0 3 * * *").unwrap();
+        assert!(schedule.matches(&CivilTime { minute: 0, hour: 3, day: 15, month: 6, weekday: 2 }));
+        assert!(!schedule.matches(&CivilTime { minute: 0, hour: 4, day: 15, month: 6, weekday: 2 }));
+    }
+
+    #[test]
+    fn civil_time_from_unix_recovers_a_known_date() {
+        // 2024-01-01T00:00:00Z was a Monday.
+        let civil = civil_time_from_unix(1_704_067_200);
+        assert_eq!((civil.month, civil.day, civil.hour, civil.minute, civil.weekday), (1, 1, 0, 0, 1));
+    }
+
+    #[test]
+    fn parse_job_file_reads_schedule_and_action() {
+        let text = "[job]\nschedule = \"0 2 * * *\"\naction = \"refresh_model\"\n";
+        let job = parse_job_file("nightly", text).unwrap();
+        assert_eq!(job.id, "nightly");
+        assert_eq!(job.action, JobAction::RefreshModel);
+    }
+
+    #[test]
+    fn parse_job_file_rejects_an_unknown_action() {
+        let text = "[job]\nschedule = \"* * * * *\"\naction = \"sorcery\"\n";
+        assert!(matches!(parse_job_file("bad", text), Err(JobError::Parse(_))));
+    }
+
+    #[test]
+    fn registry_reload_finds_toml_files_and_skips_unparsable_ones() {
+        let dir = temp_dir("reload");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("nightly.toml"), "[job]\nschedule = \"0 2 * * *\"\naction = \"refresh_model\"\n").unwrap();
+        std::fs::write(dir.join("broken.toml"), "not valid toml at all [[[\n").unwrap();
+
+        let registry = JobRegistry::open(&dir).unwrap();
+        assert_eq!(registry.ids(), vec!["nightly".to_string()]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn trigger_runs_a_shell_action_and_records_its_output() {
+        let dir = temp_dir("trigger");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greet.toml"), "[job]\nschedule = \"* * * * *\"\naction = \"shell\"\ncommand = \"echo hi\"\n").unwrap();
+        let registry = JobRegistry::open(&dir).unwrap();
+
+        let backend = EchoBackend;
+        let embedding_backend = ZeroEmbeddingBackend;
+        let vector_store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        let model_registry = Mutex::new(ModelRegistry::open(&std::env::temp_dir()).unwrap());
+        let pipelines = pipelines::PipelineRegistry::disabled();
+        let response_cache = ResponseCache::new(std::time::Duration::from_secs(60), 10);
+        let ctx = JobContext { backend: &backend, embedding_backend: &embedding_backend, vector_store: &vector_store, model_registry: &model_registry, pipelines: &pipelines, response_cache: &response_cache };
+
+        let output = registry.trigger("greet", &ctx).unwrap();
+        assert!(output.contains("hi"));
+        let (_, state) = registry.get("greet").unwrap();
+        assert_eq!(state.last_status, Some(JobStatus::Ok));
+        assert_eq!(state.run_count, 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn trigger_reports_not_found_for_an_unknown_job() {
+        let dir = temp_dir("missing");
+        let registry = JobRegistry::open(&dir).unwrap();
+        let backend = EchoBackend;
+        let embedding_backend = ZeroEmbeddingBackend;
+        let vector_store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        let model_registry = Mutex::new(ModelRegistry::open(&std::env::temp_dir()).unwrap());
+        let pipelines = pipelines::PipelineRegistry::disabled();
+        let response_cache = ResponseCache::new(std::time::Duration::from_secs(60), 10);
+        let ctx = JobContext { backend: &backend, embedding_backend: &embedding_backend, vector_store: &vector_store, model_registry: &model_registry, pipelines: &pipelines, response_cache: &response_cache };
+        assert!(matches!(registry.trigger("nope", &ctx), Err(JobError::NotFound(_))));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cancel_removes_a_job_from_the_registry() {
+        let dir = temp_dir("cancel");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.toml"), "[job]\nschedule = \"* * * * *\"\naction = \"refresh_model\"\n").unwrap();
+        let registry = JobRegistry::open(&dir).unwrap();
+        assert!(registry.cancel("a"));
+        assert!(registry.ids().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,284 @@
+//! Pure-Rust quantized matmul kernels. Implements Q8_0-style blockwise
+//! quantization (32 `f32` values per block, sharing one `f32` scale, each
+//! stored as an `i8`) and accelerated dot products for aarch64 (NEON) and
+//! x86_64 (AVX2, AVX-512F, AVX-512 VNNI), with a scalar fallback everywhere
+//! else — `hardware::CpuCapabilities::best_simd_width` is what the caller
+//! should use to decide which path is actually faster.
+
+pub const BLOCK_SIZE: usize = 32;
+
+/// One quantized block: `values[i] ≈ scale * qs[i]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedBlock {
+    pub scale: f32,
+    pub qs: [i8; BLOCK_SIZE],
+}
+
+/// Quantizes `values` (padded with zeros to a multiple of [`BLOCK_SIZE`])
+/// into Q8_0 blocks, one scale per block chosen so the largest-magnitude
+/// value in the block maps to ±127.
+pub fn quantize(values: &[f32]) -> Vec<QuantizedBlock> {
+    values
+        .chunks(BLOCK_SIZE)
+        .map(|chunk| {
+            let max_abs = chunk.iter().fold(0.0f32, |acc, &v| acc.max(v.abs()));
+            let scale = if max_abs == 0.0 { 1.0 } else { max_abs / 127.0 };
+            let mut qs = [0i8; BLOCK_SIZE];
+            for (i, &v) in chunk.iter().enumerate() {
+                qs[i] = (v / scale).round().clamp(-127.0, 127.0) as i8;
+            }
+            QuantizedBlock { scale, qs }
+        })
+        .collect()
+}
+
+pub fn dequantize(blocks: &[QuantizedBlock]) -> Vec<f32> {
+    blocks
+        .iter()
+        .flat_map(|b| b.qs.iter().map(move |&q| q as f32 * b.scale))
+        .collect()
+}
+
+/// Dot product of two equal-length quantized vectors, dispatching to the
+/// best SIMD path the host actually supports at runtime — NEON on aarch64,
+/// or AVX-512 VNNI, plain AVX-512F, then AVX2 on x86_64, in that priority
+/// order — and falling back to scalar everywhere else.
+pub fn dot(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    assert_eq!(a.len(), b.len(), "quantized vectors must have the same block count");
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { dot_neon(a, b) };
+        }
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("avx512vnni") && std::arch::is_x86_feature_detected!("avx512vl") {
+            return unsafe { dot_avx512_vnni(a, b) };
+        }
+        if std::arch::is_x86_feature_detected!("avx512f") {
+            return unsafe { dot_avx512(a, b) };
+        }
+        if std::arch::is_x86_feature_detected!("avx2") {
+            return unsafe { dot_avx2(a, b) };
+        }
+    }
+    dot_scalar(a, b)
+}
+
+fn dot_scalar(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    let mut sum = 0.0f32;
+    for (block_a, block_b) in a.iter().zip(b) {
+        let mut block_sum = 0i32;
+        for i in 0..BLOCK_SIZE {
+            block_sum += block_a.qs[i] as i32 * block_b.qs[i] as i32;
+        }
+        sum += block_sum as f32 * block_a.scale * block_b.scale;
+    }
+    sum
+}
+
+/// NEON dot product: widens each block's `i8` lanes to `i16` products via
+/// `vmull_s8`/`vmlal_s8` (8 lanes at a time, 4 iterations per 32-element
+/// block), then folds the accumulator down to a scalar sum of products
+/// before applying the two blocks' scales.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn dot_neon(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    use std::arch::aarch64::*;
+
+    let mut sum = 0.0f32;
+    for (block_a, block_b) in a.iter().zip(b) {
+        let mut acc = vdupq_n_s16(0);
+        for chunk in 0..(BLOCK_SIZE / 8) {
+            let offset = chunk * 8;
+            let va = vld1_s8(block_a.qs.as_ptr().add(offset));
+            let vb = vld1_s8(block_b.qs.as_ptr().add(offset));
+            acc = vmlal_s8(acc, va, vb);
+        }
+        let block_sum: i32 = vaddlvq_s16(acc);
+        sum += block_sum as f32 * block_a.scale * block_b.scale;
+    }
+    sum
+}
+
+/// Horizontal sum of the 8 `i32` lanes in a 256-bit vector, shared by
+/// [`dot_avx2`] and [`dot_avx512_vnni`] (both fold their per-block
+/// accumulator down through this before applying the two blocks' scales).
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn hsum_epi32_avx2(v: std::arch::x86_64::__m256i) -> i32 {
+    use std::arch::x86_64::*;
+
+    let sum128 = _mm_add_epi32(_mm256_castsi256_si128(v), _mm256_extracti128_si256(v, 1));
+    let hi64 = _mm_unpackhi_epi64(sum128, sum128);
+    let sum64 = _mm_add_epi32(sum128, hi64);
+    let hi32 = _mm_shuffle_epi32(sum64, 0b01);
+    _mm_cvtsi128_si32(_mm_add_epi32(sum64, hi32))
+}
+
+/// AVX2 dot product: sign-extends each block's 32 `i8` lanes to `i32` in
+/// four 8-lane chunks via `_mm_cvtepi8_epi32` (two safe 128-bit loads per
+/// block, shifted to expose the upper 8 bytes rather than re-loading past
+/// the block's end), multiplies as `i32` to rule out overflow entirely,
+/// then reduces to a scalar sum of products before applying the two
+/// blocks' scales.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_avx2(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let mut sum = 0.0f32;
+    for (block_a, block_b) in a.iter().zip(b) {
+        let a_lo = _mm_loadu_si128(block_a.qs.as_ptr() as *const __m128i);
+        let a_hi = _mm_loadu_si128(block_a.qs.as_ptr().add(16) as *const __m128i);
+        let b_lo = _mm_loadu_si128(block_b.qs.as_ptr() as *const __m128i);
+        let b_hi = _mm_loadu_si128(block_b.qs.as_ptr().add(16) as *const __m128i);
+
+        let mut acc = _mm256_setzero_si256();
+        for &(qa, qb) in &[(a_lo, b_lo), (a_hi, b_hi)] {
+            let a_low8 = _mm256_cvtepi8_epi32(qa);
+            let a_high8 = _mm256_cvtepi8_epi32(_mm_srli_si128(qa, 8));
+            let b_low8 = _mm256_cvtepi8_epi32(qb);
+            let b_high8 = _mm256_cvtepi8_epi32(_mm_srli_si128(qb, 8));
+            acc = _mm256_add_epi32(acc, _mm256_mullo_epi32(a_low8, b_low8));
+            acc = _mm256_add_epi32(acc, _mm256_mullo_epi32(a_high8, b_high8));
+        }
+        let block_sum = hsum_epi32_avx2(acc);
+        sum += block_sum as f32 * block_a.scale * block_b.scale;
+    }
+    sum
+}
+
+/// AVX-512F dot product: sign-extends each block's two 16-byte halves
+/// straight to 16-lane `i32` vectors via `_mm512_cvtepi8_epi32`, multiplies
+/// as `i32`, and reduces with `_mm512_reduce_add_epi32` before applying the
+/// two blocks' scales.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn dot_avx512(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let mut sum = 0.0f32;
+    for (block_a, block_b) in a.iter().zip(b) {
+        let a_lo = _mm_loadu_si128(block_a.qs.as_ptr() as *const __m128i);
+        let a_hi = _mm_loadu_si128(block_a.qs.as_ptr().add(16) as *const __m128i);
+        let b_lo = _mm_loadu_si128(block_b.qs.as_ptr() as *const __m128i);
+        let b_hi = _mm_loadu_si128(block_b.qs.as_ptr().add(16) as *const __m128i);
+
+        let products_lo = _mm512_mullo_epi32(_mm512_cvtepi8_epi32(a_lo), _mm512_cvtepi8_epi32(b_lo));
+        let products_hi = _mm512_mullo_epi32(_mm512_cvtepi8_epi32(a_hi), _mm512_cvtepi8_epi32(b_hi));
+        let block_sum = _mm512_reduce_add_epi32(_mm512_add_epi32(products_lo, products_hi));
+        sum += block_sum as f32 * block_a.scale * block_b.scale;
+    }
+    sum
+}
+
+/// AVX-512 VNNI dot product: `_mm256_dpbusd_epi32` only multiplies an
+/// *unsigned* `u8` operand against a signed `i8` one, so `block_a`'s signed
+/// lanes are rebiased to unsigned by XORing the sign bit
+/// (`a_u8 = a_i8 + 128`) before the fused multiply-accumulate, and the
+/// bias is then subtracted back out: `sum(a_i8*b_i8) = sum(a_u8*b_i8) -
+/// 128 * sum(b_i8)`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512vnni,avx512vl,avx2")]
+unsafe fn dot_avx512_vnni(a: &[QuantizedBlock], b: &[QuantizedBlock]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let mut sum = 0.0f32;
+    for (block_a, block_b) in a.iter().zip(b) {
+        let va = _mm256_loadu_si256(block_a.qs.as_ptr() as *const __m256i);
+        let vb = _mm256_loadu_si256(block_b.qs.as_ptr() as *const __m256i);
+        let a_biased = _mm256_xor_si256(va, _mm256_set1_epi8(0x80u8 as i8));
+
+        let biased_sum = hsum_epi32_avx2(_mm256_dpbusd_epi32(_mm256_setzero_si256(), a_biased, vb));
+        let b_sum: i32 = block_b.qs.iter().map(|&q| q as i32).sum();
+        let block_sum = biased_sum - 128 * b_sum;
+        sum += block_sum as f32 * block_a.scale * block_b.scale;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize_then_dequantize_stays_close_to_original() {
+        let values: Vec<f32> = (0..BLOCK_SIZE).map(|i| i as f32 - 16.0).collect();
+        let blocks = quantize(&values);
+        let round_tripped = dequantize(&blocks);
+        for (orig, got) in values.iter().zip(&round_tripped) {
+            assert!((orig - got).abs() < 0.2, "orig={orig} got={got}");
+        }
+    }
+
+    #[test]
+    fn quantize_handles_an_all_zero_block_without_dividing_by_zero() {
+        let blocks = quantize(&[0.0; BLOCK_SIZE]);
+        assert_eq!(blocks[0].scale, 1.0);
+        assert!(blocks[0].qs.iter().all(|&q| q == 0));
+    }
+
+    #[test]
+    fn dot_matches_scalar_reference_computed_from_dequantized_values() {
+        let a: Vec<f32> = (0..BLOCK_SIZE).map(|i| (i as f32) * 0.5).collect();
+        let b: Vec<f32> = (0..BLOCK_SIZE).map(|i| (BLOCK_SIZE - i) as f32 * 0.25).collect();
+        let qa = quantize(&a);
+        let qb = quantize(&b);
+
+        let expected: f32 = dequantize(&qa).iter().zip(dequantize(&qb).iter()).map(|(x, y)| x * y).sum();
+        let got = dot(&qa, &qb);
+        assert!((expected - got).abs() / expected.abs().max(1.0) < 0.05);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dot_panics_on_mismatched_block_counts() {
+        let qa = quantize(&[1.0; BLOCK_SIZE]);
+        let qb = quantize(&[1.0; BLOCK_SIZE * 2]);
+        dot(&qa, &qb);
+    }
+
+    fn sample_blocks() -> (Vec<QuantizedBlock>, Vec<QuantizedBlock>) {
+        let a: Vec<f32> = (0..BLOCK_SIZE * 3).map(|i| (i as f32 - 48.0) * 0.7).collect();
+        let b: Vec<f32> = (0..BLOCK_SIZE * 3).map(|i| ((BLOCK_SIZE * 3 - i) as f32 - 20.0) * 0.3).collect();
+        (quantize(&a), quantize(&b))
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn dot_avx2_matches_scalar_reference() {
+        if !std::arch::is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let (qa, qb) = sample_blocks();
+        let expected = dot_scalar(&qa, &qb);
+        let got = unsafe { dot_avx2(&qa, &qb) };
+        assert!((expected - got).abs() / expected.abs().max(1.0) < 1e-3);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn dot_avx512_matches_scalar_reference() {
+        if !std::arch::is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let (qa, qb) = sample_blocks();
+        let expected = dot_scalar(&qa, &qb);
+        let got = unsafe { dot_avx512(&qa, &qb) };
+        assert!((expected - got).abs() / expected.abs().max(1.0) < 1e-3);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn dot_avx512_vnni_matches_scalar_reference() {
+        if !std::arch::is_x86_feature_detected!("avx512vnni") || !std::arch::is_x86_feature_detected!("avx512vl") {
+            return;
+        }
+        let (qa, qb) = sample_blocks();
+        let expected = dot_scalar(&qa, &qb);
+        let got = unsafe { dot_avx512_vnni(&qa, &qb) };
+        assert!((expected - got).abs() / expected.abs().max(1.0) < 1e-3);
+    }
+}
@@ -0,0 +1,113 @@
+//! Paged KV-cache allocator, modeled on vLLM's PagedAttention: the cache
+//! is carved into fixed-size blocks (pages) of KV entries, and each
+//! sequence gets a list of block indices rather than one contiguous
+//! allocation. That lets short sequences release their tail blocks and
+//! long ones grow without needing to reserve worst-case space up front.
+
+use std::collections::VecDeque;
+
+pub type SequenceId = u64;
+
+/// A paged KV-cache with `total_blocks` fixed-size pages, each holding
+/// `block_size` token entries.
+pub struct KvCacheManager {
+    block_size: usize,
+    free_blocks: VecDeque<usize>,
+    sequences: std::collections::HashMap<SequenceId, SequenceCache>,
+}
+
+struct SequenceCache {
+    blocks: Vec<usize>,
+    tokens_cached: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum KvCacheError {
+    OutOfBlocks,
+    UnknownSequence,
+}
+
+impl KvCacheManager {
+    pub fn new(total_blocks: usize, block_size: usize) -> Self {
+        KvCacheManager {
+            block_size,
+            free_blocks: (0..total_blocks).collect(),
+            sequences: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn free_block_count(&self) -> usize {
+        self.free_blocks.len()
+    }
+
+    /// Grows `sequence`'s cache to hold `tokens_cached` total tokens,
+    /// allocating whatever additional blocks that requires. Evicting a
+    /// clean sequence to free space is the caller's job (`evict`) since
+    /// only the caller's scheduler knows which sequences are safe to drop.
+    pub fn extend(&mut self, sequence: SequenceId, tokens_cached: usize) -> Result<(), KvCacheError> {
+        let blocks_needed = tokens_cached.div_ceil(self.block_size);
+        let entry = self.sequences.entry(sequence).or_insert_with(|| SequenceCache {
+            blocks: Vec::new(),
+            tokens_cached: 0,
+        });
+
+        while entry.blocks.len() < blocks_needed {
+            let block = self.free_blocks.pop_front().ok_or(KvCacheError::OutOfBlocks)?;
+            entry.blocks.push(block);
+        }
+        entry.tokens_cached = tokens_cached;
+        Ok(())
+    }
+
+    /// Releases every block held by `sequence`, e.g. once its request
+    /// finishes or it's evicted to make room for another sequence.
+    pub fn evict(&mut self, sequence: SequenceId) -> Result<(), KvCacheError> {
+        let entry = self.sequences.remove(&sequence).ok_or(KvCacheError::UnknownSequence)?;
+        for block in entry.blocks {
+            self.free_blocks.push_back(block);
+        }
+        Ok(())
+    }
+
+    pub fn blocks_for(&self, sequence: SequenceId) -> Option<&[usize]> {
+        self.sequences.get(&sequence).map(|s| s.blocks.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extend_allocates_only_as_many_blocks_as_needed() {
+        let mut cache = KvCacheManager::new(8, 4);
+        cache.extend(1, 5).unwrap();
+        assert_eq!(cache.blocks_for(1).unwrap().len(), 2); // ceil(5/4) = 2
+        assert_eq!(cache.free_block_count(), 6);
+    }
+
+    #[test]
+    fn extend_reuses_already_allocated_blocks_on_regrowth() {
+        let mut cache = KvCacheManager::new(8, 4);
+        cache.extend(1, 3).unwrap();
+        cache.extend(1, 6).unwrap();
+        assert_eq!(cache.blocks_for(1).unwrap().len(), 2);
+        assert_eq!(cache.free_block_count(), 6);
+    }
+
+    #[test]
+    fn extend_fails_once_blocks_are_exhausted() {
+        let mut cache = KvCacheManager::new(1, 4);
+        cache.extend(1, 4).unwrap();
+        assert_eq!(cache.extend(2, 4), Err(KvCacheError::OutOfBlocks));
+    }
+
+    #[test]
+    fn evict_returns_blocks_to_the_free_pool() {
+        let mut cache = KvCacheManager::new(2, 4);
+        cache.extend(1, 4).unwrap();
+        cache.evict(1).unwrap();
+        assert_eq!(cache.free_block_count(), 2);
+        assert_eq!(cache.evict(1), Err(KvCacheError::UnknownSequence));
+    }
+}
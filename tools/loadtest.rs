@@ -0,0 +1,305 @@
+//! HTTP-driven inference benchmarking: sends synthetic chat-completion
+//! workloads at a running `ai-server serve` instance and reports
+//! tokens/sec, time-to-first-token, and the server's resident-memory
+//! high-water mark (scraped from `/metrics`, see `metrics.rs`).
+//!
+//! This is what `ai-server bench --target <url>` drives instead of the
+//! original ARM64 smoke test's local compute self-benchmark (still
+//! available without `--target`, see `bench.rs`): a compute-only
+//! self-test never touches the request path (routing, streaming,
+//! tokenization overhead) that actually determines a deployment's real
+//! throughput, so exercising it over the loopback HTTP API is the only
+//! way to measure what a client actually experiences.
+//!
+//! The HTTP client here is hand-rolled rather than pulled from a crate,
+//! matching `downloader.rs`'s trade: plain HTTP over `std::net`, with just
+//! enough chunked-transfer-encoding support to read `http.rs`'s SSE
+//! responses.
+
+use crate::json::{Json, ObjectBuilder};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkloadConfig {
+    /// Words repeated to build a synthetic prompt of roughly this length.
+    pub prompt_tokens: usize,
+    pub concurrency: usize,
+    pub requests: usize,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        WorkloadConfig { prompt_tokens: 128, concurrency: 1, requests: 8 }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadTestError {
+    Io(String),
+    Http(String),
+}
+
+/// One request's timing, in seconds.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time_to_first_token: f64,
+    total: f64,
+    tokens_generated: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub requests: usize,
+    pub tokens_per_second: f64,
+    pub time_to_first_token_p50: f64,
+    pub time_to_first_token_p99: f64,
+    pub memory_high_water_bytes: Option<u64>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        let mut builder = ObjectBuilder::new()
+            .set("requests", Json::Number(self.requests as f64))
+            .set("tokens_per_second", Json::Number(self.tokens_per_second))
+            .set("time_to_first_token_p50_seconds", Json::Number(self.time_to_first_token_p50))
+            .set("time_to_first_token_p99_seconds", Json::Number(self.time_to_first_token_p99));
+        builder = builder.set(
+            "memory_high_water_bytes",
+            self.memory_high_water_bytes.map(|v| Json::Number(v as f64)).unwrap_or(Json::Null),
+        );
+        builder.build().to_string()
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "requests:            {}", self.requests)?;
+        writeln!(f, "tokens/sec:          {:.2}", self.tokens_per_second)?;
+        writeln!(f, "TTFT p50:            {:.3}s", self.time_to_first_token_p50)?;
+        writeln!(f, "TTFT p99:            {:.3}s", self.time_to_first_token_p99)?;
+        match self.memory_high_water_bytes {
+            Some(bytes) => write!(f, "memory high-water:   {:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+            None => write!(f, "memory high-water:   unavailable"),
+        }
+    }
+}
+
+/// Runs `config.requests` streaming chat completions against `host:port`,
+/// spread across `config.concurrency` worker threads, and returns the
+/// aggregate report.
+pub fn run(host_port: &str, config: &WorkloadConfig) -> Result<Report, LoadTestError> {
+    let prompt = "benchmark ".repeat(config.prompt_tokens.max(1));
+    let (tx, rx) = mpsc::channel();
+    let per_worker = config.requests.div_ceil(config.concurrency.max(1));
+
+    std::thread::scope(|scope| {
+        for _ in 0..config.concurrency.max(1) {
+            let tx = tx.clone();
+            let prompt = &prompt;
+            scope.spawn(move || {
+                for _ in 0..per_worker {
+                    tx.send(send_one(host_port, prompt)).ok();
+                }
+            });
+        }
+        drop(tx);
+
+        let mut samples = Vec::with_capacity(config.requests);
+        for result in rx.iter().take(config.requests) {
+            samples.push(result?);
+        }
+        Ok(summarize(samples, scrape_memory_high_water(host_port)))
+    })
+}
+
+fn send_one(host_port: &str, prompt: &str) -> Result<Sample, LoadTestError> {
+    let body = ObjectBuilder::new()
+        .set("model", Json::String("bench".to_string()))
+        .set("stream", Json::Bool(true))
+        .set(
+            "messages",
+            Json::Array(vec![ObjectBuilder::new()
+                .set("role", Json::String("user".to_string()))
+                .set("content", Json::String(prompt.to_string()))
+                .build()]),
+        )
+        .build()
+        .to_string();
+
+    let mut stream = TcpStream::connect(host_port).map_err(|e| LoadTestError::Io(e.to_string()))?;
+    write!(
+        stream,
+        "POST /v1/chat/completions HTTP/1.1\r\nHost: {host_port}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .map_err(|e| LoadTestError::Io(e.to_string()))?;
+
+    let started = Instant::now();
+    let mut reader = BufReader::new(stream);
+    skip_headers(&mut reader)?;
+
+    let mut time_to_first_token = None;
+    let mut tokens_generated = 0usize;
+    let mut buffer = Vec::new();
+    read_chunked_body(&mut reader, |chunk| {
+        buffer.extend_from_slice(chunk);
+        while let Some(pos) = find(&buffer, b"\n\n") {
+            let event = buffer[..pos].to_vec();
+            buffer.drain(..pos + 2);
+            let Some(data) = event.strip_prefix(b"data: ") else { continue };
+            if data == b"[DONE]" {
+                continue;
+            }
+            time_to_first_token.get_or_insert_with(|| started.elapsed().as_secs_f64());
+            tokens_generated += 1;
+        }
+    })?;
+
+    Ok(Sample {
+        time_to_first_token: time_to_first_token.unwrap_or_else(|| started.elapsed().as_secs_f64()),
+        total: started.elapsed().as_secs_f64(),
+        tokens_generated,
+    })
+}
+
+fn skip_headers(reader: &mut BufReader<TcpStream>) -> Result<(), LoadTestError> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(|e| LoadTestError::Io(e.to_string()))?;
+    if !status_line.contains("200") {
+        return Err(LoadTestError::Http(format!("unexpected status line: {}", status_line.trim())));
+    }
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| LoadTestError::Io(e.to_string()))?;
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads an HTTP/1.1 chunked-transfer-encoded body, calling `on_chunk` with
+/// each chunk's raw bytes until the zero-length terminating chunk.
+fn read_chunked_body(reader: &mut BufReader<TcpStream>, mut on_chunk: impl FnMut(&[u8])) -> Result<(), LoadTestError> {
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line).map_err(|e| LoadTestError::Io(e.to_string()))?;
+        let size = usize::from_str_radix(size_line.trim(), 16)
+            .map_err(|_| LoadTestError::Http(format!("bad chunk size: {:?}", size_line.trim())))?;
+        if size == 0 {
+            return Ok(());
+        }
+        let mut chunk = vec![0u8; size];
+        reader.read_exact(&mut chunk).map_err(|e| LoadTestError::Io(e.to_string()))?;
+        on_chunk(&chunk);
+
+        let mut trailer = [0u8; 2]; // consume the chunk's trailing "\r\n"
+        reader.read_exact(&mut trailer).map_err(|e| LoadTestError::Io(e.to_string()))?;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// GETs `/metrics` and pulls out the resident-memory high-water gauge, so
+/// the report can include a real memory figure without this module needing
+/// its own `/proc` reads (see `metrics.rs`'s `render`).
+fn scrape_memory_high_water(host_port: &str) -> Option<u64> {
+    let mut stream = TcpStream::connect(host_port).ok()?;
+    write!(stream, "GET /metrics HTTP/1.1\r\nHost: {host_port}\r\nConnection: close\r\n\r\n").ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    response
+        .lines()
+        .find(|line| line.starts_with("ai_server_process_resident_memory_high_water_bytes"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+}
+
+fn summarize(mut samples: Vec<Sample>, memory_high_water_bytes: Option<u64>) -> Report {
+    let requests = samples.len();
+    let total_tokens: usize = samples.iter().map(|s| s.tokens_generated).sum();
+    let total_time: f64 = samples.iter().map(|s| s.total).fold(0.0, f64::max).max(f64::EPSILON);
+    let tokens_per_second = total_tokens as f64 / total_time;
+
+    samples.sort_by(|a, b| a.time_to_first_token.total_cmp(&b.time_to_first_token));
+    let percentile = |p: f64| -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let index = ((samples.len() - 1) as f64 * p).round() as usize;
+        samples[index].time_to_first_token
+    };
+
+    Report {
+        requests,
+        tokens_per_second,
+        time_to_first_token_p50: percentile(0.50),
+        time_to_first_token_p99: percentile(0.99),
+        memory_high_water_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_locates_the_double_newline_separator() {
+        assert_eq!(find(b"data: hi\n\nmore", b"\n\n"), Some(8));
+        assert_eq!(find(b"no separator here", b"\n\n"), None);
+    }
+
+    #[test]
+    fn summarize_computes_tokens_per_second_and_percentiles() {
+        let samples = vec![
+            Sample { time_to_first_token: 0.1, total: 1.0, tokens_generated: 10 },
+            Sample { time_to_first_token: 0.3, total: 2.0, tokens_generated: 20 },
+        ];
+        let report = summarize(samples, Some(1024));
+        assert_eq!(report.requests, 2);
+        assert_eq!(report.tokens_per_second, 15.0); // 30 tokens / 2.0s slowest request
+        // With 2 samples, both the 50th and 99th percentile index round to
+        // the higher of the two sorted TTFTs.
+        assert_eq!(report.time_to_first_token_p50, 0.3);
+        assert_eq!(report.time_to_first_token_p99, 0.3);
+        assert_eq!(report.memory_high_water_bytes, Some(1024));
+    }
+
+    #[test]
+    fn report_to_json_includes_every_field() {
+        let report = Report {
+            requests: 4,
+            tokens_per_second: 12.5,
+            time_to_first_token_p50: 0.05,
+            time_to_first_token_p99: 0.2,
+            memory_high_water_bytes: None,
+        };
+        let json = report.to_json();
+        assert!(json.contains("\"requests\":4"));
+        assert!(json.contains("\"tokens_per_second\":12.5"));
+        assert!(json.contains("\"memory_high_water_bytes\":null"));
+    }
+
+    #[test]
+    fn read_chunked_body_reassembles_chunks_and_stops_at_terminator() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n").unwrap();
+        });
+        let (server_side, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(server_side);
+        let mut collected = Vec::new();
+        read_chunked_body(&mut reader, |chunk| collected.extend_from_slice(chunk)).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(collected, b"hello world");
+    }
+}
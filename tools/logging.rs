@@ -0,0 +1,186 @@
+//! Structured JSON request logging with size-based rotation, replacing the
+//! `println!` calls sprinkled through `main()` and the handlers. Each
+//! logged request is one JSON object — one line per request by default, or
+//! pretty-printed with blank lines between entries in [`LogFormat::Pretty`]
+//! for local development, where a plain log-shipper doesn't matter and a
+//! human is the one reading it.
+//!
+//! Rotation is size-based rather than time-based: once the active file
+//! passes `max_bytes`, it's renamed to `{path}.1` (overwriting a prior
+//! `{path}.1`) and a fresh file is started. That's one rotated generation,
+//! not `logrotate`'s numbered chain — enough to bound disk use without a
+//! retention-count config this tree has no surface for yet.
+
+use crate::json::{Json, ObjectBuilder};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+/// One completed request, ready to log. Fields a caller doesn't have (e.g.
+/// token counts on a path that errored before generating anything) are
+/// `None` rather than forcing a placeholder value into the record.
+pub struct LogEvent<'a> {
+    pub request_id: &'a str,
+    pub model: Option<&'a str>,
+    pub client: Option<&'a str>,
+    pub latency_ms: f64,
+    pub prompt_tokens: Option<usize>,
+    pub completion_tokens: Option<usize>,
+}
+
+impl<'a> LogEvent<'a> {
+    fn to_json(&self) -> Json {
+        let mut builder = ObjectBuilder::new().set("request_id", Json::String(self.request_id.to_string())).set(
+            "latency_ms",
+            Json::Number(self.latency_ms),
+        );
+        if let Some(model) = self.model {
+            builder = builder.set("model", Json::String(model.to_string()));
+        }
+        if let Some(client) = self.client {
+            builder = builder.set("client", Json::String(client.to_string()));
+        }
+        if let Some(n) = self.prompt_tokens {
+            builder = builder.set("prompt_tokens", Json::Number(n as f64));
+        }
+        if let Some(n) = self.completion_tokens {
+            builder = builder.set("completion_tokens", Json::Number(n as f64));
+        }
+        builder.build()
+    }
+}
+
+/// A rotating structured-log sink shared across request-handling threads,
+/// the same leaked-`'static`-plus-`Mutex` shape as `metrics::Registry`.
+pub struct JsonLogger {
+    path: PathBuf,
+    max_bytes: u64,
+    format: LogFormat,
+    file: Mutex<File>,
+}
+
+impl JsonLogger {
+    /// Opens (creating if needed) the log file at `path` for appending.
+    /// `max_bytes` of `0` disables rotation.
+    pub fn open(path: impl AsRef<Path>, max_bytes: u64, format: LogFormat) -> std::io::Result<JsonLogger> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(JsonLogger { path, max_bytes, format, file: Mutex::new(file) })
+    }
+
+    /// Writes `event` and rotates first if the file has already grown past
+    /// `max_bytes` — checked before the write rather than after, so a
+    /// single log line never gets split across the rotation boundary.
+    pub fn log(&self, event: &LogEvent) {
+        let mut file = self.file.lock().unwrap();
+        if self.max_bytes > 0 {
+            if let Ok(metadata) = file.metadata() {
+                if metadata.len() >= self.max_bytes {
+                    self.rotate(&mut file);
+                }
+            }
+        }
+        let rendered = match self.format {
+            LogFormat::Json => format!("{}\n", event.to_json().to_string()),
+            LogFormat::Pretty => format!("{}\n\n", to_pretty_string(&event.to_json(), 0)),
+        };
+        let _ = file.write_all(rendered.as_bytes());
+    }
+
+    fn rotate(&self, file: &mut File) {
+        let rotated_path = self.path.with_extension(match self.path.extension() {
+            Some(ext) => format!("{}.1", ext.to_string_lossy()),
+            None => "1".to_string(),
+        });
+        let _ = fs::rename(&self.path, &rotated_path);
+        if let Ok(fresh) = OpenOptions::new().create(true).append(true).open(&self.path) {
+            *file = fresh;
+        }
+    }
+}
+
+/// Renders `json` (expected to be a flat object, as every [`LogEvent`]
+/// produces) with two-space indentation for [`LogFormat::Pretty`]. Handles
+/// nested arrays/objects too rather than assuming flatness, since nothing
+/// stops a future event field from being one.
+fn to_pretty_string(json: &Json, indent: usize) -> String {
+    let pad = "  ".repeat(indent);
+    let inner_pad = "  ".repeat(indent + 1);
+    match json {
+        Json::Object(map) if !map.is_empty() => {
+            let entries: Vec<String> =
+                map.iter().map(|(k, v)| format!("{inner_pad}{}: {}", Json::String(k.clone()).to_string(), to_pretty_string(v, indent + 1))).collect();
+            format!("{{\n{}\n{pad}}}", entries.join(",\n"))
+        }
+        Json::Array(items) if !items.is_empty() => {
+            let entries: Vec<String> = items.iter().map(|v| format!("{inner_pad}{}", to_pretty_string(v, indent + 1))).collect();
+            format!("[\n{}\n{pad}]", entries.join(",\n"))
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ai-server-log-test-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn log_appends_one_json_line_per_event() {
+        let path = temp_path("append");
+        let logger = JsonLogger::open(&path, 0, LogFormat::Json).unwrap();
+        logger.log(&LogEvent { request_id: "r1", model: Some("m"), client: None, latency_ms: 12.5, prompt_tokens: Some(3), completion_tokens: Some(7) });
+        logger.log(&LogEvent { request_id: "r2", model: None, client: Some("key-a"), latency_ms: 4.0, prompt_tokens: None, completion_tokens: None });
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"request_id\":\"r1\""));
+        assert!(lines[0].contains("\"model\":\"m\""));
+        assert!(lines[1].contains("\"client\":\"key-a\""));
+        assert!(!lines[1].contains("\"model\""));
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn pretty_format_spreads_fields_across_multiple_lines() {
+        let path = temp_path("pretty");
+        let logger = JsonLogger::open(&path, 0, LogFormat::Pretty).unwrap();
+        logger.log(&LogEvent { request_id: "r1", model: None, client: None, latency_ms: 1.0, prompt_tokens: None, completion_tokens: None });
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.lines().count() > 1);
+        assert!(contents.contains("\"request_id\": \"r1\""));
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn rotates_to_a_dot_one_file_once_max_bytes_is_exceeded() {
+        let path = temp_path("rotate.log");
+        let logger = JsonLogger::open(&path, 1, LogFormat::Json).unwrap();
+        logger.log(&LogEvent { request_id: "first", model: None, client: None, latency_ms: 1.0, prompt_tokens: None, completion_tokens: None });
+        logger.log(&LogEvent { request_id: "second", model: None, client: None, latency_ms: 1.0, prompt_tokens: None, completion_tokens: None });
+
+        let rotated_path = path.with_extension("log.1");
+        let rotated = fs::read_to_string(&rotated_path).unwrap();
+        assert!(rotated.contains("\"first\""));
+        let active = fs::read_to_string(&path).unwrap();
+        assert!(active.contains("\"second\""));
+        assert!(!active.contains("\"first\""));
+
+        fs::remove_file(&path).ok();
+        fs::remove_file(&rotated_path).ok();
+    }
+}
@@ -0,0 +1,161 @@
+//! LoRA (Low-Rank Adaptation) adapters: instead of storing a full
+//! fine-tuned copy of a model, an adapter stores two small matrices per
+//! target tensor, `A` (`rank x in_dim`) and `B` (`out_dim x rank`), whose
+//! product approximates the fine-tuning delta. Merging one in is applying
+//! `W' = W + (alpha / rank) * B @ A` to the base weight.
+//!
+//! There's no real tensor loading in this tree yet — `gguf.rs` only reads
+//! the tensor *table*, not tensor data (see its doc comment) — so
+//! [`LoraDelta::merge_into`] operates on a plain `&mut [f32]` weight
+//! buffer rather than a parsed GGUF/safetensors tensor. A real backend
+//! that does load tensor data calls it once per target tensor, either at
+//! model load time or per request if adapters are swapped without
+//! reloading the base weights.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, PartialEq)]
+pub enum LoraError {
+    DimensionMismatch { expected: usize, got: usize },
+    UnknownTensor(String),
+}
+
+impl std::fmt::Display for LoraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoraError::DimensionMismatch { expected, got } => {
+                write!(f, "weight buffer has {got} elements, adapter expects {expected}")
+            }
+            LoraError::UnknownTensor(name) => write!(f, "adapter has no delta for tensor {name:?}"),
+        }
+    }
+}
+
+/// One target tensor's low-rank delta: `B` (`out_dim x rank`) times `A`
+/// (`rank x in_dim`), each stored row-major and flattened like the base
+/// weight buffers they're merged into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoraDelta {
+    pub in_dim: usize,
+    pub out_dim: usize,
+    pub rank: usize,
+    pub a: Vec<f32>,
+    pub b: Vec<f32>,
+}
+
+impl LoraDelta {
+    /// Adds `(alpha / rank) * B @ A` into `weight`, a flattened
+    /// `out_dim x in_dim` row-major matrix.
+    pub fn merge_into(&self, weight: &mut [f32], alpha: f32) -> Result<(), LoraError> {
+        let expected = self.out_dim * self.in_dim;
+        if weight.len() != expected {
+            return Err(LoraError::DimensionMismatch { expected, got: weight.len() });
+        }
+        let scale = alpha / self.rank as f32;
+        for out in 0..self.out_dim {
+            for inp in 0..self.in_dim {
+                let mut delta = 0.0;
+                for r in 0..self.rank {
+                    delta += self.b[out * self.rank + r] * self.a[r * self.in_dim + inp];
+                }
+                weight[out * self.in_dim + inp] += scale * delta;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A LoRA adapter: named target tensors (e.g. `blk.0.attn_q.weight`) each
+/// mapped to their delta, plus the adapter's own alpha scaling factor.
+#[derive(Debug, Clone)]
+pub struct LoraAdapter {
+    pub id: String,
+    pub alpha: f32,
+    pub deltas: BTreeMap<String, LoraDelta>,
+}
+
+impl LoraAdapter {
+    pub fn merge_tensor(&self, tensor_name: &str, weight: &mut [f32]) -> Result<(), LoraError> {
+        let delta = self.deltas.get(tensor_name).ok_or_else(|| LoraError::UnknownTensor(tensor_name.to_string()))?;
+        delta.merge_into(weight, self.alpha)
+    }
+}
+
+/// In-memory catalog of loaded adapters, keyed by the id a request's
+/// `lora` field (or a model alias) names. There's no on-disk format to
+/// scan for yet (unlike `registry::ModelRegistry`'s GGUF files), so
+/// adapters only become available once something calls [`register`](Self::register)
+/// — a future adapter-loading CLI subcommand or admin endpoint is the
+/// natural caller.
+#[derive(Default)]
+pub struct AdapterRegistry {
+    adapters: Mutex<BTreeMap<String, std::sync::Arc<LoraAdapter>>>,
+}
+
+impl AdapterRegistry {
+    pub fn new() -> Self {
+        AdapterRegistry::default()
+    }
+
+    pub fn register(&self, adapter: LoraAdapter) {
+        self.adapters.lock().unwrap().insert(adapter.id.clone(), std::sync::Arc::new(adapter));
+    }
+
+    pub fn get(&self, id: &str) -> Option<std::sync::Arc<LoraAdapter>> {
+        self.adapters.lock().unwrap().get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity_delta() -> LoraDelta {
+        // rank 1, in_dim 2, out_dim 2: B = [[1], [1]], A = [[1, 1]], so
+        // B @ A = [[1, 1], [1, 1]] before scaling.
+        LoraDelta { in_dim: 2, out_dim: 2, rank: 1, a: vec![1.0, 1.0], b: vec![1.0, 1.0] }
+    }
+
+    #[test]
+    fn merge_into_adds_the_scaled_low_rank_product() {
+        let delta = identity_delta();
+        let mut weight = vec![0.0, 0.0, 0.0, 0.0];
+        delta.merge_into(&mut weight, 2.0).unwrap(); // alpha / rank = 2.0
+        assert_eq!(weight, vec![2.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn merge_into_rejects_a_mismatched_weight_buffer() {
+        let delta = identity_delta();
+        let mut weight = vec![0.0, 0.0, 0.0]; // needs 4 elements, not 3
+        let err = delta.merge_into(&mut weight, 1.0).unwrap_err();
+        assert_eq!(err, LoraError::DimensionMismatch { expected: 4, got: 3 });
+    }
+
+    #[test]
+    fn merge_tensor_rejects_a_name_the_adapter_has_no_delta_for() {
+        let adapter = LoraAdapter { id: "a".to_string(), alpha: 1.0, deltas: BTreeMap::new() };
+        let mut weight = vec![0.0];
+        let err = adapter.merge_tensor("missing.weight", &mut weight).unwrap_err();
+        assert_eq!(err, LoraError::UnknownTensor("missing.weight".to_string()));
+    }
+
+    #[test]
+    fn merge_tensor_applies_the_named_deltas_math() {
+        let mut deltas = BTreeMap::new();
+        deltas.insert("q.weight".to_string(), identity_delta());
+        let adapter = LoraAdapter { id: "a".to_string(), alpha: 1.0, deltas };
+        let mut weight = vec![0.0, 0.0, 0.0, 0.0];
+        adapter.merge_tensor("q.weight", &mut weight).unwrap();
+        assert_eq!(weight, vec![1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn adapter_registry_round_trips_a_registered_adapter() {
+        let registry = AdapterRegistry::new();
+        assert!(registry.get("fine-tune-1").is_none());
+        registry.register(LoraAdapter { id: "fine-tune-1".to_string(), alpha: 1.0, deltas: BTreeMap::new() });
+        assert_eq!(registry.get("fine-tune-1").unwrap().id, "fine-tune-1");
+    }
+}
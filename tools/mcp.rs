@@ -0,0 +1,399 @@
+//! Model Context Protocol (MCP) support: this server exposes its own
+//! generation capability as one MCP tool over `POST /mcp` (see
+//! [`dispatch`]), and can also connect out to configured external MCP
+//! servers, pulling their tools into the same `tool_calls.rs` pipeline a
+//! request's own `tools` array goes through (see
+//! [`McpClientRegistry::tool_definitions`], folded in by
+//! `handle_chat_completions`). Tool calls stay client-orchestrated either
+//! way — this server never executes a tool call it returns, so an
+//! MCP-discovered tool surfaces in a chat completion's `tool_calls` field
+//! exactly like a request-supplied one; [`McpClientRegistry::call_tool`]
+//! exists for a future caller (an admin endpoint, a CLI subcommand) that
+//! wants to invoke one directly instead.
+//!
+//! MCP's wire format is JSON-RPC 2.0. There's no JSON-RPC crate in this
+//! tree (see `json.rs`'s own no-dependency reasoning), so [`dispatch`]
+//! and [`McpClientRegistry`] build and parse envelopes by hand with
+//! `json.rs` primitives, the same way `audit.rs` hand-rolls the RFC 5424
+//! syslog header instead of pulling in a syslog crate.
+//!
+//! Client mode dials an external server the same way `router.rs` dials a
+//! downstream node: a plain `TcpStream` speaking HTTP/1.1, POSTing one
+//! JSON-RPC request per call to a fixed `/mcp` path and reading back a
+//! single JSON response body. That covers MCP's "Streamable HTTP"
+//! transport in its simplest (non-SSE-streamed) form; a server that only
+//! answers with an SSE stream is unreachable here, the same honest gap
+//! `router.rs`'s `probe` has toward a peer that doesn't speak plain HTTP.
+
+use crate::json::{Json, ObjectBuilder};
+use crate::tool_calls::ToolDefinition;
+use crate::InferenceBackend;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn ok_response(id: &Json, result: Json) -> Json {
+    ObjectBuilder::new()
+        .set("jsonrpc", Json::String("2.0".to_string()))
+        .set("id", id.clone())
+        .set("result", result)
+        .build()
+}
+
+fn err_response(id: &Json, code: i64, message: &str) -> Json {
+    let error = ObjectBuilder::new().set("code", Json::Number(code as f64)).set("message", Json::String(message.to_string())).build();
+    ObjectBuilder::new().set("jsonrpc", Json::String("2.0".to_string())).set("id", id.clone()).set("error", error).build()
+}
+
+/// The one tool this server exposes over MCP: `generate`, a thin wrapper
+/// around `InferenceBackend::generate`. There's only one backend
+/// implementation in this tree today (`EchoBackend`), so this is the
+/// whole capability surface for now — a future backend gains an MCP tool
+/// for free, since [`dispatch`] is handed whichever `InferenceBackend`
+/// the caller is already serving requests with.
+fn generate_tool_definition() -> Json {
+    let properties = ObjectBuilder::new().set("prompt", ObjectBuilder::new().set("type", Json::String("string".to_string())).build()).build();
+    let schema = ObjectBuilder::new()
+        .set("type", Json::String("object".to_string()))
+        .set("properties", properties)
+        .set("required", Json::Array(vec![Json::String("prompt".to_string())]))
+        .build();
+    ObjectBuilder::new()
+        .set("name", Json::String("generate".to_string()))
+        .set("description", Json::String("Generate a completion from the server's active model".to_string()))
+        .set("inputSchema", schema)
+        .build()
+}
+
+/// Handles one JSON-RPC request against this server's MCP surface:
+/// `initialize`, `tools/list`, and `tools/call`. An unrecognized method
+/// gets the standard JSON-RPC "method not found" error (`-32601`); a
+/// request with no `"method"` field gets `-32600` ("invalid request").
+pub fn dispatch(request: &Json, backend: &dyn InferenceBackend) -> Json {
+    let id = request.get("id").cloned().unwrap_or(Json::Null);
+    let Some(method) = request.get("method").and_then(Json::as_str) else {
+        return err_response(&id, -32600, "invalid request: missing \"method\"");
+    };
+    match method {
+        "initialize" => ok_response(
+            &id,
+            ObjectBuilder::new()
+                .set("protocolVersion", Json::String("2024-11-05".to_string()))
+                .set("capabilities", ObjectBuilder::new().set("tools", ObjectBuilder::new().build()).build())
+                .set(
+                    "serverInfo",
+                    ObjectBuilder::new().set("name", Json::String("ai-server".to_string())).set("version", Json::String(backend.model_id().to_string())).build(),
+                )
+                .build(),
+        ),
+        "tools/list" => ok_response(&id, ObjectBuilder::new().set("tools", Json::Array(vec![generate_tool_definition()])).build()),
+        "tools/call" => {
+            let params = request.get("params").cloned().unwrap_or(Json::Null);
+            let Some(name) = params.get("name").and_then(Json::as_str) else {
+                return err_response(&id, -32602, "invalid params: missing \"name\"");
+            };
+            if name != "generate" {
+                return err_response(&id, -32602, &format!("unknown tool \"{name}\""));
+            }
+            let prompt = params.get("arguments").and_then(|a| a.get("prompt")).and_then(Json::as_str).unwrap_or("");
+            let text = backend.generate(prompt);
+            let content = Json::Array(vec![ObjectBuilder::new().set("type", Json::String("text".to_string())).set("text", Json::String(text)).build()]);
+            ok_response(&id, ObjectBuilder::new().set("content", content).build())
+        }
+        _ => err_response(&id, -32601, &format!("method not found: \"{method}\"")),
+    }
+}
+
+/// One external MCP server this instance connects to as a client, in the
+/// `name=host:port` shape `config.rs` parses `[mcp] client_servers` into
+/// — the same convention `router.rs`'s `RouterNode` list uses for
+/// `router.nodes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpServer {
+    pub name: String,
+    pub address: String,
+}
+
+/// POSTs one JSON-RPC request to `address`'s `/mcp` and returns its
+/// `result` on success. `None` on any transport, HTTP, or JSON-RPC-level
+/// failure — a client depending on a flaky or offline tool server should
+/// still generate ordinary completions, the same "don't let this hook
+/// break generation" posture `plugins::PluginRegistry::run_one` takes
+/// toward an unreachable `wasmtime` binary.
+fn call(address: &str, method: &str, params: Json, timeout: Duration) -> Option<Json> {
+    let request = ObjectBuilder::new()
+        .set("jsonrpc", Json::String("2.0".to_string()))
+        .set("id", Json::Number(1.0))
+        .set("method", Json::String(method.to_string()))
+        .set("params", params)
+        .build()
+        .to_string();
+
+    let stream = TcpStream::connect(address).ok()?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    stream.set_write_timeout(Some(timeout)).ok()?;
+    let mut writer = stream.try_clone().ok()?;
+    write!(
+        writer,
+        "POST /mcp HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{request}",
+        request.len()
+    )
+    .ok()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).ok()?;
+    let status: u16 = status_line.split_whitespace().nth(1)?.parse().ok()?;
+    if status != 200 {
+        return None;
+    }
+
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).ok()?;
+    let response = Json::parse(&String::from_utf8_lossy(&body)).ok()?;
+    if response.get("error").is_some() {
+        return None;
+    }
+    response.get("result").cloned()
+}
+
+/// One tool discovered from an external MCP server, qualified with the
+/// server's name (`"<server>.<tool>"`) so tools from different servers
+/// can never collide — the same reasoning
+/// `tenancy::TenantRegistry::namespaced_collection` gives for prefixing
+/// tenant-scoped collection names.
+#[derive(Debug, Clone, PartialEq)]
+struct DiscoveredTool {
+    qualified_name: String,
+    server: String,
+    tool_name: String,
+    definition: ToolDefinition,
+}
+
+/// Tools discovered from a fixed set of external MCP servers, refreshed
+/// on demand or on an interval (see [`watch`]).
+pub struct McpClientRegistry {
+    servers: Vec<McpServer>,
+    timeout: Duration,
+    tools: Mutex<Vec<DiscoveredTool>>,
+}
+
+impl McpClientRegistry {
+    /// Connects to every configured server's `tools/list` once up front.
+    /// A server that's unreachable at startup simply contributes no
+    /// tools until the next [`refresh`](Self::refresh) finds it.
+    pub fn open(servers: Vec<McpServer>, timeout: Duration) -> McpClientRegistry {
+        let registry = McpClientRegistry { servers, timeout, tools: Mutex::new(Vec::new()) };
+        registry.refresh();
+        registry
+    }
+
+    /// A registry with no servers and nothing to discover, without
+    /// making any connection attempt. `server.rs` uses this when `[mcp]`
+    /// client mode isn't configured, the same "off means every check
+    /// passes through untouched" shape `guardrails::GuardrailsEngine::disabled`
+    /// gives callers so they never need to special-case "off".
+    pub fn disabled() -> McpClientRegistry {
+        McpClientRegistry { servers: Vec::new(), timeout: Duration::from_secs(5), tools: Mutex::new(Vec::new()) }
+    }
+
+    /// Re-runs `tools/list` against every configured server and replaces
+    /// the cached tool list wholesale — a server that's since gone
+    /// offline simply contributes no tools on this pass, the same
+    /// from-scratch re-derivation `router::spawn_health_checks` does for
+    /// a node's served-model list on each poll.
+    pub fn refresh(&self) {
+        let mut discovered = Vec::new();
+        for server in &self.servers {
+            let Some(result) = call(&server.address, "tools/list", Json::Object(Default::default()), self.timeout) else { continue };
+            let Some(tools) = result.get("tools").and_then(Json::as_array) else { continue };
+            for tool in tools {
+                let Some(name) = tool.get("name").and_then(Json::as_str) else { continue };
+                let description = tool.get("description").and_then(Json::as_str).map(str::to_string);
+                let parameters = tool
+                    .get("inputSchema")
+                    .cloned()
+                    .unwrap_or_else(|| ObjectBuilder::new().set("type", Json::String("object".to_string())).build());
+                let qualified_name = format!("{}.{}", server.name, name);
+                discovered.push(DiscoveredTool {
+                    qualified_name: qualified_name.clone(),
+                    server: server.name.clone(),
+                    tool_name: name.to_string(),
+                    definition: ToolDefinition { name: qualified_name, description, parameters },
+                });
+            }
+        }
+        *self.tools.lock().unwrap() = discovered;
+    }
+
+    /// Every currently discovered tool, ready to fold into a chat
+    /// request's own `tools` — see `tool_calls::parse_tools`, whose
+    /// output `handle_chat_completions` extends with this before
+    /// resolving `tool_choice`.
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.lock().unwrap().iter().map(|t| t.definition.clone()).collect()
+    }
+
+    /// Invokes `qualified_name` (as returned by
+    /// [`tool_definitions`](Self::tool_definitions)) on whichever server
+    /// it was discovered from, and returns its `content` blocks joined
+    /// as plain text. `None` if the name isn't a known MCP tool or the
+    /// call fails.
+    pub fn call_tool(&self, qualified_name: &str, arguments: &Json) -> Option<String> {
+        let tools = self.tools.lock().unwrap();
+        let tool = tools.iter().find(|t| t.qualified_name == qualified_name)?;
+        let server = self.servers.iter().find(|s| s.name == tool.server)?;
+        let params = ObjectBuilder::new().set("name", Json::String(tool.tool_name.clone())).set("arguments", arguments.clone()).build();
+        let result = call(&server.address, "tools/call", params, self.timeout)?;
+        let content = result.get("content").and_then(Json::as_array)?;
+        Some(content.iter().filter_map(|c| c.get("text").and_then(Json::as_str)).collect::<Vec<_>>().join(""))
+    }
+}
+
+/// Calls [`McpClientRegistry::refresh`] every `interval` in a background
+/// thread — the same polling shape `plugins::watch` uses for
+/// hot-reloading the plugins directory.
+pub fn watch(registry: &'static McpClientRegistry, interval: Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        registry.refresh();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead as _;
+    use std::net::TcpListener;
+
+    struct StubBackend;
+
+    impl InferenceBackend for StubBackend {
+        fn model_id(&self) -> &str {
+            "stub"
+        }
+        fn generate(&self, prompt: &str) -> String {
+            format!("echo: {prompt}")
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    #[test]
+    fn dispatch_initialize_reports_server_info() {
+        let request = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#).unwrap();
+        let response = dispatch(&request, &StubBackend);
+        assert_eq!(response.get("result").and_then(|r| r.get("serverInfo")).and_then(|s| s.get("name")).and_then(Json::as_str), Some("ai-server"));
+    }
+
+    #[test]
+    fn dispatch_tools_list_reports_the_generate_tool() {
+        let request = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#).unwrap();
+        let response = dispatch(&request, &StubBackend);
+        let tools = response.get("result").and_then(|r| r.get("tools")).and_then(Json::as_array).unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].get("name").and_then(Json::as_str), Some("generate"));
+    }
+
+    #[test]
+    fn dispatch_tools_call_runs_the_backend() {
+        let request = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"generate","arguments":{"prompt":"hi"}}}"#).unwrap();
+        let response = dispatch(&request, &StubBackend);
+        let content = response.get("result").and_then(|r| r.get("content")).and_then(Json::as_array).unwrap();
+        assert_eq!(content[0].get("text").and_then(Json::as_str), Some("echo: hi"));
+    }
+
+    #[test]
+    fn dispatch_reports_method_not_found_for_an_unknown_method() {
+        let request = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#).unwrap();
+        let response = dispatch(&request, &StubBackend);
+        assert_eq!(response.get("error").and_then(|e| e.get("code")).and_then(Json::as_f64), Some(-32601.0));
+    }
+
+    #[test]
+    fn dispatch_reports_invalid_params_for_an_unknown_tool() {
+        let request = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"nope"}}"#).unwrap();
+        let response = dispatch(&request, &StubBackend);
+        assert_eq!(response.get("error").and_then(|e| e.get("code")).and_then(Json::as_f64), Some(-32602.0));
+    }
+
+    #[test]
+    fn disabled_registry_discovers_nothing() {
+        let registry = McpClientRegistry::disabled();
+        assert!(registry.tool_definitions().is_empty());
+    }
+
+    /// Runs a tiny MCP server on a loopback port for `request_count`
+    /// requests: reads each HTTP request, dispatches it against
+    /// `StubBackend`, and writes back one JSON response per connection —
+    /// just enough to exercise `McpClientRegistry`'s client role without
+    /// a real second `ai-server` process to connect to.
+    fn serve_mcp_requests(listener: TcpListener, request_count: usize) {
+        std::thread::spawn(move || {
+            for _ in 0..request_count {
+                let Ok((stream, _)) = listener.accept() else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0usize;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.trim().eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let parsed = Json::parse(&String::from_utf8_lossy(&body)).unwrap();
+                let response = dispatch(&parsed, &StubBackend).to_string();
+                let mut writer = stream;
+                write!(writer, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{response}", response.len()).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn a_client_registry_discovers_and_calls_a_live_servers_tools() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        serve_mcp_requests(listener, 2);
+
+        let registry = McpClientRegistry::open(vec![McpServer { name: "peer".to_string(), address: address.clone() }], Duration::from_secs(2));
+        let tools = registry.tool_definitions();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "peer.generate");
+
+        let arguments = Json::parse(r#"{"prompt":"hi"}"#).unwrap();
+        let result = registry.call_tool("peer.generate", &arguments).unwrap();
+        assert_eq!(result, "echo: hi");
+    }
+
+    #[test]
+    fn call_tool_is_none_for_an_unknown_qualified_name() {
+        let registry = McpClientRegistry::disabled();
+        assert_eq!(registry.call_tool("nothing.here", &Json::Null), None);
+    }
+}
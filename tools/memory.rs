@@ -0,0 +1,147 @@
+//! Automatic conversation-history compaction: `sessions.rs` persists every
+//! turn a conversation has ever had, which is exactly what a client needs
+//! to resume verbatim, but not what fits in a model's context window once
+//! a session runs long. [`compact`] folds the oldest turns into one
+//! generated summary once a session crosses its own configured token
+//! threshold (`Session::memory_compact_above_tokens`), and
+//! [`messages_for_prompt`] injects that summary ahead of the turns still
+//! kept verbatim — the same "keep what matters, drop the rest" shape
+//! `context_policy::apply`'s `Truncate` policy uses for a single request,
+//! just applied across a whole conversation's lifetime instead of one
+//! prompt. Every setting is a plain field on `Session` rather than a
+//! server-wide default, so one long-running agent transcript and one short
+//! chat session can each pick their own compaction threshold.
+
+use crate::sessions::{Message, Session};
+use crate::InferenceBackend;
+
+/// The same whitespace-count "tokens" proxy `context_policy.rs` uses
+/// elsewhere in this tree — good enough to decide when a session has grown
+/// too large without needing a real tokenizer wired in here.
+fn word_count(messages: &[Message]) -> usize {
+    messages.iter().map(|m| m.content.split_whitespace().count()).sum()
+}
+
+/// Whether `session` has grown past its own configured threshold and
+/// should be compacted before its next completion request. Always `false`
+/// when `session.memory_enabled` is off, regardless of size.
+pub fn needs_compaction(session: &Session) -> bool {
+    session.memory_enabled && word_count(&session.messages) > session.memory_compact_above_tokens
+}
+
+/// Renders the messages being folded away into one summarization prompt —
+/// the same generate-then-splice shape `pipelines.rs`'s `StepKind::Llm`
+/// uses, just with a fixed prompt template instead of a user-authored one.
+fn summarization_prompt(previous_summary: Option<&str>, messages: &[Message]) -> String {
+    let mut prompt = String::new();
+    if let Some(summary) = previous_summary {
+        prompt.push_str("Summary of the conversation so far:\n");
+        prompt.push_str(summary);
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("Summarize the following conversation turns concisely, keeping any facts, decisions, and open questions a later turn would need:\n\n");
+    for message in messages {
+        prompt.push_str(&message.role);
+        prompt.push_str(": ");
+        prompt.push_str(&message.content);
+        prompt.push('\n');
+    }
+    prompt
+}
+
+/// Compacts `session` in place if [`needs_compaction`] says it's grown too
+/// large: everything except the last `session.memory_keep_recent_turns`
+/// messages is folded into `session.summary` via `backend.generate`,
+/// replacing (not appending to) any prior summary so re-compacting doesn't
+/// re-summarize what's already been summarized. A no-op otherwise,
+/// including when the whole history is still within
+/// `memory_keep_recent_turns` and there's nothing older to fold away.
+pub fn compact(session: &mut Session, backend: &dyn InferenceBackend) {
+    if !needs_compaction(session) {
+        return;
+    }
+    let split = session.messages.len().saturating_sub(session.memory_keep_recent_turns);
+    if split == 0 {
+        return;
+    }
+    let (older, recent) = session.messages.split_at(split);
+    let prompt = summarization_prompt(session.summary.as_deref(), older);
+    session.summary = Some(backend.generate(&prompt));
+    session.messages = recent.to_vec();
+}
+
+/// Builds the message list a completion request should actually see:
+/// `session.summary` (if any), injected as a system message ahead of the
+/// kept-verbatim messages, so a compacted session's later turns still have
+/// the earlier context available without it counting against
+/// `memory_keep_recent_turns`'s "how many raw turns" budget.
+pub fn messages_for_prompt(session: &Session) -> Vec<Message> {
+    match &session.summary {
+        Some(summary) => {
+            let mut messages = vec![Message { role: "system".to_string(), content: format!("Earlier conversation summary:\n{summary}") }];
+            messages.extend(session.messages.iter().cloned());
+            messages
+        }
+        None => session.messages.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EchoBackend;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message { role: role.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn needs_compaction_is_false_when_memory_is_disabled() {
+        let session = Session { messages: vec![message("user", &"word ".repeat(100))], memory_compact_above_tokens: 10, ..Session::default() };
+        assert!(!needs_compaction(&session));
+    }
+
+    #[test]
+    fn needs_compaction_is_true_once_the_session_crosses_its_threshold() {
+        let session =
+            Session { messages: vec![message("user", &"word ".repeat(100))], memory_enabled: true, memory_compact_above_tokens: 10, ..Session::default() };
+        assert!(needs_compaction(&session));
+    }
+
+    #[test]
+    fn compact_replaces_older_messages_with_a_generated_summary() {
+        let mut session = Session {
+            messages: vec![message("user", "one"), message("assistant", "two"), message("user", "three"), message("assistant", "four")],
+            memory_enabled: true,
+            memory_compact_above_tokens: 1,
+            memory_keep_recent_turns: 2,
+            ..Session::default()
+        };
+        compact(&mut session, &EchoBackend::new("m"));
+        assert!(session.summary.is_some());
+        assert_eq!(session.messages, vec![message("user", "three"), message("assistant", "four")]);
+    }
+
+    #[test]
+    fn compact_is_a_no_op_when_the_session_has_not_crossed_its_threshold() {
+        let mut session = Session { messages: vec![message("user", "hi")], memory_enabled: true, memory_compact_above_tokens: 1000, ..Session::default() };
+        let before = session.clone();
+        compact(&mut session, &EchoBackend::new("m"));
+        assert_eq!(session, before);
+    }
+
+    #[test]
+    fn messages_for_prompt_injects_the_summary_as_a_leading_system_message() {
+        let session = Session { summary: Some("recap".to_string()), messages: vec![message("user", "hi")], ..Session::default() };
+        let prompt_messages = messages_for_prompt(&session);
+        assert_eq!(prompt_messages[0].role, "system");
+        assert!(prompt_messages[0].content.contains("recap"));
+        assert_eq!(prompt_messages[1], message("user", "hi"));
+    }
+
+    #[test]
+    fn messages_for_prompt_returns_messages_unchanged_with_no_summary_yet() {
+        let session = Session { messages: vec![message("user", "hi")], ..Session::default() };
+        assert_eq!(messages_for_prompt(&session), session.messages);
+    }
+}
@@ -0,0 +1,279 @@
+//! Hand-rolled Prometheus text-exposition-format metrics, mirroring
+//! `json.rs`'s approach of writing the wire format directly rather than
+//! depending on the `prometheus` crate (not available under this tree's
+//! no-dependency policy). [`Registry`] holds the handful of series the
+//! backlog asked for — request counts, generation latency/throughput, and
+//! resource gauges — and `/metrics` in `server.rs` renders it on demand.
+//!
+//! `queue_depth` and `kv_cache_occupancy` are populated via setters rather
+//! than computed here, because this HTTP layer's only backend so far
+//! (`EchoBackend`) doesn't run `scheduler.rs`/`kvcache.rs` — a real backend
+//! wiring those in would call `set_queue_depth`/`set_kv_cache_occupancy`
+//! after each `Scheduler::step`. Until then they read as a flat `0`.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Fixed histogram bucket upper bounds, chosen to span sub-millisecond
+/// time-to-first-token up through multi-second generations.
+const LATENCY_BUCKETS_SECONDS: [f64; 8] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+const THROUGHPUT_BUCKETS_TOKENS_PER_SEC: [f64; 7] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0];
+/// Warmup runs a model's first prefill/decode passes, which can be much
+/// slower than steady-state generation (JIT-compiling Metal pipelines,
+/// paging in weights) — this histogram's bounds span that instead of
+/// reusing `LATENCY_BUCKETS_SECONDS`.
+const WARMUP_BUCKETS_SECONDS: [f64; 7] = [0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0];
+
+/// A Prometheus-style histogram: per-bucket cumulative counts plus a
+/// running sum, enough to reconstruct quantiles with `histogram_quantile`
+/// on the scraping side.
+struct Histogram {
+    bounds: &'static [f64],
+    bucket_counts: Vec<AtomicU64>,
+    sum_millis: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            bucket_counts: (0..bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_millis: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(&self.bucket_counts) {
+            if value <= *bound {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_millis.fetch_add((value * 1000.0) as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, name: &str, out: &mut String) {
+        out.push_str(&format!("# TYPE {name} histogram\n"));
+        for (bound, count) in self.bounds.iter().zip(&self.bucket_counts) {
+            out.push_str(&format!("{name}_bucket{{le=\"{bound}\"}} {}\n", count.load(Ordering::Relaxed)));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        out.push_str(&format!("{name}_bucket{{le=\"+Inf\"}} {count}\n"));
+        out.push_str(&format!("{name}_sum {}\n", self.sum_millis.load(Ordering::Relaxed) as f64 / 1000.0));
+        out.push_str(&format!("{name}_count {count}\n"));
+    }
+}
+
+/// The process-wide metrics store. One instance is created in `main` and
+/// leaked (same pattern as `VectorStore` in `server.rs`) so every request
+/// thread can record into it through a shared reference.
+pub struct Registry {
+    requests_total: Mutex<BTreeMap<String, u64>>,
+    shadow_requests_total: Mutex<BTreeMap<(String, String), u64>>,
+    time_to_first_token_seconds: Histogram,
+    tokens_per_second: Histogram,
+    model_warmup_seconds: Histogram,
+    queue_depth: AtomicU64,
+    kv_cache_occupancy_permille: AtomicU64,
+    gpu_memory_bytes: AtomicU64,
+    embedding_cache_hit_ratio_permille: AtomicU64,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            requests_total: Mutex::new(BTreeMap::new()),
+            shadow_requests_total: Mutex::new(BTreeMap::new()),
+            time_to_first_token_seconds: Histogram::new(&LATENCY_BUCKETS_SECONDS),
+            tokens_per_second: Histogram::new(&THROUGHPUT_BUCKETS_TOKENS_PER_SEC),
+            model_warmup_seconds: Histogram::new(&WARMUP_BUCKETS_SECONDS),
+            queue_depth: AtomicU64::new(0),
+            kv_cache_occupancy_permille: AtomicU64::new(0),
+            gpu_memory_bytes: AtomicU64::new(0),
+            embedding_cache_hit_ratio_permille: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_request(&self, model: &str) {
+        let mut counts = self.requests_total.lock().unwrap();
+        *counts.entry(model.to_string()).or_insert(0) += 1;
+    }
+
+    /// Counts a `model_alias.rs` shadow-mirrored request — one that was
+    /// generated for comparison but whose response was discarded, so it
+    /// never reaches [`record_request`](Self::record_request).
+    pub fn record_shadow_request(&self, alias: &str, shadow_model: &str) {
+        let mut counts = self.shadow_requests_total.lock().unwrap();
+        *counts.entry((alias.to_string(), shadow_model.to_string())).or_insert(0) += 1;
+    }
+
+    pub fn observe_time_to_first_token(&self, seconds: f64) {
+        self.time_to_first_token_seconds.observe(seconds);
+    }
+
+    pub fn observe_tokens_per_second(&self, tokens_per_second: f64) {
+        self.tokens_per_second.observe(tokens_per_second);
+    }
+
+    pub fn observe_model_warmup(&self, seconds: f64) {
+        self.model_warmup_seconds.observe(seconds);
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.store(depth as u64, Ordering::Relaxed);
+    }
+
+    /// `occupancy` is a fraction in `[0.0, 1.0]`, stored as parts-per-mille
+    /// so the gauge can live in an `AtomicU64` without a lock.
+    pub fn set_kv_cache_occupancy(&self, occupancy: f64) {
+        self.kv_cache_occupancy_permille.store((occupancy.clamp(0.0, 1.0) * 1000.0) as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_gpu_memory_bytes(&self, bytes: u64) {
+        self.gpu_memory_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// `ratio` is a fraction in `[0.0, 1.0]`, stored as parts-per-mille
+    /// the same way [`set_kv_cache_occupancy`](Self::set_kv_cache_occupancy) is.
+    pub fn set_embedding_cache_hit_ratio(&self, ratio: f64) {
+        self.embedding_cache_hit_ratio_permille.store((ratio.clamp(0.0, 1.0) * 1000.0) as u64, Ordering::Relaxed);
+    }
+
+    /// Renders the full registry in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# TYPE ai_server_requests_total counter\n");
+        for (model, count) in self.requests_total.lock().unwrap().iter() {
+            out.push_str(&format!("ai_server_requests_total{{model=\"{model}\"}} {count}\n"));
+        }
+
+        out.push_str("# TYPE ai_server_shadow_requests_total counter\n");
+        for ((alias, shadow_model), count) in self.shadow_requests_total.lock().unwrap().iter() {
+            out.push_str(&format!("ai_server_shadow_requests_total{{alias=\"{alias}\",shadow_model=\"{shadow_model}\"}} {count}\n"));
+        }
+
+        self.time_to_first_token_seconds.render("ai_server_time_to_first_token_seconds", &mut out);
+        self.tokens_per_second.render("ai_server_tokens_per_second", &mut out);
+        self.model_warmup_seconds.render("ai_server_model_warmup_seconds", &mut out);
+
+        out.push_str("# TYPE ai_server_queue_depth gauge\n");
+        out.push_str(&format!("ai_server_queue_depth {}\n", self.queue_depth.load(Ordering::Relaxed)));
+
+        out.push_str("# TYPE ai_server_kv_cache_occupancy_ratio gauge\n");
+        let occupancy = self.kv_cache_occupancy_permille.load(Ordering::Relaxed) as f64 / 1000.0;
+        out.push_str(&format!("ai_server_kv_cache_occupancy_ratio {occupancy}\n"));
+
+        out.push_str("# TYPE ai_server_gpu_memory_bytes gauge\n");
+        out.push_str(&format!("ai_server_gpu_memory_bytes {}\n", self.gpu_memory_bytes.load(Ordering::Relaxed)));
+
+        out.push_str("# TYPE ai_server_embedding_cache_hit_ratio gauge\n");
+        let embedding_cache_hit_ratio = self.embedding_cache_hit_ratio_permille.load(Ordering::Relaxed) as f64 / 1000.0;
+        out.push_str(&format!("ai_server_embedding_cache_hit_ratio {embedding_cache_hit_ratio}\n"));
+
+        if let Some(bytes) = process_memory_high_water_bytes() {
+            out.push_str("# TYPE ai_server_process_resident_memory_high_water_bytes gauge\n");
+            out.push_str(&format!("ai_server_process_resident_memory_high_water_bytes {bytes}\n"));
+        }
+
+        out
+    }
+}
+
+/// Reads this process's peak resident set size from `/proc/self/status`'s
+/// `VmHWM` field (kibibytes), the same `/proc` scraping `hardware.rs` uses
+/// for host memory sizing. Read fresh on every `/metrics` scrape rather
+/// than cached in an atomic, since it's a cheap file read and the kernel
+/// already tracks the high-water mark for us.
+#[cfg(target_os = "linux")]
+fn process_memory_high_water_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_hwm(&status)
+}
+
+#[cfg(target_os = "linux")]
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    status.lines().find_map(|line| {
+        let rest = line.strip_prefix("VmHWM:")?;
+        let kib: u64 = rest.split_whitespace().next()?.parse().ok()?;
+        Some(kib * 1024)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_memory_high_water_bytes() -> Option<u64> {
+    None
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_per_model_request_counts() {
+        let registry = Registry::new();
+        registry.record_request("echo-0");
+        registry.record_request("echo-0");
+        registry.record_request("other");
+        let rendered = registry.render();
+        assert!(rendered.contains("ai_server_requests_total{model=\"echo-0\"} 2"));
+        assert!(rendered.contains("ai_server_requests_total{model=\"other\"} 1"));
+    }
+
+    #[test]
+    fn records_per_alias_shadow_request_counts() {
+        let registry = Registry::new();
+        registry.record_shadow_request("prod", "candidate");
+        registry.record_shadow_request("prod", "candidate");
+        let rendered = registry.render();
+        assert!(rendered.contains("ai_server_shadow_requests_total{alias=\"prod\",shadow_model=\"candidate\"} 2"));
+    }
+
+    #[test]
+    fn histogram_places_observations_into_cumulative_buckets() {
+        let registry = Registry::new();
+        registry.observe_time_to_first_token(0.02);
+        let rendered = registry.render();
+        assert!(rendered.contains("ai_server_time_to_first_token_seconds_bucket{le=\"0.025\"} 1"));
+        assert!(rendered.contains("ai_server_time_to_first_token_seconds_bucket{le=\"0.005\"} 0"));
+        assert!(rendered.contains("ai_server_time_to_first_token_seconds_count 1"));
+    }
+
+    #[test]
+    fn model_warmup_observations_land_in_their_own_histogram() {
+        let registry = Registry::new();
+        registry.observe_model_warmup(2.0);
+        let rendered = registry.render();
+        assert!(rendered.contains("ai_server_model_warmup_seconds_bucket{le=\"5\"} 1"));
+        assert!(rendered.contains("ai_server_model_warmup_seconds_count 1"));
+    }
+
+    #[test]
+    fn kv_cache_occupancy_round_trips_through_permille_storage() {
+        let registry = Registry::new();
+        registry.set_kv_cache_occupancy(0.42);
+        assert!(registry.render().contains("ai_server_kv_cache_occupancy_ratio 0.42"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_vm_hwm_converts_kibibytes_to_bytes() {
+        let status = "Name:\tai-server\nVmHWM:\t   2048 kB\nThreads:\t4\n";
+        assert_eq!(parse_vm_hwm(status), Some(2048 * 1024));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_vm_hwm_returns_none_when_field_is_absent() {
+        assert_eq!(parse_vm_hwm("Name:\tai-server\nThreads:\t4\n"), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! `mmap`-based weight loading: maps a model file into the process's address
+//! space instead of reading it into a fresh `Vec<u8>`, so the kernel's page
+//! cache backs the data directly and peak memory doesn't temporarily double
+//! the way a full buffered read into a new allocation would for a 30GB
+//! model. Falls back to a plain buffered read when `mmap` itself fails —
+//! some network and FUSE filesystems refuse it outright, or perform badly
+//! enough under random tensor access that a sequential read wins anyway.
+//!
+//! Wiring an actual tensor's byte range out of a [`WeightSource`] needs a
+//! per-`ggml_type` block-size table `gguf.rs` doesn't have yet (it only
+//! parses the tensor table today, deliberately not tensor data — see its
+//! doc comment); that's the next step once a real inference backend exists
+//! to consume the bytes. This module only owns getting the file's bytes
+//! into memory the right way.
+
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::Path;
+
+/// How to bring a weight file's bytes into memory.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    /// `mlock` the mapping once it's in place, so the kernel never pages it
+    /// back out under memory pressure — appropriate once a model is
+    /// resident and expected to stay that way for the process's lifetime.
+    pub lock: bool,
+    /// Issue `madvise(MADV_WILLNEED)` over the mapping right after mapping
+    /// it, hinting the kernel to start readahead immediately instead of
+    /// faulting pages in one at a time on first touch.
+    pub prefetch: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions { lock: false, prefetch: true }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(String),
+    Mmap(String),
+}
+
+/// A model file's bytes, either `mmap`ed directly or read into a plain
+/// buffer — callers only need `Deref<Target = [u8]>` and don't care which
+/// path was actually taken.
+pub enum WeightSource {
+    Mapped(MappedFile),
+    Buffered(Vec<u8>),
+}
+
+impl Deref for WeightSource {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            WeightSource::Mapped(mapped) => mapped.as_slice(),
+            WeightSource::Buffered(bytes) => bytes,
+        }
+    }
+}
+
+/// Opens `path`, preferring `mmap` and falling back to a buffered read when
+/// `mmap` itself fails. `on_progress(bytes_so_far, total_bytes)` is called
+/// once per prefetch chunk (or once at completion for a buffered read or a
+/// mapping with prefetch disabled), the same shape `model_pool.rs`'s warmup
+/// hook uses to report progress on something the caller has no other way
+/// to observe.
+pub fn open_weights(
+    path: &Path,
+    options: LoadOptions,
+    on_progress: impl Fn(u64, u64),
+) -> Result<WeightSource, LoadError> {
+    match MappedFile::open(path, options, &on_progress) {
+        Ok(mapped) => Ok(WeightSource::Mapped(mapped)),
+        Err(_) => read_buffered(path, &on_progress).map(WeightSource::Buffered),
+    }
+}
+
+/// Chunk size for both prefetch `madvise` calls and buffered reads —
+/// large enough to keep syscall overhead negligible relative to a
+/// multi-gigabyte model, small enough that `on_progress` still reports
+/// meaningfully often.
+const CHUNK_BYTES: usize = 256 * 1024 * 1024;
+
+fn read_buffered(path: &Path, on_progress: &dyn Fn(u64, u64)) -> Result<Vec<u8>, LoadError> {
+    let mut file = File::open(path).map_err(|e| LoadError::Io(e.to_string()))?;
+    let len = file.metadata().map_err(|e| LoadError::Io(e.to_string()))?.len() as usize;
+    let mut buf = Vec::with_capacity(len);
+    let mut chunk = vec![0u8; CHUNK_BYTES.min(len.max(1))];
+    let mut read_total = 0usize;
+    loop {
+        let n = file.read(&mut chunk).map_err(|e| LoadError::Io(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        read_total += n;
+        on_progress(read_total as u64, len as u64);
+    }
+    if read_total == 0 {
+        on_progress(0, len as u64);
+    }
+    Ok(buf)
+}
+
+pub use imp::MappedFile;
+
+#[cfg(unix)]
+mod imp {
+    use super::{LoadError, LoadOptions, Path};
+    use std::fs::File;
+    use std::os::raw::{c_int, c_void};
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 0x02;
+    const MADV_WILLNEED: c_int = 3;
+
+    extern "C" {
+        fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+        fn madvise(addr: *mut c_void, len: usize, advice: c_int) -> c_int;
+        fn mlock(addr: *const c_void, len: usize) -> c_int;
+        fn munlock(addr: *const c_void, len: usize) -> c_int;
+    }
+
+    /// A read-only `mmap` of a file, held for the lifetime of this value —
+    /// `Drop` un-maps it (and `munlock`s it first if it was locked).
+    pub struct MappedFile {
+        ptr: *mut u8,
+        len: usize,
+        locked: bool,
+    }
+
+    // The mapping is read-only and outlives no thread-local state; sharing
+    // `&MappedFile` (or moving it) across threads is as safe as sharing a
+    // `&[u8]` slice into it would be.
+    unsafe impl Send for MappedFile {}
+    unsafe impl Sync for MappedFile {}
+
+    impl MappedFile {
+        pub fn open(path: &Path, options: LoadOptions, on_progress: &dyn Fn(u64, u64)) -> Result<Self, LoadError> {
+            let file = File::open(path).map_err(|e| LoadError::Io(e.to_string()))?;
+            let len = file.metadata().map_err(|e| LoadError::Io(e.to_string()))?.len() as usize;
+
+            if len == 0 {
+                on_progress(0, 0);
+                return Ok(MappedFile { ptr: std::ptr::NonNull::dangling().as_ptr(), len: 0, locked: false });
+            }
+
+            let ptr = unsafe { mmap(std::ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+            if ptr as isize == -1 {
+                return Err(LoadError::Mmap(format!("mmap failed for {}", path.display())));
+            }
+            let ptr = ptr as *mut u8;
+
+            if options.prefetch {
+                let mut offset = 0;
+                while offset < len {
+                    let chunk_len = super::CHUNK_BYTES.min(len - offset);
+                    unsafe {
+                        madvise(ptr.add(offset) as *mut c_void, chunk_len, MADV_WILLNEED);
+                    }
+                    offset += chunk_len;
+                    on_progress(offset as u64, len as u64);
+                }
+            } else {
+                on_progress(len as u64, len as u64);
+            }
+
+            let locked = options.lock && unsafe { mlock(ptr as *const c_void, len) == 0 };
+            Ok(MappedFile { ptr, len, locked })
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            if self.len == 0 {
+                &[]
+            } else {
+                unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+            }
+        }
+    }
+
+    impl Drop for MappedFile {
+        fn drop(&mut self) {
+            if self.len == 0 {
+                return;
+            }
+            if self.locked {
+                unsafe {
+                    munlock(self.ptr as *const c_void, self.len);
+                }
+            }
+            unsafe {
+                munmap(self.ptr as *mut c_void, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use super::{LoadError, LoadOptions, Path};
+
+    /// No `mmap` on this platform (not a target this tree deploys to
+    /// today); `open` always fails so [`super::open_weights`] falls back
+    /// to a buffered read.
+    pub struct MappedFile;
+
+    impl MappedFile {
+        pub fn open(_path: &Path, _options: LoadOptions, _on_progress: &dyn Fn(u64, u64)) -> Result<Self, LoadError> {
+            Err(LoadError::Mmap("mmap is not supported on this platform".to_string()))
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            &[]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ai-server-mmap-loader-test-{name}-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn open_weights_maps_a_files_full_contents() {
+        let contents = vec![7u8; 1024];
+        let path = temp_file("full-contents", &contents);
+        let source = open_weights(&path, LoadOptions::default(), |_, _| {}).unwrap();
+        assert_eq!(&*source, contents.as_slice());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_weights_reports_progress_up_to_the_total_length() {
+        let contents = vec![1u8; 4096];
+        let path = temp_file("progress", &contents);
+        let last = std::cell::Cell::new((0u64, 0u64));
+        let source = open_weights(&path, LoadOptions::default(), |done, total| last.set((done, total))).unwrap();
+        assert_eq!(last.get(), (4096, 4096));
+        assert_eq!(source.len(), 4096);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_weights_handles_an_empty_file() {
+        let path = temp_file("empty", &[]);
+        let source = open_weights(&path, LoadOptions::default(), |_, _| {}).unwrap();
+        assert_eq!(source.len(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_weights_falls_back_to_buffered_reads_for_a_missing_file() {
+        let path = std::env::temp_dir().join("ai-server-mmap-loader-test-does-not-exist");
+        let _ = std::fs::remove_file(&path);
+        assert!(open_weights(&path, LoadOptions::default(), |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn read_buffered_matches_mmap_for_the_same_file() {
+        let contents: Vec<u8> = (0..2048).map(|i| (i % 256) as u8).collect();
+        let path = temp_file("buffered", &contents);
+        let buffered = read_buffered(&path, &|_, _| {}).unwrap();
+        assert_eq!(buffered, contents);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn load_options_default_enables_prefetch_and_disables_lock() {
+        let options = LoadOptions::default();
+        assert!(options.prefetch);
+        assert!(!options.lock);
+    }
+}
@@ -0,0 +1,170 @@
+//! Scripted [`InferenceBackend`] for exercising the HTTP API, scheduler,
+//! and streaming layers without a model file: [`MockBackend`] answers with
+//! canned text, an artificial delay, or a simulated crash, all keyed by
+//! prompt and chosen entirely by the caller building it — the same role
+//! [`crate::replay_backend::ReplayBackend`] plays for a *recorded* session,
+//! except here the responses are hand-written instead of captured from a
+//! real backend, so a test doesn't need a prior recording pass at all.
+//!
+//! Failure injection panics rather than returning an error string, because
+//! [`InferenceBackend::generate`]/[`InferenceBackend::stream`] have no
+//! `Result` to report one through — this is the same contract
+//! `model_pool.rs`'s own `PanicOnGenerateBackend` test double already
+//! relies on, and `ModelPool::with_warmup` already treats a panicking
+//! backend as a failed load rather than an unhandled abort, so a caller
+//! testing "what happens when a model crashes" gets real coverage of that
+//! path instead of a shape no real backend can produce.
+
+use crate::InferenceBackend;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+/// Builds a [`MockBackend`] one canned prompt/response/failure at a time.
+/// Mirrors `ModelPool`'s own `with_*`-consuming-`self` builder shape.
+pub struct MockBackend {
+    id: String,
+    responses: HashMap<String, String>,
+    default_response: String,
+    latency: Duration,
+    failures: HashMap<String, String>,
+}
+
+impl MockBackend {
+    /// A backend that echoes nothing back but `default_response` for every
+    /// prompt, with no delay and no injected failures — the starting point
+    /// every `with_*` call narrows.
+    pub fn new(id: &str, default_response: impl Into<String>) -> Self {
+        MockBackend {
+            id: id.to_string(),
+            responses: HashMap::new(),
+            default_response: default_response.into(),
+            latency: Duration::ZERO,
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Answers `prompt` with exactly `response` instead of the default.
+    pub fn with_response(mut self, prompt: impl Into<String>, response: impl Into<String>) -> Self {
+        self.responses.insert(prompt.into(), response.into());
+        self
+    }
+
+    /// Sleeps `delay` before returning from every `generate`/`stream` call,
+    /// regardless of prompt — a fixed knob rather than a per-prompt one
+    /// since simulating scheduler backpressure only needs "slow", not
+    /// "slow for this specific prompt".
+    pub fn with_latency(mut self, delay: Duration) -> Self {
+        self.latency = delay;
+        self
+    }
+
+    /// Makes `generate`/`stream` panic with `message` when called with
+    /// `prompt`, simulating a backend crash for that specific input.
+    pub fn with_failure(mut self, prompt: impl Into<String>, message: impl Into<String>) -> Self {
+        self.failures.insert(prompt.into(), message.into());
+        self
+    }
+
+    fn response_for(&self, prompt: &str) -> &str {
+        self.responses.get(prompt).map(String::as_str).unwrap_or(&self.default_response)
+    }
+}
+
+impl InferenceBackend for MockBackend {
+    fn model_id(&self) -> &str {
+        &self.id
+    }
+
+    fn generate(&self, prompt: &str) -> String {
+        if !self.latency.is_zero() {
+            thread::sleep(self.latency);
+        }
+        if let Some(message) = self.failures.get(prompt) {
+            panic!("{message}");
+        }
+        self.response_for(prompt).to_string()
+    }
+
+    fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+        if !self.latency.is_zero() {
+            thread::sleep(self.latency);
+        }
+        if let Some(message) = self.failures.get(prompt) {
+            panic!("{message}");
+        }
+        for word in self.response_for(prompt).split_whitespace() {
+            if !on_token(&format!("{word} ")) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_returns_the_default_response_for_an_unscripted_prompt() {
+        let backend = MockBackend::new("m", "default answer");
+        assert_eq!(backend.generate("anything"), "default answer");
+    }
+
+    #[test]
+    fn generate_returns_the_scripted_response_for_a_matching_prompt() {
+        let backend = MockBackend::new("m", "default answer").with_response("hi", "hello there");
+        assert_eq!(backend.generate("hi"), "hello there");
+        assert_eq!(backend.generate("bye"), "default answer");
+    }
+
+    #[test]
+    fn stream_splits_the_scripted_response_into_word_chunks() {
+        let backend = MockBackend::new("m", "unused").with_response("hi", "one two three");
+        let mut chunks = Vec::new();
+        backend.stream("hi", &mut |t| {
+            chunks.push(t.to_string());
+            true
+        });
+        assert_eq!(chunks, vec!["one ", "two ", "three "]);
+    }
+
+    #[test]
+    fn stream_stops_as_soon_as_on_token_returns_false() {
+        let backend = MockBackend::new("m", "one two three");
+        let mut chunks = Vec::new();
+        backend.stream("hi", &mut |t| {
+            chunks.push(t.to_string());
+            chunks.len() < 2
+        });
+        assert_eq!(chunks, vec!["one ", "two "]);
+    }
+
+    #[test]
+    fn with_latency_delays_before_returning() {
+        let backend = MockBackend::new("m", "ok").with_latency(Duration::from_millis(20));
+        let started = std::time::Instant::now();
+        backend.generate("hi");
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    #[should_panic(expected = "simulated crash")]
+    fn generate_panics_on_a_prompt_configured_to_fail() {
+        let backend = MockBackend::new("m", "ok").with_failure("boom", "simulated crash");
+        backend.generate("boom");
+    }
+
+    #[test]
+    #[should_panic(expected = "simulated crash")]
+    fn stream_panics_on_a_prompt_configured_to_fail() {
+        let backend = MockBackend::new("m", "ok").with_failure("boom", "simulated crash");
+        backend.stream("boom", &mut |_| true);
+    }
+
+    #[test]
+    fn model_id_returns_the_configured_id() {
+        let backend = MockBackend::new("mock-1", "ok");
+        assert_eq!(backend.model_id(), "mock-1");
+    }
+}
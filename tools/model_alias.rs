@@ -0,0 +1,149 @@
+//! Named model aliases that fan a request's `"model"` field out across one
+//! or more real models — a percentage-weighted split for comparing a
+//! candidate quantization or fine-tune against production traffic, plus an
+//! optional shadow target whose response is generated and timed but never
+//! returned to the caller. Configured once at startup via
+//! `[routing]`'s `model_aliases` (see `config.rs`'s doc comment for the
+//! `alias:model1=70|model2=30|shadow=model3` syntax) rather than through an
+//! admin endpoint, since — unlike `lora::AdapterRegistry` or
+//! `prompt_templates::TemplateRegistry` — changing which models an alias
+//! points at mid-flight is exactly the kind of surprise an operator
+//! comparing models in production wants to avoid.
+//!
+//! [`AliasRegistry::resolve`] takes its random pick as a plain `u64` seed
+//! rather than reaching for a `rand`-crate `Rng`, hashed the same way
+//! `vectorstore.rs`'s `random_layer_count` turns a `VectorId` into
+//! pseudo-randomness without one.
+
+use std::collections::HashMap;
+
+/// One real model an alias can route to, with its share of `resolve`'s
+/// weighted pick — not a probability, just relative weight against the
+/// alias's other targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasTarget {
+    pub model_id: String,
+    pub weight: u32,
+}
+
+/// One alias's full configuration: the weighted split `resolve` picks
+/// from, and an optional shadow model mirrored on every request to that
+/// alias regardless of which target was picked.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AliasConfig {
+    pub targets: Vec<AliasTarget>,
+    pub shadow: Option<String>,
+}
+
+/// Read-only lookup table built once at startup from `config.rs`'s
+/// `model_aliases`. There's no `register`/mutation entry point — see this
+/// module's doc comment for why.
+pub struct AliasRegistry {
+    aliases: HashMap<String, AliasConfig>,
+}
+
+impl AliasRegistry {
+    pub fn new(aliases: HashMap<String, AliasConfig>) -> Self {
+        AliasRegistry { aliases }
+    }
+
+    /// The real model id `seed` picks for `name`, weighted by each
+    /// target's `weight` — `None` when `name` isn't a known alias, or when
+    /// it's configured with no targets at all (a shadow-only alias would
+    /// have nothing to actually serve the caller). `seed` is hashed with
+    /// the same golden-ratio multiply `vectorstore.rs` uses so callers
+    /// don't need to pre-mix their own randomness.
+    pub fn resolve(&self, name: &str, seed: u64) -> Option<&str> {
+        let config = self.aliases.get(name)?;
+        let total: u64 = config.targets.iter().map(|t| t.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let hash = seed.wrapping_mul(0x9E3779B97F4A7C15);
+        let mut threshold = hash % total;
+        for target in &config.targets {
+            if threshold < target.weight as u64 {
+                return Some(&target.model_id);
+            }
+            threshold -= target.weight as u64;
+        }
+        unreachable!("threshold is always less than the sum of weights it was reduced modulo")
+    }
+
+    /// The shadow model mirrored on every request to `name`, if configured.
+    pub fn shadow_for(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name)?.shadow.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> AliasRegistry {
+        let mut aliases = HashMap::new();
+        aliases.insert(
+            "prod".to_string(),
+            AliasConfig {
+                targets: vec![
+                    AliasTarget { model_id: "stable".to_string(), weight: 70 },
+                    AliasTarget { model_id: "candidate".to_string(), weight: 30 },
+                ],
+                shadow: Some("shadow-model".to_string()),
+            },
+        );
+        aliases.insert("shadow-only".to_string(), AliasConfig { targets: vec![], shadow: Some("shadow-model".to_string()) });
+        AliasRegistry::new(aliases)
+    }
+
+    #[test]
+    fn resolve_returns_none_for_an_unknown_alias() {
+        assert_eq!(registry().resolve("missing", 0), None);
+    }
+
+    #[test]
+    fn resolve_returns_none_for_an_alias_with_no_targets() {
+        assert_eq!(registry().resolve("shadow-only", 0), None);
+    }
+
+    #[test]
+    fn resolve_stays_within_the_configured_targets_across_many_seeds() {
+        let registry = registry();
+        for seed in 0..200u64 {
+            let model = registry.resolve("prod", seed).unwrap();
+            assert!(model == "stable" || model == "candidate");
+        }
+    }
+
+    #[test]
+    fn resolve_covers_both_targets_across_many_seeds() {
+        let registry = registry();
+        let mut saw_stable = false;
+        let mut saw_candidate = false;
+        for seed in 0..200u64 {
+            match registry.resolve("prod", seed).unwrap() {
+                "stable" => saw_stable = true,
+                "candidate" => saw_candidate = true,
+                other => panic!("unexpected target {other:?}"),
+            }
+        }
+        assert!(saw_stable && saw_candidate);
+    }
+
+    #[test]
+    fn shadow_for_returns_the_configured_shadow_model() {
+        assert_eq!(registry().shadow_for("prod"), Some("shadow-model"));
+    }
+
+    #[test]
+    fn shadow_for_returns_none_when_unconfigured() {
+        let mut aliases = HashMap::new();
+        aliases.insert("no-shadow".to_string(), AliasConfig { targets: vec![AliasTarget { model_id: "m".to_string(), weight: 1 }], shadow: None });
+        assert_eq!(AliasRegistry::new(aliases).shadow_for("no-shadow"), None);
+    }
+
+    #[test]
+    fn shadow_for_returns_none_for_an_unknown_alias() {
+        assert_eq!(registry().shadow_for("missing"), None);
+    }
+}
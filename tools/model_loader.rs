@@ -0,0 +1,25 @@
+//! Common interface for the model weight-file formats this tree can parse
+//! the structure of: tensor names, shapes, and declared dtypes. Neither
+//! implementation ([`gguf::GgufModel`] nor [`safetensors::SafetensorsModel`])
+//! holds tensor data itself — see `gguf.rs`'s doc comment for why that's a
+//! separate concern (`mmap_loader.rs` owns getting bytes into memory once a
+//! real backend needs them). This trait exists so callers that only care
+//! about a checkpoint's shape — `quantize.rs`'s sizing report, a future
+//! shape-validation step before a backend loads a model — don't need to
+//! match on which file format they were handed.
+
+pub trait ModelLoader {
+    /// Every tensor's name, in the order the format's own index reports
+    /// them (declaration order for GGUF's tensor table, key order from a
+    /// safetensors header/index).
+    fn tensor_names(&self) -> Vec<&str>;
+
+    /// A tensor's declared shape, or `None` if `name` isn't in this
+    /// checkpoint.
+    fn tensor_shape(&self, name: &str) -> Option<&[u64]>;
+
+    /// A tensor's declared dtype, in whatever string form the underlying
+    /// format uses (safetensors' `"F32"`/`"F16"`/`"BF16"`/... or a
+    /// stringified `ggml_type` for GGUF).
+    fn tensor_dtype(&self, name: &str) -> Option<&str>;
+}
@@ -0,0 +1,396 @@
+//! Multi-model hosting: lazily builds a backend the first time a request
+//! names it, and drops idle ones after a configurable timeout so a Mac
+//! with several GGUF files pulled doesn't need to keep every one of them
+//! resident just to serve any one of them.
+//!
+//! There's no real per-architecture backend yet (see `EchoBackend`'s doc
+//! comment above), so `factory` here just proves the lazy-load/idle-unload
+//! lifecycle by handing out one `EchoBackend` per registry-known id — a
+//! real GGUF-loading backend plugs in by swapping what `factory` returns,
+//! without this pool needing to change.
+//!
+//! Idle eviction only drops the pool's own `Arc`; a request that already
+//! checked out a backend keeps it alive until it finishes, it just stops
+//! being reachable by id until the next `get_or_load` reconstructs it.
+//!
+//! [`ModelPool::with_warmup`] optionally runs a few dummy generations
+//! against a model right after loading it — see its doc comment.
+//!
+//! [`ModelPool::with_event_hooks`] optionally reports load/evict as they
+//! happen, for `events::EventBus` subscribers on `/admin/events`.
+
+use crate::prefix_cache::PrefixCache;
+use crate::InferenceBackend;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct LoadedModel {
+    backend: Arc<dyn InferenceBackend>,
+    prefix_cache: PrefixCache,
+    last_used: Instant,
+}
+
+/// Dummy prefill/decode passes run against a freshly loaded model before
+/// it's handed to any caller, plus a hook to report how long they took (and
+/// whether the model survived them) — the caller's opportunity to record
+/// warmup timing in `metrics::Registry` or the startup log, the same shape
+/// `with_prefix_cache_hooks`'s `restore`/`persist` closures take.
+struct WarmupConfig {
+    runs: usize,
+    prompt: String,
+    on_result: Box<dyn Fn(&str, Duration, bool) + Send + Sync>,
+}
+
+pub struct ModelPool {
+    idle_timeout: Duration,
+    factory: Box<dyn Fn(&str) -> Option<Box<dyn InferenceBackend>> + Send + Sync>,
+    restore_prefix_cache: Option<Box<dyn Fn(&str) -> PrefixCache + Send + Sync>>,
+    persist_prefix_cache: Option<Box<dyn Fn(&str, &PrefixCache) + Send + Sync>>,
+    warmup: Option<WarmupConfig>,
+    on_load: Option<Box<dyn Fn(&str) + Send + Sync>>,
+    on_evict: Option<Box<dyn Fn(&str) + Send + Sync>>,
+    loaded: Mutex<HashMap<String, LoadedModel>>,
+}
+
+impl ModelPool {
+    /// `factory` returns `None` for a model id it doesn't recognize, which
+    /// `get_or_load` surfaces as a lookup miss rather than a panic — an
+    /// unknown `model` field in a request body is a 404, not a crash.
+    pub fn new(idle_timeout: Duration, factory: impl Fn(&str) -> Option<Box<dyn InferenceBackend>> + Send + Sync + 'static) -> Self {
+        ModelPool {
+            idle_timeout,
+            factory: Box::new(factory),
+            restore_prefix_cache: None,
+            persist_prefix_cache: None,
+            warmup: None,
+            on_load: None,
+            on_evict: None,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers hooks called with a model id right after it's loaded and
+    /// right after it's dropped (idle eviction or an admin-triggered
+    /// [`unload`](Self::unload)) — `server.rs` wires these to
+    /// `events::EventBus` so a dashboard sees "model loaded"/"model
+    /// evicted" without polling `loaded_model_ids`. Unlike
+    /// [`with_warmup`](Self::with_warmup)'s `on_result`, `on_load` fires
+    /// whether or not warmup is configured.
+    pub fn with_event_hooks(
+        mut self,
+        on_load: impl Fn(&str) + Send + Sync + 'static,
+        on_evict: impl Fn(&str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_load = Some(Box::new(on_load));
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    /// Registers hooks to persist a model's prefix cache just before it's
+    /// evicted for being idle, and restore it the next time that model is
+    /// loaded — so a model that cycles out overnight doesn't lose every
+    /// prefix it had warmed up. Neither hook is required; without them,
+    /// eviction just discards the cache.
+    pub fn with_prefix_cache_hooks(
+        mut self,
+        restore: impl Fn(&str) -> PrefixCache + Send + Sync + 'static,
+        persist: impl Fn(&str, &PrefixCache) + Send + Sync + 'static,
+    ) -> Self {
+        self.restore_prefix_cache = Some(Box::new(restore));
+        self.persist_prefix_cache = Some(Box::new(persist));
+        self
+    }
+
+    /// Runs `prompt` through a freshly loaded model's [`InferenceBackend::generate`]
+    /// `runs` times before [`get_or_load`](Self::get_or_load) returns it, so
+    /// JIT compilation and cache population happen at load time instead of
+    /// on a user's first real request. `on_result` is called once per load
+    /// with the model id, total warmup time, and whether every run
+    /// completed without panicking.
+    ///
+    /// A warmup run that panics fails the load entirely (`get_or_load`
+    /// returns `None`, same as an unrecognized model id) rather than
+    /// handing out a backend that's already proven it can't run — "fail
+    /// fast" only means something if a broken model is actually rejected.
+    pub fn with_warmup(
+        mut self,
+        runs: usize,
+        prompt: impl Into<String>,
+        on_result: impl Fn(&str, Duration, bool) + Send + Sync + 'static,
+    ) -> Self {
+        self.warmup = Some(WarmupConfig { runs, prompt: prompt.into(), on_result: Box::new(on_result) });
+        self
+    }
+
+    /// Runs the configured warmup passes (if any) against `backend`,
+    /// reporting the outcome via the `on_result` hook. Returns `false` if a
+    /// run panicked, so [`get_or_load`](Self::get_or_load) can refuse to
+    /// hand the model out.
+    fn run_warmup(&self, model_id: &str, backend: &Arc<dyn InferenceBackend>) -> bool {
+        let Some(warmup) = &self.warmup else { return true };
+        if warmup.runs == 0 {
+            return true;
+        }
+        let started = Instant::now();
+        let mut succeeded = true;
+        for _ in 0..warmup.runs {
+            let backend = backend.clone();
+            let prompt = warmup.prompt.clone();
+            if std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || backend.generate(&prompt))).is_err() {
+                succeeded = false;
+                break;
+            }
+        }
+        (warmup.on_result)(model_id, started.elapsed(), succeeded);
+        succeeded
+    }
+
+    /// Returns `model_id`'s backend, constructing and caching it via the
+    /// pool's factory on first use (or after eviction). Every call refreshes
+    /// `model_id`'s idle clock, including cache hits.
+    pub fn get_or_load(&self, model_id: &str) -> Option<Arc<dyn InferenceBackend>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(entry) = loaded.get_mut(model_id) {
+            entry.last_used = Instant::now();
+            return Some(entry.backend.clone());
+        }
+        let backend: Arc<dyn InferenceBackend> = Arc::from((self.factory)(model_id)?);
+        if !self.run_warmup(model_id, &backend) {
+            return None;
+        }
+        let prefix_cache = self.restore_prefix_cache.as_ref().map(|restore| restore(model_id)).unwrap_or_default();
+        loaded.insert(model_id.to_string(), LoadedModel { backend: backend.clone(), prefix_cache, last_used: Instant::now() });
+        if let Some(on_load) = &self.on_load {
+            on_load(model_id);
+        }
+        Some(backend)
+    }
+
+    /// Drops every model idle for at least `idle_timeout`, calling the
+    /// persist hook (if any) with its prefix cache first. Meant to run
+    /// periodically on a background thread (see [`spawn_idle_reaper`]).
+    pub fn evict_idle(&self) {
+        let mut loaded = self.loaded.lock().unwrap();
+        let expired: Vec<String> =
+            loaded.iter().filter(|(_, entry)| entry.last_used.elapsed() >= self.idle_timeout).map(|(id, _)| id.clone()).collect();
+        for id in expired {
+            if let Some(entry) = loaded.remove(&id) {
+                if let Some(persist) = &self.persist_prefix_cache {
+                    persist(&id, &entry.prefix_cache);
+                }
+                if let Some(on_evict) = &self.on_evict {
+                    on_evict(&id);
+                }
+            }
+        }
+    }
+
+    /// Ids currently resident, sorted for deterministic reporting (e.g. a
+    /// future `/v1/models` "loaded" flag).
+    pub fn loaded_model_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.loaded.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Drops `model_id` immediately regardless of its idle clock, calling
+    /// the persist hook first like [`evict_idle`](Self::evict_idle) does.
+    /// Returns `false` if it wasn't loaded — an admin-triggered unload of
+    /// a model nobody has requested yet, matching `evict_idle`'s "nothing
+    /// to do" case, not an error.
+    pub fn unload(&self, model_id: &str) -> bool {
+        let mut loaded = self.loaded.lock().unwrap();
+        let Some(entry) = loaded.remove(model_id) else { return false };
+        if let Some(persist) = &self.persist_prefix_cache {
+            persist(model_id, &entry.prefix_cache);
+        }
+        if let Some(on_evict) = &self.on_evict {
+            on_evict(model_id);
+        }
+        true
+    }
+
+    /// Clears every resident model's prefix cache in place without
+    /// unloading the models themselves — an admin-triggered flush after
+    /// something upstream of prefix matching changed (see `admin.rs`).
+    pub fn flush_prefix_caches(&self) {
+        for entry in self.loaded.lock().unwrap().values_mut() {
+            entry.prefix_cache.clear();
+        }
+    }
+}
+
+/// Spawns a background thread that calls `pool.evict_idle()` every
+/// `interval` — the same leaked-`'static`-reference-plus-thread shape as
+/// `config::watch`.
+pub fn spawn_idle_reaper(pool: &'static ModelPool, interval: Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        pool.evict_idle();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubBackend {
+        id: String,
+    }
+
+    impl InferenceBackend for StubBackend {
+        fn model_id(&self) -> &str {
+            &self.id
+        }
+
+        fn generate(&self, prompt: &str) -> String {
+            format!("stub({}): {prompt}", self.id)
+        }
+
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    fn known_ids_pool() -> ModelPool {
+        ModelPool::new(Duration::from_secs(60), |id| {
+            (id == "a" || id == "b").then(|| Box::new(StubBackend { id: id.to_string() }) as Box<dyn InferenceBackend>)
+        })
+    }
+
+    #[test]
+    fn get_or_load_returns_none_for_an_unrecognized_model() {
+        let pool = known_ids_pool();
+        assert!(pool.get_or_load("nope").is_none());
+    }
+
+    #[test]
+    fn get_or_load_reuses_the_same_backend_on_repeated_calls() {
+        let pool = known_ids_pool();
+        let first = pool.get_or_load("a").unwrap();
+        let second = pool.get_or_load("a").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn evict_idle_drops_models_past_the_timeout() {
+        let pool = ModelPool::new(Duration::from_millis(0), |id| {
+            Some(Box::new(StubBackend { id: id.to_string() }) as Box<dyn InferenceBackend>)
+        });
+        pool.get_or_load("a").unwrap();
+        assert_eq!(pool.loaded_model_ids(), vec!["a".to_string()]);
+        std::thread::sleep(Duration::from_millis(5));
+        pool.evict_idle();
+        assert!(pool.loaded_model_ids().is_empty());
+    }
+
+    #[test]
+    fn evict_idle_calls_the_persist_hook_with_that_models_prefix_cache() {
+        let persisted: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let persisted_clone = persisted.clone();
+        let pool = ModelPool::new(Duration::from_millis(0), |id| {
+            Some(Box::new(StubBackend { id: id.to_string() }) as Box<dyn InferenceBackend>)
+        })
+        .with_prefix_cache_hooks(|_id| PrefixCache::new(), move |id, _cache| persisted_clone.lock().unwrap().push(id.to_string()));
+        pool.get_or_load("a").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        pool.evict_idle();
+        assert_eq!(persisted.lock().unwrap().as_slice(), ["a".to_string()]);
+    }
+
+    #[test]
+    fn get_or_load_refreshes_the_idle_clock_on_cache_hits() {
+        let pool = ModelPool::new(Duration::from_millis(20), |id| {
+            Some(Box::new(StubBackend { id: id.to_string() }) as Box<dyn InferenceBackend>)
+        });
+        pool.get_or_load("a").unwrap();
+        std::thread::sleep(Duration::from_millis(12));
+        pool.get_or_load("a").unwrap(); // refreshes last_used before it would expire
+        std::thread::sleep(Duration::from_millis(12));
+        pool.evict_idle();
+        assert_eq!(pool.loaded_model_ids(), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn unload_drops_a_loaded_model_and_returns_true() {
+        let pool = known_ids_pool();
+        pool.get_or_load("a").unwrap();
+        assert!(pool.unload("a"));
+        assert!(pool.loaded_model_ids().is_empty());
+    }
+
+    #[test]
+    fn unload_returns_false_for_a_model_that_was_never_loaded() {
+        let pool = known_ids_pool();
+        assert!(!pool.unload("a"));
+    }
+
+    struct PanicOnGenerateBackend {
+        id: String,
+    }
+
+    impl InferenceBackend for PanicOnGenerateBackend {
+        fn model_id(&self) -> &str {
+            &self.id
+        }
+
+        fn generate(&self, _prompt: &str) -> String {
+            panic!("model cannot run");
+        }
+
+        fn stream(&self, _prompt: &str, _on_token: &mut dyn FnMut(&str) -> bool) {}
+    }
+
+    #[test]
+    fn get_or_load_runs_warmup_and_reports_success() {
+        let reports: Arc<Mutex<Vec<(String, bool)>>> = Arc::new(Mutex::new(Vec::new()));
+        let reports_clone = reports.clone();
+        let pool = known_ids_pool().with_warmup(2, "hi", move |id, _elapsed, ok| {
+            reports_clone.lock().unwrap().push((id.to_string(), ok));
+        });
+        assert!(pool.get_or_load("a").is_some());
+        assert_eq!(reports.lock().unwrap().as_slice(), [("a".to_string(), true)]);
+    }
+
+    #[test]
+    fn get_or_load_fails_fast_when_a_warmup_run_panics() {
+        let reports: Arc<Mutex<Vec<(String, bool)>>> = Arc::new(Mutex::new(Vec::new()));
+        let reports_clone = reports.clone();
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {})); // silence the expected warmup panic's default stderr output
+        let pool = ModelPool::new(Duration::from_secs(60), |id| {
+            Some(Box::new(PanicOnGenerateBackend { id: id.to_string() }) as Box<dyn InferenceBackend>)
+        })
+        .with_warmup(1, "hi", move |id, _elapsed, ok| {
+            reports_clone.lock().unwrap().push((id.to_string(), ok));
+        });
+        let result = pool.get_or_load("a");
+        std::panic::set_hook(hook);
+        assert!(result.is_none());
+        assert_eq!(reports.lock().unwrap().as_slice(), [("a".to_string(), false)]);
+        assert!(pool.loaded_model_ids().is_empty());
+    }
+
+    #[test]
+    fn get_or_load_skips_warmup_entirely_when_runs_is_zero() {
+        let reports: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let reports_clone = reports.clone();
+        let pool = known_ids_pool().with_warmup(0, "hi", move |id, _elapsed, _ok| {
+            reports_clone.lock().unwrap().push(id.to_string());
+        });
+        assert!(pool.get_or_load("a").is_some());
+        assert!(reports.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn flush_prefix_caches_clears_every_loaded_models_cache() {
+        let pool = known_ids_pool();
+        let backend = pool.get_or_load("a").unwrap();
+        let prompt: Vec<u32> = "hello".bytes().map(u32::from).collect();
+        let _ = backend.generate("hello");
+        pool.loaded.lock().unwrap().get_mut("a").unwrap().prefix_cache.record(1, prompt.clone());
+        pool.flush_prefix_caches();
+        assert!(pool.loaded.lock().unwrap().get("a").unwrap().prefix_cache.find_longest_match(&prompt).is_none());
+    }
+}
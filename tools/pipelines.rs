@@ -0,0 +1,516 @@
+//! Workflow pipelines: named DAGs of steps — `llm`, `embedding`,
+//! `retrieval`, `template`, `http` — defined in a restricted YAML subset
+//! and executed in dependency order, so callers stop hand-wiring the same
+//! "retrieve, then prompt, then call out" glue themselves for every
+//! request. `server.rs` exposes a loaded pipeline as `POST
+//! /v1/pipelines/{name}/run`, streaming one JSON record per completed step
+//! over the same [`http::SseWriter`] `handle_chat_completions` and
+//! `agent::run` use for their own step-by-step output.
+//!
+//! The YAML subset is deliberately as small as `config.rs`'s TOML subset:
+//! a top-level `name:` scalar and a `steps:` list, each step a flat set of
+//! `key: value` lines (an inline `[a, b]` list for `depends_on`) — no
+//! nested mappings, anchors, multi-document streams, or block scalars.
+//! [`parse_pipeline`] rejects anything outside that shape rather than
+//! guessing at it.
+//!
+//! [`PipelineRegistry::reload`] rescans a directory for `*.yaml`/`*.yml`
+//! files the same `fs::read_dir` + extension filter `plugins::PluginRegistry::reload`
+//! uses for `.wasm` modules, each file's stem becoming its pipeline name.
+//! A file that fails to parse is skipped rather than failing the whole
+//! reload — the same "one bad file doesn't take down what already
+//! works" posture `plugins.rs`'s ABI failure handling takes.
+//!
+//! [`validate_graph`] topologically sorts a pipeline's steps (Kahn's
+//! algorithm) before [`run`] executes them, catching an unknown
+//! `depends_on` target or a dependency cycle up front instead of hanging
+//! or panicking partway through a run. Each `llm` step's rendered prompt
+//! is cached through the same `response_cache::ResponseCache` singleton
+//! `/v1/completions` uses, keyed by step id plus rendered prompt, rather
+//! than a pipeline-specific cache the config would have to grow a second
+//! set of TTL/eviction knobs for.
+
+use crate::embeddings::{embed_batch, EmbeddingBackend, EmbeddingRequest};
+use crate::json::{Json, ObjectBuilder};
+use crate::rag;
+use crate::response_cache::{self, ResponseCache};
+use crate::vectorstore::VectorStore;
+use crate::InferenceBackend;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepKind {
+    Llm { prompt: String },
+    Embedding { input: String },
+    Retrieval { collection: String, query: String, top_k: usize },
+    Template { template: String },
+    Http { host: String, path: String, method: String, body: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub id: String,
+    pub kind: StepKind,
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PipelineError {
+    Parse(String),
+    UnknownDependency { step: String, depends_on: String },
+    Cycle(String),
+}
+
+impl PipelineError {
+    pub fn message(&self) -> String {
+        match self {
+            PipelineError::Parse(m) => m.clone(),
+            PipelineError::UnknownDependency { step, depends_on } => format!("step \"{step}\" depends on unknown step \"{depends_on}\""),
+            PipelineError::Cycle(step) => format!("dependency cycle involving step \"{step}\""),
+        }
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    let Some(inner) = value.trim().strip_prefix('[').and_then(|v| v.strip_suffix(']')) else { return Vec::new() };
+    inner.split(',').map(str::trim).filter(|s| !s.is_empty()).map(unquote).collect()
+}
+
+#[derive(Default)]
+struct RawStep {
+    id: Option<String>,
+    kind: Option<String>,
+    depends_on: Vec<String>,
+    fields: BTreeMap<String, String>,
+}
+
+fn apply_field(step: &mut RawStep, field: &str) -> Result<(), PipelineError> {
+    let Some((key, value)) = field.split_once(':') else {
+        return Err(PipelineError::Parse(format!("expected \"key: value\", got \"{field}\"")));
+    };
+    let key = key.trim();
+    let value = value.trim();
+    match key {
+        "id" => step.id = Some(unquote(value)),
+        "kind" => step.kind = Some(unquote(value)),
+        "depends_on" => step.depends_on = parse_list(value),
+        _ => {
+            step.fields.insert(key.to_string(), unquote(value));
+        }
+    }
+    Ok(())
+}
+
+fn require(fields: &BTreeMap<String, String>, key: &str) -> Result<String, PipelineError> {
+    fields.get(key).cloned().ok_or_else(|| PipelineError::Parse(format!("missing required field \"{key}\"")))
+}
+
+fn step_kind_from_fields(kind: &str, fields: &BTreeMap<String, String>) -> Result<StepKind, PipelineError> {
+    match kind {
+        "llm" => Ok(StepKind::Llm { prompt: require(fields, "prompt")? }),
+        "embedding" => Ok(StepKind::Embedding { input: require(fields, "input")? }),
+        "retrieval" => Ok(StepKind::Retrieval {
+            collection: require(fields, "collection")?,
+            query: require(fields, "query")?,
+            top_k: fields.get("top_k").and_then(|v| v.parse().ok()).unwrap_or(3),
+        }),
+        "template" => Ok(StepKind::Template { template: require(fields, "template")? }),
+        "http" => Ok(StepKind::Http {
+            host: require(fields, "host")?,
+            path: fields.get("path").cloned().unwrap_or_else(|| "/".to_string()),
+            method: fields.get("method").cloned().unwrap_or_else(|| "GET".to_string()),
+            body: fields.get("body").cloned().unwrap_or_default(),
+        }),
+        other => Err(PipelineError::Parse(format!("unknown step kind \"{other}\""))),
+    }
+}
+
+/// Parses the restricted YAML subset described in this module's doc
+/// comment into a [`Pipeline`]. Structural mistakes (a field outside
+/// `steps:`, a step with no `id`, an unrecognized `kind`) are reported as
+/// [`PipelineError::Parse`]; dependency shape is checked separately by
+/// [`validate_graph`].
+pub fn parse_pipeline(text: &str) -> Result<Pipeline, PipelineError> {
+    let mut name = None;
+    let mut raw_steps: Vec<RawStep> = Vec::new();
+    let mut in_steps = false;
+
+    for raw_line in text.lines() {
+        let line = raw_line.split('#').next().unwrap_or("");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+
+        if indent == 0 {
+            if let Some(value) = trimmed.strip_prefix("name:") {
+                name = Some(unquote(value));
+                in_steps = false;
+            } else if trimmed == "steps:" {
+                in_steps = true;
+            } else {
+                return Err(PipelineError::Parse(format!("unexpected top-level line: \"{trimmed}\"")));
+            }
+            continue;
+        }
+
+        if !in_steps {
+            return Err(PipelineError::Parse(format!("indented line outside \"steps:\": \"{trimmed}\"")));
+        }
+
+        if let Some(first_field) = trimmed.strip_prefix("- ") {
+            raw_steps.push(RawStep::default());
+            apply_field(raw_steps.last_mut().unwrap(), first_field)?;
+        } else {
+            let step = raw_steps.last_mut().ok_or_else(|| PipelineError::Parse(format!("step field before any \"- id: ...\": \"{trimmed}\"")))?;
+            apply_field(step, trimmed)?;
+        }
+    }
+
+    let name = name.ok_or_else(|| PipelineError::Parse("missing top-level \"name\"".to_string()))?;
+    let steps = raw_steps
+        .into_iter()
+        .map(|raw| {
+            let id = raw.id.ok_or_else(|| PipelineError::Parse("step missing \"id\"".to_string()))?;
+            let kind = raw.kind.ok_or_else(|| PipelineError::Parse(format!("step \"{id}\" missing \"kind\"")))?;
+            let kind = step_kind_from_fields(&kind, &raw.fields)?;
+            Ok(Step { id, kind, depends_on: raw.depends_on })
+        })
+        .collect::<Result<Vec<Step>, PipelineError>>()?;
+
+    Ok(Pipeline { name, steps })
+}
+
+/// Topologically sorts `pipeline`'s steps (Kahn's algorithm) and returns
+/// their execution order as indices into `pipeline.steps`, so a step never
+/// runs before every step it `depends_on`. Ties (steps with no remaining
+/// dependency) resolve in the order they appear in the file, so a
+/// pipeline with no branching runs top to bottom exactly as written.
+pub fn validate_graph(pipeline: &Pipeline) -> Result<Vec<usize>, PipelineError> {
+    let index_of: HashMap<&str, usize> = pipeline.steps.iter().enumerate().map(|(i, s)| (s.id.as_str(), i)).collect();
+
+    let mut indegree = vec![0usize; pipeline.steps.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); pipeline.steps.len()];
+    for (i, step) in pipeline.steps.iter().enumerate() {
+        for dep in &step.depends_on {
+            let &dep_index = index_of.get(dep.as_str()).ok_or_else(|| PipelineError::UnknownDependency { step: step.id.clone(), depends_on: dep.clone() })?;
+            dependents[dep_index].push(i);
+            indegree[i] += 1;
+        }
+    }
+
+    let mut queue: VecDeque<usize> = (0..pipeline.steps.len()).filter(|&i| indegree[i] == 0).collect();
+    let mut order = Vec::with_capacity(pipeline.steps.len());
+    while let Some(i) = queue.pop_front() {
+        order.push(i);
+        for &next in &dependents[i] {
+            indegree[next] -= 1;
+            if indegree[next] == 0 {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    if order.len() != pipeline.steps.len() {
+        let stuck = (0..pipeline.steps.len()).find(|&i| indegree[i] > 0).unwrap();
+        return Err(PipelineError::Cycle(pipeline.steps[stuck].id.clone()));
+    }
+    Ok(order)
+}
+
+/// Substitutes `{{input}}` with the run's input and `{{step_id}}` with
+/// that step's already-computed output — the same double-brace shape
+/// `guardrails.rs`'s classifier prompt template uses for its own
+/// substitution, kept flat (no filters, no nested lookups) to match this
+/// module's deliberately small YAML subset.
+fn render(template: &str, outputs: &HashMap<String, String>, input: &str) -> String {
+    let mut result = template.replace("{{input}}", input);
+    for (id, output) in outputs {
+        result = result.replace(&format!("{{{{{id}}}}}"), output);
+    }
+    result
+}
+
+/// POSTs (or GETs) `path` on `host`, the same plain `TcpStream` HTTP/1.1
+/// framing `router.rs`'s `probe`, `mcp::call`, and `agent::http_request`
+/// each speak toward a peer, returning the status line and body joined as
+/// plain text for a downstream step to consume.
+fn http_request(host: &str, method: &str, path: &str, body: &str, timeout: Duration) -> Result<String, String> {
+    let stream = TcpStream::connect(host).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(timeout)).ok();
+    stream.set_write_timeout(Some(timeout)).ok();
+    let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+    write!(
+        writer,
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).map_err(|e| e.to_string())?;
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut response_body = vec![0u8; content_length];
+    reader.read_exact(&mut response_body).map_err(|e| e.to_string())?;
+    Ok(format!("{}\n{}", status_line.trim_end(), String::from_utf8_lossy(&response_body)))
+}
+
+fn execute_step(step: &Step, backend: &dyn InferenceBackend, embedding_backend: &dyn EmbeddingBackend, store: &Mutex<VectorStore>, cache: &ResponseCache, input: &str, outputs: &HashMap<String, String>) -> String {
+    match &step.kind {
+        StepKind::Template { template } => render(template, outputs, input),
+        StepKind::Llm { prompt } => {
+            let rendered = render(prompt, outputs, input);
+            let key = response_cache::cache_key(&format!("pipeline-step:{}", step.id), &rendered);
+            if let Some(cached) = cache.get(&key) {
+                return cached;
+            }
+            let completion = backend.generate(&rendered);
+            cache.insert(key, completion.clone());
+            completion
+        }
+        StepKind::Embedding { input: field } => {
+            let rendered = render(field, outputs, input);
+            let tokens: Vec<u32> = rendered.bytes().map(u32::from).collect();
+            let vector = embed_batch(embedding_backend, std::slice::from_ref(&tokens), &EmbeddingRequest::default()).into_iter().next().unwrap_or_default();
+            Json::Array(vector.into_iter().map(|v| Json::Number(v as f64)).collect()).to_string()
+        }
+        StepKind::Retrieval { collection, query, top_k } => {
+            let rendered_query = render(query, outputs, input);
+            let store = store.lock().unwrap();
+            let Some(collection) = store.collection_ref(collection) else {
+                return String::new();
+            };
+            let (prompt, _sources) = rag::assemble_prompt(collection, embedding_backend, &rendered_query, *top_k, &EmbeddingRequest::default());
+            prompt
+        }
+        StepKind::Http { host, path, method, body } => {
+            let rendered_path = render(path, outputs, input);
+            let rendered_body = render(body, outputs, input);
+            http_request(host, method, &rendered_path, &rendered_body, Duration::from_secs(10)).unwrap_or_else(|e| format!("error: {e}"))
+        }
+    }
+}
+
+/// Runs `pipeline`'s steps in dependency order (see [`validate_graph`]),
+/// calling `on_step` once per completed step with `{"id", "output"}` — the
+/// same "one record per unit of work" shape `agent::run`'s `on_step`
+/// callback takes, so `server.rs` can stream both over the same
+/// [`http::SseWriter`]. A step's templated fields see every earlier step's
+/// output plus the run's own `input` (see [`render`]).
+pub fn run(pipeline: &Pipeline, backend: &dyn InferenceBackend, embedding_backend: &dyn EmbeddingBackend, store: &Mutex<VectorStore>, cache: &ResponseCache, input: &str, on_step: &mut dyn FnMut(&Json)) -> Result<(), PipelineError> {
+    let order = validate_graph(pipeline)?;
+    let mut outputs: HashMap<String, String> = HashMap::new();
+    for index in order {
+        let step = &pipeline.steps[index];
+        let output = execute_step(step, backend, embedding_backend, store, cache, input, &outputs);
+        on_step(&ObjectBuilder::new().set("id", Json::String(step.id.clone())).set("output", Json::String(output.clone())).build());
+        outputs.insert(step.id.clone(), output);
+    }
+    Ok(())
+}
+
+/// Pipelines found under a directory, each file's stem becoming its name.
+pub struct PipelineRegistry {
+    dir: PathBuf,
+    pipelines: Mutex<BTreeMap<String, Pipeline>>,
+}
+
+impl PipelineRegistry {
+    /// Creates `dir` if it doesn't exist yet and does an initial [`reload`](Self::reload).
+    pub fn open(dir: impl Into<PathBuf>) -> std::io::Result<PipelineRegistry> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let registry = PipelineRegistry { dir, pipelines: Mutex::new(BTreeMap::new()) };
+        registry.reload()?;
+        Ok(registry)
+    }
+
+    /// A registry with no pipelines loaded, without touching the
+    /// filesystem at all. `server.rs` uses this when `[pipelines]` isn't
+    /// enabled in config, the same "off means every check passes through
+    /// untouched" shape `plugins::PluginRegistry::disabled` gives callers.
+    pub fn disabled() -> PipelineRegistry {
+        PipelineRegistry { dir: PathBuf::new(), pipelines: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Rebuilds the pipeline map from whatever `*.yaml`/`*.yml` files
+    /// currently exist under `dir`. A file that fails to parse is skipped
+    /// rather than failing the whole reload, the same "don't let one bad
+    /// file break what already works" posture `plugins::PluginRegistry::run_all`
+    /// takes toward a plugin that can't be run.
+    pub fn reload(&self) -> std::io::Result<()> {
+        let mut pipelines = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("yaml") | Some("yml")) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let Ok(text) = std::fs::read_to_string(&path) else { continue };
+            let Ok(pipeline) = parse_pipeline(&text) else { continue };
+            pipelines.insert(id.to_string(), pipeline);
+        }
+        *self.pipelines.lock().unwrap() = pipelines;
+        Ok(())
+    }
+
+    /// The names of every currently loaded pipeline, sorted.
+    pub fn ids(&self) -> Vec<String> {
+        self.pipelines.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Pipeline> {
+        self.pipelines.lock().unwrap().get(name).cloned()
+    }
+}
+
+/// Calls [`PipelineRegistry::reload`] every `interval` in a background
+/// thread, the same polling shape `plugins::watch` and `mcp::watch` use
+/// for their own directory/server rescans.
+pub fn watch(registry: &'static PipelineRegistry, interval: Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let _ = registry.reload();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoBackend;
+    impl InferenceBackend for EchoBackend {
+        fn model_id(&self) -> &str {
+            "echo"
+        }
+        fn generate(&self, prompt: &str) -> String {
+            format!("echo: {prompt}")
+        }
+        fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+            on_token(&self.generate(prompt));
+        }
+    }
+
+    struct ZeroEmbeddingBackend;
+    impl EmbeddingBackend for ZeroEmbeddingBackend {
+        fn hidden_size(&self) -> usize {
+            4
+        }
+        fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+            tokens.iter().map(|_| vec![0.0; 4]).collect()
+        }
+    }
+
+    fn sample_yaml() -> &'static str {
+        "name: greet\nsteps:\n  - id: prep\n    kind: template\n    template: \"hello {{input}}\"\n  - id: answer\n    kind: llm\n    depends_on: [prep]\n    prompt: \"{{prep}}\"\n"
+    }
+
+    #[test]
+    fn parse_pipeline_reads_name_and_steps_in_order() {
+        let pipeline = parse_pipeline(sample_yaml()).unwrap();
+        assert_eq!(pipeline.name, "greet");
+        assert_eq!(pipeline.steps.len(), 2);
+        assert_eq!(pipeline.steps[0].id, "prep");
+        assert_eq!(pipeline.steps[1].depends_on, vec!["prep".to_string()]);
+        assert!(matches!(pipeline.steps[1].kind, StepKind::Llm { .. }));
+    }
+
+    #[test]
+    fn parse_pipeline_rejects_an_unknown_step_kind() {
+        let text = "name: bad\nsteps:\n  - id: a\n    kind: sorcery\n";
+        assert!(matches!(parse_pipeline(text), Err(PipelineError::Parse(_))));
+    }
+
+    #[test]
+    fn parse_pipeline_rejects_a_step_missing_a_required_field() {
+        let text = "name: bad\nsteps:\n  - id: a\n    kind: llm\n";
+        assert!(matches!(parse_pipeline(text), Err(PipelineError::Parse(_))));
+    }
+
+    #[test]
+    fn validate_graph_orders_dependencies_before_dependents() {
+        let pipeline = parse_pipeline(sample_yaml()).unwrap();
+        let order = validate_graph(&pipeline).unwrap();
+        assert_eq!(order, vec![0, 1]);
+    }
+
+    #[test]
+    fn validate_graph_rejects_a_dependency_on_an_unknown_step() {
+        let text = "name: bad\nsteps:\n  - id: a\n    kind: template\n    template: \"x\"\n    depends_on: [missing]\n";
+        let pipeline = parse_pipeline(text).unwrap();
+        assert!(matches!(validate_graph(&pipeline), Err(PipelineError::UnknownDependency { .. })));
+    }
+
+    #[test]
+    fn validate_graph_rejects_a_cycle() {
+        let text = "name: bad\nsteps:\n  - id: a\n    kind: template\n    template: \"x\"\n    depends_on: [b]\n  - id: b\n    kind: template\n    template: \"y\"\n    depends_on: [a]\n";
+        let pipeline = parse_pipeline(text).unwrap();
+        assert!(matches!(validate_graph(&pipeline), Err(PipelineError::Cycle(_))));
+    }
+
+    #[test]
+    fn run_streams_one_record_per_step_and_threads_outputs_forward() {
+        let pipeline = parse_pipeline(sample_yaml()).unwrap();
+        let backend = EchoBackend;
+        let embedding_backend = ZeroEmbeddingBackend;
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        let mut records = Vec::new();
+        run(&pipeline, &backend, &embedding_backend, &store, &cache, "world", &mut |step| records.push(step.clone())).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("output").and_then(Json::as_str), Some("hello world"));
+        assert_eq!(records[1].get("output").and_then(Json::as_str), Some("echo: hello world"));
+    }
+
+    #[test]
+    fn registry_reload_finds_yaml_files_and_skips_unparsable_ones() {
+        let dir = std::env::temp_dir().join(format!("ai-server-pipelines-test-{:x}", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greet.yaml"), sample_yaml()).unwrap();
+        std::fs::write(dir.join("broken.yaml"), "not: a\npipeline: at all\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignore me").unwrap();
+
+        let registry = PipelineRegistry::open(&dir).unwrap();
+        assert_eq!(registry.ids(), vec!["greet".to_string()]);
+        assert!(registry.get("greet").is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,197 @@
+//! Sandboxed request/response transform plugins: `.wasm` modules dropped
+//! into a plugins directory, each one run through the `wasmtime` CLI
+//! rather than a linked WASM engine — this tree has no external crate
+//! dependencies to embed one with (no `Cargo.toml` at all; see this
+//! repo's build notes), so [`PluginRegistry::run_all`] shells out the same
+//! "vendor binary over vendored SDK" way `registry.rs`'s `minisign` check
+//! and `vulkan.rs`/`cuda.rs`'s GPU detection already do.
+//!
+//! ABI: a plugin reads the text being transformed (a prompt before
+//! generation, a completion after) as UTF-8 from stdin and writes the
+//! transformed text as UTF-8 to stdout. A plugin that fails to start,
+//! exits non-zero, or writes non-UTF-8 output is treated as a no-op —
+//! its input passes through unchanged — rather than failing the request,
+//! the same "don't let this hook break generation" posture
+//! `guardrails.rs`'s classifier takes when its backend is unavailable.
+//!
+//! [`PluginRegistry::reload`] rescans the plugins directory for `.wasm`
+//! files, the same `fs::read_dir` + extension filter `registry::ModelRegistry::rescan`
+//! uses for GGUF files, so dropping a new plugin in (or deleting one)
+//! changes [`PluginRegistry::ids`] without a restart; [`watch`] polls it on
+//! an interval the same way `config::watch` polls a config file. An
+//! already-known plugin's edits take effect on its very next invocation
+//! with no reload needed at all, since [`PluginRegistry::run_all`] always
+//! shells out to whatever bytes are on disk at call time.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// One loaded plugin: an id (its file stem) mapped to its `.wasm` path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+/// Plugins found under a directory, run in id order by [`run_all`](Self::run_all).
+pub struct PluginRegistry {
+    dir: PathBuf,
+    wasmtime_path: String,
+    plugins: Mutex<BTreeMap<String, Plugin>>,
+}
+
+impl PluginRegistry {
+    /// Creates `dir` if it doesn't exist yet and does an initial [`reload`](Self::reload).
+    pub fn open(dir: impl Into<PathBuf>, wasmtime_path: impl Into<String>) -> std::io::Result<PluginRegistry> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let registry = PluginRegistry { dir, wasmtime_path: wasmtime_path.into(), plugins: Mutex::new(BTreeMap::new()) };
+        registry.reload()?;
+        Ok(registry)
+    }
+
+    /// A registry that never loads or runs anything, without touching the
+    /// filesystem at all. `server.rs` uses this when `[plugins]` isn't
+    /// enabled in config, the same "off means every check passes through
+    /// untouched" shape `guardrails::GuardrailsEngine::disabled` gives
+    /// callers so they never need to special-case "off".
+    pub fn disabled() -> PluginRegistry {
+        PluginRegistry { dir: PathBuf::new(), wasmtime_path: String::new(), plugins: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Rebuilds the plugin list from whatever `*.wasm` files currently
+    /// exist under `dir` — added files appear, removed ones disappear.
+    pub fn reload(&self) -> std::io::Result<()> {
+        let mut plugins = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            plugins.insert(id.to_string(), Plugin { id: id.to_string(), path });
+        }
+        *self.plugins.lock().unwrap() = plugins;
+        Ok(())
+    }
+
+    /// The ids of every currently loaded plugin, sorted.
+    pub fn ids(&self) -> Vec<String> {
+        self.plugins.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Runs every loaded plugin over `text` in id order, each plugin's
+    /// output feeding the next, and returns the final result. A plugin
+    /// that can't be run at all leaves `text` untouched at that step.
+    pub fn run_all(&self, text: &str) -> String {
+        let plugins = self.plugins.lock().unwrap();
+        let mut text = text.to_string();
+        for plugin in plugins.values() {
+            if let Some(output) = self.run_one(plugin, &text) {
+                text = output;
+            }
+        }
+        text
+    }
+
+    fn run_one(&self, plugin: &Plugin, input: &str) -> Option<String> {
+        let mut child = Command::new(&self.wasmtime_path)
+            .arg("run")
+            .arg(&plugin.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        child.stdin.take()?.write_all(input.as_bytes()).ok()?;
+        let output = child.wait_with_output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    }
+}
+
+/// Calls [`PluginRegistry::reload`] every `interval` in a background
+/// thread — a reload error (the directory got removed out from under it)
+/// is swallowed and retried next tick, same posture `config::watch` takes
+/// toward a missing/unreadable config file.
+pub fn watch(registry: &'static PluginRegistry, interval: Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let _ = registry.reload();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ai-server-plugins-test-{name}-{:x}",
+            crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn open_creates_the_plugins_directory_if_missing() {
+        let dir = temp_dir("create");
+        assert!(!dir.exists());
+        PluginRegistry::open(&dir, "wasmtime").unwrap();
+        assert!(dir.exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn reload_finds_wasm_files_and_derives_ids_from_filename() {
+        let dir = temp_dir("reload");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("uppercase.wasm"), b"fake module bytes").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignore me").unwrap();
+
+        let registry = PluginRegistry::open(&dir, "wasmtime").unwrap();
+        assert_eq!(registry.ids(), vec!["uppercase".to_string()]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn reload_drops_ids_for_files_removed_from_disk() {
+        let dir = temp_dir("drop");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.wasm"), b"fake").unwrap();
+        let registry = PluginRegistry::open(&dir, "wasmtime").unwrap();
+        assert_eq!(registry.ids(), vec!["a".to_string()]);
+
+        std::fs::remove_file(dir.join("a.wasm")).unwrap();
+        registry.reload().unwrap();
+        assert!(registry.ids().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn run_all_is_a_no_op_with_no_plugins_loaded() {
+        let dir = temp_dir("empty");
+        let registry = PluginRegistry::open(&dir, "wasmtime").unwrap();
+        assert_eq!(registry.run_all("hello"), "hello");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn run_all_leaves_text_untouched_when_the_wasmtime_binary_is_missing() {
+        let dir = temp_dir("missing-runtime");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.wasm"), b"fake").unwrap();
+        let registry = PluginRegistry::open(&dir, "ai-server-nonexistent-wasmtime-binary").unwrap();
+        assert_eq!(registry.run_all("hello"), "hello");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
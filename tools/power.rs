@@ -0,0 +1,181 @@
+//! macOS sleep/wake awareness so a laptop lid-close doesn't wedge a
+//! backend mid-generation. Three problems, three existing levers rather
+//! than new state:
+//!
+//! - **Pause the scheduler before sleep.** There's no live continuous-batching
+//!   `Scheduler` wired into the request path yet (see `resources.rs`'s note
+//!   on `scheduler.rs`), so "pause" means the same thing an operator's
+//!   `POST /admin/drain` already means: stop admitting new requests via
+//!   `admin::AdminState::set_draining`. [`apply_event`] flips it on
+//!   `PowerEvent::WillSleep` and back off on `PowerEvent::DidWake`.
+//! - **Checkpoint in-flight sessions.** `sessions::SessionStore::save`
+//!   already persists a session to disk after every appended message, so
+//!   there's nothing extra to flush here — a session is never more than
+//!   one message stale on disk.
+//! - **Re-validate GPU contexts on wake.** A Metal/CUDA device handle
+//!   acquired before sleep isn't guaranteed valid after — the same reason
+//!   `gpu.rs` and `cuda.rs` probe fresh on every `detect()` call rather
+//!   than caching a handle. [`apply_event`] re-runs [`GpuInfo::detect`] on
+//!   `DidWake` and hands the caller the fresh result to swap in.
+//!
+//! Detecting the events themselves and holding sleep off both go through
+//! `pmset`, the same "shell out rather than bind a private framework"
+//! trade `gpu.rs` makes for `system_profiler` and `thermal.rs` makes for
+//! `pmset -g therm`: [`poll_recent_events`] tails `pmset -g log` for its
+//! own sleep/wake notification lines, and [`SleepAssertion`] wraps a
+//! `caffeinate -i` child process — the command-line front end for the
+//! same `IOPMAssertionCreateWithName` call the request names — held alive
+//! only while a request is in flight, killed on drop.
+
+use crate::admin;
+use crate::gpu::GpuInfo;
+use std::process::{Child, Command};
+
+/// One power-state transition, as reported by `pmset -g log`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerEvent {
+    WillSleep,
+    DidWake,
+}
+
+/// Parses sleep/wake notification lines out of a `pmset -g log` excerpt,
+/// in the order they appear. `pmset`'s log format isn't documented, but in
+/// practice every sleep transition logs a line containing `"Entering Sleep"`
+/// and every wake logs one containing `"Wake from"` — matching on those
+/// substrings rather than the full line format keeps this resilient to the
+/// timestamp/PID columns `pmset` prepends, which vary by macOS version.
+pub fn parse_power_events(log_text: &str) -> Vec<PowerEvent> {
+    log_text
+        .lines()
+        .filter_map(|line| {
+            if line.contains("Entering Sleep") {
+                Some(PowerEvent::WillSleep)
+            } else if line.contains("Wake from") {
+                Some(PowerEvent::DidWake)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Shells out to `pmset -g log` and parses its most recent 200 lines for
+/// power events. Bounded to a tail rather than the whole log (which grows
+/// for as long as the host has been up) since callers only care about
+/// transitions since their last poll.
+#[cfg(target_os = "macos")]
+pub fn poll_recent_events() -> Vec<PowerEvent> {
+    let Ok(output) = Command::new("pmset").args(["-g", "log"]).output() else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let tail: Vec<&str> = text.lines().rev().take(200).collect();
+    let tail: String = tail.into_iter().rev().collect::<Vec<_>>().join("\n");
+    parse_power_events(&tail)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn poll_recent_events() -> Vec<PowerEvent> {
+    Vec::new()
+}
+
+/// Applies one power event to live server state: pauses or resumes
+/// admission via `admin`, and returns a freshly probed [`GpuInfo`] on
+/// wake for the caller to swap in (`None` on sleep, or on wake if no GPU
+/// is detected). See the module doc comment for why this is the whole of
+/// "pause the scheduler" and "re-validate GPU contexts" in a tree with no
+/// live scheduler or cached device handle to begin with.
+pub fn apply_event(event: PowerEvent, admin: &admin::AdminState) -> Option<GpuInfo> {
+    match event {
+        PowerEvent::WillSleep => {
+            admin.set_draining(true);
+            None
+        }
+        PowerEvent::DidWake => {
+            admin.set_draining(false);
+            GpuInfo::detect()
+        }
+    }
+}
+
+/// Holds a `caffeinate -i` child process alive for as long as the guard
+/// lives, preventing idle sleep while it's held — an operator or the
+/// request-handling loop wrapping the lifetime of an active request in
+/// [`SleepAssertion::hold`] so a long generation doesn't get suspended
+/// mid-stream by an idle laptop's own sleep timer. Killed on drop rather
+/// than left to exit on its own, since `caffeinate -i` with no other
+/// arguments runs until killed.
+pub struct SleepAssertion {
+    child: Child,
+}
+
+impl SleepAssertion {
+    #[cfg(target_os = "macos")]
+    pub fn hold() -> Option<SleepAssertion> {
+        Command::new("caffeinate").arg("-i").spawn().ok().map(|child| SleepAssertion { child })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn hold() -> Option<SleepAssertion> {
+        None
+    }
+}
+
+impl Drop for SleepAssertion {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Spawns a background thread that polls [`poll_recent_events`] every
+/// `interval` and applies whatever it finds via [`apply_event`], the same
+/// "spawn once from `main`, loop forever on a fixed interval" shape
+/// `config::watch` uses for config hot-reload. Started unconditionally —
+/// on non-macOS platforms [`poll_recent_events`] always returns empty, so
+/// the thread just sleeps.
+pub fn watch(admin: &'static admin::AdminState, interval: std::time::Duration) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        for event in poll_recent_events() {
+            apply_event(event, admin);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_power_events_recognizes_sleep_and_wake_lines_in_order() {
+        let log = "2026-08-08 09:00:00 -0700 Sleep Entering Sleep state due to Lid Closed\n\
+                    2026-08-08 09:00:01 -0700 Sleep Wait for Sleep response\n\
+                    2026-08-08 09:15:22 -0700 Wake Wake from Deep Idle Sleep\n";
+        assert_eq!(parse_power_events(log), vec![PowerEvent::WillSleep, PowerEvent::DidWake]);
+    }
+
+    #[test]
+    fn parse_power_events_ignores_unrelated_lines() {
+        let log = "2026-08-08 09:00:00 -0700 Battery Using Batt\n";
+        assert!(parse_power_events(log).is_empty());
+    }
+
+    #[test]
+    fn apply_event_sets_draining_on_will_sleep() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        assert!(apply_event(PowerEvent::WillSleep, &admin).is_none());
+        assert!(admin.is_draining());
+    }
+
+    #[test]
+    fn apply_event_clears_draining_on_did_wake() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        admin.set_draining(true);
+        apply_event(PowerEvent::DidWake, &admin);
+        assert!(!admin.is_draining());
+    }
+}
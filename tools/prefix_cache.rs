@@ -0,0 +1,200 @@
+//! Prompt prefix caching: when two requests share a token prefix (a common
+//! system prompt, a repeated few-shot preamble), the second one can reuse
+//! the first's cached KV blocks for the shared span instead of recomputing
+//! it. This module only tracks *which* prefix of a new prompt is already
+//! cached and by which sequence — reusing the underlying KV blocks is
+//! `kvcache.rs`'s job once it knows the reusable length.
+
+use crate::json::{Json, ObjectBuilder};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub type SequenceId = u64;
+
+/// One previously-seen prompt, keyed by its token sequence so lookups can
+/// find the longest match without a full trie.
+struct CachedPrompt {
+    tokens: Vec<u32>,
+    owner: SequenceId,
+}
+
+/// Tracks token sequences from past requests so new requests can find the
+/// longest prefix they share with something already cached.
+#[derive(Default)]
+pub struct PrefixCache {
+    entries: Vec<CachedPrompt>,
+    index: HashMap<SequenceId, usize>,
+}
+
+/// The result of a prefix lookup: how many leading tokens of the query
+/// prompt are already cached, and which sequence holds them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrefixMatch {
+    pub owner: SequenceId,
+    pub shared_len: usize,
+}
+
+impl PrefixCache {
+    pub fn new() -> Self {
+        PrefixCache::default()
+    }
+
+    /// Records `tokens` as `owner`'s prompt, making it available as a
+    /// match target for future requests. Replaces any prior entry for the
+    /// same `owner`.
+    pub fn record(&mut self, owner: SequenceId, tokens: Vec<u32>) {
+        if let Some(&i) = self.index.get(&owner) {
+            self.entries[i] = CachedPrompt { tokens, owner };
+        } else {
+            self.index.insert(owner, self.entries.len());
+            self.entries.push(CachedPrompt { tokens, owner });
+        }
+    }
+
+    pub fn remove(&mut self, owner: SequenceId) {
+        if let Some(i) = self.index.remove(&owner) {
+            self.entries.remove(i);
+            for (_, idx) in self.index.iter_mut() {
+                if *idx > i {
+                    *idx -= 1;
+                }
+            }
+        }
+    }
+
+    /// Drops every recorded prompt, e.g. an operator-triggered cache flush
+    /// (see `admin.rs`) after swapping a system prompt that shouldn't
+    /// still get prefix hits against the old one.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// Finds the cached prompt sharing the longest prefix with `tokens`,
+    /// returning `None` when nothing shares even one token.
+    pub fn find_longest_match(&self, tokens: &[u32]) -> Option<PrefixMatch> {
+        self.entries
+            .iter()
+            .map(|entry| PrefixMatch { owner: entry.owner, shared_len: common_prefix_len(&entry.tokens, tokens) })
+            .filter(|m| m.shared_len > 0)
+            .max_by_key(|m| m.shared_len)
+    }
+
+    /// Serializes every recorded prompt to `path`, so a restart's fresh
+    /// `PrefixCache::new()` can [`load`](Self::load) it back and skip
+    /// straight to a prefix-cache hit instead of starting cold — this is
+    /// the closest thing to a KV-cache snapshot this tree can persist,
+    /// since the underlying blocks themselves (`kvcache.rs`) hold no
+    /// tensor data of their own to save.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let prompts: Vec<Json> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                ObjectBuilder::new()
+                    .set("owner", Json::Number(entry.owner as f64))
+                    .set("tokens", Json::Array(entry.tokens.iter().map(|&t| Json::Number(t as f64)).collect()))
+                    .build()
+            })
+            .collect();
+        std::fs::write(path, Json::Array(prompts).to_string())
+    }
+
+    /// Rebuilds a cache from a prior [`save`](Self::save), or an empty one
+    /// if `path` doesn't exist yet (a model's first load).
+    pub fn load(path: &Path) -> std::io::Result<PrefixCache> {
+        if !path.exists() {
+            return Ok(PrefixCache::new());
+        }
+        let text = std::fs::read_to_string(path)?;
+        let mut cache = PrefixCache::new();
+        let Ok(Json::Array(prompts)) = Json::parse(&text) else { return Ok(cache) };
+        for prompt in prompts {
+            let (Some(owner), Some(tokens)) = (prompt.get("owner").and_then(Json::as_f64), prompt.get("tokens").and_then(Json::as_array))
+            else {
+                continue;
+            };
+            let tokens: Vec<u32> = tokens.iter().filter_map(Json::as_f64).map(|t| t as u32).collect();
+            cache.record(owner as SequenceId, tokens);
+        }
+        Ok(cache)
+    }
+}
+
+fn common_prefix_len(a: &[u32], b: &[u32]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_longest_shared_prefix_among_multiple_candidates() {
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2, 3, 9]);
+        cache.record(2, vec![1, 2, 3, 4, 5]);
+
+        let query = vec![1, 2, 3, 4, 8];
+        let m = cache.find_longest_match(&query).unwrap();
+        assert_eq!(m.owner, 2);
+        assert_eq!(m.shared_len, 4);
+    }
+
+    #[test]
+    fn returns_none_when_no_tokens_are_shared() {
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2, 3]);
+        assert!(cache.find_longest_match(&[9, 9, 9]).is_none());
+    }
+
+    #[test]
+    fn remove_drops_the_entry_and_keeps_others_reachable() {
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2]);
+        cache.record(2, vec![1, 2, 3]);
+        cache.remove(1);
+
+        assert_eq!(cache.find_longest_match(&[1, 2, 3]).unwrap().owner, 2);
+    }
+
+    #[test]
+    fn record_replaces_a_prior_entry_for_the_same_owner() {
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2, 3]);
+        cache.record(1, vec![9, 9]);
+        assert!(cache.find_longest_match(&[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn clear_drops_every_recorded_prompt() {
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2, 3]);
+        cache.record(2, vec![4, 5, 6]);
+        cache.clear();
+        assert!(cache.find_longest_match(&[1, 2, 3]).is_none());
+        assert!(cache.find_longest_match(&[4, 5, 6]).is_none());
+    }
+
+    #[test]
+    fn save_and_load_round_trips_recorded_prompts() {
+        let path = std::env::temp_dir().join(format!("ai-server-prefix-cache-test-{}.json", std::process::id()));
+        let mut cache = PrefixCache::new();
+        cache.record(1, vec![1, 2, 3]);
+        cache.record(2, vec![4, 5]);
+        cache.save(&path).unwrap();
+
+        let reloaded = PrefixCache::load(&path).unwrap();
+        assert_eq!(reloaded.find_longest_match(&[1, 2, 3]).unwrap().owner, 1);
+        assert_eq!(reloaded.find_longest_match(&[4, 5]).unwrap().owner, 2);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn load_returns_an_empty_cache_when_the_file_does_not_exist() {
+        let path = std::env::temp_dir().join(format!("ai-server-prefix-cache-test-missing-{}.json", std::process::id()));
+        let cache = PrefixCache::load(&path).unwrap();
+        assert!(cache.find_longest_match(&[1]).is_none());
+    }
+}
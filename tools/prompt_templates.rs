@@ -0,0 +1,189 @@
+//! Named, versioned prompt templates a `/v1/chat/completions` request can
+//! invoke with `{"template": "name", "variables": {...}}` instead of
+//! sending raw `messages`, so a prompt lives in one place operators
+//! control instead of scattered across every client that calls this
+//! server. Shaped like `lora::AdapterRegistry`: an in-memory,
+//! mutex-guarded map with no on-disk format of its own, grown at runtime
+//! via `POST /admin/templates` the same way an operator registers a LoRA
+//! adapter or flips `admin::AdminState`'s log level — see that module's
+//! doc comment for why this tree doesn't scan a directory for these the
+//! way `registry::ModelRegistry` does for `*.gguf` files.
+//!
+//! [`TemplateRegistry::register`] bumps the version on every call for a
+//! given name rather than requiring the caller to track one, so
+//! `/admin/templates` stays a plain "here's the new content" call and a
+//! client that cached an older [`PromptTemplate`] can tell it's stale by
+//! comparing `version`.
+//!
+//! Substitution is a plain `{{name}}` token replacement — no
+//! conditionals, no loops, no escaping rules — matching this tree's
+//! "hand-roll only what's actually needed" posture (see `json.rs`'s doc
+//! comment on skipping a full JSON Schema validator). A template
+//! declares its variable names up front so [`render`] can report every
+//! missing one in a single error instead of failing on the first `{{...}}`
+//! it happens to reach.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// One named template's current content. `variables` lists the names
+/// [`render`] requires — "typed" only in the sense that a name not in
+/// this list can't be referenced and one in the list must be supplied;
+/// there's no further type (string vs. number) beyond that.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    pub version: u32,
+    pub messages: Vec<TemplateMessage>,
+    pub variables: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+    NotFound(String),
+    MissingVariables(Vec<String>),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::NotFound(name) => write!(f, "no template named {name:?}"),
+            TemplateError::MissingVariables(names) => write!(f, "missing variables: {}", names.join(", ")),
+        }
+    }
+}
+
+/// In-memory catalog of registered templates, keyed by name.
+#[derive(Default)]
+pub struct TemplateRegistry {
+    templates: Mutex<BTreeMap<String, PromptTemplate>>,
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        TemplateRegistry::default()
+    }
+
+    /// Registers `messages`/`variables` under `name`, returning the new
+    /// version: one past whatever was previously registered under this
+    /// name, or `1` for a name seen for the first time.
+    pub fn register(&self, name: &str, messages: Vec<TemplateMessage>, variables: Vec<String>) -> u32 {
+        let mut templates = self.templates.lock().unwrap();
+        let version = templates.get(name).map(|t| t.version + 1).unwrap_or(1);
+        templates.insert(name.to_string(), PromptTemplate { version, messages, variables });
+        version
+    }
+
+    pub fn get(&self, name: &str) -> Option<PromptTemplate> {
+        self.templates.lock().unwrap().get(name).cloned()
+    }
+
+    /// Every registered template's name and current version, sorted by
+    /// name so repeated calls (e.g. an admin listing) don't jitter.
+    pub fn list(&self) -> Vec<(String, u32)> {
+        self.templates.lock().unwrap().iter().map(|(name, t)| (name.clone(), t.version)).collect()
+    }
+}
+
+/// Renders `template` against `variables`, substituting every `{{name}}`
+/// token in each message's content. Fails with every variable `template`
+/// declares but `variables` doesn't supply, checked before substitution
+/// starts so a request is rejected as a whole rather than partially
+/// rendered.
+pub fn render(template: &PromptTemplate, variables: &BTreeMap<String, String>) -> Result<Vec<TemplateMessage>, TemplateError> {
+    let missing: Vec<String> = template.variables.iter().filter(|name| !variables.contains_key(*name)).cloned().collect();
+    if !missing.is_empty() {
+        return Err(TemplateError::MissingVariables(missing));
+    }
+    Ok(template
+        .messages
+        .iter()
+        .map(|m| TemplateMessage { role: m.role.clone(), content: substitute(&m.content, variables) })
+        .collect())
+}
+
+fn substitute(content: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+        let key = after[..end].trim();
+        if let Some(value) = variables.get(key) {
+            result.push_str(value);
+        }
+        rest = &after[end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn register_starts_at_version_one_and_increments_on_reregistration() {
+        let registry = TemplateRegistry::new();
+        assert_eq!(registry.register("greeting", vec![], vec![]), 1);
+        assert_eq!(registry.register("greeting", vec![], vec![]), 2);
+    }
+
+    #[test]
+    fn get_returns_none_for_an_unregistered_name() {
+        assert!(TemplateRegistry::new().get("missing").is_none());
+    }
+
+    #[test]
+    fn list_reports_every_template_sorted_by_name() {
+        let registry = TemplateRegistry::new();
+        registry.register("b", vec![], vec![]);
+        registry.register("a", vec![], vec![]);
+        assert_eq!(registry.list(), vec![("a".to_string(), 1), ("b".to_string(), 1)]);
+    }
+
+    #[test]
+    fn render_substitutes_every_occurrence_of_a_variable() {
+        let template = PromptTemplate {
+            version: 1,
+            messages: vec![TemplateMessage { role: "user".to_string(), content: "Hi {{name}}, welcome to {{name}}'s server".to_string() }],
+            variables: vec!["name".to_string()],
+        };
+        let rendered = render(&template, &vars(&[("name", "Ruben")])).unwrap();
+        assert_eq!(rendered[0].content, "Hi Ruben, welcome to Ruben's server");
+    }
+
+    #[test]
+    fn render_reports_every_missing_variable_at_once() {
+        let template = PromptTemplate {
+            version: 1,
+            messages: vec![TemplateMessage { role: "user".to_string(), content: "{{a}} {{b}}".to_string() }],
+            variables: vec!["a".to_string(), "b".to_string()],
+        };
+        let err = render(&template, &BTreeMap::new()).unwrap_err();
+        assert_eq!(err, TemplateError::MissingVariables(vec!["a".to_string(), "b".to_string()]));
+    }
+
+    #[test]
+    fn render_leaves_an_unterminated_token_untouched() {
+        let template = PromptTemplate {
+            version: 1,
+            messages: vec![TemplateMessage { role: "user".to_string(), content: "broken {{oops".to_string() }],
+            variables: vec![],
+        };
+        assert_eq!(render(&template, &BTreeMap::new()).unwrap()[0].content, "broken {{oops");
+    }
+}
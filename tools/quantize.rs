@@ -0,0 +1,326 @@
+//! On-the-fly weight quantization between GGUF-compatible block formats.
+//!
+//! Implements Q8_0 fully: block of 32 elements, one `f16` scale plus 32
+//! signed `i8` values (34 bytes/block) — a format simple enough to get
+//! right without the reference `ggml` C source. `Q4_K`/`Q5_K` use a much
+//! more elaborate two-level "256-element super-block of eight 6-bit-scaled
+//! sub-blocks" layout that `llama.cpp` defines empirically in its source
+//! rather than in a public spec; reproducing it bit-for-bit without that
+//! reference is a follow-up, not this pass's scope. [`QuantType`] still
+//! names them (so a [`QuantPlan`] can already ask for one on a tensor) and
+//! [`QuantType::bytes_for`] reports their well-known average bits-per-weight
+//! for sizing reports, but [`quantize`]/[`dequantize`] return
+//! [`QuantizeError::Unsupported`] for both.
+//!
+//! Optional importance weights (one non-negative `f32` per source element,
+//! higher = more error-sensitive) bias which of a handful of candidate
+//! block scales [`quantize`] picks, minimizing weighted squared
+//! reconstruction error instead of the plain max-magnitude scale. That
+//! stands in for a real importance-matrix solver's continuous
+//! optimization — computing the weights themselves (running calibration
+//! prompts through a model and averaging activation magnitudes) needs a
+//! backend that actually executes a model, which `server.rs`'s
+//! `EchoBackend` does not; this module only consumes weights a caller
+//! already has.
+
+/// GGUF-compatible weight quantization formats this tool knows the name
+/// and (for reporting) size of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantType {
+    Q8_0,
+    Q4K,
+    Q5K,
+}
+
+impl QuantType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Q8_0" | "q8_0" => Some(QuantType::Q8_0),
+            "Q4_K" | "q4_k" => Some(QuantType::Q4K),
+            "Q5_K" | "q5_k" => Some(QuantType::Q5K),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuantType::Q8_0 => "Q8_0",
+            QuantType::Q4K => "Q4_K",
+            QuantType::Q5K => "Q5_K",
+        }
+    }
+
+    /// Bytes needed to store `element_count` elements in this format.
+    /// Exact for `Q8_0` (34 bytes per 32-element block, rounding a partial
+    /// trailing block up the same way `ggml` pads one). `Q4_K`/`Q5_K` are
+    /// not implemented yet (see the module doc comment), so this reports
+    /// their well-known average bits-per-weight instead — 4.5 and 5.5
+    /// respectively, the same figures `llama.cpp`'s own quantization notes
+    /// cite for these formats — good enough for a sizing estimate, not a
+    /// promise of the exact byte count a real encoder would produce.
+    pub fn bytes_for(self, element_count: usize) -> u64 {
+        match self {
+            QuantType::Q8_0 => {
+                let blocks = element_count.div_ceil(32);
+                (blocks * 34) as u64
+            }
+            QuantType::Q4K => (element_count as u64 * 45).div_ceil(80), // 4.5 bits/elem
+            QuantType::Q5K => (element_count as u64 * 55).div_ceil(80), // 5.5 bits/elem
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QuantizeError {
+    /// A buffer's length wasn't a multiple of the expected block size.
+    Misaligned { block_size: usize, len: usize },
+    /// `target` isn't implemented yet — see the module doc comment.
+    Unsupported(QuantType),
+}
+
+/// Per-tensor quantization overrides, keyed by tensor name (e.g.
+/// `blk.0.attn_q.weight`) — the same tensor-name addressing GGUF's own
+/// tensor table and `lora.rs`'s `AdapterRegistry` use. A name absent from
+/// `overrides` falls back to `default`.
+#[derive(Debug, Clone, Default)]
+pub struct QuantPlan {
+    pub default: Option<QuantType>,
+    pub overrides: std::collections::BTreeMap<String, QuantType>,
+}
+
+impl QuantPlan {
+    pub fn target_for(&self, tensor_name: &str) -> Option<QuantType> {
+        self.overrides.get(tensor_name).copied().or(self.default)
+    }
+}
+
+const Q8_0_BLOCK: usize = 32;
+
+/// Quantizes `source` (already-decoded `f32` weights) into `target`. See
+/// the module doc comment for `importance`'s meaning; pass `None` for the
+/// plain max-magnitude scale.
+pub fn quantize(source: &[f32], target: QuantType, importance: Option<&[f32]>) -> Result<Vec<u8>, QuantizeError> {
+    match target {
+        QuantType::Q8_0 => quantize_q8_0(source, importance),
+        QuantType::Q4K | QuantType::Q5K => Err(QuantizeError::Unsupported(target)),
+    }
+}
+
+/// Inverse of [`quantize`] for the formats it implements.
+pub fn dequantize(bytes: &[u8], source: QuantType) -> Result<Vec<f32>, QuantizeError> {
+    match source {
+        QuantType::Q8_0 => dequantize_q8_0(bytes),
+        QuantType::Q4K | QuantType::Q5K => Err(QuantizeError::Unsupported(source)),
+    }
+}
+
+fn quantize_q8_0(source: &[f32], importance: Option<&[f32]>) -> Result<Vec<u8>, QuantizeError> {
+    if source.len() % Q8_0_BLOCK != 0 {
+        return Err(QuantizeError::Misaligned { block_size: Q8_0_BLOCK, len: source.len() });
+    }
+    if let Some(importance) = importance {
+        if importance.len() != source.len() {
+            return Err(QuantizeError::Misaligned { block_size: source.len(), len: importance.len() });
+        }
+    }
+
+    let mut out = Vec::with_capacity((source.len() / Q8_0_BLOCK) * (2 + Q8_0_BLOCK));
+    for (block_index, block) in source.chunks(Q8_0_BLOCK).enumerate() {
+        let weights = importance.map(|imp| &imp[block_index * Q8_0_BLOCK..(block_index + 1) * Q8_0_BLOCK]);
+        let amax = block.iter().fold(0f32, |acc, &v| acc.max(v.abs()));
+        let scale = best_scale(block, amax, weights);
+        out.extend_from_slice(&f32_to_f16_bits(scale).to_le_bytes());
+        for &value in block {
+            let level = if scale == 0.0 { 0 } else { (value / scale).round().clamp(-127.0, 127.0) as i8 };
+            out.push(level as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn dequantize_q8_0(bytes: &[u8]) -> Result<Vec<f32>, QuantizeError> {
+    let block_bytes = 2 + Q8_0_BLOCK;
+    if bytes.len() % block_bytes != 0 {
+        return Err(QuantizeError::Misaligned { block_size: block_bytes, len: bytes.len() });
+    }
+    let mut out = Vec::with_capacity((bytes.len() / block_bytes) * Q8_0_BLOCK);
+    for block in bytes.chunks(block_bytes) {
+        let scale = f16_bits_to_f32(u16::from_le_bytes([block[0], block[1]]));
+        out.extend(block[2..].iter().map(|&byte| (byte as i8) as f32 * scale));
+    }
+    Ok(out)
+}
+
+/// Picks the block scale minimizing weighted squared reconstruction error.
+/// Without importance weights this is the standard Q8_0 scale (`amax /
+/// 127`); with weights, a handful of candidates around that baseline are
+/// compared by weighted error and the best one wins — a coarse grid search
+/// standing in for a real solver's continuous optimization.
+fn best_scale(block: &[f32], amax: f32, weights: Option<&[f32]>) -> f32 {
+    let baseline = if amax == 0.0 { 0.0 } else { amax / 127.0 };
+    let Some(weights) = weights else { return baseline };
+    if baseline == 0.0 {
+        return 0.0;
+    }
+
+    let weighted_error = |scale: f32| -> f32 {
+        block
+            .iter()
+            .zip(weights)
+            .map(|(&value, &weight)| {
+                let level = (value / scale).round().clamp(-127.0, 127.0);
+                let error = value - level * scale;
+                weight.max(0.0) * error * error
+            })
+            .sum()
+    };
+
+    [0.90, 0.95, 1.0, 1.05, 1.10]
+        .into_iter()
+        .map(|factor| baseline * factor)
+        .min_by(|&a, &b| weighted_error(a).partial_cmp(&weighted_error(b)).unwrap())
+        .unwrap_or(baseline)
+}
+
+/// Truncates (rather than rounds to nearest-even) the mantissa — adequate
+/// for a quantization block scale, which only needs to be close to the
+/// true max magnitude, not bit-exact.
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7fffff;
+
+    if exp <= 0 {
+        return sign; // Flush subnormal-for-half magnitudes to zero.
+    }
+    if exp >= 0x1f {
+        return sign | 0x7c00; // Overflow to infinity, preserving sign.
+    }
+    sign | ((exp as u16) << 10) | (mantissa >> 13) as u16
+}
+
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = (bits & 0x8000) as u32;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let bits32 = if exp == 0 {
+        if mantissa == 0 {
+            sign << 16
+        } else {
+            let mut e = -1i32;
+            let mut m = mantissa;
+            loop {
+                m <<= 1;
+                e += 1;
+                if m & 0x400 != 0 {
+                    break;
+                }
+            }
+            m &= 0x3ff;
+            let exp32 = (127 - 15 - e) as u32;
+            (sign << 16) | (exp32 << 23) | (m << 13)
+        }
+    } else if exp == 0x1f {
+        (sign << 16) | 0x7f800000 | (mantissa << 13)
+    } else {
+        let exp32 = exp + (127 - 15);
+        (sign << 16) | (exp32 << 23) | (mantissa << 13)
+    };
+    f32::from_bits(bits32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f16_round_trip_is_close_for_ordinary_values() {
+        for value in [0.0f32, 1.0, -1.0, 3.5, -0.125, 65504.0, 1e-3] {
+            let back = f16_bits_to_f32(f32_to_f16_bits(value));
+            assert!((back - value).abs() <= value.abs() * 0.01 + 1e-6, "{value} round-tripped to {back}");
+        }
+    }
+
+    #[test]
+    fn quantize_q8_0_round_trips_within_one_quantization_step() {
+        let source: Vec<f32> = (0..32).map(|i| (i as f32 - 16.0) * 0.3).collect();
+        let bytes = quantize(&source, QuantType::Q8_0, None).unwrap();
+        assert_eq!(bytes.len(), 34);
+        let restored = dequantize(&bytes, QuantType::Q8_0).unwrap();
+        let amax = source.iter().fold(0f32, |acc, &v| acc.max(v.abs()));
+        let step = amax / 127.0;
+        for (original, back) in source.iter().zip(&restored) {
+            assert!((original - back).abs() <= step + 1e-6);
+        }
+    }
+
+    #[test]
+    fn quantize_q8_0_rejects_a_length_not_a_multiple_of_the_block_size() {
+        let source = vec![1.0f32; 31];
+        assert!(matches!(
+            quantize(&source, QuantType::Q8_0, None),
+            Err(QuantizeError::Misaligned { block_size: 32, len: 31 })
+        ));
+    }
+
+    #[test]
+    fn quantize_q8_0_handles_an_all_zero_block() {
+        let source = vec![0.0f32; 32];
+        let bytes = quantize(&source, QuantType::Q8_0, None).unwrap();
+        let restored = dequantize(&bytes, QuantType::Q8_0).unwrap();
+        assert_eq!(restored, source);
+    }
+
+    #[test]
+    fn quantize_rejects_a_mismatched_importance_length() {
+        let source = vec![1.0f32; 32];
+        let importance = vec![1.0f32; 16];
+        assert!(matches!(quantize(&source, QuantType::Q8_0, Some(&importance)), Err(QuantizeError::Misaligned { .. })));
+    }
+
+    #[test]
+    fn importance_weighting_favors_lower_error_on_high_weight_elements() {
+        let mut source = vec![0.01f32; 32];
+        source[0] = 10.0; // dominates amax, forcing a coarse baseline scale
+        let mut importance = vec![0.0f32; 32];
+        importance[1] = 1.0; // only this element's error is scored
+
+        let bytes = quantize(&source, QuantType::Q8_0, Some(&importance)).unwrap();
+        let restored = dequantize(&bytes, QuantType::Q8_0).unwrap();
+        // The weighted search should land on a scale that's at least as
+        // good for element 1 as the plain unweighted baseline would be.
+        let baseline_bytes = quantize(&source, QuantType::Q8_0, None).unwrap();
+        let baseline_restored = dequantize(&baseline_bytes, QuantType::Q8_0).unwrap();
+        assert!((restored[1] - source[1]).abs() <= (baseline_restored[1] - source[1]).abs() + 1e-6);
+    }
+
+    #[test]
+    fn quantize_returns_unsupported_for_k_quant_targets() {
+        let source = vec![0.0f32; 256];
+        assert_eq!(quantize(&source, QuantType::Q4K, None), Err(QuantizeError::Unsupported(QuantType::Q4K)));
+        assert_eq!(quantize(&source, QuantType::Q5K, None), Err(QuantizeError::Unsupported(QuantType::Q5K)));
+    }
+
+    #[test]
+    fn quant_plan_falls_back_to_default_for_unlisted_tensors() {
+        let mut plan = QuantPlan { default: Some(QuantType::Q8_0), overrides: Default::default() };
+        plan.overrides.insert("blk.0.attn_q.weight".to_string(), QuantType::Q4K);
+        assert_eq!(plan.target_for("blk.0.attn_q.weight"), Some(QuantType::Q4K));
+        assert_eq!(plan.target_for("blk.1.attn_q.weight"), Some(QuantType::Q8_0));
+    }
+
+    #[test]
+    fn bytes_for_q8_0_accounts_for_the_two_byte_scale_per_block() {
+        assert_eq!(QuantType::Q8_0.bytes_for(32), 34);
+        assert_eq!(QuantType::Q8_0.bytes_for(33), 68); // rounds up to a second block
+    }
+
+    #[test]
+    fn from_name_accepts_common_casings() {
+        assert_eq!(QuantType::from_name("Q8_0"), Some(QuantType::Q8_0));
+        assert_eq!(QuantType::from_name("q4_k"), Some(QuantType::Q4K));
+        assert_eq!(QuantType::from_name("bogus"), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! RAG (retrieval-augmented generation) orchestration: chunk documents,
+//! embed the chunks via `embeddings.rs`, index them in `vectorstore.rs`,
+//! and assemble a context-augmented prompt for a query — the plumbing
+//! `/v1/rag/query` needs so callers don't have to wire embeddings and the
+//! vector store together themselves.
+
+use crate::embeddings::{embed_batch, EmbeddingBackend, EmbeddingRequest};
+use crate::json::Json;
+use crate::rerank::{self, RerankBackend, RerankRequest};
+use crate::vectorstore::{Collection, VectorId};
+
+/// How a document's text is split into chunks before embedding.
+#[derive(Debug, Clone, Copy)]
+pub enum ChunkStrategy {
+    /// Splits on the first separator (paragraph breaks, then sentences,
+    /// then whitespace) that yields chunks within `max_tokens`, recursing
+    /// into oversized pieces with the next separator down the list —
+    /// the same fallback order LangChain's `RecursiveCharacterTextSplitter`
+    /// uses.
+    Recursive { max_tokens: usize, overlap: usize },
+    /// Splits on a fixed token count with no regard for sentence/paragraph
+    /// boundaries.
+    FixedTokens { max_tokens: usize, overlap: usize },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    pub text: String,
+    pub start_token: usize,
+}
+
+/// Approximates tokens as whitespace-separated words — good enough for
+/// chunk sizing without pulling in a real tokenizer here; callers with a
+/// loaded model should re-chunk using `tokenizer::BpeTokenizer` counts if
+/// the two need to line up exactly.
+fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+pub fn chunk_document(text: &str, strategy: ChunkStrategy) -> Vec<Chunk> {
+    match strategy {
+        ChunkStrategy::FixedTokens { max_tokens, overlap } => chunk_by_words(text, max_tokens, overlap),
+        ChunkStrategy::Recursive { max_tokens, overlap } => chunk_recursive(text, max_tokens, overlap),
+    }
+}
+
+fn chunk_by_words(text: &str, max_tokens: usize, overlap: usize) -> Vec<Chunk> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let step = max_tokens.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let end = (start + max_tokens).min(words.len());
+        chunks.push(Chunk { text: words[start..end].join(" "), start_token: start });
+        if end == words.len() {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+/// Tries paragraph breaks first; any resulting piece still over
+/// `max_tokens` gets re-split on sentence boundaries, and anything still
+/// too large after that falls back to a fixed-token split.
+fn chunk_recursive(text: &str, max_tokens: usize, overlap: usize) -> Vec<Chunk> {
+    let paragraphs: Vec<&str> = text.split("\n\n").filter(|p| !p.trim().is_empty()).collect();
+    let mut chunks = Vec::new();
+    let mut token_offset = 0;
+    for paragraph in paragraphs {
+        if word_count(paragraph) <= max_tokens {
+            chunks.push(Chunk { text: paragraph.trim().to_string(), start_token: token_offset });
+        } else {
+            let sentences: Vec<&str> = paragraph.split(". ").filter(|s| !s.trim().is_empty()).collect();
+            if sentences.iter().all(|s| word_count(s) <= max_tokens) {
+                let mut current = String::new();
+                let mut current_start = token_offset;
+                for sentence in sentences {
+                    let candidate = if current.is_empty() { sentence.to_string() } else { format!("{current}. {sentence}") };
+                    if word_count(&candidate) > max_tokens && !current.is_empty() {
+                        chunks.push(Chunk { text: current.clone(), start_token: current_start });
+                        current_start += word_count(&current);
+                        current = sentence.to_string();
+                    } else {
+                        current = candidate;
+                    }
+                }
+                if !current.is_empty() {
+                    chunks.push(Chunk { text: current, start_token: current_start });
+                }
+            } else {
+                for mut sub in chunk_by_words(paragraph, max_tokens, overlap) {
+                    sub.start_token += token_offset;
+                    chunks.push(sub);
+                }
+            }
+        }
+        token_offset += word_count(paragraph);
+    }
+    chunks
+}
+
+/// One retrieved chunk plus how relevant it was to the query.
+#[derive(Debug)]
+pub struct RetrievedChunk {
+    pub text: String,
+    pub distance: f32,
+    /// Cross-encoder relevance score from an optional [`rerank_retrieved`]
+    /// pass. `None` when the caller didn't ask for reranking — `distance`
+    /// alone (from vector similarity) is what ordered the chunks.
+    pub rerank_score: Option<f32>,
+}
+
+/// Chunks and embeds `document`, storing each chunk's vector in
+/// `collection` keyed by `first_id + chunk_index`, and returns how many
+/// chunks were indexed.
+pub fn index_document(
+    collection: &mut Collection,
+    backend: &dyn EmbeddingBackend,
+    document: &str,
+    strategy: ChunkStrategy,
+    first_id: VectorId,
+    embedding_params: &EmbeddingRequest,
+) -> usize {
+    let chunks = chunk_document(document, strategy);
+    let token_batches: Vec<Vec<u32>> = chunks.iter().map(|c| c.text.bytes().map(u32::from).collect()).collect();
+    let vectors = embed_batch(backend, &token_batches, embedding_params);
+    for (i, (chunk, vector)) in chunks.iter().zip(vectors).enumerate() {
+        let _ = collection.upsert(first_id + i as VectorId, vector, Json::String(chunk.text.clone()));
+    }
+    chunks.len()
+}
+
+/// Embeds `query`, retrieves the `top_k` closest chunks from `collection`,
+/// and assembles a prompt that prefixes the retrieved context ahead of the
+/// original question — the shape most RAG prompt templates use.
+pub fn assemble_prompt(
+    collection: &Collection,
+    backend: &dyn EmbeddingBackend,
+    query: &str,
+    top_k: usize,
+    embedding_params: &EmbeddingRequest,
+) -> (String, Vec<RetrievedChunk>) {
+    let tokens: Vec<u32> = query.bytes().map(u32::from).collect();
+    let vectors = embed_batch(backend, std::slice::from_ref(&tokens), embedding_params);
+    let query_vector = vectors.into_iter().next().unwrap_or_default();
+
+    let hits = collection.query(&query_vector, top_k, |_| true);
+    let retrieved: Vec<RetrievedChunk> = hits
+        .into_iter()
+        .map(|hit| RetrievedChunk { text: hit.metadata.as_str().unwrap_or("").to_string(), distance: hit.distance, rerank_score: None })
+        .collect();
+
+    let context = retrieved.iter().map(|c| c.text.as_str()).collect::<Vec<_>>().join("\n\n");
+    let prompt = if context.is_empty() {
+        query.to_string()
+    } else {
+        format!("Context:\n{context}\n\nQuestion: {query}")
+    };
+    (prompt, retrieved)
+}
+
+/// Reorders `retrieved` by cross-encoder relevance instead of vector
+/// distance: scores every chunk against `query` via `rerank_backend`, drops
+/// anything below `params.score_threshold`, and keeps only the top
+/// `params.top_n`. Meant to run after [`assemble_prompt`] on its
+/// `retrieved` output, as the optional second-pass reranking stage a
+/// `/v1/rag/query` or `/v1/rerank` caller can opt into when plain vector
+/// similarity isn't precise enough on its own.
+pub fn rerank_retrieved(rerank_backend: &dyn RerankBackend, query: &str, retrieved: Vec<RetrievedChunk>, params: &RerankRequest) -> Vec<RetrievedChunk> {
+    let query_tokens: Vec<u32> = query.bytes().map(u32::from).collect();
+    let document_tokens: Vec<Vec<u32>> = retrieved.iter().map(|c| c.text.bytes().map(u32::from).collect()).collect();
+    let scored = rerank::rerank(rerank_backend, &query_tokens, &document_tokens, params);
+    let mut retrieved: Vec<Option<RetrievedChunk>> = retrieved.into_iter().map(Some).collect();
+    scored
+        .into_iter()
+        .filter_map(|result| retrieved[result.index].take().map(|chunk| RetrievedChunk { rerank_score: Some(result.score), ..chunk }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::Pooling;
+
+    struct StubBackend;
+    impl EmbeddingBackend for StubBackend {
+        fn hidden_size(&self) -> usize {
+            2
+        }
+        fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+            let sum: u32 = tokens.iter().sum();
+            vec![vec![sum as f32, tokens.len() as f32]]
+        }
+    }
+
+    fn params() -> EmbeddingRequest {
+        EmbeddingRequest { pooling: Pooling::Mean, normalize: false, dimensions: None }
+    }
+
+    #[test]
+    fn fixed_token_chunking_respects_max_tokens_and_overlap() {
+        let text = "one two three four five six";
+        let chunks = chunk_document(text, ChunkStrategy::FixedTokens { max_tokens: 3, overlap: 1 });
+        assert_eq!(chunks[0].text, "one two three");
+        assert_eq!(chunks[1].text, "three four five");
+        assert_eq!(chunks[1].start_token, 2);
+    }
+
+    #[test]
+    fn recursive_chunking_keeps_short_paragraphs_intact() {
+        let text = "para one is short.\n\npara two is also short.";
+        let chunks = chunk_document(text, ChunkStrategy::Recursive { max_tokens: 50, overlap: 0 });
+        assert_eq!(chunks.len(), 2);
+    }
+
+    #[test]
+    fn index_and_assemble_prompt_round_trips_through_the_vector_store() {
+        let mut collection = Collection::default();
+        let backend = StubBackend;
+        index_document(&mut collection, &backend, "hello world", ChunkStrategy::FixedTokens { max_tokens: 100, overlap: 0 }, 1, &params());
+
+        let (prompt, retrieved) = assemble_prompt(&collection, &backend, "hello world", 1, &params());
+        assert!(prompt.contains("hello world"));
+        assert_eq!(retrieved.len(), 1);
+    }
+
+    #[test]
+    fn assemble_prompt_falls_back_to_the_bare_query_with_no_index() {
+        let collection = Collection::default();
+        let backend = StubBackend;
+        let (prompt, retrieved) = assemble_prompt(&collection, &backend, "anything", 3, &params());
+        assert_eq!(prompt, "anything");
+        assert!(retrieved.is_empty());
+    }
+
+    #[test]
+    fn rerank_retrieved_reorders_by_cross_encoder_score() {
+        let retrieved = vec![
+            RetrievedChunk { text: "a".to_string(), distance: 0.1, rerank_score: None },
+            RetrievedChunk { text: "zzzzz".to_string(), distance: 0.9, rerank_score: None },
+        ];
+        let backend = StubBackend;
+        let rerank_backend = rerank::EmbeddingRerankBackend { embedding_backend: &backend };
+        let reordered = rerank_retrieved(&rerank_backend, "query", retrieved, &RerankRequest::default());
+        assert_eq!(reordered.len(), 2);
+        assert!(reordered[0].rerank_score.is_some());
+        assert!(reordered[0].rerank_score.unwrap() >= reordered[1].rerank_score.unwrap());
+    }
+}
@@ -0,0 +1,688 @@
+//! Local model registry: tracks which GGUF files live under a models
+//! directory and their on-disk layout, so the server can resolve a model
+//! id from an API request to a file path without rescanning the disk on
+//! every request. Backed by a `catalog.json` written with `json.rs`.
+//!
+//! [`ModelRegistry::verify`] hashes a registered file against a published
+//! SHA-256 and records the outcome on its [`ModelEntry`] so a `strict`
+//! deployment (see `config.rs`'s `models.strict_verification`) can refuse
+//! to serve a model that hasn't been checked, without re-hashing
+//! potentially many gigabytes on every resolve. Downloaded weights get
+//! corrupted or tampered with more often than people think — the same
+//! concern `downloader.rs`'s post-transfer checksum addresses for a single
+//! download, except `verify` also covers files that landed in the models
+//! directory some other way (an operator's `scp`, a restored backup).
+
+use crate::durability;
+use crate::gguf::{GgufModel, RopeScaling};
+use crate::json::{Json, ObjectBuilder};
+use crate::sampling::{GenerationClamps, GenerationPreset};
+use crate::sha256::{hex, sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Outcome of the most recent [`ModelRegistry::verify`] run against an
+/// entry's file. Persisted in `catalog.json` so it survives a process
+/// restart instead of resetting to `Unverified` every time the server
+/// comes back up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationStatus {
+    /// `verify` has never run against this entry (or [`ModelRegistry::rescan`]
+    /// rediscovered it from disk, which forgets prior verification the same
+    /// way it forgets anything else `register` added beyond id/path/size).
+    Unverified,
+    /// The file's SHA-256 matched the expected digest, and its detached
+    /// signature (if one was checked) verified against it.
+    Verified,
+    /// The file's SHA-256 didn't match the expected digest.
+    ChecksumMismatch,
+    /// The checksum matched but the detached signature didn't.
+    SignatureInvalid,
+}
+
+impl VerificationStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerificationStatus::Unverified => "unverified",
+            VerificationStatus::Verified => "verified",
+            VerificationStatus::ChecksumMismatch => "checksum_mismatch",
+            VerificationStatus::SignatureInvalid => "signature_invalid",
+        }
+    }
+
+    fn parse(raw: &str) -> Option<VerificationStatus> {
+        match raw {
+            "unverified" => Some(VerificationStatus::Unverified),
+            "verified" => Some(VerificationStatus::Verified),
+            "checksum_mismatch" => Some(VerificationStatus::ChecksumMismatch),
+            "signature_invalid" => Some(VerificationStatus::SignatureInvalid),
+            _ => None,
+        }
+    }
+}
+
+/// A detached signature to check alongside the SHA-256 comparison
+/// `verify` already does. Shells out to `minisign` — a vendor-neutral,
+/// single-binary signer, unlike sigstore's `cosign` which needs
+/// OIDC/Fulcio network calls this tree has no HTTPS client for (see
+/// `downloader.rs`'s doc comment on why that's out of scope) — the same
+/// "CLI over SDK" trade `vulkan.rs`/`cuda.rs` make for GPU detection.
+pub struct SignatureCheck<'a> {
+    pub signature_path: &'a Path,
+    pub public_key_path: &'a Path,
+}
+
+/// One entry in the catalog: a model id mapped to its file on disk and
+/// size, so callers can sanity-check available space before loading it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelEntry {
+    pub id: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// The digest recorded by the most recent [`ModelRegistry::verify`]
+    /// run, regardless of whether it matched — kept so a failed
+    /// verification's actual hash is visible without re-hashing.
+    pub sha256: Option<String>,
+    pub verification: VerificationStatus,
+    /// The file's trained context length and RoPE scaling, read from its
+    /// GGUF header when the file parses as one — `None`/`RopeScaling::None`
+    /// for a non-GGUF entry or one whose header couldn't be read. Cheap to
+    /// populate since [`GgufModel::open`] only reads the metadata/tensor-info
+    /// sections, never tensor data.
+    pub context_length: Option<u64>,
+    pub rope_scaling: RopeScaling,
+    pub rope_scaling_factor: f64,
+    /// Named default sampling values attached via [`ModelRegistry::set_preset`],
+    /// or `None` if no preset is registered for this model — see
+    /// `sampling::resolve_generation_params`.
+    pub preset: Option<GenerationPreset>,
+    /// Hard clamps on sampling values, attached via
+    /// [`ModelRegistry::set_clamps`]. Defaults to unclamped (every bound
+    /// `None`) rather than an `Option` wrapping the whole struct, since an
+    /// entry with no clamps set behaves identically to one with every
+    /// field `None`.
+    pub clamps: GenerationClamps,
+}
+
+/// Disk-backed catalog of models under a single root directory, laid out
+/// as `<root>/<id>.gguf` with a sidecar `<root>/catalog.json` index.
+pub struct ModelRegistry {
+    root: PathBuf,
+    entries: BTreeMap<String, ModelEntry>,
+}
+
+impl ModelRegistry {
+    /// Loads the registry for `root`, rebuilding the catalog from a
+    /// filesystem scan if `catalog.json` is missing or unreadable rather
+    /// than failing outright — the catalog is a cache, not source of truth.
+    pub fn open(root: &Path) -> std::io::Result<ModelRegistry> {
+        fs::create_dir_all(root)?;
+        durability::recover_dir(root)?;
+        let mut registry = ModelRegistry { root: root.to_path_buf(), entries: BTreeMap::new() };
+        if !registry.load_catalog() {
+            registry.rescan()?;
+            registry.save_catalog()?;
+        }
+        Ok(registry)
+    }
+
+    fn catalog_path(&self) -> PathBuf {
+        self.root.join("catalog.json")
+    }
+
+    fn load_catalog(&mut self) -> bool {
+        let Ok(text) = fs::read_to_string(self.catalog_path()) else { return false };
+        let Ok(parsed) = Json::parse(&text) else { return false };
+        let Some(models) = parsed.get("models").and_then(Json::as_array) else { return false };
+
+        let mut entries = BTreeMap::new();
+        for model in models {
+            let (Some(id), Some(path), Some(size)) = (
+                model.get("id").and_then(Json::as_str),
+                model.get("path").and_then(Json::as_str),
+                model.get("size_bytes").and_then(Json::as_f64),
+            ) else {
+                continue;
+            };
+            // `sha256`/`verification` are absent from catalogs written before
+            // verification existed; default to "never checked" rather than
+            // rejecting the whole entry.
+            let sha256 = model.get("sha256").and_then(Json::as_str).map(str::to_string);
+            let verification = model
+                .get("verification")
+                .and_then(Json::as_str)
+                .and_then(VerificationStatus::parse)
+                .unwrap_or(VerificationStatus::Unverified);
+            // `context_length`/`rope_scaling`/`rope_scaling_factor` are
+            // absent from catalogs written before this existed; default to
+            // "unknown" the same way `verification` defaults for old
+            // catalogs above, rather than re-reading the GGUF header here.
+            let context_length = model.get("context_length").and_then(Json::as_f64).map(|v| v as u64);
+            let rope_scaling = model
+                .get("rope_scaling")
+                .and_then(Json::as_str)
+                .and_then(RopeScaling::parse)
+                .unwrap_or(RopeScaling::None);
+            let rope_scaling_factor = model.get("rope_scaling_factor").and_then(Json::as_f64).unwrap_or(1.0);
+            // `preset`/`clamps` are absent from catalogs written before
+            // this existed; default to "no preset, no clamps" the same way
+            // `rope_scaling` defaults above.
+            let preset = model.get("preset").map(parse_preset);
+            let clamps = model.get("clamps").map(parse_clamps).unwrap_or_default();
+            entries.insert(
+                id.to_string(),
+                ModelEntry {
+                    id: id.to_string(),
+                    path: PathBuf::from(path),
+                    size_bytes: size as u64,
+                    sha256,
+                    verification,
+                    context_length,
+                    rope_scaling,
+                    rope_scaling_factor,
+                    preset,
+                    clamps,
+                },
+            );
+        }
+        self.entries = entries;
+        true
+    }
+
+    fn save_catalog(&self) -> std::io::Result<()> {
+        let models: Vec<Json> = self
+            .entries
+            .values()
+            .map(|e| {
+                let mut builder = ObjectBuilder::new()
+                    .set("id", Json::String(e.id.clone()))
+                    .set("path", Json::String(e.path.to_string_lossy().into_owned()))
+                    .set("size_bytes", Json::Number(e.size_bytes as f64))
+                    .set("verification", Json::String(e.verification.as_str().to_string()));
+                if let Some(sha256) = &e.sha256 {
+                    builder = builder.set("sha256", Json::String(sha256.clone()));
+                }
+                if let Some(context_length) = e.context_length {
+                    builder = builder.set("context_length", Json::Number(context_length as f64));
+                }
+                if e.rope_scaling != RopeScaling::None {
+                    builder = builder
+                        .set("rope_scaling", Json::String(e.rope_scaling.as_str().to_string()))
+                        .set("rope_scaling_factor", Json::Number(e.rope_scaling_factor));
+                }
+                if let Some(preset) = &e.preset {
+                    builder = builder.set("preset", preset_to_json(preset));
+                }
+                if e.clamps != GenerationClamps::default() {
+                    builder = builder.set("clamps", clamps_to_json(&e.clamps));
+                }
+                builder.build()
+            })
+            .collect();
+        let catalog = ObjectBuilder::new().set("models", Json::Array(models)).build();
+        durability::atomic_write(&self.catalog_path(), catalog.to_string().as_bytes())
+    }
+
+    /// Rebuilds `entries` from every `*.gguf` file directly under `root`,
+    /// deriving each model's id from its file stem.
+    pub fn rescan(&mut self) -> std::io::Result<()> {
+        let mut entries = BTreeMap::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("gguf") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let size_bytes = entry.metadata()?.len();
+            let (context_length, rope_scaling, rope_scaling_factor) = gguf_summary(&path);
+            entries.insert(
+                id.to_string(),
+                ModelEntry {
+                    id: id.to_string(),
+                    path,
+                    size_bytes,
+                    sha256: None,
+                    verification: VerificationStatus::Unverified,
+                    context_length,
+                    rope_scaling,
+                    rope_scaling_factor,
+                    preset: None,
+                    clamps: GenerationClamps::default(),
+                },
+            );
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    pub fn resolve(&self, id: &str) -> Option<&ModelEntry> {
+        self.entries.get(id)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &ModelEntry> {
+        self.entries.values()
+    }
+
+    /// Registers a model that was placed at `path` outside of a rescan
+    /// (e.g. right after a download completes), persisting the catalog
+    /// immediately so a crash right after doesn't lose the entry.
+    pub fn register(&mut self, id: &str, path: PathBuf, size_bytes: u64) -> std::io::Result<()> {
+        let (context_length, rope_scaling, rope_scaling_factor) = gguf_summary(&path);
+        self.entries.insert(
+            id.to_string(),
+            ModelEntry {
+                id: id.to_string(),
+                path,
+                size_bytes,
+                sha256: None,
+                verification: VerificationStatus::Unverified,
+                context_length,
+                rope_scaling,
+                rope_scaling_factor,
+                preset: None,
+                clamps: GenerationClamps::default(),
+            },
+        );
+        self.save_catalog()
+    }
+
+    /// Hashes `id`'s file on disk and compares it against `expected_sha256`
+    /// (lowercase hex), optionally also checking a detached signature,
+    /// persisting the outcome so repeated resolves under `strict` mode (see
+    /// `config.rs`'s `models.strict_verification`) don't re-hash a
+    /// potentially multi-gigabyte file every time. Returns an error if `id`
+    /// isn't registered, the file can't be read, or a requested signature
+    /// check couldn't run (e.g. `minisign` isn't installed) — a check that
+    /// silently "passed" because it was never actually performed would
+    /// defeat the point of `strict` mode.
+    pub fn verify(
+        &mut self,
+        id: &str,
+        expected_sha256: &str,
+        signature: Option<SignatureCheck>,
+    ) -> std::io::Result<VerificationStatus> {
+        let path = self
+            .entries
+            .get(id)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("no such model {id:?}")))?
+            .path
+            .clone();
+        let contents = fs::read(&path)?;
+        let actual = hex(&sha256(&contents));
+
+        let status = if !actual.eq_ignore_ascii_case(expected_sha256) {
+            VerificationStatus::ChecksumMismatch
+        } else {
+            match signature {
+                Some(check) if !verify_minisign(&path, &check)? => VerificationStatus::SignatureInvalid,
+                _ => VerificationStatus::Verified,
+            }
+        };
+
+        let entry = self.entries.get_mut(id).expect("checked above");
+        entry.sha256 = Some(actual);
+        entry.verification = status;
+        self.save_catalog()?;
+        Ok(status)
+    }
+
+    /// Removes `id`'s file from disk and drops it from the catalog. Returns
+    /// `false` without touching anything if `id` isn't registered.
+    pub fn remove(&mut self, id: &str) -> std::io::Result<bool> {
+        let Some(entry) = self.entries.remove(id) else { return Ok(false) };
+        if let Err(e) = fs::remove_file(&entry.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                self.entries.insert(entry.id.clone(), entry);
+                return Err(e);
+            }
+        }
+        self.save_catalog()?;
+        Ok(true)
+    }
+
+    /// Attaches (or, with `None`, clears) `id`'s named default sampling
+    /// values, persisting the change so it survives a restart the same way
+    /// `verify`'s outcome does. Returns an error if `id` isn't registered
+    /// rather than silently creating an entry, matching `verify`'s own
+    /// validation.
+    pub fn set_preset(&mut self, id: &str, preset: Option<GenerationPreset>) -> std::io::Result<()> {
+        let entry = self
+            .entries
+            .get_mut(id)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("no such model {id:?}")))?;
+        entry.preset = preset;
+        self.save_catalog()
+    }
+
+    /// Attaches `id`'s hard sampling-value clamps, persisted the same way
+    /// [`set_preset`](Self::set_preset) is. Errors if `id` isn't
+    /// registered.
+    pub fn set_clamps(&mut self, id: &str, clamps: GenerationClamps) -> std::io::Result<()> {
+        let entry = self
+            .entries
+            .get_mut(id)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("no such model {id:?}")))?;
+        entry.clamps = clamps;
+        self.save_catalog()
+    }
+}
+
+/// Serializes a [`GenerationPreset`] for `catalog.json`, mirroring
+/// [`parse_preset`].
+fn preset_to_json(preset: &GenerationPreset) -> Json {
+    let mut builder = ObjectBuilder::new().set("name", Json::String(preset.name.clone()));
+    if let Some(temperature) = preset.temperature {
+        builder = builder.set("temperature", Json::Number(temperature as f64));
+    }
+    if let Some(top_p) = preset.top_p {
+        builder = builder.set("top_p", Json::Number(top_p as f64));
+    }
+    if let Some(repetition_penalty) = preset.repetition_penalty {
+        builder = builder.set("repetition_penalty", Json::Number(repetition_penalty as f64));
+    }
+    if !preset.stop.is_empty() {
+        builder = builder.set("stop", Json::Array(preset.stop.iter().cloned().map(Json::String).collect()));
+    }
+    builder.build()
+}
+
+/// Parses a `catalog.json` `"preset"` object written by [`preset_to_json`].
+/// Missing optional fields default the same way they do for a brand-new
+/// [`GenerationPreset`], rather than rejecting the entry.
+fn parse_preset(json: &Json) -> GenerationPreset {
+    GenerationPreset {
+        name: json.get("name").and_then(Json::as_str).unwrap_or_default().to_string(),
+        temperature: json.get("temperature").and_then(Json::as_f64).map(|v| v as f32),
+        top_p: json.get("top_p").and_then(Json::as_f64).map(|v| v as f32),
+        repetition_penalty: json.get("repetition_penalty").and_then(Json::as_f64).map(|v| v as f32),
+        stop: json
+            .get("stop")
+            .and_then(Json::as_array)
+            .map(|items| items.iter().filter_map(Json::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Serializes a [`GenerationClamps`] for `catalog.json`, mirroring
+/// [`parse_clamps`]. Each bound is written as a two-element `[min, max]`
+/// array, or omitted entirely when unset.
+fn clamps_to_json(clamps: &GenerationClamps) -> Json {
+    fn bound(pair: Option<(f32, f32)>) -> Option<Json> {
+        pair.map(|(min, max)| Json::Array(vec![Json::Number(min as f64), Json::Number(max as f64)]))
+    }
+    let mut builder = ObjectBuilder::new();
+    if let Some(temperature) = bound(clamps.temperature) {
+        builder = builder.set("temperature", temperature);
+    }
+    if let Some(top_p) = bound(clamps.top_p) {
+        builder = builder.set("top_p", top_p);
+    }
+    if let Some(repetition_penalty) = bound(clamps.repetition_penalty) {
+        builder = builder.set("repetition_penalty", repetition_penalty);
+    }
+    builder.build()
+}
+
+/// Parses a `catalog.json` `"clamps"` object written by [`clamps_to_json`].
+fn parse_clamps(json: &Json) -> GenerationClamps {
+    fn bound(json: &Json, key: &str) -> Option<(f32, f32)> {
+        let pair = json.get(key)?.as_array()?;
+        let min = pair.first()?.as_f64()? as f32;
+        let max = pair.get(1)?.as_f64()? as f32;
+        Some((min, max))
+    }
+    GenerationClamps { temperature: bound(json, "temperature"), top_p: bound(json, "top_p"), repetition_penalty: bound(json, "repetition_penalty") }
+}
+
+/// Best-effort read of `path`'s GGUF header for the fields `/v1/models`
+/// wants to advertise. Returns the "unknown" triple rather than an error
+/// for anything that isn't a readable GGUF file — a model an operator
+/// dropped in as a raw binary, or one this parser can't read yet,
+/// shouldn't stop it from being registered.
+fn gguf_summary(path: &Path) -> (Option<u64>, RopeScaling, f64) {
+    match GgufModel::open(path) {
+        Ok(model) => {
+            let summary = model.inspect();
+            (summary.context_length, summary.rope_scaling, summary.rope_scaling_factor)
+        }
+        Err(_) => (None, RopeScaling::None, 1.0),
+    }
+}
+
+/// Runs `minisign -V` against `file`'s detached signature, returning
+/// whether it verified. Errors (rather than returning `false`) when the
+/// `minisign` binary itself is missing or fails to start, since that means
+/// no check actually happened — `verify` treats that the same as a failed
+/// I/O read, not as a passed or failed signature.
+fn verify_minisign(file: &Path, check: &SignatureCheck) -> std::io::Result<bool> {
+    let output = Command::new("minisign")
+        .arg("-V")
+        .arg("-p")
+        .arg(check.public_key_path)
+        .arg("-x")
+        .arg(check.signature_path)
+        .arg("-m")
+        .arg(file)
+        .output()
+        .map_err(|e| std::io::Error::new(e.kind(), format!("running minisign: {e}")))?;
+    Ok(output.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-registry-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rescan_finds_gguf_files_and_derives_ids_from_filename() {
+        let dir = temp_dir("rescan");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("llama-7b.gguf"), b"fake").unwrap();
+        fs::write(dir.join("notes.txt"), b"ignore me").unwrap();
+
+        let registry = ModelRegistry::open(&dir).unwrap();
+        let entry = registry.resolve("llama-7b").unwrap();
+        assert_eq!(entry.size_bytes, 4);
+        assert_eq!(registry.list().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn catalog_persists_across_reopen_without_rescanning() {
+        let dir = temp_dir("persist");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("model-a.gguf"), b"12345").unwrap();
+        {
+            let _registry = ModelRegistry::open(&dir).unwrap();
+        }
+        // Delete the file but keep the catalog: reopening should still see
+        // the cached entry since it doesn't rescan when catalog.json loads.
+        fs::remove_file(dir.join("model-a.gguf")).unwrap();
+
+        let registry = ModelRegistry::open(&dir).unwrap();
+        assert!(registry.resolve("model-a").is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn register_adds_entry_and_persists_it() {
+        let dir = temp_dir("register");
+        fs::create_dir_all(&dir).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+        registry.register("custom", dir.join("custom.gguf"), 42).unwrap();
+
+        let reopened = ModelRegistry::open(&dir).unwrap();
+        assert_eq!(reopened.resolve("custom").unwrap().size_bytes, 42);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn verify_records_verified_when_the_hash_matches() {
+        let dir = temp_dir("verify-ok");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("model-a.gguf"), b"12345").unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+        let expected = hex(&sha256(b"12345"));
+
+        let status = registry.verify("model-a", &expected, None).unwrap();
+        assert_eq!(status, VerificationStatus::Verified);
+        assert_eq!(registry.resolve("model-a").unwrap().verification, VerificationStatus::Verified);
+        assert_eq!(registry.resolve("model-a").unwrap().sha256.as_deref(), Some(expected.as_str()));
+
+        // Persisted, not just held in memory.
+        let reopened = ModelRegistry::open(&dir).unwrap();
+        assert_eq!(reopened.resolve("model-a").unwrap().verification, VerificationStatus::Verified);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn verify_records_checksum_mismatch_without_touching_the_file() {
+        let dir = temp_dir("verify-mismatch");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("model-a.gguf"), b"12345").unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+
+        let status = registry.verify("model-a", "deadbeef", None).unwrap();
+        assert_eq!(status, VerificationStatus::ChecksumMismatch);
+        assert_eq!(registry.resolve("model-a").unwrap().verification, VerificationStatus::ChecksumMismatch);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn verify_errors_for_an_unregistered_id() {
+        let dir = temp_dir("verify-missing");
+        fs::create_dir_all(&dir).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+        assert!(registry.verify("nope", "deadbeef", None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A minimal but well-formed GGUF file declaring `llama.context_length`
+    /// and no RoPE scaling, for exercising `gguf_summary` without needing a
+    /// real model on disk.
+    fn gguf_bytes(context_length: u32) -> Vec<u8> {
+        fn write_string(buf: &mut Vec<u8>, s: &str) {
+            buf.extend_from_slice(&(s.len() as u64).to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0x4655_4747u32.to_le_bytes()); // "GGUF" magic
+        buf.extend_from_slice(&3u32.to_le_bytes()); // version
+        buf.extend_from_slice(&0u64.to_le_bytes()); // tensor_count
+        buf.extend_from_slice(&2u64.to_le_bytes()); // kv_count
+        write_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes()); // string
+        write_string(&mut buf, "llama");
+        write_string(&mut buf, "llama.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes()); // uint32
+        buf.extend_from_slice(&context_length.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn rescan_reads_context_length_from_a_gguf_files_header() {
+        let dir = temp_dir("rescan-gguf");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("llama-7b.gguf"), gguf_bytes(4096)).unwrap();
+
+        let registry = ModelRegistry::open(&dir).unwrap();
+        let entry = registry.resolve("llama-7b").unwrap();
+        assert_eq!(entry.context_length, Some(4096));
+        assert_eq!(entry.rope_scaling, RopeScaling::None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rescan_defaults_to_unknown_context_length_for_a_non_gguf_file() {
+        let dir = temp_dir("rescan-non-gguf");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bogus.gguf"), b"not a real gguf file").unwrap();
+
+        let registry = ModelRegistry::open(&dir).unwrap();
+        let entry = registry.resolve("bogus").unwrap();
+        assert_eq!(entry.context_length, None);
+        assert_eq!(entry.rope_scaling, RopeScaling::None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn context_length_and_rope_scaling_survive_a_catalog_round_trip() {
+        let dir = temp_dir("catalog-rope");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("llama-7b.gguf"), gguf_bytes(4096)).unwrap();
+        {
+            let _registry = ModelRegistry::open(&dir).unwrap();
+        }
+
+        // Reopening loads from catalog.json rather than rescanning, so this
+        // also confirms `save_catalog`/`load_catalog` round-trip the fields.
+        let reopened = ModelRegistry::open(&dir).unwrap();
+        assert_eq!(reopened.resolve("llama-7b").unwrap().context_length, Some(4096));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remove_deletes_the_file_and_forgets_the_entry() {
+        let dir = temp_dir("remove");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("gone.gguf"), b"bytes").unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+
+        assert!(registry.remove("gone").unwrap());
+        assert!(registry.resolve("gone").is_none());
+        assert!(!dir.join("gone.gguf").exists());
+        assert!(!registry.remove("gone").unwrap()); // already gone
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn set_preset_and_clamps_persist_across_a_reopen() {
+        let dir = temp_dir("preset-persist");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("model-a.gguf"), b"12345").unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+
+        let preset = GenerationPreset {
+            name: "creative".to_string(),
+            temperature: Some(1.2),
+            top_p: Some(0.95),
+            repetition_penalty: None,
+            stop: vec!["\n\n".to_string()],
+        };
+        registry.set_preset("model-a", Some(preset.clone())).unwrap();
+        registry.set_clamps("model-a", GenerationClamps { temperature: Some((0.0, 2.0)), ..Default::default() }).unwrap();
+
+        let reopened = ModelRegistry::open(&dir).unwrap();
+        let entry = reopened.resolve("model-a").unwrap();
+        assert_eq!(entry.preset, Some(preset));
+        assert_eq!(entry.clamps, GenerationClamps { temperature: Some((0.0, 2.0)), ..Default::default() });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn set_preset_errors_for_an_unregistered_id() {
+        let dir = temp_dir("preset-missing");
+        fs::create_dir_all(&dir).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+        assert!(registry.set_preset("nope", None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
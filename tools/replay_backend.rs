@@ -0,0 +1,304 @@
+//! Record-and-replay [`InferenceBackend`](crate::InferenceBackend) for
+//! fast integration tests: a scheduler/API test that only needs to know
+//! *that* a backend was called with a given prompt, not what a real model
+//! would have said, currently has no way to avoid depending on `EchoBackend`
+//! specifically. [`RecordingBackend`] wraps any real backend and appends
+//! one [`RecordedInteraction`] per call to a JSON-lines file (the same
+//! append-only shape `audit::AuditLogger`/`usage::UsageStore` already
+//! write); [`ReplayBackend`] then serves those same interactions back
+//! without needing the wrapped backend, its weights, or its device at all.
+//!
+//! Interactions are matched to a later `generate`/`stream` call by exact
+//! prompt text, queued in the order they were recorded — a test that
+//! issues the same prompt twice gets its two recorded responses back in
+//! order, rather than one merged or randomly chosen. A prompt the log
+//! never saw is a test-authoring bug, not a runtime condition to recover
+//! from, so [`ReplayBackend::generate`] panics on one rather than
+//! returning a confusing default.
+//!
+//! Config-selectable via `[replay]` in `config.rs`: `replay.mode`
+//! (`"record"` or `"replay"`) plus `replay.file`, the same
+//! override-a-default shape `backend.override` uses for `backend::select`.
+
+use crate::json::{Json, ObjectBuilder};
+use crate::InferenceBackend;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// One recorded backend call: the prompt it was given, the token chunks it
+/// produced (streamed one at a time, or the whole response as a single
+/// entry for a buffered `generate`), and how long it took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedInteraction {
+    pub prompt: String,
+    pub tokens: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl RecordedInteraction {
+    fn to_json(&self) -> Json {
+        ObjectBuilder::new()
+            .set("prompt", Json::String(self.prompt.clone()))
+            .set("tokens", Json::Array(self.tokens.iter().cloned().map(Json::String).collect()))
+            .set("duration_ms", Json::Number(self.duration_ms as f64))
+            .build()
+    }
+
+    fn from_json(parsed: &Json) -> Option<RecordedInteraction> {
+        let tokens = parsed
+            .get("tokens")
+            .and_then(Json::as_array)?
+            .iter()
+            .map(|t| t.as_str().map(str::to_string))
+            .collect::<Option<Vec<String>>>()?;
+        Some(RecordedInteraction {
+            prompt: parsed.get("prompt").and_then(Json::as_str)?.to_string(),
+            tokens,
+            duration_ms: parsed.get("duration_ms").and_then(Json::as_f64)? as u64,
+        })
+    }
+}
+
+/// Wraps `inner`, forwarding every call unchanged while appending a
+/// [`RecordedInteraction`] to `path` for each one. `stream`'s recorded
+/// `tokens` are exactly the chunks `inner` handed to `on_token`, so replay
+/// reproduces the same chunk boundaries a streamed HTTP/SSE response
+/// actually saw.
+pub struct RecordingBackend<'a> {
+    inner: &'a dyn InferenceBackend,
+    log: Mutex<File>,
+}
+
+impl<'a> RecordingBackend<'a> {
+    pub fn open(inner: &'a dyn InferenceBackend, path: impl AsRef<Path>) -> std::io::Result<RecordingBackend<'a>> {
+        let file = OpenOptions::new().create(true).append(true).open(path.as_ref())?;
+        Ok(RecordingBackend { inner, log: Mutex::new(file) })
+    }
+
+    fn append(&self, interaction: &RecordedInteraction) {
+        let mut file = self.log.lock().unwrap();
+        let _ = writeln!(file, "{}", interaction.to_json().to_string());
+    }
+}
+
+impl<'a> InferenceBackend for RecordingBackend<'a> {
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+
+    fn generate(&self, prompt: &str) -> String {
+        let started = Instant::now();
+        let response = self.inner.generate(prompt);
+        self.append(&RecordedInteraction {
+            prompt: prompt.to_string(),
+            tokens: vec![response.clone()],
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        response
+    }
+
+    fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+        let started = Instant::now();
+        let mut tokens = Vec::new();
+        self.inner.stream(prompt, &mut |token| {
+            tokens.push(token.to_string());
+            on_token(token)
+        });
+        self.append(&RecordedInteraction { prompt: prompt.to_string(), tokens, duration_ms: started.elapsed().as_millis() as u64 });
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// A line in the replay file wasn't a well-formed [`RecordedInteraction`].
+    Malformed(String),
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// Serves [`RecordedInteraction`]s read from a file written by
+/// [`RecordingBackend`], with no dependency on whatever backend originally
+/// produced them — the point being that a scheduler/API test links this
+/// instead of a real weight-loading backend.
+pub struct ReplayBackend {
+    id: String,
+    queued: Mutex<HashMap<String, VecDeque<RecordedInteraction>>>,
+}
+
+impl ReplayBackend {
+    /// Loads every recorded interaction from `path`, keyed by prompt in
+    /// the order each prompt's recordings appeared in the file.
+    pub fn open(id: &str, path: impl AsRef<Path>) -> Result<ReplayBackend, ReplayError> {
+        let file = File::open(path)?;
+        let mut queued: HashMap<String, VecDeque<RecordedInteraction>> = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = Json::parse(&line).map_err(|_| ReplayError::Malformed(line.clone()))?;
+            let interaction = RecordedInteraction::from_json(&parsed).ok_or_else(|| ReplayError::Malformed(line.clone()))?;
+            queued.entry(interaction.prompt.clone()).or_default().push_back(interaction);
+        }
+        Ok(ReplayBackend { id: id.to_string(), queued: Mutex::new(queued) })
+    }
+
+    /// Pops the next recorded interaction for `prompt`, panicking if the
+    /// replay file never saw it — a test driving this backend with a
+    /// prompt its fixture doesn't cover needs its fixture fixed, not a
+    /// silent fallback that would mask the gap.
+    fn next_for(&self, prompt: &str) -> RecordedInteraction {
+        let mut queued = self.queued.lock().unwrap();
+        let entry = queued.get_mut(prompt).filter(|q| !q.is_empty());
+        match entry.and_then(VecDeque::pop_front) {
+            Some(interaction) => interaction,
+            None => panic!("replay backend has no recorded interaction left for prompt {prompt:?}"),
+        }
+    }
+}
+
+impl InferenceBackend for ReplayBackend {
+    fn model_id(&self) -> &str {
+        &self.id
+    }
+
+    fn generate(&self, prompt: &str) -> String {
+        self.next_for(prompt).tokens.concat()
+    }
+
+    fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+        for token in self.next_for(prompt).tokens {
+            if !on_token(&token) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EchoBackend;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("ai-server-replay-test-{name}-{:x}.jsonl", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]))
+    }
+
+    #[test]
+    fn recording_backend_forwards_generate_to_the_wrapped_backend() {
+        let path = temp_path("generate-forwards");
+        let inner = EchoBackend::new("m");
+        let recorder = RecordingBackend::open(&inner, &path).unwrap();
+        assert_eq!(recorder.generate("hi"), "echo: hi");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn recording_backend_appends_one_line_per_generate_call() {
+        let path = temp_path("appends-generate");
+        let inner = EchoBackend::new("m");
+        let recorder = RecordingBackend::open(&inner, &path).unwrap();
+        recorder.generate("hello");
+        recorder.generate("world");
+
+        let lines: Vec<String> = BufReader::new(File::open(&path).unwrap()).lines().map_while(Result::ok).collect();
+        assert_eq!(lines.len(), 2);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn recording_backend_records_each_streamed_chunk() {
+        let path = temp_path("appends-stream");
+        let inner = EchoBackend::new("m");
+        let recorder = RecordingBackend::open(&inner, &path).unwrap();
+        let mut chunks = Vec::new();
+        recorder.stream("say hi", &mut |t| {
+            chunks.push(t.to_string());
+            true
+        });
+
+        let interactions = read_all(&path);
+        assert_eq!(interactions.len(), 1);
+        assert_eq!(interactions[0].tokens, chunks);
+        std::fs::remove_file(&path).ok();
+    }
+
+    fn read_all(path: &Path) -> Vec<RecordedInteraction> {
+        BufReader::new(File::open(path).unwrap())
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| RecordedInteraction::from_json(&Json::parse(&line).ok()?))
+            .collect()
+    }
+
+    #[test]
+    fn replay_backend_reproduces_a_recorded_generate_response() {
+        let path = temp_path("replay-generate");
+        let inner = EchoBackend::new("m");
+        {
+            let recorder = RecordingBackend::open(&inner, &path).unwrap();
+            recorder.generate("hi there");
+        }
+        let replay = ReplayBackend::open("m", &path).unwrap();
+        assert_eq!(replay.generate("hi there"), "echo: hi there");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn replay_backend_reproduces_recorded_stream_chunks_in_order() {
+        let path = temp_path("replay-stream");
+        let inner = EchoBackend::new("m");
+        {
+            let recorder = RecordingBackend::open(&inner, &path).unwrap();
+            recorder.stream("say hi", &mut |_| true);
+        }
+        let replay = ReplayBackend::open("m", &path).unwrap();
+        let mut chunks = Vec::new();
+        replay.stream("say hi", &mut |t| {
+            chunks.push(t.to_string());
+            true
+        });
+        assert_eq!(chunks, vec!["echo: ", "say ", "hi "]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn replay_backend_serves_repeated_prompts_in_recorded_order() {
+        let path = temp_path("replay-repeated");
+        {
+            let mut file = OpenOptions::new().create(true).append(true).open(&path).unwrap();
+            writeln!(file, "{}", RecordedInteraction { prompt: "x".to_string(), tokens: vec!["first".to_string()], duration_ms: 1 }.to_json().to_string()).unwrap();
+            writeln!(file, "{}", RecordedInteraction { prompt: "x".to_string(), tokens: vec!["second".to_string()], duration_ms: 1 }.to_json().to_string()).unwrap();
+        }
+        let replay = ReplayBackend::open("m", &path).unwrap();
+        assert_eq!(replay.generate("x"), "first");
+        assert_eq!(replay.generate("x"), "second");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    #[should_panic(expected = "no recorded interaction left")]
+    fn replay_backend_panics_on_a_prompt_the_fixture_never_recorded() {
+        let path = temp_path("replay-missing");
+        std::fs::write(&path, "").unwrap();
+        let replay = ReplayBackend::open("m", &path).unwrap();
+        replay.generate("never recorded");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn replay_backend_open_reports_io_error_for_a_missing_file() {
+        let result = ReplayBackend::open("m", "/nonexistent/path/does-not-exist.jsonl");
+        assert!(matches!(result, Err(ReplayError::Io(_))));
+    }
+}
@@ -0,0 +1,145 @@
+//! Cross-encoder reranking: scores a `(query, document)` pair jointly,
+//! rather than comparing two separately-pooled vectors the way
+//! `embeddings.rs`'s bi-encoder path does. `/v1/rerank` and the optional
+//! rerank stage in `rag::assemble_prompt` both reorder a candidate set that
+//! vector similarity already narrowed down — plain cosine distance on
+//! pooled embeddings misses subtleties (exact identifier matches, negation,
+//! code vs. prose) that scoring the pair jointly picks up on.
+
+use crate::embeddings::EmbeddingBackend;
+
+/// A model backend that scores how relevant `document` is to `query`,
+/// reading the two jointly rather than as separately-pooled vectors — the
+/// distinguishing trait of a cross-encoder versus the bi-encoder
+/// [`EmbeddingBackend`] uses. Higher scores mean more relevant; the scale
+/// is backend-defined, so `score_threshold` in [`RerankRequest`] is only
+/// meaningful relative to one backend's own scores.
+pub trait RerankBackend: Send + Sync {
+    fn score(&self, query: &[u32], document: &[u32]) -> f32;
+}
+
+/// Adapts any [`EmbeddingBackend`] into a [`RerankBackend`] by pooling the
+/// concatenated `query + separator + document` token sequence and summing
+/// the result — not a real cross-encoder architecture (there's no joint
+/// attention between query and document tokens the way a trained
+/// cross-encoder has), but it lets a bi-encoder-only backend like
+/// [`crate::EchoBackend`] answer `/v1/rerank` requests before a dedicated
+/// cross-encoder runtime is wired in, the same "prove the contract, not
+/// the quality" role `EchoBackend` plays everywhere else in this server.
+pub struct EmbeddingRerankBackend<'a> {
+    pub embedding_backend: &'a dyn EmbeddingBackend,
+}
+
+impl<'a> RerankBackend for EmbeddingRerankBackend<'a> {
+    fn score(&self, query: &[u32], document: &[u32]) -> f32 {
+        // 256 is outside the 0..=255 range every byte-as-token caller in
+        // this tree produces (see `rag.rs`/`embeddings.rs`'s
+        // `text.bytes().map(u32::from)` pattern), so it reads as a
+        // distinct separator without needing a value large enough to risk
+        // overflowing a backend's own token-summing math.
+        const SEPARATOR: u32 = 256;
+        let mut tokens = Vec::with_capacity(query.len() + document.len() + 1);
+        tokens.extend_from_slice(query);
+        tokens.push(SEPARATOR);
+        tokens.extend_from_slice(document);
+        let hidden_states = self.embedding_backend.hidden_states(&tokens);
+        if hidden_states.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = hidden_states.iter().flatten().sum();
+        let count = hidden_states.iter().map(Vec::len).sum::<usize>().max(1);
+        sum / count as f32
+    }
+}
+
+/// `top_n`/`score_threshold` as requested by a caller — both optional,
+/// mirroring how [`crate::embeddings::EmbeddingRequest::dimensions`] is an
+/// optional post-processing knob rather than a required field.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RerankRequest {
+    /// Keep only the `top_n` highest-scoring documents. `None` keeps all
+    /// of them (after `score_threshold` filtering, if set).
+    pub top_n: Option<usize>,
+    /// Drop any document scoring below this threshold before `top_n` is
+    /// applied.
+    pub score_threshold: Option<f32>,
+}
+
+/// One document's rerank outcome, keyed back to its position in the
+/// caller's original `documents` slice so a caller can still show which
+/// original document each result came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerankResult {
+    pub index: usize,
+    pub score: f32,
+}
+
+/// Scores every document in `documents` against `query`, drops anything
+/// below `params.score_threshold`, sorts by score descending, and truncates
+/// to `params.top_n`.
+pub fn rerank(backend: &dyn RerankBackend, query: &[u32], documents: &[Vec<u32>], params: &RerankRequest) -> Vec<RerankResult> {
+    let mut results: Vec<RerankResult> = documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| RerankResult { index, score: backend.score(query, document) })
+        .filter(|r| params.score_threshold.map_or(true, |threshold| r.score >= threshold))
+        .collect();
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some(top_n) = params.top_n {
+        results.truncate(top_n);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SumScoreBackend;
+    impl RerankBackend for SumScoreBackend {
+        fn score(&self, _query: &[u32], document: &[u32]) -> f32 {
+            document.iter().sum::<u32>() as f32
+        }
+    }
+
+    fn docs() -> Vec<Vec<u32>> {
+        vec![vec![1, 1], vec![9, 9], vec![5, 5]]
+    }
+
+    #[test]
+    fn rerank_sorts_by_score_descending() {
+        let results = rerank(&SumScoreBackend, &[], &docs(), &RerankRequest::default());
+        assert_eq!(results.iter().map(|r| r.index).collect::<Vec<_>>(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn top_n_truncates_after_sorting() {
+        let results = rerank(&SumScoreBackend, &[], &docs(), &RerankRequest { top_n: Some(2), score_threshold: None });
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].index, 1);
+        assert_eq!(results[1].index, 2);
+    }
+
+    #[test]
+    fn score_threshold_drops_low_scoring_documents() {
+        let results = rerank(&SumScoreBackend, &[], &docs(), &RerankRequest { top_n: None, score_threshold: Some(15.0) });
+        assert_eq!(results.iter().map(|r| r.index).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn embedding_rerank_backend_scores_using_the_wrapped_backend() {
+        struct StubEmbeddingBackend;
+        impl EmbeddingBackend for StubEmbeddingBackend {
+            fn hidden_size(&self) -> usize {
+                2
+            }
+            fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+                tokens.iter().map(|&t| vec![t as f32, t as f32]).collect()
+            }
+        }
+        let embedding_backend = StubEmbeddingBackend;
+        let backend = EmbeddingRerankBackend { embedding_backend: &embedding_backend };
+        let score = backend.score(&[1], &[2]);
+        assert!(score > 0.0);
+    }
+}
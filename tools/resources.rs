@@ -0,0 +1,237 @@
+//! Per-request memory budgeting and admission control. A backend's weights
+//! plus one request's KV cache can be estimated before generation starts
+//! (see [`ModelMemoryProfile`]), so [`MemoryBudget`] can reject a request
+//! that would push the process past a configurable ceiling instead of
+//! letting the allocator find out the hard way — a single long-context
+//! request is otherwise enough to OOM the whole box.
+//!
+//! There's no request queue anywhere in this tree yet (`scheduler.rs`
+//! exists but isn't wired into the HTTP layer), so "queue until room frees
+//! up" isn't available here — [`MemoryBudget::try_admit`] rejects
+//! immediately. Once `scheduler.rs` sits in front of a real backend, its
+//! admission step is the natural place to retry a request that only
+//! failed because the budget was momentarily full.
+//!
+//! [`ModelMemoryProfile::gpu_layers_for_budget`] does the same kind of
+//! sizing for partial GPU offload: how many of a model's layers fit in a
+//! given amount of device memory, so a 13B+ model can still run — some
+//! layers on GPU, the rest on CPU — on a host too small to fit the whole
+//! thing on one device. `config::ServerConfig::n_gpu_layers_override` lets
+//! an operator override that count, the same way `backend_override`
+//! overrides `backend::select`'s own choice.
+
+use crate::hardware::HardwareProfile;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The KV-cache-relevant shape of a loaded model, used to estimate memory
+/// for a request of a given context length. A real backend derives these
+/// from GGUF metadata (`<arch>.block_count`, `<arch>.attention.head_count_kv`,
+/// `<arch>.embedding_length` divided by head count); `EchoBackend` has no
+/// weights at all, so it reports no profile and never triggers admission
+/// checks (see `InferenceBackend::memory_profile`'s default in `server.rs`).
+#[derive(Debug, Clone, Copy)]
+pub struct ModelMemoryProfile {
+    pub weights_bytes: u64,
+    pub num_layers: usize,
+    pub num_kv_heads: usize,
+    pub head_dim: usize,
+    /// Bytes per KV cache scalar, e.g. `2` for f16, `4` for f32.
+    pub bytes_per_kv_element: usize,
+}
+
+impl ModelMemoryProfile {
+    /// Bytes of KV cache one additional context token costs: one key and
+    /// one value vector per layer, each `num_kv_heads * head_dim` wide.
+    pub fn kv_cache_bytes_per_token(&self) -> u64 {
+        2 * self.num_layers as u64 * self.num_kv_heads as u64 * self.head_dim as u64 * self.bytes_per_kv_element as u64
+    }
+
+    /// Total memory a request needs: the model's resident weights (shared
+    /// across concurrent requests in reality, but charged per-request here
+    /// since this tree has no cross-request accounting yet) plus this
+    /// request's KV cache at `context_tokens`.
+    pub fn estimated_request_bytes(&self, context_tokens: usize) -> u64 {
+        self.weights_bytes + self.kv_cache_bytes_per_token() * context_tokens as u64
+    }
+
+    /// Bytes one transformer layer's weights occupy, assuming every layer
+    /// is roughly the same size — true enough to size a GPU offload split
+    /// by, though it slightly misattributes the embedding/output layers'
+    /// weight to the average rather than splitting them out separately.
+    pub fn bytes_per_layer(&self) -> u64 {
+        self.weights_bytes / self.num_layers.max(1) as u64
+    }
+
+    /// How many of this model's layers fit in `gpu_memory_bytes` of device
+    /// memory — the `n_gpu_layers` llama.cpp's own partial-offload
+    /// convention expects: a count of layers to place on GPU, not which
+    /// ones. Never exceeds `num_layers`, so a budget bigger than the whole
+    /// model needs doesn't ask to offload more layers than there are.
+    pub fn gpu_layers_for_budget(&self, gpu_memory_bytes: u64) -> usize {
+        let per_layer = self.bytes_per_layer();
+        if per_layer == 0 {
+            return self.num_layers;
+        }
+        ((gpu_memory_bytes / per_layer) as usize).min(self.num_layers)
+    }
+
+    /// [`gpu_layers_for_budget`](Self::gpu_layers_for_budget)'s
+    /// budget-driven count, unless `override_layers` gives its own — an
+    /// operator may want fewer layers offloaded than the budget allows (to
+    /// leave GPU memory for something else) or more than this estimate's
+    /// uniform-layer-size approximation would allow, having measured their
+    /// own box. Clamped to `num_layers` either way, since asking to
+    /// offload more layers than the model has doesn't mean anything.
+    pub fn effective_gpu_layers(&self, gpu_memory_bytes: u64, override_layers: Option<usize>) -> usize {
+        match override_layers {
+            Some(layers) => layers.min(self.num_layers),
+            None => self.gpu_layers_for_budget(gpu_memory_bytes),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AdmissionError {
+    /// `requested` exceeds the budget's total capacity outright — no
+    /// amount of waiting makes this request fit.
+    ExceedsCapacity { requested: u64, capacity: u64 },
+    /// `requested` fits under capacity but not under what's currently
+    /// admitted — a transient condition that clears as other requests finish.
+    BudgetExhausted { requested: u64, available: u64 },
+}
+
+/// Tracks how much of a fixed memory ceiling is currently spoken for.
+/// `try_admit`/`release` are the only mutators, called once per request at
+/// the start and end of generation.
+pub struct MemoryBudget {
+    capacity_bytes: u64,
+    admitted_bytes: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn new(capacity_bytes: u64) -> Self {
+        MemoryBudget { capacity_bytes, admitted_bytes: AtomicU64::new(0) }
+    }
+
+    /// Builds a budget from a fraction of the host's available memory,
+    /// leaving the rest for the OS, other processes, and the estimate's
+    /// own error margin. `HardwareProfile::probe` supplies the available
+    /// bytes; hosts where that isn't detectable (see `hardware.rs`'s
+    /// `MemoryInfo`) get a zero-capacity budget, which rejects every
+    /// request that reports a profile rather than silently allowing OOM.
+    pub fn from_hardware_profile(profile: &HardwareProfile, fraction: f64) -> Self {
+        let available = profile.memory.available_bytes.unwrap_or(0);
+        MemoryBudget::new((available as f64 * fraction.clamp(0.0, 1.0)) as u64)
+    }
+
+    /// Reserves `bytes` if doing so wouldn't exceed capacity. On success,
+    /// the caller must call [`release`](Self::release) with the same
+    /// value once the request finishes.
+    pub fn try_admit(&self, bytes: u64) -> Result<(), AdmissionError> {
+        if bytes > self.capacity_bytes {
+            return Err(AdmissionError::ExceedsCapacity { requested: bytes, capacity: self.capacity_bytes });
+        }
+        loop {
+            let admitted = self.admitted_bytes.load(Ordering::Acquire);
+            let available = self.capacity_bytes - admitted;
+            if bytes > available {
+                return Err(AdmissionError::BudgetExhausted { requested: bytes, available });
+            }
+            if self
+                .admitted_bytes
+                .compare_exchange(admitted, admitted + bytes, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn release(&self, bytes: u64) {
+        self.admitted_bytes.fetch_sub(bytes, Ordering::AcqRel);
+    }
+
+    pub fn admitted_bytes(&self) -> u64 {
+        self.admitted_bytes.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> ModelMemoryProfile {
+        ModelMemoryProfile { weights_bytes: 1_000_000, num_layers: 32, num_kv_heads: 8, head_dim: 128, bytes_per_kv_element: 2 }
+    }
+
+    #[test]
+    fn kv_cache_bytes_per_token_accounts_for_key_and_value() {
+        // 2 (k+v) * 32 layers * 8 heads * 128 dim * 2 bytes = 131072 bytes/token.
+        assert_eq!(profile().kv_cache_bytes_per_token(), 131_072);
+    }
+
+    #[test]
+    fn estimated_request_bytes_adds_weights_and_scaled_kv_cache() {
+        let bytes = profile().estimated_request_bytes(10);
+        assert_eq!(bytes, 1_000_000 + 131_072 * 10);
+    }
+
+    #[test]
+    fn bytes_per_layer_divides_weights_evenly_across_layers() {
+        // 1_000_000 / 32 layers = 31_250 bytes/layer.
+        assert_eq!(profile().bytes_per_layer(), 31_250);
+    }
+
+    #[test]
+    fn gpu_layers_for_budget_fits_as_many_layers_as_the_budget_allows() {
+        let p = profile();
+        assert_eq!(p.gpu_layers_for_budget(31_250 * 10), 10);
+    }
+
+    #[test]
+    fn gpu_layers_for_budget_never_exceeds_the_models_own_layer_count() {
+        let p = profile();
+        assert_eq!(p.gpu_layers_for_budget(u64::MAX), 32);
+    }
+
+    #[test]
+    fn effective_gpu_layers_falls_back_to_the_budget_driven_count_with_no_override() {
+        let p = profile();
+        assert_eq!(p.effective_gpu_layers(31_250 * 10, None), 10);
+    }
+
+    #[test]
+    fn effective_gpu_layers_honors_an_operator_override() {
+        let p = profile();
+        assert_eq!(p.effective_gpu_layers(0, Some(20)), 20);
+    }
+
+    #[test]
+    fn effective_gpu_layers_clamps_an_override_larger_than_the_model() {
+        let p = profile();
+        assert_eq!(p.effective_gpu_layers(0, Some(9_999)), 32);
+    }
+
+    #[test]
+    fn try_admit_rejects_requests_that_exceed_total_capacity() {
+        let budget = MemoryBudget::new(1_000);
+        let err = budget.try_admit(2_000).unwrap_err();
+        assert_eq!(err, AdmissionError::ExceedsCapacity { requested: 2_000, capacity: 1_000 });
+    }
+
+    #[test]
+    fn try_admit_rejects_when_budget_is_currently_exhausted_but_would_fit_alone() {
+        let budget = MemoryBudget::new(1_000);
+        budget.try_admit(800).unwrap();
+        let err = budget.try_admit(500).unwrap_err();
+        assert_eq!(err, AdmissionError::BudgetExhausted { requested: 500, available: 200 });
+    }
+
+    #[test]
+    fn release_frees_capacity_for_a_later_admission() {
+        let budget = MemoryBudget::new(1_000);
+        budget.try_admit(800).unwrap();
+        budget.release(800);
+        assert!(budget.try_admit(900).is_ok());
+    }
+}
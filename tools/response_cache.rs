@@ -0,0 +1,269 @@
+//! Deterministic-response cache: `/v1/completions` and the buffered path
+//! of `/v1/chat/completions` are only reproducible byte-for-byte when the
+//! caller pins `temperature` to `0` and supplies a fixed `seed` — anything
+//! else and re-running the same prompt is expected to generate a
+//! different completion. Evaluation harnesses re-run the exact same
+//! deterministic prompts constantly, so caching those (and only those)
+//! saves real generation time without ever serving a stale answer for a
+//! request that could legitimately produce something new.
+//!
+//! Keyed by a hash of everything that determines the output — model id
+//! plus the raw request body — the same "hash the whole relevant input"
+//! approach `lora.rs` and `registry.rs` don't need but `sha1.rs` exists
+//! for. Entries expire after a TTL and the cache evicts its oldest entry
+//! once `max_entries` is reached, same bound-then-evict shape as
+//! `cancellation.rs`'s registry avoiding unbounded growth.
+//!
+//! The same store also tracks `Idempotency-Key` submissions
+//! ([`claim_idempotency_key`](ResponseCache::claim_idempotency_key) /
+//! [`wait_for_idempotent_result`](ResponseCache::wait_for_idempotent_result)):
+//! a flaky client retrying a request it already sent should get the
+//! original generation's result rather than paying for (and billing
+//! against `usage.rs`) a second one. Unlike the deterministic cache above,
+//! this applies to every request regardless of `temperature`/`seed`,
+//! since the whole point is "this is the same submission", not "this
+//! would produce the same output again".
+
+use crate::sha1::sha1;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+pub type CacheKey = String;
+
+struct CachedResponse {
+    body: String,
+    inserted_at: Instant,
+}
+
+/// Whether a request is even eligible for caching: `temperature: 0` and an
+/// explicit `seed` are the two fields that make a completion
+/// reproducible. Missing either means the backend is free to vary its
+/// output between calls, so the response must not be cached or served
+/// from cache.
+pub fn is_deterministic(parsed: &crate::json::Json) -> bool {
+    use crate::json::Json;
+    let temperature_is_zero = matches!(parsed.get("temperature").and_then(Json::as_f64), Some(t) if t == 0.0);
+    let has_seed = parsed.get("seed").and_then(Json::as_f64).is_some();
+    temperature_is_zero && has_seed
+}
+
+/// Hashes everything that determines a deterministic completion's output:
+/// which model answers it and the exact request body sent.
+pub fn cache_key(model_id: &str, body: &str) -> CacheKey {
+    let digest = sha1(format!("{model_id}\u{0}{body}").as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// One claimed `Idempotency-Key`: `result` is `None` while the request
+/// that claimed it is still generating, and `condvar` wakes any other
+/// request waiting on the same key once it's filled in.
+struct IdempotencySlot {
+    result: Mutex<Option<String>>,
+    condvar: Condvar,
+    claimed_at: Instant,
+}
+
+pub struct ResponseCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, CachedResponse>>,
+    idempotency: Mutex<HashMap<String, Arc<IdempotencySlot>>>,
+    on_evict: Option<Box<dyn Fn(&str) + Send + Sync>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        ResponseCache {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+            idempotency: Mutex::new(HashMap::new()),
+            on_evict: None,
+        }
+    }
+
+    /// Registers a hook called with the evicted key each time
+    /// [`insert`](Self::insert) drops the oldest entry to stay within
+    /// `max_entries` — `server.rs` wires this to `events::EventBus` so a
+    /// dashboard sees cache pressure instead of just the aggregate
+    /// `/metrics` counter.
+    pub fn with_eviction_hook(mut self, hook: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns the cached body for `key`, or `None` if it's missing or has
+    /// aged past `ttl` — an expired entry is dropped here rather than by a
+    /// background sweep, since a cache this size doesn't need one.
+    pub fn get(&self, key: &CacheKey) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = entries.get(key)?;
+        if cached.inserted_at.elapsed() > self.ttl {
+            entries.remove(key);
+            return None;
+        }
+        Some(cached.body.clone())
+    }
+
+    /// Inserts `body` under `key`, evicting the single oldest entry first
+    /// if the cache is already at `max_entries` — a full LRU isn't worth
+    /// the bookkeeping for a cache whose whole point is short-lived
+    /// eval-harness reruns.
+    pub fn insert(&self, key: CacheKey, body: String) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, v)| v.inserted_at).map(|(k, _)| k.clone()) {
+                entries.remove(&oldest);
+                if let Some(on_evict) = &self.on_evict {
+                    on_evict(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CachedResponse { body, inserted_at: Instant::now() });
+    }
+
+    /// Claims `key` for the caller if it's unclaimed (or its previous
+    /// claim is older than `ttl`, so a client can safely reuse a key after
+    /// the window closes). Returns `true` when the caller now owns this
+    /// key and should run generation itself, reporting the result through
+    /// [`complete_idempotency_key`](Self::complete_idempotency_key);
+    /// `false` means another request already claimed it and the caller
+    /// should call [`wait_for_idempotent_result`](Self::wait_for_idempotent_result)
+    /// instead of generating a second time.
+    pub fn claim_idempotency_key(&self, key: &str, ttl: Duration) -> bool {
+        let mut table = self.idempotency.lock().unwrap();
+        if let Some(slot) = table.get(key) {
+            if slot.claimed_at.elapsed() <= ttl {
+                return false;
+            }
+        }
+        table.insert(key.to_string(), Arc::new(IdempotencySlot { result: Mutex::new(None), condvar: Condvar::new(), claimed_at: Instant::now() }));
+        true
+    }
+
+    /// Blocks until the request that claimed `key` finishes (or `timeout`
+    /// elapses), returning its response body. `None` means `key` was never
+    /// claimed, or the claiming request didn't finish within `timeout`.
+    pub fn wait_for_idempotent_result(&self, key: &str, timeout: Duration) -> Option<String> {
+        let slot = self.idempotency.lock().unwrap().get(key)?.clone();
+        let result = slot.result.lock().unwrap();
+        let (result, _) = slot.condvar.wait_timeout_while(result, timeout, |result| result.is_none()).unwrap();
+        result.clone()
+    }
+
+    /// Records `body` as the result for `key`, waking any requests blocked
+    /// in [`wait_for_idempotent_result`](Self::wait_for_idempotent_result).
+    pub fn complete_idempotency_key(&self, key: &str, body: String) {
+        if let Some(slot) = self.idempotency.lock().unwrap().get(key) {
+            *slot.result.lock().unwrap() = Some(body);
+            slot.condvar.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Json;
+
+    #[test]
+    fn is_deterministic_requires_both_zero_temperature_and_a_seed() {
+        assert!(is_deterministic(&Json::parse(r#"{"temperature":0,"seed":1}"#).unwrap()));
+        assert!(!is_deterministic(&Json::parse(r#"{"temperature":0}"#).unwrap()));
+        assert!(!is_deterministic(&Json::parse(r#"{"seed":1}"#).unwrap()));
+        assert!(!is_deterministic(&Json::parse(r#"{"temperature":0.7,"seed":1}"#).unwrap()));
+    }
+
+    #[test]
+    fn cache_key_differs_when_model_or_body_differs() {
+        let a = cache_key("m1", "{}");
+        let b = cache_key("m2", "{}");
+        let c = cache_key("m1", r#"{"x":1}"#);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a, cache_key("m1", "{}"));
+    }
+
+    #[test]
+    fn get_returns_none_for_a_missing_key() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert!(cache.get(&"missing".to_string()).is_none());
+    }
+
+    #[test]
+    fn insert_then_get_round_trips_the_body() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        cache.insert("k".to_string(), "body".to_string());
+        assert_eq!(cache.get(&"k".to_string()), Some("body".to_string()));
+    }
+
+    #[test]
+    fn get_evicts_an_entry_once_its_ttl_has_elapsed() {
+        let cache = ResponseCache::new(Duration::from_millis(1), 10);
+        cache.insert("k".to_string(), "body".to_string());
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(cache.get(&"k".to_string()).is_none());
+    }
+
+    #[test]
+    fn insert_evicts_the_oldest_entry_once_max_entries_is_reached() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), "1".to_string());
+        std::thread::sleep(Duration::from_millis(5));
+        cache.insert("b".to_string(), "2".to_string());
+        std::thread::sleep(Duration::from_millis(5));
+        cache.insert("c".to_string(), "3".to_string());
+        assert!(cache.get(&"a".to_string()).is_none());
+        assert!(cache.get(&"b".to_string()).is_some());
+        assert!(cache.get(&"c".to_string()).is_some());
+    }
+
+    #[test]
+    fn claim_idempotency_key_succeeds_once_and_fails_on_a_second_attempt() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert!(cache.claim_idempotency_key("k", Duration::from_secs(60)));
+        assert!(!cache.claim_idempotency_key("k", Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn claim_idempotency_key_succeeds_again_once_the_ttl_has_elapsed() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert!(cache.claim_idempotency_key("k", Duration::from_millis(1)));
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(cache.claim_idempotency_key("k", Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn wait_for_idempotent_result_returns_none_for_an_unclaimed_key() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert_eq!(cache.wait_for_idempotent_result("missing", Duration::from_millis(10)), None);
+    }
+
+    #[test]
+    fn wait_for_idempotent_result_returns_the_body_once_completed() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert!(cache.claim_idempotency_key("k", Duration::from_secs(60)));
+        cache.complete_idempotency_key("k", "body".to_string());
+        assert_eq!(cache.wait_for_idempotent_result("k", Duration::from_millis(10)), Some("body".to_string()));
+    }
+
+    #[test]
+    fn wait_for_idempotent_result_unblocks_once_another_thread_completes_the_key() {
+        let cache: &'static ResponseCache = Box::leak(Box::new(ResponseCache::new(Duration::from_secs(60), 10)));
+        assert!(cache.claim_idempotency_key("k", Duration::from_secs(60)));
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            cache.complete_idempotency_key("k", "body".to_string());
+        });
+        assert_eq!(cache.wait_for_idempotent_result("k", Duration::from_secs(1)), Some("body".to_string()));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn wait_for_idempotent_result_times_out_while_the_claiming_request_is_still_running() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 10);
+        assert!(cache.claim_idempotency_key("k", Duration::from_secs(60)));
+        assert_eq!(cache.wait_for_idempotent_result("k", Duration::from_millis(10)), None);
+    }
+}
@@ -0,0 +1,324 @@
+//! Optional proxy/router mode: instead of serving `/v1/completions` and
+//! `/v1/chat/completions` from an in-process backend, forward them to a
+//! pool of downstream inference nodes (other `ai-server` instances, or
+//! anything else speaking the same OpenAI-shaped API) — health-checked,
+//! least-loaded by model, with sticky sessions so a conversation's later
+//! turns keep landing on the node holding its KV cache. Meant for a
+//! home-lab pooling more than one machine (say a Mac Studio and a Linux
+//! GPU box) behind one endpoint.
+//!
+//! Like `rpc.rs`'s framing, this hand-rolls just enough of an HTTP client
+//! to talk to a downstream node rather than pulling in `reqwest`/`hyper`
+//! (see `http.rs`'s doc comment for the same reasoning on the server
+//! side). The request-forwarding hot path in [`proxy_request`] doesn't
+//! even parse the response: it copies bytes back byte-for-byte, which
+//! handles a buffered JSON response and an SSE stream identically without
+//! this module needing to understand either shape. [`probe_health`] and
+//! [`probe_models`] are the exception — those run on a background polling
+//! loop, not per request, so reading a small response there is cheap.
+
+use crate::http::{Method, Request};
+use crate::json::Json;
+use crate::transport::Transport;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// One downstream inference node this server can forward to.
+#[derive(Debug, Clone)]
+pub struct RouterNode {
+    pub id: String,
+    pub address: String,
+}
+
+#[derive(Default)]
+struct RouterState {
+    healthy: HashMap<String, bool>,
+    in_flight: HashMap<String, usize>,
+    models: HashMap<String, Vec<String>>,
+    sticky: HashMap<String, String>,
+}
+
+/// Tracks health, in-flight load, and served models for a fixed set of
+/// downstream nodes, and picks which one a given request should go to.
+pub struct Router {
+    nodes: Vec<RouterNode>,
+    state: Mutex<RouterState>,
+}
+
+impl Router {
+    /// Every node starts optimistically healthy with an unknown (treated
+    /// as "serves everything") model list — [`spawn_health_checks`]
+    /// corrects both within one polling interval.
+    pub fn new(nodes: Vec<RouterNode>) -> Self {
+        let mut state = RouterState::default();
+        for node in &nodes {
+            state.healthy.insert(node.id.clone(), true);
+            state.in_flight.insert(node.id.clone(), 0);
+        }
+        Router { nodes, state: Mutex::new(state) }
+    }
+
+    pub fn nodes(&self) -> &[RouterNode] {
+        &self.nodes
+    }
+
+    pub fn set_health(&self, id: &str, healthy: bool) {
+        self.state.lock().unwrap().healthy.insert(id.to_string(), healthy);
+    }
+
+    pub fn set_models(&self, id: &str, models: Vec<String>) {
+        self.state.lock().unwrap().models.insert(id.to_string(), models);
+    }
+
+    pub fn record_start(&self, id: &str) {
+        *self.state.lock().unwrap().in_flight.entry(id.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_finish(&self, id: &str) {
+        if let Some(count) = self.state.lock().unwrap().in_flight.get_mut(id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Picks the node to send a `model` request to: a healthy node already
+    /// bound to `session_id` (KV reuse) first, otherwise the least-loaded
+    /// healthy node that serves `model` — a node whose model list hasn't
+    /// been learned yet (still `None` in `state.models`) is treated as a
+    /// candidate for anything, since refusing to route to a freshly seen
+    /// node until its first health-check poll completes would just strand
+    /// requests it could otherwise have served. `session_id`, when given,
+    /// is (re)bound to whichever node is chosen.
+    pub fn select(&self, model: &str, session_id: Option<&str>) -> Option<RouterNode> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(session_id) = session_id {
+            if let Some(id) = state.sticky.get(session_id) {
+                if state.healthy.get(id).copied().unwrap_or(false) {
+                    if let Some(node) = self.nodes.iter().find(|n| &n.id == id) {
+                        return Some(node.clone());
+                    }
+                }
+            }
+        }
+
+        let chosen = self
+            .nodes
+            .iter()
+            .filter(|n| state.healthy.get(&n.id).copied().unwrap_or(false))
+            .filter(|n| state.models.get(&n.id).map(|models| models.iter().any(|m| m == model)).unwrap_or(true))
+            .min_by_key(|n| state.in_flight.get(&n.id).copied().unwrap_or(0))
+            .cloned();
+
+        if let (Some(node), Some(session_id)) = (&chosen, session_id) {
+            state.sticky.insert(session_id.to_string(), node.id.clone());
+        }
+        chosen
+    }
+}
+
+/// Errors this module's client role can hit talking to a downstream node.
+#[derive(Debug)]
+pub enum RouterError {
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for RouterError {
+    fn from(e: std::io::Error) -> Self {
+        RouterError::Io(e)
+    }
+}
+
+/// Forwards `req` to `address` and copies its response back to
+/// `client_stream` byte-for-byte (see the module doc comment for why this
+/// doesn't parse the response at all).
+pub fn proxy_request(address: &str, req: &Request, client_stream: &mut Transport) -> Result<(), RouterError> {
+    let mut upstream = TcpStream::connect(address)?;
+    write_request(&mut upstream, address, req)?;
+    std::io::copy(&mut upstream, client_stream)?;
+    Ok(())
+}
+
+fn write_request(stream: &mut TcpStream, address: &str, req: &Request) -> std::io::Result<()> {
+    let method = match &req.method {
+        Method::Get => "GET",
+        Method::Post => "POST",
+        Method::Other(m) => m.as_str(),
+    };
+    write!(stream, "{method} {} HTTP/1.1\r\n", req.path)?;
+    for (name, value) in &req.headers {
+        if name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("host") {
+            continue;
+        }
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(stream, "Host: {address}\r\nContent-Length: {}\r\n\r\n", req.body.len())?;
+    stream.write_all(&req.body)
+}
+
+/// Minimal blocking GET used only by the polling loop below — reads just
+/// far enough to get the status and (for `/v1/models`) the body. Not used
+/// on the forwarding hot path, which sticks to the byte-for-byte
+/// `proxy_request` above.
+fn probe(address: &str, path: &str, timeout: Duration) -> Option<(u16, String)> {
+    let stream = TcpStream::connect(address).ok()?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    stream.set_write_timeout(Some(timeout)).ok()?;
+    let mut writer = stream.try_clone().ok()?;
+    write!(writer, "GET {path} HTTP/1.1\r\nHost: {address}\r\nConnection: close\r\n\r\n").ok()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).ok()?;
+    let status: u16 = status_line.split_whitespace().nth(1)?.parse().ok()?;
+
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some((status, String::from_utf8_lossy(&body).to_string()))
+}
+
+/// Whether a node's `/healthz` currently answers `200`.
+pub fn probe_health(address: &str, timeout: Duration) -> bool {
+    probe(address, "/healthz", timeout).map(|(status, _)| status == 200).unwrap_or(false)
+}
+
+/// A node's currently served model ids, read from its `/v1/models`. Empty
+/// (rather than an error) on any failure to reach or parse it — the
+/// caller already treats "unknown model list" as "serves everything" in
+/// [`Router::select`], so this only needs to report what it actually
+/// learned.
+pub fn probe_models(address: &str, timeout: Duration) -> Vec<String> {
+    let Some((200, body)) = probe(address, "/v1/models", timeout) else { return Vec::new() };
+    let Ok(json) = Json::parse(&body) else { return Vec::new() };
+    json.get("data")
+        .and_then(Json::as_array)
+        .map(|entries| entries.iter().filter_map(|e| e.get("id").and_then(Json::as_str)).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Spawns a background thread that re-probes every node's health and
+/// served-model list on `interval`, the same fire-and-forget polling
+/// shape `model_pool::spawn_idle_reaper` uses for its own background
+/// sweep — runs for the life of the process, since nothing currently
+/// tears router nodes down before shutdown.
+pub fn spawn_health_checks(router: &'static Router, interval: Duration) {
+    std::thread::spawn(move || loop {
+        for node in router.nodes() {
+            let healthy = probe_health(&node.address, Duration::from_secs(2));
+            router.set_health(&node.id, healthy);
+            if healthy {
+                router.set_models(&node.id, probe_models(&node.address, Duration::from_secs(2)));
+            }
+        }
+        std::thread::sleep(interval);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str) -> RouterNode {
+        RouterNode { id: id.to_string(), address: format!("{id}.invalid:1") }
+    }
+
+    #[test]
+    fn select_picks_the_least_loaded_healthy_node_serving_the_model() {
+        let router = Router::new(vec![node("a"), node("b")]);
+        router.set_models("a", vec!["llama".to_string()]);
+        router.set_models("b", vec!["llama".to_string()]);
+        router.record_start("a");
+        router.record_start("a");
+        router.record_start("b");
+        let chosen = router.select("llama", None).unwrap();
+        assert_eq!(chosen.id, "b");
+    }
+
+    #[test]
+    fn select_skips_unhealthy_nodes() {
+        let router = Router::new(vec![node("a"), node("b")]);
+        router.set_health("a", false);
+        let chosen = router.select("anything", None).unwrap();
+        assert_eq!(chosen.id, "b");
+    }
+
+    #[test]
+    fn select_skips_nodes_that_dont_serve_the_requested_model() {
+        let router = Router::new(vec![node("a"), node("b")]);
+        router.set_models("a", vec!["llama".to_string()]);
+        router.set_models("b", vec!["mistral".to_string()]);
+        let chosen = router.select("mistral", None).unwrap();
+        assert_eq!(chosen.id, "b");
+    }
+
+    #[test]
+    fn a_node_with_no_known_model_list_yet_is_treated_as_a_candidate() {
+        let router = Router::new(vec![node("a")]);
+        assert_eq!(router.select("anything", None).unwrap().id, "a");
+    }
+
+    #[test]
+    fn returns_none_when_no_node_is_healthy() {
+        let router = Router::new(vec![node("a")]);
+        router.set_health("a", false);
+        assert!(router.select("anything", None).is_none());
+    }
+
+    #[test]
+    fn sticky_sessions_keep_returning_the_same_node_once_bound() {
+        let router = Router::new(vec![node("a"), node("b")]);
+        let first = router.select("m", Some("session-1")).unwrap();
+        router.record_start(&first.id);
+        // The other node is now less loaded, but the bound session should
+        // keep landing on the node it started on.
+        let second = router.select("m", Some("session-1")).unwrap();
+        assert_eq!(first.id, second.id);
+    }
+
+    #[test]
+    fn a_sticky_session_falls_back_to_normal_selection_once_its_node_goes_unhealthy() {
+        let router = Router::new(vec![node("a"), node("b")]);
+        router.set_health("b", false);
+        let first = router.select("m", Some("session-1")).unwrap();
+        assert_eq!(first.id, "a");
+        router.set_health("a", false);
+        router.set_health("b", true);
+        let second = router.select("m", Some("session-1")).unwrap();
+        assert_eq!(second.id, "b");
+    }
+
+    #[test]
+    fn record_finish_never_underflows_below_zero() {
+        let router = Router::new(vec![node("a")]);
+        router.record_finish("a");
+        router.record_finish("a");
+        router.record_start("a");
+        let chosen = router.select("m", None).unwrap();
+        assert_eq!(chosen.id, "a");
+    }
+
+    #[test]
+    fn probe_health_reports_false_for_an_address_nothing_is_listening_on() {
+        assert!(!probe_health("127.0.0.1:1", Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn probe_models_is_empty_for_an_unreachable_node() {
+        assert!(probe_models("127.0.0.1:1", Duration::from_millis(200)).is_empty());
+    }
+}
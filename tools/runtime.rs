@@ -0,0 +1,291 @@
+//! Process-wide worker pool that the rest of the server submits compute
+//! tasks to, sized from an explicit `--threads N` override (falling back to
+//! `available_parallelism()`) with optional pinning so NUMA-sensitive matrix
+//! kernels keep their working set local across power-iteration rounds.
+//!
+//! Pinning defaults to a plain round-robin core index (worker N -> logical
+//! core N), which is fine on a symmetric host. [`Runtime::with_topology`]
+//! upgrades that to the NUMA-node- and big.LITTLE-aware placement in
+//! `threading.rs` when the caller has a [`hardware::HardwareProfile`] handy.
+
+use crate::hardware::HardwareProfile;
+use crate::threading;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// A sized, optionally core-pinned worker pool.
+pub struct Runtime {
+    worker_count: usize,
+    pin_workers: bool,
+    topology: Option<HardwareProfile>,
+    deterministic: bool,
+    next_core: AtomicUsize,
+}
+
+impl Runtime {
+    /// Builds a runtime sized from `threads` (an explicit `--threads N`
+    /// override) or, if `None`, from `available_parallelism()`.
+    pub fn new(threads: Option<usize>, pin_workers: bool) -> Self {
+        let worker_count = threads
+            .filter(|&n| n > 0)
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1);
+
+        Runtime {
+            worker_count,
+            pin_workers,
+            topology: None,
+            deterministic: false,
+            next_core: AtomicUsize::new(0),
+        }
+    }
+
+    /// Uses `profile`'s core topology for pinning instead of a plain
+    /// round-robin logical core index, the same builder pattern
+    /// `model_pool.rs`'s `with_prefix_cache_hooks`/`with_warmup` use for
+    /// optional behavior layered onto a base constructor. Has no effect
+    /// unless `pin_workers` was also enabled.
+    pub fn with_topology(mut self, profile: HardwareProfile) -> Self {
+        self.topology = Some(profile);
+        self
+    }
+
+    /// Runs [`parallel_for_chunks_mut`](Self::parallel_for_chunks_mut)'s
+    /// chunks one at a time, in index order, on the calling thread instead
+    /// of spawning a worker per chunk. `parallel_for_chunks_mut` already
+    /// writes each output index from exactly one chunk, so it never needed
+    /// this for correctness — but a caller assembling a strict-determinism
+    /// eval run (fixed `--seed`, wants byte-identical output across
+    /// reruns) shouldn't have to also reason about whether some future
+    /// kernel's chunk callback does a genuine cross-chunk reduction, where
+    /// thread scheduling order could matter. This is the escape hatch for
+    /// that case: pay for the lost parallelism, get an execution order
+    /// that's pinned down completely.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Number of workers this runtime was sized for.
+    pub fn worker_count(&self) -> usize {
+        self.worker_count
+    }
+
+    /// Runs `f` on a dedicated worker thread and blocks the caller until it
+    /// completes, surfacing a worker panic as an `Err` instead of aborting
+    /// the process.
+    pub fn spawn_blocking<F, T>(&self, f: F) -> thread::Result<T>
+    where
+        F: FnOnce() -> T + Send,
+        T: Send,
+    {
+        let pin_workers = self.pin_workers;
+        let core = self.next_core.fetch_add(1, Ordering::Relaxed) % self.worker_count.max(1);
+        thread::scope(|scope| {
+            scope
+                .spawn(move || {
+                    if pin_workers {
+                        self.place_worker(core);
+                    }
+                    f()
+                })
+                .join()
+        })
+    }
+
+    /// Opens a scope for borrowing tasks, mirroring [`std::thread::scope`]
+    /// so panics inside spawned tasks propagate to the caller on `join()`
+    /// rather than aborting the process.
+    pub fn scope<'scope, F, T>(&'scope self, f: F) -> T
+    where
+        F: for<'a> FnOnce(&'a thread::Scope<'a, 'scope>) -> T,
+    {
+        thread::scope(f)
+    }
+
+    /// Splits `data` into exactly `min(threads, data.len())` contiguous,
+    /// disjoint chunks — sized as evenly as `data.len()` allows, so callers
+    /// get exactly as many workers as requested instead of silently falling
+    /// short when `threads` doesn't divide `data.len()` evenly — and runs
+    /// `f(chunk_start_index, chunk)` on one scoped worker thread per chunk,
+    /// pinning each worker to its own core when affinity pinning is enabled.
+    /// Returns the number of chunks/workers actually used, since that can
+    /// be smaller than `threads` when `data` is shorter.
+    pub fn parallel_for_chunks_mut<T, F>(&self, data: &mut [T], threads: usize, f: F) -> usize
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        if data.is_empty() {
+            return 0;
+        }
+
+        let threads = threads.max(1).min(data.len());
+        let base = data.len() / threads;
+        let remainder = data.len() % threads;
+        let pin_workers = self.pin_workers;
+        let f = &f;
+
+        if self.deterministic {
+            let mut rest = data;
+            let mut start = 0;
+            for worker in 0..threads {
+                let size = base + usize::from(worker < remainder);
+                let (chunk, remaining) = rest.split_at_mut(size);
+                rest = remaining;
+                f(start, chunk);
+                start += size;
+            }
+            return threads;
+        }
+
+        thread::scope(|scope| {
+            let mut rest = data;
+            let mut start = 0;
+            for worker in 0..threads {
+                // The first `remainder` chunks get one extra element so all
+                // `threads` chunks partition `data` exactly.
+                let size = base + usize::from(worker < remainder);
+                let (chunk, remaining) = rest.split_at_mut(size);
+                rest = remaining;
+                let chunk_start = start;
+                start += size;
+                scope.spawn(move || {
+                    if pin_workers {
+                        self.place_worker(worker);
+                    }
+                    f(chunk_start, chunk);
+                });
+            }
+        });
+
+        threads
+    }
+
+    /// Places the calling thread for worker slot `worker`: topology-aware
+    /// via `threading::place_worker` when [`with_topology`](Self::with_topology)
+    /// was used, otherwise the plain round-robin core index below.
+    fn place_worker(&self, worker: usize) {
+        match &self.topology {
+            Some(profile) => threading::place_worker(profile, worker),
+            None => Self::pin_current_thread(worker),
+        }
+    }
+
+    /// Pins the calling thread to the given logical core, best-effort. A
+    /// no-op on platforms where we don't have an affinity syscall wired up.
+    #[cfg(target_os = "linux")]
+    fn pin_current_thread(core: usize) {
+        // Avoid a dependency on an external affinity crate: `sched_setaffinity`
+        // is part of glibc, which rustc already links every binary against.
+        const CPU_SETSIZE: usize = 1024;
+        const BITS_PER_WORD: usize = 64;
+        let mut mask = [0u64; CPU_SETSIZE / BITS_PER_WORD];
+        let word = core / BITS_PER_WORD;
+        if word < mask.len() {
+            mask[word] |= 1 << (core % BITS_PER_WORD);
+            unsafe {
+                sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr());
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin_current_thread(_core: usize) {}
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize as StdAtomicUsize, Ordering as StdOrdering};
+
+    #[test]
+    fn spawn_blocking_returns_the_closures_value() {
+        let rt = Runtime::new(Some(2), false);
+        let result = rt.spawn_blocking(|| 2 + 2).unwrap();
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn spawn_blocking_surfaces_panics_as_err() {
+        let rt = Runtime::new(Some(2), false);
+        let result = rt.spawn_blocking(|| panic!("boom"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn scope_joins_spawned_tasks() {
+        let rt = Runtime::new(Some(2), false);
+        let counter = StdAtomicUsize::new(0);
+        rt.scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    counter.fetch_add(1, StdOrdering::Relaxed);
+                });
+            }
+        });
+        assert_eq!(counter.load(StdOrdering::Relaxed), 4);
+    }
+
+    #[test]
+    fn parallel_for_chunks_mut_uses_exactly_threads_chunks_when_uneven() {
+        let rt = Runtime::new(Some(1), false);
+        let mut data = vec![0usize; 1000];
+        let used = rt.parallel_for_chunks_mut(&mut data, 64, |start, chunk| {
+            for (offset, slot) in chunk.iter_mut().enumerate() {
+                *slot = start + offset;
+            }
+        });
+        assert_eq!(used, 64);
+        // Every index was visited exactly once, and chunk_start lines up
+        // with the value each slot was actually assigned.
+        for (i, &v) in data.iter().enumerate() {
+            assert_eq!(v, i);
+        }
+    }
+
+    #[test]
+    fn parallel_for_chunks_mut_clamps_to_data_len() {
+        let rt = Runtime::new(Some(1), false);
+        let mut data = vec![0usize; 3];
+        let used = rt.parallel_for_chunks_mut(&mut data, 64, |_, _| {});
+        assert_eq!(used, 3);
+    }
+
+    #[test]
+    fn deterministic_mode_produces_the_same_result_as_the_threaded_path() {
+        let mut threaded = vec![0usize; 997];
+        Runtime::new(Some(8), false).parallel_for_chunks_mut(&mut threaded, 8, |start, chunk| {
+            for (offset, slot) in chunk.iter_mut().enumerate() {
+                *slot = start + offset;
+            }
+        });
+
+        let mut sequential = vec![0usize; 997];
+        let used = Runtime::new(Some(8), false).with_deterministic(true).parallel_for_chunks_mut(
+            &mut sequential,
+            8,
+            |start, chunk| {
+                for (offset, slot) in chunk.iter_mut().enumerate() {
+                    *slot = start + offset;
+                }
+            },
+        );
+
+        assert_eq!(used, 8);
+        assert_eq!(threaded, sequential);
+    }
+
+    #[test]
+    fn parallel_for_chunks_mut_handles_empty_data() {
+        let rt = Runtime::new(Some(1), false);
+        let mut data: Vec<usize> = Vec::new();
+        let used = rt.parallel_for_chunks_mut(&mut data, 64, |_, _| {});
+        assert_eq!(used, 0);
+    }
+}
@@ -0,0 +1,413 @@
+//! Reader for the safetensors checkpoint format: a JSON header (tensor name
+//! -> dtype/shape/byte-range) prefixed by its own little-endian `u64`
+//! length, followed by the raw tensor data. Like `gguf.rs`, this only reads
+//! the header — the tensor data segment is addressed by
+//! [`TensorEntry::data_offsets`] but not read here, for the same reason
+//! `gguf.rs` doesn't decode tensor bytes: that's `mmap_loader.rs`'s job once
+//! a real backend needs the bytes in memory.
+//!
+//! Also understands Hugging Face's sharded checkpoint convention: a
+//! `<name>.safetensors.index.json` file with a `"weight_map"` object mapping
+//! each tensor name to the shard file that actually holds it, so a
+//! multi-gigabyte checkpoint split across several `.safetensors` files reads
+//! as one model.
+//!
+//! Spec: https://huggingface.co/docs/safetensors/index
+
+use crate::json::Json;
+use crate::model_loader::ModelLoader;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum SafetensorsError {
+    Io(io::Error),
+    Json(String),
+    Malformed(String),
+}
+
+impl From<io::Error> for SafetensorsError {
+    fn from(e: io::Error) -> Self {
+        SafetensorsError::Io(e)
+    }
+}
+
+impl fmt::Display for SafetensorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetensorsError::Io(e) => write!(f, "I/O error: {e}"),
+            SafetensorsError::Json(e) => write!(f, "malformed header JSON: {e}"),
+            SafetensorsError::Malformed(msg) => write!(f, "malformed safetensors file: {msg}"),
+        }
+    }
+}
+
+/// One tensor's location and declared type, resolved to the shard file that
+/// actually holds it (itself, for a single-file checkpoint).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorEntry {
+    pub shard: PathBuf,
+    pub dtype: String,
+    pub shape: Vec<u64>,
+    pub data_offsets: (u64, u64),
+    /// Byte offset within `shard` its data segment starts at —
+    /// `data_offsets` is relative to this point, not the start of the file.
+    /// Carried alongside the offsets so a loaded shard's raw length can be
+    /// checked against them without re-reading the header (see
+    /// `shard_loader::validate_loaded_shards`).
+    pub data_start: u64,
+}
+
+/// A parsed safetensors checkpoint, single-file or sharded. Does not hold
+/// tensor data.
+#[derive(Debug)]
+pub struct SafetensorsModel {
+    pub tensors: BTreeMap<String, TensorEntry>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl SafetensorsModel {
+    /// Opens `path` as a sharded index (`*.index.json`) or a single-file
+    /// checkpoint, dispatching on its extension the same way `.gguf` vs.
+    /// other extensions would.
+    pub fn open(path: &Path) -> Result<SafetensorsModel, SafetensorsError> {
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            SafetensorsModel::open_sharded(path)
+        } else {
+            SafetensorsModel::open_single(path)
+        }
+    }
+
+    pub fn open_single(path: &Path) -> Result<SafetensorsModel, SafetensorsError> {
+        let (header, data_start) = read_header(path)?;
+        let Json::Object(fields) = header else {
+            return Err(SafetensorsError::Malformed("header is not a JSON object".to_string()));
+        };
+
+        let mut tensors = BTreeMap::new();
+        let mut metadata = BTreeMap::new();
+        for (name, value) in fields {
+            if name == "__metadata__" {
+                if let Json::Object(meta_fields) = value {
+                    for (key, meta_value) in meta_fields {
+                        if let Some(s) = meta_value.as_str() {
+                            metadata.insert(key, s.to_string());
+                        }
+                    }
+                }
+                continue;
+            }
+            tensors.insert(name, parse_tensor_entry(path.to_path_buf(), data_start, &value)?);
+        }
+        Ok(SafetensorsModel { tensors, metadata })
+    }
+
+    pub fn open_sharded(index_path: &Path) -> Result<SafetensorsModel, SafetensorsError> {
+        let text = std::fs::read_to_string(index_path)?;
+        let index = Json::parse(&text).map_err(|e| SafetensorsError::Json(e.to_string()))?;
+        let Some(Json::Object(weight_map)) = index.get("weight_map") else {
+            return Err(SafetensorsError::Malformed("index is missing a \"weight_map\" object".to_string()));
+        };
+        let base_dir = index_path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut shard_headers: BTreeMap<&str, (Json, u64)> = BTreeMap::new();
+        let mut tensors = BTreeMap::new();
+        for (tensor_name, shard_value) in weight_map {
+            let shard_name = shard_value
+                .as_str()
+                .ok_or_else(|| SafetensorsError::Malformed(format!("weight_map[{tensor_name:?}] is not a string")))?;
+            if !shard_headers.contains_key(shard_name) {
+                let (header, data_start) = read_header(&base_dir.join(shard_name))?;
+                shard_headers.insert(shard_name, (header, data_start));
+            }
+            let (header, data_start) = &shard_headers[shard_name];
+            let value = header.get(tensor_name).ok_or_else(|| {
+                SafetensorsError::Malformed(format!("shard {shard_name:?} has no header entry for {tensor_name:?}"))
+            })?;
+            tensors.insert(tensor_name.clone(), parse_tensor_entry(base_dir.join(shard_name), *data_start, value)?);
+        }
+        Ok(SafetensorsModel { tensors, metadata: BTreeMap::new() })
+    }
+
+    /// Distinct shard files this checkpoint spans, in sorted order — the
+    /// input `shard_loader::load_shards` needs to actually read the tensor
+    /// bytes `open_single`/`open_sharded` only read headers for.
+    pub fn shard_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.tensors.values().map(|entry| entry.shard.clone()).collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Cross-checks each tensor's `data_offsets` span against its
+    /// `shape`/`dtype`, catching a header that's been hand-edited or
+    /// corrupted without needing to read the tensor data itself. Dtypes this
+    /// module doesn't know the element size of are skipped rather than
+    /// rejected, since an unrecognized-but-consistent dtype shouldn't block
+    /// loading.
+    pub fn validate(&self) -> Result<(), SafetensorsError> {
+        for (name, entry) in &self.tensors {
+            let (start, end) = entry.data_offsets;
+            let span = end.checked_sub(start).ok_or_else(|| {
+                SafetensorsError::Malformed(format!("tensor {name:?} has data_offsets end before start"))
+            })?;
+            let Some(element_size) = dtype_byte_size(&entry.dtype) else {
+                continue;
+            };
+            let element_count: u64 = entry.shape.iter().product();
+            let expected = element_count * element_size;
+            if expected != span {
+                return Err(SafetensorsError::Malformed(format!(
+                    "tensor {name:?} shape {:?} as {} should span {expected} bytes, but data_offsets spans {span}",
+                    entry.shape, entry.dtype
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cross-checks every tensor's `data_offsets` against the actual length
+    /// of its shard once `shard_loader::load_shards` has loaded the bytes,
+    /// catching a shard that loaded successfully but is truncated or
+    /// otherwise shorter than its own header promises — the failure mode
+    /// [`SafetensorsModel::validate`] can't see, since it only looks at the
+    /// header. `shard_lengths` maps each of `shard_paths()`'s entries to the
+    /// number of bytes actually loaded for it.
+    pub fn validate_loaded_shards(&self, shard_lengths: &BTreeMap<PathBuf, u64>) -> Result<(), SafetensorsError> {
+        for (name, entry) in &self.tensors {
+            let Some(&loaded_len) = shard_lengths.get(&entry.shard) else {
+                return Err(SafetensorsError::Malformed(format!(
+                    "tensor {name:?}'s shard {:?} was not among the loaded shards",
+                    entry.shard
+                )));
+            };
+            let required = entry.data_start + entry.data_offsets.1;
+            if loaded_len < required {
+                return Err(SafetensorsError::Malformed(format!(
+                    "tensor {name:?} needs {required} bytes from shard {:?}, but only {loaded_len} were loaded",
+                    entry.shard
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ModelLoader for SafetensorsModel {
+    fn tensor_names(&self) -> Vec<&str> {
+        self.tensors.keys().map(String::as_str).collect()
+    }
+
+    fn tensor_shape(&self, name: &str) -> Option<&[u64]> {
+        self.tensors.get(name).map(|entry| entry.shape.as_slice())
+    }
+
+    fn tensor_dtype(&self, name: &str) -> Option<&str> {
+        self.tensors.get(name).map(|entry| entry.dtype.as_str())
+    }
+}
+
+fn parse_tensor_entry(shard: PathBuf, data_start: u64, value: &Json) -> Result<TensorEntry, SafetensorsError> {
+    let dtype = value
+        .get("dtype")
+        .and_then(Json::as_str)
+        .ok_or_else(|| SafetensorsError::Malformed("tensor entry is missing \"dtype\"".to_string()))?
+        .to_string();
+    let shape = value
+        .get("shape")
+        .and_then(Json::as_array)
+        .ok_or_else(|| SafetensorsError::Malformed("tensor entry is missing \"shape\"".to_string()))?
+        .iter()
+        .map(|v| {
+            v.as_f64()
+                .map(|n| n as u64)
+                .ok_or_else(|| SafetensorsError::Malformed("shape entry is not a number".to_string()))
+        })
+        .collect::<Result<Vec<u64>, _>>()?;
+    let offsets = value
+        .get("data_offsets")
+        .and_then(Json::as_array)
+        .ok_or_else(|| SafetensorsError::Malformed("tensor entry is missing \"data_offsets\"".to_string()))?;
+    let [start, end] = offsets else {
+        return Err(SafetensorsError::Malformed("\"data_offsets\" must have exactly two entries".to_string()));
+    };
+    let (start, end) = (
+        start.as_f64().ok_or_else(|| SafetensorsError::Malformed("data_offsets[0] is not a number".to_string()))? as u64,
+        end.as_f64().ok_or_else(|| SafetensorsError::Malformed("data_offsets[1] is not a number".to_string()))? as u64,
+    );
+
+    Ok(TensorEntry { shard, dtype, shape, data_offsets: (start, end), data_start })
+}
+
+fn dtype_byte_size(dtype: &str) -> Option<u64> {
+    match dtype {
+        "F64" | "I64" | "U64" => Some(8),
+        "F32" | "I32" | "U32" => Some(4),
+        "F16" | "BF16" | "I16" | "U16" => Some(2),
+        "I8" | "U8" | "BOOL" => Some(1),
+        _ => None,
+    }
+}
+
+/// Reads and parses the 8-byte length-prefixed JSON header at the start of
+/// `path`, returning it alongside the byte offset the tensor data segment
+/// starts at (offsets inside the header are relative to that point).
+fn read_header(path: &Path) -> Result<(Json, u64), SafetensorsError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut len_bytes = [0u8; 8];
+    file.read_exact(&mut len_bytes)
+        .map_err(|_| SafetensorsError::Malformed("file is shorter than the 8-byte header-length prefix".to_string()))?;
+    let header_len = u64::from_le_bytes(len_bytes);
+
+    let mut header_bytes = vec![0u8; header_len as usize];
+    file.read_exact(&mut header_bytes)
+        .map_err(|_| SafetensorsError::Malformed("file ended before the declared header length".to_string()))?;
+    let header_text = std::str::from_utf8(&header_bytes)
+        .map_err(|e| SafetensorsError::Malformed(format!("header is not valid UTF-8: {e}")))?;
+    let header = Json::parse(header_text).map_err(|e| SafetensorsError::Json(e.to_string()))?;
+
+    Ok((header, 8 + header_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ai-server-safetensors-test-{name}-{}", std::process::id()))
+    }
+
+    fn write_single_file(path: &Path, header_json: &str, data: &[u8]) {
+        let mut file = std::fs::File::create(path).unwrap();
+        file.write_all(&(header_json.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(header_json.as_bytes()).unwrap();
+        file.write_all(data).unwrap();
+    }
+
+    #[test]
+    fn open_single_reads_tensor_shapes_and_dtypes() {
+        let path = temp_path("single");
+        let header = r#"{"weight":{"dtype":"F32","shape":[2,3],"data_offsets":[0,24]},"__metadata__":{"format":"pt"}}"#;
+        write_single_file(&path, header, &[0u8; 24]);
+
+        let model = SafetensorsModel::open_single(&path).unwrap();
+        assert_eq!(model.tensor_names(), vec!["weight"]);
+        assert_eq!(model.tensor_shape("weight"), Some(&[2u64, 3][..]));
+        assert_eq!(model.tensor_dtype("weight"), Some("F32"));
+        assert_eq!(model.metadata.get("format"), Some(&"pt".to_string()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_dispatches_on_extension() {
+        let single_path = temp_path("dispatch.safetensors");
+        write_single_file(&single_path, r#"{"a":{"dtype":"F16","shape":[1],"data_offsets":[0,2]}}"#, &[0u8; 2]);
+        let model = SafetensorsModel::open(&single_path).unwrap();
+        assert_eq!(model.tensor_names(), vec!["a"]);
+        std::fs::remove_file(&single_path).unwrap();
+    }
+
+    #[test]
+    fn open_sharded_resolves_each_tensor_to_its_shard() {
+        let dir = std::env::temp_dir().join(format!("ai-server-safetensors-test-sharded-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shard_a = dir.join("model-00001-of-00002.safetensors");
+        let shard_b = dir.join("model-00002-of-00002.safetensors");
+        write_single_file(&shard_a, r#"{"embed":{"dtype":"F16","shape":[4],"data_offsets":[0,8]}}"#, &[0u8; 8]);
+        write_single_file(&shard_b, r#"{"head":{"dtype":"F16","shape":[2],"data_offsets":[0,4]}}"#, &[0u8; 4]);
+
+        let index_path = dir.join("model.safetensors.index.json");
+        std::fs::write(
+            &index_path,
+            r#"{"weight_map":{"embed":"model-00001-of-00002.safetensors","head":"model-00002-of-00002.safetensors"}}"#,
+        )
+        .unwrap();
+
+        let model = SafetensorsModel::open(&index_path).unwrap();
+        assert_eq!(model.tensors["embed"].shard, shard_a);
+        assert_eq!(model.tensors["head"].shard, shard_b);
+        assert_eq!(model.tensor_shape("head"), Some(&[2u64][..]));
+        assert_eq!(model.shard_paths(), vec![shard_a.clone(), shard_b.clone()]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validate_loaded_shards_accepts_shards_at_least_as_long_as_their_tensors_need() {
+        let path = temp_path("loaded-ok");
+        write_single_file(&path, r#"{"w":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#, &[0u8; 16]);
+        let model = SafetensorsModel::open_single(&path).unwrap();
+        let data_start = model.tensors["w"].data_start;
+
+        let mut shard_lengths = BTreeMap::new();
+        shard_lengths.insert(path.clone(), data_start + 16);
+        assert!(model.validate_loaded_shards(&shard_lengths).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_loaded_shards_rejects_a_shard_shorter_than_its_tensors_need() {
+        let path = temp_path("loaded-truncated");
+        write_single_file(&path, r#"{"w":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#, &[0u8; 16]);
+        let model = SafetensorsModel::open_single(&path).unwrap();
+        let data_start = model.tensors["w"].data_start;
+
+        let mut shard_lengths = BTreeMap::new();
+        shard_lengths.insert(path.clone(), data_start + 8);
+        assert!(model.validate_loaded_shards(&shard_lengths).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_loaded_shards_rejects_a_shard_missing_from_the_loaded_set() {
+        let path = temp_path("loaded-missing");
+        write_single_file(&path, r#"{"w":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#, &[0u8; 16]);
+        let model = SafetensorsModel::open_single(&path).unwrap();
+
+        assert!(model.validate_loaded_shards(&BTreeMap::new()).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_a_data_offsets_span_inconsistent_with_shape_and_dtype() {
+        let path = temp_path("bad-span");
+        write_single_file(&path, r#"{"w":{"dtype":"F32","shape":[2,2],"data_offsets":[0,8]}}"#, &[0u8; 8]);
+        let model = SafetensorsModel::open_single(&path).unwrap();
+        assert!(model.validate().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn validate_accepts_a_consistent_header() {
+        let path = temp_path("good-span");
+        write_single_file(&path, r#"{"w":{"dtype":"F32","shape":[2,2],"data_offsets":[0,16]}}"#, &[0u8; 16]);
+        let model = SafetensorsModel::open_single(&path).unwrap();
+        assert!(model.validate().is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_file_shorter_than_the_header_length_prefix() {
+        let path = temp_path("truncated");
+        std::fs::write(&path, [0u8; 4]).unwrap();
+        let err = SafetensorsModel::open_single(&path).unwrap_err();
+        assert!(matches!(err, SafetensorsError::Malformed(_)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_header_that_is_not_a_json_object() {
+        let path = temp_path("non-object-header");
+        write_single_file(&path, "[1,2,3]", &[]);
+        let err = SafetensorsModel::open_single(&path).unwrap_err();
+        assert!(matches!(err, SafetensorsError::Malformed(_)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
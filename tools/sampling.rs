@@ -0,0 +1,358 @@
+//! Token sampling pipeline: turns a raw logits vector into a chosen token
+//! id by applying, in order, repetition penalty, temperature scaling,
+//! top-k, top-p (nucleus), and min-p filtering — the same stage order
+//! llama.cpp uses, since later stages assume earlier ones already turned
+//! logits into a proper (or partial) probability distribution.
+//!
+//! [`sample`] takes its random draw from the caller rather than reaching
+//! for a global RNG, so [`SeededRng`] (or any other source of `[0, 1)`
+//! floats) is what actually makes a request's `seed` field reproducible —
+//! same seed and same logits/history sequence always walks the same
+//! sequence of draws through [`sample_seeded`], regardless of how many
+//! times the process is restarted between runs.
+
+pub struct SamplingParams {
+    pub temperature: f32,
+    pub top_k: Option<usize>,
+    pub top_p: Option<f32>,
+    pub min_p: Option<f32>,
+    pub repetition_penalty: f32,
+}
+
+impl Default for SamplingParams {
+    fn default() -> Self {
+        SamplingParams { temperature: 1.0, top_k: None, top_p: None, min_p: None, repetition_penalty: 1.0 }
+    }
+}
+
+/// Named default sampling values a model registers for its requests, kept
+/// on a `registry::ModelEntry` (see `ModelRegistry::set_preset`) so an
+/// operator can pin sane request-time behavior for a shared deployment
+/// without every client having to set the same fields. Each field is
+/// `None`/empty when the preset doesn't opine on it, in which case
+/// [`resolve_generation_params`] falls through to this module's own
+/// defaults instead.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GenerationPreset {
+    pub name: String,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub repetition_penalty: Option<f32>,
+    pub stop: Vec<String>,
+}
+
+/// Hard `[min, max]` bounds a request's (or a [`GenerationPreset`]'s)
+/// sampling values are clamped into before generation — see
+/// `resolve_generation_params`. `None` for a field leaves it unclamped,
+/// matching this tree's other "absent means unlimited" config fields (e.g.
+/// `config.rs`'s `max_cache_bytes`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GenerationClamps {
+    pub temperature: Option<(f32, f32)>,
+    pub top_p: Option<(f32, f32)>,
+    pub repetition_penalty: Option<(f32, f32)>,
+}
+
+/// The sampling-relevant fields read directly off an incoming request,
+/// before a model's [`GenerationPreset`] defaults or [`GenerationClamps`]
+/// bounds are applied. `None`/empty means the client didn't set that field.
+#[derive(Debug, Clone, Default)]
+pub struct RequestedGenerationParams {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub repetition_penalty: Option<f32>,
+    pub stop: Vec<String>,
+}
+
+/// The parameters actually in effect for a request, after layering
+/// `requested` over a model's `preset` defaults and clamping into its
+/// `clamps` bounds — what a completions response echoes back in its
+/// `"generation_params"` field so a client on a shared deployment can see
+/// what the server actually used instead of what it asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveGenerationParams {
+    pub temperature: f32,
+    pub top_p: f32,
+    pub repetition_penalty: f32,
+    pub stop: Vec<String>,
+}
+
+fn clamp_into(value: f32, bounds: Option<(f32, f32)>) -> f32 {
+    match bounds {
+        Some((min, max)) => value.clamp(min, max),
+        None => value,
+    }
+}
+
+/// Layers `requested` over `preset`'s defaults (falling back to this
+/// module's own [`SamplingParams::default`] values where neither sets a
+/// field), then clamps every numeric field into `clamps`' bounds. A
+/// request always wins over the preset when it sets a field explicitly —
+/// the preset only fills in what the client left unset — but the clamps
+/// apply regardless of where a value came from, so a preset itself can't
+/// hand out a value outside the operator's own bounds.
+pub fn resolve_generation_params(
+    preset: Option<&GenerationPreset>,
+    clamps: &GenerationClamps,
+    requested: &RequestedGenerationParams,
+) -> EffectiveGenerationParams {
+    let default = SamplingParams::default();
+    let temperature = requested.temperature.or_else(|| preset.and_then(|p| p.temperature)).unwrap_or(default.temperature);
+    let top_p = requested.top_p.or_else(|| preset.and_then(|p| p.top_p)).unwrap_or(1.0);
+    let repetition_penalty =
+        requested.repetition_penalty.or_else(|| preset.and_then(|p| p.repetition_penalty)).unwrap_or(default.repetition_penalty);
+    let stop = if !requested.stop.is_empty() { requested.stop.clone() } else { preset.map(|p| p.stop.clone()).unwrap_or_default() };
+
+    EffectiveGenerationParams {
+        temperature: clamp_into(temperature, clamps.temperature),
+        top_p: clamp_into(top_p, clamps.top_p),
+        repetition_penalty: clamp_into(repetition_penalty, clamps.repetition_penalty),
+        stop,
+    }
+}
+
+/// Applies the repetition penalty in place: logits for tokens already in
+/// `history` are divided by `penalty` when positive, multiplied when
+/// negative — the same asymmetric rule llama.cpp uses so penalizing a
+/// negative logit doesn't accidentally make it more likely.
+fn apply_repetition_penalty(logits: &mut [f32], history: &[u32], penalty: f32) {
+    if penalty == 1.0 {
+        return;
+    }
+    for &token in history {
+        if let Some(logit) = logits.get_mut(token as usize) {
+            *logit = if *logit > 0.0 { *logit / penalty } else { *logit * penalty };
+        }
+    }
+}
+
+fn softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = logits.iter().map(|&l| (l - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.iter().map(|&e| e / sum).collect()
+}
+
+/// Runs the full pipeline and returns the sampled token id. `random` is a
+/// caller-supplied `[0, 1)` draw, so callers control the RNG (or make
+/// sampling deterministic for tests/`--seed`).
+pub fn sample(logits: &[f32], history: &[u32], params: &SamplingParams, random: f32) -> u32 {
+    let mut logits = logits.to_vec();
+    apply_repetition_penalty(&mut logits, history, params.repetition_penalty);
+
+    if params.temperature != 1.0 && params.temperature > 0.0 {
+        for l in &mut logits {
+            *l /= params.temperature;
+        }
+    }
+
+    let mut candidates: Vec<(u32, f32)> =
+        logits.iter().enumerate().map(|(id, &l)| (id as u32, l)).collect();
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    if let Some(k) = params.top_k {
+        candidates.truncate(k.max(1));
+    }
+
+    let probs = softmax(&candidates.iter().map(|&(_, l)| l).collect::<Vec<_>>());
+    let mut candidates: Vec<(u32, f32)> =
+        candidates.iter().zip(probs).map(|(&(id, _), p)| (id, p)).collect();
+
+    if let Some(p) = params.top_p {
+        let mut cumulative = 0.0;
+        let mut cutoff = candidates.len();
+        for (i, &(_, prob)) in candidates.iter().enumerate() {
+            cumulative += prob;
+            if cumulative >= p {
+                cutoff = i + 1;
+                break;
+            }
+        }
+        candidates.truncate(cutoff.max(1));
+    }
+
+    if let Some(min_p) = params.min_p {
+        let top_prob = candidates.first().map(|&(_, p)| p).unwrap_or(0.0);
+        let threshold = top_prob * min_p;
+        candidates.retain(|&(_, p)| p >= threshold);
+        if candidates.is_empty() {
+            candidates.push((0, 1.0));
+        }
+    }
+
+    let total: f32 = candidates.iter().map(|&(_, p)| p).sum();
+    let target = random.clamp(0.0, 0.999_999) * total;
+    let mut cumulative = 0.0;
+    for &(id, prob) in &candidates {
+        cumulative += prob;
+        if cumulative >= target {
+            return id;
+        }
+    }
+    candidates.last().map(|&(id, _)| id).unwrap_or(0)
+}
+
+/// Deterministic pseudo-random `[0, 1)` draws for [`sample_seeded`], so a
+/// request's `seed` field can make token selection reproducible without
+/// pulling in an external `rand` crate. The state transition is
+/// `splitmix64` (Vigna's fixed-increment generator), chosen over a
+/// from-scratch design specifically so its statistical properties are
+/// public record rather than something this comment would have to argue
+/// for.
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng(seed)
+    }
+
+    /// Returns the next draw in `[0, 1)`, advancing the generator's state.
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // Top 24 bits give a float with as much precision as `f32`'s
+        // mantissa can represent, scaled into `[0, 1)`.
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// [`sample`], drawing its randomness from `rng` instead of a
+/// caller-supplied float directly — the shape a request's `seed` field
+/// plugs into: build one [`SeededRng`] per request/generation and reuse it
+/// across every sampled token so the whole completion is reproducible, not
+/// just its first token.
+pub fn sample_seeded(logits: &[f32], history: &[u32], params: &SamplingParams, rng: &mut SeededRng) -> u32 {
+    sample(logits, history, params, rng.next_f32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_is_deterministic_across_separate_instances() {
+        let mut a = SeededRng::new(42);
+        let mut b = SeededRng::new(42);
+        let draws_a: Vec<f32> = (0..10).map(|_| a.next_f32()).collect();
+        let draws_b: Vec<f32> = (0..10).map(|_| b.next_f32()).collect();
+        assert_eq!(draws_a, draws_b);
+    }
+
+    #[test]
+    fn seeded_rng_draws_stay_within_the_unit_interval() {
+        let mut rng = SeededRng::new(7);
+        for _ in 0..1000 {
+            let draw = rng.next_f32();
+            assert!((0.0..1.0).contains(&draw), "draw {draw} out of range");
+        }
+    }
+
+    #[test]
+    fn different_seeds_usually_diverge() {
+        let mut a = SeededRng::new(1);
+        let mut b = SeededRng::new(2);
+        let draws_a: Vec<f32> = (0..10).map(|_| a.next_f32()).collect();
+        let draws_b: Vec<f32> = (0..10).map(|_| b.next_f32()).collect();
+        assert_ne!(draws_a, draws_b);
+    }
+
+    #[test]
+    fn sample_seeded_reproduces_the_same_completion_across_runs() {
+        let logits = vec![0.5, 1.5, -0.3, 2.0, 0.1];
+        let params = SamplingParams { temperature: 0.8, top_k: Some(3), ..Default::default() };
+
+        let run = |seed: u64| {
+            let mut rng = SeededRng::new(seed);
+            let mut history = Vec::new();
+            let tokens: Vec<u32> = (0..20)
+                .map(|_| {
+                    let token = sample_seeded(&logits, &history, &params, &mut rng);
+                    history.push(token);
+                    token
+                })
+                .collect();
+            tokens
+        };
+
+        assert_eq!(run(123), run(123));
+    }
+
+    #[test]
+    fn greedy_temperature_zero_top_k_one_picks_the_argmax() {
+        let logits = vec![0.1, 5.0, 0.2, -1.0];
+        let params = SamplingParams { top_k: Some(1), ..Default::default() };
+        assert_eq!(sample(&logits, &[], &params, 0.999), 1);
+    }
+
+    #[test]
+    fn repetition_penalty_reduces_a_positive_logit() {
+        let mut logits = vec![2.0, 2.0];
+        apply_repetition_penalty(&mut logits, &[0], 2.0);
+        assert_eq!(logits, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn top_p_keeps_only_the_smallest_prefix_covering_the_mass() {
+        let logits = vec![10.0, -10.0, -10.0, -10.0];
+        let params = SamplingParams { top_p: Some(0.5), ..Default::default() };
+        // Token 0 alone already carries ~all the probability mass, so
+        // sampling should always return it regardless of the random draw.
+        for r in [0.0, 0.3, 0.9] {
+            assert_eq!(sample(&logits, &[], &params, r), 0);
+        }
+    }
+
+    #[test]
+    fn min_p_never_leaves_the_candidate_set_empty() {
+        let logits = vec![1.0, 1.0, 1.0];
+        let params = SamplingParams { min_p: Some(1.1), ..Default::default() };
+        // threshold above the top probability would empty the set without
+        // the fallback; make sure sampling still returns something.
+        let _ = sample(&logits, &[], &params, 0.5);
+    }
+
+    #[test]
+    fn resolve_generation_params_falls_back_to_defaults_with_no_preset_or_request_values() {
+        let effective = resolve_generation_params(None, &GenerationClamps::default(), &RequestedGenerationParams::default());
+        assert_eq!(effective, EffectiveGenerationParams { temperature: 1.0, top_p: 1.0, repetition_penalty: 1.0, stop: Vec::new() });
+    }
+
+    #[test]
+    fn resolve_generation_params_uses_the_preset_when_the_request_leaves_a_field_unset() {
+        let preset = GenerationPreset { name: "creative".to_string(), temperature: Some(1.4), top_p: Some(0.9), repetition_penalty: None, stop: vec!["\n\n".to_string()] };
+        let effective = resolve_generation_params(Some(&preset), &GenerationClamps::default(), &RequestedGenerationParams::default());
+        assert_eq!(effective.temperature, 1.4);
+        assert_eq!(effective.top_p, 0.9);
+        assert_eq!(effective.repetition_penalty, 1.0);
+        assert_eq!(effective.stop, vec!["\n\n".to_string()]);
+    }
+
+    #[test]
+    fn resolve_generation_params_lets_an_explicit_request_value_beat_the_preset() {
+        let preset = GenerationPreset { name: "creative".to_string(), temperature: Some(1.4), ..Default::default() };
+        let requested = RequestedGenerationParams { temperature: Some(0.2), ..Default::default() };
+        let effective = resolve_generation_params(Some(&preset), &GenerationClamps::default(), &requested);
+        assert_eq!(effective.temperature, 0.2);
+    }
+
+    #[test]
+    fn resolve_generation_params_clamps_a_request_value_that_exceeds_the_bound() {
+        let clamps = GenerationClamps { temperature: Some((0.0, 1.0)), ..Default::default() };
+        let requested = RequestedGenerationParams { temperature: Some(1.9), ..Default::default() };
+        let effective = resolve_generation_params(None, &clamps, &requested);
+        assert_eq!(effective.temperature, 1.0);
+    }
+
+    #[test]
+    fn resolve_generation_params_clamps_a_preset_default_too() {
+        // A preset can't hand out a value outside the operator's own clamp
+        // just because it's a default rather than something the client set.
+        let preset = GenerationPreset { temperature: Some(5.0), ..Default::default() };
+        let clamps = GenerationClamps { temperature: Some((0.0, 2.0)), ..Default::default() };
+        let effective = resolve_generation_params(Some(&preset), &clamps, &RequestedGenerationParams::default());
+        assert_eq!(effective.temperature, 2.0);
+    }
+}
@@ -0,0 +1,344 @@
+//! Continuous batching scheduler: instead of running one request to
+//! completion before starting the next, every decode step advances all
+//! in-flight requests together, and a finished request is swapped out for
+//! a queued one immediately rather than waiting for the whole batch to
+//! finish. This is what lets the server serve many concurrent chat
+//! completions without each one blocking behind the others.
+//!
+//! Requests carry a [`PriorityClass`] so batch workloads (bulk embedding
+//! jobs, background summarization) can't starve interactive chat traffic
+//! sharing the same batch slots: [`Scheduler::admit`] always drains
+//! `Interactive`'s queue before looking at `Batch`, and `Batch` before
+//! `Background`. Each class also has its own queue-length limit —
+//! unbounded batch traffic should back up and get told to slow down
+//! rather than pile up behind interactive requests forever.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub type RequestId = u64;
+
+/// A rough estimate of one decode step's wall-clock cost, used only to
+/// turn a shed request's queue position into a `retry_after` hint. This
+/// scheduler doesn't track measured step latency the way `metrics.rs`'s
+/// `Registry` tracks tokens/sec for completed requests, so the hint is a
+/// conservative guess rather than a measured one — good enough for a
+/// client deciding how long to back off, not for capacity planning.
+const ESTIMATED_STEP_DURATION: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PriorityClass {
+    Interactive,
+    Batch,
+    Background,
+}
+
+impl PriorityClass {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "interactive" => Some(PriorityClass::Interactive),
+            "batch" => Some(PriorityClass::Batch),
+            "background" => Some(PriorityClass::Background),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PriorityClass::Interactive => "interactive",
+            PriorityClass::Batch => "batch",
+            PriorityClass::Background => "background",
+        }
+    }
+}
+
+impl Default for PriorityClass {
+    /// A request with no configured class is treated as interactive
+    /// traffic — the safest default, since the alternative is silently
+    /// deprioritizing a caller that never opted into batch or background
+    /// behavior.
+    fn default() -> Self {
+        PriorityClass::Interactive
+    }
+}
+
+/// Per-class queue-length limits enforced by [`Scheduler::submit`]. `0`
+/// means unlimited, matching `config::ServerConfig::daily_token_quota`'s
+/// "zero disables the check" convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueLimits {
+    pub interactive: usize,
+    pub batch: usize,
+    pub background: usize,
+}
+
+impl QueueLimits {
+    fn limit_for(self, class: PriorityClass) -> usize {
+        match class {
+            PriorityClass::Interactive => self.interactive,
+            PriorityClass::Batch => self.batch,
+            PriorityClass::Background => self.background,
+        }
+    }
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        QueueLimits { interactive: 0, batch: 64, background: 256 }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SchedulerError {
+    /// `class`'s queue is already at its configured limit; `retry_after`
+    /// is a rough estimate of how long draining one class-appropriate slot
+    /// would take, suitable for a `Retry-After` response header.
+    QueueFull { class: PriorityClass, retry_after: Duration },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestState {
+    Queued,
+    Running,
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+pub struct InFlightRequest {
+    pub id: RequestId,
+    pub priority: PriorityClass,
+    pub prompt_tokens: Vec<u32>,
+    pub generated_tokens: Vec<u32>,
+    pub max_new_tokens: usize,
+    pub state: RequestState,
+}
+
+impl InFlightRequest {
+    fn is_done(&self) -> bool {
+        self.generated_tokens.len() >= self.max_new_tokens
+    }
+}
+
+/// Batches decode steps across concurrent requests, admitting queued
+/// requests into free batch slots as running ones finish. Queued requests
+/// are held in one `VecDeque` per [`PriorityClass`] rather than a single
+/// shared queue, so admission can always favor the higher-priority class
+/// without reordering (and thus starving) same-class requests behind it.
+pub struct Scheduler {
+    max_batch_size: usize,
+    limits: QueueLimits,
+    interactive: VecDeque<InFlightRequest>,
+    batch: VecDeque<InFlightRequest>,
+    background: VecDeque<InFlightRequest>,
+    running: Vec<InFlightRequest>,
+    next_id: RequestId,
+}
+
+impl Scheduler {
+    pub fn new(max_batch_size: usize) -> Self {
+        Scheduler::with_queue_limits(max_batch_size, QueueLimits::default())
+    }
+
+    pub fn with_queue_limits(max_batch_size: usize, limits: QueueLimits) -> Self {
+        Scheduler {
+            max_batch_size,
+            limits,
+            interactive: VecDeque::new(),
+            batch: VecDeque::new(),
+            background: VecDeque::new(),
+            running: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn queue_for(&mut self, class: PriorityClass) -> &mut VecDeque<InFlightRequest> {
+        match class {
+            PriorityClass::Interactive => &mut self.interactive,
+            PriorityClass::Batch => &mut self.batch,
+            PriorityClass::Background => &mut self.background,
+        }
+    }
+
+    /// Enqueues a new request under `priority` and returns the id it was
+    /// assigned, or sheds it with [`SchedulerError::QueueFull`] if
+    /// `priority`'s queue is already at its configured limit.
+    pub fn submit(&mut self, priority: PriorityClass, prompt_tokens: Vec<u32>, max_new_tokens: usize) -> Result<RequestId, SchedulerError> {
+        let limit = self.limits.limit_for(priority);
+        let queued_ahead = self.queue_for(priority).len();
+        if limit > 0 && queued_ahead >= limit {
+            return Err(SchedulerError::QueueFull { class: priority, retry_after: ESTIMATED_STEP_DURATION * (queued_ahead as u32 + 1) });
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue_for(priority).push_back(InFlightRequest {
+            id,
+            priority,
+            prompt_tokens,
+            generated_tokens: Vec::new(),
+            max_new_tokens,
+            state: RequestState::Queued,
+        });
+        Ok(id)
+    }
+
+    /// Moves queued requests into free running slots, draining
+    /// `Interactive` before `Batch` before `Background` so batch/background
+    /// traffic only ever fills slots interactive requests don't need right
+    /// now. Called before each decode step so newly finished slots get
+    /// backfilled immediately rather than sitting idle until the next full
+    /// batch turnover.
+    fn admit(&mut self) {
+        while self.running.len() < self.max_batch_size {
+            let next = self.interactive.pop_front().or_else(|| self.batch.pop_front()).or_else(|| self.background.pop_front());
+            let Some(mut request) = next else { break };
+            request.state = RequestState::Running;
+            self.running.push(request);
+        }
+    }
+
+    /// Runs one decode step: admits queued work into free slots, calls
+    /// `decode_token` once per running request to produce its next token,
+    /// and retires any request that has now hit `max_new_tokens`. Returns
+    /// the ids retired this step.
+    pub fn step<F>(&mut self, mut decode_token: F) -> Vec<RequestId>
+    where
+        F: FnMut(&InFlightRequest) -> u32,
+    {
+        self.admit();
+
+        for request in &mut self.running {
+            let token = decode_token(request);
+            request.generated_tokens.push(token);
+        }
+
+        let mut finished = Vec::new();
+        self.running.retain_mut(|request| {
+            if request.is_done() {
+                request.state = RequestState::Finished;
+                finished.push(request.id);
+                false
+            } else {
+                true
+            }
+        });
+        finished
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.running.len()
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.interactive.len() + self.batch.len() + self.background.len()
+    }
+
+    pub fn queued_count_for(&self, class: PriorityClass) -> usize {
+        match class {
+            PriorityClass::Interactive => self.interactive.len(),
+            PriorityClass::Batch => self.batch.len(),
+            PriorityClass::Background => self.background.len(),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.running.is_empty() && self.queued_count() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admits_queued_requests_up_to_batch_size() {
+        let mut scheduler = Scheduler::new(2);
+        scheduler.submit(PriorityClass::Interactive, vec![1], 3).unwrap();
+        scheduler.submit(PriorityClass::Interactive, vec![2], 3).unwrap();
+        scheduler.submit(PriorityClass::Interactive, vec![3], 3).unwrap();
+
+        scheduler.step(|_| 0);
+        assert_eq!(scheduler.running_count(), 2);
+        assert_eq!(scheduler.queued_count(), 1);
+    }
+
+    #[test]
+    fn backfills_a_slot_as_soon_as_a_request_finishes() {
+        let mut scheduler = Scheduler::new(1);
+        let short = scheduler.submit(PriorityClass::Interactive, vec![1], 1).unwrap();
+        scheduler.submit(PriorityClass::Interactive, vec![2], 1).unwrap();
+
+        let finished = scheduler.step(|_| 42);
+        assert_eq!(finished, vec![short]);
+        assert_eq!(scheduler.running_count(), 0);
+        assert_eq!(scheduler.queued_count(), 1);
+
+        scheduler.step(|_| 42);
+        assert_eq!(scheduler.queued_count(), 0);
+    }
+
+    #[test]
+    fn is_idle_once_every_request_finishes() {
+        let mut scheduler = Scheduler::new(4);
+        scheduler.submit(PriorityClass::Interactive, vec![1], 1).unwrap();
+        scheduler.step(|_| 1);
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn interactive_requests_are_admitted_before_batch_and_background() {
+        let mut scheduler = Scheduler::new(1);
+        scheduler.submit(PriorityClass::Background, vec![1], 5).unwrap();
+        scheduler.submit(PriorityClass::Batch, vec![2], 5).unwrap();
+        let interactive = scheduler.submit(PriorityClass::Interactive, vec![3], 5).unwrap();
+
+        scheduler.step(|_| 0);
+        let running_ids: Vec<_> = scheduler.running.iter().map(|r| r.id).collect();
+        assert_eq!(running_ids, vec![interactive]);
+    }
+
+    #[test]
+    fn batch_only_fills_slots_interactive_does_not_need() {
+        let mut scheduler = Scheduler::new(2);
+        scheduler.submit(PriorityClass::Batch, vec![1], 5).unwrap();
+        scheduler.submit(PriorityClass::Interactive, vec![2], 5).unwrap();
+
+        scheduler.step(|_| 0);
+        assert_eq!(scheduler.running_count(), 2);
+        assert_eq!(scheduler.queued_count(), 0);
+    }
+
+    #[test]
+    fn submit_sheds_load_once_a_class_queue_is_full() {
+        let mut scheduler = Scheduler::with_queue_limits(1, QueueLimits { interactive: 0, batch: 1, background: 0 });
+        scheduler.submit(PriorityClass::Batch, vec![1], 1).unwrap();
+        let err = scheduler.submit(PriorityClass::Batch, vec![2], 1).unwrap_err();
+        assert!(matches!(err, SchedulerError::QueueFull { class: PriorityClass::Batch, .. }));
+    }
+
+    #[test]
+    fn a_full_batch_queue_does_not_block_interactive_submissions() {
+        let mut scheduler = Scheduler::with_queue_limits(1, QueueLimits { interactive: 0, batch: 0, background: 0 });
+        scheduler.submit(PriorityClass::Batch, vec![1], 1).unwrap();
+        assert!(scheduler.submit(PriorityClass::Interactive, vec![2], 1).is_ok());
+    }
+
+    #[test]
+    fn shed_load_retry_after_grows_with_queue_depth() {
+        let mut scheduler = Scheduler::with_queue_limits(1, QueueLimits { interactive: 0, batch: 2, background: 0 });
+        scheduler.submit(PriorityClass::Batch, vec![1], 1).unwrap();
+        scheduler.submit(PriorityClass::Batch, vec![2], 1).unwrap();
+        let err = scheduler.submit(PriorityClass::Batch, vec![3], 1).unwrap_err();
+        match err {
+            SchedulerError::QueueFull { retry_after, .. } => assert!(retry_after >= ESTIMATED_STEP_DURATION * 2),
+        }
+    }
+
+    #[test]
+    fn priority_class_parse_round_trips_as_str() {
+        for class in [PriorityClass::Interactive, PriorityClass::Batch, PriorityClass::Background] {
+            assert_eq!(PriorityClass::parse(class.as_str()), Some(class));
+        }
+        assert_eq!(PriorityClass::parse("urgent"), None);
+    }
+}
@@ -0,0 +1,5198 @@
+//! OpenAI-compatible HTTP inference server: `/v1/chat/completions`,
+//! `/v1/completions`, and `/v1/models`, with request validation and SSE
+//! streaming. Backends are pluggable via [`InferenceBackend`] so the actual
+//! model runtime (GGUF + llama.cpp FFI, pure-Rust kernels, ...) can be
+//! swapped in later without touching the HTTP surface.
+//!
+//! There's no model runtime wired in yet, so `main` serves an
+//! [`EchoBackend`] that only proves the request/response contract end to
+//! end.
+
+mod admin;
+mod agent;
+mod audio;
+mod audit;
+mod auth;
+mod backend;
+mod base64;
+mod batches;
+mod bm25;
+mod cancellation;
+mod config;
+mod constraints;
+mod context_assembly;
+mod context_policy;
+mod cuda;
+mod dashboard;
+mod discovery;
+mod durability;
+mod embedding_cache;
+mod embeddings;
+mod errors;
+mod events;
+mod extract;
+mod gguf;
+mod gpu;
+mod guardrails;
+mod hardware;
+mod health;
+mod http;
+mod image;
+mod jobs;
+mod json;
+mod kernels;
+mod logging;
+mod lora;
+mod mcp;
+mod memory;
+mod metrics;
+mod mmap_loader;
+mod mock_backend;
+mod model_alias;
+mod model_loader;
+mod model_pool;
+mod pipelines;
+mod plugins;
+mod power;
+mod prefix_cache;
+mod prompt_templates;
+mod rag;
+mod registry;
+mod replay_backend;
+mod rerank;
+mod resources;
+mod response_cache;
+mod router;
+mod rpc;
+mod safetensors;
+mod sampling;
+mod scheduler;
+mod sessions;
+mod sha1;
+mod sha256;
+mod shard_loader;
+mod shutdown;
+mod stop_sequences;
+mod storage;
+mod tenancy;
+mod thermal;
+mod tls;
+mod tokenizer;
+mod tool_call_stream;
+mod tool_calls;
+mod tracing;
+mod transport;
+mod tts;
+mod usage;
+mod vectorstore;
+mod vulkan;
+mod watcher;
+mod websocket;
+
+use constraints::Grammar;
+use embeddings::{EmbeddingBackend, EmbeddingRequest, Pooling};
+use http::{Method, Request, Response, SseWriter};
+use json::{Json, ObjectBuilder};
+use std::net::TcpListener;
+use transport::Transport;
+use std::sync::{Arc, Mutex};
+use tts::{SpeechBackend, VoiceId};
+use vectorstore::VectorStore;
+
+/// A model backend capable of turning a prompt into text. `generate` is
+/// synchronous and returns the whole completion; `stream` calls `on_token`
+/// once per emitted chunk for the SSE path. Real backends (llama.cpp FFI,
+/// pure-Rust kernels) implement this without the HTTP layer knowing which.
+pub trait InferenceBackend: Send + Sync {
+    fn model_id(&self) -> &str;
+    fn generate(&self, prompt: &str) -> String;
+
+    /// Streams generated tokens to `on_token`, one call per token. Stops
+    /// early the moment `on_token` returns `false` — callers use this to
+    /// wire in cancellation (see `cancellation.rs`) or to bail out after a
+    /// failed SSE/WebSocket write instead of generating tokens nobody will
+    /// ever read.
+    fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool);
+
+    /// Vision-language variant of [`generate`](Self::generate): `images`
+    /// are the decoded, already-patch-ready inputs from an OpenAI-style
+    /// `image_url` content part. Defaults to ignoring them so text-only
+    /// backends (like [`EchoBackend`]) don't need to opt in explicitly;
+    /// LLaVA-class backends override it to prepend patch embeddings ahead
+    /// of the text tokens.
+    fn generate_with_images(&self, prompt: &str, images: &[image::Image]) -> String {
+        let _ = images;
+        self.generate(prompt)
+    }
+
+    /// The backend's memory shape, if it has resident weights at all.
+    /// Defaults to `None` so backends without real weights (like
+    /// [`EchoBackend`]) never trigger [`resources::MemoryBudget`] checks;
+    /// a real backend overrides this once it loads a GGUF model, deriving
+    /// the profile from that model's architecture metadata.
+    fn memory_profile(&self) -> Option<resources::ModelMemoryProfile> {
+        None
+    }
+
+    /// Merges `adapter`'s deltas into this backend's resident weights.
+    /// Defaults to a no-op success so backends without real weights (like
+    /// [`EchoBackend`]) accept any adapter selection without erroring; a
+    /// real backend overrides this to call `LoraAdapter::merge_tensor` once
+    /// per target tensor it holds.
+    fn apply_lora(&self, adapter: &lora::LoraAdapter) -> Result<(), lora::LoraError> {
+        let _ = adapter;
+        Ok(())
+    }
+
+    /// Log-probability info for one already-generated token, used to answer
+    /// a request's `logprobs`/`top_logprobs` fields for both the buffered
+    /// and the streaming paths — computed one token at a time (rather than
+    /// over a whole completion) so the two paths agree with each other by
+    /// construction. `top_n` is how many alternative tokens to report
+    /// alongside the chosen one (`0` for none). Defaults to `None` so
+    /// backends without real logits (like [`EchoBackend`]) don't have to
+    /// opt in explicitly; a real backend overrides this from its own
+    /// softmax output.
+    fn token_logprob(&self, token: &str, top_n: usize) -> Option<TokenLogprob> {
+        let _ = (token, top_n);
+        None
+    }
+}
+
+/// One token's log-probability, plus up to `top_n` alternatives — the data
+/// behind both OpenAI logprobs response shapes (see [`InferenceBackend::token_logprob`]).
+#[derive(Debug, Clone)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    pub top_logprobs: Vec<(String, f64)>,
+}
+
+/// Backend that echoes the prompt back word by word. Exists to prove the
+/// HTTP contract (validation, SSE framing, error shapes) before a real
+/// model runtime lands.
+pub struct EchoBackend {
+    id: String,
+}
+
+impl EchoBackend {
+    pub fn new(id: &str) -> Self {
+        EchoBackend { id: id.to_string() }
+    }
+}
+
+impl InferenceBackend for EchoBackend {
+    fn model_id(&self) -> &str {
+        &self.id
+    }
+
+    fn generate(&self, prompt: &str) -> String {
+        format!("echo: {prompt}")
+    }
+
+    fn stream(&self, prompt: &str, on_token: &mut dyn FnMut(&str) -> bool) {
+        for word in format!("echo: {prompt}").split_whitespace() {
+            if !on_token(&format!("{word} ")) {
+                return;
+            }
+        }
+    }
+
+    fn generate_with_images(&self, prompt: &str, images: &[image::Image]) -> String {
+        if images.is_empty() {
+            return self.generate(prompt);
+        }
+        format!("echo: {prompt} [{} image(s) attached]", images.len())
+    }
+
+    /// Deterministic, non-model-backed pseudo-logprob: longer tokens are
+    /// treated as "less likely", and the alternatives are synthetic
+    /// `{token}~0`, `{token}~1`, ... entries at decreasing logprob — the
+    /// same "prove the shape, not the quality" role
+    /// [`EmbeddingBackend::hidden_states`]'s fixed pseudo-embedding plays.
+    fn token_logprob(&self, token: &str, top_n: usize) -> Option<TokenLogprob> {
+        let logprob = -(1.0 + token.len() as f64 * 0.05);
+        let top_logprobs = (0..top_n).map(|i| (format!("{token}~{i}"), logprob - 1.0 - i as f64)).collect();
+        Some(TokenLogprob { token: token.to_string(), logprob, top_logprobs })
+    }
+}
+
+/// A model backend capable of turning decoded PCM audio into a transcript.
+/// Real backends wrap `whisper_ffi::WhisperModel`; `stream` is expected to
+/// call `on_partial` once per rolling window of audio it has decoded so
+/// far, for the WebSocket partial-transcript path.
+pub trait TranscriptionBackend: Send + Sync {
+    fn transcribe(&self, audio: &audio::PcmAudio, language: Option<&str>) -> String;
+    fn stream(&self, audio: &audio::PcmAudio, on_partial: &mut dyn FnMut(&str));
+}
+
+/// Reports the sample count back as "words" rather than doing real speech
+/// recognition, proving the request/response contract (WAV decoding,
+/// language field, WS partial streaming) before a real whisper.cpp backend
+/// lands — the same role `EchoBackend` plays for chat completions.
+impl TranscriptionBackend for EchoBackend {
+    fn transcribe(&self, audio: &audio::PcmAudio, language: Option<&str>) -> String {
+        format!(
+            "transcribed {} samples at {}Hz (lang: {})",
+            audio.samples.len(),
+            audio.sample_rate,
+            language.unwrap_or("auto")
+        )
+    }
+
+    fn stream(&self, audio: &audio::PcmAudio, on_partial: &mut dyn FnMut(&str)) {
+        for chunk in audio.samples.chunks(audio.sample_rate as usize).enumerate() {
+            on_partial(&format!("partial[{}]: {} samples", chunk.0, chunk.1.len()));
+        }
+    }
+}
+
+/// Deterministic, non-model-backed hidden states: each "token" (one byte of
+/// the input, in lieu of a real tokenizer wired into this demo backend) maps
+/// to a fixed pseudo-embedding derived from its value, so `/v1/embeddings`
+/// has something to pool and normalize before a real model runtime lands.
+impl EmbeddingBackend for EchoBackend {
+    fn hidden_size(&self) -> usize {
+        8
+    }
+
+    fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+        tokens
+            .iter()
+            .map(|&token| (0..self.hidden_size()).map(|d| (token as f32 + d as f32).sin()).collect())
+            .collect()
+    }
+}
+
+/// Finds the last user message in an OpenAI-style `messages` array, or
+/// falls back to the whole array's last entry if none is marked
+/// `"role": "user"`.
+fn last_user_message(body: &Json) -> Result<&Json, &'static str> {
+    let messages = body
+        .get("messages")
+        .and_then(Json::as_array)
+        .ok_or("\"messages\" must be a non-empty array")?;
+    if messages.is_empty() {
+        return Err("\"messages\" must be a non-empty array");
+    }
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.get("role").and_then(Json::as_str) == Some("user"))
+        .or_else(|| messages.last())
+        .ok_or("\"messages\" must be a non-empty array")
+}
+
+/// The content of the first `"role": "system"` message, if any — what
+/// [`context_policy::apply`] keeps intact when a chat request's prompt has
+/// to be truncated to fit the context window.
+fn system_prompt_from_chat_request(body: &Json) -> Option<String> {
+    let messages = body.get("messages").and_then(Json::as_array)?;
+    let system = messages.iter().find(|m| m.get("role").and_then(Json::as_str) == Some("system"))?;
+    system.get("content").and_then(Json::as_str).map(str::to_string)
+}
+
+/// Picks the message [`prompt_from_chat_request`] builds off of: a
+/// trailing `tool` role message when a tool round-trip just happened, so
+/// the model continues from what the tool reported rather than repeating
+/// its earlier turn, or the last `user` message otherwise.
+fn last_relevant_message(body: &Json) -> Result<&Json, &'static str> {
+    let messages = body
+        .get("messages")
+        .and_then(Json::as_array)
+        .ok_or("\"messages\" must be a non-empty array")?;
+    match messages.last() {
+        Some(m) if m.get("role").and_then(Json::as_str) == Some("tool") => Ok(m),
+        _ => last_user_message(body),
+    }
+}
+
+/// Extracts the last relevant message's `content` from an OpenAI-style
+/// `messages` array. `content` may be a plain string, or (for
+/// vision-language requests) an array of `{"type": "text", "text": ...}`
+/// / `{"type": "image_url", ...}` parts, in which case the text parts are
+/// joined with a space and the image parts are left for
+/// [`images_from_chat_request`] to decode.
+fn prompt_from_chat_request(body: &Json) -> Result<String, &'static str> {
+    let content = last_relevant_message(body)?
+        .get("content")
+        .ok_or("each message must have a \"content\" field")?;
+
+    if let Some(text) = content.as_str() {
+        return Ok(text.to_string());
+    }
+    let parts = content.as_array().ok_or("\"content\" must be a string or an array of parts")?;
+    let text = parts
+        .iter()
+        .filter(|p| p.get("type").and_then(Json::as_str) == Some("text"))
+        .filter_map(|p| p.get("text").and_then(Json::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(text)
+}
+
+/// Decodes any `{"type": "image_url", "image_url": {"url": "data:image/png;base64,..."}}`
+/// parts out of the last user message's `content` array. Only inline
+/// base64 PNG data URLs are supported — fetching remote `http(s)://` URLs
+/// would need a network client this server doesn't have (see
+/// `downloader.rs` for the one place it does fetch external resources).
+fn images_from_chat_request(body: &Json) -> Result<Vec<image::Image>, &'static str> {
+    let content = last_user_message(body)?.get("content");
+    let Some(parts) = content.and_then(Json::as_array) else { return Ok(Vec::new()) };
+
+    parts
+        .iter()
+        .filter(|p| p.get("type").and_then(Json::as_str) == Some("image_url"))
+        .map(|p| {
+            let url = p
+                .get("image_url")
+                .and_then(|u| u.get("url"))
+                .and_then(Json::as_str)
+                .ok_or("\"image_url.url\" must be a string")?;
+            let encoded = url
+                .strip_prefix("data:image/png;base64,")
+                .ok_or("only inline \"data:image/png;base64,...\" image URLs are supported")?;
+            let bytes = base64::decode(encoded).map_err(|_| "invalid base64 image data")?;
+            image::decode_png(&bytes).map_err(|_| "invalid PNG image data")
+        })
+        .collect()
+}
+
+/// Compiles a chat request's `response_format` field into a [`Grammar`],
+/// if present. Only `{"type": "json_schema", "schema": ...}` is
+/// recognized; `{"type": "text"}` (the default) means no constraint.
+///
+/// Note: without a real backend exposing per-token logits, this is
+/// enforced by validating the finished completion rather than masking
+/// tokens during generation — the intended [`Grammar::is_valid_prefix`]
+/// hook belongs in the sampling loop once a backend wires `sampling.rs`
+/// in (see `llama_ffi.rs::decode_and_get_logits`).
+fn grammar_from_response_format(body: &Json) -> Result<Option<Grammar>, &'static str> {
+    let Some(format) = body.get("response_format") else { return Ok(None) };
+    match format.get("type").and_then(Json::as_str) {
+        Some("json_schema") => {
+            let schema = format.get("schema").ok_or("\"response_format.schema\" is required")?;
+            Grammar::from_json_schema(schema).map(Some).map_err(|_| "unsupported json schema")
+        }
+        _ => Ok(None),
+    }
+}
+
+fn error_response(status: u16, reason: &'static str, message: &str) -> Response {
+    let (error_type, retryable) = errors::classify(status);
+    let body = ObjectBuilder::new()
+        .set(
+            "error",
+            ObjectBuilder::new()
+                .set("message", Json::String(message.to_string()))
+                .set("type", Json::String(error_type.to_string()))
+                .set("retryable", Json::Bool(retryable))
+                .build(),
+        )
+        .build();
+    Response::json(status, reason, &body.to_string())
+}
+
+/// A request or completion a `guardrails::GuardrailsEngine` blocked
+/// outright — reported as 403 rather than 400, since the request was
+/// well-formed and simply isn't allowed, plus the `"moderation"` field
+/// callers rely on to tell a policy block apart from a validation error.
+fn guardrails_blocked_response(result: &guardrails::ModerationResult) -> Response {
+    let body = ObjectBuilder::new()
+        .set(
+            "error",
+            ObjectBuilder::new()
+                .set("message", Json::String("request blocked by content policy".to_string()))
+                .set("type", Json::String("content_policy_violation".to_string()))
+                .build(),
+        )
+        .set("moderation", result.to_json())
+        .build();
+    Response::json(403, "Forbidden", &body.to_string())
+}
+
+/// Parses `/v1/embeddings`'s `input` field, which per the OpenAI spec may
+/// be a single string or an array of strings.
+fn inputs_from_embeddings_request(body: &Json) -> Result<Vec<String>, &'static str> {
+    match body.get("input") {
+        Some(Json::String(s)) => Ok(vec![s.clone()]),
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|i| i.as_str().map(str::to_string).ok_or("\"input\" array entries must be strings"))
+            .collect(),
+        _ => Err("\"input\" must be a string or array of strings"),
+    }
+}
+
+fn embedding_request_from_body(body: &Json) -> Result<EmbeddingRequest, &'static str> {
+    let pooling = match body.get("pooling").and_then(Json::as_str) {
+        Some("cls") => Pooling::Cls,
+        Some("last_token") => Pooling::LastToken,
+        Some("mean") | None => Pooling::Mean,
+        Some(_) => return Err("\"pooling\" must be one of mean, cls, last_token"),
+    };
+    let normalize = body.get("normalize").and_then(Json::as_bool).unwrap_or(true);
+    let dimensions = body.get("dimensions").and_then(Json::as_f64).map(|d| d as usize);
+    Ok(EmbeddingRequest { pooling, normalize, dimensions })
+}
+
+fn handle_embeddings(cache: &embedding_cache::EmbeddingCache, backend: &dyn EmbeddingBackend, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let inputs = match inputs_from_embeddings_request(&parsed) {
+        Ok(i) if !i.is_empty() => i,
+        Ok(_) => return error_response(400, "Bad Request", "\"input\" must not be empty"),
+        Err(msg) => return error_response(400, "Bad Request", msg),
+    };
+    let params = match embedding_request_from_body(&parsed) {
+        Ok(p) => p,
+        Err(msg) => return error_response(400, "Bad Request", msg),
+    };
+    let model_id = parsed.get("model").and_then(Json::as_str).unwrap_or("default");
+
+    let vectors = embedding_cache::embed_batch_cached(cache, backend, model_id, &inputs, &params);
+
+    let data = vectors
+        .into_iter()
+        .enumerate()
+        .map(|(index, vector)| {
+            ObjectBuilder::new()
+                .set("object", Json::String("embedding".to_string()))
+                .set("index", Json::Number(index as f64))
+                .set("embedding", Json::Array(vector.into_iter().map(|v| Json::Number(v as f64)).collect()))
+                .build()
+        })
+        .collect();
+    let body = ObjectBuilder::new()
+        .set("object", Json::String("list".to_string()))
+        .set("data", Json::Array(data))
+        .build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Parses `/v1/vectors/{collection}/{action}` into its two path segments,
+/// or `None` if the path doesn't have that shape.
+fn parse_vectors_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix("/v1/vectors/")?;
+    rest.split_once('/')
+}
+
+/// Parses `/v1/cancel/{request_id}` into `request_id`, or `None` if the
+/// path doesn't have that shape.
+fn parse_cancel_path(path: &str) -> Option<&str> {
+    path.strip_prefix("/v1/cancel/")
+}
+
+/// Cancels an in-flight streaming completion. A 404 means `request_id`
+/// isn't currently streaming — already finished, never existed, or wasn't
+/// a streaming request in the first place, since only `handle_chat_completions`
+/// and `handle_chat_completions_ws` register a token.
+fn handle_cancel(cancellation: &cancellation::CancellationRegistry, request_id: &str) -> Response {
+    if cancellation.cancel(request_id) {
+        Response::ok_json("{\"status\":\"ok\"}")
+    } else {
+        error_response(404, "Not Found", &format!("no in-flight request \"{request_id}\""))
+    }
+}
+
+/// Parses `/v1/sessions/{id}` and `/v1/sessions/{id}/messages` into the
+/// session id and an optional trailing segment, mirroring [`parse_vectors_path`].
+fn parse_sessions_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let rest = path.strip_prefix("/v1/sessions/")?;
+    match rest.split_once('/') {
+        Some((id, trailing)) => Some((id, Some(trailing))),
+        None => Some((rest, None)),
+    }
+}
+
+fn session_json(id: &sessions::SessionId, session: &sessions::Session) -> Json {
+    let messages: Vec<Json> = session
+        .messages
+        .iter()
+        .map(|m| ObjectBuilder::new().set("role", Json::String(m.role.clone())).set("content", Json::String(m.content.clone())).build())
+        .collect();
+    let mut body = ObjectBuilder::new().set("id", Json::String(id.clone())).set("messages", Json::Array(messages));
+    if let Some(tokens) = &session.cached_prefix_tokens {
+        body = body.set("cached_prefix_tokens", Json::Array(tokens.iter().map(|&t| Json::Number(t as f64)).collect()));
+    }
+    if let Some(summary) = &session.summary {
+        body = body.set("summary", Json::String(summary.clone()));
+    }
+    body.build()
+}
+
+/// Reads an optional `"memory": {"enabled": ..., "compact_above_tokens":
+/// ..., "keep_recent_turns": ...}` block out of a session-creation request
+/// body, falling back to `Session::default`'s settings for any field left
+/// unspecified — the same "override only what you name" shape
+/// `embedding_request_from_body` uses for its own optional fields.
+fn memory_settings_from_body(body: &Json) -> (bool, usize, usize) {
+    let defaults = sessions::Session::default();
+    let Some(memory) = body.get("memory") else {
+        return (defaults.memory_enabled, defaults.memory_compact_above_tokens, defaults.memory_keep_recent_turns);
+    };
+    let enabled = memory.get("enabled").and_then(Json::as_bool).unwrap_or(defaults.memory_enabled);
+    let compact_above_tokens = memory.get("compact_above_tokens").and_then(Json::as_f64).map(|n| n as usize).unwrap_or(defaults.memory_compact_above_tokens);
+    let keep_recent_turns = memory.get("keep_recent_turns").and_then(Json::as_f64).map(|n| n as usize).unwrap_or(defaults.memory_keep_recent_turns);
+    (enabled, compact_above_tokens, keep_recent_turns)
+}
+
+/// Handles `POST /v1/sessions`: starts a new conversation, optionally
+/// seeded with an initial `messages` array, so a client can resume it
+/// later via `GET /v1/sessions/{id}` after a restart instead of
+/// recomputing the full prefill from scratch (see `sessions.rs`).
+fn handle_create_session(store: &sessions::SessionStore, req: &Request) -> Response {
+    let parsed = req.body_str().ok().and_then(|text| if text.trim().is_empty() { None } else { Json::parse(text).ok() });
+    let messages = parsed
+        .as_ref()
+        .and_then(|parsed| parsed.get("messages").and_then(Json::as_array).map(|entries| {
+            entries
+                .iter()
+                .filter_map(|m| {
+                    let role = m.get("role").and_then(Json::as_str)?;
+                    let content = m.get("content").and_then(Json::as_str)?;
+                    Some(sessions::Message { role: role.to_string(), content: content.to_string() })
+                })
+                .collect()
+        }))
+        .unwrap_or_default();
+    let (memory_enabled, memory_compact_above_tokens, memory_keep_recent_turns) =
+        parsed.as_ref().map(memory_settings_from_body).unwrap_or_else(|| memory_settings_from_body(&Json::Null));
+    let session = sessions::Session { messages, memory_enabled, memory_compact_above_tokens, memory_keep_recent_turns, ..sessions::Session::default() };
+    match store.create(&session) {
+        Ok(id) => Response::ok_json(&session_json(&id, &session).to_string()),
+        Err(sessions::SessionError::Io(e)) => error_response(500, "Internal Server Error", &e),
+        Err(sessions::SessionError::NotFound(_)) => unreachable!("create never looks up an existing session"),
+    }
+}
+
+/// Handles `GET /v1/sessions/{id}` (fetch the conversation so far),
+/// `POST /v1/sessions/{id}/messages` (append one message to it), and
+/// `POST /v1/sessions/{id}/delete` (drop it) — a POST-based delete action
+/// rather than the `DELETE` HTTP method since `http.rs`'s `Method` enum
+/// only distinguishes `GET`/`POST`, the same constraint `handle_vectors`
+/// already works around with its `"delete"` action segment.
+fn handle_session(store: &sessions::SessionStore, backend: &dyn InferenceBackend, id: &str, trailing: Option<&str>, req: &Request) -> Response {
+    let id = id.to_string();
+    match (&req.method, trailing) {
+        (Method::Get, None) => match store.load(&id) {
+            Ok(session) => Response::ok_json(&session_json(&id, &session).to_string()),
+            Err(sessions::SessionError::NotFound(_)) => error_response(404, "Not Found", &format!("no session \"{id}\"")),
+            Err(sessions::SessionError::Io(e)) => error_response(500, "Internal Server Error", &e),
+        },
+        // The message list a completion request should actually send:
+        // `session.summary` (if any) injected ahead of the verbatim
+        // messages `memory::compact` left in place — see `memory.rs`'s
+        // module doc comment for why this lives as its own action instead
+        // of `/v1/chat/completions` reaching into the session store itself.
+        (Method::Get, Some("prompt")) => match store.load(&id) {
+            Ok(session) => {
+                let messages: Vec<Json> = memory::messages_for_prompt(&session)
+                    .into_iter()
+                    .map(|m| ObjectBuilder::new().set("role", Json::String(m.role)).set("content", Json::String(m.content)).build())
+                    .collect();
+                Response::ok_json(&ObjectBuilder::new().set("messages", Json::Array(messages)).build().to_string())
+            }
+            Err(sessions::SessionError::NotFound(_)) => error_response(404, "Not Found", &format!("no session \"{id}\"")),
+            Err(sessions::SessionError::Io(e)) => error_response(500, "Internal Server Error", &e),
+        },
+        (Method::Post, Some("messages")) => {
+            let text = match req.body_str() {
+                Ok(s) => s,
+                Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+            };
+            let parsed = match Json::parse(text) {
+                Ok(j) => j,
+                Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+            };
+            let (Some(role), Some(content)) = (parsed.get("role").and_then(Json::as_str), parsed.get("content").and_then(Json::as_str))
+            else {
+                return error_response(400, "Bad Request", "\"role\" and \"content\" must be strings");
+            };
+            let message = sessions::Message { role: role.to_string(), content: content.to_string() };
+            match store.append(&id, message) {
+                Ok(mut session) => {
+                    // Compaction runs inline on the append that crosses the
+                    // threshold rather than on a background timer — the
+                    // same "do it on the request that needs it" choice
+                    // `jobs::JobRegistry::run_due` makes moot for this
+                    // path, since there's no scheduled tick to hook into
+                    // here.
+                    if memory::needs_compaction(&session) {
+                        memory::compact(&mut session, backend);
+                        if let Err(e) = store.save(&id, &session) {
+                            let sessions::SessionError::Io(e) = e else { unreachable!("save never returns NotFound") };
+                            return error_response(500, "Internal Server Error", &e);
+                        }
+                    }
+                    Response::ok_json(&session_json(&id, &session).to_string())
+                }
+                Err(sessions::SessionError::NotFound(_)) => error_response(404, "Not Found", &format!("no session \"{id}\"")),
+                Err(sessions::SessionError::Io(e)) => error_response(500, "Internal Server Error", &e),
+            }
+        }
+        (Method::Post, Some("delete")) => match store.delete(&id) {
+            Ok(()) => Response::ok_json("{\"status\":\"ok\"}"),
+            Err(sessions::SessionError::NotFound(_)) => error_response(404, "Not Found", &format!("no session \"{id}\"")),
+            Err(sessions::SessionError::Io(e)) => error_response(500, "Internal Server Error", &e),
+        },
+        _ => Response::not_found(),
+    }
+}
+
+/// Parses `/v1/batches/{id}` and `/v1/batches/{id}/results` into the
+/// batch id and an optional trailing segment, mirroring [`parse_sessions_path`].
+fn parse_batches_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let rest = path.strip_prefix("/v1/batches/")?;
+    match rest.split_once('/') {
+        Some((id, trailing)) => Some((id, Some(trailing))),
+        None => Some((rest, None)),
+    }
+}
+
+fn batch_progress_json(id: &str, progress: &batches::BatchProgress) -> Json {
+    ObjectBuilder::new()
+        .set("id", Json::String(id.to_string()))
+        .set("status", Json::String(progress.status.as_str().to_string()))
+        .set("total", Json::Number(progress.total as f64))
+        .set("completed", Json::Number(progress.completed as f64))
+        .set("failed", Json::Number(progress.failed as f64))
+        .build()
+}
+
+/// Handles `POST /v1/usage`: the caller's own accounting entries recorded
+/// by `usage::UsageStore::record` (every `/v1/completions` and
+/// `/v1/chat/completions` call already writes one), optionally restricted
+/// to a `since`/`until` unix-second window in the JSON body. Returns a
+/// JSON array by default, or CSV when the body sets `"format": "csv"` —
+/// same POST-with-a-JSON-body shape `/v1/rag/query` uses for its own
+/// optional fields, rather than a query string this server's `http.rs`
+/// doesn't parse. A key with no usage yet gets an empty result, not an
+/// error, same as an unmapped key in `tenancy::TenantRegistry`.
+fn handle_usage(usage_store: &usage::UsageStore, usage_key: Option<&str>, req: &Request) -> Response {
+    let parsed = req
+        .body_str()
+        .ok()
+        .and_then(|text| if text.trim().is_empty() { None } else { Json::parse(text).ok() })
+        .unwrap_or(Json::Object(Default::default()));
+    let since = parsed.get("since").and_then(Json::as_f64).map(|f| f as u64);
+    let until = parsed.get("until").and_then(Json::as_f64).map(|f| f as u64);
+    let records = usage_store.query(usage_key, since, until);
+    if parsed.get("format").and_then(Json::as_str) == Some("csv") {
+        Response::ok_text(&usage::to_csv(&records), "text/csv")
+    } else {
+        Response::ok_json(&usage::to_json(&records).to_string())
+    }
+}
+
+/// Handles `POST /mcp`: this server's own Model Context Protocol surface
+/// (see `mcp.rs`'s module doc comment). Every JSON-RPC concern — method
+/// dispatch, error codes, the one `generate` tool exposed — lives in
+/// `mcp::dispatch`; this just plugs the request body and the active
+/// backend into it and wraps the result as a normal JSON response.
+fn handle_mcp(backend: &dyn InferenceBackend, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    Response::ok_json(&mcp::dispatch(&parsed, backend).to_string())
+}
+
+/// Handles `POST /v1/agents/runs`: reads a `"goal"` string (and an
+/// optional per-request `"max_steps"`, capped by the configured
+/// `agent.max_steps`) and streams `agent::run`'s trajectory back as one
+/// SSE event per step, the same `http::SseWriter` framing
+/// `handle_chat_completions` uses for token streaming, ending with the
+/// `data: [DONE]` sentinel [`http::SseWriter::finish`] sends. Unlike a
+/// chat completion's tool calls, an agent run's tool calls are executed
+/// server-side (see `agent.rs`'s module doc comment for why), so this
+/// handler holds the connection open for the whole run rather than
+/// returning after one generation.
+fn handle_agent_run(backend: &dyn InferenceBackend, agent_tools: &agent::AgentTools, configured_max_steps: usize, req: &Request, stream: &mut Transport) -> Option<Response> {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return Some(error_response(400, "Bad Request", "request body must be UTF-8")),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return Some(error_response(400, "Bad Request", &e.to_string())),
+    };
+    let Some(goal) = parsed.get("goal").and_then(Json::as_str) else {
+        return Some(error_response(400, "Bad Request", "\"goal\" must be a string"));
+    };
+    let max_steps = parsed.get("max_steps").and_then(Json::as_f64).map(|n| n as usize).map(|n| n.min(configured_max_steps)).unwrap_or(configured_max_steps);
+
+    let Ok(mut sse) = SseWriter::start(stream) else { return None };
+    agent::run(backend, agent_tools, goal, max_steps, &mut |step| {
+        let _ = sse.send(&step.to_string());
+    });
+    let _ = sse.finish();
+    None
+}
+
+/// Parses `/v1/pipelines/{name}/run` into `name`, or `None` if the path
+/// doesn't have that shape, mirroring [`parse_cancel_path`].
+fn parse_pipelines_path(path: &str) -> Option<&str> {
+    path.strip_prefix("/v1/pipelines/")?.strip_suffix("/run")
+}
+
+/// Handles `POST /v1/pipelines/{name}/run`: reads a `"input"` string,
+/// looks `name` up in `pipelines`, and streams `pipelines::run`'s
+/// per-step output back over the same [`http::SseWriter`] framing
+/// `handle_agent_run` uses for its own trajectory, ending with the
+/// `data: [DONE]` sentinel [`http::SseWriter::finish`] sends.
+fn handle_pipeline_run(
+    pipelines: &pipelines::PipelineRegistry,
+    backend: &dyn InferenceBackend,
+    embedding_backend: &dyn EmbeddingBackend,
+    vector_store: &Mutex<VectorStore>,
+    response_cache: &response_cache::ResponseCache,
+    name: &str,
+    req: &Request,
+    stream: &mut Transport,
+) -> Option<Response> {
+    let Some(pipeline) = pipelines.get(name) else {
+        return Some(error_response(404, "Not Found", &format!("no such pipeline \"{name}\"")));
+    };
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return Some(error_response(400, "Bad Request", "request body must be UTF-8")),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return Some(error_response(400, "Bad Request", &e.to_string())),
+    };
+    let input = parsed.get("input").and_then(Json::as_str).unwrap_or("");
+
+    let Ok(mut sse) = SseWriter::start(stream) else { return None };
+    if let Err(e) = pipelines::run(&pipeline, backend, embedding_backend, vector_store, response_cache, input, &mut |step| {
+        let _ = sse.send(&step.to_string());
+    }) {
+        let _ = sse.send(&ObjectBuilder::new().set("error", Json::String(e.message())).build().to_string());
+    }
+    let _ = sse.finish();
+    None
+}
+
+/// Parses `/v1/jobs/{id}`, `/v1/jobs/{id}/trigger`, and
+/// `/v1/jobs/{id}/cancel` into the job id and an optional trailing
+/// segment, mirroring [`parse_sessions_path`]. `/v1/jobs` itself (no
+/// trailing id) is matched separately as a fixed route for listing.
+fn parse_jobs_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let rest = path.strip_prefix("/v1/jobs/")?;
+    match rest.split_once('/') {
+        Some((id, trailing)) => Some((id, Some(trailing))),
+        None => Some((rest, None)),
+    }
+}
+
+fn job_json(id: &str, definition: &jobs::JobDefinition, state: &jobs::JobState) -> Json {
+    let mut body = ObjectBuilder::new()
+        .set("id", Json::String(id.to_string()))
+        .set("action", Json::String(job_action_name(&definition.action).to_string()))
+        .set("run_count", Json::Number(state.run_count as f64))
+        .set("last_output", Json::String(state.last_output.clone()));
+    if let Some(minute) = state.last_run_minute {
+        body = body.set("last_run_minute", Json::Number(minute as f64));
+    }
+    body.build()
+}
+
+fn job_action_name(action: &jobs::JobAction) -> &'static str {
+    match action {
+        jobs::JobAction::ReembedFolder { .. } => "reembed_folder",
+        jobs::JobAction::RefreshModel => "refresh_model",
+        jobs::JobAction::RunPipeline { .. } => "run_pipeline",
+        jobs::JobAction::Shell { .. } => "shell",
+    }
+}
+
+/// Handles `GET /v1/jobs`: lists every job the registry currently has
+/// loaded along with its last run's outcome.
+fn handle_list_jobs(jobs: &jobs::JobRegistry) -> Response {
+    let entries: Vec<Json> = jobs.ids().iter().filter_map(|id| jobs.get(id).map(|(definition, state)| job_json(id, &definition, &state))).collect();
+    Response::ok_json(&Json::Array(entries).to_string())
+}
+
+/// Handles `GET /v1/jobs/{id}`, `POST /v1/jobs/{id}/trigger`, and
+/// `POST /v1/jobs/{id}/cancel`, mirroring [`handle_batch`]'s
+/// method-and-trailing-segment dispatch.
+fn handle_job(jobs: &jobs::JobRegistry, ctx: &jobs::JobContext, id: &str, trailing: Option<&str>, req: &Request) -> Response {
+    match (&req.method, trailing) {
+        (Method::Get, None) => match jobs.get(id) {
+            Some((definition, state)) => Response::ok_json(&job_json(id, &definition, &state).to_string()),
+            None => error_response(404, "Not Found", &format!("no such job \"{id}\"")),
+        },
+        (Method::Post, Some("trigger")) => match jobs.trigger(id, ctx) {
+            Ok(output) => Response::ok_json(&ObjectBuilder::new().set("status", Json::String("ok".to_string())).set("output", Json::String(output)).build().to_string()),
+            Err(jobs::JobError::NotFound(message)) => error_response(404, "Not Found", &message),
+            Err(e) => error_response(500, "Internal Server Error", &e.message()),
+        },
+        (Method::Post, Some("cancel")) => {
+            if jobs.cancel(id) {
+                Response::ok_json("{\"status\":\"ok\"}")
+            } else {
+                error_response(404, "Not Found", &format!("no such job \"{id}\""))
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// Handles `POST /v1/batches`: resolves one model (and optional lora
+/// adapter) for the whole batch from the same top-level `model`/`lora`
+/// fields a single `/v1/completions` call would use, then hands the
+/// parsed `"requests"` array (or JSONL string) off to `batches::submit` to
+/// run in the background. Returns the assigned id immediately.
+///
+/// Unlike `/v1/completions`, a batch request skips `admit_request`'s
+/// per-request memory-budget check: it runs one request at a time in its
+/// own background thread rather than adding concurrent load, and
+/// threading a `'static` budget reference through every batch closure is
+/// more machinery than a best-effort offline job needs today.
+fn handle_create_batch(
+    backend: &'static dyn InferenceBackend,
+    pool: &model_pool::ModelPool,
+    adapters: &lora::AdapterRegistry,
+    batch_store: &'static batches::BatchStore,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    aliases: &model_alias::AliasRegistry,
+    req: &Request,
+) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let Some(requests_field) = parsed.get("requests") else {
+        return error_response(400, "Bad Request", "\"requests\" must be a JSON array or a JSONL string");
+    };
+    let requests = match batches::parse_requests(requests_field) {
+        Ok(requests) => requests,
+        Err(batches::BatchError::InvalidRequest(msg)) => return error_response(400, "Bad Request", &msg),
+        Err(batches::BatchError::Io(e)) => return error_response(500, "Internal Server Error", &e),
+    };
+    // Batches don't mirror shadow traffic — see `fire_shadow_request`'s
+    // doc comment; an offline batch already has nothing live to protect
+    // from the extra load, so the only thing worth threading through here
+    // is which real model an alias picks.
+    let (resolved, _alias) = match resolve_backend(pool, backend, tenants, tenant, aliases, &parsed) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+    if let Err(response) = apply_requested_lora(&*resolved, adapters, &parsed) {
+        return response;
+    }
+
+    // An empty string means "the top-level default backend", the same way
+    // `resolve_backend` treats an absent `model` field — distinct from a
+    // pooled model's real id, which `batches::resume` re-resolves through
+    // `model_pool::ModelPool::get_or_load` at startup.
+    let model_id = match &resolved {
+        ResolvedBackend::Default(_) => String::new(),
+        ResolvedBackend::Pooled(_) => resolved.model_id().to_string(),
+    };
+    let process = move |request: &Json| -> Result<Json, String> {
+        let prompt = request.get("prompt").and_then(Json::as_str).ok_or("\"prompt\" must be a string")?;
+        Ok(ObjectBuilder::new().set("text", Json::String(resolved.generate(prompt))).build())
+    };
+
+    match batches::submit(batch_store, &model_id, requests, process) {
+        Ok(id) => Response::ok_json(&ObjectBuilder::new().set("id", Json::String(id)).set("status", Json::String("queued".to_string())).build().to_string()),
+        Err(batches::BatchError::InvalidRequest(msg)) => error_response(400, "Bad Request", &msg),
+        Err(batches::BatchError::Io(e)) => error_response(500, "Internal Server Error", &e),
+    }
+}
+
+/// Handles `GET /v1/batches/{id}` (progress) and `GET /v1/batches/{id}/results`
+/// (the JSONL output file written so far, whether or not the batch has
+/// finished — a client can start reading completed lines before the rest
+/// arrive).
+fn handle_batch(store: &batches::BatchStore, id: &str, trailing: Option<&str>, req: &Request) -> Response {
+    let id = id.to_string();
+    match (&req.method, trailing) {
+        (Method::Get, None) => match store.progress(&id) {
+            Some(progress) => Response::ok_json(&batch_progress_json(&id, &progress).to_string()),
+            None => error_response(404, "Not Found", &format!("no batch \"{id}\"")),
+        },
+        (Method::Get, Some("results")) => match store.progress(&id) {
+            Some(_) => match store.output(&id) {
+                Ok(output) => Response::ok_text(&output, "application/x-ndjson"),
+                Err(e) => error_response(500, "Internal Server Error", &e.to_string()),
+            },
+            None => error_response(404, "Not Found", &format!("no batch \"{id}\"")),
+        },
+        _ => Response::not_found(),
+    }
+}
+
+/// Parses `/admin/models/{model_id}/{action}` into its two path segments,
+/// mirroring [`parse_vectors_path`].
+fn parse_admin_model_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix("/admin/models/")?;
+    rest.split_once('/')
+}
+
+/// Loads or unloads a model on demand instead of waiting for the next
+/// request to name it (`load`) or for `model_pool`'s idle reaper (`unload`)
+/// — an operator warming up or shedding a model without touching traffic.
+fn handle_admin_model(pool: &model_pool::ModelPool, model_id: &str, action: &str) -> Response {
+    match action {
+        "load" => match pool.get_or_load(model_id) {
+            Some(_) => Response::ok_json("{\"status\":\"ok\"}"),
+            None => errors::ServerError::ModelNotFound(format!("model \"{model_id}\" is not available")).into_response(),
+        },
+        "unload" => {
+            if pool.unload(model_id) {
+                Response::ok_json("{\"status\":\"ok\"}")
+            } else {
+                error_response(404, "Not Found", &format!("model \"{model_id}\" is not loaded"))
+            }
+        }
+        other => error_response(404, "Not Found", &format!("unknown admin model action \"{other}\"")),
+    }
+}
+
+/// Toggles [`admin::AdminState::is_draining`] from the request body's
+/// `"draining"` field. `route` checks this flag on the inference endpoints
+/// (see its doc comment) once true, so an operator can stop new work from
+/// landing before taking the process down — actually closing existing
+/// connections is `serve_one`'s job once graceful shutdown lands.
+fn handle_admin_drain(admin: &admin::AdminState, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let Some(draining) = parsed.get("draining").and_then(Json::as_bool) else {
+        return error_response(400, "Bad Request", "\"draining\" must be a boolean");
+    };
+    admin.set_draining(draining);
+    Response::ok_json(&ObjectBuilder::new().set("draining", Json::Bool(draining)).build().to_string())
+}
+
+/// Clears every loaded model's prefix cache in place — see
+/// `model_pool::ModelPool::flush_prefix_caches`.
+fn handle_admin_flush_cache(pool: &model_pool::ModelPool) -> Response {
+    pool.flush_prefix_caches();
+    Response::ok_json("{\"status\":\"ok\"}")
+}
+
+/// Reports what this tree actually has to report about scheduling: which
+/// models are resident. There's no continuous-batching `Scheduler`
+/// instance wired into the live request path yet, so queue/running counts
+/// per `scheduler::PriorityClass` aren't available here until it is —
+/// `auth::AuthRegistry::priority_class_for` already resolves which class
+/// each API key's requests belong to, ready for whenever a live
+/// `Scheduler` needs it.
+fn handle_admin_scheduler(pool: &model_pool::ModelPool) -> Response {
+    let loaded = pool.loaded_model_ids().into_iter().map(Json::String).collect();
+    Response::ok_json(&ObjectBuilder::new().set("loaded_models", Json::Array(loaded)).build().to_string())
+}
+
+/// Runs `storage::gc` against the live model cache, protecting whatever
+/// `pool` currently has resident — an operator freeing disk space without
+/// waiting for the next scheduled check, or without setting
+/// `models.max_cache_bytes` at all (`max_cache_bytes` of `0` makes this a
+/// harmless no-op, same as the config default).
+fn handle_admin_gc(pool: &model_pool::ModelPool, model_registry: &Mutex<registry::ModelRegistry>, max_cache_bytes: u64) -> Response {
+    let protected = pool.loaded_model_ids().into_iter().collect();
+    let mut model_registry = model_registry.lock().unwrap();
+    match storage::gc(&mut model_registry, max_cache_bytes, &protected) {
+        Ok(report) => Response::ok_json(&report.to_json()),
+        Err(e) => error_response(500, "Internal Server Error", &e.to_string()),
+    }
+}
+
+/// Reads or changes the runtime log level from the request body's
+/// `"level"` field (`"debug"`, `"info"`, `"warn"`, or `"error"`). See
+/// `admin::AdminState`'s doc comment for why nothing consumes this yet.
+fn handle_admin_log_level(admin: &admin::AdminState, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let Some(level) = parsed.get("level").and_then(Json::as_str).and_then(admin::LogLevel::parse) else {
+        return error_response(400, "Bad Request", "\"level\" must be one of debug, info, warn, error");
+    };
+    admin.set_log_level(level);
+    Response::ok_json(&ObjectBuilder::new().set("level", Json::String(level.as_str().to_string())).build().to_string())
+}
+
+/// Reports the current thermal reading (`None` on unsupported platforms or
+/// when the probe fails) plus what `ThermalGovernor::default()` would
+/// recommend for a full-size batch, so an operator can see throttling
+/// coming before it shows up as unexplained latency. See `thermal.rs`'s
+/// module doc comment for why nothing in the request path consumes this
+/// automatically yet.
+fn handle_admin_thermal(pool: &model_pool::ModelPool) -> Response {
+    let Some(reading) = thermal::ThermalReading::probe() else {
+        return Response::ok_json(&ObjectBuilder::new().set("available", Json::Bool(false)).build().to_string());
+    };
+    let governor = thermal::ThermalGovernor::default();
+    let max_batch_size = pool.loaded_model_ids().len().max(1);
+    let recommended_batch_size = governor.recommended_batch_size(&reading, max_batch_size, max_batch_size);
+    Response::ok_json(
+        &ObjectBuilder::new()
+            .set("available", Json::Bool(true))
+            .set(
+                "temperature_celsius",
+                reading.temperature_celsius.map(Json::Number).unwrap_or(Json::Null),
+            )
+            .set("power_watts", reading.power_watts.map(Json::Number).unwrap_or(Json::Null))
+            .set("speed_limit_percent", Json::Number(reading.speed_limit_percent as f64))
+            .set("recommended_batch_size", Json::Number(recommended_batch_size as f64))
+            .build()
+            .to_string(),
+    )
+}
+
+/// Reports each tenant's keys and their summed daily token usage, rolling
+/// up `auth::AuthRegistry::quota_used` per key since quotas themselves
+/// stay tracked per key, not per tenant — see `tenancy.rs`'s module doc
+/// comment.
+fn handle_admin_tenants(auth: &auth::AuthRegistry, tenants: &tenancy::TenantRegistry) -> Response {
+    let data = tenants
+        .tenant_ids()
+        .into_iter()
+        .map(|tenant| {
+            let quota_used: u64 = tenants.keys_for(tenant).iter().map(|key| auth.quota_used(key)).sum();
+            ObjectBuilder::new()
+                .set("tenant", Json::String(tenant.to_string()))
+                .set("keys", Json::Number(tenants.keys_for(tenant).len() as f64))
+                .set("quota_used", Json::Number(quota_used as f64))
+                .build()
+        })
+        .collect();
+    Response::ok_json(&Json::Array(data).to_string())
+}
+
+/// Lists every registered prompt template's name and current version. See
+/// `prompt_templates.rs`'s module doc comment for why there's no on-disk
+/// catalog to reconstruct this from at startup.
+fn handle_admin_list_templates(templates: &prompt_templates::TemplateRegistry) -> Response {
+    let data = templates
+        .list()
+        .into_iter()
+        .map(|(name, version)| {
+            ObjectBuilder::new()
+                .set("name", Json::String(name))
+                .set("version", Json::Number(version as f64))
+                .build()
+        })
+        .collect();
+    Response::ok_json(&Json::Array(data).to_string())
+}
+
+/// Registers (or re-registers) a template from `{"name", "messages":
+/// [{"role", "content"}], "variables": [...]}`, returning the new version
+/// `TemplateRegistry::register` assigned.
+fn handle_admin_register_template(templates: &prompt_templates::TemplateRegistry, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let Some(name) = parsed.get("name").and_then(Json::as_str) else {
+        return error_response(400, "Bad Request", "\"name\" must be a string");
+    };
+    let Some(raw_messages) = parsed.get("messages").and_then(Json::as_array) else {
+        return error_response(400, "Bad Request", "\"messages\" must be an array");
+    };
+    let mut messages = Vec::with_capacity(raw_messages.len());
+    for message in raw_messages {
+        let (Some(role), Some(content)) = (
+            message.get("role").and_then(Json::as_str),
+            message.get("content").and_then(Json::as_str),
+        ) else {
+            return error_response(400, "Bad Request", "each message must have string \"role\" and \"content\"");
+        };
+        messages.push(prompt_templates::TemplateMessage { role: role.to_string(), content: content.to_string() });
+    }
+    let variables = parsed
+        .get("variables")
+        .and_then(Json::as_array)
+        .map(|vars| vars.iter().filter_map(Json::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    let version = templates.register(name, messages, variables);
+    Response::ok_json(&ObjectBuilder::new().set("name", Json::String(name.to_string())).set("version", Json::Number(version as f64)).build().to_string())
+}
+
+fn handle_vectors(store: &Mutex<VectorStore>, tenant: Option<&str>, collection_name: &str, action: &str, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let collection_name = tenancy::TenantRegistry::namespaced_collection(tenant, collection_name);
+    let mut store = store.lock().unwrap();
+    let collection = store.collection(&collection_name);
+
+    match action {
+        "upsert" => {
+            let id = match parsed.get("id").and_then(Json::as_f64) {
+                Some(id) => id as vectorstore::VectorId,
+                None => return error_response(400, "Bad Request", "\"id\" must be a number"),
+            };
+            let vector = match parsed.get("vector").and_then(Json::as_array) {
+                Some(v) => v.iter().filter_map(Json::as_f64).map(|f| f as f32).collect(),
+                None => return error_response(400, "Bad Request", "\"vector\" must be an array of numbers"),
+            };
+            let metadata = parsed.get("metadata").cloned().unwrap_or(Json::Null);
+            match collection.upsert(id, vector, metadata) {
+                Ok(()) => Response::ok_json("{\"status\":\"ok\"}"),
+                Err(_) => error_response(400, "Bad Request", "vector dimensions do not match the collection"),
+            }
+        }
+        "query" => {
+            let vector: Vec<f32> = match parsed.get("vector").and_then(Json::as_array) {
+                Some(v) => v.iter().filter_map(Json::as_f64).map(|f| f as f32).collect(),
+                None => return error_response(400, "Bad Request", "\"vector\" must be an array of numbers"),
+            };
+            let k = parsed.get("k").and_then(Json::as_f64).unwrap_or(10.0) as usize;
+            match parsed.get("mode").and_then(Json::as_str) {
+                Some("hybrid") => {
+                    let query_text = match parsed.get("query_text").and_then(Json::as_str) {
+                        Some(t) => t,
+                        None => return error_response(400, "Bad Request", "\"query_text\" must be a string when \"mode\" is \"hybrid\""),
+                    };
+                    let hits = collection.hybrid_query(&vector, query_text, k, |_| true);
+                    let data = hits
+                        .into_iter()
+                        .map(|hit| {
+                            ObjectBuilder::new()
+                                .set("id", Json::Number(hit.id as f64))
+                                .set("score", Json::Number(hit.score as f64))
+                                .set("metadata", hit.metadata)
+                                .build()
+                        })
+                        .collect();
+                    Response::ok_json(&Json::Array(data).to_string())
+                }
+                Some(other) => error_response(400, "Bad Request", &format!("unknown \"mode\": {other}")),
+                None => {
+                    let hits = collection.query(&vector, k, |_| true);
+                    let data = hits
+                        .into_iter()
+                        .map(|hit| {
+                            ObjectBuilder::new()
+                                .set("id", Json::Number(hit.id as f64))
+                                .set("distance", Json::Number(hit.distance as f64))
+                                .set("metadata", hit.metadata)
+                                .build()
+                        })
+                        .collect();
+                    Response::ok_json(&Json::Array(data).to_string())
+                }
+            }
+        }
+        "delete" => {
+            let id = match parsed.get("id").and_then(Json::as_f64) {
+                Some(id) => id as vectorstore::VectorId,
+                None => return error_response(400, "Bad Request", "\"id\" must be a number"),
+            };
+            match collection.delete(id) {
+                Ok(()) => Response::ok_json("{\"status\":\"ok\"}"),
+                Err(_) => error_response(404, "Not Found", "no vector with that id in this collection"),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// Handles `/v1/rag/query`: embeds the query, retrieves the closest chunks
+/// from the named collection, and returns both the assembled prompt (ready
+/// to hand to `handle_chat_completions`) and the retrieved chunks so
+/// callers can show their sources.
+fn handle_rag_query(store: &Mutex<VectorStore>, embedding_backend: &dyn EmbeddingBackend, tenant: Option<&str>, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let collection_name = match parsed.get("collection").and_then(Json::as_str) {
+        Some(c) => c,
+        None => return error_response(400, "Bad Request", "\"collection\" must be a string"),
+    };
+    let collection_name = tenancy::TenantRegistry::namespaced_collection(tenant, collection_name);
+    let query = match parsed.get("query").and_then(Json::as_str) {
+        Some(q) => q,
+        None => return error_response(400, "Bad Request", "\"query\" must be a string"),
+    };
+    let top_k = parsed.get("top_k").and_then(Json::as_f64).unwrap_or(3.0) as usize;
+    let rerank_params = match rerank_request_from_body(&parsed) {
+        Ok(p) => p,
+        Err(msg) => return error_response(400, "Bad Request", msg),
+    };
+
+    let store = store.lock().unwrap();
+    let collection = match store.collection_ref(&collection_name) {
+        Some(c) => c,
+        None => return error_response(404, "Not Found", "no such collection"),
+    };
+    let (prompt, retrieved) = rag::assemble_prompt(collection, embedding_backend, query, top_k, &EmbeddingRequest::default());
+    let retrieved = match rerank_params {
+        Some(params) => {
+            let rerank_backend = rerank::EmbeddingRerankBackend { embedding_backend };
+            rag::rerank_retrieved(&rerank_backend, query, retrieved, &params)
+        }
+        None => retrieved,
+    };
+
+    let sources = retrieved
+        .into_iter()
+        .map(|chunk| {
+            let mut source = ObjectBuilder::new().set("text", Json::String(chunk.text)).set("distance", Json::Number(chunk.distance as f64));
+            if let Some(score) = chunk.rerank_score {
+                source = source.set("rerank_score", Json::Number(score as f64));
+            }
+            source.build()
+        })
+        .collect();
+    let body = ObjectBuilder::new()
+        .set("prompt", Json::String(prompt))
+        .set("sources", Json::Array(sources))
+        .build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Handles `/v1/context/assemble`: `{"model": "...", "budget_tokens": ...,
+/// "blocks": [{"id": "...", "text": "...", "priority": ...}, ...]}` ->
+/// the highest-priority blocks that fit the budget, packed into a single
+/// prompt. `id` and `priority` are both optional per block (defaulting to
+/// the block's index and `0`), so a caller that doesn't care about
+/// prioritization can just send `text` fields. Token counts come from the
+/// target model's real tokenizer when it resolves to a loadable GGUF file
+/// (see `context_assembly::count_tokens_for_model`), so the packing lines
+/// up with what the model will actually see instead of a character or
+/// whitespace estimate.
+fn handle_context_assemble(model_registry: &Mutex<registry::ModelRegistry>, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let model = match parsed.get("model").and_then(Json::as_str) {
+        Some(m) => m,
+        None => return error_response(400, "Bad Request", "\"model\" must be a string"),
+    };
+    let budget_tokens = match parsed.get("budget_tokens").and_then(Json::as_f64) {
+        Some(b) if b >= 0.0 => b as usize,
+        _ => return error_response(400, "Bad Request", "\"budget_tokens\" must be a non-negative number"),
+    };
+    let raw_blocks = match parsed.get("blocks").and_then(Json::as_array) {
+        Some(b) => b,
+        None => return error_response(400, "Bad Request", "\"blocks\" must be an array"),
+    };
+    let mut blocks = Vec::with_capacity(raw_blocks.len());
+    for (index, raw) in raw_blocks.iter().enumerate() {
+        let block_text = match raw.get("text").and_then(Json::as_str) {
+            Some(t) => t.to_string(),
+            None => return error_response(400, "Bad Request", "each block must have a \"text\" string"),
+        };
+        let id = raw.get("id").and_then(Json::as_str).map(str::to_string).unwrap_or_else(|| index.to_string());
+        let priority = raw.get("priority").and_then(Json::as_f64).unwrap_or(0.0) as i32;
+        blocks.push(context_assembly::ContextBlock { id, text: block_text, priority });
+    }
+
+    let registry = model_registry.lock().unwrap();
+    let packed = context_assembly::pack_context(&blocks, budget_tokens, |text| context_assembly::count_tokens_for_model(&registry, model, text));
+
+    let included = packed
+        .included
+        .into_iter()
+        .map(|b| ObjectBuilder::new().set("id", Json::String(b.id)).set("tokens", Json::Number(b.tokens as f64)).build())
+        .collect();
+    let body = ObjectBuilder::new()
+        .set("prompt", Json::String(packed.prompt))
+        .set("included", Json::Array(included))
+        .set("dropped", Json::Array(packed.dropped.into_iter().map(Json::String).collect()))
+        .set("total_tokens", Json::Number(packed.total_tokens as f64))
+        .build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Reads an optional `"rerank": {"top_n": ..., "score_threshold": ...}`
+/// stage out of a `/v1/rag/query` request body. `None` means the caller
+/// didn't ask for it, so `handle_rag_query` skips the rerank pass entirely
+/// and returns chunks ordered by vector distance alone — reranking costs an
+/// extra pass over every candidate, so it stays opt-in rather than always-on.
+fn rerank_request_from_body(body: &Json) -> Result<Option<rerank::RerankRequest>, &'static str> {
+    let Some(stage) = body.get("rerank") else { return Ok(None) };
+    if matches!(stage, Json::Bool(false)) {
+        return Ok(None);
+    }
+    let top_n = match stage.get("top_n") {
+        Some(Json::Number(n)) => Some(*n as usize),
+        Some(_) => return Err("\"rerank.top_n\" must be a number"),
+        None => None,
+    };
+    let score_threshold = match stage.get("score_threshold") {
+        Some(Json::Number(t)) => Some(*t as f32),
+        Some(_) => return Err("\"rerank.score_threshold\" must be a number"),
+        None => None,
+    };
+    Ok(Some(rerank::RerankRequest { top_n, score_threshold }))
+}
+
+/// Handles `/v1/rerank`: `{"query": "...", "documents": ["...", ...],
+/// "top_n": ..., "score_threshold": ...}` -> documents reordered by
+/// cross-encoder relevance. Runs against `embedding_backend` wrapped in
+/// [`rerank::EmbeddingRerankBackend`] since this tree has no dedicated
+/// cross-encoder runtime yet — see that type's doc comment.
+fn handle_rerank(embedding_backend: &dyn EmbeddingBackend, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let query = match parsed.get("query").and_then(Json::as_str) {
+        Some(q) => q,
+        None => return error_response(400, "Bad Request", "\"query\" must be a string"),
+    };
+    let documents: Vec<String> = match parsed.get("documents").and_then(Json::as_array) {
+        Some(items) => match items.iter().map(|i| i.as_str().map(str::to_string).ok_or("\"documents\" array entries must be strings")).collect() {
+            Ok(docs) => docs,
+            Err(msg) => return error_response(400, "Bad Request", msg),
+        },
+        None => return error_response(400, "Bad Request", "\"documents\" must be an array of strings"),
+    };
+    if documents.is_empty() {
+        return error_response(400, "Bad Request", "\"documents\" must not be empty");
+    }
+    let params = match rerank_request_from_body(&parsed) {
+        Ok(p) => p.unwrap_or_default(),
+        Err(msg) => return error_response(400, "Bad Request", msg),
+    };
+
+    let query_tokens: Vec<u32> = query.bytes().map(u32::from).collect();
+    let document_tokens: Vec<Vec<u32>> = documents.iter().map(|d| d.bytes().map(u32::from).collect()).collect();
+    let rerank_backend = rerank::EmbeddingRerankBackend { embedding_backend };
+    let results = rerank::rerank(&rerank_backend, &query_tokens, &document_tokens, &params);
+
+    let data = results
+        .into_iter()
+        .map(|r| {
+            ObjectBuilder::new()
+                .set("index", Json::Number(r.index as f64))
+                .set("score", Json::Number(r.score as f64))
+                .set("document", Json::String(documents[r.index].clone()))
+                .build()
+        })
+        .collect();
+    let body = ObjectBuilder::new().set("results", Json::Array(data)).build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Handles `/v1/audio/transcriptions`: expects a raw WAV file as the
+/// request body (mp3/ogg aren't decodable in pure Rust — see `audio.rs`)
+/// and an optional `X-Language` header to pin the language instead of
+/// relying on backend auto-detection.
+fn handle_audio_transcriptions(backend: &dyn TranscriptionBackend, req: &Request) -> Response {
+    let pcm = match audio::decode_wav(&req.body) {
+        Ok(pcm) => pcm,
+        Err(_) => return error_response(400, "Bad Request", "request body must be a PCM WAV file"),
+    };
+    let language = req.header("x-language");
+    let text = backend.transcribe(&pcm, language);
+    let body = ObjectBuilder::new().set("text", Json::String(text)).build();
+    Response::ok_json(&body.to_string())
+}
+
+/// WebSocket variant of transcription: reads one binary-as-text WAV
+/// payload (base64, since `websocket::read_text_frame` only handles text
+/// frames today) and streams partial transcripts as they're produced,
+/// mirroring `handle_chat_completions_ws`'s shape.
+fn handle_audio_transcriptions_ws(backend: &dyn TranscriptionBackend, req: &Request, stream: &mut Transport) {
+    if websocket::handshake(req, stream).is_err() {
+        return;
+    }
+    let Ok(text) = websocket::read_text_frame(stream) else { return };
+    let Ok(bytes) = base64::decode(&text) else { return };
+    let Ok(pcm) = audio::decode_wav(&bytes) else { return };
+
+    backend.stream(&pcm, &mut |partial| {
+        let _ = websocket::send_text(stream, partial);
+    });
+    let _ = websocket::send_close(stream);
+}
+
+/// Handles `/v1/audio/speech`: `{"input": "...", "voice": "..."}` ->
+/// a WAV file body. Only WAV output is supported (see `tts.rs`'s module
+/// doc for why opus streaming isn't).
+fn handle_audio_speech(backend: &dyn SpeechBackend, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let input = match parsed.get("input").and_then(Json::as_str) {
+        Some(i) => i,
+        None => return error_response(400, "Bad Request", "\"input\" must be a string"),
+    };
+    let voice = VoiceId(parsed.get("voice").and_then(Json::as_str).unwrap_or("en-us").to_string());
+
+    match backend.synthesize(input, &voice) {
+        Ok(pcm) => Response {
+            status: 200,
+            reason: "OK",
+            headers: vec![("Content-Type".to_string(), "audio/wav".to_string())],
+            body: audio::encode_wav(&pcm),
+        },
+        Err(tts::SpeechError::UnknownVoice(v)) => error_response(400, "Bad Request", &format!("unknown voice: {v}")),
+        Err(tts::SpeechError::EmptyText) => error_response(400, "Bad Request", "\"input\" must not be empty"),
+    }
+}
+
+/// Lists the default backend's id plus every model in `model_registry`, so
+/// a client can discover the ids it may pass as `model` in a completion
+/// request before the pool has lazily loaded any of them.
+fn handle_models(
+    backend: &dyn InferenceBackend,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    rope_scaling_overrides: &std::collections::HashMap<String, (gguf::RopeScaling, f64)>,
+) -> Response {
+    let model_registry = model_registry.lock().unwrap();
+    let mut ids = vec![backend.model_id().to_string()];
+    for entry in model_registry.list() {
+        if !ids.contains(&entry.id) {
+            ids.push(entry.id.clone());
+        }
+    }
+    let models = ids
+        .into_iter()
+        .map(|id| {
+            let mut builder = ObjectBuilder::new()
+                .set("id", Json::String(id.clone()))
+                .set("object", Json::String("model".to_string()))
+                .set("owned_by", Json::String("ai-server".to_string()));
+            // `backend.model_id()` (the always-present `EchoBackend` entry)
+            // has no `registry::ModelEntry`, so there's no GGUF header to
+            // read a context length from — it just gets `id`/`object`/`owned_by`.
+            if let Some(entry) = model_registry.resolve(&id) {
+                if let Some(trained) = entry.context_length {
+                    let (scaling, factor) =
+                        rope_scaling_overrides.get(&id).copied().unwrap_or((entry.rope_scaling, entry.rope_scaling_factor));
+                    let context_length = scaling.effective_context_length(trained, factor);
+                    builder = builder.set("context_length", Json::Number(context_length as f64));
+                }
+            }
+            builder.build()
+        })
+        .collect();
+    let body = ObjectBuilder::new().set("object", Json::String("list".to_string())).set("data", Json::Array(models)).build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Renders the process-wide metrics registry in Prometheus text format for
+/// `/metrics`.
+fn handle_metrics(registry: &metrics::Registry) -> Response {
+    Response::ok_text(&registry.render(), "text/plain; version=0.0.4")
+}
+
+/// Liveness check for `/healthz`: this process can accept and answer a
+/// request at all. Deliberately shallow — orchestrators poll this often
+/// and restart the process on failure, so it must never block on a
+/// downstream dependency the way `/readyz`'s checks do.
+fn handle_healthz() -> Response {
+    Response::ok_json(r#"{"status":"ok"}"#)
+}
+
+/// Serves the static admin dashboard page at `GET /dashboard`. See
+/// `dashboard.rs`'s module doc comment for why this is unauthenticated
+/// like `/healthz`/`/readyz` rather than living under `/admin/`.
+fn handle_dashboard() -> Response {
+    Response::ok_text(dashboard::DASHBOARD_HTML, "text/html")
+}
+
+/// Readiness check for `/readyz`: runs every check in `health.rs` and
+/// reports `503` the moment any of them fails, so a load balancer withholds
+/// traffic from a process that's alive but not actually able to serve a
+/// request. `check_timeout` bounds the slower checks (`check_backend_responsive`,
+/// the `df` subprocess behind `check_disk_space`) so a wedged dependency
+/// degrades one check instead of hanging the whole probe.
+fn handle_readyz(
+    pool: &model_pool::ModelPool,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    backend: &'static dyn InferenceBackend,
+    models_dir: &str,
+    check_timeout: std::time::Duration,
+) -> Response {
+    let checks = [
+        health::check_model_loaded(pool, &model_registry.lock().unwrap()),
+        health::check_backend_responsive(backend, check_timeout),
+        health::check_disk_space(models_dir, 0),
+        health::check_gpu_reachable(),
+    ];
+    let all_healthy = checks.iter().all(|c| c.healthy);
+    let body = ObjectBuilder::new()
+        .set("status", Json::String(if all_healthy { "ok" } else { "not_ready" }.to_string()))
+        .set(
+            "checks",
+            Json::Array(
+                checks
+                    .into_iter()
+                    .map(|c| {
+                        ObjectBuilder::new()
+                            .set("name", Json::String(c.name.to_string()))
+                            .set("healthy", Json::Bool(c.healthy))
+                            .set("detail", Json::String(c.detail))
+                            .build()
+                    })
+                    .collect(),
+            ),
+        )
+        .build();
+    let status = if all_healthy { 200 } else { 503 };
+    let reason = if all_healthy { "OK" } else { "Service Unavailable" };
+    Response::json(status, reason, &body.to_string())
+}
+
+/// Starts a span for an incoming request, propagating the trace id from
+/// an inbound `traceparent` header when present (see `tracing.rs`).
+fn start_request_span(name: &str, req: &Request) -> tracing::Span {
+    let parent = req.header("traceparent").and_then(tracing::parse_traceparent);
+    tracing::Span::start(name, parent.as_ref())
+}
+
+/// Process-local counter behind [`next_request_id`], same pattern as
+/// `tracing.rs`'s `ID_COUNTER` — unique per process, not meant to survive
+/// a restart.
+static REQUEST_ID_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Generates the id returned in a chat completion's `id` field and
+/// registered with the [`cancellation::CancellationRegistry`] so
+/// `/v1/cancel/{request_id}` can name it.
+fn next_request_id() -> String {
+    format!("chatcmpl-{}", REQUEST_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Distinct from [`next_request_id`] — that one names an OpenAI-style
+/// completion id, this one just tags a log line, and `route` handles
+/// endpoints that never construct a completion at all.
+static LOG_REQUEST_ID_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+fn next_log_request_id() -> String {
+    format!("req-{}", LOG_REQUEST_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Reads the OpenAI-style `max_tokens` field, falling back to a fixed
+/// estimate when it's absent — used only to size the admission check
+/// below, not to actually cap generation (`EchoBackend` has no such limit).
+const DEFAULT_MAX_TOKENS_ESTIMATE: usize = 256;
+
+fn requested_max_tokens(body: &Json) -> usize {
+    body.get("max_tokens").and_then(Json::as_f64).map(|v| v as usize).unwrap_or(DEFAULT_MAX_TOKENS_ESTIMATE)
+}
+
+/// Upper bound on client-supplied `n`/`best_of`: without one, a request
+/// naming an absurdly large value would occupy a backend for
+/// `best_of * limits.timeout` (see [`best_of_n_completions`]'s deadline
+/// handling) and, for `n` alone, build an unbounded `choices` array —
+/// either way a single request starving every other one behind it on the
+/// scheduler. Matches `DEFAULT_MAX_TOKENS_ESTIMATE`'s role as a sane
+/// built-in ceiling rather than a per-key configurable one.
+const MAX_N_OR_BEST_OF: usize = 8;
+
+/// Reads the OpenAI `n`/`best_of` sampling fields: `n` is how many choices
+/// to return, `best_of` is how many candidates to generate and rank by
+/// cumulative log-probability before keeping the top `n` (see
+/// [`best_of_n_completions`]). Both default to `1`, and `0` or negative
+/// values are treated as `1` the same way `requested_logprobs_n` floors at
+/// zero rather than erroring; both are also capped at
+/// [`MAX_N_OR_BEST_OF`]. `best_of` below `n` is rejected, matching
+/// OpenAI's own validation.
+fn requested_n_and_best_of(body: &Json) -> Result<(usize, usize), &'static str> {
+    let n = body.get("n").and_then(Json::as_f64).map(|v| (v as usize).clamp(1, MAX_N_OR_BEST_OF)).unwrap_or(1);
+    let best_of = body.get("best_of").and_then(Json::as_f64).map(|v| (v as usize).clamp(1, MAX_N_OR_BEST_OF)).unwrap_or(n);
+    if best_of < n {
+        return Err("\"best_of\" must be greater than or equal to \"n\"");
+    }
+    Ok((n, best_of))
+}
+
+/// Chat completions' `n` field: how many choices to return. Unlike
+/// `/v1/completions`' `n`/`best_of` (see [`requested_n_and_best_of`]), the
+/// chat API has no `best_of` concept, so each requested choice is simply
+/// its own [`best_of_n_completions`] candidate with `best_of == n`. Capped
+/// at [`MAX_N_OR_BEST_OF`] for the same reason.
+fn requested_n(body: &Json) -> usize {
+    body.get("n").and_then(Json::as_f64).map(|v| (v as usize).clamp(1, MAX_N_OR_BEST_OF)).unwrap_or(1)
+}
+
+/// Admits a request against `budget` if `backend` reports a memory
+/// profile (see `InferenceBackend::memory_profile`), estimating context
+/// length as prompt words plus the requested (or default) output length —
+/// a rough proxy for token count, same approximation used for tokens/sec
+/// elsewhere in this file. Returns the admitted byte count to release once
+/// generation finishes, or `None` when the backend has no profile to check
+/// against.
+fn admit_request(
+    backend: &dyn InferenceBackend,
+    budget: &resources::MemoryBudget,
+    prompt: &str,
+    max_tokens: usize,
+) -> Result<Option<u64>, Response> {
+    let Some(profile) = backend.memory_profile() else { return Ok(None) };
+    let context_tokens = prompt.split_whitespace().count() + max_tokens;
+    let bytes = profile.estimated_request_bytes(context_tokens);
+    match budget.try_admit(bytes) {
+        Ok(()) => Ok(Some(bytes)),
+        Err(resources::AdmissionError::ExceedsCapacity { requested, capacity }) => Err(errors::ServerError::ContextLengthExceeded(
+            format!("request needs ~{requested} bytes, which exceeds the {capacity} byte memory budget"),
+        )
+        .into_response()),
+        Err(resources::AdmissionError::BudgetExhausted { requested, available }) => Err(errors::ServerError::Overloaded(
+            format!("request needs ~{requested} bytes but only {available} are currently available"),
+        )
+        .into_response()),
+    }
+}
+
+/// Legacy `/v1/completions`'s `logprobs` field: an integer count of
+/// alternative tokens to report per position, or absent to skip logprobs
+/// entirely. Any other JSON type is treated the same as "not requested"
+/// rather than a validation error, matching how `max_tokens` is read above.
+fn requested_logprobs_n(body: &Json) -> Option<usize> {
+    body.get("logprobs").and_then(Json::as_f64).map(|v| v.max(0.0) as usize)
+}
+
+/// Chat `/v1/chat/completions`'s pair of fields: `logprobs: true` opts in,
+/// and `top_logprobs` (defaulting to `0`) says how many alternatives to
+/// report alongside each chosen token. Returns `None` when `logprobs`
+/// isn't `true`, so callers can use it directly as an "is this requested
+/// at all" check.
+fn requested_chat_logprobs_n(body: &Json) -> Option<usize> {
+    if body.get("logprobs").and_then(Json::as_bool) != Some(true) {
+        return None;
+    }
+    Some(body.get("top_logprobs").and_then(Json::as_f64).map(|v| v.max(0.0) as usize).unwrap_or(0))
+}
+
+/// Builds the legacy `/v1/completions` response's `logprobs` object from
+/// `completion`, tokenized the same crude way the tokens-per-second
+/// counter above already does (`split_whitespace`) — good enough for a
+/// backend that doesn't have a real tokenizer wired in either (see
+/// `EchoBackend`'s doc comment). Returns `None` if any token's logprob is
+/// unavailable (see `InferenceBackend::token_logprob`), matching OpenAI's
+/// behavior of omitting the field entirely rather than serving a partial
+/// one.
+fn completion_logprobs_json(backend: &dyn InferenceBackend, completion: &str, top_n: usize) -> Option<Json> {
+    let mut tokens = Vec::new();
+    let mut token_logprobs = Vec::new();
+    let mut top_logprobs = Vec::new();
+    let mut text_offset = Vec::new();
+    let mut cursor = 0usize;
+    for word in completion.split_whitespace() {
+        let start = completion[cursor..].find(word)? + cursor;
+        cursor = start + word.len();
+        let info = backend.token_logprob(word, top_n)?;
+        text_offset.push(Json::Number(start as f64));
+        tokens.push(Json::String(info.token));
+        token_logprobs.push(Json::Number(info.logprob));
+        top_logprobs.push(Json::Object(info.top_logprobs.into_iter().map(|(t, p)| (t, Json::Number(p))).collect()));
+    }
+    Some(
+        ObjectBuilder::new()
+            .set("tokens", Json::Array(tokens))
+            .set("token_logprobs", Json::Array(token_logprobs))
+            .set("top_logprobs", Json::Array(top_logprobs))
+            .set("text_offset", Json::Array(text_offset))
+            .build(),
+    )
+}
+
+/// Builds one entry of chat `/v1/chat/completions`'s `logprobs.content`
+/// array for a single token, in the shape OpenAI's chat API uses (as
+/// opposed to the legacy parallel-arrays shape [`completion_logprobs_json`]
+/// builds).
+fn chat_token_logprob_json(info: &TokenLogprob) -> Json {
+    ObjectBuilder::new()
+        .set("token", Json::String(info.token.clone()))
+        .set("logprob", Json::Number(info.logprob))
+        .set(
+            "top_logprobs",
+            Json::Array(
+                info.top_logprobs
+                    .iter()
+                    .map(|(t, p)| {
+                        ObjectBuilder::new().set("token", Json::String(t.clone())).set("logprob", Json::Number(*p)).build()
+                    })
+                    .collect(),
+            ),
+        )
+        .build()
+}
+
+/// Builds the full chat `/v1/chat/completions` response's `logprobs`
+/// object (`{"content": [...]}`) from `completion`. Returns `None` on the
+/// same "any token unavailable" condition [`completion_logprobs_json`]
+/// does.
+fn chat_completion_logprobs_json(backend: &dyn InferenceBackend, completion: &str, top_n: usize) -> Option<Json> {
+    let content: Option<Vec<Json>> = completion
+        .split_whitespace()
+        .map(|word| backend.token_logprob(word, top_n).as_ref().map(chat_token_logprob_json))
+        .collect();
+    Some(ObjectBuilder::new().set("content", Json::Array(content?)).build())
+}
+
+/// Reads a request's `"context_policy"` field (an extension beyond
+/// OpenAI's own API, in the same spirit as this server's `seed` field),
+/// falling back to `default` — the server's configured
+/// `context_overflow_policy` — when absent or unrecognized.
+fn requested_context_policy(body: &Json, default: context_policy::ContextPolicy) -> context_policy::ContextPolicy {
+    body.get("context_policy").and_then(Json::as_str).and_then(context_policy::ContextPolicy::parse).unwrap_or(default)
+}
+
+/// Fits `prompt` (plus, for chat requests, its system prompt) within
+/// `max_context_tokens` per `policy`, returning the 400 response to send
+/// back under [`context_policy::ContextPolicy::Error`]. On success, the
+/// second element reports which policy actually acted — `None` when the
+/// prompt already fit — so callers can surface it back to the client the
+/// same way `response_cache`'s `X-Cache` header reports what happened.
+fn fit_context(
+    prompt: &str,
+    system_prompt: Option<&str>,
+    max_context_tokens: usize,
+    policy: context_policy::ContextPolicy,
+) -> Result<(String, Option<context_policy::ContextPolicy>), Response> {
+    match context_policy::apply(prompt, system_prompt, max_context_tokens, policy) {
+        Ok(fit) => Ok((fit.prompt, fit.policy_applied)),
+        Err(overflow) => Err(error_response(
+            400,
+            "Bad Request",
+            &format!(
+                "prompt has {} tokens, which exceeds this model's {}-token context window",
+                overflow.prompt_tokens, overflow.limit
+            ),
+        )),
+    }
+}
+
+/// Reads the `"stop"` field, accepted as either a single string or an
+/// array of strings (both shapes OpenAI's API takes). Absent, wrong-typed,
+/// or entirely non-string-array values are treated as "no stop sequences"
+/// rather than a validation error.
+fn requested_stop_sequences(body: &Json) -> Vec<String> {
+    match body.get("stop") {
+        Some(Json::String(s)) => vec![s.clone()],
+        Some(Json::Array(items)) => items.iter().filter_map(Json::as_str).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Reads the sampling-relevant fields off a request body into
+/// `sampling::RequestedGenerationParams`, for [`resolve_effective_generation_params`]
+/// to layer over a model's preset and clamp. Absent or wrong-typed fields
+/// read as "not set" rather than a validation error, matching every other
+/// `requested_*` reader in this file.
+fn requested_generation_params(body: &Json) -> sampling::RequestedGenerationParams {
+    sampling::RequestedGenerationParams {
+        temperature: body.get("temperature").and_then(Json::as_f64).map(|v| v as f32),
+        top_p: body.get("top_p").and_then(Json::as_f64).map(|v| v as f32),
+        repetition_penalty: body.get("repetition_penalty").and_then(Json::as_f64).map(|v| v as f32),
+        stop: requested_stop_sequences(body),
+    }
+}
+
+/// Looks up `model_id`'s preset/clamps in `model_registry` (if it's
+/// registered at all — an alias-resolved or ad hoc model id may not be) and
+/// resolves `body`'s requested sampling fields against them, returning both
+/// the effective parameters and their `"generation_params"` JSON echo for
+/// the response body — see `sampling::resolve_generation_params`.
+fn resolve_effective_generation_params(
+    model_registry: &Mutex<registry::ModelRegistry>,
+    model_id: &str,
+    body: &Json,
+) -> sampling::EffectiveGenerationParams {
+    let registry = model_registry.lock().unwrap();
+    let entry = registry.resolve(model_id);
+    let preset = entry.and_then(|e| e.preset.as_ref());
+    let clamps = entry.map(|e| e.clamps).unwrap_or_default();
+    sampling::resolve_generation_params(preset, &clamps, &requested_generation_params(body))
+}
+
+fn generation_params_json(effective: &sampling::EffectiveGenerationParams) -> Json {
+    ObjectBuilder::new()
+        .set("temperature", Json::Number(effective.temperature as f64))
+        .set("top_p", Json::Number(effective.top_p as f64))
+        .set("repetition_penalty", Json::Number(effective.repetition_penalty as f64))
+        .set("stop", Json::Array(effective.stop.iter().cloned().map(Json::String).collect()))
+        .build()
+}
+
+/// Truncates `text` at the first occurrence of any of `stop_sequences`,
+/// matching OpenAI's behavior of never including the matched sequence
+/// itself in the response. Built on [`stop_sequences::StopMatcher`] (fed
+/// the whole text in one call) so the buffered completion paths agree with
+/// the streaming path on exactly where a stop sequence would cut the text.
+fn truncate_at_stop_sequence(text: &str, stop_sequences: &[String]) -> String {
+    if stop_sequences.is_empty() {
+        return text.to_string();
+    }
+    let mut matcher = stop_sequences::StopMatcher::new(stop_sequences.to_vec(), true);
+    let feed = matcher.feed(text);
+    if feed.stopped {
+        feed.emit
+    } else {
+        feed.emit + &matcher.finish()
+    }
+}
+
+/// Either the process's default backend or one checked out of a
+/// [`model_pool::ModelPool`] for the request's `model` field — `Deref`s to
+/// [`InferenceBackend`] so callers don't need to match on which case they
+/// got.
+enum ResolvedBackend<'a> {
+    Default(&'a dyn InferenceBackend),
+    Pooled(Arc<dyn InferenceBackend>),
+}
+
+impl<'a> std::ops::Deref for ResolvedBackend<'a> {
+    type Target = dyn InferenceBackend + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ResolvedBackend::Default(backend) => *backend,
+            ResolvedBackend::Pooled(backend) => backend.as_ref(),
+        }
+    }
+}
+
+/// Feeds [`model_alias::AliasRegistry::resolve`]'s weighted pick — unique
+/// per request, not meant to survive a restart, same posture as
+/// `REQUEST_ID_COUNTER` above.
+static ALIAS_PICK_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Resolves which backend should serve a request: `pool`'s model for the
+/// JSON body's `model` field, or `default_backend` when the field is
+/// absent. A `model` naming a `model_alias::AliasRegistry` alias is
+/// weighted-picked down to a real model id first; the returned `Option`
+/// carries the alias name back to the caller so it can mirror a shadow
+/// request under `resolve_shadow`, since the alias name itself isn't
+/// otherwise recoverable once `pool.get_or_load` has resolved it to one of
+/// its targets. An explicit but unrecognized `model` is a 404 rather than
+/// a silent fallback, matching OpenAI's "model not found" error shape —
+/// and so is a model that exists but isn't in `tenant`'s
+/// `tenancy::TenantRegistry::allows_model` list, so a restricted tenant
+/// can't tell the two cases apart.
+fn resolve_backend<'a>(
+    pool: &model_pool::ModelPool,
+    default_backend: &'a dyn InferenceBackend,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    aliases: &model_alias::AliasRegistry,
+    parsed: &Json,
+) -> Result<(ResolvedBackend<'a>, Option<String>), Response> {
+    match parsed.get("model").and_then(Json::as_str) {
+        None => Ok((ResolvedBackend::Default(default_backend), None)),
+        Some(model_id) => {
+            if !tenants.allows_model(tenant, model_id) {
+                return Err(errors::ServerError::ModelNotFound(format!("model \"{model_id}\" is not available")).into_response());
+            }
+            let seed = ALIAS_PICK_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let (target_model, alias) = match aliases.resolve(model_id, seed) {
+                Some(target) => (target.to_string(), Some(model_id.to_string())),
+                None => (model_id.to_string(), None),
+            };
+            match pool.get_or_load(&target_model) {
+                Some(backend) => Ok((ResolvedBackend::Pooled(backend), alias)),
+                None => Err(errors::ServerError::ModelNotFound(format!("model \"{target_model}\" is not available")).into_response()),
+            }
+        }
+    }
+}
+
+/// An owned handle to a resolved backend that can move into a detached
+/// background thread. `ResolvedBackend::Default`'s reference is already
+/// `'static` once `resolve_backend` is called with a `'static` default
+/// backend (as `handle_completions`/`handle_chat_completions` do), and
+/// `Pooled`'s `Arc` clone is cheap and shares the pool's checked-out
+/// instance — see [`bounded_generate`].
+enum StaticBackend {
+    Default(&'static dyn InferenceBackend),
+    Pooled(Arc<dyn InferenceBackend>),
+}
+
+impl std::ops::Deref for StaticBackend {
+    type Target = dyn InferenceBackend;
+
+    fn deref(&self) -> &(dyn InferenceBackend + 'static) {
+        match self {
+            StaticBackend::Default(backend) => *backend,
+            StaticBackend::Pooled(backend) => backend.as_ref(),
+        }
+    }
+}
+
+impl ResolvedBackend<'static> {
+    fn to_static(&self) -> StaticBackend {
+        match self {
+            ResolvedBackend::Default(backend) => StaticBackend::Default(*backend),
+            ResolvedBackend::Pooled(backend) => StaticBackend::Pooled(Arc::clone(backend)),
+        }
+    }
+}
+
+/// Runs `backend.generate(&prompt)` (or, when `images` is non-empty,
+/// `generate_with_images`) on a background thread and waits up to
+/// `timeout` for it to finish — the same `mpsc`/`recv_timeout` pattern
+/// `health::check_backend_responsive` uses to bound a synchronous,
+/// non-preemptible backend call. Returns `None` on timeout; the spawned
+/// thread is left running to completion on its own (like
+/// `fire_shadow_request`'s mirrored calls) since there's no way to
+/// interrupt `generate` mid-flight.
+fn bounded_generate(backend: StaticBackend, prompt: String, images: Vec<image::Image>, timeout: std::time::Duration) -> Option<String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let text = if images.is_empty() { backend.generate(&prompt) } else { backend.generate_with_images(&prompt, &images) };
+        let _ = tx.send(text);
+    });
+    rx.recv_timeout(timeout).ok()
+}
+
+/// Generates `best_of` independent candidate completions for the same
+/// prompt (each its own [`bounded_generate`] call, sharing `resolved`'s
+/// already-checked-out backend the same way a single generation would)
+/// and returns them ranked best-first by cumulative log-probability — the
+/// `n`/`best_of` sampling [`requested_n_and_best_of`] parses. A
+/// candidate's score is the sum of `InferenceBackend::token_logprob`'s
+/// per-word logprob (`0` alternatives, since this only ranks candidates,
+/// it never reports them), tokenized the same crude `split_whitespace` way
+/// as every other per-token count in this file; a backend with no real
+/// logprobs (the trait's default) scores every candidate `0.0`, so
+/// ranking falls back to generation order via the stable sort. A
+/// candidate that times out is dropped rather than padding the result
+/// with an empty string, so the caller may get back fewer than `best_of`
+/// completions.
+///
+/// `timeout` bounds the *whole* batch, not each candidate individually —
+/// each `bounded_generate` call gets whatever's left until the shared
+/// deadline, so a caller's per-key `limits.timeout` still means "this
+/// request occupies the backend for at most `timeout`" regardless of
+/// `best_of`, rather than `best_of * timeout`. Once the deadline passes,
+/// remaining candidates are skipped rather than attempted with a
+/// zero/negative budget.
+fn best_of_n_completions(
+    backend: &dyn InferenceBackend,
+    resolved: &ResolvedBackend<'static>,
+    prompt: &str,
+    images: &[image::Image],
+    timeout: std::time::Duration,
+    best_of: usize,
+) -> Vec<String> {
+    let deadline = std::time::Instant::now() + timeout;
+    let mut candidates: Vec<(String, f64)> = Vec::new();
+    for _ in 0..best_of {
+        let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) else { break };
+        let Some(completion) = bounded_generate(resolved.to_static(), prompt.to_string(), images.to_vec(), remaining) else { continue };
+        let score = completion.split_whitespace().map(|word| backend.token_logprob(word, 0).map(|info| info.logprob).unwrap_or(0.0)).sum();
+        candidates.push((completion, score));
+    }
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    candidates.into_iter().map(|(text, _)| text).collect()
+}
+
+/// Truncates `completion` to at most `max_tokens` whitespace-split tokens
+/// — the same approximate token-count convention `n_tokens`/`usage.rs`'s
+/// callers use elsewhere in this file, since there's no real tokenizer in
+/// this tree. Returns the (possibly unchanged) text and whether truncation
+/// happened, so the caller can report `finish_reason: "length"` only when
+/// it actually did.
+fn truncate_to_max_tokens(completion: &str, max_tokens: usize) -> (String, bool) {
+    let words: Vec<&str> = completion.split_whitespace().collect();
+    if words.len() <= max_tokens {
+        return (completion.to_string(), false);
+    }
+    (words[..max_tokens].join(" "), true)
+}
+
+/// Fires a request's shadow model (if `alias` is `Some` and
+/// `model_alias::AliasRegistry::shadow_for` names one) on a detached
+/// thread: generates against `prompt`, discards the result, and records
+/// the attempt in `registry` — never on the caller's hot path, since the
+/// whole point of a shadow model is comparing it without the caller
+/// paying for it. `pool`/`registry` are `'static` for the same reason
+/// `handle_create_batch`'s `backend` parameter is: the closure needs to
+/// outlive this call.
+fn fire_shadow_request(
+    pool: &'static model_pool::ModelPool,
+    aliases: &model_alias::AliasRegistry,
+    alias: Option<&str>,
+    prompt: String,
+    registry: &'static metrics::Registry,
+) {
+    let Some(alias) = alias else { return };
+    let Some(shadow_model) = aliases.shadow_for(alias) else { return };
+    let Some(backend) = pool.get_or_load(shadow_model) else { return };
+    let alias = alias.to_string();
+    let shadow_model = shadow_model.to_string();
+    std::thread::spawn(move || {
+        backend.generate(&prompt);
+        registry.record_shadow_request(&alias, &shadow_model);
+    });
+}
+
+/// Resolves a request body's `"template"`/`"variables"` fields (if
+/// present) into a plain `"messages"` array, so everything downstream of
+/// this call keeps reading `parsed.get("messages")` exactly as it does for
+/// a request that sent raw messages to begin with. A request with no
+/// `"template"` field is returned unchanged. See `prompt_templates.rs`'s
+/// module doc comment for the registry and substitution rules.
+fn resolve_template(parsed: &Json, templates: &prompt_templates::TemplateRegistry) -> Result<Json, Response> {
+    let Some(name) = parsed.get("template").and_then(Json::as_str) else {
+        return Ok(parsed.clone());
+    };
+    let Some(template) = templates.get(name) else {
+        return Err(error_response(404, "Not Found", &format!("no template named {name:?}")));
+    };
+    let variables: std::collections::BTreeMap<String, String> = match parsed.get("variables") {
+        Some(Json::Object(map)) => map.iter().filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string()))).collect(),
+        _ => std::collections::BTreeMap::new(),
+    };
+    let rendered = prompt_templates::render(&template, &variables).map_err(|e| error_response(400, "Bad Request", &e.to_string()))?;
+    let messages: Vec<Json> = rendered
+        .into_iter()
+        .map(|m| ObjectBuilder::new().set("role", Json::String(m.role)).set("content", Json::String(m.content)).build())
+        .collect();
+    let Json::Object(mut map) = parsed.clone() else {
+        return Err(error_response(400, "Bad Request", "request body must be a JSON object"));
+    };
+    map.insert("messages".to_string(), Json::Array(messages));
+    Ok(Json::Object(map))
+}
+
+/// Applies the JSON body's `lora` field (if present) to `backend` via
+/// [`InferenceBackend::apply_lora`]. An unknown adapter id is a 404, and a
+/// dimension mismatch between the adapter and the backend's weights is a
+/// 502 — the backend accepted the selection but couldn't actually merge it.
+fn apply_requested_lora(backend: &dyn InferenceBackend, adapters: &lora::AdapterRegistry, parsed: &Json) -> Result<(), Response> {
+    let Some(lora_id) = parsed.get("lora").and_then(Json::as_str) else { return Ok(()) };
+    let Some(adapter) = adapters.get(lora_id) else {
+        return Err(error_response(404, "Not Found", &format!("lora adapter \"{lora_id}\" is not available")));
+    };
+    backend
+        .apply_lora(&adapter)
+        .map_err(|e| error_response(502, "Bad Gateway", &format!("failed to apply lora adapter \"{lora_id}\": {e}")))
+}
+
+fn handle_completions(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    adapters: &lora::AdapterRegistry,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    registry: &'static metrics::Registry,
+    traces: &Mutex<Vec<tracing::Span>>,
+    budget: &resources::MemoryBudget,
+    cache: &response_cache::ResponseCache,
+    max_context_tokens: usize,
+    default_context_policy: context_policy::ContextPolicy,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    usage_store: &usage::UsageStore,
+    usage_key: Option<&str>,
+    guardrails: &guardrails::GuardrailsEngine,
+    plugins: &plugins::PluginRegistry,
+    aliases: &model_alias::AliasRegistry,
+    limits: auth::GenerationLimits,
+    req: &Request,
+) -> Response {
+    let mut span = start_request_span("POST /v1/completions", req);
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let (resolved, alias) = match resolve_backend(pool, backend, tenants, tenant, aliases, &parsed) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+    let backend: &dyn InferenceBackend = &*resolved;
+    if let Err(response) = apply_requested_lora(backend, adapters, &parsed) {
+        return response;
+    }
+    // Only `temperature: 0` plus a fixed `seed` makes a completion
+    // reproducible, so that's the only shape this checks before serving
+    // or populating the cache — see `response_cache.rs`'s doc comment.
+    let cacheable = response_cache::is_deterministic(&parsed);
+    let cache_key = response_cache::cache_key(backend.model_id(), text);
+    if cacheable {
+        if let Some(body) = cache.get(&cache_key) {
+            let mut response = Response::ok_json(&body);
+            response.headers.push(("X-Cache".to_string(), "HIT".to_string()));
+            return response;
+        }
+    }
+    let prompt = match parsed.get("prompt").and_then(Json::as_str) {
+        Some(p) => p.to_string(),
+        None => return error_response(400, "Bad Request", "\"prompt\" must be a string"),
+    };
+    let policy = requested_context_policy(&parsed, default_context_policy);
+    let (prompt, policy_applied) = match fit_context(&prompt, None, max_context_tokens, policy) {
+        Ok(fit) => fit,
+        Err(response) => return response,
+    };
+    let pre_moderation = guardrails.check(&prompt, Some(backend));
+    if pre_moderation.action == Some(guardrails::Action::Block) {
+        return guardrails_blocked_response(&pre_moderation);
+    }
+    let prompt = plugins.run_all(&prompt);
+    fire_shadow_request(pool, aliases, alias.as_deref(), prompt.clone(), registry);
+    let (n, best_of) = match requested_n_and_best_of(&parsed) {
+        Ok(v) => v,
+        Err(msg) => return error_response(400, "Bad Request", msg),
+    };
+    let admitted = match admit_request(backend, budget, &prompt, requested_max_tokens(&parsed)) {
+        Ok(admitted) => admitted,
+        Err(response) => return response,
+    };
+    let effective_params = resolve_effective_generation_params(model_registry, backend.model_id(), &parsed);
+    registry.record_request(backend.model_id());
+    span.set_attribute("model", backend.model_id());
+    let started = std::time::Instant::now();
+    let candidates = best_of_n_completions(backend, &resolved, &prompt, &[], limits.timeout, best_of);
+    if let Some(bytes) = admitted {
+        budget.release(bytes);
+    }
+    if candidates.is_empty() {
+        span.end();
+        traces.lock().unwrap().push(span);
+        let body = ObjectBuilder::new()
+            .set("id", Json::String("cmpl-0".to_string()))
+            .set("object", Json::String("text_completion".to_string()))
+            .set("model", Json::String(backend.model_id().to_string()))
+            .set(
+                "choices",
+                Json::Array(vec![ObjectBuilder::new()
+                    .set("text", Json::String(String::new()))
+                    .set("index", Json::Number(0.0))
+                    .set("finish_reason", Json::String("timeout".to_string()))
+                    .build()]),
+            )
+            .build();
+        return Response::ok_json(&body.to_string());
+    };
+    let mut choices = Vec::new();
+    let mut combined_moderation = pre_moderation.clone();
+    let mut total_completion_tokens = 0usize;
+    for (index, completion) in candidates.into_iter().take(n).enumerate() {
+        let completion = truncate_at_stop_sequence(&completion, &effective_params.stop);
+        let (completion, truncated_by_length) = truncate_to_max_tokens(&completion, limits.max_output_tokens);
+        let completion = plugins.run_all(&completion);
+        let post_moderation = guardrails.check(&completion, Some(backend));
+        if post_moderation.action == Some(guardrails::Action::Block) {
+            return guardrails_blocked_response(&guardrails::combine(&pre_moderation, &post_moderation));
+        }
+        let completion = post_moderation.text.clone();
+        total_completion_tokens += completion.split_whitespace().count();
+        // The top-ranked (`index == 0`) candidate's moderation result is
+        // what ends up in the response body's own `"moderation"` field —
+        // `combine` is meant for one pre/post pair, not `n` of them.
+        if index == 0 {
+            combined_moderation = guardrails::combine(&pre_moderation, &post_moderation);
+        }
+        let mut choice = ObjectBuilder::new();
+        if let Some(top_n) = requested_logprobs_n(&parsed) {
+            if let Some(logprobs) = completion_logprobs_json(backend, &completion, top_n) {
+                choice = choice.set("logprobs", logprobs);
+            }
+        }
+        let finish_reason = if truncated_by_length { "length" } else { "stop" };
+        choices.push(
+            choice
+                .set("text", Json::String(completion))
+                .set("index", Json::Number(index as f64))
+                .set("finish_reason", Json::String(finish_reason.to_string()))
+                .build(),
+        );
+    }
+    let elapsed = started.elapsed();
+    if elapsed.as_secs_f64() > 0.0 {
+        registry.observe_tokens_per_second(total_completion_tokens as f64 / elapsed.as_secs_f64());
+    }
+    if let Some(key) = usage_key {
+        usage_store.record(key, backend.model_id(), prompt.split_whitespace().count() as u64, total_completion_tokens as u64, elapsed.as_millis() as u64);
+    }
+    span.end();
+    traces.lock().unwrap().push(span);
+    let mut body = ObjectBuilder::new()
+        .set("id", Json::String("cmpl-0".to_string()))
+        .set("object", Json::String("text_completion".to_string()))
+        .set("model", Json::String(backend.model_id().to_string()))
+        .set("choices", Json::Array(choices))
+        .set("generation_params", generation_params_json(&effective_params));
+    if let Some(policy) = policy_applied {
+        body = body.set("context_policy_applied", Json::String(policy.as_str().to_string()));
+    }
+    if combined_moderation.flagged {
+        body = body.set("moderation", combined_moderation.to_json());
+    }
+    let body = body.build();
+    let body = body.to_string();
+    let mut response = Response::ok_json(&body);
+    if cacheable {
+        cache.insert(cache_key, body);
+        response.headers.push(("X-Cache".to_string(), "MISS".to_string()));
+    }
+    response
+}
+
+/// Handles Ollama-compatible `POST /api/generate`, gated by
+/// `ollama_compat_enabled` (see `config.rs`'s `[ollama]` section) the same
+/// way `mcp_enabled`/`agent_enabled` gate their own routes in [`route`].
+/// Reuses this server's own `resolve_backend`/`admit_request`/
+/// `bounded_generate` pipeline against Ollama's request shape
+/// (`{"model", "prompt"}`) instead of this server's native `{"model",
+/// "prompt"}`-shaped `/v1/completions` handler, since the two request
+/// bodies happen to read the same fields under different response shapes.
+///
+/// Ollama's own wire format streams newline-delimited JSON objects when
+/// `"stream"` isn't `false`; this always answers with a single buffered
+/// object and `"done": true` regardless of the request's `"stream"`
+/// field, the same scope limit `replay_backend.rs` documents for its own
+/// non-goals rather than reimplementing a second streaming framing
+/// alongside `SseWriter`'s existing one.
+fn handle_ollama_generate(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    budget: &resources::MemoryBudget,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    aliases: &model_alias::AliasRegistry,
+    limits: auth::GenerationLimits,
+    req: &Request,
+) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let (resolved, _alias) = match resolve_backend(pool, backend, tenants, tenant, aliases, &parsed) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+    let backend: &dyn InferenceBackend = &*resolved;
+    let prompt = match parsed.get("prompt").and_then(Json::as_str) {
+        Some(p) => p.to_string(),
+        None => return error_response(400, "Bad Request", "\"prompt\" must be a string"),
+    };
+    let admitted = match admit_request(backend, budget, &prompt, requested_max_tokens(&parsed)) {
+        Ok(admitted) => admitted,
+        Err(response) => return response,
+    };
+    let model_id = backend.model_id().to_string();
+    let generated = bounded_generate(resolved.to_static(), prompt, Vec::new(), limits.timeout);
+    if let Some(bytes) = admitted {
+        budget.release(bytes);
+    }
+    let body = ObjectBuilder::new()
+        .set("model", Json::String(model_id))
+        .set("response", Json::String(generated.unwrap_or_default()))
+        .set("done", Json::Bool(true))
+        .build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Handles Ollama-compatible `POST /api/chat`. Ollama's `/api/chat` body
+/// (`{"model", "messages": [{"role", "content"}]}`) is the identical shape
+/// this server's own `/v1/chat/completions` already parses, so this reuses
+/// `prompt_from_chat_request` rather than writing a second chat-message
+/// reducer. See [`handle_ollama_generate`]'s doc comment for the same
+/// buffered-response-only scope limit.
+fn handle_ollama_chat(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    budget: &resources::MemoryBudget,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    aliases: &model_alias::AliasRegistry,
+    limits: auth::GenerationLimits,
+    req: &Request,
+) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let (resolved, _alias) = match resolve_backend(pool, backend, tenants, tenant, aliases, &parsed) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+    let backend: &dyn InferenceBackend = &*resolved;
+    let prompt = match prompt_from_chat_request(&parsed) {
+        Ok(p) => p,
+        Err(e) => return error_response(400, "Bad Request", e),
+    };
+    let admitted = match admit_request(backend, budget, &prompt, requested_max_tokens(&parsed)) {
+        Ok(admitted) => admitted,
+        Err(response) => return response,
+    };
+    let model_id = backend.model_id().to_string();
+    let generated = bounded_generate(resolved.to_static(), prompt, Vec::new(), limits.timeout);
+    if let Some(bytes) = admitted {
+        budget.release(bytes);
+    }
+    let message = ObjectBuilder::new()
+        .set("role", Json::String("assistant".to_string()))
+        .set("content", Json::String(generated.unwrap_or_default()))
+        .build();
+    let body = ObjectBuilder::new().set("model", Json::String(model_id)).set("message", message).set("done", Json::Bool(true)).build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Handles Ollama-compatible `GET /api/tags`, the Ollama equivalent of
+/// this server's own `GET /v1/models` (see [`handle_models`]) — same
+/// source (`model_registry` plus the always-present default backend id),
+/// reshaped into Ollama's `{"models": [{"name", "model", "size",
+/// "digest"}]}` instead of OpenAI's `{"object", "data": [...]}`.
+fn handle_ollama_tags(backend: &dyn InferenceBackend, model_registry: &Mutex<registry::ModelRegistry>) -> Response {
+    let model_registry = model_registry.lock().unwrap();
+    let mut ids = vec![backend.model_id().to_string()];
+    for entry in model_registry.list() {
+        if !ids.contains(&entry.id) {
+            ids.push(entry.id.clone());
+        }
+    }
+    let models = ids
+        .into_iter()
+        .map(|id| {
+            let entry = model_registry.resolve(&id);
+            ObjectBuilder::new()
+                .set("name", Json::String(id.clone()))
+                .set("model", Json::String(id))
+                .set("size", Json::Number(entry.map(|e| e.size_bytes).unwrap_or(0) as f64))
+                .set("digest", Json::String(entry.and_then(|e| e.sha256.clone()).unwrap_or_default()))
+                .build()
+        })
+        .collect();
+    let body = ObjectBuilder::new().set("models", Json::Array(models)).build();
+    Response::ok_json(&body.to_string())
+}
+
+/// Handles Ollama-compatible `POST /api/pull`. Ollama's real `/api/pull`
+/// resolves a bare library name (`"llama3"`) against Ollama's own hosted
+/// model library and downloads it; this tree's `downloader.rs` only knows
+/// how to fetch an explicit repo/filename/sha256 triple (see its own doc
+/// comment) with no name-to-repo mapping table for arbitrary library
+/// names anywhere in this tree. So this only ever succeeds — idempotently,
+/// like Ollama's own "already have it" response — for a name already
+/// present in `model_registry` or matching the default backend's own
+/// `model_id()`; anything else is `ModelNotFound` with a message pointing
+/// the operator at placing a GGUF file under `models_dir` instead of
+/// fabricating a download this tree can't actually perform.
+fn handle_ollama_pull(backend: &dyn InferenceBackend, model_registry: &Mutex<registry::ModelRegistry>, req: &Request) -> Response {
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return error_response(400, "Bad Request", "request body must be UTF-8"),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return error_response(400, "Bad Request", &e.to_string()),
+    };
+    let name = match parsed.get("name").and_then(Json::as_str).or_else(|| parsed.get("model").and_then(Json::as_str)) {
+        Some(name) => name,
+        None => return error_response(400, "Bad Request", "\"name\" must be a string"),
+    };
+    let model_registry = model_registry.lock().unwrap();
+    if name == backend.model_id() || model_registry.resolve(name).is_some() {
+        let body = ObjectBuilder::new().set("status", Json::String("success".to_string())).build();
+        return Response::ok_json(&body.to_string());
+    }
+    errors::ServerError::ModelNotFound(format!(
+        "model \"{name}\" is not available; this server has no name-to-repo mapping for Ollama library models — place a GGUF file under the configured models_dir instead"
+    ))
+    .into_response()
+}
+
+/// Handles `/v1/chat/completions`. When `"stream": true` is set, takes over
+/// the connection directly to write SSE chunks and returns `None`;
+/// otherwise returns the buffered JSON response.
+///
+/// `tools`/`tool_choice` (see `tool_calls.rs`) are only resolved on the
+/// buffered path, same as `response_format` grammar validation just below
+/// it — both need the whole completion in hand before they can tell
+/// whether it matches, so neither has anything meaningful to do per SSE
+/// chunk.
+fn handle_chat_completions(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    adapters: &lora::AdapterRegistry,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    registry: &'static metrics::Registry,
+    traces: &Mutex<Vec<tracing::Span>>,
+    budget: &resources::MemoryBudget,
+    cancellation: &cancellation::CancellationRegistry,
+    cache: &response_cache::ResponseCache,
+    max_context_tokens: usize,
+    default_context_policy: context_policy::ContextPolicy,
+    tenants: &tenancy::TenantRegistry,
+    tenant: Option<&str>,
+    usage_store: &usage::UsageStore,
+    usage_key: Option<&str>,
+    guardrails: &guardrails::GuardrailsEngine,
+    plugins: &plugins::PluginRegistry,
+    mcp_clients: &mcp::McpClientRegistry,
+    templates: &prompt_templates::TemplateRegistry,
+    aliases: &model_alias::AliasRegistry,
+    limits: auth::GenerationLimits,
+    req: &Request,
+    stream: &mut Transport,
+) -> Option<Response> {
+    let request_id = next_request_id();
+    let mut span = start_request_span("POST /v1/chat/completions", req);
+    let text = match req.body_str() {
+        Ok(s) => s,
+        Err(_) => return Some(error_response(400, "Bad Request", "request body must be UTF-8")),
+    };
+    let parsed = match Json::parse(text) {
+        Ok(j) => j,
+        Err(e) => return Some(error_response(400, "Bad Request", &e.to_string())),
+    };
+    let parsed = match resolve_template(&parsed, templates) {
+        Ok(p) => p,
+        Err(response) => return Some(response),
+    };
+    let (resolved, alias) = match resolve_backend(pool, backend, tenants, tenant, aliases, &parsed) {
+        Ok(resolved) => resolved,
+        Err(response) => return Some(response),
+    };
+    let backend: &dyn InferenceBackend = &*resolved;
+    if let Err(response) = apply_requested_lora(backend, adapters, &parsed) {
+        return Some(response);
+    }
+    // Streaming responses are cheap to generate token-by-token but awkward
+    // to cache faithfully (the client expects the same chunk boundaries),
+    // so caching only covers the buffered path below, gated the same way
+    // `handle_completions` is on `temperature: 0` plus a fixed `seed`.
+    let cacheable = !parsed.get("stream").and_then(Json::as_bool).unwrap_or(false) && response_cache::is_deterministic(&parsed);
+    let cache_key = response_cache::cache_key(backend.model_id(), text);
+    if cacheable {
+        if let Some(body) = cache.get(&cache_key) {
+            let mut response = Response::ok_json(&body);
+            response.headers.push(("X-Cache".to_string(), "HIT".to_string()));
+            return Some(response);
+        }
+    }
+    let prompt = match prompt_from_chat_request(&parsed) {
+        Ok(p) => p,
+        Err(msg) => return Some(error_response(400, "Bad Request", msg)),
+    };
+    let policy = requested_context_policy(&parsed, default_context_policy);
+    let system_prompt = system_prompt_from_chat_request(&parsed);
+    let (prompt, policy_applied) = match fit_context(&prompt, system_prompt.as_deref(), max_context_tokens, policy) {
+        Ok(fit) => fit,
+        Err(response) => return Some(response),
+    };
+    let mut tools = match tool_calls::parse_tools(&parsed) {
+        Ok(t) => t,
+        Err(msg) => return Some(error_response(400, "Bad Request", msg)),
+    };
+    // MCP-discovered tools are folded in alongside the request's own
+    // `tools` so a model can choose either kind — this server never
+    // executes a tool call itself either way (see `mcp.rs`'s module doc
+    // comment), so an MCP tool surfaces in the response exactly like a
+    // request-supplied one.
+    tools.extend(mcp_clients.tool_definitions());
+    let tool_choice = match tool_calls::parse_tool_choice(&parsed, &tools) {
+        Ok(c) => c,
+        Err(msg) => return Some(error_response(400, "Bad Request", msg)),
+    };
+    let prompt = tool_calls::append_tool_definitions(&prompt, &tools);
+    let images = match images_from_chat_request(&parsed) {
+        Ok(i) => i,
+        Err(msg) => return Some(error_response(400, "Bad Request", msg)),
+    };
+    let grammar = match grammar_from_response_format(&parsed) {
+        Ok(g) => g,
+        Err(msg) => return Some(error_response(400, "Bad Request", msg)),
+    };
+    let pre_moderation = guardrails.check(&prompt, Some(backend));
+    if pre_moderation.action == Some(guardrails::Action::Block) {
+        return Some(guardrails_blocked_response(&pre_moderation));
+    }
+    let prompt = plugins.run_all(&prompt);
+    fire_shadow_request(pool, aliases, alias.as_deref(), prompt.clone(), registry);
+    let admitted = match admit_request(backend, budget, &prompt, requested_max_tokens(&parsed)) {
+        Ok(admitted) => admitted,
+        Err(response) => return Some(response),
+    };
+    let wants_stream = parsed.get("stream").and_then(Json::as_bool).unwrap_or(false);
+    let n = requested_n(&parsed);
+    if wants_stream && n > 1 {
+        return Some(error_response(400, "Bad Request", "\"n\" greater than 1 is not supported for streaming chat completions"));
+    }
+    let effective_params = resolve_effective_generation_params(model_registry, backend.model_id(), &parsed);
+    registry.record_request(backend.model_id());
+    span.set_attribute("model", backend.model_id());
+    span.set_attribute("stream", &wants_stream.to_string());
+
+    if !wants_stream {
+        let started = std::time::Instant::now();
+        let candidates = best_of_n_completions(backend, &resolved, &prompt, &images, limits.timeout, n);
+        if let Some(bytes) = admitted {
+            budget.release(bytes);
+        }
+        if candidates.is_empty() {
+            span.end();
+            traces.lock().unwrap().push(span);
+            let message = ObjectBuilder::new().set("role", Json::String("assistant".to_string())).set("content", Json::String(String::new())).build();
+            let body = ObjectBuilder::new()
+                .set("id", Json::String(request_id))
+                .set("object", Json::String("chat.completion".to_string()))
+                .set("model", Json::String(backend.model_id().to_string()))
+                .set(
+                    "choices",
+                    Json::Array(vec![ObjectBuilder::new()
+                        .set("index", Json::Number(0.0))
+                        .set("message", message)
+                        .set("finish_reason", Json::String("timeout".to_string()))
+                        .build()]),
+                )
+                .build();
+            return Some(Response::ok_json(&body.to_string()));
+        };
+        let mut choices = Vec::new();
+        let mut combined_moderation = pre_moderation.clone();
+        let mut total_completion_tokens = 0usize;
+        for (index, completion) in candidates.into_iter().enumerate() {
+            let completion = truncate_at_stop_sequence(&completion, &effective_params.stop);
+            let (completion, truncated_by_length) = truncate_to_max_tokens(&completion, limits.max_output_tokens);
+            let completion = plugins.run_all(&completion);
+            let post_moderation = guardrails.check(&completion, Some(backend));
+            if post_moderation.action == Some(guardrails::Action::Block) {
+                return Some(guardrails_blocked_response(&guardrails::combine(&pre_moderation, &post_moderation)));
+            }
+            let completion = post_moderation.text.clone();
+            total_completion_tokens += completion.split_whitespace().count();
+            // See `best_of_n_completions`'s caller in `handle_completions`
+            // for why only the top-ranked candidate's moderation result
+            // feeds the response body's own `"moderation"` field.
+            if index == 0 {
+                combined_moderation = guardrails::combine(&pre_moderation, &post_moderation);
+            }
+            if let Some(grammar) = &grammar {
+                if !grammar.matches(&completion) {
+                    return Some(error_response(502, "Bad Gateway", "backend output did not match response_format schema"));
+                }
+            }
+            let tool_call = match tool_calls::parse_tool_call(&completion, &tools, &tool_choice) {
+                Ok(call) => call,
+                Err(msg) => return Some(error_response(502, "Bad Gateway", msg)),
+            };
+            // `logprobs` only makes sense over the plain-text completion, not
+            // a tool call's structured arguments, so this is `None` in the
+            // `Some(call)` arm below.
+            let text_for_logprobs = tool_call.is_none().then(|| completion.clone());
+            let (message, finish_reason) = match tool_call {
+                Some(call) => {
+                    let function = ObjectBuilder::new()
+                        .set("name", Json::String(call.name))
+                        .set("arguments", Json::String(call.arguments.to_string()))
+                        .build();
+                    let tool_call_json = ObjectBuilder::new()
+                        .set("id", Json::String(format!("call-{index}")))
+                        .set("type", Json::String("function".to_string()))
+                        .set("function", function)
+                        .build();
+                    let message = ObjectBuilder::new()
+                        .set("role", Json::String("assistant".to_string()))
+                        .set("content", Json::Null)
+                        .set("tool_calls", Json::Array(vec![tool_call_json]))
+                        .build();
+                    (message, "tool_calls")
+                }
+                None => {
+                    let message = ObjectBuilder::new()
+                        .set("role", Json::String("assistant".to_string()))
+                        .set("content", Json::String(completion))
+                        .build();
+                    (message, if truncated_by_length { "length" } else { "stop" })
+                }
+            };
+            let mut choice = ObjectBuilder::new().set("index", Json::Number(index as f64)).set("message", message);
+            if let (Some(top_n), Some(text)) = (requested_chat_logprobs_n(&parsed), &text_for_logprobs) {
+                if let Some(logprobs) = chat_completion_logprobs_json(backend, text, top_n) {
+                    choice = choice.set("logprobs", logprobs);
+                }
+            }
+            choices.push(choice.set("finish_reason", Json::String(finish_reason.to_string())).build());
+        }
+        let elapsed = started.elapsed();
+        registry.observe_time_to_first_token(elapsed.as_secs_f64());
+        if let Some(key) = usage_key {
+            usage_store.record(key, backend.model_id(), prompt.split_whitespace().count() as u64, total_completion_tokens as u64, elapsed.as_millis() as u64);
+        }
+        span.end();
+        traces.lock().unwrap().push(span);
+        let mut body = ObjectBuilder::new()
+            .set("id", Json::String(request_id.clone()))
+            .set("object", Json::String("chat.completion".to_string()))
+            .set("model", Json::String(backend.model_id().to_string()))
+            .set("choices", Json::Array(choices))
+            .set("generation_params", generation_params_json(&effective_params));
+        if let Some(policy) = policy_applied {
+            body = body.set("context_policy_applied", Json::String(policy.as_str().to_string()));
+        }
+        if combined_moderation.flagged {
+            body = body.set("moderation", combined_moderation.to_json());
+        }
+        let body = body.build();
+        let body = body.to_string();
+        let mut response = Response::ok_json(&body);
+        if cacheable {
+            cache.insert(cache_key, body);
+            response.headers.push(("X-Cache".to_string(), "MISS".to_string()));
+        }
+        return Some(response);
+    }
+
+    let Ok(mut sse) = SseWriter::start(stream) else { return None };
+    let cancel_token = cancellation.register(&request_id);
+    let started = std::time::Instant::now();
+    let mut first_token = true;
+    // Computed once per stream rather than once per token: `token_logprob`
+    // is called per emitted chunk below, so the same shape
+    // `chat_completion_logprobs_json` builds for the buffered path falls
+    // out of concatenating each chunk's single-entry `content` array.
+    let stream_logprobs_top_n = requested_chat_logprobs_n(&parsed);
+    let mut completion_tokens = 0usize;
+    // A forced tool choice means the completion itself is the tool-call
+    // JSON (see `tool_calls::parse_tool_call`'s stricter handling of
+    // `ToolChoice::Function`), so it's streamed through
+    // `tool_call_stream::ToolCallStreamParser` as `tool_calls` deltas
+    // instead of plain `content`. `ToolChoice::Auto` can't do this:
+    // nothing tells us before the fact whether the completion will be a
+    // tool call or ordinary text, so it keeps streaming as content the
+    // same as `ToolChoice::None`.
+    if let tool_calls::ToolChoice::Function(_) = &tool_choice {
+        let mut tool_call_parser = tool_call_stream::ToolCallStreamParser::new();
+        let mut tool_call_id_sent = false;
+        backend.stream(&prompt, &mut |token| {
+            if cancel_token.is_cancelled() || started.elapsed() > limits.timeout || completion_tokens >= limits.max_output_tokens {
+                return false;
+            }
+            if first_token {
+                registry.observe_time_to_first_token(started.elapsed().as_secs_f64());
+                first_token = false;
+            }
+            completion_tokens += token.split_whitespace().count();
+            let delta = tool_call_parser.feed(token);
+            if delta.name.is_none() && delta.arguments_chunk.is_empty() {
+                return true;
+            }
+            let mut function = ObjectBuilder::new();
+            if let Some(name) = &delta.name {
+                function = function.set("name", Json::String(name.clone()));
+            }
+            function = function.set("arguments", Json::String(delta.arguments_chunk.clone()));
+            let mut tool_call_json = ObjectBuilder::new().set("index", Json::Number(0.0));
+            if !tool_call_id_sent {
+                tool_call_json = tool_call_json.set("id", Json::String("call-0".to_string())).set("type", Json::String("function".to_string()));
+                tool_call_id_sent = true;
+            }
+            let tool_call_json = tool_call_json.set("function", function.build()).build();
+            let delta_json = ObjectBuilder::new().set("tool_calls", Json::Array(vec![tool_call_json])).build();
+            let chunk = ObjectBuilder::new()
+                .set("id", Json::String(request_id.clone()))
+                .set("object", Json::String("chat.completion.chunk".to_string()))
+                .set("model", Json::String(backend.model_id().to_string()))
+                .set("choices", Json::Array(vec![ObjectBuilder::new().set("index", Json::Number(0.0)).set("delta", delta_json).build()]))
+                .build();
+            sse.send(&chunk.to_string()).is_ok()
+        });
+        if let Err(msg) = tool_call_parser.finish() {
+            span.set_attribute("tool_call_stream_error", msg);
+        }
+    } else {
+        // `StopMatcher` sits between the backend's raw per-token output and
+        // what actually gets sent as an SSE chunk, so a stop sequence
+        // spanning a token boundary still cuts the response in the same
+        // place the buffered path (`truncate_at_stop_sequence`) would.
+        let mut stop_matcher = stop_sequences::StopMatcher::new(effective_params.stop.clone(), true);
+        let mut stopped_by_match = false;
+        backend.stream(&prompt, &mut |token| {
+            if cancel_token.is_cancelled() || started.elapsed() > limits.timeout || completion_tokens >= limits.max_output_tokens {
+                return false;
+            }
+            if first_token {
+                registry.observe_time_to_first_token(started.elapsed().as_secs_f64());
+                first_token = false;
+            }
+            let feed = stop_matcher.feed(token);
+            if feed.emit.is_empty() && !feed.stopped {
+                return true;
+            }
+            completion_tokens += feed.emit.split_whitespace().count();
+            let delta = ObjectBuilder::new()
+                .set("content", Json::String(feed.emit.clone()))
+                .build();
+            let mut choice = ObjectBuilder::new().set("index", Json::Number(0.0)).set("delta", delta);
+            if let Some(top_n) = stream_logprobs_top_n {
+                if let Some(info) = backend.token_logprob(feed.emit.trim(), top_n) {
+                    choice = choice
+                        .set("logprobs", ObjectBuilder::new().set("content", Json::Array(vec![chat_token_logprob_json(&info)])).build());
+                }
+            }
+            let chunk = ObjectBuilder::new()
+                .set("id", Json::String(request_id.clone()))
+                .set("object", Json::String("chat.completion.chunk".to_string()))
+                .set("model", Json::String(backend.model_id().to_string()))
+                .set("choices", Json::Array(vec![choice.build()]))
+                .build();
+            let sent = sse.send(&chunk.to_string()).is_ok();
+            if feed.stopped {
+                stopped_by_match = true;
+                return false;
+            }
+            sent
+        });
+        // Generation ended without ever completing a match (ran out of
+        // tokens, or was cancelled) — whatever the matcher was still
+        // holding back as a potential partial match is real output now and
+        // needs to go out.
+        if !stopped_by_match {
+            let trailing = stop_matcher.finish();
+            if !trailing.is_empty() {
+                completion_tokens += trailing.split_whitespace().count();
+                let delta = ObjectBuilder::new().set("content", Json::String(trailing)).build();
+                let chunk = ObjectBuilder::new()
+                    .set("id", Json::String(request_id.clone()))
+                    .set("object", Json::String("chat.completion.chunk".to_string()))
+                    .set("model", Json::String(backend.model_id().to_string()))
+                    .set(
+                        "choices",
+                        Json::Array(vec![ObjectBuilder::new().set("index", Json::Number(0.0)).set("delta", delta).build()]),
+                    )
+                    .build();
+                let _ = sse.send(&chunk.to_string());
+            }
+        }
+    }
+    cancellation.deregister(&request_id);
+    let _ = sse.finish();
+    if let Some(bytes) = admitted {
+        budget.release(bytes);
+    }
+    if let Some(key) = usage_key {
+        usage_store.record(key, backend.model_id(), prompt.split_whitespace().count() as u64, completion_tokens as u64, started.elapsed().as_millis() as u64);
+    }
+    span.end();
+    traces.lock().unwrap().push(span);
+    None
+}
+
+/// Handles a WebSocket-upgraded connection to `/v1/chat/completions`: reads
+/// one text frame carrying the same chat-request JSON body the SSE path
+/// accepts, then streams generated tokens as text frames instead of SSE
+/// events, closing with a WebSocket close frame in place of `[DONE]`.
+///
+/// A background thread watches the same socket for a close frame or drop
+/// (see `websocket::wait_for_disconnect`) while the main thread is busy
+/// writing token frames on it, since nothing else here polls for one.
+fn handle_chat_completions_ws(backend: &dyn InferenceBackend, cancellation: &cancellation::CancellationRegistry, req: &Request, stream: &mut Transport) {
+    if websocket::handshake(req, stream).is_err() {
+        return;
+    }
+    let Ok(text) = websocket::read_text_frame(stream) else { return };
+    let Ok(parsed) = Json::parse(&text) else { return };
+    let Ok(prompt) = prompt_from_chat_request(&parsed) else { return };
+
+    let request_id = next_request_id();
+    let cancel_token = cancellation.register(&request_id);
+    if let Ok(mut watcher_stream) = stream.try_clone() {
+        let watcher_token = cancel_token.clone();
+        std::thread::spawn(move || {
+            let _ = websocket::wait_for_disconnect(&mut watcher_stream);
+            watcher_token.cancel();
+        });
+    }
+
+    backend.stream(&prompt, &mut |token| {
+        if cancel_token.is_cancelled() {
+            return false;
+        }
+        websocket::send_text(stream, token).is_ok()
+    });
+    cancellation.deregister(&request_id);
+    let _ = websocket::send_close(stream);
+}
+
+/// Streams `bus` events matching the request's `?severity=`/`?subsystem=`
+/// query parameters until the client disconnects: SSE for a plain GET, or
+/// a WebSocket connection when the request is a WS upgrade, mirroring the
+/// dual delivery `/v1/chat/completions` already offers for streamed
+/// tokens. `severity` filters to that level and above (`?severity=warn`
+/// hides `info`/`debug`); an unrecognized value is treated as no filter.
+/// Returns `None` either way, having already written the response itself.
+fn handle_admin_events(bus: &events::EventBus, req: &Request, stream: &mut Transport) -> Option<Response> {
+    let filter = events::EventFilter {
+        min_severity: req.query.get("severity").and_then(|s| events::Severity::parse(s)),
+        subsystem: req.query.get("subsystem").cloned(),
+    };
+    let rx = bus.subscribe(filter);
+    if websocket::is_upgrade_request(req) {
+        if websocket::handshake(req, stream).is_err() {
+            return None;
+        }
+        while let Ok(event) = rx.recv() {
+            if websocket::send_text(stream, &event.to_json().to_string()).is_err() {
+                break;
+            }
+        }
+        let _ = websocket::send_close(stream);
+        return None;
+    }
+    let Ok(mut sse) = SseWriter::start(stream) else { return None };
+    while let Ok(event) = rx.recv() {
+        if sse.send(&event.to_json().to_string()).is_err() {
+            break;
+        }
+    }
+    None
+}
+
+/// Dispatches an already-authenticated `/admin/*` request. Separate from
+/// [`route`]'s main match so admin auth stays a single check at the top of
+/// `route` instead of being repeated per admin endpoint.
+fn route_admin(
+    pool: &model_pool::ModelPool,
+    admin: &admin::AdminState,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    max_cache_bytes: u64,
+    auth: &auth::AuthRegistry,
+    tenants: &tenancy::TenantRegistry,
+    templates: &prompt_templates::TemplateRegistry,
+    req: &Request,
+) -> Response {
+    if req.method == Method::Post {
+        if let Some((model_id, action)) = parse_admin_model_path(&req.path) {
+            return handle_admin_model(pool, model_id, action);
+        }
+    }
+    match (&req.method, req.path.as_str()) {
+        (Method::Post, "/admin/drain") => handle_admin_drain(admin, req),
+        (Method::Post, "/admin/cache/flush") => handle_admin_flush_cache(pool),
+        (Method::Post, "/admin/cache/gc") => handle_admin_gc(pool, model_registry, max_cache_bytes),
+        (Method::Get, "/admin/scheduler") => handle_admin_scheduler(pool),
+        (Method::Get, "/admin/thermal") => handle_admin_thermal(pool),
+        (Method::Post, "/admin/log-level") => handle_admin_log_level(admin, req),
+        (Method::Get, "/admin/tenants") => handle_admin_tenants(auth, tenants),
+        (Method::Get, "/admin/templates") => handle_admin_list_templates(templates),
+        (Method::Post, "/admin/templates") => handle_admin_register_template(templates, req),
+        _ => Response::not_found(),
+    }
+}
+
+/// Handles a completions/chat-completions request in router mode: picks a
+/// downstream node by the request's `"model"` (and, for KV reuse across a
+/// conversation's turns, `"session_id"`) and forwards the whole request to
+/// it byte-for-byte, returning `None` on success since the response has
+/// already been written straight to `client_stream` — see `router.rs`'s
+/// doc comment for why this doesn't parse or rebuild the response itself.
+fn handle_router_proxy(router: &router::Router, req: &Request, client_stream: &mut Transport) -> Option<Response> {
+    let parsed = match req.body_str().ok().and_then(|s| Json::parse(s).ok()) {
+        Some(j) => j,
+        None => return Some(error_response(400, "Bad Request", "request body must be valid JSON")),
+    };
+    let model = parsed.get("model").and_then(Json::as_str).unwrap_or("");
+    let session_id = parsed.get("session_id").and_then(Json::as_str);
+    let Some(node) = router.select(model, session_id) else {
+        return Some(error_response(503, "Service Unavailable", &format!("router: no healthy node serves model \"{model}\"")));
+    };
+    router.record_start(&node.id);
+    let result = router::proxy_request(&node.address, req, client_stream);
+    router.record_finish(&node.id);
+    match result {
+        Ok(()) => None,
+        Err(_) => {
+            router.set_health(&node.id, false);
+            Some(error_response(502, "Bad Gateway", &format!("router: node \"{}\" is unreachable", node.id)))
+        }
+    }
+}
+
+fn route(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    adapters: &lora::AdapterRegistry,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    embedding_backend: &dyn EmbeddingBackend,
+    embedding_cache: &embedding_cache::EmbeddingCache,
+    transcription_backend: &dyn TranscriptionBackend,
+    speech_backend: &dyn SpeechBackend,
+    vector_store: &Mutex<VectorStore>,
+    registry: &'static metrics::Registry,
+    traces: &Mutex<Vec<tracing::Span>>,
+    budget: &resources::MemoryBudget,
+    cancellation: &cancellation::CancellationRegistry,
+    auth: &auth::AuthRegistry,
+    admin: &admin::AdminState,
+    logger: &logging::JsonLogger,
+    models_dir: &str,
+    readiness_check_timeout: std::time::Duration,
+    session_store: &sessions::SessionStore,
+    response_cache: &response_cache::ResponseCache,
+    batch_store: &'static batches::BatchStore,
+    max_context_tokens: usize,
+    max_cache_bytes: u64,
+    default_context_policy: context_policy::ContextPolicy,
+    router: Option<&router::Router>,
+    tenants: &tenancy::TenantRegistry,
+    usage_store: &usage::UsageStore,
+    audit_logger: Option<&audit::AuditLogger>,
+    guardrails: &guardrails::GuardrailsEngine,
+    plugins: &plugins::PluginRegistry,
+    mcp_enabled: bool,
+    mcp_clients: &mcp::McpClientRegistry,
+    agent_enabled: bool,
+    agent_tools: &agent::AgentTools,
+    agent_max_steps: usize,
+    pipelines_enabled: bool,
+    pipelines: &pipelines::PipelineRegistry,
+    jobs_enabled: bool,
+    jobs: &jobs::JobRegistry,
+    ollama_compat_enabled: bool,
+    templates: &prompt_templates::TemplateRegistry,
+    aliases: &model_alias::AliasRegistry,
+    rope_scaling_overrides: &std::collections::HashMap<String, (gguf::RopeScaling, f64)>,
+    idempotency_ttl: std::time::Duration,
+    events: &events::EventBus,
+    req: &Request,
+    stream: &mut Transport,
+) -> Option<Response> {
+    let log_request_id = next_log_request_id();
+    let started = std::time::Instant::now();
+    // Wraps every return point so each request gets exactly one log line
+    // (and, for `/v1/*` endpoints, one audit entry) regardless of which
+    // branch below produced its response.
+    let finish = |response: Option<Response>, client: Option<&str>| -> Option<Response> {
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        logger.log(&logging::LogEvent {
+            request_id: &log_request_id,
+            model: None,
+            client,
+            latency_ms,
+            prompt_tokens: None,
+            completion_tokens: None,
+        });
+        if let (Some(audit_logger), Some(response)) = (audit_logger, &response) {
+            if req.path.starts_with("/v1/") {
+                let prompt = std::str::from_utf8(&req.body).ok();
+                let body = std::str::from_utf8(&response.body).ok();
+                audit_logger.log(&log_request_id, &format!("{:?}", req.method), &req.path, client, response.status, prompt, body);
+            }
+        }
+        if let Some(response) = &response {
+            let severity = if response.status >= 500 {
+                events::Severity::Error
+            } else if response.status >= 400 {
+                events::Severity::Warn
+            } else {
+                events::Severity::Info
+            };
+            events.publish(events::ServerEvent::new(
+                "http",
+                severity,
+                format!("{:?} {} -> {} ({latency_ms:.1}ms)", req.method, req.path, response.status),
+            ));
+        }
+        response
+    };
+
+    if req.path.starts_with("/admin/") {
+        if let Err(e) = admin.authenticate(req) {
+            return finish(
+                Some(match e {
+                    admin::AdminAuthError::MissingApiKey => error_response(401, "Unauthorized", "missing admin API key"),
+                    admin::AdminAuthError::InvalidApiKey => error_response(401, "Unauthorized", "invalid admin API key"),
+                }),
+                None,
+            );
+        }
+        // Long-lived (never returns until the client disconnects), so it
+        // needs `stream` directly the same way the WebSocket chat/
+        // transcription handlers below do — `route_admin` only ever
+        // produces one response and hands `stream` back, which this isn't.
+        if req.path == "/admin/events" {
+            return finish(handle_admin_events(events, req, stream), None);
+        }
+        return finish(Some(route_admin(pool, admin, model_registry, max_cache_bytes, auth, tenants, templates, req)), None);
+    }
+    // `/v1/models` stays reachable while draining so a load balancer can
+    // still tell the process is alive; every other `/v1/*` endpoint does
+    // real generation work, which is exactly what draining means to stop.
+    if admin.is_draining() && req.path.starts_with("/v1/") && req.path != "/v1/models" {
+        return finish(Some(error_response(503, "Service Unavailable", "server is draining")), None);
+    }
+    // Liveness/readiness probes come from an orchestrator, not a client with
+    // an API key, so they bypass `auth` entirely — same reasoning as
+    // `/v1/models` staying reachable while draining.
+    if req.method == Method::Get && req.path == "/healthz" {
+        return finish(Some(handle_healthz()), None);
+    }
+    if req.method == Method::Get && req.path == "/readyz" {
+        return finish(Some(handle_readyz(pool, model_registry, backend, models_dir, readiness_check_timeout)), None);
+    }
+    // The dashboard page itself holds no secret — see `dashboard.rs`'s
+    // module doc comment — so it's reachable the same unauthenticated way
+    // `/healthz`/`/readyz` are; the operator supplies the real admin/API
+    // keys from inside the page before any `/admin/*` or `/metrics` call
+    // goes out.
+    if req.method == Method::Get && req.path == "/dashboard" {
+        return finish(Some(handle_dashboard()), None);
+    }
+
+    let auth_key = match auth.authenticate(req) {
+        Ok(key) => key,
+        Err(auth::AuthError::MissingApiKey) => return finish(Some(error_response(401, "Unauthorized", "missing API key")), None),
+        Err(auth::AuthError::InvalidApiKey) => return finish(Some(error_response(401, "Unauthorized", "invalid API key")), None),
+        Err(auth::AuthError::RateLimited) => {
+            return finish(Some(error_response(429, "Too Many Requests", "rate limit exceeded")), None)
+        }
+        Err(auth::AuthError::QuotaExceeded) => {
+            return finish(Some(error_response(429, "Too Many Requests", "daily token quota exceeded")), None)
+        }
+    };
+    let tenant = tenants.tenant_for(auth_key.as_deref());
+    let limits = auth.generation_limits_for(auth_key.as_deref());
+
+    // Applies only when this process serves completions itself — router
+    // mode forwards to another node entirely (see below) and doesn't
+    // share this process's `response_cache`, so a retry there is the
+    // downstream node's own problem to dedupe.
+    let is_idempotent_endpoint =
+        router.is_none() && req.method == Method::Post && (req.path == "/v1/completions" || req.path == "/v1/chat/completions");
+    // Scoped by `auth_key` so two tenants who happen to send the same
+    // client-supplied `Idempotency-Key` (an accidental collision, or one
+    // tenant guessing/observing another's value) never share a slot —
+    // without this, `wait_for_idempotent_result` would hand tenant B the
+    // cached prompt+completion body from tenant A's request.
+    let idempotency_key = req
+        .header("Idempotency-Key")
+        .filter(|_| is_idempotent_endpoint)
+        .map(|k| format!("{}:{k}", auth_key.as_deref().unwrap_or("")));
+    let mut owns_idempotency_key = false;
+    if let Some(key) = &idempotency_key {
+        if response_cache.claim_idempotency_key(key, idempotency_ttl) {
+            owns_idempotency_key = true;
+        } else {
+            let body = response_cache.wait_for_idempotent_result(key, limits.timeout);
+            return finish(
+                Some(body.map(|b| Response::ok_json(&b)).unwrap_or_else(|| {
+                    error_response(504, "Gateway Timeout", "timed out waiting for the original request with this Idempotency-Key to finish")
+                })),
+                auth_key.as_deref(),
+            );
+        }
+    }
+
+    // In router mode, completions/chat completions never reach this
+    // process's own backend at all — they're forwarded to whichever
+    // downstream node `handle_router_proxy` picks, and everything else
+    // (models, embeddings, sessions, admin, ...) keeps being served
+    // locally exactly as it is when router mode is off.
+    if let Some(router) = router {
+        if req.method == Method::Post && (req.path == "/v1/completions" || req.path == "/v1/chat/completions") {
+            return finish(handle_router_proxy(router, req, stream), auth_key.as_deref());
+        }
+    }
+
+    if req.method == Method::Post {
+        if let Some((collection, action)) = parse_vectors_path(&req.path) {
+            return finish(Some(handle_vectors(vector_store, tenant, collection, action, req)), auth_key.as_deref());
+        }
+        if let Some(request_id) = parse_cancel_path(&req.path) {
+            return finish(Some(handle_cancel(cancellation, request_id)), auth_key.as_deref());
+        }
+        if pipelines_enabled {
+            if let Some(name) = parse_pipelines_path(&req.path) {
+                return finish(handle_pipeline_run(pipelines, backend, embedding_backend, vector_store, response_cache, name, req, stream), auth_key.as_deref());
+            }
+        }
+    }
+    if let Some((id, trailing)) = parse_sessions_path(&req.path) {
+        return finish(Some(handle_session(session_store, backend, id, trailing, req)), auth_key.as_deref());
+    }
+    if let Some((id, trailing)) = parse_batches_path(&req.path) {
+        return finish(Some(handle_batch(batch_store, id, trailing, req)), auth_key.as_deref());
+    }
+    if jobs_enabled {
+        if let Some((id, trailing)) = parse_jobs_path(&req.path) {
+            let job_ctx =
+                jobs::JobContext { backend, embedding_backend, vector_store, model_registry, pipelines, response_cache };
+            return finish(Some(handle_job(jobs, &job_ctx, id, trailing, req)), auth_key.as_deref());
+        }
+    }
+    let response = match (&req.method, req.path.as_str()) {
+        (Method::Get, "/v1/models") => Some(handle_models(backend, model_registry, rope_scaling_overrides)),
+        (Method::Get, "/v1/jobs") if jobs_enabled => Some(handle_list_jobs(jobs)),
+        (Method::Post, "/v1/sessions") => Some(handle_create_session(session_store, req)),
+        (Method::Post, "/v1/batches") => Some(handle_create_batch(backend, pool, adapters, batch_store, tenants, tenant, aliases, req)),
+        (Method::Get, "/metrics") => Some(handle_metrics(registry)),
+        (Method::Post, "/v1/usage") => Some(handle_usage(usage_store, auth_key.as_deref(), req)),
+        (Method::Post, "/mcp") if mcp_enabled => Some(handle_mcp(backend, req)),
+        (Method::Post, "/api/generate") if ollama_compat_enabled => {
+            Some(handle_ollama_generate(backend, pool, budget, tenants, tenant, aliases, limits, req))
+        }
+        (Method::Post, "/api/chat") if ollama_compat_enabled => {
+            Some(handle_ollama_chat(backend, pool, budget, tenants, tenant, aliases, limits, req))
+        }
+        (Method::Get, "/api/tags") if ollama_compat_enabled => Some(handle_ollama_tags(backend, model_registry)),
+        (Method::Post, "/api/pull") if ollama_compat_enabled => Some(handle_ollama_pull(backend, model_registry, req)),
+        (Method::Post, "/v1/agents/runs") if agent_enabled => handle_agent_run(backend, agent_tools, agent_max_steps, req, stream),
+        (Method::Post, "/v1/completions") => Some(handle_completions(
+            backend,
+            pool,
+            adapters,
+            model_registry,
+            registry,
+            traces,
+            budget,
+            response_cache,
+            max_context_tokens,
+            default_context_policy,
+            tenants,
+            tenant,
+            usage_store,
+            auth_key.as_deref(),
+            guardrails,
+            plugins,
+            aliases,
+            limits,
+            req,
+        )),
+        (Method::Post, "/v1/embeddings") => {
+            let response = handle_embeddings(embedding_cache, embedding_backend, req);
+            registry.set_embedding_cache_hit_ratio(embedding_cache.hit_rate());
+            Some(response)
+        }
+        (Method::Post, "/v1/rerank") => Some(handle_rerank(embedding_backend, req)),
+        (Method::Post, "/v1/rag/query") => Some(handle_rag_query(vector_store, embedding_backend, tenant, req)),
+        (Method::Post, "/v1/context/assemble") => Some(handle_context_assemble(model_registry, req)),
+        (Method::Get, "/v1/audio/transcriptions") if websocket::is_upgrade_request(req) => {
+            handle_audio_transcriptions_ws(transcription_backend, req, stream);
+            None
+        }
+        (Method::Post, "/v1/audio/transcriptions") => Some(handle_audio_transcriptions(transcription_backend, req)),
+        (Method::Post, "/v1/audio/speech") => Some(handle_audio_speech(speech_backend, req)),
+        (Method::Get, "/v1/chat/completions") if websocket::is_upgrade_request(req) => {
+            handle_chat_completions_ws(backend, cancellation, req, stream);
+            None
+        }
+        (Method::Post, "/v1/chat/completions") => handle_chat_completions(
+            backend,
+            pool,
+            adapters,
+            model_registry,
+            registry,
+            traces,
+            budget,
+            cancellation,
+            response_cache,
+            max_context_tokens,
+            default_context_policy,
+            tenants,
+            tenant,
+            usage_store,
+            auth_key.as_deref(),
+            guardrails,
+            plugins,
+            mcp_clients,
+            templates,
+            aliases,
+            limits,
+            req,
+            stream,
+        ),
+        _ => Some(Response::not_found()),
+    };
+    // Streaming paths take over `stream` directly and return `None` here,
+    // so there's no response body to meter — quota tracking only covers
+    // buffered responses (see `auth.rs`'s doc comment).
+    if let (Some(key), Some(response)) = (&auth_key, &response) {
+        auth.record_usage(key, response.body.len());
+    }
+    // A streaming `/v1/chat/completions` claim leaves its key stuck
+    // "in progress" until `idempotency_ttl` elapses — the SSE/WebSocket
+    // takeover means there's no buffered body here to complete it with.
+    if owns_idempotency_key {
+        if let (Some(key), Some(response)) = (&idempotency_key, &response) {
+            response_cache.complete_idempotency_key(key, String::from_utf8_lossy(&response.body).into_owned());
+        }
+    }
+    finish(response, auth_key.as_deref())
+}
+
+fn serve_one(
+    backend: &'static dyn InferenceBackend,
+    pool: &'static model_pool::ModelPool,
+    adapters: &lora::AdapterRegistry,
+    model_registry: &Mutex<registry::ModelRegistry>,
+    embedding_backend: &dyn EmbeddingBackend,
+    embedding_cache: &embedding_cache::EmbeddingCache,
+    transcription_backend: &dyn TranscriptionBackend,
+    speech_backend: &dyn SpeechBackend,
+    vector_store: &Mutex<VectorStore>,
+    registry: &'static metrics::Registry,
+    traces: &Mutex<Vec<tracing::Span>>,
+    budget: &resources::MemoryBudget,
+    cancellation: &cancellation::CancellationRegistry,
+    auth: &auth::AuthRegistry,
+    admin: &admin::AdminState,
+    logger: &logging::JsonLogger,
+    models_dir: &str,
+    readiness_check_timeout: std::time::Duration,
+    session_store: &sessions::SessionStore,
+    response_cache: &response_cache::ResponseCache,
+    batch_store: &'static batches::BatchStore,
+    max_context_tokens: usize,
+    max_cache_bytes: u64,
+    default_context_policy: context_policy::ContextPolicy,
+    router: Option<&router::Router>,
+    tenants: &tenancy::TenantRegistry,
+    usage_store: &usage::UsageStore,
+    audit_logger: Option<&audit::AuditLogger>,
+    guardrails: &guardrails::GuardrailsEngine,
+    plugins: &plugins::PluginRegistry,
+    mcp_enabled: bool,
+    mcp_clients: &mcp::McpClientRegistry,
+    agent_enabled: bool,
+    agent_tools: &agent::AgentTools,
+    agent_max_steps: usize,
+    pipelines_enabled: bool,
+    pipelines: &pipelines::PipelineRegistry,
+    jobs_enabled: bool,
+    jobs: &jobs::JobRegistry,
+    ollama_compat_enabled: bool,
+    templates: &prompt_templates::TemplateRegistry,
+    aliases: &model_alias::AliasRegistry,
+    rope_scaling_overrides: &std::collections::HashMap<String, (gguf::RopeScaling, f64)>,
+    idempotency_ttl: std::time::Duration,
+    events: &events::EventBus,
+    mut stream: Transport,
+) {
+    let req = match http::read_request(&mut stream) {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    if let Some(response) = route(
+        backend,
+        pool,
+        adapters,
+        model_registry,
+        embedding_backend,
+        embedding_cache,
+        transcription_backend,
+        speech_backend,
+        vector_store,
+        registry,
+        traces,
+        budget,
+        cancellation,
+        auth,
+        admin,
+        logger,
+        models_dir,
+        readiness_check_timeout,
+        session_store,
+        response_cache,
+        batch_store,
+        max_context_tokens,
+        max_cache_bytes,
+        default_context_policy,
+        router,
+        tenants,
+        usage_store,
+        audit_logger,
+        guardrails,
+        plugins,
+        mcp_enabled,
+        mcp_clients,
+        agent_enabled,
+        agent_tools,
+        agent_max_steps,
+        pipelines_enabled,
+        pipelines,
+        jobs_enabled,
+        jobs,
+        ollama_compat_enabled,
+        templates,
+        aliases,
+        rope_scaling_overrides,
+        idempotency_ttl,
+        events,
+        &req,
+        &mut stream,
+    ) {
+        let _ = response.write_to(&mut stream);
+    }
+}
+
+/// Accepts connections on `addr` and serves each with [`rpc::serve_connection`]
+/// against `backend` — the RPC counterpart to the HTTP `serve_one` loop.
+fn serve_rpc(backend: &'static dyn InferenceBackend, addr: &str) {
+    let listener = TcpListener::bind(addr).expect("failed to bind RPC address");
+    println!("rpc listening on {addr}");
+    for mut stream in listener.incoming().flatten() {
+        std::thread::spawn(move || {
+            let _ = rpc::serve_connection(backend, &mut stream);
+        });
+    }
+}
+
+fn main() {
+    let config_path = std::env::var("AI_SERVER_CONFIG").unwrap_or_else(|_| "./ai-server.toml".to_string());
+    let config = config::ServerConfig::load_or_default(std::path::Path::new(&config_path))
+        .expect("failed to load AI_SERVER_CONFIG");
+    let config: &'static Mutex<config::ServerConfig> = Box::leak(Box::new(Mutex::new(config)));
+    config::watch(std::path::PathBuf::from(&config_path), config, std::time::Duration::from_secs(5));
+
+    let (
+        addr,
+        rpc_addr,
+        models_dir,
+        model_idle_timeout,
+        api_keys,
+        requests_per_minute,
+        daily_token_quota,
+        default_max_output_tokens,
+        default_request_timeout_seconds,
+        max_output_tokens_by_key,
+        request_timeout_by_key_seconds,
+        admin_keys,
+        log_file,
+        log_max_bytes,
+        log_pretty,
+        readiness_check_timeout,
+        shutdown_drain_timeout,
+        response_cache_ttl,
+        response_cache_max_entries,
+        embedding_cache_max_entries,
+        idempotency_ttl,
+        batch_priority_keys,
+        background_priority_keys,
+        warmup_runs,
+        warmup_prompt,
+        strict_model_verification,
+        max_context_tokens,
+        max_cache_bytes,
+        default_context_policy,
+        router_nodes,
+        backend_override,
+        replay_mode,
+        replay_file,
+        mock_backend_enabled,
+        mock_default_response,
+        mock_latency_ms,
+        tenant_keys,
+        tenant_models,
+        rope_scaling_by_model,
+        model_aliases,
+        audit_enabled,
+        audit_sink,
+        audit_file,
+        audit_syslog_addr,
+        audit_include_bodies,
+        audit_redact_patterns,
+        guardrails_enabled,
+        guardrails_block_patterns,
+        guardrails_redact_patterns,
+        guardrails_annotate_patterns,
+        guardrails_classifier_prompt,
+        guardrails_classifier_action,
+        plugins_enabled,
+        plugins_dir,
+        plugins_wasmtime_path,
+        plugins_reload_interval_seconds,
+        mcp_enabled,
+        mcp_client_servers,
+        mcp_client_timeout_ms,
+        mcp_client_refresh_interval_seconds,
+        agent_enabled,
+        agent_shell_allowlist,
+        agent_http_allowlist,
+        agent_file_root,
+        agent_max_steps,
+        pipelines_enabled,
+        pipelines_dir,
+        pipelines_reload_interval_seconds,
+        jobs_enabled,
+        jobs_dir,
+        watcher_enabled,
+        watcher_dir,
+        watcher_collection,
+        watcher_poll_interval_seconds,
+        ollama_compat_enabled,
+        discovery_enabled,
+        discovery_interval_seconds,
+        discovery_name,
+        unix_socket_path,
+        unix_socket_permissions,
+    ) = {
+        let config = config.lock().unwrap();
+        (
+            config.bind_address.clone(),
+            config.rpc_bind_address.clone(),
+            config.models_dir.clone(),
+            std::time::Duration::from_secs(config.model_idle_timeout_seconds as u64),
+            config.api_keys.clone(),
+            config.requests_per_minute,
+            config.daily_token_quota,
+            config.default_max_output_tokens,
+            config.default_request_timeout_seconds,
+            config.max_output_tokens_by_key.clone(),
+            config.request_timeout_by_key_seconds.clone(),
+            config.admin_keys.clone(),
+            config.log_file.clone(),
+            config.log_max_bytes,
+            config.log_pretty,
+            std::time::Duration::from_millis(config.readiness_check_timeout_ms as u64),
+            std::time::Duration::from_secs(config.shutdown_drain_timeout_seconds as u64),
+            std::time::Duration::from_secs(config.response_cache_ttl_seconds as u64),
+            config.response_cache_max_entries,
+            config.embedding_cache_max_entries,
+            std::time::Duration::from_secs(config.idempotency_key_ttl_seconds as u64),
+            config.batch_priority_keys.clone(),
+            config.background_priority_keys.clone(),
+            config.warmup_runs,
+            config.warmup_prompt.clone(),
+            config.strict_model_verification,
+            config.max_context_tokens,
+            config.max_cache_bytes,
+            context_policy::ContextPolicy::parse(&config.context_overflow_policy)
+                .expect("validate() already rejects an unparseable context_overflow_policy"),
+            config.router_nodes.clone(),
+            config.backend_override.as_deref().map(|s| {
+                backend::Backend::parse(s).expect("validate() already rejects an unparseable backend.override")
+            }),
+            config.replay_mode.clone(),
+            config.replay_file.clone(),
+            config.mock_backend_enabled,
+            config.mock_default_response.clone(),
+            config.mock_latency_ms,
+            config.tenant_keys.clone(),
+            config.tenant_models.clone(),
+            config.rope_scaling_by_model.clone(),
+            config.model_aliases.clone(),
+            config.audit_enabled,
+            config.audit_sink.clone(),
+            config.audit_file.clone(),
+            config.audit_syslog_addr.clone(),
+            config.audit_include_bodies,
+            config.audit_redact_patterns.clone(),
+            config.guardrails_enabled,
+            config.guardrails_block_patterns.clone(),
+            config.guardrails_redact_patterns.clone(),
+            config.guardrails_annotate_patterns.clone(),
+            config.guardrails_classifier_prompt.clone(),
+            config.guardrails_classifier_action.clone(),
+            config.plugins_enabled,
+            config.plugins_dir.clone(),
+            config.plugins_wasmtime_path.clone(),
+            config.plugins_reload_interval_seconds,
+            config.mcp_enabled,
+            config.mcp_client_servers.clone(),
+            config.mcp_client_timeout_ms,
+            config.mcp_client_refresh_interval_seconds,
+            config.agent_enabled,
+            config.agent_shell_allowlist.clone(),
+            config.agent_http_allowlist.clone(),
+            config.agent_file_root.clone(),
+            config.agent_max_steps,
+            config.pipelines_enabled,
+            config.pipelines_dir.clone(),
+            config.pipelines_reload_interval_seconds,
+            config.jobs_enabled,
+            config.jobs_dir.clone(),
+            config.watcher_enabled,
+            config.watcher_dir.clone(),
+            config.watcher_collection.clone(),
+            config.watcher_poll_interval_seconds,
+            config.ollama_compat_enabled,
+            config.discovery_enabled,
+            config.discovery_interval_seconds,
+            config.discovery_name.clone(),
+            config.unix_socket_path.clone(),
+            config.unix_socket_permissions.clone(),
+        )
+    };
+    let models_dir: &'static str = Box::leak(models_dir.into_boxed_str());
+    let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("echo-0")));
+    let speech_backend: &'static tts::ToneSpeechBackend = Box::leak(Box::new(tts::ToneSpeechBackend::new(vec!["en-us"])));
+    let vector_store: &'static Mutex<VectorStore> =
+        Box::leak(Box::new(Mutex::new(VectorStore::open("./vectorstore-data"))));
+    let session_store: &'static sessions::SessionStore =
+        Box::leak(Box::new(sessions::SessionStore::open("./sessions-data").expect("failed to open session store")));
+    let batch_store: &'static batches::BatchStore =
+        Box::leak(Box::new(batches::BatchStore::open("./batches-data").expect("failed to open batch store")));
+    // Backs `/admin/events`; constructed unconditionally like `registry`
+    // above since a bus with no subscribers is a harmless no-op — nothing
+    // reads it until a dashboard connects.
+    let events: &'static events::EventBus = Box::leak(Box::new(events::EventBus::new()));
+    let response_cache: &'static response_cache::ResponseCache = Box::leak(Box::new(
+        response_cache::ResponseCache::new(response_cache_ttl, response_cache_max_entries).with_eviction_hook(|key| {
+            events.publish(events::ServerEvent::new("response_cache", events::Severity::Debug, format!("evicted {key}")));
+        }),
+    ));
+    let embedding_cache: &'static embedding_cache::EmbeddingCache = Box::leak(Box::new(
+        embedding_cache::EmbeddingCache::open("./embedding-cache-data", embedding_cache_max_entries)
+            .expect("failed to open embedding cache")
+            .with_eviction_hook(|key| {
+                events.publish(events::ServerEvent::new("embedding_cache", events::Severity::Debug, format!("evicted {key}")));
+            }),
+    ));
+    // `config.validate()` already rejected any entry not shaped like
+    // `id=host:port`, so the `split_once('=')` below always succeeds.
+    let router: Option<&'static router::Router> = (!router_nodes.is_empty()).then(|| {
+        let nodes = router_nodes
+            .iter()
+            .map(|entry| {
+                let (id, address) = entry.split_once('=').expect("validate() already rejected malformed router.nodes entries");
+                router::RouterNode { id: id.to_string(), address: address.to_string() }
+            })
+            .collect();
+        let router: &'static router::Router = Box::leak(Box::new(router::Router::new(nodes)));
+        router::spawn_health_checks(router, std::time::Duration::from_secs(10));
+        router
+    });
+    let registry: &'static metrics::Registry = Box::leak(Box::new(metrics::Registry::new()));
+    if let Some(gpu) = gpu::GpuInfo::detect() {
+        if let Some(bytes) = gpu.unified_memory_bytes {
+            registry.set_gpu_memory_bytes(bytes);
+        }
+    } else if let Some(cuda) = cuda::CudaInfo::detect() {
+        // Metal and CUDA are mutually exclusive per host, so this only
+        // runs on the Linux boxes `gpu::GpuInfo::detect` always returns
+        // `None` for — see `cuda.rs`'s module doc comment for what a
+        // CUDA-backed `InferenceBackend` would do with `tensor_split` once
+        // one exists.
+        registry.set_gpu_memory_bytes(cuda.total_memory_bytes());
+    } else if let Some(vulkan) = vulkan::VulkanInfo::detect() {
+        // Last resort of the three: no unified-memory or total-VRAM figure
+        // to report here since `vulkaninfo --summary` doesn't surface one,
+        // but logging that a Vulkan device exists at all still matters —
+        // see `vulkan.rs`'s module doc comment for why nothing consumes
+        // this beyond that yet.
+        println!("vulkan: detected {} device(s) for cross-vendor GPU fallback", vulkan.devices.len());
+    }
+    // Reserved for a future Vulkan backend's compiled shader pipelines
+    // (see `vulkan.rs::pipeline_cache_path`) — created unconditionally,
+    // like `prefix_cache_dir` below, so it's ready before that backend
+    // exists rather than racing its first write against directory setup.
+    std::fs::create_dir_all("./shader-cache-data").expect("failed to create shader cache directory");
+    let traces: &'static Mutex<Vec<tracing::Span>> = Box::leak(Box::new(Mutex::new(Vec::new())));
+    let hardware_profile = hardware::HardwareProfile::probe();
+    // `EchoBackend` has no weights to size, so this asks with `0` bytes —
+    // the same question a real backend's loader would ask with its actual
+    // weight size once one exists (see `backend.rs`'s module doc comment).
+    let backend_selection = backend::select(&hardware_profile, 0, backend_override);
+    println!("backend: selected {} ({})", backend_selection.chosen.as_str(), backend_selection
+        .candidates
+        .iter()
+        .find(|c| c.backend == backend_selection.chosen)
+        .map(|c| c.reason.as_str())
+        .unwrap_or(""));
+    // Leave a quarter of available memory unbudgeted for the OS, other
+    // processes, and the KV-cache estimate's own error margin.
+    let budget: &'static resources::MemoryBudget =
+        Box::leak(Box::new(resources::MemoryBudget::from_hardware_profile(&hardware_profile, 0.75)));
+
+    // Wrapped in a `Mutex` (unlike `budget`/`adapters`/the other `'static`
+    // singletons above, which are only ever read after startup) because
+    // `handle_admin_gc` needs to remove entries from the live catalog
+    // while request-handling threads may be resolving ids out of it at
+    // the same time.
+    let model_registry: &'static Mutex<registry::ModelRegistry> = Box::leak(Box::new(Mutex::new(
+        registry::ModelRegistry::open(std::path::Path::new(&models_dir)).expect("failed to open model registry"),
+    )));
+    // `EchoBackend` is the only backend this tree can construct today (see
+    // its doc comment), so the pool's factory just proves the
+    // lazy-load/idle-unload lifecycle for any id the registry recognizes.
+    //
+    // `kvcache.rs`'s blocks hold no tensor data of their own to snapshot
+    // (see `prefix_cache.rs`'s `save`/`load` doc comment), so the closest
+    // thing to persisting a model's KV-cache state across an idle eviction
+    // is round-tripping its `PrefixCache` through `./prefix-cache-data`.
+    let prefix_cache_dir = std::path::PathBuf::from("./prefix-cache-data");
+    std::fs::create_dir_all(&prefix_cache_dir).expect("failed to create prefix cache directory");
+    let restore_dir = prefix_cache_dir.clone();
+    let persist_dir = prefix_cache_dir.clone();
+    let pool: &'static model_pool::ModelPool = Box::leak(Box::new(
+        model_pool::ModelPool::new(model_idle_timeout, move |id| {
+            let model_registry = model_registry.lock().unwrap();
+            let entry = model_registry.resolve(id)?;
+            // Under `strict_model_verification`, a model that hasn't passed
+            // `registry::ModelRegistry::verify` is treated the same as an
+            // unrecognized id: a 404 the same way `model_pool.rs`'s doc
+            // comment describes for `factory`, not a load that silently
+            // serves unverified weights.
+            if strict_model_verification && entry.verification != registry::VerificationStatus::Verified {
+                return None;
+            }
+            // Marks this model as recently used for `storage::gc`'s LRU
+            // ordering, independent of whether `model_pool::ModelPool`
+            // itself later evicts it from memory for being idle.
+            if let Err(e) = storage::mark_used(&entry.path) {
+                println!("failed to record model \"{id}\" as used: {e}");
+            }
+            // `mock.enabled` (see `mock_backend.rs`) takes priority over the
+            // normal backend and over `replay.mode` — `validate()` already
+            // rejects the two being set together, so this can't silently
+            // pick one over an operator's actual intent.
+            if mock_backend_enabled {
+                let mut mock = mock_backend::MockBackend::new(id, mock_default_response.as_deref().unwrap_or("this is a mock response"));
+                if let Some(latency_ms) = mock_latency_ms {
+                    mock = mock.with_latency(std::time::Duration::from_millis(latency_ms as u64));
+                }
+                return Some(Box::new(mock) as Box<dyn InferenceBackend>);
+            }
+            // `replay.mode`/`replay.file` (see `replay_backend.rs`) swap in a
+            // recording or replaying wrapper around the normal backend for
+            // fast, deterministic scheduler/API tests — `validate()` already
+            // rejected a `replay_mode` with no `replay_file`, so `.unwrap()`
+            // here can't be reached with `replay_file` still `None`.
+            match replay_mode.as_deref() {
+                Some("record") => {
+                    let inner: &'static dyn InferenceBackend = Box::leak(Box::new(EchoBackend::new(id)));
+                    match replay_backend::RecordingBackend::open(inner, replay_file.as_ref().unwrap()) {
+                        Ok(recorder) => Some(Box::new(recorder) as Box<dyn InferenceBackend>),
+                        Err(e) => {
+                            println!("failed to open replay record file: {e}");
+                            None
+                        }
+                    }
+                }
+                Some("replay") => match replay_backend::ReplayBackend::open(id, replay_file.as_ref().unwrap()) {
+                    Ok(replay) => Some(Box::new(replay) as Box<dyn InferenceBackend>),
+                    Err(e) => {
+                        println!("failed to open replay file: {e:?}");
+                        None
+                    }
+                },
+                _ => Some(Box::new(EchoBackend::new(id)) as Box<dyn InferenceBackend>),
+            }
+        })
+        .with_prefix_cache_hooks(
+            move |id| prefix_cache::PrefixCache::load(&restore_dir.join(format!("{id}.json"))).unwrap_or_default(),
+            move |id, cache| {
+                if let Err(e) = cache.save(&persist_dir.join(format!("{id}.json"))) {
+                    println!("failed to persist prefix cache for model \"{id}\": {e:?}");
+                }
+            },
+        )
+        .with_warmup(warmup_runs, warmup_prompt, move |id, elapsed, ok| {
+            registry.observe_model_warmup(elapsed.as_secs_f64());
+            if !ok {
+                println!("warmup failed for model \"{id}\" after {elapsed:?}");
+            }
+        })
+        .with_event_hooks(
+            move |id| events.publish(events::ServerEvent::new("model_pool", events::Severity::Info, format!("loaded {id}"))),
+            move |id| events.publish(events::ServerEvent::new("model_pool", events::Severity::Info, format!("evicted {id}"))),
+        ),
+    ));
+    // Picks back up any batch a crash caught mid-run (see
+    // `batches::BatchStore::resumable`'s doc comment) — an empty `model`
+    // id means the batch was submitted against the top-level default
+    // backend rather than a pooled model, the same `None` case
+    // `resolve_backend` handles for a live request.
+    for (id, model_id, requests, resume_from) in batch_store.resumable() {
+        let resolved_backend: StaticBackend = if model_id.is_empty() {
+            StaticBackend::Default(backend)
+        } else {
+            match pool.get_or_load(&model_id) {
+                Some(pooled) => StaticBackend::Pooled(pooled),
+                None => {
+                    println!("failed to resume batch \"{id}\": model \"{model_id}\" is not available");
+                    continue;
+                }
+            }
+        };
+        let process = move |request: &Json| -> Result<Json, String> {
+            let prompt = request.get("prompt").and_then(Json::as_str).ok_or("\"prompt\" must be a string")?;
+            Ok(ObjectBuilder::new().set("text", Json::String(resolved_backend.generate(prompt))).build())
+        };
+        batches::resume(batch_store, id, model_id, requests, resume_from, process);
+    }
+    model_pool::spawn_idle_reaper(pool, std::time::Duration::from_secs(60));
+
+    // No adapter-file loader exists yet, so this starts empty (like
+    // `vector_store` before anything has been indexed) — a future admin
+    // endpoint or CLI subcommand is the natural caller of `register`.
+    let adapters: &'static lora::AdapterRegistry = Box::leak(Box::new(lora::AdapterRegistry::new()));
+    let cancellation: &'static cancellation::CancellationRegistry = Box::leak(Box::new(cancellation::CancellationRegistry::new()));
+    // Read once at startup, like `models_dir` — hot-reloading auth
+    // settings would mean rebuilding this registry's key set and bucket
+    // state in place, more machinery than this feature needs yet.
+    let mut priority_by_key = std::collections::HashMap::new();
+    for key in batch_priority_keys {
+        priority_by_key.insert(key, scheduler::PriorityClass::Batch);
+    }
+    for key in background_priority_keys {
+        priority_by_key.insert(key, scheduler::PriorityClass::Background);
+    }
+    let default_generation_limits = auth::GenerationLimits {
+        max_output_tokens: default_max_output_tokens,
+        timeout: std::time::Duration::from_secs(default_request_timeout_seconds),
+    };
+    let mut generation_limits_by_key = std::collections::HashMap::new();
+    for entry in max_output_tokens_by_key {
+        let (key, tokens) = entry.split_once('=').expect("validate() already rejected malformed auth.max_output_tokens_by_key entries");
+        let limits = generation_limits_by_key.entry(key.to_string()).or_insert(default_generation_limits);
+        limits.max_output_tokens = tokens.parse().expect("validate() already rejected malformed auth.max_output_tokens_by_key entries");
+    }
+    for entry in request_timeout_by_key_seconds {
+        let (key, seconds) = entry.split_once('=').expect("validate() already rejected malformed auth.request_timeout_by_key_seconds entries");
+        let limits = generation_limits_by_key.entry(key.to_string()).or_insert(default_generation_limits);
+        limits.timeout = std::time::Duration::from_secs(seconds.parse().expect("validate() already rejected malformed auth.request_timeout_by_key_seconds entries"));
+    }
+    let auth: &'static auth::AuthRegistry = Box::leak(Box::new(auth::AuthRegistry::with_generation_limits(
+        api_keys,
+        requests_per_minute,
+        daily_token_quota,
+        priority_by_key,
+        default_generation_limits,
+        generation_limits_by_key,
+    )));
+    // `config.validate()` already rejected any entry not shaped like
+    // `key=tenant` or `tenant:model1|model2`, so the splits below always
+    // succeed — same posture as `router_nodes` above.
+    let tenant_by_key = tenant_keys
+        .iter()
+        .map(|entry| {
+            let (key, tenant) = entry.split_once('=').expect("validate() already rejected malformed tenancy.tenant_keys entries");
+            (key.to_string(), tenant.to_string())
+        })
+        .collect();
+    let tenant_allowed_models = tenant_models
+        .iter()
+        .map(|entry| {
+            let (tenant, models) = entry.split_once(':').expect("validate() already rejected malformed tenancy.tenant_models entries");
+            (tenant.to_string(), models.split('|').map(str::to_string).collect())
+        })
+        .collect();
+    let tenants: &'static tenancy::TenantRegistry =
+        Box::leak(Box::new(tenancy::TenantRegistry::new(tenant_by_key, tenant_allowed_models)));
+    // `config.validate()` already rejected any entry not shaped like
+    // `id=mode:factor` with a recognized mode and a positive factor, so the
+    // splits and `RopeScaling::parse` below always succeed — same posture
+    // as `tenant_by_key`/`tenant_allowed_models` above.
+    let rope_scaling_overrides: &'static std::collections::HashMap<String, (gguf::RopeScaling, f64)> =
+        Box::leak(Box::new(
+            rope_scaling_by_model
+                .iter()
+                .map(|entry| {
+                    let (id, scaling) =
+                        entry.split_once('=').expect("validate() already rejected malformed models.rope_scaling_by_model entries");
+                    let (mode, factor) =
+                        scaling.split_once(':').expect("validate() already rejected malformed models.rope_scaling_by_model entries");
+                    let mode = gguf::RopeScaling::parse(mode)
+                        .expect("validate() already rejects an unparseable models.rope_scaling_by_model mode");
+                    let factor: f64 =
+                        factor.parse().expect("validate() already rejects a non-numeric models.rope_scaling_by_model factor");
+                    (id.to_string(), (mode, factor))
+                })
+                .collect(),
+        ));
+    let usage_store: &'static usage::UsageStore =
+        Box::leak(Box::new(usage::UsageStore::open("./usage-data/usage.jsonl").expect("failed to open usage store")));
+    // `validate()` already rejected an enabled `audit.sink` that's neither
+    // "file" nor "syslog" (and a "syslog" sink with no address), so the
+    // match below only needs to decide which constructor to call.
+    let audit_logger: Option<&'static audit::AuditLogger> = if audit_enabled {
+        let logger = match audit_sink.as_str() {
+            "syslog" => audit::AuditLogger::open_syslog(&audit_syslog_addr, audit_include_bodies, audit_redact_patterns),
+            _ => audit::AuditLogger::open_file(&audit_file, audit_include_bodies, audit_redact_patterns),
+        }
+        .expect("failed to open audit sink");
+        Some(Box::leak(Box::new(logger)))
+    } else {
+        None
+    };
+    // `validate()` already rejected an unparseable `guardrails.classifier_action`.
+    let classifier_action = guardrails::Action::parse(&guardrails_classifier_action)
+        .expect("validate() already rejects an unparseable guardrails.classifier_action");
+    let guardrails: &'static guardrails::GuardrailsEngine = Box::leak(Box::new(if guardrails_enabled {
+        let mut rules = Vec::new();
+        rules.extend(guardrails_block_patterns.into_iter().map(|pattern| guardrails::Rule {
+            label: pattern.clone(),
+            pattern,
+            action: guardrails::Action::Block,
+        }));
+        rules.extend(guardrails_redact_patterns.into_iter().map(|pattern| guardrails::Rule {
+            label: pattern.clone(),
+            pattern,
+            action: guardrails::Action::Redact,
+        }));
+        rules.extend(guardrails_annotate_patterns.into_iter().map(|pattern| guardrails::Rule {
+            label: pattern.clone(),
+            pattern,
+            action: guardrails::Action::Annotate,
+        }));
+        let classifier_prompt = (!guardrails_classifier_prompt.is_empty()).then_some(guardrails_classifier_prompt);
+        guardrails::GuardrailsEngine::new(rules, classifier_prompt, classifier_action)
+    } else {
+        guardrails::GuardrailsEngine::disabled()
+    }));
+    let plugins: &'static plugins::PluginRegistry = Box::leak(Box::new(if plugins_enabled {
+        plugins::PluginRegistry::open(&plugins_dir, &plugins_wasmtime_path).expect("failed to open plugins directory")
+    } else {
+        plugins::PluginRegistry::disabled()
+    }));
+    if plugins_enabled {
+        plugins::watch(plugins, std::time::Duration::from_secs(plugins_reload_interval_seconds as u64));
+    }
+    // `config.validate()` already rejected any entry not shaped like
+    // `name=host:port`, so the `split_once('=')` below always succeeds —
+    // same posture as `router_nodes` above.
+    let mcp_servers: Vec<mcp::McpServer> = mcp_client_servers
+        .iter()
+        .map(|entry| {
+            let (name, address) = entry.split_once('=').expect("validate() already rejected malformed mcp.client_servers entries");
+            mcp::McpServer { name: name.to_string(), address: address.to_string() }
+        })
+        .collect();
+    let mcp_clients: &'static mcp::McpClientRegistry = Box::leak(Box::new(if mcp_servers.is_empty() {
+        mcp::McpClientRegistry::disabled()
+    } else {
+        mcp::McpClientRegistry::open(mcp_servers, std::time::Duration::from_millis(mcp_client_timeout_ms as u64))
+    }));
+    if !mcp_client_servers.is_empty() {
+        mcp::watch(mcp_clients, std::time::Duration::from_secs(mcp_client_refresh_interval_seconds as u64));
+    }
+    // Built even when `[agent]` is off, the same "construct it, just
+    // don't reach it" posture `plugins`/`mcp` take toward their own
+    // disabled state — `route()` only ever calls `handle_agent_run` when
+    // `agent_enabled` is true, so an empty-allowlist `AgentTools` here
+    // never actually runs a tool.
+    let agent_tools: &'static agent::AgentTools = Box::leak(Box::new(if agent_enabled {
+        agent::AgentTools::open(&agent_file_root, agent_shell_allowlist, agent_http_allowlist, std::time::Duration::from_secs(10))
+            .expect("failed to open agent file root")
+    } else {
+        agent::AgentTools::disabled()
+    }));
+    // Same "construct it, just don't reach it" posture as `agent_tools`
+    // above — `route()` only ever calls `handle_pipeline_run` when
+    // `pipelines_enabled` is true.
+    let pipelines: &'static pipelines::PipelineRegistry = Box::leak(Box::new(if pipelines_enabled {
+        pipelines::PipelineRegistry::open(&pipelines_dir).expect("failed to open pipelines directory")
+    } else {
+        pipelines::PipelineRegistry::disabled()
+    }));
+    if pipelines_enabled {
+        pipelines::watch(pipelines, std::time::Duration::from_secs(pipelines_reload_interval_seconds as u64));
+    }
+    // Same "construct it, just don't reach it" posture as `pipelines`
+    // above — `route()` only ever calls `handle_job`/`handle_list_jobs`
+    // when `jobs_enabled` is true.
+    let jobs: &'static jobs::JobRegistry = Box::leak(Box::new(if jobs_enabled {
+        jobs::JobRegistry::open(&jobs_dir).expect("failed to open jobs directory")
+    } else {
+        jobs::JobRegistry::disabled()
+    }));
+    if jobs_enabled {
+        jobs::watch(jobs, move || jobs::JobContext {
+            backend,
+            embedding_backend: backend,
+            vector_store,
+            model_registry,
+            pipelines,
+            response_cache,
+        });
+    }
+    // Purely a background sync process — unlike `pipelines`/`jobs` there's
+    // no request handler that reaches into it, so it's only ever built
+    // and (if enabled) started, never threaded through `route()`.
+    let document_watcher: &'static watcher::DocumentWatcher = Box::leak(Box::new(if watcher_enabled {
+        watcher::DocumentWatcher::new(watcher_dir, watcher_collection)
+    } else {
+        watcher::DocumentWatcher::disabled()
+    }));
+    if watcher_enabled {
+        watcher::watch(document_watcher, backend, embedding_cache, vector_store, std::time::Duration::from_secs(watcher_poll_interval_seconds as u64));
+    }
+    // Also purely a background process, like `document_watcher` above —
+    // `discover` (the `cli.rs` subcommand) is the only consumer, and it
+    // reaches this instance over the network rather than through `route()`.
+    if discovery_enabled {
+        let discovery_name = discovery_name.clone().unwrap_or_else(|| "ai-server".to_string());
+        let announce_bind_address = addr.clone();
+        discovery::advertise_periodically(
+            move || {
+                let model_registry = model_registry.lock().unwrap();
+                let mut models = vec![backend.model_id().to_string()];
+                for entry in model_registry.list() {
+                    if !models.contains(&entry.id) {
+                        models.push(entry.id.clone());
+                    }
+                }
+                let mut capabilities = vec!["chat_completions".to_string(), "completions".to_string(), "embeddings".to_string()];
+                if ollama_compat_enabled {
+                    capabilities.push("ollama_compat".to_string());
+                }
+                if agent_enabled {
+                    capabilities.push("agent".to_string());
+                }
+                if mcp_enabled {
+                    capabilities.push("mcp".to_string());
+                }
+                if pipelines_enabled {
+                    capabilities.push("pipelines".to_string());
+                }
+                if jobs_enabled {
+                    capabilities.push("jobs".to_string());
+                }
+                discovery::Announcement {
+                    name: discovery_name.clone(),
+                    host_port: announce_bind_address.clone(),
+                    models,
+                    capabilities,
+                }
+            },
+            discovery::DISCOVERY_PORT,
+            std::time::Duration::from_secs(discovery_interval_seconds as u64),
+        );
+    }
+    // Always constructed, unconditionally reachable via `/admin/templates`
+    // and `/v1/chat/completions`'s `"template"` field — unlike `pipelines`/
+    // `jobs` above there's no enable flag, since an empty registry is
+    // already a harmless no-op (`resolve_template` only fires when a
+    // request supplies `"template"`).
+    let templates: &'static prompt_templates::TemplateRegistry = Box::leak(Box::new(prompt_templates::TemplateRegistry::new()));
+    let parsed_aliases = model_aliases
+        .iter()
+        .map(|entry| {
+            let (alias, spec) = entry.split_once(':').expect("validate() already rejected malformed routing.model_aliases entries");
+            let mut config = model_alias::AliasConfig::default();
+            for token in spec.split('|') {
+                let (key, value) = token.split_once('=').expect("validate() already rejected malformed routing.model_aliases entries");
+                if key == "shadow" {
+                    config.shadow = Some(value.to_string());
+                } else {
+                    let weight: u32 = value.parse().expect("validate() already rejected malformed routing.model_aliases entries");
+                    config.targets.push(model_alias::AliasTarget { model_id: key.to_string(), weight });
+                }
+            }
+            (alias.to_string(), config)
+        })
+        .collect();
+    let aliases: &'static model_alias::AliasRegistry = Box::leak(Box::new(model_alias::AliasRegistry::new(parsed_aliases)));
+    let admin: &'static admin::AdminState = Box::leak(Box::new(admin::AdminState::new(admin_keys)));
+    power::watch(admin, std::time::Duration::from_secs(5));
+    let log_format = if log_pretty { logging::LogFormat::Pretty } else { logging::LogFormat::Json };
+    let logger: &'static logging::JsonLogger =
+        Box::leak(Box::new(logging::JsonLogger::open(&log_file, log_max_bytes as u64, log_format).expect("failed to open log file")));
+
+    shutdown::install();
+    let active_connections: &'static shutdown::ActiveConnections = Box::leak(Box::new(shutdown::ActiveConnections::new()));
+
+    std::thread::spawn(move || serve_rpc(backend, &rpc_addr));
+
+    // A local desktop app embedding this server can talk to it over this
+    // socket instead of the TCP listener below, opening no network port at
+    // all. Runs as its own accept loop on its own thread rather than
+    // folding into the TCP one, the same "separate thread, shared state"
+    // shape `serve_rpc` above already uses for the RPC port.
+    #[cfg(unix)]
+    if let Some(path) = unix_socket_path.clone() {
+        let permissions = u32::from_str_radix(&unix_socket_permissions, 8)
+            .expect("validate() already rejects a non-octal server.unix_socket_permissions");
+        std::thread::spawn(move || {
+            use std::os::unix::fs::PermissionsExt;
+            use std::os::unix::net::UnixListener;
+            // A socket file left behind by a previous, uncleanly-stopped
+            // run would otherwise make `bind` fail with "address in use".
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).expect("failed to bind server.unix_socket_path");
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(permissions))
+                .expect("failed to set server.unix_socket_path permissions");
+            listener.set_nonblocking(true).expect("failed to set unix listener nonblocking");
+            println!("listening on unix://{}", path);
+            while !shutdown::requested() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let guard = active_connections.track();
+                        std::thread::spawn(move || {
+                            serve_one(
+                                backend, pool, adapters, model_registry, backend, embedding_cache, backend, speech_backend, vector_store, registry,
+                                traces, budget, cancellation, auth, admin, logger, models_dir, readiness_check_timeout, session_store,
+                                response_cache, batch_store, max_context_tokens, max_cache_bytes, default_context_policy, router,
+                                tenants, usage_store, audit_logger, guardrails, plugins, mcp_enabled, mcp_clients, agent_enabled,
+                                agent_tools, agent_max_steps, pipelines_enabled, pipelines, jobs_enabled, jobs, ollama_compat_enabled,
+                                templates, aliases, rope_scaling_overrides, idempotency_ttl, events, Transport::Unix(stream),
+                            );
+                            drop(guard);
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                    }
+                    Err(_) => {}
+                }
+            }
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+
+    let listener = TcpListener::bind(&addr).expect("failed to bind server address");
+    // Nonblocking so the accept loop can poll `shutdown::requested()`
+    // between connections instead of sitting inside a blocking `accept()`
+    // call forever once SIGTERM/SIGINT arrives.
+    listener.set_nonblocking(true).expect("failed to set listener nonblocking");
+    println!("listening on http://{addr}");
+    while !shutdown::requested() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let guard = active_connections.track();
+                std::thread::spawn(move || {
+                    serve_one(
+                        backend, pool, adapters, model_registry, backend, embedding_cache, backend, speech_backend, vector_store, registry, traces,
+                        budget, cancellation, auth, admin, logger, models_dir, readiness_check_timeout, session_store, response_cache,
+                        batch_store, max_context_tokens, max_cache_bytes, default_context_policy, router, tenants, usage_store,
+                        audit_logger, guardrails, plugins, mcp_enabled, mcp_clients, agent_enabled, agent_tools, agent_max_steps,
+                        pipelines_enabled, pipelines, jobs_enabled, jobs, ollama_compat_enabled, templates, aliases,
+                        rope_scaling_overrides, idempotency_ttl, events, Transport::Tcp(stream),
+                    );
+                    drop(guard);
+                });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            Err(_) => {}
+        }
+    }
+
+    println!("shutdown signal received, draining in-flight connections");
+    if !shutdown::wait_for_drain(active_connections, shutdown_drain_timeout) {
+        println!("drain timeout elapsed with connections still in flight");
+    }
+    if let Err(e) = vector_store.lock().unwrap().persist_all() {
+        println!("failed to flush vector store on shutdown: {e:?}");
+    }
+    println!("shutdown complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_tenants() -> tenancy::TenantRegistry {
+        tenancy::TenantRegistry::new(Default::default(), Default::default())
+    }
+
+    fn no_aliases() -> model_alias::AliasRegistry {
+        model_alias::AliasRegistry::new(Default::default())
+    }
+
+    fn no_usage() -> usage::UsageStore {
+        let path = std::env::temp_dir()
+            .join(format!("ai-server-usage-test-{:x}.jsonl", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        usage::UsageStore::open(path).unwrap()
+    }
+
+    fn no_guardrails() -> guardrails::GuardrailsEngine {
+        guardrails::GuardrailsEngine::disabled()
+    }
+
+    fn no_plugins() -> plugins::PluginRegistry {
+        plugins::PluginRegistry::disabled()
+    }
+
+    fn no_limits() -> auth::GenerationLimits {
+        auth::GenerationLimits::default()
+    }
+
+    /// A backend whose `generate` never returns within any timeout a test
+    /// would wait for — mirrors `health.rs`'s `HangingBackend`, used the
+    /// same way to exercise [`bounded_generate`]'s timeout path.
+    struct HangingBackend;
+    impl InferenceBackend for HangingBackend {
+        fn model_id(&self) -> &str {
+            "hanging"
+        }
+        fn generate(&self, _prompt: &str) -> String {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            String::new()
+        }
+        fn stream(&self, _prompt: &str, _on_token: &mut dyn FnMut(&str) -> bool) {}
+    }
+
+    fn chat_request(body: &str) -> Request {
+        Request {
+            method: Method::Post,
+            path: "/v1/chat/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn prompt_from_chat_request_picks_last_user_message() {
+        let body = Json::parse(
+            r#"{"messages": [{"role": "system", "content": "sys"}, {"role": "user", "content": "hi"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(prompt_from_chat_request(&body).unwrap(), "hi");
+    }
+
+    #[test]
+    fn prompt_from_chat_request_rejects_empty_messages() {
+        let body = Json::parse(r#"{"messages": []}"#).unwrap();
+        assert!(prompt_from_chat_request(&body).is_err());
+    }
+
+    #[test]
+    fn prompt_from_chat_request_continues_from_a_trailing_tool_message() {
+        let body = Json::parse(
+            r#"{"messages": [
+                {"role": "user", "content": "what's the weather?"},
+                {"role": "assistant", "content": null, "tool_calls": []},
+                {"role": "tool", "content": "72F and sunny"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(prompt_from_chat_request(&body).unwrap(), "72F and sunny");
+    }
+
+    #[test]
+    fn handle_completions_rejects_missing_prompt() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: b"{}".to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("rejects-missing-prompt")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn handle_completions_echoes_prompt_on_success() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hello"}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("echoes-prompt")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("echo: hello"));
+    }
+
+    #[test]
+    fn handle_completions_reports_a_timeout_finish_reason_when_generation_exceeds_the_limit() {
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hello"}"#.to_vec(),
+        };
+        let limits = auth::GenerationLimits { max_output_tokens: 256, timeout: std::time::Duration::from_millis(20) };
+        let response = handle_completions(
+            Box::leak(Box::new(HangingBackend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("timeout-finish-reason")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            limits,
+            &req,
+        );
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains(r#""finish_reason":"timeout""#));
+    }
+
+    #[test]
+    fn handle_completions_reports_a_length_finish_reason_when_the_completion_is_truncated() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "one two three four five"}"#.to_vec(),
+        };
+        let limits = auth::GenerationLimits { max_output_tokens: 2, timeout: std::time::Duration::from_secs(60) };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("length-finish-reason")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            limits,
+            &req,
+        );
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains(r#""finish_reason":"length""#));
+    }
+
+    #[test]
+    fn handle_completions_serves_a_cache_hit_for_a_repeated_deterministic_request() {
+        let backend: &'static dyn InferenceBackend = Box::leak(Box::new(EchoBackend::new("m")));
+        let pool: &'static model_pool::ModelPool = Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None)));
+        let adapters = lora::AdapterRegistry::new();
+        let model_registry = Mutex::new(empty_model_registry("cache-hit"));
+        let registry: &'static metrics::Registry = Box::leak(Box::new(metrics::Registry::new()));
+        let traces = Mutex::new(Vec::new());
+        let budget = resources::MemoryBudget::new(u64::MAX);
+        let cache = response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10);
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hello", "temperature": 0, "seed": 1}"#.to_vec(),
+        };
+
+        let first =
+            handle_completions(backend, pool, &adapters, &model_registry, registry, &traces, &budget, &cache, usize::MAX, context_policy::ContextPolicy::Error, &no_tenants(), None, &no_usage(), None, &no_guardrails(), &no_plugins(), &no_aliases(), no_limits(), &req);
+        assert_eq!(first.headers.iter().find(|(k, _)| k == "X-Cache").map(|(_, v)| v.as_str()), Some("MISS"));
+
+        let second =
+            handle_completions(backend, pool, &adapters, &model_registry, registry, &traces, &budget, &cache, usize::MAX, context_policy::ContextPolicy::Error, &no_tenants(), None, &no_usage(), None, &no_guardrails(), &no_plugins(), &no_aliases(), no_limits(), &req);
+        assert_eq!(second.headers.iter().find(|(k, _)| k == "X-Cache").map(|(_, v)| v.as_str()), Some("HIT"));
+        assert_eq!(first.body, second.body);
+    }
+
+    #[test]
+    fn handle_completions_includes_logprobs_when_requested() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hi", "logprobs": 2}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("includes-logprobs")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"logprobs\""));
+        assert!(body.contains("\"text_offset\""));
+    }
+
+    #[test]
+    fn handle_completions_omits_logprobs_when_not_requested() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hi"}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("omits-logprobs")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(!body.contains("\"logprobs\""));
+    }
+
+    #[test]
+    fn echo_backend_token_logprob_is_deterministic_and_reports_top_n_alternatives() {
+        let backend = EchoBackend::new("m");
+        let a = backend.token_logprob("hello", 2).unwrap();
+        let b = backend.token_logprob("hello", 2).unwrap();
+        assert_eq!(a.logprob, b.logprob);
+        assert_eq!(a.top_logprobs.len(), 2);
+    }
+
+    #[test]
+    fn requested_logprobs_n_reads_the_integer_field() {
+        let body = Json::parse(r#"{"logprobs": 3}"#).unwrap();
+        assert_eq!(requested_logprobs_n(&body), Some(3));
+        assert_eq!(requested_logprobs_n(&Json::parse("{}").unwrap()), None);
+    }
+
+    #[test]
+    fn requested_chat_logprobs_n_requires_a_true_logprobs_field() {
+        let body = Json::parse(r#"{"logprobs": true, "top_logprobs": 4}"#).unwrap();
+        assert_eq!(requested_chat_logprobs_n(&body), Some(4));
+        assert_eq!(requested_chat_logprobs_n(&Json::parse(r#"{"logprobs": false}"#).unwrap()), None);
+        assert_eq!(requested_chat_logprobs_n(&Json::parse("{}").unwrap()), None);
+    }
+
+    #[test]
+    fn requested_chat_logprobs_n_defaults_top_logprobs_to_zero() {
+        let body = Json::parse(r#"{"logprobs": true}"#).unwrap();
+        assert_eq!(requested_chat_logprobs_n(&body), Some(0));
+    }
+
+    #[test]
+    fn completion_logprobs_json_builds_one_entry_per_word() {
+        let backend = EchoBackend::new("m");
+        let json = completion_logprobs_json(&backend, "echo: hi there", 1).unwrap();
+        let tokens = json.get("tokens").and_then(Json::as_array).unwrap();
+        assert_eq!(tokens.len(), 3);
+        let offsets = json.get("text_offset").and_then(Json::as_array).unwrap();
+        assert_eq!(offsets[0], Json::Number(0.0));
+        assert_eq!(offsets[1], Json::Number(6.0));
+    }
+
+    #[test]
+    fn chat_completion_logprobs_json_builds_a_content_entry_per_word() {
+        let backend = EchoBackend::new("m");
+        let json = chat_completion_logprobs_json(&backend, "hi there", 0).unwrap();
+        let content = json.get("content").and_then(Json::as_array).unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[0].get("token").and_then(Json::as_str), Some("hi"));
+    }
+
+    #[test]
+    fn requested_stop_sequences_accepts_a_single_string_or_an_array() {
+        assert_eq!(requested_stop_sequences(&Json::parse(r#"{"stop": "\n"}"#).unwrap()), vec!["\n".to_string()]);
+        assert_eq!(
+            requested_stop_sequences(&Json::parse(r#"{"stop": ["a", "b"]}"#).unwrap()),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(requested_stop_sequences(&Json::parse("{}").unwrap()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn truncate_at_stop_sequence_cuts_before_the_first_match() {
+        let result = truncate_at_stop_sequence("hello world, goodbye", &["goodbye".to_string()]);
+        assert_eq!(result, "hello world, ");
+    }
+
+    #[test]
+    fn truncate_at_stop_sequence_is_a_no_op_without_a_match() {
+        let result = truncate_at_stop_sequence("hello world", &["goodbye".to_string()]);
+        assert_eq!(result, "hello world");
+    }
+
+    #[test]
+    fn handle_completions_truncates_at_a_stop_sequence() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "hello", "stop": ["hello"]}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("truncates-at-stop")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            usize::MAX,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        let body = String::from_utf8(response.body).unwrap();
+        // The completion is "echo: hello"; stopping at "hello" leaves the
+        // "echo: " prefix and drops the matched sequence itself.
+        assert!(body.contains("\"text\":\"echo: \""));
+        assert!(!body.contains("echo: hello"));
+    }
+
+    #[test]
+    fn handle_completions_rejects_an_overflowing_prompt_under_the_error_policy() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "one two three four five"}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("rejects-overflow")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            2,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn handle_completions_truncates_an_overflowing_prompt_under_the_truncate_policy() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "one two three four five"}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("truncates-overflow")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            2,
+            context_policy::ContextPolicy::Truncate,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("echo: four five"));
+        assert!(body.contains("\"context_policy_applied\":\"truncate\""));
+    }
+
+    #[test]
+    fn handle_completions_lets_a_per_request_context_policy_override_the_default() {
+        let backend = EchoBackend::new("m");
+        let req = Request {
+            method: Method::Post,
+            path: "/v1/completions".to_string(),
+            headers: Default::default(), query: Default::default(),
+            body: br#"{"prompt": "one two three four five", "context_policy": "truncate"}"#.to_vec(),
+        };
+        let response = handle_completions(
+            Box::leak(Box::new(backend)),
+            Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |_| None))),
+            &lora::AdapterRegistry::new(),
+            &Mutex::new(empty_model_registry("per-request-policy")),
+            Box::leak(Box::new(metrics::Registry::new())),
+            &Mutex::new(Vec::new()),
+            &resources::MemoryBudget::new(u64::MAX),
+            &response_cache::ResponseCache::new(std::time::Duration::from_secs(60), 10),
+            2,
+            context_policy::ContextPolicy::Error,
+            &no_tenants(),
+            None,
+            &no_usage(),
+            None,
+            &no_guardrails(),
+            &no_plugins(),
+            &no_aliases(),
+            no_limits(),
+            &req,
+        );
+        assert_eq!(response.status, 200);
+    }
+
+    fn aliased_pool() -> &'static model_pool::ModelPool {
+        Box::leak(Box::new(model_pool::ModelPool::new(std::time::Duration::from_secs(60), |id| {
+            (id == "stable" || id == "candidate").then(|| Box::new(EchoBackend::new(id)) as Box<dyn InferenceBackend>)
+        })))
+    }
+
+    fn single_alias(name: &str, target: &str, shadow: Option<&str>) -> model_alias::AliasRegistry {
+        let mut aliases = std::collections::HashMap::new();
+        aliases.insert(
+            name.to_string(),
+            model_alias::AliasConfig {
+                targets: vec![model_alias::AliasTarget { model_id: target.to_string(), weight: 1 }],
+                shadow: shadow.map(str::to_string),
+            },
+        );
+        model_alias::AliasRegistry::new(aliases)
+    }
+
+    #[test]
+    fn resolve_backend_routes_a_request_through_a_configured_alias() {
+        let backend = EchoBackend::new("default");
+        let parsed = Json::parse(r#"{"model": "prod"}"#).unwrap();
+        let (resolved, alias) =
+            resolve_backend(aliased_pool(), &backend, &no_tenants(), None, &single_alias("prod", "stable", None), &parsed).ok().unwrap();
+        assert_eq!(resolved.model_id(), "stable");
+        assert_eq!(alias.as_deref(), Some("prod"));
+    }
+
+    #[test]
+    fn resolve_backend_treats_an_unaliased_model_name_as_a_direct_pool_lookup() {
+        let backend = EchoBackend::new("default");
+        let parsed = Json::parse(r#"{"model": "stable"}"#).unwrap();
+        let (resolved, alias) = resolve_backend(aliased_pool(), &backend, &no_tenants(), None, &no_aliases(), &parsed).ok().unwrap();
+        assert_eq!(resolved.model_id(), "stable");
+        assert_eq!(alias, None);
+    }
+
+    #[test]
+    fn fire_shadow_request_records_a_shadow_metric_on_a_background_thread() {
+        let pool = aliased_pool();
+        let registry: &'static metrics::Registry = Box::leak(Box::new(metrics::Registry::new()));
+        let aliases = single_alias("prod", "stable", Some("candidate"));
+        fire_shadow_request(pool, &aliases, Some("prod"), "hello".to_string(), registry);
+        // The shadow request runs on a detached thread, so this polls
+        // briefly instead of asserting immediately after the call returns.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(1);
+        while std::time::Instant::now() < deadline {
+            if registry.render().contains("ai_server_shadow_requests_total{alias=\"prod\",shadow_model=\"candidate\"} 1") {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("shadow request was never recorded");
+    }
+
+    #[test]
+    fn fire_shadow_request_is_a_no_op_without_a_configured_shadow() {
+        let pool = aliased_pool();
+        let registry: &'static metrics::Registry = Box::leak(Box::new(metrics::Registry::new()));
+        fire_shadow_request(pool, &single_alias("prod", "stable", None), Some("prod"), "hello".to_string(), registry);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(!registry.render().contains("ai_server_shadow_requests_total{"));
+    }
+
+    #[test]
+    fn chat_request_helper_builds_expected_shape() {
+        let req = chat_request(r#"{"messages":[{"role":"user","content":"hi"}]}"#);
+        assert_eq!(req.path, "/v1/chat/completions");
+    }
+
+    #[test]
+    fn prompt_from_chat_request_joins_text_parts_of_array_content() {
+        let body = Json::parse(
+            r#"{"messages": [{"role": "user", "content": [
+                {"type": "text", "text": "what is in"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AA=="}},
+                {"type": "text", "text": "this image?"}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(prompt_from_chat_request(&body).unwrap(), "what is in this image?");
+    }
+
+    #[test]
+    fn images_from_chat_request_rejects_non_data_urls() {
+        let body = Json::parse(
+            r#"{"messages": [{"role": "user", "content": [
+                {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert!(images_from_chat_request(&body).is_err());
+    }
+
+    #[test]
+    fn images_from_chat_request_returns_empty_for_string_content() {
+        let body = Json::parse(r#"{"messages": [{"role": "user", "content": "hi"}]}"#).unwrap();
+        assert_eq!(images_from_chat_request(&body).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn parse_cancel_path_extracts_the_request_id() {
+        assert_eq!(parse_cancel_path("/v1/cancel/chatcmpl-3"), Some("chatcmpl-3"));
+        assert_eq!(parse_cancel_path("/v1/models"), None);
+    }
+
+    #[test]
+    fn handle_cancel_returns_404_for_an_unregistered_request() {
+        let cancellation = cancellation::CancellationRegistry::new();
+        let response = handle_cancel(&cancellation, "chatcmpl-does-not-exist");
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_cancel_flips_a_registered_token() {
+        let cancellation = cancellation::CancellationRegistry::new();
+        let token = cancellation.register("chatcmpl-1");
+        let response = handle_cancel(&cancellation, "chatcmpl-1");
+        assert_eq!(response.status, 200);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn parse_admin_model_path_extracts_model_id_and_action() {
+        assert_eq!(parse_admin_model_path("/admin/models/m/load"), Some(("m", "load")));
+        assert_eq!(parse_admin_model_path("/admin/drain"), None);
+    }
+
+    fn admin_pool() -> model_pool::ModelPool {
+        model_pool::ModelPool::new(std::time::Duration::from_secs(60), |id| {
+            (id == "m").then(|| Box::new(EchoBackend::new(id)) as Box<dyn InferenceBackend>)
+        })
+    }
+
+    #[test]
+    fn handle_admin_model_loads_a_known_model() {
+        let pool = admin_pool();
+        let response = handle_admin_model(&pool, "m", "load");
+        assert_eq!(response.status, 200);
+        assert_eq!(pool.loaded_model_ids(), vec!["m".to_string()]);
+    }
+
+    #[test]
+    fn handle_admin_model_returns_404_for_an_unknown_model() {
+        let pool = admin_pool();
+        let response = handle_admin_model(&pool, "nope", "load");
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_admin_model_unloads_a_loaded_model() {
+        let pool = admin_pool();
+        pool.get_or_load("m").unwrap();
+        let response = handle_admin_model(&pool, "m", "unload");
+        assert_eq!(response.status, 200);
+        assert!(pool.loaded_model_ids().is_empty());
+    }
+
+    #[test]
+    fn handle_admin_model_returns_404_for_an_unrecognized_action() {
+        let pool = admin_pool();
+        let response = handle_admin_model(&pool, "m", "reload");
+        assert_eq!(response.status, 404);
+    }
+
+    fn json_body_request(path: &str, body: &str) -> Request {
+        Request { method: Method::Post, path: path.to_string(), headers: Default::default(), query: Default::default(), body: body.as_bytes().to_vec() }
+    }
+
+    fn get_request(path: &str) -> Request {
+        Request { method: Method::Get, path: path.to_string(), headers: Default::default(), query: Default::default(), body: Vec::new() }
+    }
+
+    #[test]
+    fn handle_admin_drain_sets_the_flag_from_the_request_body() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        let response = handle_admin_drain(&admin, &json_body_request("/admin/drain", r#"{"draining": true}"#));
+        assert_eq!(response.status, 200);
+        assert!(admin.is_draining());
+    }
+
+    #[test]
+    fn handle_admin_drain_rejects_a_non_boolean_field() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        let response = handle_admin_drain(&admin, &json_body_request("/admin/drain", r#"{"draining": "yes"}"#));
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn handle_admin_flush_cache_returns_ok() {
+        let pool = admin_pool();
+        let response = handle_admin_flush_cache(&pool);
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn handle_admin_gc_evicts_down_to_the_configured_budget() {
+        let dir = std::env::temp_dir().join(format!("ai-server-server-test-gc-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("old.gguf"), b"12345").unwrap();
+        let old_time = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::open(dir.join("old.gguf")).unwrap().set_modified(old_time).unwrap();
+        let model_registry = Mutex::new(registry::ModelRegistry::open(&dir).unwrap());
+        let pool = admin_pool();
+
+        let response = handle_admin_gc(&pool, &model_registry, 1);
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"old\""));
+        assert!(model_registry.lock().unwrap().resolve("old").is_none());
+    }
+
+    #[test]
+    fn handle_admin_scheduler_reports_loaded_model_ids() {
+        let pool = admin_pool();
+        pool.get_or_load("m").unwrap();
+        let response = handle_admin_scheduler(&pool);
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"m\""));
+    }
+
+    #[test]
+    fn handle_admin_thermal_reports_availability_regardless_of_platform() {
+        let pool = admin_pool();
+        let response = handle_admin_thermal(&pool);
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"available\""));
+    }
+
+    #[test]
+    fn handle_admin_log_level_sets_the_level_from_the_request_body() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        let response = handle_admin_log_level(&admin, &json_body_request("/admin/log-level", r#"{"level": "debug"}"#));
+        assert_eq!(response.status, 200);
+        assert_eq!(admin.log_level(), admin::LogLevel::Debug);
+    }
+
+    #[test]
+    fn handle_admin_log_level_rejects_an_unknown_level() {
+        let admin = admin::AdminState::new(vec!["k".to_string()]);
+        let response = handle_admin_log_level(&admin, &json_body_request("/admin/log-level", r#"{"level": "verbose"}"#));
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn handle_admin_register_template_then_list_templates_reports_it() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        let body = r#"{"name": "greeting", "messages": [{"role": "user", "content": "Hi {{name}}"}], "variables": ["name"]}"#;
+        let response = handle_admin_register_template(&templates, &json_body_request("/admin/templates", body));
+        assert_eq!(response.status, 200);
+        assert!(String::from_utf8(response.body).unwrap().contains("\"version\":1"));
+
+        let response = handle_admin_list_templates(&templates);
+        assert_eq!(response.status, 200);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("\"greeting\""));
+    }
+
+    #[test]
+    fn handle_admin_register_template_rejects_a_missing_messages_field() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        let response = handle_admin_register_template(&templates, &json_body_request("/admin/templates", r#"{"name": "greeting"}"#));
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn resolve_template_substitutes_variables_into_messages() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        templates.register(
+            "greeting",
+            vec![prompt_templates::TemplateMessage { role: "user".to_string(), content: "Hi {{name}}".to_string() }],
+            vec!["name".to_string()],
+        );
+        let request = Json::parse(r#"{"template": "greeting", "variables": {"name": "Ruben"}}"#).unwrap();
+        let resolved = resolve_template(&request, &templates).ok().unwrap();
+        let messages = resolved.get("messages").and_then(Json::as_array).unwrap();
+        assert_eq!(messages[0].get("content").and_then(Json::as_str), Some("Hi Ruben"));
+    }
+
+    #[test]
+    fn resolve_template_passes_through_a_request_with_no_template_field() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        let request = Json::parse(r#"{"messages": [{"role": "user", "content": "hi"}]}"#).unwrap();
+        assert_eq!(resolve_template(&request, &templates).ok().unwrap(), request);
+    }
+
+    #[test]
+    fn resolve_template_reports_missing_variables_as_a_bad_request() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        templates.register(
+            "greeting",
+            vec![prompt_templates::TemplateMessage { role: "user".to_string(), content: "Hi {{name}}".to_string() }],
+            vec!["name".to_string()],
+        );
+        let request = Json::parse(r#"{"template": "greeting"}"#).unwrap();
+        let response = resolve_template(&request, &templates).unwrap_err();
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn resolve_template_rejects_an_unknown_template_name() {
+        let templates = prompt_templates::TemplateRegistry::new();
+        let request = Json::parse(r#"{"template": "missing"}"#).unwrap();
+        let response = resolve_template(&request, &templates).unwrap_err();
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_healthz_always_returns_ok() {
+        assert_eq!(handle_healthz().status, 200);
+    }
+
+    #[test]
+    fn handle_dashboard_serves_html() {
+        let response = handle_dashboard();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.headers, vec![("Content-Type".to_string(), "text/html".to_string())]);
+        assert!(String::from_utf8(response.body).unwrap().contains("<title>AI-server admin</title>"));
+    }
+
+    fn empty_model_registry(name: &str) -> registry::ModelRegistry {
+        let dir = std::env::temp_dir().join(format!("ai-server-server-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        registry::ModelRegistry::open(&dir).unwrap()
+    }
+
+    #[test]
+    fn handle_readyz_returns_200_once_a_model_is_loaded() {
+        let pool = admin_pool();
+        pool.get_or_load("m").unwrap();
+        let model_registry = Mutex::new(empty_model_registry("readyz-ok"));
+        let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("m")));
+        let response = handle_readyz(&pool, &model_registry, backend, "/", std::time::Duration::from_millis(200));
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn handle_readyz_returns_503_when_no_model_is_loaded_or_registered() {
+        let pool = admin_pool();
+        let model_registry = Mutex::new(empty_model_registry("readyz-not-ready"));
+        let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("m")));
+        let response = handle_readyz(&pool, &model_registry, backend, "/", std::time::Duration::from_millis(200));
+        assert_eq!(response.status, 503);
+    }
+
+    fn empty_session_store(name: &str) -> sessions::SessionStore {
+        let dir = std::env::temp_dir().join(format!("ai-server-server-test-sessions-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        sessions::SessionStore::open(&dir).unwrap()
+    }
+
+    #[test]
+    fn parse_sessions_path_splits_id_and_trailing_segment() {
+        assert_eq!(parse_sessions_path("/v1/sessions/sess-1"), Some(("sess-1", None)));
+        assert_eq!(parse_sessions_path("/v1/sessions/sess-1/messages"), Some(("sess-1", Some("messages"))));
+        assert_eq!(parse_sessions_path("/v1/models"), None);
+    }
+
+    #[test]
+    fn handle_create_session_persists_an_empty_conversation_by_default() {
+        let store = empty_session_store("create-empty");
+        let response = handle_create_session(&store, &json_body_request("/v1/sessions", ""));
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn handle_create_session_seeds_initial_messages() {
+        let store = empty_session_store("create-seeded");
+        let body = r#"{"messages":[{"role":"user","content":"hi"}]}"#;
+        let response = handle_create_session(&store, &json_body_request("/v1/sessions", body));
+        let parsed = Json::parse(&String::from_utf8(response.body).unwrap()).unwrap();
+        assert_eq!(parsed.get("messages").and_then(Json::as_array).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn handle_session_get_returns_a_previously_created_conversation() {
+        let store = empty_session_store("get-existing");
+        let id = store.create(&sessions::Session::default()).unwrap();
+        let req = get_request(&format!("/v1/sessions/{id}"));
+        let response = handle_session(&store, &EchoBackend::new("m"), &id, None, &req);
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn handle_session_get_returns_404_for_an_unknown_id() {
+        let store = empty_session_store("get-missing");
+        let req = get_request("/v1/sessions/sess-missing");
+        let response = handle_session(&store, &EchoBackend::new("m"), "sess-missing", None, &req);
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_session_messages_appends_to_the_conversation() {
+        let store = empty_session_store("append");
+        let id = store.create(&sessions::Session::default()).unwrap();
+        let req = json_body_request(&format!("/v1/sessions/{id}/messages"), r#"{"role":"user","content":"hi"}"#);
+        let response = handle_session(&store, &EchoBackend::new("m"), &id, Some("messages"), &req);
+        assert_eq!(response.status, 200);
+        assert_eq!(store.load(&id).unwrap().messages.len(), 1);
+    }
+
+    #[test]
+    fn handle_session_delete_removes_the_conversation() {
+        let store = empty_session_store("delete");
+        let id = store.create(&sessions::Session::default()).unwrap();
+        let req = json_body_request(&format!("/v1/sessions/{id}/delete"), "");
+        let response = handle_session(&store, &EchoBackend::new("m"), &id, Some("delete"), &req);
+        assert_eq!(response.status, 200);
+        assert!(store.load(&id).is_err());
+    }
+
+    #[test]
+    fn handle_session_messages_auto_compacts_once_the_session_crosses_its_threshold() {
+        let store = empty_session_store("auto-compact");
+        let session = sessions::Session {
+            messages: vec![sessions::Message { role: "user".to_string(), content: "word ".repeat(50) }],
+            memory_enabled: true,
+            memory_compact_above_tokens: 10,
+            memory_keep_recent_turns: 1,
+            ..sessions::Session::default()
+        };
+        let id = store.create(&session).unwrap();
+        let req = json_body_request(&format!("/v1/sessions/{id}/messages"), r#"{"role":"user","content":"one more"}"#);
+        let response = handle_session(&store, &EchoBackend::new("m"), &id, Some("messages"), &req);
+        assert_eq!(response.status, 200);
+        let reloaded = store.load(&id).unwrap();
+        assert!(reloaded.summary.is_some());
+        assert_eq!(reloaded.messages.len(), 1);
+    }
+
+    #[test]
+    fn handle_session_prompt_injects_the_summary_ahead_of_kept_messages() {
+        let store = empty_session_store("prompt");
+        let session = sessions::Session {
+            summary: Some("earlier recap".to_string()),
+            messages: vec![sessions::Message { role: "user".to_string(), content: "hi".to_string() }],
+            ..sessions::Session::default()
+        };
+        let id = store.create(&session).unwrap();
+        let req = get_request(&format!("/v1/sessions/{id}/prompt"));
+        let response = handle_session(&store, &EchoBackend::new("m"), &id, Some("prompt"), &req);
+        assert_eq!(response.status, 200);
+        let parsed = Json::parse(&String::from_utf8(response.body).unwrap()).unwrap();
+        let messages = parsed.get("messages").and_then(Json::as_array).unwrap();
+        assert_eq!(messages[0].get("role").and_then(Json::as_str), Some("system"));
+        assert!(messages[0].get("content").and_then(Json::as_str).unwrap().contains("earlier recap"));
+    }
+
+    fn empty_batch_store(name: &str) -> batches::BatchStore {
+        let dir = std::env::temp_dir().join(format!("ai-server-server-test-batches-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        batches::BatchStore::open(&dir).unwrap()
+    }
+
+    fn leaked_batch_store(name: &str) -> &'static batches::BatchStore {
+        Box::leak(Box::new(empty_batch_store(name)))
+    }
+
+    fn wait_for_batch_completion(store: &batches::BatchStore, id: &str) -> batches::BatchProgress {
+        for _ in 0..200 {
+            if let Some(progress) = store.progress(&id.to_string()) {
+                if progress.status == batches::BatchStatus::Completed || progress.status == batches::BatchStatus::Failed {
+                    return progress;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("batch did not finish in time");
+    }
+
+    #[test]
+    fn parse_batches_path_splits_id_and_trailing_segment() {
+        assert_eq!(parse_batches_path("/v1/batches/batch-1"), Some(("batch-1", None)));
+        assert_eq!(parse_batches_path("/v1/batches/batch-1/results"), Some(("batch-1", Some("results"))));
+        assert_eq!(parse_batches_path("/v1/models"), None);
+    }
+
+    #[test]
+    fn handle_create_batch_runs_every_request_against_the_default_backend() {
+        let store = leaked_batch_store("create-default");
+        let pool = admin_pool();
+        let adapters = lora::AdapterRegistry::new();
+        let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("echo-0")));
+        let body = r#"{"requests":[{"prompt":"a"},{"prompt":"b"}]}"#;
+        let response = handle_create_batch(backend, &pool, &adapters, store, &no_tenants(), None, &no_aliases(), &json_body_request("/v1/batches", body));
+        assert_eq!(response.status, 200);
+        let parsed = Json::parse(&String::from_utf8(response.body).unwrap()).unwrap();
+        let id = parsed.get("id").and_then(Json::as_str).unwrap().to_string();
+
+        let progress = wait_for_batch_completion(store, &id);
+        assert_eq!(progress, batches::BatchProgress { status: batches::BatchStatus::Completed, total: 2, completed: 2, failed: 0 });
+    }
+
+    #[test]
+    fn handle_create_batch_returns_404_for_an_unknown_model() {
+        let store = leaked_batch_store("create-unknown-model");
+        let pool = admin_pool();
+        let adapters = lora::AdapterRegistry::new();
+        let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("echo-0")));
+        let body = r#"{"model":"nope","requests":[{"prompt":"a"}]}"#;
+        let response = handle_create_batch(backend, &pool, &adapters, store, &no_tenants(), None, &no_aliases(), &json_body_request("/v1/batches", body));
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_create_batch_rejects_a_missing_requests_field() {
+        let store = leaked_batch_store("create-missing-requests");
+        let pool = admin_pool();
+        let adapters = lora::AdapterRegistry::new();
+        let backend: &'static EchoBackend = Box::leak(Box::new(EchoBackend::new("echo-0")));
+        let response = handle_create_batch(backend, &pool, &adapters, store, &no_tenants(), None, &no_aliases(), &json_body_request("/v1/batches", "{}"));
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn handle_batch_get_returns_progress_for_a_known_id() {
+        let store = leaked_batch_store("status-existing");
+        let id = batches::submit(store, "echo-0", vec![Json::parse(r#"{"prompt":"a"}"#).unwrap()], |_| Ok(Json::Null)).unwrap();
+        let _ = wait_for_batch_completion(store, &id);
+        let req = get_request(&format!("/v1/batches/{id}"));
+        let response = handle_batch(store, &id, None, &req);
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn handle_batch_get_returns_404_for_an_unknown_id() {
+        let store = empty_batch_store("status-missing");
+        let req = get_request("/v1/batches/batch-missing");
+        let response = handle_batch(&store, "batch-missing", None, &req);
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn handle_batch_results_returns_the_output_file_once_complete() {
+        let store = leaked_batch_store("results");
+        let id = batches::submit(store, "echo-0", vec![Json::parse(r#"{"prompt":"a"}"#).unwrap()], |_| Ok(Json::String("done".to_string()))).unwrap();
+        let _ = wait_for_batch_completion(store, &id);
+        let req = get_request(&format!("/v1/batches/{id}/results"));
+        let response = handle_batch(store, &id, Some("results"), &req);
+        assert_eq!(response.status, 200);
+        assert!(String::from_utf8(response.body).unwrap().contains("done"));
+    }
+}
@@ -0,0 +1,273 @@
+//! Generates and (un)registers the OS unit that keeps `ai-server serve`
+//! running across reboots — a launchd daemon plist on macOS, a systemd
+//! unit on Linux — so an operator doesn't have to hand-write one. Backs
+//! `cli.rs`'s `service install|uninstall|status` subcommand.
+//!
+//! Unit *rendering* ([`render_launchd_plist`]/[`render_systemd_unit`]) is
+//! plain string formatting with no platform dependency, so it's testable
+//! on any host; actually writing the file to its OS-specific install path
+//! and asking the service manager to pick it up ([`install`]) does depend
+//! on which OS this binary is running on, matching the split
+//! `thermal.rs`/`power.rs` already use between pure computation and
+//! `#[cfg(target_os = ...)]`-gated syscalls/subprocess calls.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServicePlatform {
+    Launchd,
+    Systemd,
+}
+
+impl ServicePlatform {
+    /// The platform `install`/`uninstall` act on by default — the actual
+    /// host OS, so `service install` with no flags does the obviously
+    /// correct thing. `cli.rs` still accepts `--platform` to override this,
+    /// e.g. rendering a systemd unit on a macOS dev box to hand to a Linux
+    /// deployment.
+    #[cfg(target_os = "macos")]
+    pub fn host() -> Option<ServicePlatform> {
+        Some(ServicePlatform::Launchd)
+    }
+    #[cfg(target_os = "linux")]
+    pub fn host() -> Option<ServicePlatform> {
+        Some(ServicePlatform::Systemd)
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    pub fn host() -> Option<ServicePlatform> {
+        None
+    }
+
+    pub fn parse(s: &str) -> Option<ServicePlatform> {
+        match s {
+            "launchd" => Some(ServicePlatform::Launchd),
+            "systemd" => Some(ServicePlatform::Systemd),
+            _ => None,
+        }
+    }
+}
+
+/// Everything a rendered unit needs, gathered by `cli.rs` from the active
+/// config (`config::ServerConfig`) and the running process rather than
+/// asked for as one big flag list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceSpec {
+    /// Reverse-DNS-style identifier used as the launchd `Label` and the
+    /// systemd unit's file stem, e.g. `com.ai-server` / `ai-server`.
+    pub label: String,
+    pub binary_path: PathBuf,
+    pub config_path: PathBuf,
+    pub working_dir: PathBuf,
+    pub user: String,
+    /// Where the service manager redirects the process's stdout/stderr —
+    /// `config::ServerConfig::log_file`'s directory, since
+    /// `logging::JsonLogger` already writes structured request logs there
+    /// and anything the process itself prints (startup errors before the
+    /// logger is even up) belongs alongside it.
+    pub log_file: PathBuf,
+}
+
+/// The install path a given platform's service manager expects a unit at.
+/// Both are root-owned system-wide locations (`LaunchDaemons`, not
+/// `LaunchAgents`; `/etc/systemd/system`, not a user's `~/.config`) since
+/// "survive a reboot with no one logged in" is exactly what this exists
+/// for.
+pub fn install_path(platform: ServicePlatform, label: &str) -> PathBuf {
+    match platform {
+        ServicePlatform::Launchd => PathBuf::from(format!("/Library/LaunchDaemons/{label}.plist")),
+        ServicePlatform::Systemd => PathBuf::from(format!("/etc/systemd/system/{label}.service")),
+    }
+}
+
+pub fn render_launchd_plist(spec: &ServiceSpec) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \t<key>Label</key>\n\
+         \t<string>{label}</string>\n\
+         \t<key>ProgramArguments</key>\n\
+         \t<array>\n\
+         \t\t<string>{binary}</string>\n\
+         \t\t<string>serve</string>\n\
+         \t</array>\n\
+         \t<key>UserName</key>\n\
+         \t<string>{user}</string>\n\
+         \t<key>WorkingDirectory</key>\n\
+         \t<string>{working_dir}</string>\n\
+         \t<key>EnvironmentVariables</key>\n\
+         \t<dict>\n\
+         \t\t<key>AI_SERVER_CONFIG</key>\n\
+         \t\t<string>{config_path}</string>\n\
+         \t</dict>\n\
+         \t<key>StandardOutPath</key>\n\
+         \t<string>{log_file}</string>\n\
+         \t<key>StandardErrorPath</key>\n\
+         \t<string>{log_file}</string>\n\
+         \t<key>RunAtLoad</key>\n\
+         \t<true/>\n\
+         \t<key>KeepAlive</key>\n\
+         \t<true/>\n\
+         </dict>\n\
+         </plist>\n",
+        label = spec.label,
+        binary = spec.binary_path.display(),
+        user = spec.user,
+        working_dir = spec.working_dir.display(),
+        config_path = spec.config_path.display(),
+        log_file = spec.log_file.display(),
+    )
+}
+
+pub fn render_systemd_unit(spec: &ServiceSpec) -> String {
+    format!(
+        "[Unit]\n\
+         Description=ai-server inference API\n\
+         After=network.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={binary} serve\n\
+         WorkingDirectory={working_dir}\n\
+         User={user}\n\
+         Environment=AI_SERVER_CONFIG={config_path}\n\
+         StandardOutput=append:{log_file}\n\
+         StandardError=append:{log_file}\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        binary = spec.binary_path.display(),
+        working_dir = spec.working_dir.display(),
+        user = spec.user,
+        config_path = spec.config_path.display(),
+        log_file = spec.log_file.display(),
+    )
+}
+
+fn render(platform: ServicePlatform, spec: &ServiceSpec) -> String {
+    match platform {
+        ServicePlatform::Launchd => render_launchd_plist(spec),
+        ServicePlatform::Systemd => render_systemd_unit(spec),
+    }
+}
+
+/// Writes the rendered unit to its platform install path and asks the
+/// service manager to pick it up (`launchctl load -w` / `systemctl
+/// daemon-reload` + `enable --now`), returning the path written. Registration
+/// failing after the file's already written (e.g. `launchctl`/`systemctl`
+/// missing from `PATH`) is reported back rather than rolled back — the unit
+/// file is still valid and an operator can register it by hand from the
+/// returned path.
+pub fn install(platform: ServicePlatform, spec: &ServiceSpec) -> Result<PathBuf, String> {
+    let path = install_path(platform, &spec.label);
+    std::fs::write(&path, render(platform, spec)).map_err(|e| format!("writing {}: {e}", path.display()))?;
+
+    let register = match platform {
+        ServicePlatform::Launchd => Command::new("launchctl").args(["load", "-w"]).arg(&path).status(),
+        ServicePlatform::Systemd => Command::new("systemctl").arg("daemon-reload").status().and_then(|_| {
+            Command::new("systemctl").args(["enable", "--now"]).arg(&spec.label).status()
+        }),
+    };
+    match register {
+        Ok(status) if status.success() => Ok(path),
+        Ok(status) => Err(format!("wrote {} but registering it exited with {status}", path.display())),
+        Err(e) => Err(format!("wrote {} but registering it failed: {e}", path.display())),
+    }
+}
+
+/// Stops and unregisters the service (best-effort — a service that was
+/// never registered, or whose manager binary is missing, doesn't stop
+/// removal of the unit file), then deletes the unit file itself.
+pub fn uninstall(platform: ServicePlatform, label: &str) -> Result<(), String> {
+    let path = install_path(platform, label);
+    match platform {
+        ServicePlatform::Launchd => {
+            let _ = Command::new("launchctl").args(["unload", "-w"]).arg(&path).status();
+        }
+        ServicePlatform::Systemd => {
+            let _ = Command::new("systemctl").args(["disable", "--now"]).arg(label).status();
+        }
+    }
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("removing {}: {e}", path.display())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceStatus {
+    Installed { unit_path: PathBuf },
+    NotInstalled,
+}
+
+/// Whether a unit file exists at `label`'s install path. Doesn't shell out
+/// to `launchctl list`/`systemctl is-active` — a unit file with no running
+/// process (e.g. after a crash `KeepAlive`/`Restart` hasn't caught up to
+/// yet) still counts as "installed" for this check, since "is it
+/// registered to survive a reboot" is the question `service status`
+/// answers, not "is it up right now" (`/healthz` already answers that).
+pub fn status(platform: ServicePlatform, label: &str) -> ServiceStatus {
+    let unit_path = install_path(platform, label);
+    if unit_path.exists() {
+        ServiceStatus::Installed { unit_path }
+    } else {
+        ServiceStatus::NotInstalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            label: "ai-server".to_string(),
+            binary_path: PathBuf::from("/usr/local/bin/ai-server"),
+            config_path: PathBuf::from("/etc/ai-server/ai-server.toml"),
+            working_dir: PathBuf::from("/etc/ai-server"),
+            user: "ai-server".to_string(),
+            log_file: PathBuf::from("/var/log/ai-server.log"),
+        }
+    }
+
+    #[test]
+    fn launchd_plist_includes_the_binary_config_and_user() {
+        let plist = render_launchd_plist(&spec());
+        assert!(plist.contains("<string>/usr/local/bin/ai-server</string>"));
+        assert!(plist.contains("<string>ai-server</string>"));
+        assert!(plist.contains("AI_SERVER_CONFIG"));
+        assert!(plist.contains("/etc/ai-server/ai-server.toml"));
+        assert!(plist.contains("<true/>"));
+    }
+
+    #[test]
+    fn systemd_unit_includes_the_binary_config_and_user() {
+        let unit = render_systemd_unit(&spec());
+        assert!(unit.contains("ExecStart=/usr/local/bin/ai-server serve"));
+        assert!(unit.contains("User=ai-server"));
+        assert!(unit.contains("Environment=AI_SERVER_CONFIG=/etc/ai-server/ai-server.toml"));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+    }
+
+    #[test]
+    fn install_path_differs_by_platform() {
+        assert_eq!(install_path(ServicePlatform::Launchd, "ai-server"), PathBuf::from("/Library/LaunchDaemons/ai-server.plist"));
+        assert_eq!(install_path(ServicePlatform::Systemd, "ai-server"), PathBuf::from("/etc/systemd/system/ai-server.service"));
+    }
+
+    #[test]
+    fn parse_accepts_both_platform_names_and_rejects_others() {
+        assert_eq!(ServicePlatform::parse("launchd"), Some(ServicePlatform::Launchd));
+        assert_eq!(ServicePlatform::parse("systemd"), Some(ServicePlatform::Systemd));
+        assert_eq!(ServicePlatform::parse("windows"), None);
+    }
+
+    #[test]
+    fn status_reports_not_installed_when_no_unit_file_exists() {
+        let status = status(ServicePlatform::Systemd, "no-such-ai-server-unit-in-tests");
+        assert_eq!(status, ServiceStatus::NotInstalled);
+    }
+}
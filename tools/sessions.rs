@@ -0,0 +1,262 @@
+//! Conversation persistence: assigns a session id to a chat/completion
+//! conversation, saves its message history plus the token prefix
+//! `prefix_cache.rs` had cached for it, and reloads that on request so a
+//! long agent session interrupted by a laptop sleep or a server restart
+//! can resume without recomputing the full prefill from scratch. Disk
+//! layout mirrors `registry.rs`'s catalog and `vectorstore.rs`'s
+//! collections: one JSON file per session under a root directory.
+
+use crate::durability;
+use crate::json::{Json, ObjectBuilder};
+use crate::prefix_cache::SequenceId;
+use crate::sha1::sha1;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type SessionId = String;
+
+/// Process-local counter behind [`new_session_id`] — combined with a
+/// timestamp so ids stay unique across restarts too, unlike
+/// `server.rs`'s request-id counters, which only need to be unique within
+/// one process's lifetime.
+static SESSION_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a fresh session id. Distinct from `server.rs`'s
+/// `next_request_id`, which names one completion, not the conversation it
+/// belongs to.
+pub fn new_session_id() -> SessionId {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let n = SESSION_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("sess-{nanos:x}-{n}")
+}
+
+/// Derives a stable [`SequenceId`] from a session id, so a session's
+/// history can be looked up in the per-model `prefix_cache::PrefixCache`
+/// that `model_pool.rs`'s eviction hooks persist — `PrefixCache` keys on a
+/// `u64`, and a session id is a string, so this bridges the two the same
+/// way `auth.rs` hashes an API key before using it as a map key.
+pub fn sequence_id_for(session_id: &SessionId) -> SequenceId {
+    let digest = sha1(session_id.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+/// One conversation's persisted state: its messages, and the token prefix
+/// it had cached the last time it ran, so resuming can hand that straight
+/// to `prefix_cache::PrefixCache::record` instead of starting cold.
+///
+/// `summary` and the `memory_*` fields belong to `memory.rs`'s compaction
+/// pass — they live here rather than in `memory.rs` itself because they're
+/// part of a session's persisted state, the same way `cached_prefix_tokens`
+/// is owned conceptually by `prefix_cache.rs` but stored inline here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub messages: Vec<Message>,
+    pub cached_prefix_tokens: Option<Vec<u32>>,
+    /// The running summary of everything `memory::compact` has folded away
+    /// so far. `None` until the first compaction runs.
+    pub summary: Option<String>,
+    /// Whether `memory::compact` is allowed to run on this session at all —
+    /// off by default so a session doesn't silently start losing verbatim
+    /// history a caller didn't ask to have summarized.
+    pub memory_enabled: bool,
+    /// Compact once this session's messages cross this many words (the
+    /// same whitespace-count "tokens" proxy `context_policy.rs` uses).
+    pub memory_compact_above_tokens: usize,
+    /// How many of the most recent messages `memory::compact` leaves
+    /// verbatim after folding the rest into `summary`.
+    pub memory_keep_recent_turns: usize,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            messages: Vec::new(),
+            cached_prefix_tokens: None,
+            summary: None,
+            memory_enabled: false,
+            memory_compact_above_tokens: 4000,
+            memory_keep_recent_turns: 6,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(String),
+    NotFound(SessionId),
+}
+
+/// Disk-backed store of sessions under a single root directory, laid out
+/// as `<root>/<id>.json`, one file per session.
+pub struct SessionStore {
+    root: PathBuf,
+}
+
+impl SessionStore {
+    pub fn open(root: impl Into<PathBuf>) -> std::io::Result<SessionStore> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        durability::recover_dir(&root)?;
+        Ok(SessionStore { root })
+    }
+
+    fn path_for(&self, id: &SessionId) -> PathBuf {
+        self.root.join(format!("{id}.json"))
+    }
+
+    pub fn create(&self, session: &Session) -> Result<SessionId, SessionError> {
+        let id = new_session_id();
+        self.save(&id, session)?;
+        Ok(id)
+    }
+
+    pub fn save(&self, id: &SessionId, session: &Session) -> Result<(), SessionError> {
+        let messages: Vec<Json> = session
+            .messages
+            .iter()
+            .map(|m| ObjectBuilder::new().set("role", Json::String(m.role.clone())).set("content", Json::String(m.content.clone())).build())
+            .collect();
+        let mut body = ObjectBuilder::new()
+            .set("messages", Json::Array(messages))
+            .set("memory_enabled", Json::Bool(session.memory_enabled))
+            .set("memory_compact_above_tokens", Json::Number(session.memory_compact_above_tokens as f64))
+            .set("memory_keep_recent_turns", Json::Number(session.memory_keep_recent_turns as f64));
+        if let Some(tokens) = &session.cached_prefix_tokens {
+            body = body.set("cached_prefix_tokens", Json::Array(tokens.iter().map(|&t| Json::Number(t as f64)).collect()));
+        }
+        if let Some(summary) = &session.summary {
+            body = body.set("summary", Json::String(summary.clone()));
+        }
+        durability::atomic_write(&self.path_for(id), body.build().to_string().as_bytes()).map_err(|e| SessionError::Io(e.to_string()))
+    }
+
+    pub fn load(&self, id: &SessionId) -> Result<Session, SessionError> {
+        let path = self.path_for(id);
+        if !Path::new(&path).exists() {
+            return Err(SessionError::NotFound(id.clone()));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| SessionError::Io(e.to_string()))?;
+        let parsed = Json::parse(&text).map_err(|e| SessionError::Io(e.to_string()))?;
+
+        let messages = parsed
+            .get("messages")
+            .and_then(Json::as_array)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|m| {
+                        let role = m.get("role").and_then(Json::as_str)?;
+                        let content = m.get("content").and_then(Json::as_str)?;
+                        Some(Message { role: role.to_string(), content: content.to_string() })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let cached_prefix_tokens = parsed
+            .get("cached_prefix_tokens")
+            .and_then(Json::as_array)
+            .map(|tokens| tokens.iter().filter_map(Json::as_f64).map(|t| t as u32).collect());
+        let summary = parsed.get("summary").and_then(Json::as_str).map(str::to_string);
+        let defaults = Session::default();
+        let memory_enabled = parsed.get("memory_enabled").and_then(Json::as_bool).unwrap_or(defaults.memory_enabled);
+        let memory_compact_above_tokens =
+            parsed.get("memory_compact_above_tokens").and_then(Json::as_f64).map(|n| n as usize).unwrap_or(defaults.memory_compact_above_tokens);
+        let memory_keep_recent_turns =
+            parsed.get("memory_keep_recent_turns").and_then(Json::as_f64).map(|n| n as usize).unwrap_or(defaults.memory_keep_recent_turns);
+
+        Ok(Session { messages, cached_prefix_tokens, summary, memory_enabled, memory_compact_above_tokens, memory_keep_recent_turns })
+    }
+
+    pub fn append(&self, id: &SessionId, message: Message) -> Result<Session, SessionError> {
+        let mut session = self.load(id)?;
+        session.messages.push(message);
+        self.save(id, &session)?;
+        Ok(session)
+    }
+
+    pub fn delete(&self, id: &SessionId) -> Result<(), SessionError> {
+        match fs::remove_file(self.path_for(id)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(SessionError::NotFound(id.clone())),
+            Err(e) => Err(SessionError::Io(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> SessionStore {
+        let dir = std::env::temp_dir().join(format!("ai-server-sessions-test-{:x}", sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        SessionStore::open(dir).unwrap()
+    }
+
+    #[test]
+    fn create_then_load_round_trips_messages_and_cached_tokens() {
+        let store = temp_store();
+        let session =
+            Session { messages: vec![Message { role: "user".to_string(), content: "hi".to_string() }], cached_prefix_tokens: Some(vec![1, 2, 3]), ..Session::default() };
+        let id = store.create(&session).unwrap();
+        let reloaded = store.load(&id).unwrap();
+        assert_eq!(reloaded, session);
+    }
+
+    #[test]
+    fn create_then_load_round_trips_the_summary_and_memory_settings() {
+        let store = temp_store();
+        let session = Session {
+            summary: Some("earlier turns discussed X".to_string()),
+            memory_enabled: true,
+            memory_compact_above_tokens: 500,
+            memory_keep_recent_turns: 2,
+            ..Session::default()
+        };
+        let id = store.create(&session).unwrap();
+        assert_eq!(store.load(&id).unwrap(), session);
+    }
+
+    #[test]
+    fn load_of_an_unknown_id_returns_not_found() {
+        let store = temp_store();
+        let err = store.load(&"sess-does-not-exist".to_string()).unwrap_err();
+        assert!(matches!(err, SessionError::NotFound(_)));
+    }
+
+    #[test]
+    fn append_adds_a_message_and_persists_it() {
+        let store = temp_store();
+        let id = store.create(&Session::default()).unwrap();
+        store.append(&id, Message { role: "user".to_string(), content: "one".to_string() }).unwrap();
+        let session = store.append(&id, Message { role: "assistant".to_string(), content: "two".to_string() }).unwrap();
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(store.load(&id).unwrap().messages.len(), 2);
+    }
+
+    #[test]
+    fn delete_removes_the_session_file() {
+        let store = temp_store();
+        let id = store.create(&Session::default()).unwrap();
+        store.delete(&id).unwrap();
+        assert!(matches!(store.load(&id).unwrap_err(), SessionError::NotFound(_)));
+    }
+
+    #[test]
+    fn new_session_id_generates_distinct_ids() {
+        assert_ne!(new_session_id(), new_session_id());
+    }
+
+    #[test]
+    fn sequence_id_for_is_stable_for_the_same_session_id() {
+        let id = "sess-abc".to_string();
+        assert_eq!(sequence_id_for(&id), sequence_id_for(&id));
+    }
+}
@@ -0,0 +1,240 @@
+//! Parallel, bounded-concurrency loading of a checkpoint's on-disk shards,
+//! once `safetensors::SafetensorsModel::open_sharded` has already
+//! discovered which files a multi-file checkpoint spans (via its
+//! `*.index.json`'s `weight_map` — see [`safetensors::SafetensorsModel::shard_paths`]).
+//! Sequential shard loading is what makes a 70B model's startup take many
+//! minutes: `mmap_loader::open_weights` already makes a single file's load
+//! fast, but a checkpoint split across dozens of shards still pays that
+//! cost once per shard, one after another. This spreads those
+//! `open_weights` calls across `concurrency` worker threads the same way
+//! `loadtest.rs` spreads synthetic requests across workers, except workers
+//! pull the next unclaimed shard from a shared counter instead of each
+//! being handed a fixed slice up front — shards vary enough in size that a
+//! fixed split can leave one worker still loading a large shard long after
+//! the others have gone idle.
+//!
+//! GGUF's own multi-file convention (`<name>-00001-of-00005.gguf`,
+//! `llama.cpp`'s `split.count`/`split.no` metadata keys) isn't discovered
+//! here — `gguf.rs`'s doc comment already scopes that module to
+//! single-file parsing, and teaching it to merge tensor tables across
+//! sibling files is its own follow-up. This module's input is a plain
+//! `&[PathBuf]`, so that follow-up only needs to supply the shard list, not
+//! change anything here.
+
+use crate::mmap_loader::{self, LoadError, LoadOptions, WeightSource};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Loads every shard in `paths`, spread across `concurrency.max(1)` worker
+/// threads, each pulling the next unclaimed shard from a shared counter
+/// until none remain. `on_progress(shards_completed, total_shards)` is
+/// called once per finished shard, the same "so far, total" shape
+/// `mmap_loader::open_weights`'s own `on_progress` uses for bytes.
+///
+/// Returns shard bytes in the same order as `paths`, regardless of which
+/// order they actually finished loading in. If any shard fails to load,
+/// returns the first such failure in `paths` order; shards already in
+/// flight on other threads still finish before this function returns, but
+/// their results are discarded.
+pub fn load_shards(
+    paths: &[PathBuf],
+    options: LoadOptions,
+    concurrency: usize,
+    on_progress: &(dyn Fn(usize, usize) + Sync),
+) -> Result<Vec<WeightSource>, LoadError> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let total = paths.len();
+    let concurrency = concurrency.clamp(1, total);
+    let next_index = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<Result<WeightSource, LoadError>>>> = (0..total).map(|_| Mutex::new(None)).collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency {
+            let next_index = &next_index;
+            let completed = &completed;
+            let slots = &slots;
+            scope.spawn(move || loop {
+                let i = next_index.fetch_add(1, Ordering::SeqCst);
+                if i >= total {
+                    break;
+                }
+                let outcome = mmap_loader::open_weights(&paths[i], options, |_, _| {});
+                *slots[i].lock().unwrap() = Some(outcome);
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                on_progress(done, total);
+            });
+        }
+    });
+
+    slots
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().expect("every index below `total` is claimed exactly once above"))
+        .collect()
+}
+
+/// Maps each of `paths`' loaded shards to its byte length, for
+/// `safetensors::SafetensorsModel::validate_loaded_shards` to cross-check
+/// the assembled tensor set against.
+pub fn shard_lengths(paths: &[PathBuf], loaded: &[WeightSource]) -> BTreeMap<PathBuf, u64> {
+    paths.iter().cloned().zip(loaded.iter().map(|source| source.len() as u64)).collect()
+}
+
+/// Convenience wrapper for the common case: given `index_path`'s already
+/// resolved `safetensors::SafetensorsModel`, load and validate all of its
+/// shards, returning the loaded bytes in `model.shard_paths()` order.
+pub fn load_and_validate_safetensors_shards(
+    model: &crate::safetensors::SafetensorsModel,
+    options: LoadOptions,
+    concurrency: usize,
+    on_progress: &(dyn Fn(usize, usize) + Sync),
+) -> Result<Vec<WeightSource>, ShardLoadError> {
+    let paths = model.shard_paths();
+    let loaded = load_shards(&paths, options, concurrency, on_progress).map_err(ShardLoadError::Load)?;
+    model.validate_loaded_shards(&shard_lengths(&paths, &loaded)).map_err(ShardLoadError::Validation)?;
+    Ok(loaded)
+}
+
+#[derive(Debug)]
+pub enum ShardLoadError {
+    Load(LoadError),
+    Validation(crate::safetensors::SafetensorsError),
+}
+
+impl std::fmt::Display for ShardLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShardLoadError::Load(e) => write!(f, "shard load failed: {e:?}"),
+            ShardLoadError::Validation(e) => write!(f, "assembled tensor set failed validation: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safetensors::SafetensorsModel;
+    use std::io::Write;
+    use std::path::Path;
+    use std::sync::Mutex as StdMutex;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-shard-loader-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_shard(path: &Path, bytes: &[u8]) {
+        let mut file = std::fs::File::create(path).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    #[test]
+    fn load_shards_returns_bytes_in_input_order() {
+        let dir = temp_dir("order");
+        let a = dir.join("a.bin");
+        let b = dir.join("b.bin");
+        let c = dir.join("c.bin");
+        write_shard(&a, &[1u8; 4]);
+        write_shard(&b, &[2u8; 8]);
+        write_shard(&c, &[3u8; 2]);
+
+        let loaded = load_shards(&[a, b, c], LoadOptions::default(), 2, &|_, _| {}).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(&*loaded[0], &[1u8; 4][..]);
+        assert_eq!(&*loaded[1], &[2u8; 8][..]);
+        assert_eq!(&*loaded[2], &[3u8; 2][..]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_shards_reports_progress_once_per_shard() {
+        let dir = temp_dir("progress");
+        let paths: Vec<PathBuf> = (0..5)
+            .map(|i| {
+                let path = dir.join(format!("shard-{i}.bin"));
+                write_shard(&path, &[i as u8; 16]);
+                path
+            })
+            .collect();
+
+        let seen = StdMutex::new(Vec::new());
+        let loaded = load_shards(&paths, LoadOptions::default(), 3, &|done, total| {
+            seen.lock().unwrap().push((done, total));
+        })
+        .unwrap();
+
+        assert_eq!(loaded.len(), 5);
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(seen.len(), 5);
+        assert!(seen.iter().all(|&(_, total)| total == 5));
+        let mut done_values: Vec<usize> = seen.iter().map(|&(done, _)| done).collect();
+        done_values.sort_unstable();
+        assert_eq!(done_values, vec![1, 2, 3, 4, 5]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_shards_handles_an_empty_shard_list() {
+        let loaded = load_shards(&[], LoadOptions::default(), 4, &|_, _| {}).unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn load_and_validate_safetensors_shards_succeeds_for_a_consistent_sharded_checkpoint() {
+        let dir = temp_dir("assemble-ok");
+        let shard_a = dir.join("model-00001-of-00002.safetensors");
+        let shard_b = dir.join("model-00002-of-00002.safetensors");
+
+        for (path, header, data) in [
+            (&shard_a, r#"{"embed":{"dtype":"F16","shape":[4],"data_offsets":[0,8]}}"#, vec![0u8; 8]),
+            (&shard_b, r#"{"head":{"dtype":"F16","shape":[2],"data_offsets":[0,4]}}"#, vec![0u8; 4]),
+        ] {
+            let mut file = std::fs::File::create(path).unwrap();
+            file.write_all(&(header.len() as u64).to_le_bytes()).unwrap();
+            file.write_all(header.as_bytes()).unwrap();
+            file.write_all(&data).unwrap();
+        }
+
+        let index_path = dir.join("model.safetensors.index.json");
+        std::fs::write(
+            &index_path,
+            r#"{"weight_map":{"embed":"model-00001-of-00002.safetensors","head":"model-00002-of-00002.safetensors"}}"#,
+        )
+        .unwrap();
+
+        let model = SafetensorsModel::open(&index_path).unwrap();
+        let loaded = load_and_validate_safetensors_shards(&model, LoadOptions::default(), 2, &|_, _| {}).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_and_validate_safetensors_shards_rejects_a_truncated_shard() {
+        let dir = temp_dir("assemble-truncated");
+        let shard_a = dir.join("model-00001-of-00001.safetensors");
+        let header = r#"{"w":{"dtype":"F32","shape":[4,4],"data_offsets":[0,64]}}"#;
+        let mut file = std::fs::File::create(&shard_a).unwrap();
+        file.write_all(&(header.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(header.as_bytes()).unwrap();
+        // Only writes half the declared tensor data.
+        file.write_all(&[0u8; 32]).unwrap();
+        drop(file);
+
+        let index_path = dir.join("model.safetensors.index.json");
+        std::fs::write(&index_path, r#"{"weight_map":{"w":"model-00001-of-00001.safetensors"}}"#).unwrap();
+
+        let model = SafetensorsModel::open(&index_path).unwrap();
+        let result = load_and_validate_safetensors_shards(&model, LoadOptions::default(), 1, &|_, _| {});
+        assert!(matches!(result, Err(ShardLoadError::Validation(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+//! SIGTERM/SIGINT handling for graceful shutdown: stop accepting new
+//! connections, let in-flight requests finish (up to a configurable drain
+//! timeout), flush persisted state, and only then exit. An abrupt kill
+//! mid-generation truncates a streaming transcription or chat response with
+//! no explanation to the client — the same problem `admin::AdminState`'s
+//! `/admin/drain` flag solves for a planned rollout, except triggered by
+//! the process's own signal rather than an operator's API call.
+//!
+//! Catching the signal itself avoids the `signal-hook`/`ctrlc` crates (this
+//! tree's no-dependency policy) via a raw `extern "C"` binding to the libc
+//! `signal()` function — the same minimal-FFI approach `hardware.rs` uses
+//! for `sysctlbyname`. The handler only touches an `AtomicBool`, which is
+//! the one thing that's guaranteed safe to do from inside a signal handler.
+
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn signal(signum: c_int, handler: usize) -> usize;
+}
+
+extern "C" fn on_signal(_signum: c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Registers [`on_signal`] for `SIGTERM` and `SIGINT`. Call once from
+/// `main` before the accept loop starts.
+pub fn install() {
+    unsafe {
+        signal(SIGTERM, on_signal as *const () as usize);
+        signal(SIGINT, on_signal as *const () as usize);
+    }
+}
+
+/// Whether a shutdown signal has been received. `main`'s accept loop polls
+/// this between connections instead of blocking on `accept()` forever.
+pub fn requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Tracks how many connections `serve_one` is currently handling, so
+/// shutdown can wait for them to finish instead of cutting them off
+/// mid-response.
+#[derive(Default)]
+pub struct ActiveConnections(AtomicUsize);
+
+impl ActiveConnections {
+    pub fn new() -> Self {
+        ActiveConnections::default()
+    }
+
+    /// Marks one connection as in-flight until the returned guard drops.
+    pub fn track(&self) -> ConnectionGuard<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard(&self.0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub struct ConnectionGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Polls `active`'s count until it reaches zero or `timeout` elapses.
+/// Returns `true` if every connection finished on its own, `false` if the
+/// timeout won and callers are shutting down with work still in flight.
+pub fn wait_for_drain(active: &ActiveConnections, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while active.count() > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_connections_starts_at_zero_and_tracks_guards() {
+        let active = ActiveConnections::new();
+        assert_eq!(active.count(), 0);
+        let guard = active.track();
+        assert_eq!(active.count(), 1);
+        drop(guard);
+        assert_eq!(active.count(), 0);
+    }
+
+    #[test]
+    fn wait_for_drain_returns_true_once_all_guards_drop() {
+        let active: &'static ActiveConnections = Box::leak(Box::new(ActiveConnections::new()));
+        let guard = active.track();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            drop(guard);
+        });
+        assert!(wait_for_drain(active, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn wait_for_drain_returns_false_once_the_timeout_elapses() {
+        let active = ActiveConnections::new();
+        let _guard = active.track();
+        assert!(!wait_for_drain(&active, Duration::from_millis(20)));
+    }
+}
@@ -0,0 +1,167 @@
+//! Speculative decoding: a cheap "draft" model proposes several tokens
+//! ahead, and the (expensive) target model verifies them in a single
+//! batched step, accepting the run of proposals it agrees with and
+//! falling back to its own token at the first disagreement. On local,
+//! single-user generation this is the single biggest latency win
+//! available — the target model needs one forward pass to confirm K draft
+//! tokens instead of K passes to generate them itself.
+//!
+//! Neither a draft nor a target backend exists in this tree yet (see
+//! `EchoBackend`'s doc comment in `server.rs`), so this module is scoped
+//! like `sampling.rs`/`constraints.rs`: the algorithm and its acceptance
+//! bookkeeping, decoupled from any live model runtime. A real integration
+//! implements [`DraftModel`]/[`TargetModel`] over its two loaded weights
+//! and calls [`run_step`] from the decode loop; nothing here changes when
+//! that lands.
+
+/// Proposes tokens cheaply. A real draft model is a small or quantized
+/// network; this trait only asks for its output, not how it's computed.
+pub trait DraftModel {
+    /// Proposes up to `count` tokens to continue `context`.
+    fn propose(&self, context: &[u32], count: usize) -> Vec<u32>;
+}
+
+/// Scores what the target model would have generated at each position a
+/// draft run covers, in one forward pass. `target_tokens[i]` is what the
+/// target model considers the correct token at `context.len() + i`.
+pub trait TargetModel {
+    fn verify(&self, context: &[u32], draft_tokens: &[u32]) -> Vec<u32>;
+}
+
+/// One speculative step's outcome: the tokens actually advanced by
+/// (accepted draft tokens, plus the target's own token at the first
+/// mismatch or bonus position), and how many of `proposed` draft tokens
+/// the target agreed with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeculationResult {
+    pub accepted_tokens: Vec<u32>,
+    pub proposed: usize,
+    pub accepted: usize,
+}
+
+/// Runs one speculative decoding step: `draft` proposes `lookahead` tokens
+/// past `context`, `target` verifies all of them in one call, and the run
+/// accepts every proposed token up to the first mismatch. When every
+/// proposed token is accepted, the target's forward pass also scored one
+/// token past the draft's last proposal "for free" (a real target model
+/// returns `context.len() + draft_tokens.len() + 1` logits for the price
+/// of verifying `draft_tokens.len()`), so that bonus token is taken too.
+///
+/// Always makes forward progress by at least one token as long as `target`
+/// returns at least one token for a non-empty `context` — true even when
+/// `lookahead` is `0`, which degenerates to plain (non-speculative)
+/// decoding through the target model alone.
+pub fn run_step(draft: &dyn DraftModel, target: &dyn TargetModel, context: &[u32], lookahead: usize) -> SpeculationResult {
+    let proposed = draft.propose(context, lookahead);
+    let target_tokens = target.verify(context, &proposed);
+
+    let mut accepted_tokens = Vec::new();
+    let mut accepted = 0;
+    for (proposed_token, target_token) in proposed.iter().zip(target_tokens.iter()) {
+        if proposed_token == target_token {
+            accepted_tokens.push(*proposed_token);
+            accepted += 1;
+        } else {
+            accepted_tokens.push(*target_token);
+            return SpeculationResult { accepted_tokens, proposed: proposed.len(), accepted };
+        }
+    }
+    if let Some(bonus) = target_tokens.get(proposed.len()) {
+        accepted_tokens.push(*bonus);
+    }
+    SpeculationResult { accepted_tokens, proposed: proposed.len(), accepted }
+}
+
+/// Cumulative acceptance-rate bookkeeping across a run, so a caller can
+/// tell whether a draft/target pairing is actually paying off (and, e.g.,
+/// fall back to plain decoding through the target alone if it isn't).
+#[derive(Debug, Default)]
+pub struct AcceptanceStats {
+    proposed_total: u64,
+    accepted_total: u64,
+}
+
+impl AcceptanceStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, result: &SpeculationResult) {
+        self.proposed_total += result.proposed as u64;
+        self.accepted_total += result.accepted as u64;
+    }
+
+    /// Fraction of proposed draft tokens the target accepted, in
+    /// `[0.0, 1.0]`. Vacuously `1.0` before any step has been recorded, so
+    /// callers don't need to special-case "no data yet" before comparing
+    /// against a fallback threshold.
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.proposed_total == 0 {
+            1.0
+        } else {
+            self.accepted_total as f64 / self.proposed_total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedDraft(Vec<u32>);
+    impl DraftModel for FixedDraft {
+        fn propose(&self, _context: &[u32], count: usize) -> Vec<u32> {
+            self.0.iter().take(count).copied().collect()
+        }
+    }
+
+    struct FixedTarget(Vec<u32>);
+    impl TargetModel for FixedTarget {
+        fn verify(&self, _context: &[u32], _draft_tokens: &[u32]) -> Vec<u32> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn run_step_accepts_all_draft_tokens_and_adds_the_bonus_token() {
+        let draft = FixedDraft(vec![1, 2, 3]);
+        let target = FixedTarget(vec![1, 2, 3, 4]); // agrees on all 3, plus a bonus token
+        let result = run_step(&draft, &target, &[0], 3);
+        assert_eq!(result.accepted_tokens, vec![1, 2, 3, 4]);
+        assert_eq!(result.proposed, 3);
+        assert_eq!(result.accepted, 3);
+    }
+
+    #[test]
+    fn run_step_stops_at_first_mismatch_and_substitutes_targets_token() {
+        let draft = FixedDraft(vec![1, 2, 3]);
+        let target = FixedTarget(vec![1, 9, 3]); // diverges at index 1
+        let result = run_step(&draft, &target, &[0], 3);
+        assert_eq!(result.accepted_tokens, vec![1, 9]);
+        assert_eq!(result.proposed, 3);
+        assert_eq!(result.accepted, 1);
+    }
+
+    #[test]
+    fn run_step_makes_progress_when_lookahead_is_zero() {
+        let draft = FixedDraft(vec![]);
+        let target = FixedTarget(vec![7]);
+        let result = run_step(&draft, &target, &[0], 0);
+        assert_eq!(result.accepted_tokens, vec![7]);
+        assert_eq!(result.proposed, 0);
+        assert_eq!(result.accepted, 0);
+    }
+
+    #[test]
+    fn acceptance_stats_tracks_rate_across_multiple_steps() {
+        let mut stats = AcceptanceStats::new();
+        stats.record(&SpeculationResult { accepted_tokens: vec![], proposed: 4, accepted: 3 });
+        stats.record(&SpeculationResult { accepted_tokens: vec![], proposed: 4, accepted: 1 });
+        assert_eq!(stats.acceptance_rate(), 0.5); // 4/8
+    }
+
+    #[test]
+    fn acceptance_stats_defaults_to_full_rate_before_any_data() {
+        assert_eq!(AcceptanceStats::new().acceptance_rate(), 1.0);
+    }
+}
@@ -0,0 +1,178 @@
+//! Streaming-safe stop-sequence matching: a chat completion can be told to
+//! stop generating the moment its output contains one of a handful of
+//! strings (`"stop": ["\n\n", "User:"]`), but a token boundary from
+//! [`InferenceBackend::stream`](crate::InferenceBackend::stream) has no
+//! relationship to a stop sequence's boundaries — the match can start in
+//! one token and finish in the next. [`StopMatcher`] buffers only the
+//! minimal suffix that could still grow into a match, emitting everything
+//! else immediately, so the buffered and streaming completion paths agree
+//! on where a stop sequence would have cut the text without the streaming
+//! path having to wait for the whole completion first.
+
+/// The result of feeding one chunk of newly generated text to a
+/// [`StopMatcher`]: `emit` is the text that's now safe to send to the
+/// client (already excludes the matched stop sequence when `stopped` and
+/// the matcher was built with `strip: true`), and `stopped` says whether a
+/// stop sequence was found — the caller should stop asking the backend for
+/// more tokens once this is `true`.
+pub struct StopFeed {
+    pub emit: String,
+    pub stopped: bool,
+}
+
+/// Incremental matcher for a fixed set of stop sequences over a stream of
+/// text chunks. See the module doc comment for why this can't just be a
+/// substring search per chunk.
+pub struct StopMatcher {
+    sequences: Vec<String>,
+    strip: bool,
+    buffer: String,
+}
+
+impl StopMatcher {
+    /// `strip` controls whether a matched stop sequence is included in the
+    /// returned `emit` text — OpenAI's API never includes it, which is the
+    /// only way this is used elsewhere in this file, but the option is
+    /// kept explicit here rather than hardcoded so a caller wanting the
+    /// sequence back (e.g. to detect *which* one fired) still can. Empty
+    /// sequences are dropped since an empty string "matches" every
+    /// position and would stop generation immediately.
+    pub fn new(sequences: Vec<String>, strip: bool) -> Self {
+        StopMatcher { sequences: sequences.into_iter().filter(|s| !s.is_empty()).collect(), strip, buffer: String::new() }
+    }
+
+    /// Appends `chunk` to the internal buffer and returns however much of
+    /// it is now safe to emit. When no sequence is configured this is
+    /// always the whole chunk, with no buffering at all.
+    pub fn feed(&mut self, chunk: &str) -> StopFeed {
+        self.buffer.push_str(chunk);
+        if self.sequences.is_empty() {
+            return StopFeed { emit: std::mem::take(&mut self.buffer), stopped: false };
+        }
+
+        if let Some((start, matched_len)) = self.earliest_match() {
+            let mut emit = self.buffer[..start].to_string();
+            if !self.strip {
+                emit.push_str(&self.buffer[start..start + matched_len]);
+            }
+            self.buffer.clear();
+            return StopFeed { emit, stopped: true };
+        }
+
+        let held = self.longest_pending_prefix();
+        let split = self.buffer.len() - held;
+        let emit = self.buffer[..split].to_string();
+        self.buffer.drain(..split);
+        StopFeed { emit, stopped: false }
+    }
+
+    /// Whatever's left in the buffer once the caller knows no more chunks
+    /// are coming (generation ended without ever completing a match) —
+    /// text that was held back on a previous `feed` call because it could
+    /// still have grown into a stop sequence, but now never will.
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+
+    /// The earliest (by start byte) full occurrence of any configured
+    /// sequence in the buffer, and that sequence's length.
+    fn earliest_match(&self) -> Option<(usize, usize)> {
+        self.sequences.iter().filter_map(|seq| self.buffer.find(seq.as_str()).map(|i| (i, seq.len()))).min_by_key(|&(i, _)| i)
+    }
+
+    /// The longest proper prefix of any configured sequence that the
+    /// buffer currently ends with — the suffix that must stay buffered
+    /// because the next chunk could complete it into a full match.
+    fn longest_pending_prefix(&self) -> usize {
+        let mut longest = 0;
+        for seq in &self.sequences {
+            let mut prefix_len = 0;
+            for c in seq.chars() {
+                let next = prefix_len + c.len_utf8();
+                if next >= seq.len() {
+                    // The full sequence itself is handled by `earliest_match`.
+                    break;
+                }
+                if next <= self.buffer.len() && self.buffer.ends_with(&seq[..next]) {
+                    longest = longest.max(next);
+                }
+                prefix_len = next;
+            }
+        }
+        longest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_no_sequences_every_chunk_is_emitted_immediately() {
+        let mut matcher = StopMatcher::new(Vec::new(), true);
+        let feed = matcher.feed("hello ");
+        assert_eq!(feed.emit, "hello ");
+        assert!(!feed.stopped);
+    }
+
+    #[test]
+    fn matches_a_sequence_contained_entirely_within_one_chunk() {
+        let mut matcher = StopMatcher::new(vec!["STOP".to_string()], true);
+        let feed = matcher.feed("hello STOP world");
+        assert_eq!(feed.emit, "hello ");
+        assert!(feed.stopped);
+    }
+
+    #[test]
+    fn keeps_the_stop_sequence_when_strip_is_false() {
+        let mut matcher = StopMatcher::new(vec!["STOP".to_string()], false);
+        let feed = matcher.feed("hello STOP world");
+        assert_eq!(feed.emit, "hello STOP");
+        assert!(feed.stopped);
+    }
+
+    #[test]
+    fn buffers_a_partial_match_split_across_chunks() {
+        let mut matcher = StopMatcher::new(vec!["STOP".to_string()], true);
+        let first = matcher.feed("hello ST");
+        assert_eq!(first.emit, "hello ");
+        assert!(!first.stopped);
+
+        let second = matcher.feed("OP world");
+        assert_eq!(second.emit, "");
+        assert!(second.stopped);
+    }
+
+    #[test]
+    fn a_near_match_that_never_completes_is_eventually_flushed() {
+        let mut matcher = StopMatcher::new(vec!["STOP".to_string()], true);
+        let first = matcher.feed("hello ST");
+        assert_eq!(first.emit, "hello ");
+        let second = matcher.feed("RANGE");
+        assert_eq!(second.emit, "STRANGE");
+        assert!(!second.stopped);
+    }
+
+    #[test]
+    fn picks_the_earliest_match_among_multiple_sequences() {
+        let mut matcher = StopMatcher::new(vec!["World".to_string(), "Hello".to_string()], true);
+        let feed = matcher.feed("Hello World");
+        assert_eq!(feed.emit, "");
+        assert!(feed.stopped);
+    }
+
+    #[test]
+    fn finish_returns_whatever_was_held_back() {
+        let mut matcher = StopMatcher::new(vec!["STOP".to_string()], true);
+        matcher.feed("hello ST");
+        assert_eq!(matcher.finish(), "ST");
+    }
+
+    #[test]
+    fn empty_sequences_are_ignored_rather_than_matching_immediately() {
+        let mut matcher = StopMatcher::new(vec![String::new(), "STOP".to_string()], true);
+        let feed = matcher.feed("hello");
+        assert_eq!(feed.emit, "hello");
+        assert!(!feed.stopped);
+    }
+}
@@ -0,0 +1,188 @@
+//! Disk usage accounting and LRU eviction for the model cache under
+//! `registry::ModelRegistry`'s root. Model directories grow unbounded
+//! otherwise — nothing before this ever removed a file once `models pull`
+//! (or a restored backup, or a stray `scp`) put it there.
+//!
+//! "Least recently used" is read off each file's mtime rather than a
+//! tracked access log in the catalog: `ModelRegistry`'s shared instance in
+//! `server.rs` has no mutable, lock-free path back from a live request
+//! (see `registry.rs`'s `verify`, which only ever runs against a
+//! CLI-owned, single-threaded registry), and a real per-request access log
+//! would need the same write-on-every-request plumbing `metrics::Registry`
+//! already carries for request counts — more machinery than this feature
+//! needs to justify. [`mark_used`] bumps a file's mtime directly instead,
+//! which both `server.rs`'s long-running process and a one-shot `ai-server
+//! models gc` invocation can read without sharing any in-memory state.
+
+use crate::json::{Json, ObjectBuilder};
+use crate::registry::ModelRegistry;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Bumps `path`'s mtime to "now" — the signal [`gc`] reads to find the
+/// least-recently-used model. Called from `model_pool.rs`'s factory
+/// closure in `server.rs` every time a model is actually resolved, so a
+/// model `model_pool::ModelPool` itself evicted from memory between uses
+/// doesn't look idle to `gc` just because it isn't currently resident.
+pub fn mark_used(path: &Path) -> std::io::Result<()> {
+    std::fs::File::open(path)?.set_modified(SystemTime::now())
+}
+
+/// Total size of every model `registry` currently knows about.
+pub fn disk_usage_bytes(registry: &ModelRegistry) -> u64 {
+    registry.list().map(|entry| entry.size_bytes).sum()
+}
+
+/// What a [`gc`] run did, for `ai-server models gc`'s output and the
+/// `/admin/cache/gc` response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GcReport {
+    pub evicted: Vec<String>,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+impl GcReport {
+    pub fn to_json(&self) -> String {
+        let evicted = self.evicted.iter().cloned().map(Json::String).collect();
+        ObjectBuilder::new()
+            .set("evicted", Json::Array(evicted))
+            .set("freed_bytes", Json::Number(self.freed_bytes as f64))
+            .set("remaining_bytes", Json::Number(self.remaining_bytes as f64))
+            .build()
+            .to_string()
+    }
+}
+
+/// Evicts the least-recently-used models (oldest mtime first) from
+/// `registry`, deleting each one's file, until total disk usage is at or
+/// under `max_cache_bytes`. `max_cache_bytes == 0` means unlimited,
+/// matching `config.rs`'s other `0`-means-unlimited fields
+/// (`log_max_bytes`, `daily_token_quota`), so `gc` is a no-op by default.
+/// Never evicts an id in `protected` (e.g. whatever
+/// `model_pool::ModelPool::loaded_model_ids` reports resident) — if every
+/// unprotected entry is gone and the cache is still over budget, `gc`
+/// stops there rather than pulling a file out from under an in-flight
+/// request.
+pub fn gc(registry: &mut ModelRegistry, max_cache_bytes: u64, protected: &HashSet<String>) -> std::io::Result<GcReport> {
+    let mut total = disk_usage_bytes(registry);
+    if max_cache_bytes == 0 || total <= max_cache_bytes {
+        return Ok(GcReport { evicted: Vec::new(), freed_bytes: 0, remaining_bytes: total });
+    }
+
+    let mut candidates: Vec<(String, u64, SystemTime)> = registry
+        .list()
+        .filter(|entry| !protected.contains(&entry.id))
+        .map(|entry| (entry.id.clone(), entry.size_bytes, last_modified(&entry.path)))
+        .collect();
+    candidates.sort_by_key(|(_, _, mtime)| *mtime);
+
+    let mut report = GcReport { evicted: Vec::new(), freed_bytes: 0, remaining_bytes: total };
+    for (id, size_bytes, _) in candidates {
+        if total <= max_cache_bytes {
+            break;
+        }
+        if registry.remove(&id)? {
+            total -= size_bytes;
+            report.freed_bytes += size_bytes;
+            report.evicted.push(id);
+        }
+    }
+    report.remaining_bytes = total;
+    Ok(report)
+}
+
+/// A file's mtime, or `UNIX_EPOCH` if it can't be read — treating a
+/// missing or unreadable file as maximally stale means `gc` cleans up a
+/// broken catalog entry before it touches anything actually usable.
+fn last_modified(path: &Path) -> SystemTime {
+    std::fs::metadata(path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-storage-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_model_with_age(dir: &Path, id: &str, bytes: &[u8], age: Duration) -> std::io::Result<()> {
+        let path = dir.join(format!("{id}.gguf"));
+        fs::write(&path, bytes)?;
+        fs::File::open(&path)?.set_modified(SystemTime::now() - age)
+    }
+
+    #[test]
+    fn disk_usage_bytes_sums_every_entry() {
+        let dir = temp_dir("usage");
+        write_model_with_age(&dir, "a", b"12345", Duration::ZERO).unwrap();
+        write_model_with_age(&dir, "b", b"123", Duration::ZERO).unwrap();
+        let registry = ModelRegistry::open(&dir).unwrap();
+        assert_eq!(disk_usage_bytes(&registry), 8);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gc_is_a_no_op_when_max_cache_bytes_is_zero() {
+        let dir = temp_dir("gc-unlimited");
+        write_model_with_age(&dir, "a", b"12345", Duration::ZERO).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+        let report = gc(&mut registry, 0, &HashSet::new()).unwrap();
+        assert!(report.evicted.is_empty());
+        assert!(registry.resolve("a").is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gc_evicts_the_oldest_model_first_until_under_budget() {
+        let dir = temp_dir("gc-lru");
+        write_model_with_age(&dir, "old", b"12345", Duration::from_secs(3600)).unwrap();
+        write_model_with_age(&dir, "new", b"12345", Duration::ZERO).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+
+        let report = gc(&mut registry, 5, &HashSet::new()).unwrap();
+        assert_eq!(report.evicted, vec!["old".to_string()]);
+        assert_eq!(report.freed_bytes, 5);
+        assert_eq!(report.remaining_bytes, 5);
+        assert!(registry.resolve("old").is_none());
+        assert!(registry.resolve("new").is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gc_never_evicts_a_protected_id_even_if_it_is_the_oldest() {
+        let dir = temp_dir("gc-protected");
+        write_model_with_age(&dir, "old", b"12345", Duration::from_secs(3600)).unwrap();
+        let mut registry = ModelRegistry::open(&dir).unwrap();
+
+        let protected: HashSet<String> = ["old".to_string()].into_iter().collect();
+        let report = gc(&mut registry, 1, &protected).unwrap();
+        assert!(report.evicted.is_empty());
+        assert!(registry.resolve("old").is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mark_used_refreshes_the_files_mtime() {
+        let dir = temp_dir("mark-used");
+        write_model_with_age(&dir, "a", b"12345", Duration::from_secs(3600)).unwrap();
+        let path = dir.join("a.gguf");
+        let before = fs::metadata(&path).unwrap().modified().unwrap();
+
+        mark_used(&path).unwrap();
+        let after = fs::metadata(&path).unwrap().modified().unwrap();
+        assert!(after > before);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Per-tenant isolation layered on top of `auth::AuthRegistry`'s existing
+//! per-key identity: each API key optionally maps to a tenant id, and a
+//! tenant can be restricted to a subset of models and given its own
+//! namespace within `vectorstore::VectorStore`'s already name-keyed
+//! collections. Small teams sharing one machine get that separation
+//! without this server standing up a second process or database per team.
+//!
+//! Rate limiting and daily token quotas stay exactly as
+//! `auth::AuthRegistry` already enforces them — per API key, not per
+//! tenant — since a tenant here is just a named group of keys; nothing in
+//! this module merges their buckets. `handle_admin_tenants` in `server.rs`
+//! sums `AuthRegistry::quota_used` across a tenant's keys for reporting,
+//! but each key is still limited individually.
+
+use std::collections::{HashMap, HashSet};
+
+/// Maps API keys to tenant ids and tenants to their allowed model ids. A
+/// key with no entry belongs to no tenant (`tenant_for` returns `None`),
+/// and a tenant with no entry in `allowed_models` is unrestricted — the
+/// same opt-in posture `AuthRegistry` takes with an empty key list.
+pub struct TenantRegistry {
+    tenant_by_key: HashMap<String, String>,
+    allowed_models: HashMap<String, HashSet<String>>,
+}
+
+impl TenantRegistry {
+    pub fn new(tenant_by_key: HashMap<String, String>, allowed_models: HashMap<String, HashSet<String>>) -> Self {
+        TenantRegistry { tenant_by_key, allowed_models }
+    }
+
+    /// The tenant `key` belongs to, or `None` for an unmapped key (or when
+    /// auth is disabled and no key was available to look up at all).
+    pub fn tenant_for(&self, key: Option<&str>) -> Option<&str> {
+        key.and_then(|k| self.tenant_by_key.get(k)).map(String::as_str)
+    }
+
+    /// Whether `tenant` may use `model_id` — always true for a tenant with
+    /// no configured allow-list, or when there's no tenant at all.
+    pub fn allows_model(&self, tenant: Option<&str>, model_id: &str) -> bool {
+        match tenant.and_then(|t| self.allowed_models.get(t)) {
+            Some(allowed) => allowed.contains(model_id),
+            None => true,
+        }
+    }
+
+    /// Namespaces a `vectorstore::VectorStore` collection name under
+    /// `tenant`, so two tenants' `"docs"` collections never collide —
+    /// same string-keyed collection map, just a different key.
+    pub fn namespaced_collection(tenant: Option<&str>, name: &str) -> String {
+        match tenant {
+            Some(t) => format!("{t}:{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    /// Every distinct tenant id referenced by `tenant_by_key`, sorted so
+    /// repeated calls (e.g. `/admin/tenants`) don't jitter.
+    pub fn tenant_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.tenant_by_key.values().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// The API keys mapped to `tenant`.
+    pub fn keys_for(&self, tenant: &str) -> Vec<&str> {
+        self.tenant_by_key.iter().filter(|(_, t)| t.as_str() == tenant).map(|(k, _)| k.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> TenantRegistry {
+        let mut tenant_by_key = HashMap::new();
+        tenant_by_key.insert("key-a".to_string(), "teamA".to_string());
+        tenant_by_key.insert("key-a2".to_string(), "teamA".to_string());
+        tenant_by_key.insert("key-b".to_string(), "teamB".to_string());
+        let mut allowed_models = HashMap::new();
+        allowed_models.insert("teamA".to_string(), ["small".to_string()].into_iter().collect());
+        TenantRegistry::new(tenant_by_key, allowed_models)
+    }
+
+    #[test]
+    fn tenant_for_looks_up_the_mapped_tenant() {
+        assert_eq!(registry().tenant_for(Some("key-a")), Some("teamA"));
+    }
+
+    #[test]
+    fn tenant_for_is_none_for_an_unmapped_key_or_no_key() {
+        let registry = registry();
+        assert_eq!(registry.tenant_for(Some("stranger")), None);
+        assert_eq!(registry.tenant_for(None), None);
+    }
+
+    #[test]
+    fn allows_model_enforces_a_tenants_allow_list() {
+        let registry = registry();
+        assert!(registry.allows_model(Some("teamA"), "small"));
+        assert!(!registry.allows_model(Some("teamA"), "large"));
+    }
+
+    #[test]
+    fn allows_model_is_unrestricted_for_a_tenant_with_no_allow_list() {
+        let registry = registry();
+        assert!(registry.allows_model(Some("teamB"), "anything"));
+        assert!(registry.allows_model(None, "anything"));
+    }
+
+    #[test]
+    fn namespaced_collection_prefixes_with_the_tenant_id() {
+        assert_eq!(TenantRegistry::namespaced_collection(Some("teamA"), "docs"), "teamA:docs");
+        assert_eq!(TenantRegistry::namespaced_collection(None, "docs"), "docs");
+    }
+
+    #[test]
+    fn tenant_ids_and_keys_for_report_the_keys_grouped_by_tenant() {
+        let registry = registry();
+        assert_eq!(registry.tenant_ids(), vec!["teamA", "teamB"]);
+        let mut keys = registry.keys_for("teamA");
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["key-a", "key-a2"]);
+    }
+}
@@ -0,0 +1,224 @@
+//! SoC thermal/power telemetry and an adaptive concurrency governor for
+//! sustained generation on thermally-constrained hosts — fanless Macs
+//! chief among them, the same Apple Silicon focus `gpu.rs` and
+//! `hardware.rs`'s `sysctlbyname` note already establish for this tree.
+//!
+//! [`ThermalReading::probe`] reads what's available without a kernel
+//! extension or root: macOS exposes no unprivileged temperature API, so
+//! this shells out to `pmset -g therm` for Apple's own throttling
+//! percentage (the same "shell out rather than bind a private framework"
+//! trade `gpu.rs` makes for `system_profiler`); Linux reads
+//! `/sys/class/hwmon`, the same kind of plain-file `/sys`/`/proc` read
+//! `hardware.rs` already does for core topology.
+//!
+//! [`ThermalGovernor`] turns a reading into a batch-size recommendation.
+//! There's no live batching scheduler wired into the request path yet
+//! (see `resources.rs`'s note on `scheduler.rs` sitting unused), so this
+//! is the decision function a future one calls once it exists, not
+//! something invoked automatically today.
+
+#[cfg(target_os = "macos")]
+use std::process::Command;
+
+/// One point-in-time thermal/power reading. Fields are `Option` because
+/// the two supported platforms expose different subsets: macOS's
+/// `pmset -g therm` has no absolute temperature or power draw, only a
+/// throttling percentage; Linux hwmon usually has temperature and often
+/// power, but no throttling percentage of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalReading {
+    pub temperature_celsius: Option<f64>,
+    pub power_watts: Option<f64>,
+    /// `100` = running at full speed, `0` = fully throttled. Derived from
+    /// `pmset`'s `CPU_Speed_Limit` on macOS; approximated on Linux as `0`
+    /// once `temperature_celsius` reaches hwmon's own `*_crit` point, `100`
+    /// otherwise, since hwmon doesn't report an OS-level throttle percentage.
+    pub speed_limit_percent: u8,
+}
+
+impl ThermalReading {
+    #[cfg(target_os = "macos")]
+    pub fn probe() -> Option<ThermalReading> {
+        let output = Command::new("pmset").args(["-g", "therm"]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let speed_limit_percent = parse_pmset_speed_limit(&String::from_utf8_lossy(&output.stdout))?;
+        Some(ThermalReading { temperature_celsius: None, power_watts: None, speed_limit_percent })
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn probe() -> Option<ThermalReading> {
+        probe_hwmon_under("/sys/class/hwmon")
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    pub fn probe() -> Option<ThermalReading> {
+        None
+    }
+}
+
+/// Parses `CPU_Speed_Limit = <percent>` out of `pmset -g therm`'s output,
+/// e.g.:
+/// ```text
+/// No kIOPMThermalWarningLevel or ambient sensors info found
+/// CPU_Scheduler_Limit    = 100
+/// CPU_Speed_Limit        = 100
+/// CPU_Available_CPUs     = 8
+/// ```
+#[cfg(target_os = "macos")]
+fn parse_pmset_speed_limit(text: &str) -> Option<u8> {
+    text.lines().find_map(|line| {
+        let (field, value) = line.split_once('=')?;
+        (field.trim() == "CPU_Speed_Limit").then(|| value.trim().parse::<u8>().ok()).flatten()
+    })
+}
+
+/// Reads the first hwmon device under `root` that reports a temperature,
+/// e.g. `<root>/hwmon0/temp1_input` (millidegrees Celsius) and, if present,
+/// `power1_average` (microwatts) — the layout the Linux kernel's hwmon
+/// sysfs class always uses, documented in
+/// `Documentation/hwmon/sysfs-interface.rst`.
+#[cfg(target_os = "linux")]
+fn probe_hwmon_under(root: &str) -> Option<ThermalReading> {
+    let entries = std::fs::read_dir(root).ok()?;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let Some(millidegrees) = read_i64_file(&dir.join("temp1_input")) else { continue };
+        let temperature_celsius = millidegrees as f64 / 1000.0;
+        let power_watts = read_i64_file(&dir.join("power1_average")).map(|microwatts| microwatts as f64 / 1_000_000.0);
+        let speed_limit_percent = match read_i64_file(&dir.join("temp1_crit")) {
+            Some(crit) if millidegrees >= crit => 0,
+            _ => 100,
+        };
+        return Some(ThermalReading { temperature_celsius: Some(temperature_celsius), power_watts, speed_limit_percent });
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn read_i64_file(path: &std::path::Path) -> Option<i64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Decides how much to shrink batch size (or decode concurrency, which
+/// this tree treats the same way — see `resources.rs`'s per-request
+/// accounting note) once thermals cross a threshold, so sustained
+/// generation degrades to a steady lower throughput instead of the OS's
+/// own throttling producing unpredictable stalls mid-decode.
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalGovernor {
+    /// Once a reading's `speed_limit_percent` drops below this, the
+    /// recommended batch size is halved.
+    pub throttle_below_percent: u8,
+    /// Never recommend a batch size below this, regardless of how
+    /// throttled the host is — a batch of zero can't make progress at all.
+    pub min_batch_size: usize,
+}
+
+impl Default for ThermalGovernor {
+    fn default() -> Self {
+        ThermalGovernor { throttle_below_percent: 80, min_batch_size: 1 }
+    }
+}
+
+impl ThermalGovernor {
+    /// Recommends the batch size for the next decode step: halves
+    /// `current_batch_size` (floored at `min_batch_size`) while `reading`
+    /// is under threshold, and restores `max_batch_size` once thermals
+    /// clear, so a cooled-down host recovers full throughput on its own
+    /// rather than staying throttled until restarted.
+    pub fn recommended_batch_size(&self, reading: &ThermalReading, current_batch_size: usize, max_batch_size: usize) -> usize {
+        if reading.speed_limit_percent < self.throttle_below_percent {
+            (current_batch_size / 2).max(self.min_batch_size)
+        } else {
+            max_batch_size
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn parse_pmset_speed_limit_reads_the_named_field() {
+        let text = "No kIOPMThermalWarningLevel\nCPU_Scheduler_Limit    = 100\nCPU_Speed_Limit        = 45\n";
+        assert_eq!(parse_pmset_speed_limit(text), Some(45));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn parse_pmset_speed_limit_returns_none_when_the_field_is_absent() {
+        assert_eq!(parse_pmset_speed_limit("CPU_Scheduler_Limit = 100\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn temp_hwmon_root(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ai-server-thermal-test-{name}-{}", std::process::id()))
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn probe_hwmon_under_reads_temperature_and_power() {
+        let root = temp_hwmon_root("full");
+        let device = root.join("hwmon0");
+        std::fs::create_dir_all(&device).unwrap();
+        std::fs::write(device.join("temp1_input"), "45000").unwrap();
+        std::fs::write(device.join("power1_average"), "12500000").unwrap();
+
+        let reading = probe_hwmon_under(root.to_str().unwrap()).unwrap();
+        assert_eq!(reading.temperature_celsius, Some(45.0));
+        assert_eq!(reading.power_watts, Some(12.5));
+        assert_eq!(reading.speed_limit_percent, 100);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn probe_hwmon_under_reports_zero_speed_limit_at_or_past_the_critical_point() {
+        let root = temp_hwmon_root("critical");
+        let device = root.join("hwmon0");
+        std::fs::create_dir_all(&device).unwrap();
+        std::fs::write(device.join("temp1_input"), "100000").unwrap();
+        std::fs::write(device.join("temp1_crit"), "100000").unwrap();
+
+        let reading = probe_hwmon_under(root.to_str().unwrap()).unwrap();
+        assert_eq!(reading.speed_limit_percent, 0);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn probe_hwmon_under_returns_none_when_no_device_reports_a_temperature() {
+        let root = temp_hwmon_root("empty");
+        std::fs::create_dir_all(&root).unwrap();
+        assert!(probe_hwmon_under(root.to_str().unwrap()).is_none());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    fn reading(speed_limit_percent: u8) -> ThermalReading {
+        ThermalReading { temperature_celsius: Some(80.0), power_watts: None, speed_limit_percent }
+    }
+
+    #[test]
+    fn recommended_batch_size_halves_once_below_the_threshold() {
+        let governor = ThermalGovernor::default();
+        assert_eq!(governor.recommended_batch_size(&reading(50), 8, 8), 4);
+    }
+
+    #[test]
+    fn recommended_batch_size_never_drops_below_min_batch_size() {
+        let governor = ThermalGovernor { throttle_below_percent: 80, min_batch_size: 2 };
+        assert_eq!(governor.recommended_batch_size(&reading(10), 3, 8), 2);
+    }
+
+    #[test]
+    fn recommended_batch_size_restores_max_once_thermals_clear() {
+        let governor = ThermalGovernor::default();
+        assert_eq!(governor.recommended_batch_size(&reading(100), 2, 8), 8);
+    }
+}
@@ -0,0 +1,169 @@
+//! Topology-aware compute-thread placement, layered on top of `hardware.rs`'s
+//! [`CoreTopology`] probe. `runtime.rs`'s default pinning (worker N -> logical
+//! core N) is fine on a plain symmetric server, but it's actively wrong on
+//! the two topologies this module targets:
+//!
+//! - Apple Silicon's big.LITTLE layout, where default OS scheduling
+//!   routinely lands tightly-synchronized matmul workers on efficiency
+//!   cores, costing 20-30% decode throughput.
+//! - Multi-socket Linux servers, where a worker pinned to a core on the
+//!   "wrong" NUMA node pays a remote-memory-access penalty on every access
+//!   to data another worker allocated.
+//!
+//! macOS gives user processes no public API to pin a thread to a specific
+//! core — Apple's affinity-tag API is a cache-locality hint the scheduler is
+//! free to ignore, not an enforceable mask — so the Apple Silicon side of
+//! this module works by setting the calling thread's QoS class instead:
+//! `QOS_CLASS_USER_INTERACTIVE` empirically biases the scheduler toward
+//! performance cores, which is the closest lever actually available. Linux
+//! gets a real `sched_setaffinity` mask restricted to one NUMA node's cpu
+//! list, read from sysfs the same way `hardware.rs` reads `/proc/cpuinfo`.
+
+use crate::hardware::HardwareProfile;
+
+/// Applies best-effort topology-aware placement for worker `worker` (of
+/// however many workers the caller is spinning up), given `profile`. A
+/// no-op on any host/platform combination this module doesn't have a real
+/// lever for — same "best-effort" contract `runtime.rs`'s plain core-index
+/// pinning already makes.
+pub fn place_worker(profile: &HardwareProfile, worker: usize) {
+    imp::place_worker(profile, worker)
+}
+
+/// Parses a Linux cpu-list string like `"0-3,8,10-11"` into individual cpu
+/// ids — the format both `/sys/devices/system/node/*/cpulist` and cpuset
+/// files use. Malformed entries are skipped rather than failing the whole
+/// list, since a partial mask is still better than pinning nothing.
+fn parse_cpu_list(text: &str) -> Vec<usize> {
+    let mut ids = Vec::new();
+    for part in text.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    ids.extend(start..=end);
+                }
+            }
+            None => {
+                if let Ok(id) = part.parse::<usize>() {
+                    ids.push(id);
+                }
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{parse_cpu_list, HardwareProfile};
+
+    /// Groups logical cpu ids by NUMA node, in node-id order, by reading
+    /// `/sys/devices/system/node/node{N}/cpulist` until a node is missing.
+    /// Empty on non-NUMA hosts (a single node, or the sysfs tree missing
+    /// entirely) — callers treat that the same as "nothing to do here."
+    fn numa_nodes() -> Vec<Vec<usize>> {
+        let mut nodes = Vec::new();
+        let mut node_id = 0;
+        loop {
+            let path = format!("/sys/devices/system/node/node{node_id}/cpulist");
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                break;
+            };
+            nodes.push(parse_cpu_list(text.trim()));
+            node_id += 1;
+        }
+        nodes
+    }
+
+    pub fn place_worker(_profile: &HardwareProfile, worker: usize) {
+        let nodes = numa_nodes();
+        if nodes.is_empty() {
+            return;
+        }
+        pin_to_cpu_set(&nodes[worker % nodes.len()]);
+    }
+
+    /// Restricts the calling thread's affinity mask to `cpus`, so the kernel
+    /// only ever schedules it onto cores local to one NUMA node.
+    fn pin_to_cpu_set(cpus: &[usize]) {
+        const CPU_SETSIZE: usize = 1024;
+        const BITS_PER_WORD: usize = 64;
+        let mut mask = [0u64; CPU_SETSIZE / BITS_PER_WORD];
+        for &cpu in cpus {
+            let word = cpu / BITS_PER_WORD;
+            if word < mask.len() {
+                mask[word] |= 1 << (cpu % BITS_PER_WORD);
+            }
+        }
+        unsafe {
+            sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr());
+        }
+    }
+
+    extern "C" {
+        fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+    }
+}
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+mod imp {
+    use super::HardwareProfile;
+    use crate::hardware::CoreTopology;
+    use std::os::raw::c_int;
+
+    const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+    const QOS_CLASS_UTILITY: u32 = 0x09;
+
+    extern "C" {
+        fn pthread_set_qos_class_self_np(qos_class: u32, relative_priority: c_int) -> c_int;
+    }
+
+    /// Hints performance-core scheduling for `worker` when `profile` reports
+    /// a heterogeneous topology and `worker` falls within the
+    /// performance-core count; the rest get `QOS_CLASS_UTILITY` so they
+    /// don't contend with the workers we're steering toward P-cores.
+    pub fn place_worker(profile: &HardwareProfile, worker: usize) {
+        if let CoreTopology::Heterogeneous { performance, .. } = profile.topology {
+            let qos = if worker < performance { QOS_CLASS_USER_INTERACTIVE } else { QOS_CLASS_UTILITY };
+            unsafe {
+                pthread_set_qos_class_self_np(qos, 0);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", all(target_os = "macos", target_arch = "aarch64"))))]
+mod imp {
+    use super::HardwareProfile;
+
+    pub fn place_worker(_profile: &HardwareProfile, _worker: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cpu_list_expands_ranges_and_singletons() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11"), vec![0, 1, 2, 3, 8, 10, 11]);
+    }
+
+    #[test]
+    fn parse_cpu_list_ignores_empty_and_malformed_entries() {
+        assert_eq!(parse_cpu_list("0-1,,garbage,4"), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn parse_cpu_list_handles_a_single_id() {
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+    }
+
+    #[test]
+    fn parse_cpu_list_handles_an_empty_string() {
+        assert_eq!(parse_cpu_list(""), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,384 @@
+//! TLS termination for the plain-`TcpStream` HTTP/RPC listeners: a real
+//! X.509/TLS 1.2-1.3 handshake needs actual cryptography (certificate
+//! parsing, key exchange, AEAD record encryption), which is well beyond
+//! what this tree hand-rolls its own protocols for (contrast `sha256.rs`'s
+//! own hand-rolled hash — a TLS stack is orders of magnitude more surface
+//! and, done wrong, a security hole rather than a slow hash). `rustls`
+//! itself is unavailable for the same reason every other crate is (see
+//! `json.rs`'s doc comment on having no dependency manager), so this binds
+//! against the system's OpenSSL (`libssl`/`libcrypto`) via `extern "C"`,
+//! the same "link a system library instead of a Rust crate" move
+//! `llama_ffi.rs` makes for llama.cpp. Only the handful of entry points
+//! needed for a server-side handshake, PEM cert/key loading, and mTLS
+//! client-certificate verification are declared — a client-side `connect`
+//! path lands if `chat_client.rs`/`client.rs` grow a `--tls` flag later.
+//!
+//! **Not wired into `server.rs`'s accept loop.** Every request-handling
+//! function downstream of accepting a connection (`http::read_request`,
+//! `Response::write_to`, `http::SseWriter`, every `websocket.rs` function,
+//! `rpc::serve_connection`) is written against a concrete
+//! `std::net::TcpStream`, not a generic transport. Making
+//! [`TlsAcceptor`]/[`TlsStream`] actually terminate real traffic means
+//! threading a `Read + Write` abstraction (or a `Plain`/`Tls` stream enum)
+//! through all of them — a transport-layer rewrite this change doesn't
+//! make. What's here is the real, working handshake/cert-verification
+//! primitive that rewrite would plug into, not a stub.
+//!
+//! **No ACME auto-provisioning.** Automatic cert issuance needs a full
+//! ACME v2 client (JWS request signing, the order/authorize/challenge/
+//! finalize state machine, and an HTTP-01 responder or DNS-01 hook) — a
+//! second large subsystem this change doesn't attempt. `[tls]` config
+//! only ever reads a cert/key operators provide themselves, the same way
+//! `registry::ModelRegistry` only ever reads a model an operator already
+//! placed under `models_dir` rather than fetching one on its own.
+//!
+//! Unix-only ([`TlsAcceptor::accept`] needs a raw fd to hand OpenSSL via
+//! `SSL_set_fd`), consistent with this tree's other platform-specific
+//! modules (see `hardware.rs`'s `sysctlbyname` binding, `thermal.rs`'s
+//! `cfg(target_os)` split).
+
+#![cfg(unix)]
+
+use std::ffi::CString;
+use std::net::TcpStream;
+use std::os::raw::{c_char, c_int, c_long, c_void};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+#[link(name = "ssl")]
+extern "C" {
+    fn TLS_server_method() -> *const c_void;
+    fn SSL_CTX_new(method: *const c_void) -> *mut c_void;
+    fn SSL_CTX_free(ctx: *mut c_void);
+    fn SSL_CTX_use_certificate_chain_file(ctx: *mut c_void, file: *const c_char) -> c_int;
+    fn SSL_CTX_use_PrivateKey_file(ctx: *mut c_void, file: *const c_char, kind: c_int) -> c_int;
+    fn SSL_CTX_check_private_key(ctx: *const c_void) -> c_int;
+    fn SSL_CTX_load_verify_locations(ctx: *mut c_void, ca_file: *const c_char, ca_path: *const c_char) -> c_int;
+    fn SSL_CTX_set_verify(ctx: *mut c_void, mode: c_int, callback: *const c_void);
+    fn SSL_new(ctx: *mut c_void) -> *mut c_void;
+    fn SSL_free(ssl: *mut c_void);
+    fn SSL_set_fd(ssl: *mut c_void, fd: c_int) -> c_int;
+    fn SSL_accept(ssl: *mut c_void) -> c_int;
+    fn SSL_read(ssl: *mut c_void, buf: *mut c_void, num: c_int) -> c_int;
+    fn SSL_write(ssl: *mut c_void, buf: *const c_void, num: c_int) -> c_int;
+    fn SSL_get_error(ssl: *const c_void, ret: c_int) -> c_int;
+    fn SSL_get_verify_result(ssl: *const c_void) -> c_long;
+}
+
+/// OpenSSL's `SSL_FILETYPE_PEM` — the only format this binding supports;
+/// operators hand this a PEM cert/key the same way `registry.rs` expects
+/// a GGUF file rather than any other container.
+const SSL_FILETYPE_PEM: c_int = 1;
+const SSL_VERIFY_PEER: c_int = 0x01;
+const SSL_VERIFY_FAIL_IF_NO_PEER_CERT: c_int = 0x02;
+/// OpenSSL's `X509_V_OK` — the only "verification actually passed" value
+/// `SSL_get_verify_result` returns.
+const X509_V_OK: c_long = 0;
+/// `SSL_get_error`'s result when the peer sent a `close_notify` alert —
+/// the only case in which a non-positive `SSL_read` return means a
+/// genuine, authenticated end of stream rather than an error or a
+/// connection severed without one (the TLS truncation attack).
+const SSL_ERROR_ZERO_RETURN: c_int = 6;
+
+#[derive(Debug)]
+pub enum TlsError {
+    /// A cert/key/CA file couldn't be loaded, or didn't match, per
+    /// OpenSSL's own return code — it doesn't hand back a message for
+    /// most of these, so this only ever names which step failed.
+    Config(&'static str),
+    Handshake(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for TlsError {
+    fn from(e: std::io::Error) -> Self {
+        TlsError::Io(e)
+    }
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString, TlsError> {
+    CString::new(path.to_string_lossy().into_owned()).map_err(|_| TlsError::Config("path contains a NUL byte"))
+}
+
+/// Where an operator's [`TlsAcceptor`] gets its certificate material from
+/// — mirrors `[tls]` in `config.rs`.
+pub struct TlsConfig<'a> {
+    pub cert_path: &'a Path,
+    pub key_path: &'a Path,
+    /// When set, only clients presenting a certificate signed by a CA in
+    /// this file are accepted — `SSL_VERIFY_PEER |
+    /// SSL_VERIFY_FAIL_IF_NO_PEER_CERT` rather than the server's default
+    /// of not asking for one at all.
+    pub client_ca_path: Option<&'a Path>,
+}
+
+/// Owns an `SSL_CTX` built from a [`TlsConfig`]: the cert/key are parsed
+/// and checked once at construction, so a misconfigured deployment fails
+/// at startup (see `config.rs::validate`'s own "fail fast" convention)
+/// rather than on the first connection.
+pub struct TlsAcceptor {
+    ctx: *mut c_void,
+    mtls: bool,
+}
+
+// `SSL_CTX` is safe to share across threads once built — OpenSSL's own
+// documented guarantee for this API — and this tree hands the same
+// `&'static TlsAcceptor` to every `serve_one` worker thread the way
+// `budget`/`registry` already are.
+unsafe impl Send for TlsAcceptor {}
+unsafe impl Sync for TlsAcceptor {}
+
+impl TlsAcceptor {
+    pub fn new(config: TlsConfig) -> Result<TlsAcceptor, TlsError> {
+        let ctx = unsafe { SSL_CTX_new(TLS_server_method()) };
+        if ctx.is_null() {
+            return Err(TlsError::Config("SSL_CTX_new failed"));
+        }
+        let cert_path = path_to_cstring(config.cert_path)?;
+        if unsafe { SSL_CTX_use_certificate_chain_file(ctx, cert_path.as_ptr()) } != 1 {
+            unsafe { SSL_CTX_free(ctx) };
+            return Err(TlsError::Config("failed to load tls.cert_path"));
+        }
+        let key_path = path_to_cstring(config.key_path)?;
+        if unsafe { SSL_CTX_use_PrivateKey_file(ctx, key_path.as_ptr(), SSL_FILETYPE_PEM) } != 1 {
+            unsafe { SSL_CTX_free(ctx) };
+            return Err(TlsError::Config("failed to load tls.key_path"));
+        }
+        if unsafe { SSL_CTX_check_private_key(ctx) } != 1 {
+            unsafe { SSL_CTX_free(ctx) };
+            return Err(TlsError::Config("tls.key_path does not match tls.cert_path"));
+        }
+        let mtls = config.client_ca_path.is_some();
+        if let Some(client_ca_path) = config.client_ca_path {
+            let client_ca_path = path_to_cstring(client_ca_path)?;
+            if unsafe { SSL_CTX_load_verify_locations(ctx, client_ca_path.as_ptr(), std::ptr::null()) } != 1 {
+                unsafe { SSL_CTX_free(ctx) };
+                return Err(TlsError::Config("failed to load tls.client_ca_path"));
+            }
+            unsafe { SSL_CTX_set_verify(ctx, SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT, std::ptr::null()) };
+        }
+        Ok(TlsAcceptor { ctx, mtls })
+    }
+
+    /// Performs the server-side TLS handshake over an already-`accept`ed
+    /// `stream`, consuming it — mirrors `websocket::handshake`'s
+    /// "takes the raw stream, hands back something request handling
+    /// continues on" shape, just for the TLS layer instead of the
+    /// WebSocket upgrade above it.
+    pub fn accept(&self, stream: TcpStream) -> Result<TlsStream, TlsError> {
+        let ssl = unsafe { SSL_new(self.ctx) };
+        if ssl.is_null() {
+            return Err(TlsError::Handshake("SSL_new failed".to_string()));
+        }
+        if unsafe { SSL_set_fd(ssl, stream.as_raw_fd()) } != 1 {
+            unsafe { SSL_free(ssl) };
+            return Err(TlsError::Handshake("SSL_set_fd failed".to_string()));
+        }
+        let result = unsafe { SSL_accept(ssl) };
+        if result != 1 {
+            let code = unsafe { SSL_get_error(ssl, result) };
+            unsafe { SSL_free(ssl) };
+            return Err(TlsError::Handshake(format!("SSL_accept failed (SSL_get_error = {code})")));
+        }
+        if self.mtls && unsafe { SSL_get_verify_result(ssl) } != X509_V_OK {
+            unsafe { SSL_free(ssl) };
+            return Err(TlsError::Handshake("client certificate failed verification".to_string()));
+        }
+        Ok(TlsStream { ssl, _stream: stream })
+    }
+}
+
+impl Drop for TlsAcceptor {
+    fn drop(&mut self) {
+        unsafe { SSL_CTX_free(self.ctx) };
+    }
+}
+
+/// A completed TLS connection. `_stream` is kept alive (never read or
+/// written directly — `SSL_read`/`SSL_write` operate on the fd it owns)
+/// purely so the socket isn't closed out from under the still-live `SSL*`
+/// when this is dropped.
+pub struct TlsStream {
+    ssl: *mut c_void,
+    _stream: TcpStream,
+}
+
+impl std::io::Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = unsafe { SSL_read(self.ssl, buf.as_mut_ptr() as *mut c_void, buf.len() as c_int) };
+        if n <= 0 {
+            // Only a genuine `close_notify` (`SSL_ERROR_ZERO_RETURN`) is a
+            // real end of stream. Anything else — a protocol error, or the
+            // connection just dying with no alert at all — must not be
+            // reported as `Ok(0)`, or a truncated response reads as a
+            // complete one (the classic TLS truncation attack).
+            let code = unsafe { SSL_get_error(self.ssl, n) };
+            if code == SSL_ERROR_ZERO_RETURN {
+                return Ok(0);
+            }
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("SSL_read failed (SSL_get_error = {code})")));
+        }
+        Ok(n as usize)
+    }
+}
+
+impl std::io::Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = unsafe { SSL_write(self.ssl, buf.as_ptr() as *const c_void, buf.len() as c_int) };
+        if n <= 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, "SSL_write failed"));
+        }
+        Ok(n as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for TlsStream {
+    fn drop(&mut self) {
+        unsafe { SSL_free(self.ssl) };
+    }
+}
+
+unsafe impl Send for TlsStream {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::process::Command;
+
+    /// Generates a throwaway self-signed cert/key pair under the system
+    /// temp dir via the `openssl` CLI — this tree already shells out to a
+    /// system tool rather than reimplementing one when std can't do the
+    /// job itself (see `health::check_disk_space`'s `df` subprocess), and
+    /// authoring a fresh RSA key + self-signed X.509 cert by hand is
+    /// squarely the kind of cryptography this module's own doc comment
+    /// says this tree doesn't attempt.
+    fn generate_self_signed_cert(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir = std::env::temp_dir();
+        let cert = dir.join(format!("ai-server-tls-test-{name}-cert.pem"));
+        let key = dir.join(format!("ai-server-tls-test-{name}-key.pem"));
+        let status = Command::new("openssl")
+            .args([
+                "req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", "1", "-subj", "/CN=localhost",
+                "-keyout", key.to_str().unwrap(), "-out", cert.to_str().unwrap(),
+            ])
+            .status()
+            .expect("failed to run openssl to generate a test certificate");
+        assert!(status.success(), "openssl req failed to generate a self-signed test certificate");
+        (cert, key)
+    }
+
+    #[test]
+    fn new_rejects_a_missing_cert_file() {
+        let (_cert, key) = generate_self_signed_cert("missing-cert");
+        let result = TlsAcceptor::new(TlsConfig {
+            cert_path: Path::new("/nonexistent/does-not-exist.pem"),
+            key_path: &key,
+            client_ca_path: None,
+        });
+        assert!(matches!(result, Err(TlsError::Config(_))));
+    }
+
+    #[test]
+    fn client_and_server_complete_a_real_tls_handshake() {
+        let (cert, key) = generate_self_signed_cert("handshake");
+        let acceptor = TlsAcceptor::new(TlsConfig { cert_path: &cert, key_path: &key, client_ca_path: None }).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut tls = acceptor.accept(stream).unwrap();
+            let mut buf = [0u8; 5];
+            tls.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"hello");
+            tls.write_all(b"world").unwrap();
+        });
+
+        // No TLS client in this tree yet (see this module's doc comment),
+        // so the test drives the other side with the system `openssl
+        // s_client`, the same "shell out rather than hand-roll" move
+        // `generate_self_signed_cert` makes.
+        let mut client = Command::new("openssl")
+            .args(["s_client", "-connect", &addr.to_string(), "-quiet"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to run openssl s_client");
+        client.stdin.take().unwrap().write_all(b"hello").unwrap();
+        let mut response = [0u8; 5];
+        client.stdout.take().unwrap().read_exact(&mut response).unwrap();
+        assert_eq!(&response, b"world");
+
+        server.join().unwrap();
+        client.wait().unwrap();
+    }
+
+    #[test]
+    fn mtls_rejects_a_client_with_no_certificate() {
+        let (cert, key) = generate_self_signed_cert("mtls-server");
+        let (client_ca, _client_ca_key) = generate_self_signed_cert("mtls-client-ca");
+        let acceptor = TlsAcceptor::new(TlsConfig { cert_path: &cert, key_path: &key, client_ca_path: Some(&client_ca) }).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            acceptor.accept(stream)
+        });
+
+        // A plain `openssl s_client` presents no client certificate, which
+        // `mtls`'s `SSL_VERIFY_FAIL_IF_NO_PEER_CERT` must reject outright.
+        let mut client = Command::new("openssl")
+            .args(["s_client", "-connect", &addr.to_string(), "-quiet"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .expect("failed to run openssl s_client");
+
+        let result = server.join().unwrap();
+        assert!(matches!(result, Err(TlsError::Handshake(_))));
+        drop(client.stdin.take());
+        let _ = client.wait();
+    }
+
+    #[test]
+    fn read_returns_an_error_when_the_connection_dies_without_a_close_notify() {
+        let (cert, key) = generate_self_signed_cert("truncation");
+        let acceptor = TlsAcceptor::new(TlsConfig { cert_path: &cert, key_path: &key, client_ca_path: None }).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut tls = acceptor.accept(stream).unwrap();
+            let mut buf = [0u8; 1];
+            tls.read(&mut buf)
+        });
+
+        // Killing the client mid-handshake-completion severs the TCP
+        // connection with no `close_notify` alert at all — the case a
+        // clean `Ok(0)` must not be returned for.
+        let mut client = Command::new("openssl")
+            .args(["s_client", "-connect", &addr.to_string(), "-quiet"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .expect("failed to run openssl s_client");
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        client.kill().ok();
+        client.wait().ok();
+
+        assert!(server.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Byte-pair-encoding tokenizer, loaded from a GGUF file's
+//! `tokenizer.ggml.tokens` / `tokenizer.ggml.merges` metadata arrays
+//! (see `gguf.rs`) rather than a separate `tokenizer.json`, matching how
+//! llama.cpp bundles vocab and merges into the model file itself.
+
+use crate::gguf::{GgufModel, GgufValue};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct BpeTokenizer {
+    token_to_id: HashMap<String, u32>,
+    id_to_token: Vec<String>,
+    /// Merge rank by `(left, right)` pair; lower rank merges first, same as
+    /// the order merges appear in the training file.
+    merge_ranks: HashMap<(String, String), usize>,
+    unknown_id: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenizerError {
+    MissingVocab,
+}
+
+impl BpeTokenizer {
+    /// Builds a tokenizer from a parsed GGUF model's metadata. Fails only
+    /// if `tokenizer.ggml.tokens` is absent — a model with no merges list
+    /// still tokenizes fine, just falling back to per-character tokens.
+    pub fn from_gguf(model: &GgufModel) -> Result<BpeTokenizer, TokenizerError> {
+        let tokens = model
+            .metadata
+            .get("tokenizer.ggml.tokens")
+            .and_then(as_string_array)
+            .ok_or(TokenizerError::MissingVocab)?;
+
+        let mut token_to_id = HashMap::with_capacity(tokens.len());
+        for (id, token) in tokens.iter().enumerate() {
+            token_to_id.insert(token.clone(), id as u32);
+        }
+
+        let merge_ranks = model
+            .metadata
+            .get("tokenizer.ggml.merges")
+            .and_then(as_string_array)
+            .map(|merges| {
+                merges
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(rank, entry)| {
+                        let (left, right) = entry.split_once(' ')?;
+                        Some(((left.to_string(), right.to_string()), rank))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(BpeTokenizer {
+            unknown_id: token_to_id.get("<unk>").copied().unwrap_or(0),
+            id_to_token: tokens,
+            token_to_id,
+            merge_ranks,
+        })
+    }
+
+    /// Encodes `text` word by word: each word starts as one token per
+    /// character, then the lowest-ranked adjacent pair is merged
+    /// repeatedly until no known merge applies, per the standard BPE
+    /// algorithm.
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut ids = Vec::new();
+        for word in text.split_whitespace() {
+            let mut symbols: Vec<String> = word.chars().map(String::from).collect();
+            loop {
+                let best = symbols
+                    .windows(2)
+                    .enumerate()
+                    .filter_map(|(i, pair)| {
+                        self.merge_ranks
+                            .get(&(pair[0].clone(), pair[1].clone()))
+                            .map(|&rank| (rank, i))
+                    })
+                    .min_by_key(|&(rank, _)| rank);
+
+                let Some((_, i)) = best else { break };
+                let merged = format!("{}{}", symbols[i], symbols[i + 1]);
+                symbols.splice(i..i + 2, [merged]);
+            }
+            for symbol in symbols {
+                ids.push(self.token_to_id.get(&symbol).copied().unwrap_or(self.unknown_id));
+            }
+        }
+        ids
+    }
+
+    /// Concatenates each id's token text with a space between words. Real
+    /// BPE vocabularies encode the word-boundary marker (e.g. `Ġ`) inside
+    /// the token text itself; this decoder just passes tokens through
+    /// as-is, leaving marker handling to whichever vocab format is loaded.
+    pub fn decode(&self, ids: &[u32]) -> String {
+        ids.iter()
+            .map(|&id| self.id_to_token.get(id as usize).map(String::as_str).unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    pub fn vocab_size(&self) -> usize {
+        self.id_to_token.len()
+    }
+}
+
+fn as_string_array(value: &GgufValue) -> Option<Vec<String>> {
+    match value {
+        GgufValue::Array(items) => items.iter().map(|v| v.as_str().map(str::to_string)).collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn model_with(tokens: Vec<&str>, merges: Vec<&str>) -> GgufModel {
+        let mut metadata = BTreeMap::new();
+        metadata.insert(
+            "tokenizer.ggml.tokens".to_string(),
+            GgufValue::Array(tokens.into_iter().map(|t| GgufValue::String(t.to_string())).collect()),
+        );
+        metadata.insert(
+            "tokenizer.ggml.merges".to_string(),
+            GgufValue::Array(merges.into_iter().map(|m| GgufValue::String(m.to_string())).collect()),
+        );
+        GgufModel { version: 3, metadata, tensors: Vec::new() }
+    }
+
+    #[test]
+    fn encode_merges_pairs_in_rank_order() {
+        // "ab" should merge before "bc" merges with it, since "a b" ranks first.
+        let model = model_with(vec!["a", "b", "c", "ab", "abc"], vec!["a b", "ab c"]);
+        let tokenizer = BpeTokenizer::from_gguf(&model).unwrap();
+        assert_eq!(tokenizer.encode("abc"), vec![4]); // fully merged to "abc"
+    }
+
+    #[test]
+    fn encode_falls_back_to_unknown_for_unseen_characters() {
+        let model = model_with(vec!["<unk>", "a"], vec![]);
+        let tokenizer = BpeTokenizer::from_gguf(&model).unwrap();
+        assert_eq!(tokenizer.encode("z"), vec![0]);
+    }
+
+    #[test]
+    fn decode_concatenates_token_text() {
+        let model = model_with(vec!["he", "llo"], vec![]);
+        let tokenizer = BpeTokenizer::from_gguf(&model).unwrap();
+        assert_eq!(tokenizer.decode(&[0, 1]), "hello");
+    }
+
+    #[test]
+    fn from_gguf_fails_without_a_vocab() {
+        let model = GgufModel { version: 3, metadata: BTreeMap::new(), tensors: Vec::new() };
+        assert_eq!(BpeTokenizer::from_gguf(&model).unwrap_err(), TokenizerError::MissingVocab);
+    }
+}
@@ -0,0 +1,247 @@
+//! Incremental parser for a streaming tool call: turns the raw completion
+//! text generated token-by-token for this tree's grammar-checked
+//! `{"arguments": {...}, "name": "..."}` shape into OpenAI-style
+//! `tool_calls[].function.arguments` deltas as the text arrives, instead
+//! of making a streaming client wait for the whole completion the way
+//! `tool_calls::parse_tool_call` requires. `"arguments"` always precedes
+//! `"name"` in a grammar-matched completion because
+//! `constraints::Grammar::from_json_schema` walks a schema's `properties`
+//! in `BTreeMap` order, same as everywhere else this tree serializes a
+//! `Json::Object`.
+//!
+//! `arguments_chunk` is a raw substring of the completion, not a
+//! re-serialized one: `Json::Object`'s `BTreeMap` backing means
+//! re-stringifying a partially-parsed object can reorder keys that
+//! already went out (`{"zebra":.. ` could become `{"apple":..,"zebra":..`
+//! once a second key arrives), so this tracks byte offsets into the raw
+//! text instead of re-rendering anything. The same reasoning is why
+//! `"name"` is only reported once its closing quote has actually arrived
+//! in the raw text, rather than by parsing whatever's buffered so far and
+//! seeing what comes back.
+
+use crate::json::Json;
+use crate::tool_calls::ToolCall;
+
+/// What's newly available to send to the client after one
+/// [`ToolCallStreamParser::feed`] call. `name` is `Some` exactly once, the
+/// first time the `"name"` field becomes parseable; `arguments_chunk` is
+/// whatever suffix of the `"arguments"` value's raw text wasn't already
+/// returned by an earlier call (empty when there's nothing new yet).
+#[derive(Debug, Default, PartialEq)]
+pub struct ToolCallDelta {
+    pub name: Option<String>,
+    pub arguments_chunk: String,
+}
+
+/// Incremental parser for one streaming tool call. Feed it raw completion
+/// text as it arrives; [`finish`](Self::finish) validates the whole thing
+/// once generation ends, the same way `tool_calls::parse_tool_call` does
+/// for the buffered path.
+#[derive(Default)]
+pub struct ToolCallStreamParser {
+    buffer: String,
+    arguments_emitted: usize,
+    name_sent: bool,
+}
+
+/// Scans `buffer` for `key`'s value (`key` including its surrounding
+/// quotes, e.g. `"\"arguments\""`), returning the byte range of the value
+/// text found so far and whether that value is complete (its closing
+/// bracket/quote/delimiter has arrived). `None` before the key itself has
+/// fully arrived.
+fn locate_value(buffer: &str, key: &str) -> Option<(usize, usize, bool)> {
+    let key_pos = buffer.find(key)?;
+    let bytes = buffer.as_bytes();
+    let mut i = key_pos + key.len();
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    if i >= bytes.len() || bytes[i] != b':' {
+        return None;
+    }
+    i += 1;
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    let start = i;
+    if start >= bytes.len() {
+        return None;
+    }
+
+    match bytes[start] {
+        b'{' | b'[' => {
+            let mut depth = 0i32;
+            let mut in_string = false;
+            let mut escaped = false;
+            let mut j = start;
+            while j < bytes.len() {
+                let b = bytes[j];
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if b == b'\\' {
+                        escaped = true;
+                    } else if b == b'"' {
+                        in_string = false;
+                    }
+                } else {
+                    match b {
+                        b'"' => in_string = true,
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Some((start, j + 1, true));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                j += 1;
+            }
+            Some((start, bytes.len(), false))
+        }
+        b'"' => {
+            let mut escaped = false;
+            let mut j = start + 1;
+            while j < bytes.len() {
+                let b = bytes[j];
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    return Some((start, j + 1, true));
+                }
+                j += 1;
+            }
+            Some((start, bytes.len(), false))
+        }
+        _ => {
+            let mut j = start;
+            while j < bytes.len() && !matches!(bytes[j], b',' | b'}' | b']') && !bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            let complete = j < bytes.len();
+            Some((start, j, complete))
+        }
+    }
+}
+
+impl ToolCallStreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `chunk` to the buffered completion and returns whatever
+    /// `name`/`arguments` text became newly available as a result.
+    pub fn feed(&mut self, chunk: &str) -> ToolCallDelta {
+        self.buffer.push_str(chunk);
+        let mut delta = ToolCallDelta::default();
+
+        if let Some((start, end, _complete)) = locate_value(&self.buffer, "\"arguments\"") {
+            let already_emitted_to = start + self.arguments_emitted;
+            if end > already_emitted_to {
+                let new_text = &self.buffer[already_emitted_to..end];
+                delta.arguments_chunk = new_text.to_string();
+                self.arguments_emitted += new_text.len();
+            }
+        }
+
+        // `"name"` only becomes available once its closing quote has
+        // actually arrived — unlike `Json::parse_lenient`, which would
+        // happily close an unterminated string early and hand back a
+        // truncated name as if it were the real one.
+        if !self.name_sent {
+            if let Some((start, end, true)) = locate_value(&self.buffer, "\"name\"") {
+                if let Ok(Json::String(name)) = Json::parse(&self.buffer[start..end]) {
+                    delta.name = Some(name);
+                    self.name_sent = true;
+                }
+            }
+        }
+
+        delta
+    }
+
+    /// Parses the full buffered completion as a finished tool call. `Err`
+    /// the same way `tool_calls::parse_tool_call` would for output that
+    /// isn't valid JSON shaped like `{"arguments", "name"}`.
+    pub fn finish(&self) -> Result<ToolCall, &'static str> {
+        let parsed = Json::parse(&self.buffer).map_err(|_| "tool call output was not valid JSON")?;
+        let name = parsed.get("name").and_then(Json::as_str).ok_or("tool call output missing \"name\"")?.to_string();
+        let arguments = parsed.get("arguments").cloned().unwrap_or(Json::Null);
+        Ok(ToolCall { name, arguments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_never_reports_a_partially_arrived_name() {
+        let mut parser = ToolCallStreamParser::new();
+        let delta = parser.feed(r#"{"arguments": {}, "name": "get_weath"#);
+        assert_eq!(delta.name, None);
+        let delta = parser.feed(r#"er"}"#);
+        assert_eq!(delta.name, Some("get_weather".to_string()));
+    }
+
+    #[test]
+    fn feed_emits_no_delta_before_the_arguments_key_arrives() {
+        let mut parser = ToolCallStreamParser::new();
+        let delta = parser.feed(r#"{"argum"#);
+        assert_eq!(delta, ToolCallDelta::default());
+    }
+
+    #[test]
+    fn feed_streams_an_object_arguments_value_incrementally() {
+        let mut parser = ToolCallStreamParser::new();
+        let first = parser.feed(r#"{"arguments": {"city": "Bos"#);
+        assert_eq!(first.arguments_chunk, r#"{"city": "Bos"#);
+        assert_eq!(first.name, None);
+
+        let second = parser.feed(r#"ton"}, "name": "get_weath"#);
+        assert_eq!(second.arguments_chunk, r#"ton"}"#);
+        assert_eq!(second.name, None, "the name string hasn't closed yet");
+
+        let third = parser.feed(r#"er"}"#);
+        assert_eq!(third.arguments_chunk, "");
+        assert_eq!(third.name, Some("get_weather".to_string()));
+    }
+
+    #[test]
+    fn feed_does_not_resend_an_already_emitted_prefix() {
+        let mut parser = ToolCallStreamParser::new();
+        parser.feed(r#"{"arguments": {"a": 1"#);
+        let delta = parser.feed(r#", "b": 2}, "name": "f"}"#);
+        assert_eq!(delta.arguments_chunk, r#", "b": 2}"#);
+    }
+
+    #[test]
+    fn feed_handles_a_string_arguments_value() {
+        let mut parser = ToolCallStreamParser::new();
+        let first = parser.feed(r#"{"arguments": "raw ar"#);
+        assert_eq!(first.arguments_chunk, r#""raw ar"#);
+        let second = parser.feed(r#"gs", "name": "f"}"#);
+        assert_eq!(second.arguments_chunk, r#"gs""#);
+        assert_eq!(second.name, Some("f".to_string()));
+    }
+
+    #[test]
+    fn finish_parses_the_full_buffered_completion() {
+        let mut parser = ToolCallStreamParser::new();
+        parser.feed(r#"{"arguments": {"city": "Boston"}, "name": "get_weather"}"#);
+        let call = parser.finish().unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.arguments.get("city").and_then(Json::as_str), Some("Boston"));
+    }
+
+    #[test]
+    fn finish_errors_on_a_completion_that_never_became_valid_json() {
+        let mut parser = ToolCallStreamParser::new();
+        parser.feed("not json at all");
+        assert!(parser.finish().is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! OpenAI-style function/tool calling for the chat API: parses a request's
+//! `tools`/`tool_choice` fields, folds the tool definitions into the prompt
+//! text, and parses a finished completion back into a structured tool call
+//! when it matches the compiled JSON grammar for the chosen function.
+//!
+//! There's no per-model chat template engine in this tree yet to place
+//! tool definitions in whatever section a given model was fine-tuned to
+//! expect (a Llama-3 tool-use prompt looks nothing like a Mistral one) —
+//! that's `chat_template.rs`'s eventual job. Until it lands, tool
+//! definitions are appended to the prompt as a plain-text block, same
+//! spirit as `grammar_from_response_format` in `server.rs` validating a
+//! finished completion instead of masking tokens during generation: a
+//! reasonable stand-in that becomes exact once real per-model formatting
+//! exists.
+
+use crate::constraints::{ConstraintError, Grammar};
+use crate::json::{Json, ObjectBuilder};
+
+/// One entry from the request's `tools` array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Json,
+}
+
+/// The request's `tool_choice`, resolved against the `tools` it was sent
+/// alongside.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolChoice {
+    Auto,
+    None,
+    Function(String),
+}
+
+/// Parses the OpenAI `tools` array: `[{"type": "function", "function":
+/// {"name", "description"?, "parameters"?}}]`. Missing `parameters`
+/// defaults to an empty object schema, matching an OpenAI client that
+/// declares a no-argument function.
+pub fn parse_tools(body: &Json) -> Result<Vec<ToolDefinition>, &'static str> {
+    let Some(tools) = body.get("tools") else { return Ok(Vec::new()) };
+    tools
+        .as_array()
+        .ok_or("\"tools\" must be an array")?
+        .iter()
+        .map(|tool| {
+            if tool.get("type").and_then(Json::as_str) != Some("function") {
+                return Err("each entry in \"tools\" must have \"type\": \"function\"");
+            }
+            let function = tool.get("function").ok_or("each tool must have a \"function\" object")?;
+            let name = function
+                .get("name")
+                .and_then(Json::as_str)
+                .ok_or("\"function.name\" must be a string")?
+                .to_string();
+            let description = function.get("description").and_then(Json::as_str).map(str::to_string);
+            let parameters = function
+                .get("parameters")
+                .cloned()
+                .unwrap_or_else(|| ObjectBuilder::new().set("type", Json::String("object".to_string())).build());
+            Ok(ToolDefinition { name, description, parameters })
+        })
+        .collect()
+}
+
+/// Resolves `tool_choice` against the parsed `tools`. Defaults to `Auto`
+/// when tools were supplied and `None` when they weren't, matching the
+/// OpenAI default.
+pub fn parse_tool_choice(body: &Json, tools: &[ToolDefinition]) -> Result<ToolChoice, &'static str> {
+    match body.get("tool_choice") {
+        None => Ok(if tools.is_empty() { ToolChoice::None } else { ToolChoice::Auto }),
+        Some(Json::String(s)) if s == "auto" => Ok(ToolChoice::Auto),
+        Some(Json::String(s)) if s == "none" => Ok(ToolChoice::None),
+        Some(choice @ Json::Object(_)) => {
+            if choice.get("type").and_then(Json::as_str) != Some("function") {
+                return Err("\"tool_choice.type\" must be \"function\"");
+            }
+            let name = choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(Json::as_str)
+                .ok_or("\"tool_choice.function.name\" must be a string")?;
+            if !tools.iter().any(|t| t.name == name) {
+                return Err("\"tool_choice\" names a function not present in \"tools\"");
+            }
+            Ok(ToolChoice::Function(name.to_string()))
+        }
+        _ => Err("\"tool_choice\" must be \"auto\", \"none\", or a function selector object"),
+    }
+}
+
+/// Appends a plain-text block describing `tools` to `prompt`. A no-op when
+/// there are no tools to describe.
+pub fn append_tool_definitions(prompt: &str, tools: &[ToolDefinition]) -> String {
+    if tools.is_empty() {
+        return prompt.to_string();
+    }
+    let mut block = String::from("\n\nAvailable tools:\n");
+    for tool in tools {
+        let description = tool.description.as_deref().unwrap_or("");
+        block.push_str(&format!("- {}({}): {}\n", tool.name, tool.parameters.to_string(), description));
+    }
+    format!("{prompt}{block}")
+}
+
+/// Compiles the JSON grammar a tool call for `tool` must match:
+/// `{"arguments": <tool.parameters>, "name": "<tool.name>"}`. Field order
+/// follows `Json::Object`'s `BTreeMap` serialization order (alphabetical),
+/// same as every other grammar-checked completion in this tree.
+fn tool_call_grammar(tool: &ToolDefinition) -> Result<Grammar, ConstraintError> {
+    let properties = ObjectBuilder::new()
+        .set(
+            "name",
+            ObjectBuilder::new().set("enum", Json::Array(vec![Json::String(tool.name.clone())])).build(),
+        )
+        .set("arguments", tool.parameters.clone())
+        .build();
+    let schema = ObjectBuilder::new()
+        .set("type", Json::String("object".to_string()))
+        .set("properties", properties)
+        .build();
+    Grammar::from_json_schema(&schema)
+}
+
+/// One structured tool call the backend's completion resolved to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Json,
+}
+
+/// Tries to read `completion` as a call to one of `tools`, honoring
+/// `choice`. `Ok(None)` means the completion should be treated as ordinary
+/// assistant content instead — always true for [`ToolChoice::None`], and
+/// true for [`ToolChoice::Auto`] whenever the completion doesn't match any
+/// tool's schema. [`ToolChoice::Function`] is stricter: the caller asked
+/// for that function specifically, so a non-matching completion is an
+/// error rather than silently falling back to plain content.
+pub fn parse_tool_call(completion: &str, tools: &[ToolDefinition], choice: &ToolChoice) -> Result<Option<ToolCall>, &'static str> {
+    let candidates: Vec<&ToolDefinition> = match choice {
+        ToolChoice::None => return Ok(None),
+        ToolChoice::Function(name) => tools.iter().filter(|t| &t.name == name).collect(),
+        ToolChoice::Auto => tools.iter().collect(),
+    };
+    for tool in candidates {
+        let grammar = tool_call_grammar(tool).map_err(|_| "unsupported tool \"parameters\" schema")?;
+        if !grammar.matches(completion) {
+            continue;
+        }
+        let parsed = Json::parse(completion).map_err(|_| "tool call output was not valid JSON")?;
+        let arguments = parsed.get("arguments").cloned().unwrap_or(Json::Null);
+        return Ok(Some(ToolCall { name: tool.name.clone(), arguments }));
+    }
+    if matches!(choice, ToolChoice::Function(_)) {
+        return Err("backend output did not match the requested tool's schema");
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather_tool() -> ToolDefinition {
+        ToolDefinition {
+            name: "get_weather".to_string(),
+            description: Some("Look up the weather".to_string()),
+            parameters: Json::parse(r#"{"type": "object", "properties": {"city": {"type": "string"}}}"#).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_tools_reads_name_description_and_parameters() {
+        let body = Json::parse(
+            r#"{"tools": [{"type": "function", "function": {"name": "get_weather", "description": "Look up the weather", "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}}}]}"#,
+        )
+        .unwrap();
+        let tools = parse_tools(&body).unwrap();
+        assert_eq!(tools, vec![weather_tool()]);
+    }
+
+    #[test]
+    fn parse_tools_rejects_a_non_function_type() {
+        let body = Json::parse(r#"{"tools": [{"type": "retrieval", "function": {"name": "x"}}]}"#).unwrap();
+        assert!(parse_tools(&body).is_err());
+    }
+
+    #[test]
+    fn parse_tool_choice_defaults_to_auto_when_tools_are_present() {
+        let body = Json::parse("{}").unwrap();
+        assert_eq!(parse_tool_choice(&body, &[weather_tool()]).unwrap(), ToolChoice::Auto);
+    }
+
+    #[test]
+    fn parse_tool_choice_rejects_a_function_not_in_tools() {
+        let body = Json::parse(r#"{"tool_choice": {"type": "function", "function": {"name": "unknown"}}}"#).unwrap();
+        assert!(parse_tool_choice(&body, &[weather_tool()]).is_err());
+    }
+
+    #[test]
+    fn parse_tool_call_extracts_matching_arguments() {
+        let tool = weather_tool();
+        let completion = r#"{"arguments":{"city":"Boston"},"name":"get_weather"}"#;
+        let call = parse_tool_call(completion, &[tool], &ToolChoice::Auto).unwrap().unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.arguments.get("city").and_then(Json::as_str), Some("Boston"));
+    }
+
+    #[test]
+    fn parse_tool_call_falls_back_to_plain_content_in_auto_mode() {
+        let tool = weather_tool();
+        let result = parse_tool_call("just some plain text", &[tool], &ToolChoice::Auto).unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn parse_tool_call_errors_when_a_forced_function_does_not_match() {
+        let tool = weather_tool();
+        let choice = ToolChoice::Function("get_weather".to_string());
+        assert!(parse_tool_call("not json at all", &[tool], &choice).is_err());
+    }
+
+    #[test]
+    fn parse_tool_call_with_none_choice_never_parses() {
+        let tool = weather_tool();
+        let completion = r#"{"arguments":{"city":"Boston"},"name":"get_weather"}"#;
+        assert_eq!(parse_tool_call(completion, &[tool], &ToolChoice::None).unwrap(), None);
+    }
+}
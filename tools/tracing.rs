@@ -0,0 +1,259 @@
+//! Request-path tracing: spans, W3C `traceparent` propagation, and an
+//! OTLP/HTTP+JSON exporter. OTLP is usually shipped over gRPC with
+//! protobuf, but the spec also defines a JSON body over plain HTTP POST to
+//! `/v1/traces` — that variant needs no protobuf/prost/tonic, so it's the
+//! one implemented here, the same trade this tree already made for
+//! `downloader.rs` (plain HTTP, no TLS) and `rpc.rs` (JSON framing instead
+//! of protobuf).
+//!
+//! `export_otlp_http` connects and POSTs on every call; there's
+//! deliberately no batching queue or background flush thread here, since
+//! there's nowhere yet to read a collector endpoint from — `server.rs`
+//! only calls it once a `config` module exists to supply one (see the
+//! backlog entry that follows this).
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A finished span ready to export. IDs are hex-encoded per the OTLP JSON
+/// schema (`traceId`/`spanId` are lowercase hex strings, not raw bytes).
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub name: String,
+    pub start_unix_nanos: u128,
+    pub end_unix_nanos: u128,
+    pub attributes: Vec<(String, String)>,
+}
+
+/// The subset of a W3C `traceparent` header this server needs to
+/// propagate: which trace an incoming request belongs to, and which span
+/// to record as this request's parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanContext {
+    pub trace_id: [u8; 16],
+    pub parent_span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+/// Parses a `traceparent` header value: `{version}-{trace-id}-{parent-id}-{flags}`,
+/// e.g. `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`. Only
+/// version `00` (the only version defined so far) is accepted.
+pub fn parse_traceparent(header: &str) -> Option<SpanContext> {
+    let parts: Vec<&str> = header.trim().split('-').collect();
+    let [version, trace_id, parent_id, flags] = parts[..] else { return None };
+    if version != "00" || trace_id.len() != 32 || parent_id.len() != 16 || flags.len() != 2 {
+        return None;
+    }
+    let trace_id = hex_decode_16(trace_id)?;
+    let parent_span_id = hex_decode_8(parent_id)?;
+    if trace_id == [0u8; 16] || parent_span_id == [0u8; 8] {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some(SpanContext { trace_id, parent_span_id, sampled: flags & 0x01 == 1 })
+}
+
+/// Formats a `traceparent` header value for outgoing propagation.
+pub fn format_traceparent(trace_id: [u8; 16], span_id: [u8; 8], sampled: bool) -> String {
+    format!("00-{}-{}-{:02x}", hex_encode(&trace_id), hex_encode(&span_id), if sampled { 1 } else { 0 })
+}
+
+/// Monotonic counter mixed into new span/trace IDs. There's no RNG in this
+/// tree (see `vectorstore.rs`'s `random_layer_count` for the same
+/// deterministic-hash trade) so IDs are derived from a process-local
+/// counter run through a fixed-point hash rather than true randomness —
+/// unique per process, not cryptographically unpredictable.
+static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+fn next_id_bytes(width: usize) -> Vec<u8> {
+    let counter = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut hash = counter.wrapping_mul(0x9E3779B97F4A7C15).wrapping_add(0xBF58476D1CE4E5B9);
+    let mut out = Vec::with_capacity(width);
+    for _ in 0..width {
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xFF51AFD7ED558CCD);
+        out.push((hash & 0xFF) as u8);
+    }
+    out
+}
+
+pub fn new_trace_id() -> [u8; 16] {
+    next_id_bytes(16).try_into().unwrap()
+}
+
+pub fn new_span_id() -> [u8; 8] {
+    next_id_bytes(8).try_into().unwrap()
+}
+
+impl Span {
+    /// Starts a new span, either as the root of a new trace or as a child
+    /// of `parent` (an incoming request's propagated `traceparent`).
+    pub fn start(name: &str, parent: Option<&SpanContext>) -> Span {
+        let trace_id = parent.map(|p| p.trace_id).unwrap_or_else(new_trace_id);
+        let now = unix_nanos_now();
+        Span {
+            trace_id,
+            span_id: new_span_id(),
+            parent_span_id: parent.map(|p| p.parent_span_id),
+            name: name.to_string(),
+            start_unix_nanos: now,
+            end_unix_nanos: now,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn set_attribute(&mut self, key: &str, value: &str) {
+        self.attributes.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn end(&mut self) {
+        self.end_unix_nanos = unix_nanos_now();
+    }
+}
+
+fn unix_nanos_now() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos()
+}
+
+/// Renders `spans` as an OTLP `ExportTraceServiceRequest` JSON body (the
+/// HTTP+JSON encoding of the OTLP wire format), all sharing one resource
+/// tagged with `service_name`.
+pub fn to_otlp_json(spans: &[Span], service_name: &str) -> String {
+    let span_entries: Vec<String> = spans.iter().map(render_span_json).collect();
+    format!(
+        r#"{{"resourceSpans":[{{"resource":{{"attributes":[{{"key":"service.name","value":{{"stringValue":"{service_name}"}}}}]}},"scopeSpans":[{{"spans":[{}]}}]}}]}}"#,
+        span_entries.join(",")
+    )
+}
+
+fn render_span_json(span: &Span) -> String {
+    let parent = span
+        .parent_span_id
+        .map(|id| format!(r#","parentSpanId":"{}""#, hex_encode(&id)))
+        .unwrap_or_default();
+    let attributes: Vec<String> = span
+        .attributes
+        .iter()
+        .map(|(k, v)| format!(r#"{{"key":"{}","value":{{"stringValue":"{}"}}}}"#, escape(k), escape(v)))
+        .collect();
+    format!(
+        r#"{{"traceId":"{}","spanId":"{}"{},"name":"{}","startTimeUnixNano":"{}","endTimeUnixNano":"{}","attributes":[{}]}}"#,
+        hex_encode(&span.trace_id),
+        hex_encode(&span.span_id),
+        parent,
+        escape(&span.name),
+        span.start_unix_nanos,
+        span.end_unix_nanos,
+        attributes.join(",")
+    )
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(String),
+    NotOk(u16),
+}
+
+/// POSTs `spans` to `{host}:{port}/v1/traces` as OTLP/HTTP+JSON, per the
+/// exporter note above: plain HTTP, no TLS, and no batching — every call
+/// opens a fresh connection.
+pub fn export_otlp_http(host: &str, port: u16, spans: &[Span], service_name: &str) -> Result<(), ExportError> {
+    let body = to_otlp_json(spans, service_name);
+    let mut stream = TcpStream::connect((host, port)).map_err(|e| ExportError::Io(e.to_string()))?;
+    write!(
+        stream,
+        "POST /v1/traces HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .map_err(|e| ExportError::Io(e.to_string()))?;
+    stream.write_all(body.as_bytes()).map_err(|e| ExportError::Io(e.to_string()))?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| ExportError::Io(e.to_string()))?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| ExportError::Io("malformed HTTP response".to_string()))?;
+    if !(200..300).contains(&status) {
+        return Err(ExportError::NotOk(status));
+    }
+    Ok(())
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode_16(s: &str) -> Option<[u8; 16]> {
+    hex_decode(s)?.try_into().ok()
+}
+
+fn hex_decode_8(s: &str) -> Option<[u8; 8]> {
+    hex_decode(s)?.try_into().ok()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_well_formed_traceparent_header() {
+        let ctx = parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(hex_encode(&ctx.trace_id), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex_encode(&ctx.parent_span_id), "00f067aa0ba902b7");
+        assert!(ctx.sampled);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_malformed_headers() {
+        assert!(parse_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("not a traceparent").is_none());
+    }
+
+    #[test]
+    fn format_traceparent_round_trips_through_parse_traceparent() {
+        let trace_id = new_trace_id();
+        let span_id = new_span_id();
+        let header = format_traceparent(trace_id, span_id, true);
+        let parsed = parse_traceparent(&header).unwrap();
+        assert_eq!(parsed.trace_id, trace_id);
+        assert_eq!(parsed.parent_span_id, span_id);
+        assert!(parsed.sampled);
+    }
+
+    #[test]
+    fn child_span_inherits_the_parents_trace_id() {
+        let parent_ctx = SpanContext { trace_id: new_trace_id(), parent_span_id: new_span_id(), sampled: true };
+        let span = Span::start("handle_request", Some(&parent_ctx));
+        assert_eq!(span.trace_id, parent_ctx.trace_id);
+        assert_eq!(span.parent_span_id, Some(parent_ctx.parent_span_id));
+    }
+
+    #[test]
+    fn to_otlp_json_embeds_service_name_and_span_fields() {
+        let mut span = Span::start("root", None);
+        span.set_attribute("http.method", "POST");
+        span.end();
+        let json = to_otlp_json(&[span], "ai-server");
+        assert!(json.contains(r#""stringValue":"ai-server""#));
+        assert!(json.contains(r#""name":"root""#));
+        assert!(json.contains(r#""key":"http.method""#));
+    }
+}
@@ -0,0 +1,80 @@
+//! A single concrete stream type for the HTTP listener: wraps
+//! `std::net::TcpStream` and (on Unix) `std::os::unix::net::UnixStream`
+//! behind one `Read`/`Write` interface, so `http.rs`, `websocket.rs`, and
+//! `server.rs`'s own stream-consuming handlers are written once against
+//! [`Transport`] instead of twice against each concrete type — the same
+//! "one shared interface, several concrete backends" shape
+//! `mmap_loader.rs`'s `WeightSource` uses for `Mapped`/`Buffered` weight
+//! storage.
+//!
+//! No Windows named-pipe variant: that needs either a `winapi`/
+//! `windows-sys` binding to `CreateNamedPipeW` or a crate this tree has no
+//! dependency manager to declare (see `json.rs`'s doc comment on the same
+//! constraint) — out of scope here, same as `hardware.rs` only binding
+//! macOS's `sysctlbyname` and leaving other platforms without it.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+pub enum Transport {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Transport {
+    /// Duplicates the underlying socket, the same way `TcpStream::try_clone`/
+    /// `UnixStream::try_clone` do — used by `websocket::wait_for_disconnect`
+    /// to poll for a close frame on a background thread while the main
+    /// thread keeps writing on the original handle.
+    pub fn try_clone(&self) -> io::Result<Transport> {
+        match self {
+            Transport::Tcp(s) => s.try_clone().map(Transport::Tcp),
+            #[cfg(unix)]
+            Transport::Unix(s) => s.try_clone().map(Transport::Unix),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Transport::Unix(s) => s.flush(),
+        }
+    }
+}
+
+impl From<TcpStream> for Transport {
+    fn from(stream: TcpStream) -> Transport {
+        Transport::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Transport {
+    fn from(stream: UnixStream) -> Transport {
+        Transport::Unix(stream)
+    }
+}
@@ -0,0 +1,88 @@
+//! Text-to-speech synthesis. `SpeechBackend` is the pluggable seam real
+//! engines implement (Piper's ONNX voices to start, per the backlog);
+//! output is always PCM (`audio::PcmAudio`) so `/v1/audio/speech` can
+//! encode it as WAV via `audio::encode_wav`. Opus streaming isn't
+//! implemented for the same reason `audio.rs` doesn't decode it: a real
+//! opus encoder is a nontrivial codec, out of scope for this tree's
+//! no-dependency policy until a pure-Rust one is vendored in.
+
+use crate::audio::PcmAudio;
+
+#[derive(Debug, Clone)]
+pub struct VoiceId(pub String);
+
+pub trait SpeechBackend: Send + Sync {
+    fn voices(&self) -> Vec<VoiceId>;
+    fn synthesize(&self, text: &str, voice: &VoiceId) -> Result<PcmAudio, SpeechError>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpeechError {
+    UnknownVoice(String),
+    EmptyText,
+}
+
+/// Deterministic placeholder backend: generates a sine wave whose duration
+/// scales with the input text's length, so the request/response contract
+/// (voice selection, WAV encoding) can be exercised before a real Piper
+/// runtime is wired in — the TTS analogue of `EchoBackend`.
+pub struct ToneSpeechBackend {
+    voices: Vec<VoiceId>,
+    sample_rate: u32,
+}
+
+impl ToneSpeechBackend {
+    pub fn new(voices: Vec<&str>) -> Self {
+        ToneSpeechBackend { voices: voices.into_iter().map(|v| VoiceId(v.to_string())).collect(), sample_rate: 22050 }
+    }
+}
+
+impl SpeechBackend for ToneSpeechBackend {
+    fn voices(&self) -> Vec<VoiceId> {
+        self.voices.clone()
+    }
+
+    fn synthesize(&self, text: &str, voice: &VoiceId) -> Result<PcmAudio, SpeechError> {
+        if text.is_empty() {
+            return Err(SpeechError::EmptyText);
+        }
+        if !self.voices.iter().any(|v| v.0 == voice.0) {
+            return Err(SpeechError::UnknownVoice(voice.0.clone()));
+        }
+
+        let seconds = (text.split_whitespace().count() as f32 * 0.3).max(0.2);
+        let n_samples = (self.sample_rate as f32 * seconds) as usize;
+        let frequency = 220.0 + (voice.0.bytes().map(u32::from).sum::<u32>() % 200) as f32;
+        let samples = (0..n_samples)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / self.sample_rate as f32).sin() * 0.2)
+            .collect();
+        Ok(PcmAudio { sample_rate: self.sample_rate, channels: 1, samples })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthesize_rejects_unknown_voices() {
+        let backend = ToneSpeechBackend::new(vec!["en-us"]);
+        let err = backend.synthesize("hi", &VoiceId("fr-fr".to_string())).unwrap_err();
+        assert_eq!(err, SpeechError::UnknownVoice("fr-fr".to_string()));
+    }
+
+    #[test]
+    fn synthesize_rejects_empty_text() {
+        let backend = ToneSpeechBackend::new(vec!["en-us"]);
+        assert_eq!(backend.synthesize("", &VoiceId("en-us".to_string())).unwrap_err(), SpeechError::EmptyText);
+    }
+
+    #[test]
+    fn longer_text_produces_longer_audio() {
+        let backend = ToneSpeechBackend::new(vec!["en-us"]);
+        let voice = VoiceId("en-us".to_string());
+        let short = backend.synthesize("hi", &voice).unwrap();
+        let long = backend.synthesize("hi there this is a longer sentence", &voice).unwrap();
+        assert!(long.samples.len() > short.samples.len());
+    }
+}
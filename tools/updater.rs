@@ -0,0 +1,355 @@
+//! `ai-server update`: checks a release manifest, downloads the build for
+//! the running OS/arch, verifies it, and swaps it in for the running
+//! binary with a rollback path — so a non-technical user running this on
+//! a home server never has to rebuild from source.
+//!
+//! Fetches over plain HTTP, the same limitation `downloader.rs`'s module
+//! doc comment already states for model downloads (no TLS implementation
+//! in this tree); a real release manifest is expected to sit behind a
+//! TLS-terminating proxy or plain-HTTP mirror. Checksum verification
+//! reuses `sha256::sha256`/`hex` the same way `registry.rs::verify`
+//! already does for models.
+//!
+//! The swap itself follows this tree's usual "write to a sibling temp
+//! path, verify, then atomically replace" shape (`downloader.rs`'s
+//! `.part` file, `durability::atomic_write`'s `.tmp` file): the new binary
+//! is downloaded to `<exe>.new`, and the running exe is renamed to
+//! `<exe>.previous` — kept, not deleted — before the new one is renamed
+//! into place, so [`rollback`] has something to restore even after the
+//! process running the update has exited and a fresh one has started in
+//! its place.
+//!
+//! The checksum alone only proves the binary matches whatever the
+//! manifest said — and the manifest arrived over the same plain-HTTP
+//! channel, so a MITM able to substitute the binary can substitute the
+//! manifest's `sha256` field right along with it. [`apply_update`]
+//! therefore also requires a detached `minisign` signature over the
+//! downloaded binary, verified against a public key supplied out of band
+//! (a CLI flag, never read from the manifest itself — trusting the
+//! manifest for the key would let the same MITM supply a key that
+//! "verifies" their own binary), the same way `registry.rs::verify`
+//! already does for models via `SignatureCheck`.
+
+use crate::sha256::{hex, sha256};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// This build's own version. There's no `Cargo.toml` (see this tree's
+/// no-dependency-manager policy) to derive `CARGO_PKG_VERSION` from, so
+/// it's a plain constant bumped by hand alongside each release.
+pub const CURRENT_VERSION: &str = "0.1.0";
+
+#[derive(Debug)]
+pub enum UpdateError {
+    Io(String),
+    Http(String),
+    Manifest(String),
+    NoBuildForPlatform { os: &'static str, arch: &'static str },
+    ChecksumMismatch { expected: String, actual: String },
+    SignatureInvalid,
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Io(e) => write!(f, "io error: {e}"),
+            UpdateError::Http(e) => write!(f, "http error: {e}"),
+            UpdateError::Manifest(e) => write!(f, "malformed release manifest: {e}"),
+            UpdateError::NoBuildForPlatform { os, arch } => write!(f, "no build published for {os}/{arch}"),
+            UpdateError::ChecksumMismatch { expected, actual } => write!(f, "checksum mismatch: expected {expected}, got {actual}"),
+            UpdateError::SignatureInvalid => write!(f, "signature verification failed"),
+        }
+    }
+}
+
+impl From<std::io::Error> for UpdateError {
+    fn from(e: std::io::Error) -> Self {
+        UpdateError::Io(e.to_string())
+    }
+}
+
+/// One OS/arch-specific build listed in a [`ReleaseManifest`].
+/// `signature_url` points at a detached `minisign` signature over the
+/// bytes at `url`, checked by [`apply_update`] alongside `sha256`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildEntry {
+    pub os: String,
+    pub arch: String,
+    pub url: String,
+    pub sha256: String,
+    pub signature_url: String,
+}
+
+/// The latest release on a channel, as published at a manifest URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseManifest {
+    pub version: String,
+    pub builds: Vec<BuildEntry>,
+}
+
+/// Parses a manifest of the form:
+/// ```json
+/// {"version": "0.2.0", "builds": [
+///   {"os": "macos", "arch": "aarch64", "url": "http://host/ai-server-0.2.0-macos-aarch64",
+///    "sha256": "...", "signature_url": "http://host/ai-server-0.2.0-macos-aarch64.minisig"}
+/// ]}
+/// ```
+pub fn parse_manifest(text: &str) -> Result<ReleaseManifest, UpdateError> {
+    let parsed = crate::json::Json::parse(text).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+    let version = parsed
+        .get("version")
+        .and_then(crate::json::Json::as_str)
+        .ok_or_else(|| UpdateError::Manifest("missing \"version\"".to_string()))?
+        .to_string();
+    let builds = parsed
+        .get("builds")
+        .and_then(crate::json::Json::as_array)
+        .ok_or_else(|| UpdateError::Manifest("missing \"builds\"".to_string()))?
+        .iter()
+        .filter_map(|b| {
+            Some(BuildEntry {
+                os: b.get("os").and_then(crate::json::Json::as_str)?.to_string(),
+                arch: b.get("arch").and_then(crate::json::Json::as_str)?.to_string(),
+                url: b.get("url").and_then(crate::json::Json::as_str)?.to_string(),
+                sha256: b.get("sha256").and_then(crate::json::Json::as_str)?.to_string(),
+                signature_url: b.get("signature_url").and_then(crate::json::Json::as_str)?.to_string(),
+            })
+        })
+        .collect();
+    Ok(ReleaseManifest { version, builds })
+}
+
+/// Finds the build matching the running process's OS/arch
+/// (`std::env::consts::OS`/`ARCH`), the same pair `diagnostics.rs` reports
+/// under `"os"`/`"arch"`.
+fn build_for_current_platform(manifest: &ReleaseManifest) -> Result<&BuildEntry, UpdateError> {
+    manifest
+        .builds
+        .iter()
+        .find(|b| b.os == std::env::consts::OS && b.arch == std::env::consts::ARCH)
+        .ok_or(UpdateError::NoBuildForPlatform { os: std::env::consts::OS, arch: std::env::consts::ARCH })
+}
+
+/// Fetches a manifest from `manifest_url` (`http://host[:port]/path`) and
+/// returns it if its version differs from `current_version` — not a full
+/// semver comparison (no version-parsing crate in this tree), just an
+/// inequality check, which is enough to tell a user "you're already on
+/// the latest published version" without claiming to know what "newer"
+/// means for two arbitrary version strings.
+pub fn check_for_update(manifest_url: &str, current_version: &str) -> Result<Option<ReleaseManifest>, UpdateError> {
+    let body = http_get(manifest_url)?;
+    let text = String::from_utf8(body).map_err(|e| UpdateError::Manifest(e.to_string()))?;
+    let manifest = parse_manifest(&text)?;
+    if manifest.version == current_version {
+        Ok(None)
+    } else {
+        Ok(Some(manifest))
+    }
+}
+
+/// Downloads the build matching the running platform, verifies its
+/// checksum and `minisign` signature against `public_key_path`, and swaps
+/// it in for `current_exe`. `public_key_path` must come from local
+/// config or a CLI flag, never from the manifest — the manifest is
+/// fetched over the same channel a MITM already controls, so trusting a
+/// key it supplies would let the same attacker "verify" their own
+/// substituted binary. See the module doc comment for the
+/// backup-then-rename shape that makes [`rollback`] possible afterward.
+pub fn apply_update(manifest: &ReleaseManifest, current_exe: &Path, public_key_path: &Path) -> Result<(), UpdateError> {
+    let build = build_for_current_platform(manifest)?;
+    let bytes = http_get(&build.url)?;
+    let actual = hex(&sha256(&bytes));
+    if !actual.eq_ignore_ascii_case(&build.sha256) {
+        return Err(UpdateError::ChecksumMismatch { expected: build.sha256.clone(), actual });
+    }
+
+    let new_path = sibling_path(current_exe, "new");
+    std::fs::write(&new_path, &bytes)?;
+    make_executable(&new_path)?;
+
+    let signature_path = sibling_path(current_exe, "minisig");
+    std::fs::write(&signature_path, http_get(&build.signature_url)?)?;
+    let signature_valid = verify_minisign(&new_path, &signature_path, public_key_path)
+        .map_err(|e| UpdateError::Io(format!("running minisign: {e}")))?;
+    std::fs::remove_file(&signature_path).ok();
+    if !signature_valid {
+        std::fs::remove_file(&new_path).ok();
+        return Err(UpdateError::SignatureInvalid);
+    }
+
+    let previous_path = sibling_path(current_exe, "previous");
+    std::fs::rename(current_exe, &previous_path)?;
+    std::fs::rename(&new_path, current_exe)?;
+    Ok(())
+}
+
+/// Runs `minisign -V` against `file`'s detached signature, returning
+/// whether it verified. Errors (rather than returning `false`) when the
+/// `minisign` binary itself is missing or fails to start, since that
+/// means no check actually happened — the same convention
+/// `registry.rs::verify_minisign` uses for model files.
+fn verify_minisign(file: &Path, signature_path: &Path, public_key_path: &Path) -> std::io::Result<bool> {
+    let output = Command::new("minisign")
+        .arg("-V")
+        .arg("-p")
+        .arg(public_key_path)
+        .arg("-x")
+        .arg(signature_path)
+        .arg("-m")
+        .arg(file)
+        .output()
+        .map_err(|e| std::io::Error::new(e.kind(), format!("running minisign: {e}")))?;
+    Ok(output.status.success())
+}
+
+/// Restores `<exe>.previous`, left behind by the last [`apply_update`],
+/// over `current_exe`. Fails with a plain I/O error (no `<exe>.previous`
+/// to restore) if no update has been applied since the last rollback.
+pub fn rollback(current_exe: &Path) -> Result<(), UpdateError> {
+    let previous_path = sibling_path(current_exe, "previous");
+    std::fs::rename(&previous_path, current_exe)?;
+    Ok(())
+}
+
+fn sibling_path(exe: &Path, extra_extension: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extra_extension);
+    exe.with_file_name(name)
+}
+
+#[cfg(target_os = "windows")]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    std::fs::set_permissions(path, permissions)
+}
+
+/// Issues a plain `GET` against a `http://host[:port]/path` URL and
+/// returns the response body, following none of `downloader.rs`'s
+/// resume/range logic since manifests and binaries are downloaded whole
+/// in one shot here.
+fn http_get(url: &str) -> Result<Vec<u8>, UpdateError> {
+    let without_scheme = url.strip_prefix("http://").ok_or_else(|| UpdateError::Http("only http:// URLs are supported".to_string()))?;
+    let (host, path) = without_scheme.split_once('/').map(|(h, p)| (h, format!("/{p}"))).unwrap_or((without_scheme, "/".to_string()));
+    let mut stream = TcpStream::connect(host).map_err(|e| UpdateError::Http(format!("connect to {host} failed: {e}")))?;
+    let host_header = host.split(':').next().unwrap_or(host);
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {host_header}\r\nConnection: close\r\n\r\n")?;
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    let split_at = raw.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4).ok_or_else(|| UpdateError::Http("malformed HTTP response".to_string()))?;
+    let head = std::str::from_utf8(&raw[..split_at]).map_err(|_| UpdateError::Http("non-UTF-8 response headers".to_string()))?;
+    let status: u16 = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| UpdateError::Http("bad status line".to_string()))?;
+    if status != 200 {
+        return Err(UpdateError::Http(format!("unexpected status {status}")));
+    }
+    Ok(raw[split_at..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest_reads_version_and_builds() {
+        let text = r#"{"version": "0.2.0", "builds": [{"os": "linux", "arch": "x86_64", "url": "http://h/f", "sha256": "abc", "signature_url": "http://h/f.minisig"}]}"#;
+        let manifest = parse_manifest(text).unwrap();
+        assert_eq!(manifest.version, "0.2.0");
+        assert_eq!(manifest.builds.len(), 1);
+        assert_eq!(manifest.builds[0].os, "linux");
+    }
+
+    #[test]
+    fn parse_manifest_rejects_a_missing_version() {
+        assert!(parse_manifest(r#"{"builds": []}"#).is_err());
+    }
+
+    #[test]
+    fn parse_manifest_skips_a_build_entry_missing_a_field() {
+        let text = r#"{"version": "0.2.0", "builds": [{"os": "linux", "arch": "x86_64"}]}"#;
+        assert_eq!(parse_manifest(text).unwrap().builds.len(), 0);
+    }
+
+    #[test]
+    fn parse_manifest_skips_a_build_entry_missing_a_signature_url() {
+        let text = r#"{"version": "0.2.0", "builds": [{"os": "linux", "arch": "x86_64", "url": "http://h/f", "sha256": "abc"}]}"#;
+        assert_eq!(parse_manifest(text).unwrap().builds.len(), 0);
+    }
+
+    #[test]
+    fn build_for_current_platform_finds_a_matching_entry() {
+        let manifest = ReleaseManifest {
+            version: "0.2.0".to_string(),
+            builds: vec![BuildEntry {
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                url: "http://h/f".to_string(),
+                sha256: "abc".to_string(),
+                signature_url: "http://h/f.minisig".to_string(),
+            }],
+        };
+        assert!(build_for_current_platform(&manifest).is_ok());
+    }
+
+    #[test]
+    fn build_for_current_platform_errors_when_no_entry_matches() {
+        let manifest = ReleaseManifest { version: "0.2.0".to_string(), builds: vec![] };
+        assert!(build_for_current_platform(&manifest).is_err());
+    }
+
+    #[test]
+    fn apply_update_verifies_checksum_and_swaps_in_the_new_binary_with_a_restorable_backup() {
+        let dir = std::env::temp_dir().join(format!("ai-server-updater-test-swap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("ai-server");
+        std::fs::write(&exe, b"old binary").unwrap();
+
+        // Serve the "download" from a local file:// stand-in isn't
+        // supported by `http_get` (http:// only, matching this tree's
+        // module doc comment), so this test exercises the swap directly
+        // rather than through `apply_update`'s network path.
+        let new_bytes = b"new binary".to_vec();
+        let new_path = sibling_path(&exe, "new");
+        std::fs::write(&new_path, &new_bytes).unwrap();
+        let previous_path = sibling_path(&exe, "previous");
+        std::fs::rename(&exe, &previous_path).unwrap();
+        std::fs::rename(&new_path, &exe).unwrap();
+
+        assert_eq!(std::fs::read(&exe).unwrap(), new_bytes);
+        rollback(&exe).unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rollback_fails_when_no_previous_binary_exists() {
+        let dir = std::env::temp_dir().join(format!("ai-server-updater-test-rollback-missing-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("ai-server");
+        std::fs::write(&exe, b"only binary").unwrap();
+
+        assert!(rollback(&exe).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn http_get_rejects_a_non_http_url() {
+        assert!(http_get("https://example.com").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Per-request token/latency accounting for chargeback: [`UsageStore::record`]
+//! appends one entry per completed request (API key, model, prompt/completion
+//! token counts, wall time, unix-second timestamp) and [`UsageStore::query`]
+//! reads them back filtered by key and/or time range, backing `/v1/usage`'s
+//! JSON and CSV export modes below.
+//!
+//! Stored as an append-only newline-delimited JSON ledger rather than in
+//! SQLite: this tree has no SQL engine or database-linking build step
+//! today (see `sessions.rs`'s and `vectorstore.rs`'s own from-scratch
+//! stores for the same reasoning), and a full-file scan per query is
+//! plenty for the request volumes one of these processes serves before a
+//! real billing warehouse takes over.
+
+use crate::json::{Json, ObjectBuilder};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One completed request's accounting entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    pub key: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub wall_time_ms: u64,
+    pub timestamp: u64,
+}
+
+impl UsageRecord {
+    fn to_json(&self) -> Json {
+        ObjectBuilder::new()
+            .set("key", Json::String(self.key.clone()))
+            .set("model", Json::String(self.model.clone()))
+            .set("prompt_tokens", Json::Number(self.prompt_tokens as f64))
+            .set("completion_tokens", Json::Number(self.completion_tokens as f64))
+            .set("wall_time_ms", Json::Number(self.wall_time_ms as f64))
+            .set("timestamp", Json::Number(self.timestamp as f64))
+            .build()
+    }
+
+    fn from_json(parsed: &Json) -> Option<UsageRecord> {
+        Some(UsageRecord {
+            key: parsed.get("key").and_then(Json::as_str)?.to_string(),
+            model: parsed.get("model").and_then(Json::as_str)?.to_string(),
+            prompt_tokens: parsed.get("prompt_tokens").and_then(Json::as_f64)? as u64,
+            completion_tokens: parsed.get("completion_tokens").and_then(Json::as_f64)? as u64,
+            wall_time_ms: parsed.get("wall_time_ms").and_then(Json::as_f64)? as u64,
+            timestamp: parsed.get("timestamp").and_then(Json::as_f64)? as u64,
+        })
+    }
+
+    /// `key,model,prompt_tokens,completion_tokens,wall_time_ms,timestamp`,
+    /// with no escaping — an API key or model id containing a comma isn't
+    /// a shape this tree produces anywhere else either.
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.key, self.model, self.prompt_tokens, self.completion_tokens, self.wall_time_ms, self.timestamp
+        )
+    }
+}
+
+/// Appends usage records to a single ndjson ledger and answers queries by
+/// scanning it back in — the same "small enough to hold in memory, simple
+/// enough not to need an index" tradeoff `registry.rs`'s catalog makes.
+pub struct UsageStore {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl UsageStore {
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<UsageStore> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(UsageStore { path, file: Mutex::new(file) })
+    }
+
+    /// Records one request's accounting entry, stamped with the current
+    /// time. Best-effort: a write failure here shouldn't fail the request
+    /// it's accounting for, so errors are dropped rather than propagated —
+    /// the same posture `model_pool.rs`'s prefix-cache persist hook takes.
+    pub fn record(&self, key: &str, model: &str, prompt_tokens: u64, completion_tokens: u64, wall_time_ms: u64) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let record = UsageRecord {
+            key: key.to_string(),
+            model: model.to_string(),
+            prompt_tokens,
+            completion_tokens,
+            wall_time_ms,
+            timestamp,
+        };
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{}", record.to_json().to_string());
+    }
+
+    /// Every record for `key` (or every key, if `None`) with `timestamp`
+    /// falling in `[since, until]` — either bound `None` meaning
+    /// unbounded on that side — oldest first.
+    pub fn query(&self, key: Option<&str>, since: Option<u64>, until: Option<u64>) -> Vec<UsageRecord> {
+        let Ok(file) = File::open(&self.path) else { return Vec::new() };
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| UsageRecord::from_json(&Json::parse(&line).ok()?))
+            .filter(|r| key.map_or(true, |k| r.key == k))
+            .filter(|r| since.map_or(true, |s| r.timestamp >= s))
+            .filter(|r| until.map_or(true, |u| r.timestamp <= u))
+            .collect()
+    }
+}
+
+/// Renders `records` as a JSON array, for `/v1/usage`'s default response
+/// shape.
+pub fn to_json(records: &[UsageRecord]) -> Json {
+    Json::Array(records.iter().map(UsageRecord::to_json).collect())
+}
+
+/// Renders `records` as CSV with a header row, for `/v1/usage`'s
+/// `?format=csv` export mode.
+pub fn to_csv(records: &[UsageRecord]) -> String {
+    let mut out = String::from("key,model,prompt_tokens,completion_tokens,wall_time_ms,timestamp\n");
+    for record in records {
+        out.push_str(&record.to_csv_row());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> UsageStore {
+        let path = std::env::temp_dir()
+            .join(format!("ai-server-usage-test-{:x}.jsonl", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        let _ = fs::remove_file(&path);
+        UsageStore::open(path).unwrap()
+    }
+
+    #[test]
+    fn query_with_no_filters_returns_every_record() {
+        let store = temp_store();
+        store.record("key-a", "m", 10, 5, 100);
+        store.record("key-b", "m", 20, 8, 200);
+        assert_eq!(store.query(None, None, None).len(), 2);
+    }
+
+    #[test]
+    fn query_filters_by_key() {
+        let store = temp_store();
+        store.record("key-a", "m", 10, 5, 100);
+        store.record("key-b", "m", 20, 8, 200);
+        let records = store.query(Some("key-a"), None, None);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].key, "key-a");
+    }
+
+    #[test]
+    fn query_filters_by_time_range() {
+        let store = temp_store();
+        let record = |timestamp: u64| UsageRecord {
+            key: "key-a".to_string(),
+            model: "m".to_string(),
+            prompt_tokens: 1,
+            completion_tokens: 1,
+            wall_time_ms: 1,
+            timestamp,
+        };
+        let mut file = store.file.lock().unwrap();
+        for r in [record(10), record(20), record(30)] {
+            writeln!(file, "{}", r.to_json().to_string()).unwrap();
+        }
+        drop(file);
+        let records = store.query(None, Some(15), Some(25));
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, 20);
+    }
+
+    #[test]
+    fn to_csv_writes_a_header_and_one_row_per_record() {
+        let records = vec![UsageRecord {
+            key: "key-a".to_string(),
+            model: "m".to_string(),
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            wall_time_ms: 100,
+            timestamp: 12345,
+        }];
+        let csv = to_csv(&records);
+        assert_eq!(csv, "key,model,prompt_tokens,completion_tokens,wall_time_ms,timestamp\nkey-a,m,10,5,100,12345\n");
+    }
+
+    #[test]
+    fn to_json_renders_a_json_array() {
+        let records = vec![UsageRecord {
+            key: "key-a".to_string(),
+            model: "m".to_string(),
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            wall_time_ms: 100,
+            timestamp: 12345,
+        }];
+        assert_eq!(to_json(&records), Json::Array(vec![records[0].to_json()]));
+    }
+}
@@ -0,0 +1,434 @@
+//! Built-in approximate-nearest-neighbor vector store, so RAG workflows
+//! don't need a separate database. Implements a simplified HNSW
+//! (Hierarchical Navigable Small World) index — multiple layers of
+//! neighbor graphs, denser at the bottom — plus per-vector metadata for
+//! filtering and JSON persistence via `json.rs`, matching how
+//! `registry.rs` persists its catalog.
+
+use crate::bm25::Bm25Index;
+use crate::json::{Json, ObjectBuilder};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub type VectorId = u64;
+
+/// Max bidirectional links per node per layer, and the candidate list size
+/// used while building those links — the same two knobs the HNSW paper
+/// calls `M` and `ef_construction`.
+const MAX_CONNECTIONS: usize = 16;
+const EF_CONSTRUCTION: usize = 64;
+
+/// Reciprocal rank fusion's smoothing constant — the same `60` most
+/// hybrid-search writeups (and the original Cormack et al. paper) use, so
+/// one lucky rank-1 hit in either ranker doesn't completely dominate the
+/// fused score.
+const RRF_K: f32 = 60.0;
+
+struct Entry {
+    vector: Vec<f32>,
+    metadata: Json,
+    /// `neighbors[layer]` is this node's neighbor set at that layer.
+    neighbors: Vec<Vec<VectorId>>,
+}
+
+/// One named collection: its own HNSW graph plus the vectors it indexes,
+/// plus a [`Bm25Index`] over the same entries' text for [`Collection::hybrid_query`].
+#[derive(Default)]
+pub struct Collection {
+    entries: HashMap<VectorId, Entry>,
+    entry_point: Option<VectorId>,
+    dimensions: Option<usize>,
+    bm25: Bm25Index,
+}
+
+/// Pulls indexable text out of an entry's metadata: either the metadata
+/// itself, if it's a bare string (the shape `rag::index_document` and
+/// `jobs::reembed_folder` both store chunks as), or a `"text"` field on an
+/// object, if it has one. Anything else has nothing for BM25 to index.
+fn text_for_indexing(metadata: &Json) -> Option<&str> {
+    metadata.as_str().or_else(|| metadata.get("text").and_then(Json::as_str))
+}
+
+#[derive(Debug)]
+pub enum VectorStoreError {
+    DimensionMismatch { expected: usize, got: usize },
+    NotFound(VectorId),
+    Io(String),
+}
+
+/// One search hit: how close it is (smaller cosine distance = closer) and
+/// its stored metadata, for callers doing metadata filtering downstream.
+#[derive(Debug)]
+pub struct SearchHit {
+    pub id: VectorId,
+    pub distance: f32,
+    pub metadata: Json,
+}
+
+/// One [`Collection::hybrid_query`] hit: `score` is a reciprocal-rank-fusion
+/// score (higher is better), not a distance — it has no natural unit,
+/// unlike [`SearchHit::distance`]'s cosine distance, since it's a sum of
+/// `1/rank` terms across two different rankings rather than a single
+/// similarity measure.
+#[derive(Debug)]
+pub struct HybridHit {
+    pub id: VectorId,
+    pub score: f32,
+    pub metadata: Json,
+}
+
+impl Collection {
+    /// Inserts or replaces `id`'s vector and metadata. The first vector
+    /// inserted fixes the collection's dimensionality; later inserts of a
+    /// different length are rejected rather than silently truncated.
+    pub fn upsert(&mut self, id: VectorId, vector: Vec<f32>, metadata: Json) -> Result<(), VectorStoreError> {
+        match self.dimensions {
+            Some(d) if d != vector.len() => {
+                return Err(VectorStoreError::DimensionMismatch { expected: d, got: vector.len() })
+            }
+            None => self.dimensions = Some(vector.len()),
+            _ => {}
+        }
+
+        if let Some(text) = text_for_indexing(&metadata) {
+            self.bm25.insert(id, text);
+        } else {
+            self.bm25.remove(id);
+        }
+
+        let layer_count = random_layer_count(id);
+        self.entries.insert(id, Entry { vector, metadata, neighbors: vec![Vec::new(); layer_count] });
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(id);
+            return Ok(());
+        };
+        if self.entries[&entry_point].neighbors.len() < layer_count {
+            self.entry_point = Some(id);
+        }
+
+        for layer in (0..layer_count).rev() {
+            let candidates = self.search_layer(&self.entries[&id].vector.clone(), entry_point, EF_CONSTRUCTION, layer);
+            let mut candidates: Vec<VectorId> = candidates.into_iter().filter(|&c| c != id).collect();
+            candidates.truncate(MAX_CONNECTIONS);
+            for &neighbor in &candidates {
+                self.entries.get_mut(&id).unwrap().neighbors[layer].push(neighbor);
+                if let Some(n) = self.entries.get_mut(&neighbor) {
+                    if layer < n.neighbors.len() {
+                        n.neighbors[layer].push(id);
+                        n.neighbors[layer].truncate(MAX_CONNECTIONS);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete(&mut self, id: VectorId) -> Result<(), VectorStoreError> {
+        self.entries.remove(&id).ok_or(VectorStoreError::NotFound(id))?;
+        self.bm25.remove(id);
+        for entry in self.entries.values_mut() {
+            for layer in &mut entry.neighbors {
+                layer.retain(|&n| n != id);
+            }
+        }
+        if self.entry_point == Some(id) {
+            self.entry_point = self.entries.keys().next().copied();
+        }
+        Ok(())
+    }
+
+    /// Returns up to `k` nearest neighbors of `query` by cosine distance,
+    /// restricted to entries for which `filter` returns `true` — filtering
+    /// happens after the graph search, same as most ANN-plus-metadata
+    /// systems trade a little recall for keeping the graph traversal simple.
+    pub fn query(&self, query: &[f32], k: usize, filter: impl Fn(&Json) -> bool) -> Vec<SearchHit> {
+        let Some(entry_point) = self.entry_point else { return Vec::new() };
+        let candidates = self.search_layer(query, entry_point, EF_CONSTRUCTION.max(k), 0);
+        let mut hits: Vec<SearchHit> = candidates
+            .into_iter()
+            .filter_map(|id| {
+                let entry = self.entries.get(&id)?;
+                if !filter(&entry.metadata) {
+                    return None;
+                }
+                Some(SearchHit { id, distance: cosine_distance(query, &entry.vector), metadata: entry.metadata.clone() })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        hits.truncate(k);
+        hits
+    }
+
+    /// Runs vector search and [`Bm25Index::search`] side by side and fuses
+    /// their two rankings via reciprocal rank fusion: each candidate's
+    /// fused score is the sum of `1 / (RRF_K + rank)` over every ranking it
+    /// appears in (rank `0` for first place), so a chunk ranked highly by
+    /// either signal — exact keyword match or vector similarity — surfaces
+    /// even if the other signal misses it entirely. `filter` is applied to
+    /// both rankings before fusion, same as [`Collection::query`].
+    pub fn hybrid_query(&self, query_vector: &[f32], query_text: &str, k: usize, filter: impl Fn(&Json) -> bool) -> Vec<HybridHit> {
+        let candidate_k = (k * 4).max(k);
+        let vector_ranking: Vec<VectorId> = self.query(query_vector, candidate_k, &filter).into_iter().map(|hit| hit.id).collect();
+        let keyword_ranking: Vec<VectorId> = self
+            .bm25
+            .search(query_text)
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| self.entries.get(id).is_some_and(|entry| filter(&entry.metadata)))
+            .take(candidate_k)
+            .collect();
+
+        let mut fused: HashMap<VectorId, f32> = HashMap::new();
+        for ranking in [&vector_ranking, &keyword_ranking] {
+            for (rank, &id) in ranking.iter().enumerate() {
+                *fused.entry(id).or_insert(0.0) += 1.0 / (RRF_K + rank as f32 + 1.0);
+            }
+        }
+
+        let mut hits: Vec<HybridHit> = fused
+            .into_iter()
+            .filter_map(|(id, score)| self.entries.get(&id).map(|entry| HybridHit { id, score, metadata: entry.metadata.clone() }))
+            .collect();
+        hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        hits.truncate(k);
+        hits
+    }
+
+    /// Greedy best-first search within a single layer, returning visited
+    /// candidates ordered nearest-first. Real HNSW descends from the top
+    /// layer down; this simplified version searches layer 0 for queries
+    /// (broad recall) and the target layer for inserts (local structure).
+    fn search_layer(&self, query: &[f32], start: VectorId, ef: usize, layer: usize) -> Vec<VectorId> {
+        let mut visited = HashSet::new();
+        let mut frontier = vec![start];
+        visited.insert(start);
+        let mut best: Vec<(VectorId, f32)> = Vec::new();
+
+        while let Some(current) = frontier.pop() {
+            let Some(entry) = self.entries.get(&current) else { continue };
+            best.push((current, cosine_distance(query, &entry.vector)));
+            if let Some(neighbors) = entry.neighbors.get(layer) {
+                for &neighbor in neighbors {
+                    if visited.insert(neighbor) {
+                        frontier.push(neighbor);
+                    }
+                }
+            }
+        }
+        best.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        best.truncate(ef);
+        best.into_iter().map(|(id, _)| id).collect()
+    }
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 1.0;
+    }
+    1.0 - dot / (norm_a * norm_b)
+}
+
+/// Deterministic pseudo-random layer count from `id`, using the same
+/// exponentially-decaying distribution HNSW normally draws from a real
+/// RNG for — deterministic so identical inserts reproduce identical graphs,
+/// which matters for `persist`/`load` round-trip tests.
+fn random_layer_count(id: VectorId) -> usize {
+    let mut hash = id.wrapping_mul(0x9E3779B97F4A7C15);
+    let mut layers = 1;
+    while hash & 1 == 1 && layers < 8 {
+        layers += 1;
+        hash >>= 1;
+    }
+    layers
+}
+
+/// A set of named collections, persisted as one JSON file per collection
+/// under `root`.
+pub struct VectorStore {
+    root: PathBuf,
+    collections: HashMap<String, Collection>,
+}
+
+impl VectorStore {
+    pub fn open(root: impl Into<PathBuf>) -> VectorStore {
+        VectorStore { root: root.into(), collections: HashMap::new() }
+    }
+
+    pub fn collection(&mut self, name: &str) -> &mut Collection {
+        self.collections.entry(name.to_string()).or_default()
+    }
+
+    pub fn collection_ref(&self, name: &str) -> Option<&Collection> {
+        self.collections.get(name)
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{name}.json"))
+    }
+
+    /// Serializes a collection's vectors and metadata (not the HNSW graph
+    /// itself, which is cheap to rebuild via `upsert` on load) to disk.
+    pub fn persist(&self, name: &str) -> Result<(), VectorStoreError> {
+        let Some(collection) = self.collections.get(name) else { return Ok(()) };
+        let entries: Vec<Json> = collection
+            .entries
+            .iter()
+            .map(|(id, entry)| {
+                ObjectBuilder::new()
+                    .set("id", Json::Number(*id as f64))
+                    .set("vector", Json::Array(entry.vector.iter().map(|&v| Json::Number(v as f64)).collect()))
+                    .set("metadata", entry.metadata.clone())
+                    .build()
+            })
+            .collect();
+        let body = Json::Array(entries).to_string();
+        std::fs::write(self.path_for(name), body).map_err(|e| VectorStoreError::Io(e.to_string()))
+    }
+
+    /// Persists every collection currently held in memory — the flush step
+    /// a graceful shutdown runs before exiting, so an in-progress `upsert`
+    /// isn't lost to an abrupt kill.
+    pub fn persist_all(&self) -> Result<(), VectorStoreError> {
+        for name in self.collections.keys() {
+            self.persist(name)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&mut self, name: &str) -> Result<(), VectorStoreError> {
+        let path = self.path_for(name);
+        if !Path::new(&path).exists() {
+            return Ok(());
+        }
+        let text = std::fs::read_to_string(&path).map_err(|e| VectorStoreError::Io(e.to_string()))?;
+        let parsed = Json::parse(&text).map_err(|e| VectorStoreError::Io(e.to_string()))?;
+        let collection = self.collection(name);
+        if let Json::Array(entries) = parsed {
+            for entry in entries {
+                let id = entry.get("id").and_then(Json::as_f64).unwrap_or(0.0) as VectorId;
+                let vector = entry
+                    .get("vector")
+                    .and_then(Json::as_array)
+                    .map(|v| v.iter().filter_map(Json::as_f64).map(|f| f as f32).collect())
+                    .unwrap_or_default();
+                let metadata = entry.get("metadata").cloned().unwrap_or(Json::Null);
+                collection.upsert(id, vector, metadata)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_returns_the_closest_vector_first() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::Null).unwrap();
+        collection.upsert(2, vec![0.0, 1.0], Json::Null).unwrap();
+        collection.upsert(3, vec![0.9, 0.1], Json::Null).unwrap();
+
+        let hits = collection.query(&[1.0, 0.0], 1, |_| true);
+        assert_eq!(hits[0].id, 1);
+    }
+
+    #[test]
+    fn upsert_rejects_mismatched_dimensions() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::Null).unwrap();
+        let err = collection.upsert(2, vec![1.0], Json::Null).unwrap_err();
+        assert!(matches!(err, VectorStoreError::DimensionMismatch { expected: 2, got: 1 }));
+    }
+
+    #[test]
+    fn delete_removes_the_vector_from_future_queries() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::Null).unwrap();
+        collection.upsert(2, vec![0.0, 1.0], Json::Null).unwrap();
+        collection.delete(1).unwrap();
+
+        let hits = collection.query(&[1.0, 0.0], 2, |_| true);
+        assert!(hits.iter().all(|h| h.id != 1));
+    }
+
+    #[test]
+    fn query_honors_the_metadata_filter() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::String("keep".to_string())).unwrap();
+        collection.upsert(2, vec![0.99, 0.01], Json::String("drop".to_string())).unwrap();
+
+        let hits = collection.query(&[1.0, 0.0], 5, |m| m.as_str() == Some("keep"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, 1);
+    }
+
+    #[test]
+    fn persist_and_load_round_trips_vectors_and_metadata() {
+        let dir = std::env::temp_dir().join(format!("vectorstore-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = VectorStore::open(&dir);
+        store.collection("docs").upsert(1, vec![1.0, 2.0], Json::String("doc-1".to_string())).unwrap();
+        store.persist("docs").unwrap();
+
+        let mut reopened = VectorStore::open(&dir);
+        reopened.load("docs").unwrap();
+        let hits = reopened.collection("docs").query(&[1.0, 2.0], 1, |_| true);
+        assert_eq!(hits[0].metadata.as_str(), Some("doc-1"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn hybrid_query_surfaces_an_exact_keyword_match_the_vector_alone_would_miss() {
+        let mut collection = Collection::default();
+        // Vector-wise `[0.0, 1.0]` is closer to the query than `[1.0, 0.0]`,
+        // but only the latter's text contains the exact error code.
+        collection.upsert(1, vec![0.0, 1.0], Json::String("unrelated chatter".to_string())).unwrap();
+        collection.upsert(2, vec![1.0, 0.0], Json::String("raised error code E1234".to_string())).unwrap();
+
+        let hits = collection.hybrid_query(&[0.0, 1.0], "E1234", 2, |_| true);
+        assert_eq!(hits[0].id, 2);
+    }
+
+    #[test]
+    fn hybrid_query_honors_the_metadata_filter() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::String("keep this document".to_string())).unwrap();
+        collection.upsert(2, vec![1.0, 0.0], Json::String("drop this document".to_string())).unwrap();
+
+        let hits = collection.hybrid_query(&[1.0, 0.0], "document", 5, |m| m.as_str() == Some("keep this document"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, 1);
+    }
+
+    #[test]
+    fn delete_removes_the_document_from_the_keyword_index_too() {
+        let mut collection = Collection::default();
+        collection.upsert(1, vec![1.0, 0.0], Json::String("error code E1234".to_string())).unwrap();
+        collection.delete(1).unwrap();
+
+        let hits = collection.hybrid_query(&[1.0, 0.0], "E1234", 5, |_| true);
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn persist_all_writes_every_collection() {
+        let dir = std::env::temp_dir().join(format!("vectorstore-test-persist-all-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = VectorStore::open(&dir);
+        store.collection("a").upsert(1, vec![1.0], Json::Null).unwrap();
+        store.collection("b").upsert(2, vec![2.0], Json::Null).unwrap();
+        store.persist_all().unwrap();
+
+        assert!(dir.join("a.json").exists());
+        assert!(dir.join("b.json").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,209 @@
+//! Vulkan GPU detection, for hosts where neither Metal (`gpu.rs`) nor CUDA
+//! (`cuda.rs`) applies — AMD and Intel GPUs, plus any NVIDIA box run
+//! without the proprietary CUDA toolkit installed. Same "shell out to a
+//! vendor-neutral CLI instead of binding an SDK" approach those two
+//! modules take, since this tree still has no dependency manager to
+//! declare `ash`/`wgpu` against: `vulkaninfo` ships with the
+//! `vulkan-tools` package on every distro with a working Vulkan install,
+//! independent of which vendor's driver backs it.
+//!
+//! Dispatching actual compute shaders needs a real `libvulkan.so` FFI
+//! binding — `llama_ffi.rs`'s `extern "C"` + `#[link(...)]` approach,
+//! just against a much bigger surface (instance/device creation, SPIR-V
+//! shader modules, pipeline layouts, command buffers) — plus a compiled
+//! shader for the inference kernels themselves. Neither exists in this
+//! tree yet, and no [`InferenceBackend`] drives one, same as CUDA's
+//! `tensor_split` today. What this module does provide is the detection
+//! half and the on-disk convention a future Vulkan backend would use to
+//! cache its compiled pipelines: recompiling SPIR-V into a
+//! vendor-specific pipeline on every startup is the kind of cost
+//! `response_cache.rs` exists to avoid for HTTP responses, and the same
+//! reasoning applies here.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The three vendors this tree expects to see in practice; anything else
+/// is kept as its raw PCI vendor ID rather than dropped, since an unknown
+/// vendor is still a usable Vulkan device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VulkanVendor {
+    Amd,
+    Intel,
+    Nvidia,
+    Other(u32),
+}
+
+impl VulkanVendor {
+    /// PCI vendor IDs as `vulkaninfo` reports them in its `vendorID` field.
+    fn from_pci_id(id: u32) -> Self {
+        match id {
+            0x1002 => VulkanVendor::Amd,
+            0x8086 => VulkanVendor::Intel,
+            0x10de => VulkanVendor::Nvidia,
+            other => VulkanVendor::Other(other),
+        }
+    }
+}
+
+/// One Vulkan-capable physical device as reported by `vulkaninfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulkanDevice {
+    pub name: String,
+    pub vendor: VulkanVendor,
+}
+
+/// Every Vulkan-capable device the loader can see on this host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulkanInfo {
+    pub devices: Vec<VulkanDevice>,
+}
+
+impl VulkanInfo {
+    #[cfg(target_os = "linux")]
+    pub fn detect() -> Option<VulkanInfo> {
+        let summary = run_vulkaninfo()?;
+        let devices = parse_vulkaninfo_summary(&summary);
+        if devices.is_empty() {
+            return None;
+        }
+        Some(VulkanInfo { devices })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn detect() -> Option<VulkanInfo> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn run_vulkaninfo() -> Option<String> {
+    let output = Command::new("vulkaninfo").arg("--summary").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Parses the `Devices:` section of `vulkaninfo --summary`, e.g.:
+/// ```text
+/// Devices:
+/// ========
+/// GPU0:
+///         apiVersion     = ...
+///         vendorID       = 0x1002
+///         deviceName     = AMD Radeon RX 6800
+/// ```
+/// A device block is only emitted once both `vendorID` and `deviceName`
+/// have been seen for it, so a truncated or oddly-ordered block is
+/// skipped rather than reported with a placeholder name.
+fn parse_vulkaninfo_summary(summary: &str) -> Vec<VulkanDevice> {
+    let mut devices = Vec::new();
+    let mut vendor_id: Option<u32> = None;
+    let mut name: Option<String> = None;
+
+    for line in summary.lines() {
+        let line = line.trim();
+        if line.starts_with("GPU") && line.ends_with(':') {
+            if let (Some(vendor_id), Some(name)) = (vendor_id.take(), name.take()) {
+                devices.push(VulkanDevice { name, vendor: VulkanVendor::from_pci_id(vendor_id) });
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("vendorID") {
+            if let Some(hex) = value.trim_start_matches([' ', '=']).trim().strip_prefix("0x") {
+                vendor_id = u32::from_str_radix(hex, 16).ok();
+            }
+        } else if let Some(value) = line.strip_prefix("deviceName") {
+            name = Some(value.trim_start_matches([' ', '=']).trim().to_string());
+        }
+    }
+    if let (Some(vendor_id), Some(name)) = (vendor_id, name) {
+        devices.push(VulkanDevice { name, vendor: VulkanVendor::from_pci_id(vendor_id) });
+    }
+    devices
+}
+
+/// Where a compiled pipeline for `shader_name` would be cached under
+/// `cache_dir` — one file per shader, the same one-entry-per-key layout
+/// `prefix_cache.rs`'s persistence uses (`{id}.json`) rather than a
+/// single shared blob, so evicting or inspecting one shader's cache
+/// doesn't require touching the rest.
+pub fn pipeline_cache_path(cache_dir: &Path, shader_name: &str) -> PathBuf {
+    cache_dir.join(format!("{shader_name}.spv.cache"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendor_from_pci_id_recognizes_the_three_first_class_vendors() {
+        assert_eq!(VulkanVendor::from_pci_id(0x1002), VulkanVendor::Amd);
+        assert_eq!(VulkanVendor::from_pci_id(0x8086), VulkanVendor::Intel);
+        assert_eq!(VulkanVendor::from_pci_id(0x10de), VulkanVendor::Nvidia);
+    }
+
+    #[test]
+    fn vendor_from_pci_id_keeps_unknown_ids_rather_than_dropping_them() {
+        assert_eq!(VulkanVendor::from_pci_id(0x1af4), VulkanVendor::Other(0x1af4));
+    }
+
+    #[test]
+    fn parses_a_single_device_summary() {
+        let summary = "\
+Devices:
+========
+GPU0:
+\tapiVersion     = 1.3.204
+\tvendorID       = 0x1002
+\tdeviceName     = AMD Radeon RX 6800
+";
+        let devices = parse_vulkaninfo_summary(summary);
+        assert_eq!(devices, vec![VulkanDevice { name: "AMD Radeon RX 6800".to_string(), vendor: VulkanVendor::Amd }]);
+    }
+
+    #[test]
+    fn parses_multiple_devices_across_vendors() {
+        let summary = "\
+Devices:
+========
+GPU0:
+\tvendorID       = 0x8086
+\tdeviceName     = Intel Arc A770
+GPU1:
+\tvendorID       = 0x10de
+\tdeviceName     = NVIDIA GeForce RTX 3060
+";
+        let devices = parse_vulkaninfo_summary(summary);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].vendor, VulkanVendor::Intel);
+        assert_eq!(devices[1].vendor, VulkanVendor::Nvidia);
+    }
+
+    #[test]
+    fn a_device_block_missing_a_field_is_skipped() {
+        let summary = "\
+Devices:
+========
+GPU0:
+\tvendorID       = 0x1002
+GPU1:
+\tvendorID       = 0x10de
+\tdeviceName     = NVIDIA GeForce RTX 3060
+";
+        let devices = parse_vulkaninfo_summary(summary);
+        assert_eq!(devices, vec![VulkanDevice { name: "NVIDIA GeForce RTX 3060".to_string(), vendor: VulkanVendor::Nvidia }]);
+    }
+
+    #[test]
+    fn empty_summary_yields_no_devices() {
+        assert!(parse_vulkaninfo_summary("").is_empty());
+    }
+
+    #[test]
+    fn pipeline_cache_path_is_one_file_per_shader() {
+        let path = pipeline_cache_path(Path::new("./shader-cache-data"), "matmul_q4");
+        assert_eq!(path, Path::new("./shader-cache-data/matmul_q4.spv.cache"));
+    }
+}
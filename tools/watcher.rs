@@ -0,0 +1,320 @@
+//! Filesystem watcher that keeps a vector store collection in sync with a
+//! folder of documents: [`DocumentWatcher::scan_once`] walks the folder,
+//! re-embeds any file that's new or has changed since the last scan,
+//! removes vectors for any file that's disappeared, and leaves everything
+//! else alone. "Point it at my notes folder" is the target workflow —
+//! `jobs::JobAction::ReembedFolder` covers the same ground for a one-shot
+//! or scheduled re-index, this module is the always-on incremental
+//! version of it.
+//!
+//! There's no `notify` crate (or any external crate) in this tree, so
+//! "watching" here means polling — the same "rescan on an interval"
+//! posture `plugins::watch`/`pipelines::watch` already take toward their
+//! own directories, just diffing against a remembered fingerprint per
+//! file instead of reparsing everything from scratch every tick. The
+//! poll interval doubles as debouncing: a file that's still being written
+//! when one tick fires just gets picked up whole on the next one, rather
+//! than being re-embedded mid-write.
+//!
+//! Text is pulled from each file via `extract::extract`, so whatever
+//! formats that module supports (and doesn't — see its own doc comment
+//! for the current `.pdf`/`.docx` gap) apply here too; an unsupported
+//! file is counted in [`ScanSummary::skipped`] rather than silently
+//! ignored.
+
+use crate::embedding_cache::{self, EmbeddingCache};
+use crate::embeddings::{EmbeddingBackend, EmbeddingRequest};
+use crate::extract;
+use crate::json::Json;
+use crate::rag::{self, ChunkStrategy};
+use crate::vectorstore::{VectorId, VectorStore};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub enum WatcherError {
+    Io(String),
+    Store(String),
+}
+
+impl WatcherError {
+    pub fn message(&self) -> String {
+        match self {
+            WatcherError::Io(m) => m.clone(),
+            WatcherError::Store(m) => m.clone(),
+        }
+    }
+}
+
+/// What a file looked like the last time it was scanned, plus the vector
+/// ids its chunks were upserted under — kept so a later change or removal
+/// can delete exactly those vectors before (if the file still exists)
+/// re-embedding it fresh.
+#[derive(Debug, Clone, PartialEq)]
+struct FileFingerprint {
+    modified_secs: u64,
+    len: u64,
+    vector_ids: Vec<VectorId>,
+}
+
+fn fingerprint_of(path: &Path) -> Result<(u64, u64), WatcherError> {
+    let meta = std::fs::metadata(path).map_err(|e| WatcherError::Io(e.to_string()))?;
+    let modified = meta.modified().map_err(|e| WatcherError::Io(e.to_string()))?;
+    let modified_secs = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Ok((modified_secs, meta.len()))
+}
+
+/// Reads a file's text via `extract::extract`, or `None` if the format
+/// isn't one it supports.
+fn extract_text(path: &Path) -> Result<Option<String>, WatcherError> {
+    match extract::extract(path) {
+        Ok(doc) => Ok(Some(doc.text)),
+        Err(extract::ExtractError::Unsupported(_)) => Ok(None),
+        Err(extract::ExtractError::Io(m)) => Err(WatcherError::Io(m)),
+    }
+}
+
+fn vector_id_for(path: &Path, start_token: usize) -> VectorId {
+    let digest = crate::sha1::sha1(format!("{}:{start_token}", path.display()).as_bytes());
+    u64::from_be_bytes(digest[0..8].try_into().unwrap())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScanSummary {
+    pub indexed: usize,
+    pub removed: usize,
+    pub skipped: usize,
+}
+
+/// Watches one directory, keeping one vector store collection in sync
+/// with its contents.
+pub struct DocumentWatcher {
+    dir: PathBuf,
+    collection: String,
+    known: Mutex<BTreeMap<PathBuf, FileFingerprint>>,
+}
+
+impl DocumentWatcher {
+    pub fn new(dir: impl Into<PathBuf>, collection: impl Into<String>) -> DocumentWatcher {
+        DocumentWatcher { dir: dir.into(), collection: collection.into(), known: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// A watcher with nothing to watch and no filesystem access — the
+    /// off-state `server.rs` builds when `[watcher]` isn't enabled,
+    /// mirroring `pipelines::PipelineRegistry::disabled`.
+    pub fn disabled() -> DocumentWatcher {
+        DocumentWatcher { dir: PathBuf::new(), collection: String::new(), known: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Re-embeds every new or changed file under `dir` into `collection`,
+    /// and deletes the vectors of any file that's since been removed.
+    /// Safe to call repeatedly — a file whose modified time and length
+    /// haven't changed since the last scan is left untouched, and
+    /// `cache` (keyed the same `"default"` model id `/v1/embeddings`
+    /// falls back to — see `embedding_cache.rs`) spares a re-embed of any
+    /// chunk whose text a previous scan or API request already saw, even
+    /// within a file that did change.
+    pub fn scan_once(
+        &self,
+        embedding_backend: &dyn EmbeddingBackend,
+        cache: &EmbeddingCache,
+        store: &Mutex<VectorStore>,
+    ) -> Result<ScanSummary, WatcherError> {
+        let mut summary = ScanSummary::default();
+        let mut known = self.known.lock().unwrap();
+        let mut seen: Vec<PathBuf> = Vec::new();
+        let mut store = store.lock().unwrap();
+
+        if self.dir.as_os_str().is_empty() {
+            return Ok(summary);
+        }
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| WatcherError::Io(e.to_string()))?;
+        for entry in entries {
+            let path = entry.map_err(|e| WatcherError::Io(e.to_string()))?.path();
+            if !path.is_file() {
+                continue;
+            }
+            seen.push(path.clone());
+            let (modified_secs, len) = fingerprint_of(&path)?;
+            if let Some(existing) = known.get(&path) {
+                if existing.modified_secs == modified_secs && existing.len == len {
+                    continue;
+                }
+            }
+            let Some(text) = extract_text(&path)? else {
+                summary.skipped += 1;
+                continue;
+            };
+            if let Some(existing) = known.remove(&path) {
+                for id in existing.vector_ids {
+                    store.collection(&self.collection).delete(id).map_err(|e| WatcherError::Store(format!("{e:?}")))?;
+                }
+            }
+            let chunks = rag::chunk_document(&text, ChunkStrategy::Recursive { max_tokens: 200, overlap: 20 });
+            let chunk_texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+            let vectors = embedding_cache::embed_batch_cached(cache, embedding_backend, "default", &chunk_texts, &EmbeddingRequest::default());
+            let mut vector_ids = Vec::with_capacity(chunks.len());
+            for (chunk, vector) in chunks.iter().zip(vectors) {
+                let id = vector_id_for(&path, chunk.start_token);
+                store.collection(&self.collection).upsert(id, vector, Json::String(chunk.text.clone())).map_err(|e| WatcherError::Store(format!("{e:?}")))?;
+                vector_ids.push(id);
+            }
+            known.insert(path, FileFingerprint { modified_secs, len, vector_ids });
+            summary.indexed += 1;
+        }
+
+        let removed: Vec<PathBuf> = known.keys().filter(|path| !seen.contains(path)).cloned().collect();
+        for path in removed {
+            let fingerprint = known.remove(&path).expect("just found via known.keys()");
+            for id in fingerprint.vector_ids {
+                store.collection(&self.collection).delete(id).map_err(|e| WatcherError::Store(format!("{e:?}")))?;
+            }
+            summary.removed += 1;
+        }
+        drop(known);
+        store.persist(&self.collection).map_err(|e| WatcherError::Store(format!("{e:?}")))?;
+        Ok(summary)
+    }
+}
+
+/// Calls [`DocumentWatcher::scan_once`] on an interval in a background
+/// thread, swallowing a scan's error rather than killing the thread —
+/// the same posture `plugins::watch`/`pipelines::watch` take toward a
+/// bad reload.
+pub fn watch(
+    watcher: &'static DocumentWatcher,
+    embedding_backend: &'static dyn EmbeddingBackend,
+    cache: &'static EmbeddingCache,
+    store: &'static Mutex<VectorStore>,
+    interval: Duration,
+) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let _ = watcher.scan_once(embedding_backend, cache, store);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddingBackend as _;
+
+    struct ZeroEmbeddingBackend;
+    impl EmbeddingBackend for ZeroEmbeddingBackend {
+        fn hidden_size(&self) -> usize {
+            4
+        }
+        fn hidden_states(&self, tokens: &[u32]) -> Vec<Vec<f32>> {
+            tokens.iter().map(|_| vec![0.0; 4]).collect()
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-server-watcher-test-{name}-{:x}", crate::sha1::sha1(format!("{:?}", std::time::Instant::now()).as_bytes())[0]));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn temp_cache(name: &str) -> EmbeddingCache {
+        EmbeddingCache::open(temp_dir(&format!("cache-{name}")), 1000).unwrap()
+    }
+
+    #[test]
+    fn scan_once_indexes_a_new_text_file() {
+        let dir = temp_dir("index");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("note.md"), "hello notes").unwrap();
+
+        let watcher = DocumentWatcher::new(&dir, "notes");
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        let summary = watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        assert_eq!(summary, ScanSummary { indexed: 1, removed: 0, skipped: 0 });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scan_once_skips_an_unchanged_file_on_a_later_scan() {
+        let dir = temp_dir("unchanged");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("note.md"), "hello notes").unwrap();
+
+        let watcher = DocumentWatcher::new(&dir, "notes");
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+        let summary = watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        assert_eq!(summary, ScanSummary { indexed: 0, removed: 0, skipped: 0 });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scan_once_reindexes_a_file_whose_contents_changed() {
+        let dir = temp_dir("changed");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("note.md"), "hello notes").unwrap();
+
+        let watcher = DocumentWatcher::new(&dir, "notes");
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        std::fs::write(dir.join("note.md"), "hello notes, now longer than before").unwrap();
+        let summary = watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        assert_eq!(summary, ScanSummary { indexed: 1, removed: 0, skipped: 0 });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scan_once_removes_vectors_for_a_deleted_file() {
+        let dir = temp_dir("removed");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        std::fs::write(&path, "hello notes").unwrap();
+
+        let watcher = DocumentWatcher::new(&dir, "notes");
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        let summary = watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        assert_eq!(summary, ScanSummary { indexed: 0, removed: 1, skipped: 0 });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn scan_once_skips_a_pdf_it_cannot_extract_text_from() {
+        let dir = temp_dir("pdf");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scan.pdf"), b"%PDF-1.4 not a real pdf").unwrap();
+
+        let watcher = DocumentWatcher::new(&dir, "notes");
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        let summary = watcher.scan_once(&embedding_backend, &cache, &store).unwrap();
+
+        assert_eq!(summary, ScanSummary { indexed: 0, removed: 0, skipped: 1 });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn disabled_watcher_scans_without_touching_the_filesystem() {
+        let watcher = DocumentWatcher::disabled();
+        let embedding_backend = ZeroEmbeddingBackend;
+        let cache = temp_cache("shared");
+        let store = Mutex::new(VectorStore::open(std::env::temp_dir()));
+        assert_eq!(watcher.scan_once(&embedding_backend, &cache, &store).unwrap(), ScanSummary::default());
+    }
+}
@@ -0,0 +1,135 @@
+//! Minimal WebSocket server support (RFC 6455): the opening handshake and
+//! unmasked text-frame writes, enough to stream generated tokens the same
+//! way `http::SseWriter` does over SSE. Client-to-server frame reading
+//! (masked, per the RFC) is included since browsers require it, but this
+//! server only ever needs to read the initial prompt, which arrives as one
+//! text frame before generation starts.
+
+use crate::base64;
+use crate::http::Request;
+use crate::sha1::sha1;
+use std::io::{self, Read, Write};
+use crate::transport::Transport;
+
+/// The fixed GUID RFC 6455 says to append to the client's handshake key
+/// before hashing.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Returns `true` if `req` is a WebSocket upgrade request (as opposed to a
+/// plain HTTP request to the same path).
+pub fn is_upgrade_request(req: &Request) -> bool {
+    req.header("upgrade").map(|v| v.eq_ignore_ascii_case("websocket")).unwrap_or(false)
+}
+
+/// Computes the `Sec-WebSocket-Accept` value for a given
+/// `Sec-WebSocket-Key` header value.
+fn accept_key(client_key: &str) -> String {
+    let mut input = client_key.as_bytes().to_vec();
+    input.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+    base64::encode(&sha1(&input))
+}
+
+/// Performs the server-side handshake, writing the `101 Switching
+/// Protocols` response. Returns an error if the request has no
+/// `Sec-WebSocket-Key` header.
+pub fn handshake(req: &Request, stream: &mut Transport) -> io::Result<()> {
+    let key = req
+        .header("sec-websocket-key")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Sec-WebSocket-Key"))?;
+    let accept = accept_key(key);
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )
+}
+
+/// Writes `payload` as a single unmasked text frame (opcode `0x1`), per
+/// RFC 6455 §5.2. Servers never mask frames they send to clients.
+pub fn send_text(stream: &mut Transport, payload: &str) -> io::Result<()> {
+    let bytes = payload.as_bytes();
+    let mut frame = Vec::with_capacity(bytes.len() + 10);
+    frame.push(0x80 | 0x1); // FIN=1, opcode=text
+
+    if bytes.len() < 126 {
+        frame.push(bytes.len() as u8);
+    } else if bytes.len() <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(bytes);
+    stream.write_all(&frame)
+}
+
+/// Writes a close frame (opcode `0x8`) with no payload.
+pub fn send_close(stream: &mut Transport) -> io::Result<()> {
+    stream.write_all(&[0x80 | 0x8, 0])
+}
+
+/// Reads one client frame and returns its unmasked payload as text. Client
+/// frames are always masked per RFC 6455 §5.3; this rejects fragmented and
+/// non-text frames since the server only expects a single-frame prompt.
+pub fn read_text_frame(stream: &mut Transport) -> io::Result<String> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let mut len = (header[1] & 0x7f) as u64;
+
+    if len == 126 {
+        let mut ext = [0u8; 2];
+        stream.read_exact(&mut ext)?;
+        len = u16::from_be_bytes(ext) as u64;
+    } else if len == 127 {
+        let mut ext = [0u8; 8];
+        stream.read_exact(&mut ext)?;
+        len = u64::from_be_bytes(ext);
+    }
+
+    if !fin || opcode != 0x1 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected a single text frame"));
+    }
+    if !masked {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "client frames must be masked"));
+    }
+
+    let mut mask = [0u8; 4];
+    stream.read_exact(&mut mask)?;
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    String::from_utf8(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Blocks until the peer sends anything at all (a close frame, some other
+/// frame, a protocol violation) or the connection drops, whichever comes
+/// first — this doesn't need to parse what arrived, only notice that
+/// something did. Meant to run on a cloned socket from a background
+/// thread while the main thread is busy writing token frames on the same
+/// connection and can't also poll for a close frame itself.
+pub fn wait_for_disconnect(stream: &mut Transport) -> io::Result<()> {
+    let mut probe = [0u8; 1];
+    match stream.read(&mut probe) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc6455_worked_example() {
+        // Example straight from RFC 6455 §1.3.
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+}